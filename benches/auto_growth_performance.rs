@@ -20,7 +20,7 @@ fn bench_sequential_growth(c: &mut Criterion) {
                     let mut file_num = 0;
                     while cart.header().total_blocks < target_blocks as u64 {
                         let size = 512 * 1024; // 512KB to trigger extent allocator
-                        cart.write(&format!("/file{}.bin", file_num), &vec![0xAB; size]).unwrap();
+                        cart.write(format!("/file{}.bin", file_num), &vec![0xAB; size]).unwrap();
                         file_num += 1;
                     }
 
@@ -39,7 +39,7 @@ fn bench_hybrid_allocator_dispatch(c: &mut Criterion) {
         b.iter(|| {
             let mut cart = Cartridge::create("bench-hybrid-small", "Bench Hybrid").unwrap();
             for i in 0..100 {
-                cart.write(&format!("/small{}.txt", i), &vec![i as u8; 10 * 1024]).unwrap(); // 10KB
+                cart.write(format!("/small{}.txt", i), &vec![i as u8; 10 * 1024]).unwrap(); // 10KB
             }
             std::fs::remove_file("bench-hybrid-small.cart").ok();
         });
@@ -49,7 +49,7 @@ fn bench_hybrid_allocator_dispatch(c: &mut Criterion) {
         b.iter(|| {
             let mut cart = Cartridge::create("bench-hybrid-large", "Bench Hybrid").unwrap();
             for i in 0..10 {
-                cart.write(&format!("/large{}.bin", i), &vec![i as u8; 512 * 1024]).unwrap(); // 512KB
+                cart.write(format!("/large{}.bin", i), &vec![i as u8; 512 * 1024]).unwrap(); // 512KB
             }
             std::fs::remove_file("bench-hybrid-large.cart").ok();
         });
@@ -59,8 +59,8 @@ fn bench_hybrid_allocator_dispatch(c: &mut Criterion) {
         b.iter(|| {
             let mut cart = Cartridge::create("bench-hybrid-mixed", "Bench Hybrid").unwrap();
             for i in 0..50 {
-                cart.write(&format!("/small{}.txt", i), &vec![i as u8; 10 * 1024]).unwrap();
-                cart.write(&format!("/large{}.bin", i), &vec![i as u8; 512 * 1024]).unwrap();
+                cart.write(format!("/small{}.txt", i), &vec![i as u8; 10 * 1024]).unwrap();
+                cart.write(format!("/large{}.bin", i), &vec![i as u8; 512 * 1024]).unwrap();
             }
             std::fs::remove_file("bench-hybrid-mixed.cart").ok();
         });
@@ -80,7 +80,7 @@ fn bench_growth_overhead_measurement(c: &mut Criterion) {
                 let mut cart = Cartridge::create("bench-growth-op", "Bench Growth Op").unwrap();
                 // Fill to near capacity to trigger growth on next write
                 for i in 0..10 {
-                    cart.write(&format!("/file{}.bin", i), &vec![0xAB; 256 * 1024]).unwrap();
+                    cart.write(format!("/file{}.bin", i), &vec![0xAB; 256 * 1024]).unwrap();
                 }
                 cart
             },
@@ -105,12 +105,12 @@ fn bench_allocator_free_blocks_tracking(c: &mut Criterion) {
 
             // Allocate
             for i in 0..100 {
-                cart.write(&format!("/file{}.bin", i), &vec![i as u8; 64 * 1024]).unwrap();
+                cart.write(format!("/file{}.bin", i), &vec![i as u8; 64 * 1024]).unwrap();
             }
 
             // Deallocate
             for i in 0..100 {
-                cart.delete(&format!("/file{}.bin", i)).unwrap();
+                cart.delete(format!("/file{}.bin", i)).unwrap();
             }
 
             std::fs::remove_file("bench-alloc-track.cart").ok();
@@ -1,7 +1,7 @@
 //! Benchmarks for ARC buffer pool performance
 
-use cartridge_rs::buffer_pool::BufferPool;
-use cartridge_rs::page::{Page, PageType};
+use cartridge_rs::core::buffer_pool::BufferPool;
+use cartridge_rs::core::page::{Page, PageType};
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 use std::sync::Arc;
 
@@ -179,7 +179,7 @@ fn benchmark_arc_adaptation(c: &mut Criterion) {
             let mut rng = 12345u64;
             for _ in 0..200 {
                 rng = rng.wrapping_mul(1103515245).wrapping_add(12345);
-                let page_id = if (rng % 2) == 0 {
+                let page_id = if rng.is_multiple_of(2) {
                     rng % 10 // Hot set
                 } else {
                     10 + (rng % 190) // Cold set
@@ -1,4 +1,4 @@
-use cartridge_rs::{compression::CompressionMethod, Cartridge};
+use cartridge_rs::core::{compression::CompressionMethod, Cartridge};
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 use std::time::Duration;
 
@@ -226,7 +226,7 @@ fn bench_directory_traversal(c: &mut Criterion) {
 
 /// Simulate compression-heavy workload (text files, logs)
 fn bench_compression_workload(c: &mut Criterion) {
-    use cartridge::compression::{compress, decompress};
+    use cartridge_rs::core::compression::{compress, decompress};
 
     let file_counts = vec![10, 50, 100];
 
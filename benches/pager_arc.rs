@@ -1,4 +1,4 @@
-use cartridge_rs::Cartridge;
+use cartridge_rs::core::Cartridge;
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 
 /// Benchmark pager read performance with hot cache (repeated reads)
@@ -53,7 +53,7 @@ fn bench_pager_cold_reads(c: &mut Criterion) {
                     }
                     cart
                 },
-                |mut cart| {
+                |cart| {
                     // Measure: Read all files (cold cache)
                     for i in 0..count {
                         let path = format!("/file_{}.txt", i);
@@ -56,7 +56,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Adding multiple medium files...");
     for i in 0..10 {
         let data = vec![i as u8; 50_000];
-        cart.write(&format!("file_{}.dat", i), &data)?;
+        cart.write(format!("file_{}.dat", i), &data)?;
     }
     println!("✓ Written 10 medium files (50KB each)\n");
 
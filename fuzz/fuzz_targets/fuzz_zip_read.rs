@@ -0,0 +1,18 @@
+#![no_main]
+use cartridge_rs::{Vfs, ZipVfs};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+// Arbitrary bytes as a zip archive: opening, listing and reading every
+// entry may fail but must not panic
+fuzz_target!(|data: &[u8]| {
+    let Ok(zip) = ZipVfs::new(Cursor::new(data)) else {
+        return;
+    };
+    let Ok(entries) = zip.list_entries("/") else {
+        return;
+    };
+    for entry in entries {
+        let _ = zip.read(&entry.path);
+    }
+});
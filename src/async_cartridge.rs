@@ -0,0 +1,184 @@
+//! Async API (requires the `tokio` feature)
+//!
+//! [`AsyncCartridge`] wraps a [`SharedCartridge`] and runs every call on
+//! tokio's blocking thread pool, so file I/O never stalls a runtime worker
+//! and no lock is held across an `.await`.
+
+use crate::{Cartridge, Entry, FileMetadata, FileType, Result, SharedCartridge};
+use std::future::Future;
+
+/// Async counterpart of [`Vfs`](crate::Vfs)
+///
+/// Lets async code (such as an S3 frontend) work against any backend without
+/// blocking the runtime.
+pub trait AsyncVfs {
+    /// List all entries under a given prefix with rich metadata
+    fn list_entries(&self, prefix: &str) -> impl Future<Output = Result<Vec<Entry>>> + Send;
+
+    /// List immediate children of a directory (non-recursive)
+    fn list_children(&self, parent: &str) -> impl Future<Output = Result<Vec<Entry>>> + Send;
+
+    /// Read the contents of a file
+    fn read(&self, path: &str) -> impl Future<Output = Result<Vec<u8>>> + Send;
+
+    /// Write or update a file
+    fn write(&self, path: &str, data: Vec<u8>) -> impl Future<Output = Result<()>> + Send;
+
+    /// Delete a file
+    fn delete(&self, path: &str) -> impl Future<Output = Result<()>> + Send;
+
+    /// Check if a path exists
+    fn exists(&self, path: &str) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Check if a path is a directory
+    fn is_dir(&self, path: &str) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Get metadata for a path
+    fn metadata(&self, path: &str) -> impl Future<Output = Result<FileMetadata>> + Send;
+}
+
+/// Async handle to a cartridge
+///
+/// Cheap to clone; clones share the same cartridge. Reads run concurrently
+/// on the blocking pool, writes are serialized by the underlying
+/// [`SharedCartridge`] lock.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use cartridge_rs::{AsyncCartridge, Cartridge};
+/// # async fn run() -> cartridge_rs::Result<()> {
+/// let cart = AsyncCartridge::new(Cartridge::create("my-data", "My Data")?);
+/// cart.write("hello.txt", b"hello".to_vec()).await?;
+/// assert_eq!(cart.read("hello.txt").await?, b"hello");
+/// cart.flush().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AsyncCartridge {
+    shared: SharedCartridge,
+}
+
+impl AsyncCartridge {
+    /// Wrap a cartridge for async use
+    pub fn new(cartridge: Cartridge) -> Self {
+        Self::from_shared(cartridge.into_shared())
+    }
+
+    /// Wrap an existing shared handle; sync and async callers then see the
+    /// same cartridge
+    pub fn from_shared(shared: SharedCartridge) -> Self {
+        AsyncCartridge { shared }
+    }
+
+    /// The shared handle underneath, for synchronous access
+    pub fn shared(&self) -> &SharedCartridge {
+        &self.shared
+    }
+
+    /// Read a file's contents
+    pub async fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let path = path.to_string();
+        self.blocking(move |cart| cart.read(path)).await
+    }
+
+    /// Write a file, creating it if it doesn't exist
+    pub async fn write(&self, path: &str, data: Vec<u8>) -> Result<()> {
+        let path = path.to_string();
+        self.blocking(move |cart| cart.write(path, &data)).await
+    }
+
+    /// Delete a file
+    pub async fn delete(&self, path: &str) -> Result<()> {
+        let path = path.to_string();
+        self.blocking(move |cart| cart.delete(path)).await
+    }
+
+    /// List entries under a prefix
+    pub async fn list_entries(&self, prefix: &str) -> Result<Vec<Entry>> {
+        let prefix = prefix.to_string();
+        self.blocking(move |cart| cart.list_entries(prefix)).await
+    }
+
+    /// List the immediate children of a directory
+    pub async fn list_children(&self, parent: &str) -> Result<Vec<Entry>> {
+        let parent = parent.to_string();
+        self.blocking(move |cart| cart.list_children(parent)).await
+    }
+
+    /// Check if a path exists
+    pub async fn exists(&self, path: &str) -> Result<bool> {
+        let path = path.to_string();
+        self.blocking(move |cart| cart.exists(path)).await
+    }
+
+    /// Check if a path is a directory
+    pub async fn is_dir(&self, path: &str) -> Result<bool> {
+        let path = path.to_string();
+        self.blocking(move |cart| cart.is_dir(path)).await
+    }
+
+    /// What a path is, or `None` if missing
+    pub async fn path_type(&self, path: &str) -> Result<Option<FileType>> {
+        let path = path.to_string();
+        self.blocking(move |cart| cart.path_type(path)).await
+    }
+
+    /// Get metadata for a path
+    pub async fn metadata(&self, path: &str) -> Result<FileMetadata> {
+        let path = path.to_string();
+        self.blocking(move |cart| cart.metadata(path)).await
+    }
+
+    /// Flush pending changes to disk
+    pub async fn flush(&self) -> Result<()> {
+        self.blocking(|cart| cart.flush()).await
+    }
+
+    /// Run `f` on the blocking pool with a clone of the shared handle
+    async fn blocking<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&SharedCartridge) -> Result<T> + Send + 'static,
+    {
+        let shared = self.shared.clone();
+        tokio::task::spawn_blocking(move || f(&shared))
+            .await
+            .map_err(std::io::Error::from)?
+    }
+}
+
+impl AsyncVfs for AsyncCartridge {
+    fn list_entries(&self, prefix: &str) -> impl Future<Output = Result<Vec<Entry>>> + Send {
+        AsyncCartridge::list_entries(self, prefix)
+    }
+
+    fn list_children(&self, parent: &str) -> impl Future<Output = Result<Vec<Entry>>> + Send {
+        AsyncCartridge::list_children(self, parent)
+    }
+
+    fn read(&self, path: &str) -> impl Future<Output = Result<Vec<u8>>> + Send {
+        AsyncCartridge::read(self, path)
+    }
+
+    fn write(&self, path: &str, data: Vec<u8>) -> impl Future<Output = Result<()>> + Send {
+        AsyncCartridge::write(self, path, data)
+    }
+
+    fn delete(&self, path: &str) -> impl Future<Output = Result<()>> + Send {
+        AsyncCartridge::delete(self, path)
+    }
+
+    fn exists(&self, path: &str) -> impl Future<Output = Result<bool>> + Send {
+        AsyncCartridge::exists(self, path)
+    }
+
+    fn is_dir(&self, path: &str) -> impl Future<Output = Result<bool>> + Send {
+        AsyncCartridge::is_dir(self, path)
+    }
+
+    fn metadata(&self, path: &str) -> impl Future<Output = Result<FileMetadata>> + Send {
+        AsyncCartridge::metadata(self, path)
+    }
+}
@@ -0,0 +1,460 @@
+//! `cart`: inspect and edit cartridges from the command line
+//!
+//! Built with `--features cli`. Every subcommand names the `.cart` file
+//! first, and `--json` switches the output to JSON for scripts.
+//!
+//! Exit codes: 0 on success, 1 for errors with no more specific code, 2 for
+//! bad arguments, 3 when a cartridge, path, manifest or snapshot doesn't
+//! exist, 4 for other I/O errors, and 5 when `cart check` finds problems.
+
+use cartridge_rs::{Cartridge, CartridgeError, FileType, Manifest, Result, SnapshotManager};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use serde_json::{json, Map, Value};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const EXIT_ERROR: u8 = 1;
+const EXIT_NOT_FOUND: u8 = 3;
+const EXIT_IO: u8 = 4;
+const EXIT_INCONSISTENT: u8 = 5;
+
+fn cli() -> Command {
+    let cart = || Arg::new("cart").required(true).help("Cartridge file");
+    let path = || Arg::new("path").required(true).help("Path inside the cartridge");
+
+    Command::new("cart")
+        .about("Inspect and edit cartridge archives")
+        .version(env!("CARGO_PKG_VERSION"))
+        .subcommand_required(true)
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Print JSON instead of text"),
+        )
+        .subcommand(
+            Command::new("ls")
+                .about("List entries under a prefix")
+                .arg(cart())
+                .arg(Arg::new("prefix").default_value("").help("Only list under this path"))
+                .arg(
+                    Arg::new("long")
+                        .short('l')
+                        .long("long")
+                        .action(ArgAction::SetTrue)
+                        .help("Show type, size and modification time"),
+                ),
+        )
+        .subcommand(Command::new("cat").about("Write a file to stdout").arg(cart()).arg(path()))
+        .subcommand(
+            Command::new("put")
+                .about("Copy a local file in, creating the cartridge if needed")
+                .arg(cart())
+                .arg(Arg::new("local").required(true).help("Local file to read"))
+                .arg(path()),
+        )
+        .subcommand(
+            Command::new("get")
+                .about("Copy a file out to the local filesystem")
+                .arg(cart())
+                .arg(path())
+                .arg(Arg::new("local").help("Where to write it (default: the file's name)")),
+        )
+        .subcommand(
+            Command::new("rm")
+                .about("Remove a file, or a directory with -r")
+                .arg(cart())
+                .arg(path())
+                .arg(
+                    Arg::new("recursive")
+                        .short('r')
+                        .long("recursive")
+                        .action(ArgAction::SetTrue)
+                        .help("Remove a directory and everything under it"),
+                ),
+        )
+        .subcommand(
+            Command::new("stat")
+                .about("Show a file's metadata, or the cartridge's when no path is given")
+                .arg(cart())
+                .arg(Arg::new("path").help("Path inside the cartridge")),
+        )
+        .subcommand(
+            Command::new("manifest")
+                .about("Show the manifest, or change top-level fields with --set")
+                .arg(cart())
+                .arg(
+                    Arg::new("set")
+                        .long("set")
+                        .value_name("KEY=VALUE")
+                        .action(ArgAction::Append)
+                        .help("Set a field; non-string fields take a JSON value"),
+                ),
+        )
+        .subcommand(
+            Command::new("snapshot")
+                .about("Create, list and restore snapshots")
+                .subcommand_required(true)
+                .arg(
+                    Arg::new("dir")
+                        .long("dir")
+                        .global(true)
+                        .help("Snapshot directory (default: <cartridge>.snapshots)"),
+                )
+                .subcommand(
+                    Command::new("create")
+                        .about("Snapshot the cartridge")
+                        .arg(cart())
+                        .arg(Arg::new("name").required(true))
+                        .arg(Arg::new("description").long("description").default_value("")),
+                )
+                .subcommand(Command::new("list").about("List snapshots").arg(cart()))
+                .subcommand(
+                    Command::new("restore")
+                        .about("Restore a snapshot by id or name")
+                        .arg(cart())
+                        .arg(Arg::new("snapshot").required(true)),
+                ),
+        )
+        .subcommand(
+            Command::new("check")
+                .about("Check catalog and allocator consistency")
+                .arg(cart()),
+        )
+        .subcommand(Command::new("vacuum").about("Compact the cartridge").arg(cart()))
+}
+
+fn main() -> ExitCode {
+    let matches = cli().get_matches();
+    let json = matches.get_flag("json");
+    match run(&matches, json) {
+        Ok(code) => ExitCode::from(code),
+        Err(e) => {
+            eprintln!("cart: {e}");
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+/// Exit code for an error, so scripts can tell "not there" from failures
+fn exit_code(error: &CartridgeError) -> u8 {
+    match error {
+        CartridgeError::NotFound { .. }
+        | CartridgeError::ManifestNotFound
+        | CartridgeError::SnapshotNotFound { .. } => EXIT_NOT_FOUND,
+        CartridgeError::Io(e) if e.kind() == std::io::ErrorKind::NotFound => EXIT_NOT_FOUND,
+        CartridgeError::Io(_) => EXIT_IO,
+        _ => EXIT_ERROR,
+    }
+}
+
+fn run(matches: &ArgMatches, json: bool) -> Result<u8> {
+    let (name, args) = matches.subcommand().expect("subcommand is required");
+    let arg = |id: &str| args.get_one::<String>(id).map(String::as_str);
+    let cart_path = || PathBuf::from(arg("cart").expect("required"));
+    let path = || arg("path").expect("required");
+
+    match name {
+        "ls" => {
+            let cart = Cartridge::open_read_only(cart_path())?;
+            let entries = cart.list_entries(arg("prefix").unwrap_or(""))?;
+            if json {
+                print_json(&serde_json::to_value(&entries)?)?;
+            } else {
+                for entry in entries {
+                    if args.get_flag("long") {
+                        let kind = match entry.file_type {
+                            FileType::Directory => 'd',
+                            FileType::Symlink => 'l',
+                            FileType::File => '-',
+                        };
+                        let size = entry.size.map_or("-".to_string(), |s| s.to_string());
+                        let modified = entry.modified.map_or("-".to_string(), format_time);
+                        println!("{kind} {size:>12} {modified:>16} {}", entry.path);
+                    } else {
+                        println!("{}", entry.path);
+                    }
+                }
+            }
+        }
+        "cat" => {
+            let cart = Cartridge::open_read_only(cart_path())?;
+            let data = cart.read(path())?;
+            std::io::stdout().write_all(&data)?;
+        }
+        "put" => {
+            let cart_path = cart_path();
+            let slug = cart_path.file_stem().and_then(|s| s.to_str()).unwrap_or("cartridge");
+            let mut cart = Cartridge::open_or_create_at(&cart_path, slug, slug)?;
+            let data = std::fs::read(arg("local").expect("required"))?;
+            cart.write(path(), &data)?;
+            cart.flush()?;
+            report(json, json!({ "path": path(), "bytes": data.len() }))?;
+        }
+        "get" => {
+            let cart = Cartridge::open_read_only(cart_path())?;
+            let data = cart.read(path())?;
+            let local = match arg("local") {
+                Some(local) => PathBuf::from(local),
+                None => PathBuf::from(path().rsplit('/').next().unwrap_or(path())),
+            };
+            std::fs::write(&local, &data)?;
+            report(json, json!({ "path": path(), "local": local, "bytes": data.len() }))?;
+        }
+        "rm" => {
+            let mut cart = Cartridge::open(cart_path())?;
+            let removed = remove(&mut cart, path(), args.get_flag("recursive"))?;
+            cart.flush()?;
+            report(json, json!({ "removed": removed }))?;
+        }
+        "stat" => {
+            let cart = Cartridge::open_read_only(cart_path())?;
+            let value = match arg("path") {
+                Some(path) => {
+                    let metadata = cart.metadata(path)?;
+                    json!({
+                        "path": path,
+                        "type": format!("{:?}", metadata.file_type).to_lowercase(),
+                        "size": metadata.size,
+                        "blocks": metadata.data_blocks().count(),
+                        "holes": metadata.hole_count(),
+                        "inline": metadata.is_inline(),
+                        "created": metadata.created_at,
+                        "modified": metadata.modified_at,
+                        "permissions": format!("{:o}", metadata.permissions),
+                        "content_type": metadata.content_type,
+                        "user_metadata": metadata.user_metadata,
+                    })
+                }
+                None => {
+                    let mut value = serde_json::to_value(cart.detailed_stats()?)?;
+                    value["slug"] = json!(cart.slug()?);
+                    value["title"] = json!(cart.title()?);
+                    value["page_size"] = json!(cart.page_size());
+                    value
+                }
+            };
+            print_object(json, &value)?;
+        }
+        "manifest" => {
+            let sets: Vec<&String> =
+                args.get_many("set").map(Iterator::collect).unwrap_or_default();
+            if sets.is_empty() {
+                let cart = Cartridge::open_read_only(cart_path())?;
+                print_json(&serde_json::to_value(cart.read_manifest()?)?)?;
+            } else {
+                let mut cart = Cartridge::open(cart_path())?;
+                let mut value = serde_json::to_value(cart.read_manifest()?)?;
+                for set in sets {
+                    apply_set(&mut value, set)?;
+                }
+                let manifest: Manifest = serde_json::from_value(value)?;
+                cart.update_manifest(|current| *current = manifest)?;
+                cart.flush()?;
+                print_json(&serde_json::to_value(cart.read_manifest()?)?)?;
+            }
+        }
+        "snapshot" => return snapshot(args, json),
+        "check" => {
+            let cart = Cartridge::open_read_only(cart_path())?;
+            let report = cart.check()?;
+            if json {
+                print_json(&serde_json::to_value(&report)?)?;
+            } else if report.is_consistent() {
+                println!("ok");
+            } else {
+                print_object(false, &serde_json::to_value(&report)?)?;
+            }
+            if !report.is_consistent() {
+                return Ok(EXIT_INCONSISTENT);
+            }
+        }
+        "vacuum" => {
+            let mut cart = Cartridge::open(cart_path())?;
+            let vacuum = cart.vacuum()?;
+            print_object(
+                json,
+                &json!({
+                    "pages_relocated": vacuum.pages_relocated,
+                    "blocks_before": vacuum.blocks_before,
+                    "blocks_after": vacuum.blocks_after,
+                    "bytes_before": vacuum.bytes_before,
+                    "bytes_after": vacuum.bytes_after,
+                    "bytes_reclaimed": vacuum.bytes_reclaimed,
+                }),
+            )?;
+        }
+        _ => unreachable!("unknown subcommand {name}"),
+    }
+    Ok(0)
+}
+
+/// `cart snapshot create|list|restore`
+fn snapshot(matches: &ArgMatches, json: bool) -> Result<u8> {
+    let (name, args) = matches.subcommand().expect("subcommand is required");
+    let cart_path = PathBuf::from(args.get_one::<String>("cart").expect("required"));
+    let dir = match args.get_one::<String>("dir") {
+        Some(dir) => PathBuf::from(dir),
+        None => cart_path.with_extension("snapshots"),
+    };
+
+    match name {
+        "create" => {
+            let cart = Cartridge::open(&cart_path)?;
+            let snapshot_name = args.get_one::<String>("name").expect("required").clone();
+            let description = args.get_one::<String>("description").cloned().unwrap_or_default();
+            let id = cart.create_snapshot(snapshot_name, description, &dir)?;
+            if json {
+                print_json(&json!({ "id": id }))?;
+            } else {
+                println!("{id}");
+            }
+        }
+        "list" => {
+            let manager = open_snapshots(&dir)?;
+            let snapshots: Vec<Value> = manager
+                .list_snapshots()
+                .into_iter()
+                .map(|s| {
+                    json!({
+                        "id": s.id,
+                        "name": s.name,
+                        "description": s.description,
+                        "created_at": s.created_at,
+                        "size_bytes": s.size_bytes,
+                        "parent_id": s.parent_id,
+                    })
+                })
+                .collect();
+            if json {
+                print_json(&Value::Array(snapshots))?;
+            } else {
+                for s in &snapshots {
+                    let created = format_time(s["created_at"].as_u64().unwrap_or(0) / 1_000_000);
+                    println!("{}  {created}  {}", s["id"], s["name"].as_str().unwrap_or(""));
+                }
+            }
+        }
+        "restore" => {
+            let wanted = args.get_one::<String>("snapshot").expect("required");
+            let id = match wanted.parse::<u64>() {
+                Ok(id) => id,
+                Err(_) => open_snapshots(&dir)?
+                    .list_snapshots()
+                    .into_iter()
+                    .filter(|s| &s.name == wanted)
+                    .map(|s| s.id)
+                    .max()
+                    .ok_or_else(|| CartridgeError::NotFound {
+                        path: format!("snapshot {wanted}"),
+                    })?,
+            };
+            let mut cart = Cartridge::open(&cart_path)?;
+            cart.restore_snapshot(id, &dir)?;
+            cart.flush()?;
+            report(json, json!({ "restored": id }))?;
+        }
+        _ => unreachable!("unknown snapshot subcommand {name}"),
+    }
+    Ok(0)
+}
+
+fn open_snapshots(dir: &Path) -> Result<SnapshotManager> {
+    if !dir.is_dir() {
+        return Err(CartridgeError::NotFound {
+            path: dir.display().to_string(),
+        });
+    }
+    SnapshotManager::new(dir)
+}
+
+/// Remove `path`, and everything under it when `recursive`; returns how many
+/// catalog entries went
+fn remove(cart: &mut Cartridge, path: &str, recursive: bool) -> Result<usize> {
+    match cart.path_type(path)? {
+        None => Err(CartridgeError::NotFound {
+            path: path.to_string(),
+        }),
+        Some(FileType::Directory) if !recursive => Err(CartridgeError::NotAFile {
+            path: format!("{path} (use -r to remove a directory)"),
+        }),
+        Some(FileType::Directory) => {
+            let mut paths = cart.list(path)?;
+            // Children sort after their parents, so this empties each
+            // directory before removing it
+            paths.sort();
+            let mut removed = 0;
+            for child in paths.iter().rev() {
+                cart.delete(child)?;
+                removed += 1;
+            }
+            // The directory itself may only be implied by its children
+            match cart.delete(path) {
+                Ok(()) => removed += 1,
+                Err(CartridgeError::NotFound { .. }) => {}
+                Err(e) => return Err(e),
+            }
+            Ok(removed)
+        }
+        Some(_) => {
+            cart.delete(path)?;
+            Ok(1)
+        }
+    }
+}
+
+/// Set a top-level manifest field from `KEY=VALUE`
+///
+/// String fields, and fields the manifest doesn't have yet, take the value
+/// as written; any other field needs it as JSON.
+fn apply_set(manifest: &mut Value, set: &str) -> Result<()> {
+    let (key, raw) = set.split_once('=').ok_or_else(|| {
+        CartridgeError::ManifestValidation(format!("expected KEY=VALUE, got {set:?}"))
+    })?;
+    let fields = manifest
+        .as_object_mut()
+        .ok_or_else(|| CartridgeError::ManifestValidation("manifest is not an object".into()))?;
+    let value = match fields.get(key) {
+        None | Some(Value::String(_)) | Some(Value::Null) => Value::String(raw.to_string()),
+        Some(_) => serde_json::from_str(raw)?,
+    };
+    fields.insert(key.to_string(), value);
+    Ok(())
+}
+
+/// Print a confirmation object, in JSON mode only; text mode stays quiet
+fn report(json: bool, value: Value) -> Result<()> {
+    if json {
+        print_json(&value)?;
+    }
+    Ok(())
+}
+
+/// Print an object as JSON, or as `key: value` lines
+fn print_object(json: bool, value: &Value) -> Result<()> {
+    if json {
+        return print_json(value);
+    }
+    let empty = Map::new();
+    for (key, field) in value.as_object().unwrap_or(&empty) {
+        match field {
+            Value::String(s) => println!("{key}: {s}"),
+            Value::Null => println!("{key}: -"),
+            other => println!("{key}: {other}"),
+        }
+    }
+    Ok(())
+}
+
+fn print_json(value: &Value) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Unix seconds as local-agnostic `YYYY-MM-DD HH:MM` (UTC)
+fn format_time(secs: u64) -> String {
+    chrono::DateTime::from_timestamp(secs as i64, 0)
+        .map_or_else(|| secs.to_string(), |t| t.format("%Y-%m-%d %H:%M").to_string())
+}
@@ -0,0 +1,286 @@
+//! Copying a directory tree out of any [`Vfs`] into a cartridge
+//!
+//! [`Cartridge::copy_tree_from`] is how release cartridges get assembled
+//! from several sources: another cartridge, a [`LocalVfs`](crate::LocalVfs)
+//! directory or a zip archive. Content is streamed through
+//! [`Vfs::open_reader`], so a file is never held in memory whole when the
+//! source can read it in pieces (a cartridge source reads one page at a
+//! time). Blocks can't be shared between containers, so unlike
+//! [`Cartridge::clone_file`] the content is always copied.
+
+use crate::iam::PatternMatcher;
+use crate::transfer::{join_prefix, skipped};
+use crate::validation::normalize_path;
+use crate::{
+    is_internal, Cartridge, CartridgeError, FileType, MetadataPatch, Result, SkippedEntry, Vfs,
+};
+use serde::{Deserialize, Serialize};
+
+/// What [`Cartridge::copy_tree_from`] does when a destination file exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OverwritePolicy {
+    /// Fail with [`CartridgeError::AlreadyExists`] before anything is
+    /// written (default)
+    #[default]
+    Error,
+    /// Leave the existing file alone and record it as skipped
+    Skip,
+    /// Replace the existing file's content and metadata
+    Replace,
+}
+
+/// Options for [`Cartridge::copy_tree_from`]
+#[derive(Debug, Clone, Default)]
+pub struct CopyOptions {
+    /// What to do with files that already exist in the destination
+    pub overwrite: OverwritePolicy,
+
+    /// Only copy entries whose path relative to the source prefix matches
+    /// this glob (`*` for one segment, `**` for any depth), e.g. `"**/*.json"`
+    pub include: Option<String>,
+
+    /// Leave out entries whose relative path matches this glob, even if
+    /// they match `include`
+    pub exclude: Option<String>,
+
+    /// Work out what would be copied and report it without writing anything
+    pub dry_run: bool,
+}
+
+/// Summary of a [`Cartridge::copy_tree_from`] call
+///
+/// On a dry run the counts describe what would have been copied.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CopyReport {
+    /// Files written into the cartridge
+    pub files_copied: usize,
+    /// Directories created in the cartridge
+    pub directories_created: usize,
+    /// Total content bytes written
+    pub bytes_copied: u64,
+    /// Entries left out by the `include` or `exclude` glob
+    pub entries_filtered: usize,
+    /// Entries that couldn't or shouldn't be copied
+    pub skipped: Vec<SkippedEntry>,
+}
+
+/// One source entry that will be copied
+struct Planned {
+    source: String,
+    dest: String,
+    kind: FileType,
+    size: u64,
+    /// Replacing a file already in the cartridge
+    replaces: bool,
+}
+
+impl Cartridge {
+    /// Copy every file and directory under `src_prefix` in `src` to
+    /// `dst_prefix` in this cartridge
+    ///
+    /// Paths keep their position relative to `src_prefix`. Each copy takes
+    /// the source's creation and modification times, content type and user
+    /// metadata, where the source records them, and the catalog is updated
+    /// for all of them at once at the end. Directories are created only
+    /// where the source has them as entries of their own, so empty
+    /// directories survive and no extra ones appear.
+    ///
+    /// Every conflict is found before anything is written: with
+    /// [`OverwritePolicy::Error`] an existing destination fails the call
+    /// with nothing copied. The container is then grown once for the whole
+    /// copy. Symlinks (which [`Vfs`] can't read without following) and
+    /// anything that would land under `.cartridge/` are recorded in
+    /// [`CopyReport::skipped`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::{Cartridge, CopyOptions, OverwritePolicy};
+    /// let assets = Cartridge::open("assets.cart")?;
+    /// let mut release = Cartridge::create("release", "Release")?;
+    /// let options = CopyOptions {
+    ///     overwrite: OverwritePolicy::Replace,
+    ///     exclude: Some("**/*.psd".to_string()),
+    ///     ..Default::default()
+    /// };
+    /// let report = release.copy_tree_from(&assets, "/textures", "/assets/textures", &options)?;
+    /// println!("copied {} files ({} bytes)", report.files_copied, report.bytes_copied);
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn copy_tree_from<V: Vfs + ?Sized>(
+        &mut self,
+        src: &V,
+        src_prefix: &str,
+        dst_prefix: &str,
+        options: &CopyOptions,
+    ) -> Result<CopyReport> {
+        let src_prefix = normalize_path(src_prefix)?;
+        let dst_prefix = normalize_path(dst_prefix)?;
+        let mut report = CopyReport::default();
+        let mut plan = Vec::new();
+
+        for entry in src.list_entries(&src_prefix)? {
+            let Some(rel) = relative_to(&entry.path, &src_prefix) else {
+                continue;
+            };
+            if !selected(&rel, options) {
+                report.entries_filtered += 1;
+                continue;
+            }
+            if entry.file_type == FileType::Symlink {
+                report.skipped.push(skipped(&entry.path, "symlink"));
+                continue;
+            }
+
+            let dest = join_prefix(&dst_prefix, &rel);
+            if is_internal(&dest) {
+                report.skipped.push(skipped(
+                    &entry.path,
+                    "destination is reserved for cartridge metadata",
+                ));
+                continue;
+            }
+
+            if entry.is_dir {
+                // Directories only implied by the files below them have no
+                // metadata of their own and don't need creating
+                match src.metadata(&entry.path) {
+                    Ok(metadata) if metadata.is_directory() => {}
+                    Ok(_) | Err(CartridgeError::NotFound { .. }) => continue,
+                    Err(e) => return Err(e),
+                }
+                if self.is_dir(&dest)? {
+                    continue;
+                }
+                if self.exists(&dest)? {
+                    report
+                        .skipped
+                        .push(skipped(&entry.path, "a file exists at the destination"));
+                    continue;
+                }
+                plan.push(Planned {
+                    source: entry.path,
+                    dest,
+                    kind: FileType::Directory,
+                    size: 0,
+                    replaces: false,
+                });
+                continue;
+            }
+
+            if self.is_dir(&dest)? {
+                report.skipped.push(skipped(
+                    &entry.path,
+                    "a directory exists at the destination",
+                ));
+                continue;
+            }
+            let replaces = self.exists(&dest)?;
+            if replaces {
+                match options.overwrite {
+                    OverwritePolicy::Error => {
+                        return Err(CartridgeError::AlreadyExists { path: dest });
+                    }
+                    OverwritePolicy::Skip => {
+                        report.skipped.push(skipped(&entry.path, "already exists"));
+                        continue;
+                    }
+                    OverwritePolicy::Replace => {}
+                }
+            }
+            let size = match entry.size {
+                Some(size) => size,
+                None => src.metadata(&entry.path)?.size,
+            };
+            plan.push(Planned {
+                source: entry.path,
+                dest,
+                kind: FileType::File,
+                size,
+                replaces,
+            });
+        }
+
+        if options.dry_run {
+            for planned in &plan {
+                if planned.kind == FileType::Directory {
+                    report.directories_created += 1;
+                } else {
+                    report.files_copied += 1;
+                    report.bytes_copied += planned.size;
+                }
+            }
+            return Ok(report);
+        }
+
+        // Directories first, parents before children, so creating one never
+        // trips over a parent that create_dir already made
+        plan.sort_by(|a, b| {
+            let dir_first = |p: &Planned| p.kind != FileType::Directory;
+            (dir_first(a), &a.dest).cmp(&(dir_first(b), &b.dest))
+        });
+
+        let page_size = self.page_size() as u64;
+        let reserve = plan
+            .iter()
+            .map(|planned| planned.size.div_ceil(page_size) * page_size)
+            .sum();
+        self.inner.reserve_bytes(reserve)?;
+
+        let mut patches = Vec::with_capacity(plan.len());
+        for planned in plan {
+            let metadata = src.metadata(&planned.source)?;
+            if planned.kind == FileType::Directory {
+                self.create_dir(&planned.dest)?;
+                report.directories_created += 1;
+            } else {
+                let mut reader = src.open_reader(&planned.source)?;
+                report.bytes_copied += self.write_stream(&planned.dest, &mut reader)?;
+                report.files_copied += 1;
+            }
+
+            let mut patch =
+                MetadataPatch::times(Some(metadata.created_at), Some(metadata.modified_at));
+            if metadata.content_type.is_some() {
+                patch.content_type = Some(metadata.content_type);
+            }
+            if planned.replaces {
+                // Keys the old file had but the source doesn't are dropped
+                for key in self.get_metadata_keys(&planned.dest)?.into_keys() {
+                    patch.user_metadata.insert(key, None);
+                }
+            }
+            for (key, value) in metadata.user_metadata {
+                patch.user_metadata.insert(key, Some(value));
+            }
+            patches.push((planned.dest, patch));
+        }
+        self.update_metadata_bulk(patches)?;
+
+        Ok(report)
+    }
+}
+
+/// `path` relative to `prefix`, without a leading slash, or `None` when it
+/// isn't strictly below it
+fn relative_to(path: &str, prefix: &str) -> Option<String> {
+    let rel = if prefix == "/" {
+        path.strip_prefix('/')?
+    } else {
+        path.strip_prefix(prefix)?.strip_prefix('/')?
+    };
+    (!rel.is_empty()).then(|| rel.to_string())
+}
+
+/// Whether the include and exclude globs let `rel` through
+fn selected(rel: &str, options: &CopyOptions) -> bool {
+    let included = options
+        .include
+        .as_ref()
+        .is_none_or(|pattern| PatternMatcher::matches(pattern, rel));
+    let excluded = options
+        .exclude
+        .as_ref()
+        .is_some_and(|pattern| PatternMatcher::matches(pattern, rel));
+    included && !excluded
+}
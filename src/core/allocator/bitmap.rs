@@ -1,7 +1,9 @@
 //! Bitmap allocator for small files (<256KB)
 //!
 //! Uses a multi-level bitmap with <2% overhead for tracking free blocks.
-//! Each bit represents one 4KB block.
+//! Each bit represents one 4KB block, and a summary level has one bit per
+//! bitmap word that has a free block in it, so allocation skips full words
+//! 64 at a time instead of scanning from block 0.
 
 use crate::allocator::BlockAllocator;
 use crate::error::{CartridgeError, Result};
@@ -23,16 +25,55 @@ pub struct BitmapAllocator {
 
     /// Number of free blocks available
     free_blocks: usize,
+
+    /// Bit `i` of word `j` is set when bitmap word `j * 64 + i` has a free
+    /// bit. Not serialized; rebuilt on first use after loading.
+    #[serde(skip)]
+    summary: Vec<u64>,
+
+    /// Bitmap words allocation has looked at, so tests can check the
+    /// summary keeps that down
+    #[cfg(test)]
+    #[serde(skip)]
+    words_visited: usize,
 }
 
 impl BitmapAllocator {
     /// Create a new bitmap allocator
     pub fn new(total_blocks: usize) -> Self {
-        let num_words = (total_blocks + 63) / 64;
-        BitmapAllocator {
+        let num_words = total_blocks.div_ceil(64);
+        let mut allocator = BitmapAllocator {
             bitmap: vec![0u64; num_words],
             total_blocks,
             free_blocks: total_blocks,
+            summary: Vec::new(),
+            #[cfg(test)]
+            words_visited: 0,
+        };
+        allocator.rebuild_summary();
+        allocator
+    }
+
+    /// Recompute the summary level from the bitmap
+    fn rebuild_summary(&mut self) {
+        self.summary = vec![0u64; self.bitmap.len().div_ceil(64)];
+        for word_idx in 0..self.bitmap.len() {
+            self.update_summary(word_idx);
+        }
+    }
+
+    /// Make the summary bit for `word_idx` match the bitmap word
+    ///
+    /// A no-op while the summary hasn't been built yet.
+    fn update_summary(&mut self, word_idx: usize) {
+        if self.summary.len() != self.bitmap.len().div_ceil(64) {
+            return;
+        }
+        let bit = 1u64 << (word_idx % 64);
+        if self.bitmap[word_idx] == u64::MAX {
+            self.summary[word_idx / 64] &= !bit;
+        } else {
+            self.summary[word_idx / 64] |= bit;
         }
     }
 
@@ -44,30 +85,41 @@ impl BitmapAllocator {
             return Err(CartridgeError::OutOfSpace);
         }
 
-        let mut allocated = Vec::with_capacity(num_blocks);
-
-        'outer: for (word_idx, word) in self.bitmap.iter_mut().enumerate() {
-            if *word == u64::MAX {
-                continue; // All bits set (all allocated)
-            }
+        if self.summary.len() != self.bitmap.len().div_ceil(64) {
+            self.rebuild_summary();
+        }
 
-            // Find free bits in this word
-            for bit_idx in 0..64 {
-                if allocated.len() == num_blocks {
-                    break 'outer;
+        // Lowest free blocks first, visiting only words the summary says
+        // have room
+        let mut allocated = Vec::with_capacity(num_blocks);
+        'outer: for summary_idx in 0..self.summary.len() {
+            let mut candidates = self.summary[summary_idx];
+            while candidates != 0 {
+                let word_idx = summary_idx * 64 + candidates.trailing_zeros() as usize;
+                candidates &= candidates - 1;
+                #[cfg(test)]
+                {
+                    self.words_visited += 1;
                 }
 
-                if (*word & (1u64 << bit_idx)) == 0 {
-                    // Free block found
+                let mut free = !self.bitmap[word_idx];
+                while free != 0 && allocated.len() < num_blocks {
+                    let bit_idx = free.trailing_zeros() as usize;
+                    free &= free - 1;
                     let block_id = (word_idx * 64 + bit_idx) as u64;
 
                     // Don't allocate beyond total_blocks
                     if block_id >= self.total_blocks as u64 {
-                        break 'outer;
+                        break;
                     }
 
                     allocated.push(block_id);
-                    *word |= 1u64 << bit_idx; // Mark as allocated
+                    self.bitmap[word_idx] |= 1u64 << bit_idx; // Mark as allocated
+                }
+                self.update_summary(word_idx);
+
+                if allocated.len() == num_blocks {
+                    break 'outer;
                 }
             }
         }
@@ -78,6 +130,7 @@ impl BitmapAllocator {
                 let word_idx = (block_id / 64) as usize;
                 let bit_idx = (block_id % 64) as usize;
                 self.bitmap[word_idx] &= !(1u64 << bit_idx);
+                self.update_summary(word_idx);
             }
             return Err(CartridgeError::OutOfSpace);
         }
@@ -87,7 +140,10 @@ impl BitmapAllocator {
     }
 
     /// Free previously allocated blocks
+    ///
+    /// Blocks that are already free are skipped and not counted.
     pub fn free_allocated_blocks(&mut self, blocks: &[u64]) -> Result<()> {
+        let mut freed = 0;
         for &block_id in blocks {
             if block_id >= self.total_blocks as u64 {
                 return Err(CartridgeError::InvalidBlockId(block_id));
@@ -104,9 +160,11 @@ impl BitmapAllocator {
             }
 
             self.bitmap[word_idx] &= !(1u64 << bit_idx); // Clear bit
+            self.update_summary(word_idx);
+            freed += 1;
         }
 
-        self.free_blocks += blocks.len();
+        self.free_blocks += freed;
         Ok(())
     }
 
@@ -122,6 +180,27 @@ impl BitmapAllocator {
         (self.bitmap[word_idx] & (1u64 << bit_idx)) != 0
     }
 
+    /// Whether every block in `start..end` is free
+    ///
+    /// Checks a word at a time. Blocks past `total_blocks` count as not free.
+    pub fn is_range_free(&self, start: u64, end: u64) -> bool {
+        if end > self.total_blocks as u64 {
+            return false;
+        }
+        let mut block = start;
+        while block < end {
+            let word_idx = (block / 64) as usize;
+            let bit_idx = block % 64;
+            let bits = (end - block).min(64 - bit_idx);
+            let mask = if bits == 64 { u64::MAX } else { ((1u64 << bits) - 1) << bit_idx };
+            if self.bitmap[word_idx] & mask != 0 {
+                return false;
+            }
+            block += bits;
+        }
+        true
+    }
+
     /// Mark specific blocks as allocated (without changing free_blocks counter)
     ///
     /// Used by HybridAllocator to keep allocators in sync
@@ -136,6 +215,7 @@ impl BitmapAllocator {
 
             // Set bit to 1 (allocated)
             self.bitmap[word_idx] |= 1u64 << bit_idx;
+            self.update_summary(word_idx);
         }
         Ok(())
     }
@@ -157,6 +237,7 @@ impl BitmapAllocator {
             // Only count if actually transitioning from free to allocated
             if (self.bitmap[word_idx] & (1u64 << bit_idx)) == 0 {
                 self.bitmap[word_idx] |= 1u64 << bit_idx;
+                self.update_summary(word_idx);
                 newly_allocated += 1;
             }
         }
@@ -178,6 +259,7 @@ impl BitmapAllocator {
 
             // Clear bit to 0 (free)
             self.bitmap[word_idx] &= !(1u64 << bit_idx);
+            self.update_summary(word_idx);
         }
         Ok(())
     }
@@ -207,9 +289,11 @@ impl BitmapAllocator {
         free
     }
 
-    /// Recalibrate the internal `free_blocks` counter from the actual bitmap state.
+    /// Recalibrate the internal `free_blocks` counter and the summary level
+    /// from the actual bitmap state.
     pub fn recalibrate(&mut self) {
         self.free_blocks = self.count_free();
+        self.rebuild_summary();
     }
 
     /// Extend bitmap capacity to track more blocks
@@ -220,10 +304,11 @@ impl BitmapAllocator {
             return Ok(()); // No need to extend
         }
 
-        let new_num_words = (new_total_blocks + 63) / 64;
+        let new_num_words = new_total_blocks.div_ceil(64);
 
         // Extend bitmap with zeros (representing free blocks)
         self.bitmap.resize(new_num_words, 0u64);
+        self.rebuild_summary();
 
         let added_blocks = new_total_blocks - self.total_blocks;
         self.total_blocks = new_total_blocks;
@@ -252,7 +337,7 @@ impl BitmapAllocator {
         }
 
         // Truncate bitmap
-        let new_num_words = (new_total_blocks + 63) / 64;
+        let new_num_words = new_total_blocks.div_ceil(64);
         self.bitmap.truncate(new_num_words);
 
         // Clear any bits beyond new_total_blocks in the last word
@@ -274,7 +359,7 @@ impl BitmapAllocator {
 
 impl BlockAllocator for BitmapAllocator {
     fn allocate(&mut self, size: u64) -> Result<Vec<u64>> {
-        let num_blocks = ((size + PAGE_SIZE as u64 - 1) / PAGE_SIZE as u64) as usize;
+        let num_blocks = size.div_ceil(PAGE_SIZE as u64) as usize;
         self.allocate_blocks(num_blocks)
     }
 
@@ -359,7 +444,7 @@ mod tests {
         let mut alloc = BitmapAllocator::new(10);
 
         // Allocate all blocks
-        let blocks = alloc.allocate_blocks(10).unwrap();
+        let _blocks = alloc.allocate_blocks(10).unwrap();
         assert_eq!(alloc.free_blocks(), 0);
 
         // Try to allocate more
@@ -404,4 +489,61 @@ mod tests {
         // Fragmentation should increase (some transitions exist)
         assert!(score2 > score1);
     }
+
+    #[test]
+    fn test_summary_survives_serialization() {
+        let mut alloc = BitmapAllocator::new(200);
+        alloc.allocate_blocks(130).unwrap();
+        alloc.free_allocated_blocks(&[5, 70]).unwrap();
+
+        // Saved allocators carry no summary
+        let bytes = bincode::serialize(&alloc).unwrap();
+        let mut loaded: BitmapAllocator = bincode::deserialize(&bytes).unwrap();
+        assert!(loaded.summary.is_empty());
+        assert_eq!(loaded.allocate_blocks(3).unwrap(), vec![5, 70, 130]);
+
+        // Growing adds words the summary has to cover
+        loaded.allocate_blocks(69).unwrap();
+        assert!(loaded.allocate_blocks(1).is_err());
+        loaded.extend_capacity(300).unwrap();
+        assert_eq!(loaded.allocate_blocks(1).unwrap(), vec![200]);
+    }
+
+    #[test]
+    fn test_churn_on_nearly_full_bitmap() {
+        // 400k blocks, 90% used, then 100k free/allocate pairs at
+        // scattered positions
+        let total = 400_000;
+        let mut alloc = BitmapAllocator::new(total);
+        alloc.allocate_blocks(total * 9 / 10).unwrap();
+
+        let mut block = 0u64;
+        let before = alloc.words_visited;
+        for _ in 0..100_000 {
+            block = (block + 7919) % (total as u64 * 9 / 10);
+            alloc.free_allocated_blocks(&[block]).unwrap();
+            assert_eq!(alloc.allocate_blocks(1).unwrap(), vec![block]);
+        }
+        // Each allocation goes straight to the one word with a free bit
+        // rather than scanning the full words before it
+        assert_eq!(alloc.words_visited - before, 100_000);
+        assert_eq!(alloc.free_blocks(), total / 10);
+    }
+
+    #[test]
+    fn test_range_free_and_double_free() {
+        let mut alloc = BitmapAllocator::new(200);
+        alloc.mark_allocated_with_count(&[70, 150]).unwrap();
+
+        assert!(alloc.is_range_free(0, 70));
+        assert!(!alloc.is_range_free(0, 71));
+        assert!(alloc.is_range_free(71, 150));
+        assert!(!alloc.is_range_free(100, 151));
+        assert!(alloc.is_range_free(151, 200));
+        assert!(!alloc.is_range_free(151, 201));
+
+        alloc.free_allocated_blocks(&[70, 70, 71]).unwrap();
+        assert_eq!(alloc.free_blocks(), 199);
+        assert_eq!(alloc.free_blocks(), alloc.count_free());
+    }
 }
@@ -230,11 +230,27 @@ impl ExtentAllocator {
         self.free_extents.len()
     }
 
+    /// Length in blocks of the largest free extent (0 when full)
+    pub fn largest_free_extent(&self) -> u64 {
+        self.free_extents.values().map(|e| e.length).max().unwrap_or(0)
+    }
+
+    /// Free extents in block order
+    pub fn free_extents(&self) -> impl Iterator<Item = &Extent> + '_ {
+        self.free_extents.values()
+    }
+
     /// Mark specific blocks as allocated (without changing free_blocks counter)
     ///
     /// Used by HybridAllocator to keep allocators in sync
     pub fn mark_allocated(&mut self, blocks: &[u64]) -> Result<()> {
-        // Remove blocks from free extents
+        self.take_blocks(blocks);
+        Ok(())
+    }
+
+    /// Remove blocks from the free extents, returning how many were free
+    fn take_blocks(&mut self, blocks: &[u64]) -> usize {
+        let mut taken = 0;
         for &block_id in blocks {
             // Find and remove from free extents
             let mut keys_to_remove = Vec::new();
@@ -260,6 +276,9 @@ impl ExtentAllocator {
             }
 
             // Apply changes
+            if !keys_to_remove.is_empty() {
+                taken += 1;
+            }
             for key in keys_to_remove {
                 self.free_extents.remove(&key);
             }
@@ -267,6 +286,17 @@ impl ExtentAllocator {
                 self.free_extents.insert(extent.start, extent);
             }
         }
+        taken
+    }
+
+    /// Mark specific blocks as allocated AND take them off the free_blocks
+    /// counter
+    ///
+    /// Used by HybridAllocator for allocations made through the bitmap, so
+    /// both allocators count every block in use.
+    pub fn mark_allocated_with_count(&mut self, blocks: &[u64]) -> Result<()> {
+        let newly_allocated = self.take_blocks(blocks);
+        self.free_blocks = self.free_blocks.saturating_sub(newly_allocated);
         Ok(())
     }
 
@@ -442,7 +472,7 @@ impl ExtentAllocator {
 
 impl BlockAllocator for ExtentAllocator {
     fn allocate(&mut self, size: u64) -> Result<Vec<u64>> {
-        let num_blocks = ((size + PAGE_SIZE as u64 - 1) / PAGE_SIZE as u64) as usize;
+        let num_blocks = size.div_ceil(PAGE_SIZE as u64) as usize;
         self.allocate_contiguous(num_blocks)
     }
 
@@ -639,9 +669,9 @@ mod tests {
         let score1 = alloc.fragmentation_score();
 
         // Allocate and free to create fragmentation
-        let b1 = alloc.allocate_contiguous(100).unwrap();
+        let _b1 = alloc.allocate_contiguous(100).unwrap();
         let b2 = alloc.allocate_contiguous(100).unwrap();
-        let b3 = alloc.allocate_contiguous(100).unwrap();
+        let _b3 = alloc.allocate_contiguous(100).unwrap();
 
         alloc.free_extent(&b2).unwrap(); // Create a gap
 
@@ -3,19 +3,26 @@
 //! Strategy:
 //! - Small files (<256KB): Use bitmap allocator (fast, low overhead)
 //! - Large files (≥256KB): Use extent allocator (contiguous, better performance)
+//!
+//! Both allocators track the whole block range: whichever one finds the
+//! blocks, the other marks them too, and both counters move with every
+//! allocation and free. [`HybridAllocator::verify_consistency`] checks that
+//! they agree with each other and with the hybrid's own counter; debug
+//! builds run it after every change.
 
 use crate::allocator::bitmap::BitmapAllocator;
 use crate::allocator::extent::ExtentAllocator;
 use crate::allocator::BlockAllocator;
-use crate::error::Result;
+use crate::error::{CartridgeError, Result};
 use crate::header::PAGE_SIZE;
 use serde::{Deserialize, Serialize};
 
 /// Size threshold for switching allocators (256KB)
 const SMALL_FILE_THRESHOLD: u64 = 256 * 1024; // 256KB
 
-/// Number of blocks that represent the small file threshold
-const SMALL_FILE_BLOCKS: usize = (SMALL_FILE_THRESHOLD / PAGE_SIZE as u64) as usize; // 64 blocks
+fn default_block_size() -> u64 {
+    PAGE_SIZE as u64
+}
 
 /// Hybrid allocator that dispatches to bitmap or extent allocator
 ///
@@ -35,6 +42,10 @@ pub struct HybridAllocator {
 
     /// Number of free blocks (canonical counter shared across both allocators)
     free_blocks: usize,
+
+    /// Bytes per block; not serialized, the cartridge sets it from its header
+    #[serde(skip, default = "default_block_size")]
+    block_size: u64,
 }
 
 impl HybridAllocator {
@@ -45,9 +56,16 @@ impl HybridAllocator {
             extent: ExtentAllocator::new(total_blocks),
             total_blocks,
             free_blocks: total_blocks,
+            block_size: default_block_size(),
         }
     }
 
+    /// Set the bytes per block that `allocate` sizes are divided by
+    /// (default: 4096)
+    pub fn set_block_size(&mut self, bytes: usize) {
+        self.block_size = bytes as u64;
+    }
+
     /// Determine if a size should use bitmap allocator
     fn should_use_bitmap(size: u64) -> bool {
         size < SMALL_FILE_THRESHOLD
@@ -85,6 +103,34 @@ impl HybridAllocator {
         }
     }
 
+    /// Usage and fragmentation of each strategy
+    ///
+    /// Both strategies track the whole block range, so their counts agree
+    /// unless the allocator has drifted. They are counted from the
+    /// bitmap and the free-extent list rather than the cached counters.
+    pub fn stats(&self) -> AllocatorStats {
+        let bitmap_free = self.bitmap.count_free();
+        let extent_free = self.extent.count_free();
+        let bitmap_used = self.total_blocks - bitmap_free;
+        AllocatorStats {
+            total_blocks: self.total_blocks,
+            free_blocks: self.free_blocks,
+            bitmap_free,
+            bitmap_used,
+            extent_free,
+            extent_used: self.total_blocks - extent_free,
+            small_file_utilization: match self.total_blocks {
+                0 => 0.0,
+                total => bitmap_used as f64 / total as f64,
+            },
+            free_extent_count: self.extent.extent_count(),
+            largest_free_extent: self.extent.largest_free_extent(),
+            bitmap_fragmentation: self.bitmap.fragmentation_score(),
+            extent_fragmentation: self.extent.fragmentation_score(),
+            combined_fragmentation: self.combined_fragmentation_score(),
+        }
+    }
+
     /// Extend allocator capacity to new block count
     ///
     /// Used by auto-growth to expand the allocator's tracking capacity.
@@ -103,6 +149,7 @@ impl HybridAllocator {
         self.total_blocks = new_total_blocks;
         self.free_blocks += added_blocks;
 
+        self.debug_verify();
         Ok(())
     }
 
@@ -121,12 +168,6 @@ impl HybridAllocator {
         self.free_blocks = self.bitmap.free_blocks();
     }
 
-    /// Mark specific pages as allocated in both sub-allocators, adjusting all
-    /// internal counters.
-    ///
-    /// Used after loading the allocator from disk to account for the allocator's
-    /// own overflow pages (which were allocated after the allocator was serialized
-    /// and therefore aren't reflected in the deserialized state).
     /// Check if a specific block is allocated.
     ///
     /// Delegates to the bitmap allocator (authoritative source).
@@ -144,27 +185,116 @@ impl HybridAllocator {
     /// Shrink allocator capacity to fewer blocks.
     ///
     /// All blocks at or above `new_total_blocks` must already be free.
-    /// Delegates to both sub-allocators and updates counters.
+    /// Otherwise fails with [`CartridgeError::BlocksInUse`] listing them, so
+    /// they can be relocated first, and changes nothing.
     pub fn shrink_capacity(&mut self, new_total_blocks: usize) -> Result<()> {
         if new_total_blocks >= self.total_blocks {
             return Ok(());
         }
 
-        self.bitmap.shrink_capacity(new_total_blocks)?;
+        let in_use = self.blocks_in_use_from(new_total_blocks as u64);
+        if !in_use.is_empty() {
+            return Err(CartridgeError::BlocksInUse {
+                new_total_blocks: new_total_blocks as u64,
+                blocks: in_use,
+            });
+        }
+
+        // The extent allocator checks its own map again; going first means a
+        // disagreement leaves both untouched
         self.extent.shrink_capacity(new_total_blocks)?;
+        self.bitmap.shrink_capacity(new_total_blocks)?;
 
+        self.free_blocks -= self.total_blocks - new_total_blocks;
         self.total_blocks = new_total_blocks;
-        self.free_blocks = self.bitmap.free_blocks();
 
+        self.debug_verify();
         Ok(())
     }
 
+    /// Allocated blocks at or past `boundary`, in order
+    pub fn blocks_in_use_from(&self, boundary: u64) -> Vec<u64> {
+        (boundary..self.total_blocks as u64)
+            .filter(|&block| self.bitmap.is_allocated(block))
+            .collect()
+    }
+
+    /// Mark specific pages as allocated in both sub-allocators, adjusting all
+    /// internal counters.
+    ///
+    /// Used after loading the allocator from disk to account for the allocator's
+    /// own overflow pages (which were allocated after the allocator was serialized
+    /// and therefore aren't reflected in the deserialized state). Pages that are
+    /// already allocated are left as they are.
     pub fn mark_pages_allocated(&mut self, pages: &[u64]) -> Result<()> {
+        let free_before = self.bitmap.free_blocks();
         self.bitmap.mark_allocated_with_count(pages)?;
-        self.extent.mark_allocated(pages)?;
-        self.free_blocks = self.free_blocks.saturating_sub(pages.len());
+        self.extent.mark_allocated_with_count(pages)?;
+        let newly_allocated = free_before - self.bitmap.free_blocks();
+        self.free_blocks = self.free_blocks.saturating_sub(newly_allocated);
+        Ok(())
+    }
+
+    /// Check that the bitmap, the extent list and every counter agree
+    ///
+    /// Both allocators must mark exactly the same blocks free, and the
+    /// hybrid's counter and each allocator's own counter must equal that
+    /// number. Fails with [`CartridgeError::Corruption`] describing the
+    /// first disagreement. Debug builds run this after every allocation,
+    /// free and capacity change and panic on failure.
+    pub fn verify_consistency(&self) -> Result<()> {
+        let mismatch = |what: &str, found: usize, expected: usize| {
+            Err(CartridgeError::Corruption(format!(
+                "Allocator inconsistent: {} is {}, expected {}",
+                what, found, expected
+            )))
+        };
+
+        for (what, total) in [
+            ("bitmap total", self.bitmap.total_blocks()),
+            ("extent total", self.extent.total_blocks()),
+        ] {
+            if total != self.total_blocks {
+                return mismatch(what, total, self.total_blocks);
+            }
+        }
+
+        let free = self.bitmap.count_free();
+        for (what, count) in [
+            ("free counter", self.free_blocks),
+            ("bitmap free counter", self.bitmap.free_blocks()),
+            ("extent free counter", self.extent.free_blocks()),
+            ("blocks in free extents", self.extent.count_free()),
+        ] {
+            if count != free {
+                return mismatch(what, count, free);
+            }
+        }
+
+        // With the counts equal, the free extents cover the bitmap's free
+        // blocks exactly if they're disjoint and free in the bitmap too
+        let mut previous_end = 0;
+        for extent in self.extent.free_extents() {
+            let end = extent.start + extent.length;
+            if extent.start < previous_end || !self.bitmap.is_range_free(extent.start, end) {
+                return Err(CartridgeError::Corruption(format!(
+                    "Allocator inconsistent: free extent {}..{} overlaps blocks in use",
+                    extent.start, end
+                )));
+            }
+            previous_end = end;
+        }
         Ok(())
     }
+
+    /// Panic if [`verify_consistency`](Self::verify_consistency) fails, in
+    /// debug builds only
+    fn debug_verify(&self) {
+        #[cfg(debug_assertions)]
+        if let Err(e) = self.verify_consistency() {
+            panic!("{}", e);
+        }
+    }
 }
 
 /// Statistics about hybrid allocator usage
@@ -178,53 +308,95 @@ pub struct AllocationStats {
     pub combined_fragmentation: f64,
 }
 
+/// Per-strategy usage from [`HybridAllocator::stats`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AllocatorStats {
+    pub total_blocks: usize,
+    /// Canonical free count, shared by both strategies
+    pub free_blocks: usize,
+    /// Free blocks according to the bitmap (small-file) allocator
+    pub bitmap_free: usize,
+    pub bitmap_used: usize,
+    /// Free blocks according to the extent (large-file) allocator
+    pub extent_free: usize,
+    pub extent_used: usize,
+    /// Fraction of blocks the bitmap marks used, 0.0–1.0
+    pub small_file_utilization: f64,
+    /// Number of separate free runs
+    pub free_extent_count: usize,
+    /// Longest free run, in blocks: the biggest large-file allocation that
+    /// stays contiguous; bigger ones are scattered over the free blocks
+    pub largest_free_extent: u64,
+    pub bitmap_fragmentation: f64,
+    pub extent_fragmentation: f64,
+    pub combined_fragmentation: f64,
+}
+
 impl BlockAllocator for HybridAllocator {
     fn allocate(&mut self, size: u64) -> Result<Vec<u64>> {
-        let num_blocks = ((size + PAGE_SIZE as u64 - 1) / PAGE_SIZE as u64) as usize;
+        let num_blocks = size.div_ceil(self.block_size) as usize;
 
         // Check canonical free_blocks counter
         if num_blocks > self.free_blocks {
+            crate::telemetry::allocation_failed();
             return Err(crate::error::CartridgeError::OutOfSpace);
         }
 
         let result = if Self::should_use_bitmap(size) {
             // Small file: use bitmap allocator
-            let blocks = self.bitmap.allocate(size)?;
+            let blocks = self.bitmap.allocate_blocks(num_blocks)?;
             // Mark blocks as allocated in extent allocator too (to prevent collision)
-            self.extent.mark_allocated(&blocks)?;
+            self.extent.mark_allocated_with_count(&blocks)?;
             blocks
-        } else {
+        } else if let Ok(blocks) = self.extent.allocate_contiguous(num_blocks) {
             // Large file: use extent allocator
-            let blocks = self.extent.allocate(size)?;
             // Mark blocks as allocated in bitmap allocator too (to prevent collision)
-            self.bitmap.mark_allocated(&blocks)?;
+            self.bitmap.mark_allocated_with_count(&blocks)?;
+            blocks
+        } else {
+            // No free run is long enough: scatter the file rather than fail
+            // while the free space is there
+            let blocks = self.bitmap.allocate_blocks(num_blocks)?;
+            self.extent.mark_allocated_with_count(&blocks)?;
             blocks
         };
 
         // Update canonical free_blocks counter
         self.free_blocks -= result.len();
 
+        self.debug_verify();
         Ok(result)
     }
 
     fn free(&mut self, blocks: &[u64]) -> Result<()> {
-        // Determine which allocator to use based on number of blocks
-        let num_blocks = blocks.len();
-
-        // Free from BOTH allocators to keep them in sync
-        if num_blocks < SMALL_FILE_BLOCKS {
-            // Small allocation: free via bitmap (primary)
-            self.bitmap.free(blocks)?;
-            self.extent.mark_free(blocks)?;
-        } else {
-            // Large allocation: free via extent (primary)
-            self.extent.free(blocks)?;
-            self.bitmap.mark_free(blocks)?;
+        if let Some(&block_id) = blocks.iter().find(|&&b| b >= self.total_blocks as u64) {
+            return Err(CartridgeError::InvalidBlockId(block_id));
         }
 
+        // Only blocks still in use are freed and counted. Blocks can be
+        // freed in any grouping (truncation frees the tail of a large
+        // file), so both allocators free every block
+        let mut live: Vec<u64> = blocks
+            .iter()
+            .copied()
+            .filter(|&b| self.bitmap.is_allocated(b))
+            .collect();
+        live.sort_unstable();
+        live.dedup();
+        if live.len() < blocks.len() {
+            tracing::warn!(
+                "Double-free detected for {} blocks",
+                blocks.len() - live.len()
+            );
+        }
+
+        self.bitmap.free_allocated_blocks(&live)?;
+        self.extent.free_extent(&live)?;
+
         // Update canonical free_blocks counter
-        self.free_blocks += num_blocks;
+        self.free_blocks += live.len();
 
+        self.debug_verify();
         Ok(())
     }
 
@@ -288,11 +460,15 @@ mod tests {
 
         // Just below threshold (256KB - 1 byte)
         let small = alloc.allocate(SMALL_FILE_THRESHOLD - 1).unwrap();
-        assert_eq!(alloc.bitmap.free_blocks(), 10000 - small.len());
+        assert_eq!(small.len(), 64);
 
         // At threshold (256KB exactly)
         let large = alloc.allocate(SMALL_FILE_THRESHOLD).unwrap();
-        assert_eq!(alloc.extent.free_blocks(), 10000 - large.len());
+        assert_eq!(large.len(), 64);
+
+        // Both strategies count every allocation
+        assert_eq!(alloc.bitmap.free_blocks(), 10000 - 128);
+        assert_eq!(alloc.extent.free_blocks(), 10000 - 128);
     }
 
     #[test]
@@ -302,19 +478,19 @@ mod tests {
         // Mix of small and large allocations
         let small1 = alloc.allocate(10 * 1024).unwrap(); // 3 blocks via bitmap
         let large1 = alloc.allocate(1024 * 1024).unwrap(); // 256 blocks via extent
-        let small2 = alloc.allocate(20 * 1024).unwrap(); // 5 blocks via bitmap
-        let large2 = alloc.allocate(512 * 1024).unwrap(); // 128 blocks via extent
+        let _small2 = alloc.allocate(20 * 1024).unwrap(); // 5 blocks via bitmap
+        let _large2 = alloc.allocate(512 * 1024).unwrap(); // 128 blocks via extent
 
         // Verify allocation counts
-        assert_eq!(alloc.bitmap.free_blocks(), 10000 - 3 - 5);
-        assert_eq!(alloc.extent.free_blocks(), 10000 - 256 - 128);
+        assert_eq!(alloc.bitmap.free_blocks(), 10000 - 3 - 256 - 5 - 128);
+        assert_eq!(alloc.extent.free_blocks(), 10000 - 3 - 256 - 5 - 128);
 
         // Free some allocations
         alloc.free(&small1).unwrap();
         alloc.free(&large1).unwrap();
 
-        assert_eq!(alloc.bitmap.free_blocks(), 10000 - 5);
-        assert_eq!(alloc.extent.free_blocks(), 10000 - 128);
+        assert_eq!(alloc.bitmap.free_blocks(), 10000 - 5 - 128);
+        assert_eq!(alloc.extent.free_blocks(), 10000 - 5 - 128);
     }
 
     #[test]
@@ -349,8 +525,8 @@ mod tests {
         alloc.allocate(1024 * 1024).unwrap(); // 256 blocks via extent
 
         let stats = alloc.allocation_stats();
-        assert_eq!(stats.bitmap_free, 10000 - 3);
-        assert_eq!(stats.extent_free, 10000 - 256);
+        assert_eq!(stats.bitmap_free, 10000 - 3 - 256);
+        assert_eq!(stats.extent_free, 10000 - 3 - 256);
     }
 
     #[test]
@@ -361,9 +537,9 @@ mod tests {
         let frag1 = alloc.fragmentation_score();
 
         // Create some fragmentation
-        let b1 = alloc.allocate(10 * 1024).unwrap();
+        let _b1 = alloc.allocate(10 * 1024).unwrap();
         let b2 = alloc.allocate(10 * 1024).unwrap();
-        let b3 = alloc.allocate(10 * 1024).unwrap();
+        let _b3 = alloc.allocate(10 * 1024).unwrap();
 
         alloc.free(&b2).unwrap(); // Create a gap
 
@@ -386,11 +562,59 @@ mod tests {
         assert_eq!(alloc.extent.free_blocks(), 0);
     }
 
+    #[test]
+    fn test_large_allocation_scatters_when_fragmented() {
+        let mut alloc = HybridAllocator::new(200);
+        let small: Vec<_> = (0..20).map(|_| alloc.allocate(8 * 1024).unwrap()).collect();
+        for blocks in small.iter().step_by(2) {
+            alloc.free(blocks).unwrap();
+        }
+        // 20 two-block holes, 160 contiguous blocks after them
+        let run = alloc.allocate(160 * PAGE_SIZE as u64).unwrap();
+        assert_eq!(run, (40..200).collect::<Vec<_>>());
+
+        // No run of 64 is left, but 20 blocks are still free
+        let scattered = alloc.allocate(20 * PAGE_SIZE as u64).unwrap();
+        assert_eq!(scattered.len(), 20);
+        assert_eq!(alloc.free_blocks(), 0);
+        assert!(scattered.iter().all(|&b| b < 40));
+
+        alloc.free(&scattered).unwrap();
+        assert_eq!(alloc.free_blocks(), 20);
+    }
+
+    #[test]
+    fn test_repeated_scattered_allocations_keep_counters_balanced() {
+        let mut alloc = HybridAllocator::new(200);
+        let small: Vec<_> = (0..100).map(|_| alloc.allocate(8 * 1024).unwrap()).collect();
+        for blocks in small.iter().step_by(2) {
+            alloc.free(blocks).unwrap();
+        }
+        let bitmap_free = alloc.bitmap.free_blocks();
+        let extent_free = alloc.extent.free_blocks();
+
+        // Too large for the bitmap, too fragmented for an extent, so each
+        // one is scattered; the counters must come back every time
+        for _ in 0..10 {
+            let blocks = alloc.allocate(70 * PAGE_SIZE as u64).unwrap();
+            assert_eq!(blocks.len(), 70);
+            alloc.free(&blocks).unwrap();
+            assert_eq!(alloc.free_blocks(), 100);
+            assert_eq!(alloc.bitmap.free_blocks(), bitmap_free);
+            assert_eq!(alloc.extent.free_blocks(), extent_free);
+        }
+        alloc.allocate(100 * PAGE_SIZE as u64).unwrap();
+        assert_eq!(alloc.free_blocks(), 0);
+    }
+
     #[test]
     fn test_threshold_constant() {
         // Verify threshold is correct
         assert_eq!(SMALL_FILE_THRESHOLD, 256 * 1024);
-        assert_eq!(SMALL_FILE_BLOCKS, 64); // 256KB / 4KB
+        let mut alloc = HybridAllocator::new(100);
+        assert_eq!(alloc.allocate(SMALL_FILE_THRESHOLD).unwrap().len(), 64); // 256KB / 4KB
+        alloc.set_block_size(64 * 1024);
+        assert_eq!(alloc.allocate(SMALL_FILE_THRESHOLD).unwrap().len(), 4); // 256KB / 64KB
     }
 
     #[test]
@@ -418,4 +642,80 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_stats_report_free_runs() {
+        let mut alloc = HybridAllocator::new(100);
+        let small = alloc.allocate(10 * PAGE_SIZE as u64).unwrap(); // blocks 0..10
+        alloc.allocate(10 * PAGE_SIZE as u64).unwrap(); // blocks 10..20
+        alloc.free(&small[2..5]).unwrap();
+
+        let stats = alloc.stats();
+        assert_eq!(stats.free_blocks, 83);
+        assert_eq!(stats.bitmap_used, 17);
+        assert_eq!(stats.extent_used, 17);
+        assert_eq!(stats.free_extent_count, 2);
+        assert_eq!(stats.largest_free_extent, 80);
+        assert!((stats.small_file_utilization - 0.17).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_shrink_refuses_blocks_in_use_and_changes_nothing() {
+        let mut alloc = HybridAllocator::new(200);
+        let blocks = alloc.allocate(150 * PAGE_SIZE as u64).unwrap();
+        alloc.free(&blocks[..140]).unwrap();
+
+        let before = alloc.stats();
+        match alloc.shrink_capacity(100) {
+            Err(CartridgeError::BlocksInUse {
+                new_total_blocks,
+                blocks: in_use,
+            }) => {
+                assert_eq!(new_total_blocks, 100);
+                assert_eq!(in_use, (140..150).collect::<Vec<_>>());
+            }
+            other => panic!("expected BlocksInUse, got {:?}", other),
+        }
+        assert_eq!(alloc.stats(), before);
+
+        alloc.free(&blocks[140..]).unwrap();
+        alloc.shrink_capacity(100).unwrap();
+        assert_eq!(alloc.total_blocks(), 100);
+        assert_eq!(alloc.free_blocks(), 100);
+        alloc.verify_consistency().unwrap();
+    }
+
+    #[test]
+    fn test_partial_and_double_frees_keep_counters_in_sync() {
+        let mut alloc = HybridAllocator::new(300);
+        let large = alloc.allocate(200 * PAGE_SIZE as u64).unwrap();
+
+        // A truncated file frees the tail of a large allocation only
+        alloc.free(&large[190..]).unwrap();
+        assert_eq!(alloc.free_blocks(), 110);
+        alloc.free(&large[190..]).unwrap();
+        assert_eq!(alloc.free_blocks(), 110);
+
+        alloc.mark_pages_allocated(&[0, 250, 250]).unwrap();
+        assert_eq!(alloc.free_blocks(), 109);
+        alloc.verify_consistency().unwrap();
+    }
+
+    #[test]
+    fn test_verify_consistency_reports_drift() {
+        let mut alloc = HybridAllocator::new(100);
+        alloc.allocate(8 * 1024).unwrap();
+        alloc.verify_consistency().unwrap();
+
+        alloc.bitmap.mark_allocated(&[50]).unwrap();
+        assert!(matches!(
+            alloc.verify_consistency(),
+            Err(CartridgeError::Corruption(_))
+        ));
+        alloc.recalibrate();
+        assert!(alloc.verify_consistency().is_err());
+        alloc.extent.mark_allocated_with_count(&[50]).unwrap();
+        alloc.recalibrate();
+        alloc.verify_consistency().unwrap();
+    }
 }
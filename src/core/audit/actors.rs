@@ -0,0 +1,37 @@
+//! Named actors for audit entries
+//!
+//! Entries carry a numeric actor id, set with
+//! [`Cartridge::set_actor`](crate::cartridge::Cartridge::set_actor). Names
+//! registered for ids are stored in [`ACTORS_PATH`] so the trail stays
+//! readable wherever the cartridge goes.
+
+use crate::cartridge::Cartridge;
+use crate::error::Result;
+use std::collections::BTreeMap;
+
+/// Internal file mapping actor ids to names
+pub const ACTORS_PATH: &str = "/.cartridge/actors.json";
+
+/// Actor id used until [`Cartridge::set_actor`] is called
+pub const DEFAULT_ACTOR_ID: u32 = 1;
+
+impl Cartridge {
+    /// Register `name` for `actor_id`, replacing any previous name
+    pub fn register_actor(&mut self, actor_id: u32, name: &str) -> Result<()> {
+        let mut actors = self.actors()?;
+        if actors.get(&actor_id).map(String::as_str) == Some(name) {
+            return Ok(());
+        }
+        actors.insert(actor_id, name.to_string());
+        self.system()
+            .write(ACTORS_PATH, &serde_json::to_vec_pretty(&actors)?)
+    }
+
+    /// Registered actor names by id
+    pub fn actors(&self) -> Result<BTreeMap<u32, String>> {
+        if !self.exists(ACTORS_PATH)? {
+            return Ok(BTreeMap::new());
+        }
+        Ok(serde_json::from_slice(&self.read_file(ACTORS_PATH)?)?)
+    }
+}
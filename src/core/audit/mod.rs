@@ -2,18 +2,35 @@
 //!
 //! Provides append-only audit trail for all file operations with:
 //! - Lock-free ring buffer for high-performance logging
-//! - Background flush thread for persistence
+//! - Background flush thread for persistence (not built with `no-fs`;
+//!   drain entries with [`AuditLogger::read_batch`] instead)
+//! - Drop-oldest or blocking overflow, with a count of dropped entries
 //! - Microsecond-precision timestamps
 //! - Actor and session tracking
+//! - Filtered queries and CSV/JSONL export of the persisted trail
+//! - Named actors registered in the cartridge
 
+mod actors;
+mod query;
 mod ring_buffer;
 
+pub use actors::{ACTORS_PATH, DEFAULT_ACTOR_ID};
+pub(crate) use query::is_trail_file;
+pub use query::{
+    decode_entries, path_id, write_csv, write_jsonl, AuditFilter, AuditRecord, AUDIT_LOG_PATH,
+    AUDIT_PATHS_PATH,
+};
 pub use ring_buffer::RingBuffer;
 
-use parking_lot::Mutex;
+use parking_lot::{Condvar, Mutex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
+#[cfg(not(feature = "no-fs"))]
+use parking_lot::MutexGuard;
+#[cfg(not(feature = "no-fs"))]
 use std::thread::{self, JoinHandle};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant};
 
 /// Single audit log entry (32 bytes, cache-line friendly)
 #[repr(C)]
@@ -44,10 +61,7 @@ impl AuditEntry {
         resource_id: u64,
         session_id: u32,
     ) -> Self {
-        let timestamp_us = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_micros() as u64;
+        let timestamp_us = crate::clock::unix_micros();
 
         AuditEntry {
             timestamp_us,
@@ -59,11 +73,46 @@ impl AuditEntry {
             _padding: 0,
         }
     }
+
+    /// Size of an entry in bytes, in memory and on disk
+    pub const SIZE: usize = 32;
+
+    /// Little-endian on-disk form of this entry
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        bytes[0..8].copy_from_slice(&self.timestamp_us.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.actor_id.to_le_bytes());
+        bytes[12..14].copy_from_slice(&(self.operation as u16).to_le_bytes());
+        bytes[14..16].copy_from_slice(&self.resource_table.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.resource_id.to_le_bytes());
+        bytes[24..28].copy_from_slice(&self.session_id.to_le_bytes());
+        bytes
+    }
+
+    /// Parse an entry written by [`to_bytes`](Self::to_bytes)
+    ///
+    /// Returns `None` if `bytes` is too short or names an unknown operation.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let bytes: &[u8; Self::SIZE] = bytes.get(..Self::SIZE)?.try_into().ok()?;
+        let u16_at = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let u64_at = |i: usize| u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+        Some(AuditEntry {
+            timestamp_us: u64_at(0),
+            actor_id: u32_at(8),
+            operation: Operation::from_u16(u16_at(12))?,
+            resource_table: u16_at(14),
+            resource_id: u64_at(16),
+            session_id: u32_at(24),
+            _padding: 0,
+        })
+    }
 }
 
 /// Operation types for audit logging
 #[repr(u16)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Operation {
     /// File or resource creation
     Create = 0,
@@ -79,16 +128,82 @@ pub enum Operation {
     Flush = 5,
 }
 
+impl Operation {
+    /// The operation stored as `value`, if there is one
+    pub fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            0 => Some(Operation::Create),
+            1 => Some(Operation::Read),
+            2 => Some(Operation::Update),
+            3 => Some(Operation::Delete),
+            4 => Some(Operation::Query),
+            5 => Some(Operation::Flush),
+            _ => None,
+        }
+    }
+
+    /// Lowercase name, as used in exports
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Create => "create",
+            Operation::Read => "read",
+            Operation::Update => "update",
+            Operation::Delete => "delete",
+            Operation::Query => "query",
+            Operation::Flush => "flush",
+        }
+    }
+}
+
+/// What [`AuditLogger::log`] does when the ring buffer is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Overwrite the oldest unread entry, counting it in
+    /// [`AuditStats::dropped`]
+    #[default]
+    DropOldest,
+    /// Wait up to the given time for the flush thread to make room, then
+    /// fall back to dropping the oldest entry
+    Block(Duration),
+}
+
+/// Counters reported by [`AuditLogger::stats`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuditStats {
+    /// Entries logged
+    pub written: usize,
+    /// Entries taken out of the ring buffer
+    pub read: usize,
+    /// Entries overwritten before they were read
+    pub dropped: usize,
+}
+
+/// Receives batches of entries drained from the ring buffer
+type FlushCallback = Box<dyn Fn(&[AuditEntry]) + Send>;
+
+/// Most entries handed to the flush callback at once
+const FLUSH_BATCH: usize = 1000;
+
 /// High-performance audit logger with background flushing
 pub struct AuditLogger {
     /// Lock-free ring buffer for audit entries
     ring_buffer: Arc<RingBuffer<AuditEntry>>,
     /// Background flush thread handle
+    #[cfg(not(feature = "no-fs"))]
     flush_thread: Option<JoinHandle<()>>,
+    /// Callback passed to [`start`](Self::start), shared with the flush thread
+    flush_callback: Arc<Mutex<Option<FlushCallback>>>,
     /// How often to flush entries to disk
+    #[cfg_attr(feature = "no-fs", allow(dead_code))]
     flush_interval: Duration,
-    /// Whether the logger is running
-    running: Arc<Mutex<bool>>,
+    /// Whether the logger is running, and a signal to wake the flush thread
+    running: Arc<(Mutex<bool>, Condvar)>,
+    /// What to do when the ring buffer is full
+    overflow_policy: OverflowPolicy,
+    /// Whether [`log_path_op`](Self::log_path_op) remembers paths
+    record_paths: bool,
+    /// Paths logged since the last [`take_paths`](Self::take_paths)
+    paths: Mutex<HashMap<u64, String>>,
 }
 
 impl AuditLogger {
@@ -100,38 +215,58 @@ impl AuditLogger {
     pub fn new(capacity: usize, flush_interval: Duration) -> Self {
         AuditLogger {
             ring_buffer: Arc::new(RingBuffer::new(capacity)),
+            #[cfg(not(feature = "no-fs"))]
             flush_thread: None,
+            flush_callback: Arc::new(Mutex::new(None)),
             flush_interval,
-            running: Arc::new(Mutex::new(false)),
+            running: Arc::new((Mutex::new(false), Condvar::new())),
+            overflow_policy: OverflowPolicy::default(),
+            record_paths: false,
+            paths: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Remember the path behind each resource id logged by
+    /// [`log_path_op`](Self::log_path_op), so queries can show it
+    pub fn with_path_recording(mut self) -> Self {
+        self.record_paths = true;
+        self
+    }
+
+    /// Set what [`log`](Self::log) does when the ring buffer is full
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
+    /// Whether this logger remembers paths
+    pub fn records_paths(&self) -> bool {
+        self.record_paths
+    }
+
     /// Start the background flush thread
     ///
     /// # Arguments
     /// * `flush_callback` - Function called with batches of audit entries
+    #[cfg(not(feature = "no-fs"))]
     pub fn start<F>(&mut self, flush_callback: F)
     where
         F: Fn(&[AuditEntry]) + Send + 'static,
     {
-        *self.running.lock() = true;
+        *self.flush_callback.lock() = Some(Box::new(flush_callback));
+        *self.running.0.lock() = true;
 
         let ring_buffer = Arc::clone(&self.ring_buffer);
+        let callback = Arc::clone(&self.flush_callback);
         let flush_interval = self.flush_interval;
         let running = Arc::clone(&self.running);
 
         let flush_thread = thread::spawn(move || {
-            while *running.lock() {
-                thread::sleep(flush_interval);
-
-                // Read batch from ring buffer
-                let entries = ring_buffer.read_batch(1000);
-                if entries.is_empty() {
-                    continue;
-                }
-
-                // Call flush callback
-                flush_callback(&entries);
+            let (lock, wake) = &*running;
+            let mut is_running = lock.lock();
+            while *is_running {
+                wake.wait_for(&mut is_running, flush_interval);
+                MutexGuard::unlocked(&mut is_running, || drain(&ring_buffer, &callback));
             }
         });
 
@@ -139,28 +274,86 @@ impl AuditLogger {
     }
 
     /// Stop the background flush thread
+    ///
+    /// Entries still in the ring buffer are passed to the flush callback
+    /// before this returns.
     pub fn stop(&mut self) {
-        *self.running.lock() = false;
+        let (lock, wake) = &*self.running;
+        *lock.lock() = false;
+        wake.notify_all();
 
+        #[cfg(not(feature = "no-fs"))]
         if let Some(thread) = self.flush_thread.take() {
             let _ = thread.join();
         }
+        self.flush_now();
+    }
+
+    /// Pass every pending entry to the flush callback now, returning how many
+    /// there were
+    ///
+    /// Without a callback (the logger was never [`start`](Self::start)ed)
+    /// entries are left for [`read_batch`](Self::read_batch) and this
+    /// returns 0.
+    pub fn flush_now(&self) -> usize {
+        drain(&self.ring_buffer, &self.flush_callback)
     }
 
-    /// Log an audit entry (non-blocking)
+    /// Log an audit entry
+    ///
+    /// Doesn't block unless the ring buffer is full and the overflow policy
+    /// is [`OverflowPolicy::Block`].
     pub fn log(&self, entry: AuditEntry) {
+        if let OverflowPolicy::Block(timeout) = self.overflow_policy {
+            let deadline = Instant::now() + timeout;
+            while self.ring_buffer.unread_count() >= self.ring_buffer.capacity()
+                && Instant::now() < deadline
+            {
+                std::thread::sleep(Duration::from_micros(100));
+            }
+        }
         self.ring_buffer.write(entry);
     }
 
+    /// Take up to `max` pending entries, oldest first
+    ///
+    /// For callers that drain the log themselves instead of running the
+    /// flush thread.
+    pub fn read_batch(&self, max: usize) -> Vec<AuditEntry> {
+        self.ring_buffer.read_batch(max)
+    }
+
     /// Convenience method to log a file operation
     pub fn log_file_op(&self, actor_id: u32, operation: Operation, file_id: u64, session_id: u32) {
         let entry = AuditEntry::new(actor_id, operation, 0, file_id, session_id);
         self.log(entry);
     }
 
+    /// Log a file operation on `path`, using [`path_id`] as the file id
+    pub fn log_path_op(&self, actor_id: u32, operation: Operation, path: &str, session_id: u32) {
+        let file_id = path_id(path);
+        if self.record_paths {
+            self.paths
+                .lock()
+                .entry(file_id)
+                .or_insert_with(|| path.to_string());
+        }
+        self.log_file_op(actor_id, operation, file_id, session_id);
+    }
+
+    /// Take the paths recorded since the last call
+    pub fn take_paths(&self) -> HashMap<u64, String> {
+        std::mem::take(&mut *self.paths.lock())
+    }
+
     /// Get current ring buffer statistics
-    pub fn stats(&self) -> (usize, usize) {
-        self.ring_buffer.stats()
+    pub fn stats(&self) -> AuditStats {
+        let (written, read) = self.ring_buffer.stats();
+        AuditStats {
+            written,
+            read,
+            dropped: self.ring_buffer.dropped(),
+        }
     }
 }
 
@@ -170,6 +363,26 @@ impl Drop for AuditLogger {
     }
 }
 
+/// Pass everything in `ring_buffer` to `callback`, if there is one
+///
+/// Holding the callback lock throughout keeps batches in order between the
+/// flush thread and [`AuditLogger::flush_now`].
+fn drain(ring_buffer: &RingBuffer<AuditEntry>, callback: &Mutex<Option<FlushCallback>>) -> usize {
+    let callback = callback.lock();
+    let Some(callback) = callback.as_ref() else {
+        return 0;
+    };
+    let mut drained = 0;
+    loop {
+        let entries = ring_buffer.read_batch(FLUSH_BATCH);
+        if entries.is_empty() {
+            return drained;
+        }
+        drained += entries.len();
+        callback(&entries);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -200,9 +413,10 @@ mod tests {
         let entry = AuditEntry::new(1, Operation::Create, 0, 42, 100);
         logger.log(entry);
 
-        let (write_pos, read_pos) = logger.stats();
-        assert_eq!(write_pos, 1);
-        assert_eq!(read_pos, 0);
+        let stats = logger.stats();
+        assert_eq!(stats.written, 1);
+        assert_eq!(stats.read, 0);
+        assert_eq!(stats.dropped, 0);
     }
 
     #[test]
@@ -230,13 +444,123 @@ mod tests {
         assert!(flush_count.load(Ordering::SeqCst) > 0);
     }
 
+    #[test]
+    fn test_block_loses_nothing() {
+        let mut logger = AuditLogger::new(64, Duration::from_millis(1))
+            .with_overflow_policy(OverflowPolicy::Block(Duration::from_secs(10)));
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&received);
+        logger.start(move |entries| {
+            thread::sleep(Duration::from_millis(1));
+            sink.lock().extend(entries.iter().map(|e| e.resource_id));
+        });
+
+        for i in 0..10_000 {
+            logger.log_file_op(1, Operation::Create, i, 0);
+        }
+        logger.stop();
+
+        assert_eq!(*received.lock(), (0..10_000).collect::<Vec<_>>());
+        assert_eq!(logger.stats().dropped, 0);
+    }
+
+    #[test]
+    fn test_drop_oldest_counts_dropped() {
+        // The thread won't wake on its own, so the buffer overflows
+        let mut logger = AuditLogger::new(64, Duration::from_secs(3600));
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&received);
+        logger.start(move |entries| {
+            thread::sleep(Duration::from_millis(1));
+            sink.lock().extend(entries.iter().map(|e| e.resource_id));
+        });
+
+        for i in 0..10_000 {
+            logger.log_file_op(1, Operation::Create, i, 0);
+        }
+        assert_eq!(logger.stats().dropped, 10_000 - 64);
+        logger.stop();
+
+        assert_eq!(*received.lock(), (10_000 - 64..10_000).collect::<Vec<_>>());
+        let stats = logger.stats();
+        assert_eq!(stats.dropped, 10_000 - 64);
+        assert_eq!(stats.read, stats.written);
+    }
+
+    #[test]
+    fn test_flush_now_and_drop_deliver_pending() {
+        let mut logger = AuditLogger::new(1024, Duration::from_secs(3600));
+        assert_eq!(logger.flush_now(), 0);
+
+        let count = Arc::new(AtomicUsize::new(0));
+        let count_clone = Arc::clone(&count);
+        logger.start(move |entries| {
+            count_clone.fetch_add(entries.len(), Ordering::SeqCst);
+        });
+
+        for i in 0..10 {
+            logger.log_file_op(1, Operation::Read, i, 0);
+        }
+        assert_eq!(logger.flush_now(), 10);
+        assert_eq!(count.load(Ordering::SeqCst), 10);
+
+        // Logged right before the logger goes away
+        for i in 0..5 {
+            logger.log_file_op(1, Operation::Update, i, 0);
+        }
+        drop(logger);
+        assert_eq!(count.load(Ordering::SeqCst), 15);
+    }
+
+    #[test]
+    fn test_read_batch_without_flush_thread() {
+        let logger = AuditLogger::new(16, Duration::from_secs(60));
+        for file_id in 0..5 {
+            logger.log_file_op(1, Operation::Read, file_id, 7);
+        }
+
+        let first = logger.read_batch(3);
+        assert_eq!(first.iter().map(|e| e.resource_id).collect::<Vec<_>>(), [0, 1, 2]);
+        assert_eq!(logger.read_batch(10).len(), 2);
+        assert!(logger.read_batch(10).is_empty());
+    }
+
+    #[test]
+    fn test_entry_bytes_roundtrip() {
+        let entry = AuditEntry::new(3, Operation::Delete, 1, u64::MAX - 7, 42);
+        let bytes = entry.to_bytes();
+        let parsed = AuditEntry::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.to_bytes(), bytes);
+        assert_eq!(parsed.operation, Operation::Delete);
+        assert_eq!(parsed.resource_id, u64::MAX - 7);
+
+        let mut unknown = bytes;
+        unknown[12] = 99;
+        assert!(AuditEntry::from_bytes(&unknown).is_none());
+        assert!(AuditEntry::from_bytes(&bytes[..31]).is_none());
+    }
+
+    #[test]
+    fn test_path_recording() {
+        let logger = AuditLogger::new(16, Duration::from_secs(60));
+        logger.log_path_op(1, Operation::Read, "a.txt", 0);
+        assert!(logger.take_paths().is_empty());
+
+        let logger = AuditLogger::new(16, Duration::from_secs(60)).with_path_recording();
+        logger.log_path_op(1, Operation::Read, "a.txt", 0);
+        logger.log_path_op(1, Operation::Update, "a.txt", 0);
+        let paths = logger.take_paths();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[&path_id("a.txt")], "a.txt");
+        assert_eq!(logger.read_batch(10).len(), 2);
+    }
+
     #[test]
     fn test_log_file_op_convenience() {
         let logger = AuditLogger::new(1024, Duration::from_millis(100));
 
         logger.log_file_op(1, Operation::Read, 42, 100);
 
-        let (write_pos, _) = logger.stats();
-        assert_eq!(write_pos, 1);
+        assert_eq!(logger.stats().written, 1);
     }
 }
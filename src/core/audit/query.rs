@@ -0,0 +1,292 @@
+//! Querying and exporting the persisted audit trail
+//!
+//! [`Cartridge::flush`](crate::cartridge::Cartridge::flush) appends pending
+//! entries to [`AUDIT_LOG_PATH`] as raw 32-byte records. Queries decode them
+//! into [`AuditRecord`]s, which resolve the operation and timestamp and, for
+//! loggers that record paths, the path each resource id was hashed from.
+
+use super::{AuditEntry, Operation, ACTORS_PATH};
+use crate::cartridge::Cartridge;
+use crate::error::Result;
+use crate::validation::normalize_path;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Internal file holding the persisted audit entries
+pub const AUDIT_LOG_PATH: &str = "/.cartridge/audit.log";
+
+/// Internal file mapping resource ids back to paths
+pub const AUDIT_PATHS_PATH: &str = "/.cartridge/audit-paths.json";
+
+/// Resource id logged for operations on `path`
+///
+/// Stable across builds, so ids in a persisted trail can be matched against
+/// paths later. Operations log the normalized path (see
+/// [`normalize_path`]), so pass it in that form.
+pub fn path_id(path: &str) -> u64 {
+    xxhash_rust::xxh3::xxh3_64(path.as_bytes())
+}
+
+/// Which audit entries a query returns
+///
+/// Every field that is set must match; the default matches everything.
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    /// Only entries at or after this time
+    pub since: Option<SystemTime>,
+    /// Only entries before this time
+    pub until: Option<SystemTime>,
+    /// Only these operations (empty for any)
+    pub operations: Vec<Operation>,
+    /// Only entries by this actor
+    pub actor_id: Option<u32>,
+    /// Only entries on this resource (see [`path_id`])
+    pub resource_id: Option<u64>,
+}
+
+impl AuditFilter {
+    /// Filter for entries on `path`, in any spelling
+    pub fn for_path(path: &str) -> Self {
+        let path = normalize_path(path).unwrap_or_else(|_| path.to_string());
+        AuditFilter {
+            resource_id: Some(path_id(&path)),
+            ..Default::default()
+        }
+    }
+
+    /// Whether `entry` passes this filter
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        let time = UNIX_EPOCH + Duration::from_micros(entry.timestamp_us);
+        self.since.is_none_or(|since| time >= since)
+            && self.until.is_none_or(|until| time < until)
+            && (self.operations.is_empty() || self.operations.contains(&entry.operation))
+            && self.actor_id.is_none_or(|actor| entry.actor_id == actor)
+            && self.resource_id.is_none_or(|id| entry.resource_id == id)
+    }
+}
+
+/// An audit entry in readable, serializable form
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// When the operation happened, RFC 3339 in UTC
+    pub timestamp: String,
+    /// Microseconds since the UNIX epoch
+    pub timestamp_us: u64,
+    /// What was done
+    pub operation: Operation,
+    /// Who did it
+    pub actor_id: u32,
+    /// Name registered for the actor, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    /// Session it belonged to
+    pub session_id: u32,
+    /// Which resource table the id refers to
+    pub resource_table: u16,
+    /// Resource the operation was on
+    pub resource_id: u64,
+    /// Path of the resource, if the logger recorded it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+impl AuditRecord {
+    /// Resolve `entry`, looking its path up in `paths`
+    pub fn new(entry: &AuditEntry, paths: &HashMap<u64, String>) -> Self {
+        let time = DateTime::<Utc>::from(UNIX_EPOCH + Duration::from_micros(entry.timestamp_us));
+        AuditRecord {
+            timestamp: time.to_rfc3339_opts(SecondsFormat::Micros, true),
+            timestamp_us: entry.timestamp_us,
+            operation: entry.operation,
+            actor_id: entry.actor_id,
+            actor: None,
+            session_id: entry.session_id,
+            resource_table: entry.resource_table,
+            resource_id: entry.resource_id,
+            path: paths.get(&entry.resource_id).cloned(),
+        }
+    }
+}
+
+/// Decode entries persisted with [`AuditEntry::to_bytes`]
+///
+/// A trailing partial record or one with an unknown operation is skipped.
+pub fn decode_entries(data: &[u8]) -> Vec<AuditEntry> {
+    data.chunks_exact(AuditEntry::SIZE)
+        .filter_map(AuditEntry::from_bytes)
+        .collect()
+}
+
+/// Write `records` as CSV with a header row
+pub fn write_csv<W: Write>(records: &[AuditRecord], mut writer: W) -> io::Result<()> {
+    writeln!(
+        writer,
+        "timestamp,operation,actor_id,actor,session_id,resource_table,resource_id,path"
+    )?;
+    for r in records {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{}",
+            r.timestamp,
+            r.operation.as_str(),
+            r.actor_id,
+            csv_field(r.actor.as_deref().unwrap_or("")),
+            r.session_id,
+            r.resource_table,
+            r.resource_id,
+            csv_field(r.path.as_deref().unwrap_or(""))
+        )?;
+    }
+    writer.flush()
+}
+
+/// Write `records` as JSON lines, one object per record
+pub fn write_jsonl<W: Write>(records: &[AuditRecord], mut writer: W) -> io::Result<()> {
+    for record in records {
+        serde_json::to_writer(&mut writer, record)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+}
+
+impl Cartridge {
+    /// Persisted audit entries that pass `filter`, oldest first
+    ///
+    /// Entries reach the trail when the cartridge is flushed; ones still
+    /// pending in the logger aren't included.
+    pub fn query_audit(&self, filter: &AuditFilter) -> Result<Vec<AuditRecord>> {
+        if !self.exists(AUDIT_LOG_PATH)? {
+            return Ok(Vec::new());
+        }
+        let entries = decode_entries(&self.read_file(AUDIT_LOG_PATH)?);
+        let paths = self.audit_paths()?;
+        let actors = self.actors()?;
+        Ok(entries
+            .iter()
+            .filter(|entry| filter.matches(entry))
+            .map(|entry| AuditRecord {
+                actor: actors.get(&entry.actor_id).cloned(),
+                ..AuditRecord::new(entry, &paths)
+            })
+            .collect())
+    }
+
+    /// Write the entries [`query_audit`](Self::query_audit) returns as CSV,
+    /// returning how many there were
+    pub fn export_audit_csv<W: Write>(&self, filter: &AuditFilter, writer: W) -> Result<usize> {
+        let records = self.query_audit(filter)?;
+        write_csv(&records, writer)?;
+        Ok(records.len())
+    }
+
+    /// Write the entries [`query_audit`](Self::query_audit) returns as JSON
+    /// lines, returning how many there were
+    pub fn export_audit_jsonl<W: Write>(&self, filter: &AuditFilter, writer: W) -> Result<usize> {
+        let records = self.query_audit(filter)?;
+        write_jsonl(&records, writer)?;
+        Ok(records.len())
+    }
+
+    /// Append the logger's pending entries and recorded paths to the trail
+    pub(crate) fn persist_audit(&mut self) -> Result<()> {
+        let Some(logger) = self.audit_logger().cloned() else {
+            return Ok(());
+        };
+        let mut data = Vec::new();
+        loop {
+            let batch = logger.read_batch(1000);
+            if batch.is_empty() {
+                break;
+            }
+            data.extend(batch.iter().flat_map(AuditEntry::to_bytes));
+        }
+        if !data.is_empty() {
+            self.system().append(AUDIT_LOG_PATH, &data)?;
+        }
+
+        let new_paths = logger.take_paths();
+        if new_paths.is_empty() {
+            return Ok(());
+        }
+        let mut paths = self.audit_paths()?;
+        let known = paths.len();
+        paths.extend(new_paths);
+        if paths.len() > known {
+            let sorted: BTreeMap<_, _> = paths.into_iter().collect();
+            self.system()
+                .write(AUDIT_PATHS_PATH, &serde_json::to_vec(&sorted)?)?;
+        }
+        Ok(())
+    }
+
+    /// The persisted resource id to path map
+    fn audit_paths(&self) -> Result<HashMap<u64, String>> {
+        if !self.exists(AUDIT_PATHS_PATH)? {
+            return Ok(HashMap::new());
+        }
+        Ok(serde_json::from_slice(&self.read_file(AUDIT_PATHS_PATH)?)?)
+    }
+}
+
+/// Whether `path` is one of the files the audit trail is kept in
+pub(crate) fn is_trail_file(path: &str) -> bool {
+    path == AUDIT_LOG_PATH || path == AUDIT_PATHS_PATH || path == ACTORS_PATH
+}
+
+/// Quote a CSV field if it needs it
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_matches() {
+        let entry = AuditEntry::new(2, Operation::Update, 0, path_id("/a.txt"), 9);
+        let time = UNIX_EPOCH + Duration::from_micros(entry.timestamp_us);
+
+        assert!(AuditFilter::default().matches(&entry));
+        assert!(AuditFilter::for_path("a.txt").matches(&entry));
+        assert!(AuditFilter::for_path("/a.txt").matches(&entry));
+        assert!(!AuditFilter::for_path("b.txt").matches(&entry));
+
+        let filter = AuditFilter {
+            since: Some(time),
+            until: Some(time + Duration::from_micros(1)),
+            operations: vec![Operation::Create, Operation::Update],
+            actor_id: Some(2),
+            ..Default::default()
+        };
+        assert!(filter.matches(&entry));
+        assert!(!AuditFilter { until: Some(time), ..filter.clone() }.matches(&entry));
+        assert!(!AuditFilter { actor_id: Some(1), ..filter }.matches(&entry));
+    }
+
+    #[test]
+    fn test_csv_and_jsonl() {
+        let entry = AuditEntry::new(1, Operation::Create, 0, path_id("a,b.txt"), 0);
+        let paths = HashMap::from([(entry.resource_id, "a,b.txt".to_string())]);
+        let records = [AuditRecord::new(&entry, &paths)];
+
+        let mut csv = Vec::new();
+        write_csv(&records, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let row = csv.lines().nth(1).unwrap();
+        assert!(row.contains(",create,1,,0,0,"));
+        assert!(row.ends_with("\"a,b.txt\""));
+
+        let mut jsonl = Vec::new();
+        write_jsonl(&records, &mut jsonl).unwrap();
+        let parsed: AuditRecord = serde_json::from_slice(jsonl.trim_ascii_end()).unwrap();
+        assert_eq!(parsed, records[0]);
+    }
+}
@@ -6,6 +6,9 @@
 use crossbeam::utils::CachePadded;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+/// How long a reader waits for a claimed slot to be written before skipping it
+const MAX_SPINS: usize = 1000;
+
 /// Lock-free single-producer, single-consumer ring buffer
 ///
 /// Optimized for audit logging where:
@@ -25,6 +28,8 @@ pub struct RingBuffer<T: Copy> {
     write_pos: CachePadded<AtomicUsize>,
     /// Read position (monotonically increasing)
     read_pos: CachePadded<AtomicUsize>,
+    /// Entries overwritten before they were read
+    dropped: CachePadded<AtomicUsize>,
 }
 
 impl<T: Copy> RingBuffer<T> {
@@ -51,6 +56,7 @@ impl<T: Copy> RingBuffer<T> {
             capacity,
             write_pos: CachePadded::new(AtomicUsize::new(0)),
             read_pos: CachePadded::new(AtomicUsize::new(0)),
+            dropped: CachePadded::new(AtomicUsize::new(0)),
         }
     }
 
@@ -58,7 +64,8 @@ impl<T: Copy> RingBuffer<T> {
     ///
     /// If the buffer is full, this will overwrite the oldest entry.
     /// This is acceptable for audit logging where we prioritize availability
-    /// over guaranteed delivery of every single log entry.
+    /// over guaranteed delivery of every single log entry. Overwritten
+    /// entries are counted by [`dropped`](Self::dropped).
     pub fn write(&self, value: T) {
         // Get write position and increment atomically
         let pos = self.write_pos.fetch_add(1, Ordering::SeqCst);
@@ -88,6 +95,14 @@ impl<T: Copy> RingBuffer<T> {
         let current_write = self.write_pos.load(Ordering::SeqCst);
         let mut current_read = self.read_pos.load(Ordering::SeqCst);
 
+        // Entries more than a lap behind the writer were overwritten
+        let oldest = current_write.saturating_sub(self.capacity);
+        if current_read < oldest {
+            self.dropped.fetch_add(oldest - current_read, Ordering::SeqCst);
+            crate::telemetry::audit_dropped(oldest - current_read);
+            current_read = oldest;
+        }
+
         // Read available entries up to max_count
         while batch.len() < max_count && current_read < current_write {
             let index = current_read & (self.capacity - 1);
@@ -96,6 +111,15 @@ impl<T: Copy> RingBuffer<T> {
             // Writer may have written new value, but that's OK
             unsafe {
                 let ptr = self.buffer.as_ptr() as *mut Option<T>;
+
+                // A writer that has claimed this position may not have
+                // stored its value yet
+                let mut spins = 0;
+                while std::ptr::read_volatile(ptr.add(index)).is_none() && spins < MAX_SPINS {
+                    std::hint::spin_loop();
+                    spins += 1;
+                }
+
                 if let Some(value) = *ptr.add(index) {
                     batch.push(value);
                     // Clear the slot (optional, helps debugging)
@@ -125,7 +149,14 @@ impl<T: Copy> RingBuffer<T> {
     /// Get number of unread entries currently in the buffer
     pub fn unread_count(&self) -> usize {
         let (write_pos, read_pos) = self.stats();
-        write_pos.saturating_sub(read_pos)
+        write_pos.saturating_sub(read_pos).min(self.capacity)
+    }
+
+    /// Get number of entries overwritten before they were read
+    pub fn dropped(&self) -> usize {
+        let (write_pos, read_pos) = self.stats();
+        let overwritten = write_pos.saturating_sub(read_pos).saturating_sub(self.capacity);
+        self.dropped.load(Ordering::SeqCst) + overwritten
     }
 
     /// Check if the buffer is empty
@@ -259,6 +290,24 @@ mod tests {
         assert!(batch.len() >= 8);
     }
 
+    #[test]
+    fn test_dropped_count() {
+        let rb = RingBuffer::new(8);
+        for i in 0..20 {
+            rb.write(i);
+        }
+        assert_eq!(rb.unread_count(), 8);
+        assert_eq!(rb.dropped(), 12);
+
+        // Only the newest lap survives, and the count holds after reading
+        assert_eq!(rb.read_batch(100), (12..20).collect::<Vec<_>>());
+        assert_eq!(rb.dropped(), 12);
+
+        rb.write(20);
+        assert_eq!(rb.read_batch(100), [20]);
+        assert_eq!(rb.dropped(), 12);
+    }
+
     #[test]
     fn test_stats() {
         let rb = RingBuffer::new(1024);
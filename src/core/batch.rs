@@ -0,0 +1,353 @@
+//! Atomic multi-file write batches
+//!
+//! A [`WriteBatch`] stages writes, deletes and renames in memory; the
+//! cartridge itself is untouched until [`WriteBatch::commit`]. Commit applies
+//! every operation and then flushes once. The flush goes through the
+//! journal, so after a crash either the whole batch is on disk or none of it
+//! is.
+
+use crate::cartridge::Cartridge;
+use crate::error::{CartridgeError, Result};
+use crate::iam::Action;
+use crate::validation::normalize_path;
+use std::collections::HashMap;
+
+/// A staged operation, replayed in order at commit
+#[derive(Debug)]
+enum BatchOp {
+    Write { path: String, data: Vec<u8> },
+    Delete { path: String },
+    Rename { from: String, to: String },
+}
+
+/// What a path looks like from inside the batch
+#[derive(Debug, Clone)]
+enum Staged {
+    /// Content of the `BatchOp::Write` at this index
+    Written(usize),
+    /// Deleted or renamed away
+    Deleted,
+    /// Renamed here from this path in the underlying cartridge
+    Moved(String),
+}
+
+/// Resolved view of one path
+enum View<'b> {
+    Data(&'b [u8]),
+    Missing,
+    Base(&'b str),
+}
+
+/// A group of writes, deletes and renames applied all at once
+///
+/// Created by [`Cartridge::begin_batch`]. Reads through the batch see its
+/// staged changes layered over the cartridge (read-your-writes); the
+/// cartridge itself sees nothing until [`commit`](WriteBatch::commit).
+/// Dropping a batch without committing discards it, same as
+/// [`abort`](WriteBatch::abort).
+///
+/// IAM checks and existence checks run when an operation is staged, so most
+/// mistakes surface immediately rather than at commit.
+pub struct WriteBatch<'a> {
+    cartridge: &'a mut Cartridge,
+    ops: Vec<BatchOp>,
+    staged: HashMap<String, Staged>,
+}
+
+impl Cartridge {
+    /// Start a batch of changes that commit atomically
+    pub fn begin_batch(&mut self) -> WriteBatch<'_> {
+        WriteBatch {
+            cartridge: self,
+            ops: Vec::new(),
+            staged: HashMap::new(),
+        }
+    }
+}
+
+impl<'a> WriteBatch<'a> {
+    /// Stage a write, creating the file or replacing its content
+    pub fn write(&mut self, path: &str, data: &[u8]) -> Result<()> {
+        let path = &normalize_path(path)?;
+        self.cartridge.ensure_writable()?;
+        self.cartridge.check_protected(path)?;
+        let action = if self.exists(path)? {
+            Action::Write
+        } else {
+            Action::Create
+        };
+        let context = self.cartridge.write_context(path, data);
+        self.cartridge.check_access_with(&action, path, &context)?;
+
+        self.staged
+            .insert(path.to_string(), Staged::Written(self.ops.len()));
+        self.ops.push(BatchOp::Write {
+            path: path.to_string(),
+            data: data.to_vec(),
+        });
+        Ok(())
+    }
+
+    /// Stage a delete
+    pub fn delete(&mut self, path: &str) -> Result<()> {
+        let path = &normalize_path(path)?;
+        self.cartridge.ensure_writable()?;
+        self.cartridge.check_protected(path)?;
+        if !self.exists(path)? {
+            return Err(CartridgeError::NotFound {
+                path: path.to_string(),
+            });
+        }
+        self.cartridge.check_access(&Action::Delete, path)?;
+
+        self.staged.insert(path.to_string(), Staged::Deleted);
+        self.ops.push(BatchOp::Delete {
+            path: path.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Stage a rename; fails if `from` is missing or `to` already exists
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<()> {
+        let (from, to) = (&normalize_path(from)?, &normalize_path(to)?);
+        self.cartridge.ensure_writable()?;
+        self.cartridge.check_protected(from)?;
+        self.cartridge.check_protected(to)?;
+        let moved = match self.staged.get(from) {
+            Some(Staged::Deleted) => None,
+            Some(staged) => Some(staged.clone()),
+            None => self
+                .cartridge
+                .exists(from)?
+                .then(|| Staged::Moved(from.to_string())),
+        };
+        let Some(moved) = moved else {
+            return Err(CartridgeError::NotFound {
+                path: from.to_string(),
+            });
+        };
+        if self.exists(to)? {
+            return Err(CartridgeError::AlreadyExists {
+                path: to.to_string(),
+            });
+        }
+        self.cartridge.check_access(&Action::Delete, from)?;
+        self.cartridge.check_access(&Action::Create, to)?;
+
+        self.staged.insert(to.to_string(), moved);
+        self.staged.insert(from.to_string(), Staged::Deleted);
+        self.ops.push(BatchOp::Rename {
+            from: from.to_string(),
+            to: to.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Read a file as it would be after commit
+    pub fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let path = &normalize_path(path)?;
+        match self.view(path) {
+            View::Data(data) => Ok(data.to_vec()),
+            View::Missing => Err(CartridgeError::NotFound {
+                path: path.to_string(),
+            }),
+            View::Base(base) => self.cartridge.read_file(base),
+        }
+    }
+
+    /// Check whether a path would exist after commit
+    pub fn exists(&self, path: &str) -> Result<bool> {
+        let path = &normalize_path(path)?;
+        match self.view(path) {
+            View::Data(_) => Ok(true),
+            View::Missing => Ok(false),
+            View::Base(base) => self.cartridge.exists(base),
+        }
+    }
+
+    /// Number of staged operations
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// True if nothing has been staged
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Apply every staged operation and flush once
+    ///
+    /// If an operation fails part-way through, the cartridge's in-memory
+    /// state is rolled back and nothing is flushed.
+    pub fn commit(self) -> Result<()> {
+        let WriteBatch { cartridge, ops, .. } = self;
+        if ops.is_empty() {
+            return Ok(());
+        }
+
+        // Grow once up front; growth isn't undone by a rollback
+        let page_size = cartridge.page_size() as u64;
+        let reserve: u64 = ops
+            .iter()
+            .map(|op| match op {
+                BatchOp::Write { data, .. } => {
+                    (data.len() as u64).div_ceil(page_size) * page_size
+                }
+                _ => 0,
+            })
+            .sum();
+        cartridge.reserve_bytes(reserve)?;
+
+        // Watchers only hear about the batch once it has applied
+        let checkpoint = cartridge.checkpoint();
+        cartridge.watchers().hold();
+        let applied = ops.iter().try_for_each(|op| match op {
+            BatchOp::Write { path, data } => {
+                if cartridge.exists(path)? {
+                    cartridge.write_file(path, data)
+                } else {
+                    cartridge.create_file(path, data)
+                }
+            }
+            BatchOp::Delete { path } => cartridge.delete_file(path),
+            BatchOp::Rename { from, to } => cartridge.rename(from, to),
+        });
+        cartridge.watchers().release(applied.is_ok());
+        if let Err(e) = applied {
+            cartridge.rollback(checkpoint);
+            return Err(e);
+        }
+
+        cartridge.flush()
+    }
+
+    /// Discard every staged operation
+    pub fn abort(self) {}
+
+    fn view<'b>(&'b self, path: &'b str) -> View<'b> {
+        match self.staged.get(path) {
+            Some(Staged::Written(index)) => match &self.ops[*index] {
+                BatchOp::Write { data, .. } => View::Data(data),
+                _ => unreachable!("staged write points at a non-write op"),
+            },
+            Some(Staged::Deleted) => View::Missing,
+            Some(Staged::Moved(base)) => View::Base(base),
+            None => View::Base(path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::PAGE_SIZE;
+
+    fn file_backed(dir: &tempfile::TempDir) -> (std::path::PathBuf, Cartridge) {
+        let path = dir.path().join("batch.cart");
+        let mut cart = Cartridge::create_at(&path, "batch-test", "Batch Test").unwrap();
+        cart.create_file("index.json", b"{\"v\":1}").unwrap();
+        cart.create_file("data/old.bin", &[1u8; 100]).unwrap();
+        cart.create_file("data/keep.bin", &[2u8; 100]).unwrap();
+        cart.flush().unwrap();
+        (path, cart)
+    }
+
+    #[test]
+    fn test_commit_applies_all_ops_after_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, mut cart) = file_backed(&dir);
+
+        let mut batch = cart.begin_batch();
+        batch.write("index.json", b"{\"v\":2}").unwrap();
+        batch
+            .write("data/new.bin", &vec![3u8; 2 * PAGE_SIZE])
+            .unwrap();
+        batch.rename("data/keep.bin", "data/kept.bin").unwrap();
+        batch.delete("data/old.bin").unwrap();
+        assert_eq!(batch.len(), 4);
+        batch.commit().unwrap();
+        drop(cart);
+
+        let cart = Cartridge::open(&path).unwrap();
+        assert_eq!(cart.read_file("index.json").unwrap(), b"{\"v\":2}");
+        assert_eq!(
+            cart.read_file("data/new.bin").unwrap(),
+            vec![3u8; 2 * PAGE_SIZE]
+        );
+        assert_eq!(cart.read_file("data/kept.bin").unwrap(), vec![2u8; 100]);
+        assert!(!cart.exists("data/keep.bin").unwrap());
+        assert!(!cart.exists("data/old.bin").unwrap());
+        assert!(cart.check().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_abort_leaves_no_trace() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, mut cart) = file_backed(&dir);
+        let free_before = cart.header().free_blocks;
+
+        let mut batch = cart.begin_batch();
+        batch.write("index.json", b"{\"v\":2}").unwrap();
+        batch.write("data/new.bin", &[3u8; 10]).unwrap();
+        batch.delete("data/old.bin").unwrap();
+        batch.abort();
+
+        assert_eq!(cart.header().free_blocks, free_before);
+        assert_eq!(cart.read_file("index.json").unwrap(), b"{\"v\":1}");
+        assert!(!cart.exists("data/new.bin").unwrap());
+        drop(cart);
+
+        let cart = Cartridge::open(&path).unwrap();
+        assert_eq!(cart.read_file("index.json").unwrap(), b"{\"v\":1}");
+        assert!(cart.exists("data/old.bin").unwrap());
+        assert!(!cart.exists("data/new.bin").unwrap());
+    }
+
+    #[test]
+    fn test_reads_see_staged_changes() {
+        let mut cart = Cartridge::new(100);
+        cart.create_file("a.txt", b"a").unwrap();
+        cart.create_file("b.txt", b"b").unwrap();
+
+        let mut batch = cart.begin_batch();
+        batch.write("a.txt", b"a2").unwrap();
+        batch.rename("a.txt", "c.txt").unwrap();
+        batch.rename("b.txt", "a.txt").unwrap();
+        batch.write("b.txt", b"b2").unwrap();
+
+        assert_eq!(batch.read("c.txt").unwrap(), b"a2");
+        assert_eq!(batch.read("a.txt").unwrap(), b"b");
+        assert_eq!(batch.read("b.txt").unwrap(), b"b2");
+
+        // Staging-time checks use the batch's view
+        assert!(batch.rename("c.txt", "a.txt").is_err());
+        batch.delete("c.txt").unwrap();
+        assert!(batch.read("c.txt").is_err());
+        assert!(batch.delete("c.txt").is_err());
+        batch.commit().unwrap();
+
+        assert_eq!(cart.read_file("a.txt").unwrap(), b"b");
+        assert_eq!(cart.read_file("b.txt").unwrap(), b"b2");
+        assert!(!cart.exists("c.txt").unwrap());
+    }
+
+    #[test]
+    fn test_failed_commit_rolls_back() {
+        let mut cart = Cartridge::new(100);
+        cart.create_file("a.txt", b"a").unwrap();
+        cart.create_dir("dir").unwrap();
+        let free_before = cart.header().free_blocks;
+
+        // Writing over a directory only fails when applied
+        let mut batch = cart.begin_batch();
+        batch.write("new.txt", b"new").unwrap();
+        batch.delete("a.txt").unwrap();
+        batch.write("dir", b"not a file").unwrap();
+        assert!(batch.commit().is_err());
+
+        assert_eq!(cart.header().free_blocks, free_before);
+        assert!(!cart.exists("new.txt").unwrap());
+        assert_eq!(cart.read_file("a.txt").unwrap(), b"a");
+        assert!(cart.check().unwrap().is_consistent());
+    }
+}
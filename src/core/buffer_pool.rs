@@ -9,11 +9,32 @@
 //!
 //! The algorithm self-tunes parameter `p` based on workload to balance
 //! recency vs. frequency, achieving near-optimal cache hit rates.
-
-#![allow(dead_code)] // Module reserved for future use
-
+//!
+//! ## Borrowing cached pages
+//!
+//! [`Cartridge::pin_page`] and [`Cartridge::read_file_zero_copy`] hand out
+//! the page cache's own buffers instead of copies. A [`PageGuard`] holds a
+//! reference to its buffer, not a lock, so it can't block other readers, and
+//! it borrows the cartridge, so no write through the same handle can start
+//! while one is alive. A write that lands on a page someone still holds
+//! (possible only through another handle sharing the cache) gets a new
+//! buffer, leaving the guard with the content it pinned. The cache never
+//! drops a buffer a guard holds: dropping the cache entry only drops the
+//! cache's own reference.
+
+#![allow(dead_code)] // ARC pool reserved for future use
+
+use crate::audit::Operation;
+use crate::cartridge::Cartridge;
+use crate::catalog::HOLE_BLOCK;
+use crate::error::{CartridgeError, Result};
+use crate::header::PAGE_SIZE;
+use crate::iam::Action;
 use crate::page::Page;
+use crate::telemetry;
+use crate::validation::normalize_path;
 use std::collections::{HashMap, VecDeque};
+use std::ops::Deref;
 use std::sync::Arc;
 
 /// LRU list for cache entries
@@ -279,7 +300,7 @@ impl BufferPool {
 
     /// Replace a page according to ARC policy
     fn replace(&mut self, _page_id: u64) {
-        if self.t1.len() > 0
+        if !self.t1.is_empty()
             && ((self.t1.len() > self.p) || (self.b2.contains(_page_id) && self.t1.len() == self.p))
         {
             // Evict from T1
@@ -298,10 +319,9 @@ impl BufferPool {
 
     /// Maintain ghost list sizes (B1 + B2 ≤ 2c)
     fn maintain_ghost_lists(&mut self) {
-        let total_ghost = self.b1.len() + self.b2.len();
         let max_ghost = 2 * self.capacity;
 
-        while total_ghost > max_ghost {
+        while self.b1.len() + self.b2.len() > max_ghost {
             if self.b1.len() > self.b2.len() {
                 self.b1.pop_back();
             } else {
@@ -347,6 +367,135 @@ impl BufferPool {
     }
 }
 
+/// A content page borrowed from a cartridge's page cache
+///
+/// Derefs to the page's bytes, [`Cartridge::page_size`] of them. Returned by
+/// [`Cartridge::pin_page`]; see the [module docs](self) for how guards
+/// interact with writers.
+#[derive(Debug, Clone)]
+pub struct PageGuard<'a> {
+    page_id: u64,
+    data: Arc<Vec<u8>>,
+    cartridge: std::marker::PhantomData<&'a Cartridge>,
+}
+
+impl PageGuard<'_> {
+    /// The pinned page's id
+    pub fn page_id(&self) -> u64 {
+        self.page_id
+    }
+
+    /// The page as a fixed-size array, when the cartridge uses the default
+    /// [`PAGE_SIZE`]
+    pub fn as_array(&self) -> Option<&[u8; PAGE_SIZE]> {
+        self.data.as_slice().try_into().ok()
+    }
+}
+
+impl Deref for PageGuard<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl AsRef<[u8]> for PageGuard<'_> {
+    fn as_ref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Cartridge {
+    /// Borrow one allocated page from the page cache, loading it from disk
+    /// first if it isn't cached
+    ///
+    /// The guard keeps the page's buffer alive without copying it. Page ids
+    /// come from [`FileMetadata::data_blocks`](crate::catalog::FileMetadata::data_blocks).
+    /// Content is returned as stored, so pages of encrypted files hold
+    /// ciphertext.
+    ///
+    /// Fails with [`CartridgeError::InvalidBlockId`] for pages that aren't
+    /// allocated, which may still hold a deleted file's data, and with
+    /// [`CartridgeError::AccessDenied`] whenever an IAM policy is set, since
+    /// a page has no path to check it against.
+    pub fn pin_page(&self, page_id: u64) -> Result<PageGuard<'_>> {
+        if self.has_policy() {
+            return Err(CartridgeError::AccessDenied {
+                action: Action::Read,
+                path: format!("page {}", page_id),
+            });
+        }
+        if page_id == HOLE_BLOCK || !self.is_block_allocated(page_id) {
+            return Err(CartridgeError::InvalidBlockId(page_id));
+        }
+        Ok(PageGuard {
+            page_id,
+            data: self.cached_page("", page_id)?,
+            cartridge: std::marker::PhantomData,
+        })
+    }
+
+    /// Read a file page by page, handing `visitor` each page's bytes
+    /// straight from the cache
+    ///
+    /// Follows symlinks like [`read_file`](Self::read_file). Each slice is
+    /// the cache's own buffer, trimmed to the file's length on the last page,
+    /// so nothing is copied or concatenated. Holes come through as pages of
+    /// zeros and inline files as a single slice. Encrypted files have to be
+    /// decrypted first, so they are read whole and visited once.
+    pub fn read_file_zero_copy(&self, path: &str, mut visitor: impl FnMut(&[u8])) -> Result<()> {
+        let path = &normalize_path(path)?;
+        self.check_access(&Action::Read, path)?;
+        let target = match self.follow(path)? {
+            Some((target, _)) if target != *path => {
+                self.check_access(&Action::Read, &target)?;
+                target
+            }
+            _ => path.clone(),
+        };
+
+        let metadata = self
+            .catalog()
+            .get(&target)?
+            .ok_or_else(|| CartridgeError::NotFound {
+                path: target.clone(),
+            })?;
+        if !metadata.is_file() {
+            return Err(CartridgeError::NotAFile { path: target });
+        }
+        if metadata.is_encrypted() {
+            visitor(&self.read_file_nofollow(&target)?);
+            return Ok(());
+        }
+
+        self.audit_log(Operation::Read, &target);
+        if let Some(data) = &metadata.inline_data {
+            visitor(data);
+            telemetry::bytes_read(data.len());
+            return Ok(());
+        }
+
+        let page_size = self.page_size();
+        let zeros = vec![0u8; page_size];
+        let mut remaining = metadata.size as usize;
+        for &block_id in &metadata.blocks {
+            if remaining == 0 {
+                break;
+            }
+            let len = remaining.min(page_size);
+            if block_id == HOLE_BLOCK {
+                visitor(&zeros[..len]);
+            } else {
+                visitor(&self.cached_page(&target, block_id)?[..len]);
+            }
+            remaining -= len;
+        }
+        telemetry::bytes_read(metadata.size as usize - remaining);
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -523,4 +672,97 @@ mod tests {
         assert!(pool.get(5).is_some());
         assert!(pool.get(2).is_some());
     }
+
+    fn cartridge_with_file(content: &[u8]) -> Cartridge {
+        let mut cart = Cartridge::in_memory("pinned", "Pinned Pages").unwrap();
+        cart.create_file("/data.bin", content).unwrap();
+        cart
+    }
+
+    #[test]
+    fn test_pinned_page_is_the_cached_buffer() {
+        let content: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+        let cart = cartridge_with_file(&content);
+        let first = cart.metadata("/data.bin").unwrap().blocks[0];
+
+        let a = cart.pin_page(first).unwrap();
+        let b = cart.pin_page(first).unwrap();
+        assert_eq!(a.page_id(), first);
+        assert_eq!(a.as_ptr(), b.as_ptr());
+        assert_eq!(a.as_array().unwrap()[..PAGE_SIZE], content[..PAGE_SIZE]);
+    }
+
+    #[test]
+    fn test_zero_copy_read_visits_cached_buffers() {
+        let content: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+        let cart = cartridge_with_file(&content);
+        let blocks = cart.metadata("/data.bin").unwrap().blocks;
+        let guards: Vec<_> = blocks.iter().map(|&b| cart.pin_page(b).unwrap()).collect();
+
+        let mut visited = Vec::new();
+        let mut pointers = Vec::new();
+        cart.read_file_zero_copy("/data.bin", |page| {
+            pointers.push(page.as_ptr());
+            visited.extend_from_slice(page);
+        })
+        .unwrap();
+
+        assert_eq!(visited, content);
+        let pinned: Vec<_> = guards.iter().map(|guard| guard.as_ptr()).collect();
+        assert_eq!(pointers, pinned);
+    }
+
+    #[test]
+    fn test_zero_copy_read_of_holes_and_inline_files() {
+        let mut cart = Cartridge::in_memory("holes", "Holes").unwrap();
+        cart.create_file("/sparse.bin", b"start").unwrap();
+        cart.write_at("/sparse.bin", 3 * PAGE_SIZE as u64, b"end")
+            .unwrap();
+
+        let mut visited = Vec::new();
+        cart.read_file_zero_copy("/sparse.bin", |page| visited.extend_from_slice(page))
+            .unwrap();
+        assert_eq!(visited, cart.read_file("/sparse.bin").unwrap());
+
+        cart.create_file("/small.txt", b"inline").unwrap();
+        let mut calls = Vec::new();
+        cart.read_file_zero_copy("/small.txt", |page| calls.push(page.to_vec()))
+            .unwrap();
+        assert_eq!(calls, vec![b"inline".to_vec()]);
+
+        assert!(matches!(
+            cart.read_file_zero_copy("/missing", |_| {}),
+            Err(CartridgeError::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_pinned_buffer_survives_overwrite() {
+        let mut cart = cartridge_with_file(&[1u8; 1000]);
+        let block = cart.metadata("/data.bin").unwrap().blocks[0];
+        // What a guard holds, without the borrow that would stop the write
+        let pinned = cart.cached_page("", block).unwrap();
+
+        cart.write_at("/data.bin", 0, &[2u8; 10]).unwrap();
+        assert_eq!(pinned[..1000], [1u8; 1000]);
+        assert_eq!(cart.read_file("/data.bin").unwrap()[..10], [2u8; 10]);
+    }
+
+    #[test]
+    fn test_pin_page_refuses_free_pages_and_policies() {
+        let mut cart = cartridge_with_file(&[7u8; 1000]);
+        let free = cart.header().total_blocks - 1;
+        assert!(matches!(
+            cart.pin_page(free),
+            Err(CartridgeError::InvalidBlockId(_))
+        ));
+        assert!(cart.pin_page(HOLE_BLOCK).is_err());
+
+        cart.set_policy(crate::iam::Policy::new());
+        let block = cart.metadata("/data.bin").unwrap().blocks[0];
+        assert!(matches!(
+            cart.pin_page(block),
+            Err(CartridgeError::AccessDenied { .. })
+        ));
+    }
 }
@@ -2,27 +2,174 @@
 //!
 //! Provides high-level file operations for the Cartridge archive format.
 
-use crate::allocator::{hybrid::HybridAllocator, BlockAllocator};
-use crate::audit::{AuditLogger, Operation};
-use crate::catalog::{Catalog, FileMetadata, FileType};
-use crate::encryption::EncryptionConfig;
+use crate::allocator::{
+    hybrid::{AllocatorStats, HybridAllocator},
+    BlockAllocator,
+};
+use crate::audit::{self, AuditLogger, Operation};
+use crate::catalog::metadata::{FILE_NONCE_KEY, SYMLINK_TARGET_KEY};
+use crate::clock;
+use crate::catalog::{Catalog, FileMetadata, FileType, MetadataPatch, HOLE_BLOCK};
+use crate::check::{BlockRef, ConsistencyReport, ScrubError, ScrubResult, ScrubToken, SharedBlock};
+use crate::content_type;
+use crate::dedup::{self, DedupIndex};
+use crate::encryption::{self, EncryptionConfig, EncryptionKey, PageCipher, NONCE_SIZE};
 use crate::error::{CartridgeError, Result};
-use crate::header::{Header, PAGE_SIZE};
-use crate::iam::{Action, Policy, PolicyEngine};
+use crate::events::{
+    CartridgeEvent, EventListener, WRITE_PROGRESS_BLOCKS, WRITE_PROGRESS_THRESHOLD,
+};
+use crate::find::CaseSensitivity;
+#[cfg(not(feature = "no-fs"))]
+use crate::header::EncryptionParams;
+use crate::header::{
+    Header, PageSize, FEATURE_CASE_INSENSITIVE, FEATURE_ENCRYPTED, FEATURE_INLINE_DATA,
+    FEATURE_JOURNAL, FEATURE_PAGE_CHECKSUMS, FEATURE_SEGMENTED_CATALOG, FEATURE_SPARSE,
+    PAGE_SIZE, VERSION_MAJOR, VERSION_MINOR,
+};
+use crate::iam::{Action, CacheStats, Policy, PolicyEngine, RequestContext};
 use crate::io::CartridgeFile;
-use crate::manifest::Manifest;
-use crate::validation;
+use crate::manifest::{Bump, Dependency, Manifest};
+use crate::quota::{self, QuotaUsage, Quotas};
+use crate::retention::RetentionPolicy;
+use crate::telemetry;
+use crate::validation::{self, normalize_path};
+use crate::watch::{ChangeKind, Watchers};
 use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+#[cfg(not(feature = "no-fs"))]
 use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 // Auto-growth constants
 const MIN_BLOCKS: usize = 3; // Minimum: header + catalog + data
 const DEFAULT_INITIAL_BLOCKS: usize = 3; // Start minimal by default
-const GROW_THRESHOLD: f64 = 0.10; // Grow when <10% free
 const GROW_FACTOR: usize = 2; // Double size each time
+
+/// How often [`Cartridge::open_with_timeout`] retries a locked file
+#[cfg(not(feature = "no-fs"))]
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(20);
 const DEFAULT_MAX_BLOCKS: usize = 10_000_000; // ~40GB safety limit
-const MANIFEST_PATH: &str = ".cartridge/manifest.json";
+const VACUUM_BATCH_SIZE: usize = 256; // Pages relocated per vacuum step
+const FLUSH_RUN_PAGES: usize = 256; // Most pages coalesced into one flush write
+const MANIFEST_PATH: &str = "/.cartridge/manifest.json";
+const DEDUP_INDEX_PATH: &str = "/.cartridge/dedup.idx";
+const DEFAULT_MAX_USER_METADATA_BYTES: usize = 2048; // Same limit as S3
+const DEFAULT_INLINE_THRESHOLD: usize = 512; // Files this small live in the catalog
+
+/// `user_metadata` keys the cartridge manages itself
+const RESERVED_METADATA_KEYS: &[&str] =
+    &["encrypted", "encrypted_size", FILE_NONCE_KEY, SYMLINK_TARGET_KEY];
+
+/// Page id to cached page data
+///
+/// Buffers are shared so a [`PageGuard`] can hold one without copying it;
+/// changing a page swaps in a new buffer rather than writing to a pinned one.
+type PageCache = HashMap<u64, Arc<Vec<u8>>>;
+
+/// Options for creating a new disk-backed cartridge
+///
+/// Passed to [`Cartridge::create_with_options`]. The high-level
+/// `CartridgeBuilder` fills this in from its builder methods.
+#[derive(Debug, Clone)]
+pub struct CreateOptions {
+    /// Passphrase for at-rest encryption (`None` creates a plaintext cartridge)
+    pub passphrase: Option<String>,
+
+    /// Store a CRC32 with every page and verify it on read (default: true)
+    pub page_checksums: bool,
+
+    /// Blocks the file starts with, including the 3 reserved ones (default: 3)
+    ///
+    /// Sizing this for a known import up front avoids growing the file
+    /// repeatedly along the way.
+    pub initial_blocks: usize,
+
+    /// How the container grows when it runs out of space
+    pub growth: GrowthPolicy,
+
+    /// Grow automatically when full (default: true); when off, writes that
+    /// don't fit fail with `OutOfSpace` unless space is [reserved](Cartridge::reserve)
+    pub auto_grow: bool,
+
+    /// Size of every page (default: 4KB). Fixed for the cartridge's life.
+    pub page_size: PageSize,
+
+    /// How paths compare (default: exact). Fixed for the cartridge's life.
+    ///
+    /// With [`CaseSensitivity::Insensitive`], `Logo.PNG` and `logo.png` are
+    /// the same file: lookups match either spelling, listings keep the case
+    /// the file was created with, and creating one while the other exists
+    /// fails with `AlreadyExists`.
+    pub case_sensitivity: CaseSensitivity,
+}
+
+impl Default for CreateOptions {
+    fn default() -> Self {
+        CreateOptions {
+            passphrase: None,
+            page_checksums: true,
+            initial_blocks: DEFAULT_INITIAL_BLOCKS,
+            growth: GrowthPolicy::default(),
+            auto_grow: true,
+            page_size: PageSize::default(),
+            case_sensitivity: CaseSensitivity::Exact,
+        }
+    }
+}
+
+/// How far the container grows each time it fills up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GrowthPolicy {
+    /// Multiply the block count by this factor (default: 2.0)
+    Factor(f64),
+    /// Add this many blocks; gentler on flash, where doubling fragments
+    Linear(usize),
+}
+
+impl Default for GrowthPolicy {
+    fn default() -> Self {
+        GrowthPolicy::Factor(GROW_FACTOR as f64)
+    }
+}
+
+impl GrowthPolicy {
+    /// Block count after one growth step from `current`, capped at `max`
+    ///
+    /// Always grows by at least one block unless already at `max`.
+    fn next_size(self, current: usize, max: usize) -> usize {
+        let next = match self {
+            GrowthPolicy::Factor(factor) => (current as f64 * factor) as usize,
+            GrowthPolicy::Linear(blocks) => current.saturating_add(blocks),
+        };
+        next.max(current + 1).min(max)
+    }
+}
+
+/// When a flush fsyncs the backing file
+///
+/// Every flush writes its pages (through the journal, for journaled
+/// cartridges) before returning, so a process crash loses nothing under any
+/// policy. The fsync is what survives a power loss: until it happens the
+/// data sits in the OS page cache. Skipping it saves flash wear and latency
+/// at the price of losing every flush since the last fsync on power loss,
+/// possibly leaving the file torn so that opening it fails with
+/// [`CartridgeError::TornWrite`]. [`Cartridge::sync`], [`Cartridge::close`]
+/// and dropping the cartridge always fsync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyncPolicy {
+    /// Fsync on every flush (default)
+    #[default]
+    Always,
+    /// Only fsync on [`Cartridge::sync`], close and drop
+    OnClose,
+    /// Fsync on the first flush at least this long after the last fsync
+    ///
+    /// There is no timer: data flushed just before a quiet spell stays
+    /// unsynced until the next flush, sync or close.
+    Periodic(Duration),
+}
 
 /// Cartridge archive
 ///
@@ -42,7 +189,7 @@ pub struct Cartridge {
     file: Option<Mutex<CartridgeFile>>,
 
     /// In-memory page cache (page_id -> page data) - uses interior mutability for concurrent reads
-    pages: Arc<Mutex<std::collections::HashMap<u64, Vec<u8>>>>,
+    pages: Arc<Mutex<PageCache>>,
 
     /// Dirty pages that need to be flushed - uses interior mutability for concurrent reads
     dirty_pages: Arc<Mutex<std::collections::HashSet<u64>>>,
@@ -53,21 +200,77 @@ pub struct Cartridge {
     /// Session ID for audit logging
     session_id: u32,
 
+    /// Actor ID for audit logging
+    actor_id: u32,
+
     /// IAM policy for access control (optional)
     policy: Option<Policy>,
 
     /// IAM policy engine for evaluation - uses interior mutability for cache updates
     policy_engine: Option<Arc<Mutex<PolicyEngine>>>,
 
+    /// Principal that access checks are evaluated for (anonymous if unset)
+    principal: Option<String>,
+
+    /// Source tag reported to IAM conditions (optional)
+    request_source: Option<String>,
+
     /// Encryption configuration (optional)
     encryption_config: Option<EncryptionConfig>,
 
     /// Enable automatic growth (default: true)
     auto_grow: bool,
 
+    /// How far each automatic growth step goes
+    growth: GrowthPolicy,
+
+    /// When flushes fsync
+    sync_policy: SyncPolicy,
+
+    /// Last fsync by a flush or [`Self::sync`], tracked under `Periodic` only
+    last_sync: Option<Instant>,
+
+    /// Opened with [`Cartridge::open_read_only`]; every mutation fails
+    read_only: bool,
+
+    /// Set while [`Self::privileged`] runs: `.cartridge/` paths may change
+    system_access: bool,
+
     /// Maximum blocks allowed (prevents runaway growth)
     max_blocks: usize,
 
+    /// Cap on a file's user metadata (keys + values, in bytes)
+    max_user_metadata_bytes: usize,
+
+    /// Largest content stored inline in the catalog (0 disables inlining)
+    inline_threshold: usize,
+
+    /// Share blocks between identical pages on write (see [`crate::dedup`])
+    dedup: bool,
+
+    /// Hashes and reference counts of deduplicated blocks
+    dedup_index: DedupIndex,
+
+    /// Running digest of the file written last, so appending to it extends
+    /// the stored content hash instead of reading the file again
+    content_hasher: Option<ContentHasher>,
+
+    /// Per-prefix quotas with running usage (limits persist in the manifest)
+    quotas: Quotas,
+
+    /// Receives progress events (see [`crate::events`])
+    event_listener: Option<EventListener>,
+
+    /// Change subscribers (see [`crate::watch`])
+    watchers: Watchers,
+
+    /// Catalog or allocator changed since the last flush
+    metadata_dirty: bool,
+
+    /// Generation and last flush time of the on-disk header that the cached
+    /// pages, catalog and allocator reflect (see [`Self::refresh`])
+    disk_stamp: (u64, u64),
+
     /// Pages allocated for catalog overflow (multi-page serialization)
     catalog_overflow_pages: Vec<u64>,
 
@@ -99,16 +302,49 @@ impl Cartridge {
             dirty_pages: Arc::new(Mutex::new(std::collections::HashSet::new())),
             audit_logger: None,
             session_id: 0,
+            actor_id: audit::DEFAULT_ACTOR_ID,
             policy: None,
             policy_engine: None,
+            principal: None,
+            request_source: None,
             encryption_config: None,
             auto_grow: true,
+            growth: GrowthPolicy::default(),
+            sync_policy: SyncPolicy::default(),
+            last_sync: None,
+            read_only: false,
+            system_access: false,
             max_blocks: DEFAULT_MAX_BLOCKS,
+            max_user_metadata_bytes: DEFAULT_MAX_USER_METADATA_BYTES,
+            inline_threshold: DEFAULT_INLINE_THRESHOLD,
+            dedup: false,
+            dedup_index: DedupIndex::default(),
+            content_hasher: None,
+            quotas: Quotas::default(),
+            event_listener: None,
+            watchers: Watchers::default(),
+            metadata_dirty: true,
+            disk_stamp: (0, 0),
             catalog_overflow_pages: Vec::new(),
             allocator_overflow_pages: Vec::new(),
         }
     }
 
+    /// Create an in-memory cartridge with slug and title
+    ///
+    /// Like [`create`](Self::create) but with no file behind it; use
+    /// [`to_bytes`](Self::to_bytes) to get the archive out.
+    pub fn in_memory(slug: &str, title: &str) -> Result<Self> {
+        validation::ContainerSlug::new(slug)?;
+        let manifest = Manifest::new(slug, title, semver::Version::new(0, 1, 0))?;
+
+        let mut cartridge = Self::new(MIN_BLOCKS);
+        cartridge
+            .system()
+            .write(MANIFEST_PATH, &serde_json::to_vec_pretty(&manifest)?)?;
+        Ok(cartridge)
+    }
+
     /// Create a new disk-backed cartridge with slug and title
     ///
     /// # Arguments
@@ -119,71 +355,26 @@ impl Cartridge {
     /// # Examples
     ///
     /// ```no_run
-    /// use cartridge_core::Cartridge;
+    /// use cartridge_rs::core::Cartridge;
     ///
     /// // Creates "my-container.cart" in current directory
     /// let cart = Cartridge::create("my-container", "My Container")?;
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
+    #[cfg(not(feature = "no-fs"))]
     pub fn create(slug: &str, title: &str) -> Result<Self> {
         // Validate slug and create path from it
         let slug_validated = validation::ContainerSlug::new(slug)?;
         let path = std::path::PathBuf::from(slug_validated.as_str());
-        let normalized_path = validation::normalize_container_path(&path)?;
-
-        // Create with minimal initial blocks (auto-growth enabled by default)
-        let total_blocks = DEFAULT_INITIAL_BLOCKS;
-
-        let mut header = Header::new();
-        header.total_blocks = total_blocks as u64;
-        // Reserve pages 0, 1, 2 for header, catalog, allocator
-        header.free_blocks = (total_blocks - 3) as u64;
-        header.btree_root_page = 1;
-
-        let file = CartridgeFile::create(normalized_path, &header)?;
-
-        let mut allocator = HybridAllocator::new(total_blocks);
-        // Mark pages 0, 1, 2 as allocated (reserved)
-        allocator.allocate(3 * PAGE_SIZE as u64)?;
-
-        let catalog = Catalog::new(1);
-
-        let mut cartridge = Cartridge {
-            header,
-            allocator,
-            catalog,
-            file: Some(Mutex::new(file)),
-            pages: Arc::new(Mutex::new(std::collections::HashMap::new())),
-            dirty_pages: Arc::new(Mutex::new(std::collections::HashSet::new())),
-            audit_logger: None,
-            session_id: 0,
-            policy: None,
-            policy_engine: None,
-            encryption_config: None,
-            auto_grow: true,
-            max_blocks: DEFAULT_MAX_BLOCKS,
-            catalog_overflow_pages: Vec::new(),
-            allocator_overflow_pages: Vec::new(),
-        };
-
-        // Create manifest
-        let manifest = Manifest::new(slug, title, semver::Version::new(0, 1, 0))?;
-        let manifest_json = serde_json::to_vec_pretty(&manifest)?;
-
-        // Ensure .cartridge directory exists
-        cartridge.create_dir(".cartridge")?;
-
-        // Write manifest to .cartridge/manifest.json
-        cartridge.create_file(MANIFEST_PATH, &manifest_json)?;
-
-        // Flush to persist catalog + allocator state to disk
-        cartridge.flush()?;
-
-        Ok(cartridge)
+        Self::create_with_options(path, slug, title, &CreateOptions::default())
     }
 
     /// Create a new disk-backed cartridge at a specific path
     ///
-    /// Use this when you need to specify a custom directory or path.
+    /// Use this when you need to specify a custom directory or path. An
+    /// existing cartridge at `path` is replaced, unless another handle has it
+    /// open; see [`open_or_create_with_options`](Self::open_or_create_with_options)
+    /// to keep it.
     ///
     /// # Arguments
     ///
@@ -194,32 +385,169 @@ impl Cartridge {
     /// # Examples
     ///
     /// ```no_run
-    /// use cartridge_core::Cartridge;
+    /// use cartridge_rs::core::Cartridge;
     ///
     /// // Creates "/data/my-container.cart"
     /// let cart = Cartridge::create_at("/data/my-container", "my-container", "My Container")?;
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
+    #[cfg(not(feature = "no-fs"))]
     pub fn create_at<P: AsRef<Path>>(path: P, slug: &str, title: &str) -> Result<Self> {
-        // Validate slug
+        Self::create_with_options(path, slug, title, &CreateOptions::default())
+    }
+
+    /// Create a new disk-backed cartridge at a specific path with options
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cartridge_rs::core::{Cartridge, CreateOptions};
+    ///
+    /// let options = CreateOptions {
+    ///     passphrase: Some("correct horse battery staple".to_string()),
+    ///     ..Default::default()
+    /// };
+    /// let cart = Cartridge::create_with_options("/data/secrets", "secrets", "Secrets", &options)?;
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    #[cfg(not(feature = "no-fs"))]
+    pub fn create_with_options<P: AsRef<Path>>(
+        path: P,
+        slug: &str,
+        title: &str,
+        options: &CreateOptions,
+    ) -> Result<Self> {
+        let encryption = Self::new_encryption(options)?;
+        Self::create_inner(path.as_ref(), slug, title, encryption, options)
+    }
+
+    /// Open the cartridge at `path`, or create it if there is none
+    ///
+    /// Unlike checking [`Path::exists`] first, this can't race another
+    /// caller: the file is locked before it is inspected, so only one of two
+    /// concurrent callers creates it and the other fails with
+    /// [`CartridgeError::Locked`]. An existing cartridge must have been
+    /// created with `slug`, or this fails with
+    /// [`CartridgeError::SlugMismatch`]; its title is left alone.
+    ///
+    /// `options.passphrase` unlocks an existing encrypted cartridge and
+    /// `auto_grow`/`growth` apply either way. The rest of `options` only
+    /// matters when the cartridge is created.
+    #[cfg(not(feature = "no-fs"))]
+    pub fn open_or_create_with_options<P: AsRef<Path>>(
+        path: P,
+        slug: &str,
+        title: &str,
+        options: &CreateOptions,
+    ) -> Result<Self> {
         let _slug_validated = validation::ContainerSlug::new(slug)?;
         let normalized_path = validation::normalize_container_path(path.as_ref())?;
+        let (mut file, created) = CartridgeFile::open_or_create(normalized_path)?;
+        if created {
+            let encryption = Self::new_encryption(options)?;
+            let (header, cipher) = Self::new_header(encryption, options);
+            file.write_header(&header)?;
+            return Self::init_new(file, header, cipher, slug, title, options);
+        }
+
+        let mut cartridge = Self::load(file, options.passphrase.as_deref(), false)?;
+        let found = cartridge.slug()?;
+        if found != slug {
+            return Err(CartridgeError::SlugMismatch {
+                expected: slug.to_string(),
+                found,
+            });
+        }
+        cartridge.auto_grow = options.auto_grow;
+        cartridge.growth = options.growth;
+        Ok(cartridge)
+    }
+
+    #[cfg(not(feature = "no-fs"))]
+    fn new_encryption(options: &CreateOptions) -> Result<Option<(EncryptionParams, PageCipher)>> {
+        match &options.passphrase {
+            Some(passphrase) => {
+                let (params, key) = encryption::new_encryption_params(passphrase)?;
+                Ok(Some((params, PageCipher::new(&key))))
+            }
+            None => Ok(None),
+        }
+    }
+
+    #[cfg(not(feature = "no-fs"))]
+    fn create_inner(
+        path: &Path,
+        slug: &str,
+        title: &str,
+        encryption: Option<(EncryptionParams, PageCipher)>,
+        options: &CreateOptions,
+    ) -> Result<Self> {
+        // Validate slug
+        let _slug_validated = validation::ContainerSlug::new(slug)?;
+        let normalized_path = validation::normalize_container_path(path)?;
+        let (header, cipher) = Self::new_header(encryption, options);
+        let file = CartridgeFile::create(normalized_path, &header)?;
+        Self::init_new(file, header, cipher, slug, title, options)
+    }
 
-        // Create with minimal initial blocks (auto-growth enabled by default)
-        let total_blocks = DEFAULT_INITIAL_BLOCKS;
+    /// Header for a new cartridge, plus the cipher its pages are written with
+    #[cfg(not(feature = "no-fs"))]
+    fn new_header(
+        encryption: Option<(EncryptionParams, PageCipher)>,
+        options: &CreateOptions,
+    ) -> (Header, Option<PageCipher>) {
+        let page_checksums = options.page_checksums;
+
+        // Create with minimal initial blocks by default (auto-growth takes it from there)
+        let total_blocks = options.initial_blocks.clamp(MIN_BLOCKS, DEFAULT_MAX_BLOCKS);
 
         let mut header = Header::new();
+        header.block_size = options.page_size.bytes() as u32;
         header.total_blocks = total_blocks as u64;
         // Reserve pages 0, 1, 2 for header, catalog, allocator
         header.free_blocks = (total_blocks - 3) as u64;
         header.btree_root_page = 1;
 
-        let file = CartridgeFile::create(normalized_path, &header)?;
+        // Encryption params live in the (plaintext) header so open() can
+        // detect them before touching any encrypted page.
+        let cipher = encryption.map(|(params, cipher)| {
+            header.set_encryption_params(params);
+            cipher
+        });
+        header.set_feature(FEATURE_PAGE_CHECKSUMS, page_checksums);
+        header.set_feature(FEATURE_JOURNAL, true);
+        let case_insensitive = options.case_sensitivity == CaseSensitivity::Insensitive;
+        header.set_feature(FEATURE_CASE_INSENSITIVE, case_insensitive);
+        (header, cipher)
+    }
+
+    /// Set up the catalog, allocator and manifest in a freshly created file
+    #[cfg(not(feature = "no-fs"))]
+    fn init_new(
+        mut file: CartridgeFile,
+        header: Header,
+        cipher: Option<PageCipher>,
+        slug: &str,
+        title: &str,
+        options: &CreateOptions,
+    ) -> Result<Self> {
+        let total_blocks = header.total_blocks as usize;
+        let case_insensitive = header.has_feature(FEATURE_CASE_INSENSITIVE);
+        if let Some(cipher) = cipher {
+            file.set_cipher(cipher);
+        }
+        file.set_checksums(options.page_checksums);
+        file.set_page_size(header.page_size());
 
         let mut allocator = HybridAllocator::new(total_blocks);
+        allocator.set_block_size(header.page_size());
         // Mark pages 0, 1, 2 as allocated (reserved)
-        allocator.allocate(3 * PAGE_SIZE as u64)?;
+        allocator.allocate(3 * header.page_size() as u64)?;
 
-        let catalog = Catalog::new(1);
+        let mut catalog = Catalog::new(1);
+        if case_insensitive {
+            catalog.set_case_insensitive();
+        }
 
         let mut cartridge = Cartridge {
             header,
@@ -230,11 +558,29 @@ impl Cartridge {
             dirty_pages: Arc::new(Mutex::new(std::collections::HashSet::new())),
             audit_logger: None,
             session_id: 0,
+            actor_id: audit::DEFAULT_ACTOR_ID,
             policy: None,
             policy_engine: None,
+            principal: None,
+            request_source: None,
             encryption_config: None,
-            auto_grow: true,
+            auto_grow: options.auto_grow,
+            growth: options.growth,
+            sync_policy: SyncPolicy::default(),
+            last_sync: None,
+            read_only: false,
+            system_access: false,
             max_blocks: DEFAULT_MAX_BLOCKS,
+            max_user_metadata_bytes: DEFAULT_MAX_USER_METADATA_BYTES,
+            inline_threshold: DEFAULT_INLINE_THRESHOLD,
+            dedup: false,
+            dedup_index: DedupIndex::default(),
+            content_hasher: None,
+            quotas: Quotas::default(),
+            event_listener: None,
+            watchers: Watchers::default(),
+            metadata_dirty: true,
+            disk_stamp: (0, 0),
             catalog_overflow_pages: Vec::new(),
             allocator_overflow_pages: Vec::new(),
         };
@@ -243,11 +589,8 @@ impl Cartridge {
         let manifest = Manifest::new(slug, title, semver::Version::new(0, 1, 0))?;
         let manifest_json = serde_json::to_vec_pretty(&manifest)?;
 
-        // Ensure .cartridge directory exists
-        cartridge.create_dir(".cartridge")?;
-
         // Write manifest to .cartridge/manifest.json
-        cartridge.create_file(MANIFEST_PATH, &manifest_json)?;
+        cartridge.system().write(MANIFEST_PATH, &manifest_json)?;
 
         // Flush to disk so the catalog and allocator state (including reserved
         // page tracking) are persisted. Without this, reopening the cartridge
@@ -262,37 +605,163 @@ impl Cartridge {
     ///
     /// Loads the manifest if present. For backwards compatibility,
     /// containers without manifests will open successfully with a warning.
+    ///
+    /// Fails with [`CartridgeError::EncryptionRequired`] if the cartridge is
+    /// encrypted at rest; use [`Cartridge::open_encrypted`] instead.
+    ///
+    /// A cartridge written by a newer minor format version opens read-only,
+    /// as if by [`open_read_only`](Self::open_read_only). One that needs a
+    /// feature this version doesn't know fails with
+    /// [`CartridgeError::UnsupportedFeatures`].
+    #[cfg(not(feature = "no-fs"))]
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_with(path.as_ref(), None, false)
+    }
+
+    /// Open an existing cartridge without write access
+    ///
+    /// The backing file is opened read-only, so this works on files the
+    /// process can't write. Every mutating method fails with
+    /// [`CartridgeError::ReadOnly`], and nothing is written back on flush or
+    /// drop. Interrupted operations are left for the next read-write open to
+    /// recover.
+    ///
+    /// Takes no lock, so any number of read-only handles can open a file,
+    /// including while a read-write handle has it open. A reader sees the
+    /// file as it was when opened; [`is_stale`](Self::is_stale) and
+    /// [`refresh`](Self::refresh) pick up what the writer flushed since.
+    #[cfg(not(feature = "no-fs"))]
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_with(path.as_ref(), None, true)
+    }
+
+    /// Open an existing cartridge, failing unless its paths compare the way
+    /// the caller expects
+    ///
+    /// Code written for a case-insensitive cartridge would silently miss
+    /// files in a case-sensitive one and vice versa, so a mismatch fails with
+    /// [`CartridgeError::CaseSensitivityMismatch`] instead.
+    #[cfg(not(feature = "no-fs"))]
+    pub fn open_with_case<P: AsRef<Path>>(path: P, expected: CaseSensitivity) -> Result<Self> {
+        let cartridge = Self::open(path)?;
+        let found = cartridge.case_sensitivity();
+        if found != expected {
+            return Err(CartridgeError::CaseSensitivityMismatch { expected, found });
+        }
+        Ok(cartridge)
+    }
+
+    /// Like [`open`](Self::open), but keep retrying for up to `timeout`
+    /// while another handle holds the file's lock
+    ///
+    /// Fails with [`CartridgeError::Locked`] if the lock is still held when
+    /// the time runs out; other errors are returned immediately.
+    #[cfg(not(feature = "no-fs"))]
+    pub fn open_with_timeout<P: AsRef<Path>>(path: P, timeout: Duration) -> Result<Self> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match Self::open(path.as_ref()) {
+                Err(CartridgeError::Locked { .. }) if Instant::now() < deadline => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    std::thread::sleep(LOCK_RETRY_INTERVAL.min(remaining));
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Open an existing cartridge that is encrypted at rest
+    ///
+    /// Derives the key from `passphrase` using the KDF parameters stored in
+    /// the header. Fails with [`CartridgeError::InvalidPassphrase`] if the
+    /// passphrase is wrong, or [`CartridgeError::NotEncrypted`] if the
+    /// cartridge is plaintext.
+    #[cfg(not(feature = "no-fs"))]
+    pub fn open_encrypted<P: AsRef<Path>>(path: P, passphrase: &str) -> Result<Self> {
+        Self::open_with(path.as_ref(), Some(passphrase), false)
+    }
+
+    #[cfg(not(feature = "no-fs"))]
+    fn open_with(path: &Path, passphrase: Option<&str>, read_only: bool) -> Result<Self> {
         // Normalize path (handles .cart extension)
-        let normalized_path = validation::normalize_container_path(path.as_ref())?;
+        let normalized_path = validation::normalize_container_path(path)?;
 
-        let mut file = CartridgeFile::open(normalized_path)?;
-        let mut header = file.read_header()?;
+        let file = if read_only {
+            CartridgeFile::open_read_only(normalized_path)?
+        } else {
+            CartridgeFile::open(normalized_path)?
+        };
+        Self::load(file, passphrase, read_only)
+    }
 
-        // Load allocator first (catalog overflow pages are tracked in the allocator)
-        let (mut allocator, allocator_overflow_pages) =
-            Self::load_allocator_multi(&mut file, header.total_blocks as usize)?;
+    /// Load a cartridge from the bytes of a `.cart` file
+    ///
+    /// The result lives in memory, with no file handle or lock behind it:
+    /// changes stay in the image, and [`to_bytes`](Self::to_bytes) gets it
+    /// back out. Encrypted images fail with
+    /// [`CartridgeError::EncryptionRequired`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::load(CartridgeFile::from_bytes(bytes.to_vec()), None, false)
+    }
 
-        // The serialized allocator doesn't know about its own overflow pages
-        // (they were allocated after serialization). Mark them as allocated now
-        // so future flush() calls don't double-allocate them.
-        if !allocator_overflow_pages.is_empty() {
-            allocator.mark_pages_allocated(&allocator_overflow_pages)?;
+    /// Serialize the whole cartridge to the bytes of a `.cart` file
+    ///
+    /// Flushes first, so the bytes hold every change and can be written to
+    /// disk and opened with [`open`](Self::open) or loaded back with
+    /// [`from_bytes`](Self::from_bytes). An in-memory cartridge from
+    /// [`new`](Self::new) keeps the image around afterwards, so later calls
+    /// only write what changed.
+    pub fn to_bytes(&mut self) -> Result<Vec<u8>> {
+        if self.file.is_none() {
+            let mut file = CartridgeFile::in_memory(&self.header);
+            file.extend(self.header.total_blocks as usize)?;
+            self.file = Some(Mutex::new(file));
+            // Nothing has been written anywhere yet
+            let cached: Vec<u64> = self.pages.lock().keys().copied().collect();
+            self.dirty_pages.lock().extend(cached);
+            self.metadata_dirty = true;
+        }
+        self.flush()?;
+        self.file.as_ref().unwrap().lock().to_bytes()
+    }
+
+    /// Finish opening: unlock, then load the allocator and catalog
+    fn load(mut file: CartridgeFile, passphrase: Option<&str>, read_only: bool) -> Result<Self> {
+        let mut header = file.read_header()?;
+        if header.requires_read_only() && !read_only {
+            tracing::warn!(
+                "Cartridge format {}.{} is newer than {}.{}; opening read-only",
+                header.version_major,
+                header.version_minor,
+                VERSION_MAJOR,
+                VERSION_MINOR
+            );
         }
+        let read_only = read_only || header.requires_read_only();
 
-        // Recalibrate all internal free-block counters from the actual bitmap.
-        // The canonical `free_blocks` counter can become stale across
-        // serialize/deserialize cycles; recalibrating from the bitmap (which is
-        // the authoritative record of every allocation) eliminates the
-        // desynchronization that causes spurious OutOfSpace errors.
-        allocator.recalibrate();
+        // The header is always plaintext; everything after it needs the key
+        match (header.encryption_params(), passphrase) {
+            (Some(params), Some(passphrase)) => {
+                let key = encryption::unlock(passphrase, &params)?;
+                file.set_cipher(PageCipher::new(&key));
+            }
+            (Some(_), None) => return Err(CartridgeError::EncryptionRequired),
+            (None, Some(_)) => return Err(CartridgeError::NotEncrypted),
+            (None, None) => {}
+        }
 
-        // Sync header free_blocks from recalibrated allocator.
-        header.free_blocks = allocator.free_blocks() as u64;
+        // Cartridges written before checksums existed don't set the flag
+        file.set_checksums(header.has_feature(FEATURE_PAGE_CHECKSUMS));
+        file.set_page_size(header.page_size());
 
-        // Load catalog (may span multiple pages)
-        let (catalog, catalog_overflow_pages) =
-            Self::load_catalog_multi(&mut file, header.btree_root_page)?;
+        let Structures {
+            allocator,
+            allocator_overflow_pages,
+            catalog,
+            catalog_overflow_pages,
+            needs_rewrite,
+        } = Self::load_structures(&mut file, &mut header)?;
+        let disk_stamp = stamp(&header);
 
         let cartridge = Cartridge {
             header,
@@ -303,11 +772,29 @@ impl Cartridge {
             dirty_pages: Arc::new(Mutex::new(std::collections::HashSet::new())),
             audit_logger: None,
             session_id: 0,
+            actor_id: audit::DEFAULT_ACTOR_ID,
             policy: None,
             policy_engine: None,
+            principal: None,
+            request_source: None,
             encryption_config: None,
             auto_grow: true,
+            growth: GrowthPolicy::default(),
+            sync_policy: SyncPolicy::default(),
+            last_sync: None,
+            read_only,
+            system_access: false,
             max_blocks: DEFAULT_MAX_BLOCKS,
+            max_user_metadata_bytes: DEFAULT_MAX_USER_METADATA_BYTES,
+            inline_threshold: DEFAULT_INLINE_THRESHOLD,
+            dedup: false,
+            dedup_index: DedupIndex::default(),
+            content_hasher: None,
+            quotas: Quotas::default(),
+            event_listener: None,
+            watchers: Watchers::default(),
+            metadata_dirty: !read_only && needs_rewrite,
+            disk_stamp,
             catalog_overflow_pages,
             allocator_overflow_pages,
         };
@@ -321,110 +808,445 @@ impl Cartridge {
 
         // Recover from any interrupted vacuum operations
         let mut cartridge = cartridge;
+        cartridge.load_quotas();
+        cartridge.load_dedup_index();
+        if read_only {
+            return Ok(cartridge);
+        }
         match cartridge.recover_vacuum_wal() {
             Ok(0) => {}
             Ok(n) => tracing::info!("Recovered {n} interrupted vacuum operations on open"),
             Err(e) => tracing::warn!("WAL recovery failed (non-fatal): {e}"),
         }
+        if let Err(e) = cartridge.save_migrated_manifest() {
+            tracing::warn!("Manifest migration failed (non-fatal): {e}");
+        }
 
         Ok(cartridge)
     }
 
-    /// Flush all dirty pages to disk
-    pub fn flush(&mut self) -> Result<()> {
-        if self.file.is_none() {
-            return Ok(());
+    /// Wrap a damaged file in a read-only cartridge over whatever catalog
+    /// and allocator [`open_recovery`](Self::open_recovery) pieced together
+    #[cfg(not(feature = "no-fs"))]
+    pub(crate) fn recovered(
+        file: CartridgeFile,
+        header: Header,
+        allocator: HybridAllocator,
+        catalog: Catalog,
+    ) -> Self {
+        let mut cartridge = Cartridge::new(header.total_blocks.max(MIN_BLOCKS as u64) as usize);
+        cartridge.header = header;
+        cartridge.allocator = allocator;
+        cartridge.catalog = catalog;
+        cartridge.file = Some(Mutex::new(file));
+        cartridge.read_only = true;
+        cartridge.metadata_dirty = false;
+        cartridge
+    }
+
+    /// Read one page straight from the backing file, bypassing the page cache
+    #[cfg(not(feature = "no-fs"))]
+    pub(crate) fn read_disk_page(&self, page_id: u64) -> Result<Vec<u8>> {
+        match &self.file {
+            Some(file) => file.lock().read_page_data(page_id),
+            None => Err(CartridgeError::Corruption(format!(
+                "Block {} can't be read: no disk backing",
+                page_id
+            ))),
         }
+    }
 
-        let mut file = self.file.as_ref().unwrap().lock();
+    /// Load the allocator and catalog the header points at
+    ///
+    /// Fails with [`CartridgeError::TornWrite`] if they and the header come
+    /// from different flushes. Syncs the header's free count with the
+    /// allocator.
+    fn load_structures(file: &mut CartridgeFile, header: &mut Header) -> Result<Structures> {
+        // Load allocator first (catalog overflow pages are tracked in the allocator)
+        let (mut allocator, allocator_overflow_pages, allocator_gen) =
+            Self::load_allocator_multi(file, header.total_blocks as usize)?;
 
-        // Write header (updated below after we know overflow state)
-        file.write_header(&self.header)?;
-
-        // --- Free ALL old overflow pages before any new allocations ---
-        // This prevents a bug where old allocator overflow pages overlap with
-        // newly allocated catalog overflow pages: if we freed allocator overflow
-        // AFTER catalog allocation, an old allocator overflow page that was just
-        // reallocated for catalog overflow would be incorrectly freed.
-        let old_overflow_count = self.catalog_overflow_pages.len()
-            + self.allocator_overflow_pages.len();
-        if !self.catalog_overflow_pages.is_empty() {
-            self.allocator.free(&self.catalog_overflow_pages)?;
-            self.catalog_overflow_pages.clear();
-        }
-        if !self.allocator_overflow_pages.is_empty() {
-            self.allocator.free(&self.allocator_overflow_pages)?;
-            self.allocator_overflow_pages.clear();
-        }
-        if old_overflow_count > 0 {
-            self.header.free_blocks = self.allocator.free_blocks() as u64;
+        // The serialized allocator doesn't know about its own overflow pages
+        // (they were allocated after serialization). Mark them as allocated now
+        // so future flush() calls don't double-allocate them.
+        if !allocator_overflow_pages.is_empty() {
+            allocator.mark_pages_allocated(&allocator_overflow_pages)?;
         }
 
-        // --- Catalog: serialize with bincode, write multi-page ---
-        let catalog_data = self.catalog.to_bytes()?;
-        self.catalog_overflow_pages = Self::write_multi_page_blob(
-            &mut file,
-            &self.pages,
-            1,
-            &catalog_data,
-            &mut self.allocator,
-            &mut self.header,
-        )?;
-
-        // --- Allocator: serialize with bincode, write multi-page ---
-        let allocator_data = bincode::serialize(&self.allocator)
-            .map_err(|e| CartridgeError::Corruption(format!("allocator serialize: {e}")))?;
-        self.allocator_overflow_pages = Self::write_multi_page_blob(
-            &mut file,
-            &self.pages,
-            2,
-            &allocator_data,
-            &mut self.allocator,
-            &mut self.header,
-        )?;
-
-        // Re-write header (total_blocks / free_blocks may have changed from overflow)
-        file.write_header(&self.header)?;
-
-        // Write dirty content pages
-        let pages = self.pages.lock();
-        let mut dirty_pages = self.dirty_pages.lock();
-        for &page_id in dirty_pages.iter() {
-            if let Some(data) = pages.get(&page_id) {
-                file.write_page_data(page_id, data)?;
+        // Recalibrate all internal free-block counters from the actual bitmap.
+        // The canonical `free_blocks` counter can become stale across
+        // serialize/deserialize cycles; recalibrating from the bitmap (which is
+        // the authoritative record of every allocation) eliminates the
+        // desynchronization that causes spurious OutOfSpace errors.
+        allocator.recalibrate();
+
+        // Sync header free_blocks from recalibrated allocator.
+        header.free_blocks = allocator.free_blocks() as u64;
+
+        // Load catalog (may span multiple pages)
+        let (mut catalog, catalog_overflow_pages, catalog_gen) =
+            Self::load_catalog_multi(file, header.btree_root_page)?;
+
+        // A flush that stopped part way leaves structures from different
+        // generations; 0 means a structure predates generations
+        let stamps = [header.generation(), catalog_gen, allocator_gen];
+        let mut known = stamps.iter().filter(|&&generation| generation != 0);
+        if let Some(first) = known.next() {
+            if known.any(|generation| generation != first) {
+                return Err(CartridgeError::TornWrite {
+                    header_gen: stamps[0],
+                    catalog_gen,
+                    allocator_gen,
+                });
             }
         }
 
-        dirty_pages.clear();
-        file.sync()?;
-
-        // Post-flush assertion: detect dud cart (empty catalog in a non-empty file)
-        let entry_count = self.catalog.len();
-        let total = self.header.total_blocks;
-        if entry_count == 0 && total > MIN_BLOCKS as u64 {
-            tracing::error!(
-                "CRITICAL: flush produced empty catalog with {} total blocks ({} bytes). \
-                 This cartridge may be a dud.",
-                total,
-                total * PAGE_SIZE as u64,
-            );
+        // Catalogs written before path normalization may hold "docs/a.txt"
+        // or "a//b"; re-key them in memory so lookups find them. A writable
+        // cartridge persists the new keys on its next flush.
+        let migrated = catalog.normalize_keys();
+        if migrated > 0 {
+            tracing::info!("Normalized {migrated} legacy catalog paths on open");
+        }
+        if header.has_feature(FEATURE_CASE_INSENSITIVE) {
+            catalog.set_case_insensitive();
         }
 
-        Ok(())
+        Ok(Structures {
+            allocator,
+            allocator_overflow_pages,
+            catalog,
+            catalog_overflow_pages,
+            // Unstamped structures are in an older layout; store them anew
+            needs_rewrite: migrated > 0 || catalog_gen == 0 || allocator_gen == 0,
+        })
     }
 
-    // =========================================================================
-    // Multi-page blob serialization
-    // =========================================================================
+    /// The on-disk header, if another handle has flushed since this one
+    /// last loaded or flushed
+    fn newer_disk_header(&self) -> Result<Option<Header>> {
+        let Some(file) = &self.file else {
+            return Ok(None);
+        };
+        let mut file = file.lock();
+        if file.is_in_memory() {
+            return Ok(None);
+        }
+        let header = file.read_header()?;
+        Ok((stamp(&header) != self.disk_stamp).then_some(header))
+    }
 
-    /// Multi-page blob header discriminator.
-    /// Old format: page starts with 0x7B (`{`) — raw JSON.
-    /// New format: page starts with 0x00 — multi-page header.
-    const MULTI_PAGE_MAGIC: u8 = 0x00;
+    /// Whether a writer has flushed changes this handle hasn't seen
+    ///
+    /// Only a read-only handle can fall behind a writer that follows the
+    /// locking rules, since read-write handles lock each other out. Reads
+    /// just the header. Always `false` for in-memory cartridges.
+    pub fn is_stale(&self) -> Result<bool> {
+        Ok(self.newer_disk_header()?.is_some())
+    }
 
-    /// Multi-page header size: 1 (magic) + 4 (data_len) + 2 (num_overflow) = 7 bytes.
+    /// Pick up changes a writer flushed to the same file
+    ///
+    /// Cached pages are kept until the header shows the file moved on. When
+    /// it has, they are all dropped and the catalog and allocator reloaded,
+    /// so reads see the writer's changes. A read-write handle holds the
+    /// file's lock, so its file only moves on if something that ignores the
+    /// lock rewrote it; then this fails with [`CartridgeError::Conflict`],
+    /// changing nothing, if the handle has unflushed changes of its own,
+    /// since they were made against state that no longer exists.
+    pub fn refresh(&mut self) -> Result<RefreshReport> {
+        let Some(mut header) = self.newer_disk_header()? else {
+            return Ok(RefreshReport {
+                changed: false,
+                generation: self.disk_stamp.0,
+                pages_dropped: 0,
+            });
+        };
+        let generation = header.generation();
+        if self.metadata_dirty || !self.dirty_pages.lock().is_empty() {
+            return Err(CartridgeError::Conflict { generation });
+        }
+
+        let loaded = {
+            let mut file = self.file.as_ref().unwrap().lock();
+            Self::load_structures(&mut file, &mut header)?
+        };
+        let pages_dropped = {
+            let mut pages = self.pages.lock();
+            let count = pages.len();
+            pages.clear();
+            count
+        };
+        self.disk_stamp = stamp(&header);
+        self.header = header;
+        self.allocator = loaded.allocator;
+        self.allocator_overflow_pages = loaded.allocator_overflow_pages;
+        self.catalog = loaded.catalog;
+        self.catalog_overflow_pages = loaded.catalog_overflow_pages;
+        self.load_quotas();
+        self.load_dedup_index();
+        tracing::debug!("Refreshed to generation {generation}, dropped {pages_dropped} pages");
+
+        Ok(RefreshReport {
+            changed: true,
+            generation,
+            pages_dropped,
+        })
+    }
+
+    /// Flush all dirty pages to disk
+    ///
+    /// Pending audit entries go to the logger's flush callback if it was
+    /// started, and to the persisted trail otherwise. A read-only cartridge
+    /// has nothing else to flush and returns `Ok`.
+    pub fn flush(&mut self) -> Result<()> {
+        if let Some(logger) = &self.audit_logger {
+            logger.flush_now();
+        }
+        if self.read_only {
+            return Ok(());
+        }
+        self.persist_audit()?;
+        if self.file.is_none() {
+            return Ok(());
+        }
+        self.persist_dedup_index()?;
+
+        let dirty_count = self.dirty_pages.lock().len();
+        self.emit(|| CartridgeEvent::FlushStarted { dirty_pages: dirty_count });
+
+        let mut file = self.file.as_ref().unwrap().lock();
+        let on_disk = !file.is_in_memory();
+        let durable = !on_disk || self.sync_due();
+        file.set_durable(durable);
+
+        // Journaled cartridges buffer the whole flush and commit it atomically,
+        // so a crash never leaves the header pointing at a half-written catalog
+        let journaled = self.header.has_feature(FEATURE_JOURNAL);
+        if journaled {
+            file.begin_batch();
+        }
+
+        // A flush with nothing to write leaves the file byte-for-byte alone
+        if self.metadata_dirty || dirty_count > 0 {
+            self.header.set_last_flush_us(clock::unix_micros());
+        }
+
+        let result = (|| -> Result<()> {
+            // Write header (updated below after we know overflow state)
+            file.write_header(&self.header)?;
+
+            // Nothing but content changed: the catalog and allocator on disk
+            // are current, so skip re-serializing them
+            if !self.metadata_dirty {
+                return Self::write_dirty_pages(&mut file, &self.pages, &self.dirty_pages);
+            }
+
+            // --- Free ALL old overflow pages before any new allocations ---
+            // This prevents a bug where old allocator overflow pages overlap with
+            // newly allocated catalog overflow pages: if we freed allocator overflow
+            // AFTER catalog allocation, an old allocator overflow page that was just
+            // reallocated for catalog overflow would be incorrectly freed.
+            //
+            // Pages of catalog segments that haven't changed stay where they are.
+            let kept: std::collections::HashSet<u64> =
+                self.catalog.clean_segment_pages().into_iter().collect();
+            let stale_catalog: Vec<u64> = self
+                .catalog_overflow_pages
+                .iter()
+                .copied()
+                .filter(|p| !kept.contains(p))
+                .collect();
+            let old_overflow_count = stale_catalog.len() + self.allocator_overflow_pages.len();
+            if !stale_catalog.is_empty() {
+                self.allocator.free(&stale_catalog)?;
+            }
+            self.catalog_overflow_pages = kept.into_iter().collect();
+            if !self.allocator_overflow_pages.is_empty() {
+                self.allocator.free(&self.allocator_overflow_pages)?;
+                self.allocator_overflow_pages.clear();
+            }
+            if old_overflow_count > 0 {
+                self.header.free_blocks = self.allocator.free_blocks() as u64;
+            }
+
+            let (growth, max_blocks) = (self.growth, self.max_blocks);
+
+            // The catalog and allocator are stamped with the next generation;
+            // the header only takes it once both are written
+            let generation = self.header.generation() + 1;
+
+            // --- Catalog: serialize with bincode, write multi-page ---
+            // A catalog that fits the catalog page is stored whole. A bigger
+            // one goes out in segments, only the changed ones rewritten, and
+            // the catalog page lists them.
+            let whole = match self.catalog.is_segmented() {
+                true => None,
+                false => Some(self.catalog.to_bytes()?),
+            };
+            let catalog_data = match whole {
+                Some(data) if data.len() + Self::MULTI_PAGE_HEADER_FIXED <= file.page_size() => {
+                    data
+                }
+                _ => {
+                    for (first_key, payload) in self.catalog.take_dirty_segments()? {
+                        let pages = Self::write_catalog_segment(
+                            &mut file,
+                            &self.pages,
+                            &payload,
+                            &mut self.allocator,
+                            &mut self.header,
+                            |current| growth.next_size(current, max_blocks),
+                        )?;
+                        self.catalog.add_segment(first_key, pages, payload.len() as u64);
+                    }
+                    self.catalog.segment_directory()?
+                }
+            };
+            let mut catalog_pages = Self::write_multi_page_blob(
+                &mut file,
+                &self.pages,
+                1,
+                &catalog_data,
+                generation,
+                &mut self.allocator,
+                &mut self.header,
+                |current| growth.next_size(current, max_blocks),
+            )?;
+            catalog_pages.extend(self.catalog.segment_pages());
+            self.catalog_overflow_pages = catalog_pages;
+
+            // --- Allocator: serialize with bincode, write multi-page ---
+            let allocator_data = bincode::serialize(&self.allocator)
+                .map_err(|e| CartridgeError::Corruption(format!("allocator serialize: {e}")))?;
+            self.allocator_overflow_pages = Self::write_multi_page_blob(
+                &mut file,
+                &self.pages,
+                2,
+                &allocator_data,
+                generation,
+                &mut self.allocator,
+                &mut self.header,
+                |current| growth.next_size(current, max_blocks),
+            )?;
+
+            // Flag the structures older readers couldn't make sense of
+            self.header.set_feature(FEATURE_SEGMENTED_CATALOG, self.catalog.is_segmented());
+            if self.catalog.has_holes() {
+                self.header.set_feature(FEATURE_SPARSE, true);
+            }
+            if self.catalog.has_inline_data() {
+                self.header.set_feature(FEATURE_INLINE_DATA, true);
+            }
+            self.header.version_minor = self.header.version_minor.max(VERSION_MINOR);
+
+            // Re-write header (total_blocks / free_blocks may have changed from overflow)
+            self.header.set_generation(generation);
+            file.write_header(&self.header)?;
+
+            Self::write_dirty_pages(&mut file, &self.pages, &self.dirty_pages)
+        })();
+
+        match result {
+            Ok(()) if journaled => file.commit_batch()?,
+            Ok(()) => file.sync_or_defer()?,
+            Err(e) => {
+                file.abort_batch();
+                // The segment layout may be half updated; store it all anew
+                self.catalog.forget_segments();
+                return Err(e);
+            }
+        }
+        drop(file);
+        if durable && on_disk {
+            self.note_sync();
+        }
+        self.dirty_pages.lock().clear();
+        self.metadata_dirty = false;
+        self.disk_stamp = stamp(&self.header);
+        self.emit(|| CartridgeEvent::FlushFinished { dirty_pages: dirty_count });
+        telemetry::pages_flushed(dirty_count);
+        #[cfg(feature = "metrics")]
+        telemetry::space(
+            self.header.total_blocks,
+            self.header.free_blocks,
+            self.allocator.fragmentation_score(),
+        );
+
+        // Post-flush assertion: detect dud cart (empty catalog in a non-empty file)
+        let entry_count = self.catalog.len();
+        let total = self.header.total_blocks;
+        if entry_count == 0 && total > MIN_BLOCKS as u64 {
+            tracing::error!(
+                "CRITICAL: flush produced empty catalog with {} total blocks ({} bytes). \
+                 This cartridge may be a dud.",
+                total,
+                total * self.page_size() as u64,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Write dirty content pages in page order, one call per contiguous run
+    ///
+    /// Runs are capped at [`FLUSH_RUN_PAGES`] so a huge dirty set doesn't
+    /// need a second copy of itself in memory.
+    fn write_dirty_pages(
+        file: &mut CartridgeFile,
+        pages: &Mutex<PageCache>,
+        dirty_pages: &Mutex<std::collections::HashSet<u64>>,
+    ) -> Result<()> {
+        let pages = pages.lock();
+        let mut dirty: Vec<u64> = dirty_pages
+            .lock()
+            .iter()
+            .copied()
+            .filter(|id| pages.contains_key(id))
+            .collect();
+        dirty.sort_unstable();
+
+        let mut run: Vec<u8> = Vec::new();
+        let mut run_start = 0;
+        let mut run_len = 0u64;
+        for page_id in dirty {
+            if run_len > 0 && (page_id != run_start + run_len || run_len as usize == FLUSH_RUN_PAGES)
+            {
+                file.write_pages_contiguous(run_start, &run)?;
+                run.clear();
+                run_len = 0;
+            }
+            if run_len == 0 {
+                run_start = page_id;
+            }
+            run.extend_from_slice(&pages[&page_id]);
+            run_len += 1;
+        }
+        if run_len > 0 {
+            file.write_pages_contiguous(run_start, &run)?;
+        }
+        Ok(())
+    }
+
+    // =========================================================================
+    // Multi-page blob serialization
+    // =========================================================================
+
+    /// Multi-page blob header discriminator.
+    /// Old format: page starts with 0x7B (`{`) — raw JSON.
+    /// Unstamped format: page starts with 0x00 — multi-page header.
+    /// Current format: page starts with 0x01 — multi-page header carrying
+    /// the flush generation.
+    const MULTI_PAGE_MAGIC: u8 = 0x00;
+    const STAMPED_MAGIC: u8 = 0x01;
+
+    /// Unstamped header size: 1 (magic) + 4 (data_len) + 2 (num_overflow) = 7 bytes.
+    const UNSTAMPED_HEADER_FIXED: usize = 7;
+
+    /// Multi-page header size: 1 (magic) + 4 (data_len) + 2 (num_overflow) +
+    /// 8 (generation) = 15 bytes.
     /// Followed by num_overflow * 8 bytes of overflow page IDs (u64 LE each).
-    const MULTI_PAGE_HEADER_FIXED: usize = 7;
+    const MULTI_PAGE_HEADER_FIXED: usize = 15;
 
     /// Write a blob that may span multiple pages.
     ///
@@ -433,30 +1255,40 @@ impl Cartridge {
     /// allocates overflow pages from the allocator for the remaining data.
     ///
     /// Returns the list of overflow page IDs allocated (empty if single-page).
+    /// `generation` is stamped into the primary page so `open` can tell
+    /// whether the header, catalog and allocator come from the same flush.
+    /// `grow_to` maps the current block count to the next one when the
+    /// container has to grow for the overflow pages.
+    #[allow(clippy::too_many_arguments)]
     fn write_multi_page_blob(
         file: &mut CartridgeFile,
-        pages_cache: &Mutex<std::collections::HashMap<u64, Vec<u8>>>,
+        pages_cache: &Mutex<PageCache>,
         primary_page: u64,
         data: &[u8],
+        generation: u64,
         allocator: &mut HybridAllocator,
         header: &mut Header,
+        grow_to: impl Fn(usize) -> usize,
     ) -> Result<Vec<u64>> {
         // Always write with the multi-page header format so we preserve
         // the exact data length. Bincode data can contain embedded 0x00 bytes,
         // so we can't rely on null-termination for single-page detection.
         //
-        // Primary page layout: [1 magic][4 data_len][2 num_overflow][N*8 page_ids][data_chunk]
+        // Primary page layout:
+        // [1 magic][4 data_len][2 num_overflow][8 generation][N*8 page_ids][data_chunk]
 
-        if data.len() + Self::MULTI_PAGE_HEADER_FIXED <= PAGE_SIZE {
+        let page_size = file.page_size();
+        if data.len() + Self::MULTI_PAGE_HEADER_FIXED <= page_size {
             // Fits in one page with header — no overflow pages needed
-            let mut page = vec![0u8; PAGE_SIZE];
-            page[0] = Self::MULTI_PAGE_MAGIC;
+            let mut page = vec![0u8; page_size];
+            page[0] = Self::STAMPED_MAGIC;
             page[1..5].copy_from_slice(&(data.len() as u32).to_le_bytes());
             page[5..7].copy_from_slice(&0u16.to_le_bytes()); // 0 overflow pages
+            page[7..15].copy_from_slice(&generation.to_le_bytes());
             page[Self::MULTI_PAGE_HEADER_FIXED..Self::MULTI_PAGE_HEADER_FIXED + data.len()]
                 .copy_from_slice(data);
             file.write_page_data(primary_page, &page)?;
-            pages_cache.lock().insert(primary_page, page);
+            pages_cache.lock().insert(primary_page, Arc::new(page));
             return Ok(vec![]);
         }
 
@@ -464,45 +1296,30 @@ impl Cartridge {
         let mut num_overflow: u16 = 1;
         loop {
             let header_size = Self::MULTI_PAGE_HEADER_FIXED + (num_overflow as usize) * 8;
-            let first_chunk = PAGE_SIZE - header_size;
+            let first_chunk = page_size - header_size;
             let remaining = data.len().saturating_sub(first_chunk);
-            let needed = (remaining + PAGE_SIZE - 1) / PAGE_SIZE;
+            let needed = remaining.div_ceil(page_size);
             if needed <= num_overflow as usize {
                 break;
             }
             num_overflow = needed as u16;
         }
 
-        // Allocate overflow pages
-        let overflow_size = (num_overflow as usize) * PAGE_SIZE;
-        // Ensure capacity (auto-grow if needed)
-        while allocator.free_blocks() < num_overflow as usize {
-            // Grow the container
-            let current = header.total_blocks as usize;
-            let new_total = (current * GROW_FACTOR).min(DEFAULT_MAX_BLOCKS);
-            if new_total == current {
-                return Err(CartridgeError::OutOfSpace);
-            }
-            file.extend(new_total)?;
-            header.total_blocks = new_total as u64;
-            allocator.extend_capacity(new_total)?;
-            header.free_blocks = allocator.free_blocks() as u64;
-        }
-
-        let overflow_page_ids = allocator.allocate(overflow_size as u64)?;
-        header.free_blocks = allocator.free_blocks() as u64;
+        let overflow_page_ids =
+            Self::allocate_metadata_pages(file, allocator, header, num_overflow as usize, grow_to)?;
 
         // Build primary page
         let header_size = Self::MULTI_PAGE_HEADER_FIXED + overflow_page_ids.len() * 8;
-        let first_chunk_size = (PAGE_SIZE - header_size).min(data.len());
-        let mut page = vec![0u8; PAGE_SIZE];
+        let first_chunk_size = (page_size - header_size).min(data.len());
+        let mut page = vec![0u8; page_size];
 
-        // Header: magic + data_len + num_overflow + page_ids
-        page[0] = Self::MULTI_PAGE_MAGIC;
+        // Header: magic + data_len + num_overflow + generation + page_ids
+        page[0] = Self::STAMPED_MAGIC;
         page[1..5].copy_from_slice(&(data.len() as u32).to_le_bytes());
         page[5..7].copy_from_slice(&(overflow_page_ids.len() as u16).to_le_bytes());
+        page[7..15].copy_from_slice(&generation.to_le_bytes());
         for (i, &pid) in overflow_page_ids.iter().enumerate() {
-            let off = 7 + i * 8;
+            let off = Self::MULTI_PAGE_HEADER_FIXED + i * 8;
             page[off..off + 8].copy_from_slice(&pid.to_le_bytes());
         }
 
@@ -510,35 +1327,98 @@ impl Cartridge {
         page[header_size..header_size + first_chunk_size]
             .copy_from_slice(&data[..first_chunk_size]);
         file.write_page_data(primary_page, &page)?;
-        pages_cache.lock().insert(primary_page, page);
+        pages_cache.lock().insert(primary_page, Arc::new(page));
 
         // Write overflow pages
         let mut offset = first_chunk_size;
         for &pid in &overflow_page_ids {
-            let mut opage = vec![0u8; PAGE_SIZE];
-            let chunk = PAGE_SIZE.min(data.len() - offset);
+            let mut opage = vec![0u8; page_size];
+            let chunk = page_size.min(data.len() - offset);
             opage[..chunk].copy_from_slice(&data[offset..offset + chunk]);
             file.write_page_data(pid, &opage)?;
-            pages_cache.lock().insert(pid, opage);
+            pages_cache.lock().insert(pid, Arc::new(opage));
             offset += chunk;
         }
 
         Ok(overflow_page_ids)
     }
 
+    /// Allocate `count` pages for metadata, growing the container if needed
+    fn allocate_metadata_pages(
+        file: &mut CartridgeFile,
+        allocator: &mut HybridAllocator,
+        header: &mut Header,
+        count: usize,
+        grow_to: impl Fn(usize) -> usize,
+    ) -> Result<Vec<u64>> {
+        // Ensure capacity (auto-grow if needed)
+        while allocator.free_blocks() < count {
+            // Grow the container
+            let current = header.total_blocks as usize;
+            let new_total = grow_to(current);
+            if new_total == current {
+                return Err(CartridgeError::OutOfSpace);
+            }
+            file.extend(new_total)?;
+            header.total_blocks = new_total as u64;
+            allocator.extend_capacity(new_total)?;
+            header.free_blocks = allocator.free_blocks() as u64;
+        }
+
+        let page_ids = allocator.allocate((count * file.page_size()) as u64)?;
+        header.free_blocks = allocator.free_blocks() as u64;
+        Ok(page_ids)
+    }
+
+    /// Write one catalog segment to freshly allocated pages
+    ///
+    /// Returns the pages, in payload order; an empty payload takes none.
+    fn write_catalog_segment(
+        file: &mut CartridgeFile,
+        pages_cache: &Mutex<PageCache>,
+        payload: &[u8],
+        allocator: &mut HybridAllocator,
+        header: &mut Header,
+        grow_to: impl Fn(usize) -> usize,
+    ) -> Result<Vec<u64>> {
+        if payload.is_empty() {
+            return Ok(vec![]);
+        }
+        let page_size = file.page_size();
+        let count = payload.len().div_ceil(page_size);
+        let page_ids = Self::allocate_metadata_pages(file, allocator, header, count, grow_to)?;
+        for (&pid, chunk) in page_ids.iter().zip(payload.chunks(page_size)) {
+            let mut page = vec![0u8; page_size];
+            page[..chunk.len()].copy_from_slice(chunk);
+            file.write_page_data(pid, &page)?;
+            pages_cache.lock().insert(pid, Arc::new(page));
+        }
+        Ok(page_ids)
+    }
+
     /// Read a multi-page blob from disk.
     ///
-    /// Detects old single-page format (starts with `{`) vs new multi-page
-    /// format (starts with 0x00). Returns the reassembled data and overflow
-    /// page IDs (empty for single-page).
+    /// Detects old single-page format (starts with `{`) vs multi-page
+    /// format (starts with 0x00, or 0x01 when stamped). Returns the
+    /// reassembled data, overflow page IDs (empty for single-page) and the
+    /// flush generation (0 when the blob isn't stamped).
     fn read_multi_page_blob(
-        file: &mut CartridgeFile,
+        page_size: usize,
+        read_page: &mut dyn FnMut(u64) -> Result<Vec<u8>>,
         primary_page: u64,
-    ) -> Result<(Vec<u8>, Vec<u64>)> {
-        let page_data = file.read_page_data(primary_page)?;
+    ) -> Result<(Vec<u8>, Vec<u64>, u64)> {
+        let page_data = read_page(primary_page)?;
 
-        if page_data[0] == Self::MULTI_PAGE_MAGIC && page_data.len() >= Self::MULTI_PAGE_HEADER_FIXED {
-            // New multi-page format
+        let stamped = page_data[0] == Self::STAMPED_MAGIC;
+        if (stamped || page_data[0] == Self::MULTI_PAGE_MAGIC)
+            && page_data.len() >= Self::MULTI_PAGE_HEADER_FIXED
+        {
+            let (fixed, generation) = if stamped {
+                let generation = u64::from_le_bytes(page_data[7..15].try_into().unwrap());
+                (Self::MULTI_PAGE_HEADER_FIXED, generation)
+            } else {
+                (Self::UNSTAMPED_HEADER_FIXED, 0)
+            };
             let data_len = u32::from_le_bytes([
                 page_data[1], page_data[2], page_data[3], page_data[4],
             ]) as usize;
@@ -549,7 +1429,7 @@ impl Cartridge {
             // Read overflow page IDs
             let mut overflow_pages = Vec::with_capacity(num_overflow);
             for i in 0..num_overflow {
-                let off = 7 + i * 8;
+                let off = fixed + i * 8;
                 let pid = u64::from_le_bytes([
                     page_data[off], page_data[off + 1], page_data[off + 2], page_data[off + 3],
                     page_data[off + 4], page_data[off + 5], page_data[off + 6], page_data[off + 7],
@@ -557,37 +1437,67 @@ impl Cartridge {
                 overflow_pages.push(pid);
             }
 
-            let header_size = Self::MULTI_PAGE_HEADER_FIXED + num_overflow * 8;
-            let first_chunk_size = (PAGE_SIZE - header_size).min(data_len);
+            let header_size = fixed + num_overflow * 8;
+            let first_chunk_size = (page_size - header_size).min(data_len);
 
             let mut data = Vec::with_capacity(data_len);
             data.extend_from_slice(&page_data[header_size..header_size + first_chunk_size]);
 
             // Read overflow pages
             for &pid in &overflow_pages {
-                let opage = file.read_page_data(pid)?;
+                let opage = read_page(pid)?;
                 let remaining = data_len - data.len();
-                let chunk = PAGE_SIZE.min(remaining);
+                let chunk = page_size.min(remaining);
                 data.extend_from_slice(&opage[..chunk]);
             }
 
-            Ok((data, overflow_pages))
+            Ok((data, overflow_pages, generation))
         } else {
             // Old single-page format: raw JSON terminated by null or end of page
-            let end = page_data.iter().position(|&b| b == 0).unwrap_or(PAGE_SIZE);
-            Ok((page_data[..end].to_vec(), vec![]))
+            let end = page_data.iter().position(|&b| b == 0).unwrap_or(page_data.len());
+            Ok((page_data[..end].to_vec(), vec![], 0))
         }
     }
 
-    /// Load catalog state from disk (supports multi-page, bincode + legacy JSON)
-    fn load_catalog_multi(
+    /// Load catalog state from disk (whole or segmented, bincode + legacy JSON)
+    ///
+    /// A segmented catalog is read in full, every segment at once. Also
+    /// returns the catalog's overflow pages and flush generation.
+    pub(crate) fn load_catalog_multi(
         file: &mut CartridgeFile,
         root_page: u64,
-    ) -> Result<(Catalog, Vec<u64>)> {
-        let (data, overflow_pages) = Self::read_multi_page_blob(file, 1)?;
+    ) -> Result<(Catalog, Vec<u64>, u64)> {
+        let page_size = file.page_size();
+        Self::load_catalog_from(page_size, &mut |pid| file.read_page_data(pid), root_page)
+    }
+
+    /// [`load_catalog_multi`](Self::load_catalog_multi) over pages from
+    /// `read_page` instead of the file
+    fn load_catalog_from(
+        page_size: usize,
+        read_page: &mut dyn FnMut(u64) -> Result<Vec<u8>>,
+        root_page: u64,
+    ) -> Result<(Catalog, Vec<u64>, u64)> {
+        let (data, overflow_pages, generation) =
+            Self::read_multi_page_blob(page_size, read_page, 1)?;
 
         if data.is_empty() {
-            return Ok((Catalog::new(root_page), vec![]));
+            return Ok((Catalog::new(root_page), vec![], generation));
+        }
+
+        if let Some(directory) = Catalog::parse_segment_directory(&data) {
+            let (root_page, segments) = directory?;
+            let mut pages = overflow_pages;
+            let mut loaded = Vec::with_capacity(segments.len());
+            for segment in segments {
+                let mut payload = Vec::with_capacity(segment.pages.len() * page_size);
+                for &pid in &segment.pages {
+                    payload.extend_from_slice(&read_page(pid)?);
+                }
+                pages.extend_from_slice(&segment.pages);
+                loaded.push((segment, payload));
+            }
+            return Ok((Catalog::from_segments(root_page, loaded)?, pages, generation));
         }
 
         // Try bincode first (new format), fall back to legacy JSON
@@ -603,22 +1513,34 @@ impl Cartridge {
             Catalog::from_bytes(&data)?
         };
 
-        Ok((catalog, overflow_pages))
+        Ok((catalog, overflow_pages, generation))
     }
 
     /// Load allocator state from disk (supports multi-page, bincode + legacy JSON)
-    fn load_allocator_multi(
+    ///
+    /// Also returns the allocator's overflow pages and flush generation.
+    pub(crate) fn load_allocator_multi(
         file: &mut CartridgeFile,
         total_blocks: usize,
-    ) -> Result<(HybridAllocator, Vec<u64>)> {
-        let (data, overflow_pages) = Self::read_multi_page_blob(file, 2)?;
+    ) -> Result<(HybridAllocator, Vec<u64>, u64)> {
+        let page_size = file.page_size();
+        Self::load_allocator_from(page_size, &mut |pid| file.read_page_data(pid), total_blocks)
+    }
 
-        if data.is_empty() {
-            return Ok((HybridAllocator::new(total_blocks), vec![]));
-        }
+    /// [`load_allocator_multi`](Self::load_allocator_multi) over pages from
+    /// `read_page` instead of the file
+    fn load_allocator_from(
+        page_size: usize,
+        read_page: &mut dyn FnMut(u64) -> Result<Vec<u8>>,
+        total_blocks: usize,
+    ) -> Result<(HybridAllocator, Vec<u64>, u64)> {
+        let (data, overflow_pages, generation) =
+            Self::read_multi_page_blob(page_size, read_page, 2)?;
 
         // Try bincode first (new format), fall back to legacy JSON
-        let allocator = if data.first() == Some(&b'{') {
+        let mut allocator = if data.is_empty() {
+            HybridAllocator::new(total_blocks)
+        } else if data.first() == Some(&b'{') {
             serde_json::from_slice(&data)
                 .map_err(|e| CartridgeError::Corruption(
                     format!("Corrupted legacy allocator: {}", e)
@@ -629,13 +1551,60 @@ impl Cartridge {
                     format!("Corrupted allocator: {}", e)
                 ))?
         };
+        allocator.set_block_size(page_size);
+        // Older versions counted each strategy's own allocations only; start
+        // from what the maps show
+        allocator.recalibrate();
+
+        Ok((allocator, overflow_pages, generation))
+    }
+
+    /// Size of every page in this cartridge, in bytes
+    pub fn page_size(&self) -> usize {
+        self.header.page_size()
+    }
+
+    /// Whether this flush should fsync under the sync policy
+    fn sync_due(&self) -> bool {
+        match self.sync_policy {
+            SyncPolicy::Always => true,
+            SyncPolicy::OnClose => false,
+            SyncPolicy::Periodic(interval) => {
+                self.last_sync.is_none_or(|last| last.elapsed() >= interval)
+            }
+        }
+    }
+
+    /// Start the `Periodic` interval over; the clock isn't read otherwise
+    fn note_sync(&mut self) {
+        if let SyncPolicy::Periodic(_) = self.sync_policy {
+            self.last_sync = Some(Instant::now());
+        }
+    }
 
-        Ok((allocator, overflow_pages))
+    /// Flush, then fsync the backing file whatever the sync policy
+    pub fn sync(&mut self) -> Result<()> {
+        self.flush()?;
+        if self.read_only {
+            return Ok(());
+        }
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        let mut file = file.lock();
+        if !file.is_in_memory() {
+            file.sync()?;
+            drop(file);
+            self.note_sync();
+        }
+        Ok(())
     }
 
     /// Close the cartridge, flushing all changes
+    ///
+    /// Always ends with an fsync, whatever the sync policy.
     pub fn close(mut self) -> Result<()> {
-        self.flush()
+        self.sync()
     }
 
     /// Enable audit logging with a shared logger
@@ -648,28 +1617,109 @@ impl Cartridge {
         self.session_id = session_id;
     }
 
+    /// The session operations are currently attributed to
+    pub fn session_id(&self) -> u32 {
+        self.session_id
+    }
+
+    /// Set the actor that later operations are attributed to in the audit log
+    ///
+    /// Name the actor with [`register_actor`](Self::register_actor).
+    pub fn set_actor(&mut self, actor_id: u32) {
+        self.actor_id = actor_id;
+    }
+
+    /// The actor operations are currently attributed to
+    pub fn actor_id(&self) -> u32 {
+        self.actor_id
+    }
+
     /// Set IAM policy for access control
+    ///
+    /// Decisions cached under the previous policy are dropped.
     pub fn set_policy(&mut self, policy: Policy) {
         self.policy = Some(policy);
-        // Initialize policy engine if not already present
-        if self.policy_engine.is_none() {
-            self.policy_engine = Some(Arc::new(Mutex::new(PolicyEngine::new_default())));
+        match &self.policy_engine {
+            Some(engine) => engine.lock().clear_cache(),
+            None => self.policy_engine = Some(Arc::new(Mutex::new(PolicyEngine::new_default()))),
         }
     }
 
-    /// Check if an action on a resource is allowed by the policy
+    /// Size the IAM decision cache: at most `capacity` decisions, each
+    /// kept for at most `ttl`
+    ///
+    /// Replaces the cache, dropping cached decisions and their statistics.
+    /// A capacity of 0 turns caching off. See [`PolicyEngine::with_cache`].
+    pub fn set_policy_cache(&mut self, capacity: usize, ttl: Option<Duration>) {
+        let engine = PolicyEngine::with_cache(capacity, ttl);
+        self.policy_engine = Some(Arc::new(Mutex::new(engine)));
+    }
+
+    /// Hit, miss and eviction counts for the IAM decision cache, or `None`
+    /// if no policy has been set
+    pub fn policy_cache_stats(&self) -> Option<CacheStats> {
+        self.policy_engine.as_ref().map(|engine| engine.lock().cache_stats())
+    }
+
+    /// Set the principal that later access checks are evaluated for
+    pub fn set_principal(&mut self, principal: &str) {
+        self.principal = Some(principal.to_string());
+    }
+
+    /// Go back to evaluating access checks for an anonymous caller
+    pub fn clear_principal(&mut self) {
+        self.principal = None;
+    }
+
+    /// The principal access checks are evaluated for, if any
+    pub fn principal(&self) -> Option<&str> {
+        self.principal.as_deref()
+    }
+
+    /// Tag later requests with where they came from, for IAM conditions on
+    /// `cartridge:Source`
+    pub fn set_request_source(&mut self, source: &str) {
+        self.request_source = Some(source.to_string());
+    }
+
+    /// Context describing a request made now, without content
+    pub fn request_context(&self) -> RequestContext {
+        RequestContext::new(self.session_id).with_source(self.request_source.as_deref())
+    }
+
+    /// Context describing a request that writes `content` to `path`
+    pub(crate) fn write_context(&self, path: &str, content: &[u8]) -> RequestContext {
+        self.request_context()
+            .with_content(content.len() as u64, content_type::from_path(path))
+    }
+
+    /// Check if the current principal may perform an action on a resource
     ///
-    /// Returns `Ok(())` if allowed, `Err` if denied or no policy is set.
+    /// Returns `Ok(())` if allowed or no policy is set, `Err` if denied.
     pub fn check_access(&self, action: &Action, path: &str) -> Result<()> {
+        self.check_access_with(action, path, &self.request_context())
+    }
+
+    /// Like [`check_access`](Self::check_access), evaluating conditions
+    /// against `context`
+    pub fn check_access_with(
+        &self,
+        action: &Action,
+        path: &str,
+        context: &RequestContext,
+    ) -> Result<()> {
         if let (Some(policy), Some(engine)) = (&self.policy, &self.policy_engine) {
             let mut engine = engine.lock();
-            if engine.evaluate(policy, action, path, None) {
+            let principal = self.principal.as_deref();
+            let values = context.condition_values();
+            if engine.evaluate_as(policy, principal, action, path, Some(&values)) {
                 Ok(())
             } else {
-                Err(CartridgeError::Allocation(format!(
-                    "Access denied: {:?} on {}",
-                    action, path
-                )))
+                telemetry::policy_denied();
+                Err(CartridgeError::AccessDenied {
+                    action: action.clone(),
+                    path: path.to_string(),
+                })
             }
         } else {
             // No policy set - allow all operations (permissive by default)
@@ -686,12 +1736,13 @@ impl Cartridge {
     /// # Examples
     ///
     /// ```no_run
-    /// use cartridge_core::Cartridge;
-    /// use cartridge_core::encryption::EncryptionConfig;
+    /// use cartridge_rs::core::Cartridge;
+    /// use cartridge_rs::core::encryption::EncryptionConfig;
     ///
     /// let mut cart = Cartridge::create("data", "My Data")?;
     /// let key = EncryptionConfig::generate_key();
     /// cart.enable_encryption(&key)?;
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
     pub fn enable_encryption(&mut self, key: &[u8; 32]) -> Result<()> {
         self.encryption_config = Some(EncryptionConfig::new(*key));
@@ -715,9 +1766,96 @@ impl Cartridge {
             .unwrap_or(false)
     }
 
-    /// Get the encryption configuration (if set)
-    pub(crate) fn encryption_config(&self) -> Option<&EncryptionConfig> {
-        self.encryption_config.as_ref()
+    /// Check if the whole cartridge is encrypted at rest
+    ///
+    /// This is independent of [`Cartridge::enable_encryption`], which only
+    /// encrypts the contents of newly written files.
+    pub fn is_encrypted_at_rest(&self) -> bool {
+        self.header.has_feature(FEATURE_ENCRYPTED)
+    }
+
+    /// Write a file encrypted with a key of its own, creating it or
+    /// replacing its content
+    ///
+    /// The content is sealed with AES-256-GCM under a fresh nonce, kept in
+    /// the file's metadata; the key itself is never stored. Plain reads of
+    /// the file then fail with [`CartridgeError::EncryptedFile`]: read it
+    /// back with [`read_encrypted`](Self::read_encrypted) and the same key.
+    pub fn write_encrypted(
+        &mut self,
+        path: &str,
+        content: &[u8],
+        key: &EncryptionKey,
+    ) -> Result<()> {
+        if self.exists(path)? {
+            self.replace_content(path, content, Some(key))
+        } else {
+            self.create_sealed(path, content, Some(key))
+        }
+    }
+
+    /// Read a file written with [`write_encrypted`](Self::write_encrypted),
+    /// following symlinks
+    ///
+    /// Fails with [`CartridgeError::DecryptionFailed`] if `key` isn't the
+    /// one the file was written with, and with
+    /// [`CartridgeError::FileNotEncrypted`] for files without a key of
+    /// their own.
+    pub fn read_encrypted(&self, path: &str, key: &EncryptionKey) -> Result<Vec<u8>> {
+        let path = &normalize_path(path)?;
+        // Check IAM policy
+        self.check_access(&Action::Read, path)?;
+
+        match self.follow(path)? {
+            Some((target, _)) if target != *path => self.read_unsealed(&target, Some(key)),
+            _ => self.read_unsealed(path, Some(key)),
+        }
+    }
+
+    /// Re-encrypt a file written with [`write_encrypted`](Self::write_encrypted)
+    /// under a new key
+    ///
+    /// The new ciphertext is stored before the old is released, so an
+    /// error part way leaves the file readable with `old_key`.
+    pub fn reencrypt(
+        &mut self,
+        path: &str,
+        old_key: &EncryptionKey,
+        new_key: &EncryptionKey,
+    ) -> Result<()> {
+        let content = self.read_encrypted(path, old_key)?;
+        self.replace_content(path, &content, Some(new_key))
+    }
+
+    /// Encrypt `content` for storage: with `file_key` if given, otherwise
+    /// with the content key if encryption is enabled
+    fn seal(&self, content: &[u8], file_key: Option<&EncryptionKey>) -> Result<Sealed> {
+        if let Some(key) = file_key {
+            let (nonce, content) = encryption::encrypt_detached(content, key)?;
+            return Ok(Sealed {
+                content,
+                encrypted: true,
+                nonce: Some(nonce),
+            });
+        }
+        let (content, encrypted) = match &self.encryption_config {
+            Some(config) => encryption::encrypt_if_enabled(content, config)?,
+            None => (content.to_vec(), false),
+        };
+        Ok(Sealed {
+            content,
+            encrypted,
+            nonce: None,
+        })
+    }
+
+    /// How paths compare, as fixed when the cartridge was created
+    pub fn case_sensitivity(&self) -> CaseSensitivity {
+        if self.header.has_feature(FEATURE_CASE_INSENSITIVE) {
+            CaseSensitivity::Insensitive
+        } else {
+            CaseSensitivity::Exact
+        }
     }
 
     /// Clear the IAM policy evaluation cache
@@ -769,109 +1907,255 @@ impl Cartridge {
     /// Create a snapshot of the current cartridge state
     ///
     /// Returns the snapshot ID
+    #[cfg(not(feature = "no-fs"))]
     pub fn create_snapshot(
         &self,
         name: String,
         description: String,
         snapshot_dir: &std::path::Path,
     ) -> Result<u64> {
-        use crate::snapshot::SnapshotManager;
+        self.snapshot_into(None, name, description, snapshot_dir)
+    }
+
+    /// Create a snapshot that stores only the pages changed since snapshot
+    /// `parent_id`
+    ///
+    /// Restoring it applies the parent chain; see
+    /// [`SnapshotManager::restore_snapshot`](crate::snapshot::SnapshotManager::restore_snapshot).
+    #[cfg(not(feature = "no-fs"))]
+    pub fn create_snapshot_incremental(
+        &self,
+        parent_id: u64,
+        name: String,
+        description: String,
+        snapshot_dir: &std::path::Path,
+    ) -> Result<u64> {
+        self.snapshot_into(Some(parent_id), name, description, snapshot_dir)
+    }
 
-        let mut manager = SnapshotManager::new(snapshot_dir)?;
+    /// Shared body of the snapshot constructors: a full snapshot, or an
+    /// incremental one against `parent_id`
+    #[cfg(not(feature = "no-fs"))]
+    fn snapshot_into(
+        &self,
+        parent_id: Option<u64>,
+        name: String,
+        description: String,
+        snapshot_dir: &std::path::Path,
+    ) -> Result<u64> {
+        let mut manager = self.snapshot_manager(snapshot_dir)?;
 
         // Get cartridge path (if disk-backed)
         let parent_path = self
             .file
             .as_ref()
-            .map(|f| f.lock().path().to_path_buf())
+            .map(|f| f.lock())
+            .filter(|f| !f.is_in_memory())
+            .map(|f| f.path().to_path_buf())
             .unwrap_or_else(|| std::path::PathBuf::from("memory"));
 
-        let pages = self.pages.lock();
-        let snapshot_id = manager.create_snapshot(
-            name,
-            description,
-            parent_path,
-            self.header.clone(),
-            &*pages,
-        )?;
+        let pages = self.snapshot_pages()?;
+        let snapshot_id = match parent_id {
+            Some(parent_id) => manager.create_snapshot_incremental(
+                parent_id,
+                name,
+                description,
+                parent_path,
+                self.header,
+                &pages,
+            )?,
+            None => {
+                manager.create_snapshot(name, description, parent_path, self.header, &pages)?
+            }
+        };
+        manager.save_catalog(snapshot_id, &self.catalog)?;
 
         Ok(snapshot_id)
     }
 
-    /// Restore from a snapshot
+    /// Every page a snapshot needs: the cached pages, plus any allocated
+    /// page that is only on disk
     ///
-    /// Replaces current pages with snapshot data
-    pub fn restore_snapshot(
-        &mut self,
-        snapshot_id: u64,
-        snapshot_dir: &std::path::Path,
-    ) -> Result<()> {
-        use crate::snapshot::SnapshotManager;
-
-        let mut manager = SnapshotManager::new(snapshot_dir)?;
-
+    /// Without the disk pages a snapshot of a freshly opened cartridge
+    /// would hold little more than the header.
+    #[cfg(not(feature = "no-fs"))]
+    fn snapshot_pages(&self) -> Result<HashMap<u64, Vec<u8>>> {
+        let mut pages: HashMap<u64, Vec<u8>> = self
+            .pages
+            .lock()
+            .iter()
+            .map(|(&page_id, data)| (page_id, data.to_vec()))
+            .collect();
+        if let Some(file) = &self.file {
+            let mut file = file.lock();
+            for page_id in 1..self.header.total_blocks {
+                if !pages.contains_key(&page_id) && self.allocator.is_allocated(page_id) {
+                    pages.insert(page_id, file.read_page_data(page_id)?);
+                }
+            }
+        }
+        Ok(pages)
+    }
+
+    /// A snapshot manager for `snapshot_dir` that encrypts with this
+    /// cartridge's page cipher when it is encrypted at rest
+    #[cfg(not(feature = "no-fs"))]
+    fn snapshot_manager(
+        &self,
+        snapshot_dir: &std::path::Path,
+    ) -> Result<crate::snapshot::SnapshotManager> {
+        let mut manager = crate::snapshot::SnapshotManager::new(snapshot_dir)?;
+        if let Some(cipher) = self.file.as_ref().and_then(|f| f.lock().cipher().cloned()) {
+            manager.set_cipher(cipher);
+        }
+        Ok(manager)
+    }
+
+    /// Paths added, removed and modified since a snapshot was taken
+    ///
+    /// Compares the snapshot's saved catalog with the current one, so no
+    /// content is read. Unflushed changes count.
+    #[cfg(not(feature = "no-fs"))]
+    pub fn diff_since(
+        &self,
+        snapshot_id: u64,
+        snapshot_dir: &std::path::Path,
+    ) -> Result<crate::snapshot::SnapshotDiff> {
+        let manager = self.snapshot_manager(snapshot_dir)?;
+        let before = manager.load_catalog(snapshot_id)?;
+        Ok(crate::snapshot::SnapshotDiff::between(&before, &self.catalog))
+    }
+
+    /// A read-only, in-memory cartridge holding a snapshot's state
+    ///
+    /// Built from the snapshot's header, saved catalog and pages, leaving
+    /// this cartridge untouched. Content pages the snapshot didn't capture
+    /// are read from this cartridge, where they haven't changed since. The
+    /// current IAM policy and principal carry over, so a view reveals no
+    /// more than the live cartridge would. Fails with
+    /// [`CartridgeError::SnapshotCatalogMissing`] for snapshots taken
+    /// before catalogs were saved.
+    #[cfg(not(feature = "no-fs"))]
+    pub fn open_snapshot_view(
+        &self,
+        snapshot_id: u64,
+        snapshot_dir: &std::path::Path,
+    ) -> Result<Cartridge> {
+        let mut manager = self.snapshot_manager(snapshot_dir)?;
+        let metadata = manager.load_snapshot(snapshot_id)?;
+        let mut catalog = manager.load_catalog(snapshot_id)?;
+        let mut pages = manager.restore_snapshot(snapshot_id)?;
+
+        let mut header = metadata.header;
+        // As in restore_snapshot, the reserved area isn't in the metadata
+        header.reserved = self.header.reserved;
+        if header.has_feature(FEATURE_CASE_INSENSITIVE) {
+            catalog.set_case_insensitive();
+        }
+
+        for (path, file) in catalog.list_prefix("")? {
+            for &block in file.blocks.iter().filter(|&&block| block != HOLE_BLOCK) {
+                if let std::collections::hash_map::Entry::Vacant(slot) = pages.entry(block) {
+                    slot.insert(self.load_page(&path, block)?);
+                }
+            }
+        }
+
+        let mut view = Cartridge::new(header.total_blocks.max(3) as usize);
+        view.header = header;
+        view.catalog = catalog;
+        view.pages = Arc::new(Mutex::new(
+            pages.into_iter().map(|(page_id, data)| (page_id, Arc::new(data))).collect(),
+        ));
+        view.read_only = true;
+        view.metadata_dirty = false;
+        if let Some(policy) = &self.policy {
+            view.set_policy(policy.clone());
+        }
+        view.principal = self.principal.clone();
+        view.request_source = self.request_source.clone();
+        Ok(view)
+    }
+
+    /// Store the snapshot retention policy in the manifest, or clear it
+    pub fn set_snapshot_retention(&mut self, policy: Option<RetentionPolicy>) -> Result<()> {
+        self.update_manifest(|manifest| manifest.snapshot_retention = policy)
+    }
+
+    /// The snapshot retention policy stored in the manifest, if any
+    pub fn snapshot_retention(&self) -> Result<Option<RetentionPolicy>> {
+        Ok(self.read_manifest()?.snapshot_retention)
+    }
+
+    /// Apply the manifest's retention policy to the snapshots in
+    /// `snapshot_dir`, returning the ids deleted
+    ///
+    /// Deletes nothing when no policy is set.
+    #[cfg(not(feature = "no-fs"))]
+    pub fn apply_snapshot_retention(&self, snapshot_dir: &std::path::Path) -> Result<Vec<u64>> {
+        let Some(policy) = self.snapshot_retention()? else {
+            return Ok(Vec::new());
+        };
+        crate::snapshot::SnapshotManager::new(snapshot_dir)?.apply_retention(&policy)
+    }
+
+    /// Restore from a snapshot
+    ///
+    /// Replaces current pages with snapshot data. Nothing is written to disk
+    /// until the next [`flush`](Self::flush), which writes the restored
+    /// pages through the journal like any other change; if the snapshot
+    /// can't be read or parsed, the cartridge is left as it was.
+    #[cfg(not(feature = "no-fs"))]
+    pub fn restore_snapshot(
+        &mut self,
+        snapshot_id: u64,
+        snapshot_dir: &std::path::Path,
+    ) -> Result<()> {
+        self.ensure_writable()?;
+        let mut manager = self.snapshot_manager(snapshot_dir)?;
+
         // Load snapshot metadata from disk
         let metadata = manager.load_snapshot(snapshot_id)?;
 
         // Restore pages
         let restored_pages = manager.restore_snapshot(snapshot_id)?;
 
-        // Replace current state
-        *self.pages.lock() = restored_pages.clone();
-        self.header = metadata.header.clone();
-
-        // Reload catalog and allocator from restored pages (supports multi-page)
-        // We need to read from disk since overflow pages may not be in the map
-        if let Some(ref file_mutex) = self.file {
-            let mut file = file_mutex.lock();
-            let (catalog, cat_overflow) =
-                Self::load_catalog_multi(&mut file, self.header.btree_root_page)?;
-            self.catalog = catalog;
-            self.catalog_overflow_pages = cat_overflow;
-
-            let (mut allocator, alloc_overflow) =
-                Self::load_allocator_multi(&mut file, self.header.total_blocks as usize)?;
-            if !alloc_overflow.is_empty() {
-                let _ = allocator.mark_pages_allocated(&alloc_overflow);
-            }
-            self.allocator = allocator;
-            self.allocator_overflow_pages = alloc_overflow;
-        } else if let Some(catalog_page) = restored_pages.get(&1) {
-            // In-memory only: parse from page data directly
-            let end = catalog_page.iter().position(|&b| b == 0).unwrap_or(PAGE_SIZE);
-            if end > 0 {
-                let data = &catalog_page[..end];
-                self.catalog = if data.first() == Some(&b'{') {
-                    // Legacy JSON
-                    use crate::catalog::btree;
-                    let btree: btree::BTree = serde_json::from_slice(data)
-                        .map_err(|e| CartridgeError::Corruption(
-                            format!("Corrupted legacy catalog in snapshot: {}", e)
-                        ))?;
-                    btree.into_catalog(1)
-                } else {
-                    Catalog::from_bytes(data)?
-                };
-            }
-            if let Some(alloc_page) = restored_pages.get(&2) {
-                let end = alloc_page.iter().position(|&b| b == 0).unwrap_or(PAGE_SIZE);
-                if end > 0 {
-                    let data = &alloc_page[..end];
-                    self.allocator = if data.first() == Some(&b'{') {
-                        serde_json::from_slice(data)
-                            .map_err(|e| CartridgeError::Corruption(
-                                format!("Corrupted legacy allocator in snapshot: {}", e)
-                            ))?
-                    } else {
-                        bincode::deserialize(data)
-                            .map_err(|e| CartridgeError::Corruption(
-                                format!("Corrupted allocator in snapshot: {}", e)
-                            ))?
-                    };
-                }
-            }
+        // Snapshot metadata doesn't store the reserved area, so the feature
+        // flags and other settings kept there come from the live header,
+        // and the flush generation keeps counting up
+        let mut header = metadata.header;
+        header.reserved = self.header.reserved;
+        header.set_generation(self.header.generation());
+
+        // Parse the catalog and allocator (multi-page included) from the
+        // restored pages. Pages the snapshot didn't capture weren't
+        // allocated when it was taken; they come from disk, or are blank
+        // for an in-memory cartridge.
+        let page_size = self.page_size();
+        let mut read_page = |page_id: u64| match restored_pages.get(&page_id) {
+            Some(data) => Ok(data.clone()),
+            None if self.file.is_none() => Ok(vec![0u8; page_size]),
+            None => self.read_disk_page(page_id),
+        };
+        let (catalog, cat_overflow, _) =
+            Self::load_catalog_from(page_size, &mut read_page, header.btree_root_page)?;
+        let (mut allocator, alloc_overflow, _) =
+            Self::load_allocator_from(page_size, &mut read_page, header.total_blocks as usize)?;
+        if !alloc_overflow.is_empty() {
+            let _ = allocator.mark_pages_allocated(&alloc_overflow);
         }
 
+        // Replace current state
+        *self.pages.lock() = restored_pages
+            .into_iter()
+            .map(|(page_id, data)| (page_id, Arc::new(data)))
+            .collect();
+        self.header = header;
+        self.catalog = catalog;
+        self.catalog_overflow_pages = cat_overflow;
+        self.allocator = allocator;
+        self.allocator_overflow_pages = alloc_overflow;
+
         let mut dirty_pages = self.dirty_pages.lock();
         dirty_pages.clear();
 
@@ -880,81 +2164,129 @@ impl Cartridge {
         for &page_id in pages.keys() {
             dirty_pages.insert(page_id);
         }
+        drop(pages);
+        drop(dirty_pages);
 
+        self.catalog.normalize_keys();
+        if self.header.has_feature(FEATURE_CASE_INSENSITIVE) {
+            self.catalog.set_case_insensitive();
+        }
+        self.metadata_dirty = true;
+        self.load_quotas();
+        self.load_dedup_index();
         Ok(())
     }
 
+    /// The audit logger, if one is set
+    pub(crate) fn audit_logger(&self) -> Option<&Arc<AuditLogger>> {
+        self.audit_logger.as_ref()
+    }
+
     /// Log an audit event (internal helper)
-    fn audit_log(&self, operation: Operation, path: &str) {
+    ///
+    /// Reads and writes of the audit trail itself aren't logged.
+    pub(crate) fn audit_log(&self, operation: Operation, path: &str) {
         if let Some(logger) = &self.audit_logger {
-            // Use simple hash of path as file_id for auditing
-            use std::collections::hash_map::DefaultHasher;
-            use std::hash::{Hash, Hasher};
-            let mut hasher = DefaultHasher::new();
-            path.hash(&mut hasher);
-            let file_id = hasher.finish();
-            logger.log_file_op(1, operation, file_id, self.session_id);
+            if audit::is_trail_file(path) {
+                return;
+            }
+            logger.log_path_op(self.actor_id, operation, path, self.session_id);
         }
     }
 
     /// Create a file with content
     pub fn create_file(&mut self, path: &str, content: &[u8]) -> Result<()> {
+        self.create_sealed(path, content, None)
+    }
+
+    /// Create a file, encrypting its content with `file_key` if given
+    fn create_sealed(
+        &mut self,
+        path: &str,
+        content: &[u8],
+        file_key: Option<&EncryptionKey>,
+    ) -> Result<()> {
+        let path = &normalize_path(path)?;
+        self.ensure_writable()?;
+        self.check_protected(path)?;
         // Check IAM policy
-        self.check_access(&Action::Create, &path)?;
+        self.check_access_with(&Action::Create, path, &self.write_context(path, content))?;
 
         // Check if file already exists
-        if self.catalog.get(&path)?.is_some() {
-            return Err(CartridgeError::Allocation(format!(
-                "File already exists: {}",
-                path
-            )));
+        if path == "/" || self.catalog.get(path)?.is_some() {
+            return Err(CartridgeError::AlreadyExists {
+                path: path.to_string(),
+            });
         }
+        self.quotas.check(path, 0, content.len() as u64)?;
 
-        // Encrypt content if encryption is enabled
-        let (final_content, was_encrypted) = if let Some(config) = &self.encryption_config {
-            use crate::encryption::encrypt_if_enabled;
-            encrypt_if_enabled(content, config)?
-        } else {
-            (content.to_vec(), false)
-        };
+        // Encrypt content if encryption is enabled or a file key was given
+        let sealed = self.seal(content, file_key)?;
 
-        // Ensure capacity before allocating (using final content size after encryption)
-        if !final_content.is_empty() {
-            self.ensure_capacity(final_content.len())?;
-        }
+        let inline = self.should_inline(&sealed.content);
+        let mut digest = sealed.digest();
 
-        // Allocate blocks for content
-        let blocks = if final_content.is_empty() {
+        // Allocate blocks and write content to pages (encrypted if enabled)
+        let blocks = if inline {
+            if let Some(digest) = &mut digest {
+                digest.update(&sealed.content);
+            }
             Vec::new()
         } else {
-            self.allocator.allocate(final_content.len() as u64)?
+            self.store_content_hashed(path, &sealed.content, digest.as_mut())?
         };
 
-        // Write content to pages (encrypted if enabled)
-        self.write_content(&blocks, &final_content)?;
-
         // Create metadata (store original size and encryption flag)
         let mut metadata = FileMetadata::new(FileType::File, content.len() as u64, blocks);
-        if was_encrypted {
-            // Store encryption flag and encrypted size in user metadata
-            metadata.user_metadata.insert("encrypted".to_string(), "true".to_string());
-            metadata.user_metadata.insert("encrypted_size".to_string(), final_content.len().to_string());
+        metadata.content_hash = digest.as_ref().map(|d| d.clone().finalize().into());
+        sealed.mark(&mut metadata);
+        if inline {
+            metadata.inline_data = Some(sealed.content);
         }
 
         // Add to catalog
-        self.catalog.insert(path, metadata)?;
+        self.insert_staged(path, metadata)?;
+        self.keep_hasher(path, digest);
+        self.quotas.record(path, 0, content.len() as u64);
+        telemetry::bytes_written(content.len());
 
         // Update header
         self.header.free_blocks = self.allocator.free_blocks() as u64;
 
         // Audit log
         self.audit_log(Operation::Create, path);
+        self.watchers.notify(path, ChangeKind::Created);
 
         Ok(())
     }
 
-    /// Read a file's content
+    /// Read a file's content, following symlinks
+    ///
+    /// Both the link and its target must be readable under the IAM policy.
     pub fn read_file(&self, path: &str) -> Result<Vec<u8>> {
+        let path = &normalize_path(path)?;
+        // Check IAM policy
+        self.check_access(&Action::Read, path)?;
+
+        match self.follow(path)? {
+            Some((target, _)) if target != *path => self.read_file_nofollow(&target),
+            _ => self.read_file_nofollow(path),
+        }
+    }
+
+    /// Read a file's content without following symlinks
+    ///
+    /// Fails if `path` is itself a symlink, like `O_NOFOLLOW`, and with
+    /// [`CartridgeError::EncryptedFile`] if the file was written with
+    /// [`write_encrypted`](Self::write_encrypted).
+    pub fn read_file_nofollow(&self, path: &str) -> Result<Vec<u8>> {
+        self.read_unsealed(path, None)
+    }
+
+    /// Read a file without following symlinks, decrypting it with
+    /// `file_key` if given
+    fn read_unsealed(&self, path: &str, file_key: Option<&EncryptionKey>) -> Result<Vec<u8>> {
+        let path = &normalize_path(path)?;
         // Check IAM policy
         self.check_access(&Action::Read, path)?;
 
@@ -963,14 +2295,30 @@ impl Cartridge {
         let metadata = self
             .catalog
             .get(path)?
-            .ok_or_else(|| CartridgeError::Allocation(format!("File not found: {}", path)))?;
+            .ok_or_else(|| CartridgeError::NotFound {
+                path: path.to_string(),
+            })?;
 
+        if metadata.is_symlink() {
+            return Err(CartridgeError::NotAFile {
+                path: path.to_string(),
+            });
+        }
         if !metadata.is_file() {
-            return Err(CartridgeError::Allocation(format!("Not a file: {}", path)));
+            return Err(CartridgeError::NotAFile {
+                path: path.to_string(),
+            });
         }
 
-        // Check if file was encrypted
-        let was_encrypted = metadata.user_metadata.get("encrypted").map(|v| v == "true").unwrap_or(false);
+        // Check if file was encrypted, and with which key
+        let was_encrypted = metadata.is_encrypted();
+        match (metadata.has_file_key(), file_key) {
+            (true, None) => return Err(CartridgeError::EncryptedFile { path: path.to_string() }),
+            (false, Some(_)) => {
+                return Err(CartridgeError::FileNotEncrypted { path: path.to_string() })
+            }
+            _ => {}
+        }
 
         // If encrypted, we need to read the encrypted size, not the original size
         // If not encrypted, use the metadata size
@@ -985,177 +2333,972 @@ impl Cartridge {
             metadata.size as usize
         };
 
-        // Read content from blocks (this reads the raw data, encrypted or not)
-        let raw_content = self.read_content(&metadata.blocks, read_size)?;
+        // Read content from the catalog or from blocks (raw data, encrypted or not)
+        let raw_content = match metadata.inline_data {
+            Some(data) => data,
+            None => self.read_content(path, &metadata.blocks, read_size)?,
+        };
 
         // Decrypt if needed
-        if was_encrypted {
+        let content = if let Some(key) = file_key {
+            let nonce = metadata.user_metadata.get(FILE_NONCE_KEY).and_then(|hex| parse_nonce(hex));
+            let Some(nonce) = nonce else {
+                return Err(CartridgeError::Corruption(format!("invalid nonce on {}", path)));
+            };
+            encryption::decrypt_detached(&raw_content, &nonce, key)
+                .ok_or_else(|| CartridgeError::DecryptionFailed { path: path.to_string() })?
+        } else if was_encrypted {
             if let Some(config) = &self.encryption_config {
                 use crate::encryption::decrypt_if_encrypted;
-                decrypt_if_encrypted(&raw_content, config, true)
+                decrypt_if_encrypted(&raw_content, config, true)?
             } else {
-                Err(CartridgeError::Allocation(
-                    "File is encrypted but no encryption key is set".to_string()
-                ))
+                return Err(CartridgeError::EncryptionRequired);
             }
         } else {
-            Ok(raw_content)
+            raw_content
+        };
+        telemetry::bytes_read(content.len());
+        Ok(content)
+    }
+
+    /// Verify the on-disk checksum of every file's pages
+    ///
+    /// Reads each block straight from the backing file, bypassing the page
+    /// cache, and returns the paths of files with at least one corrupted page.
+    /// Pages that haven't been flushed yet are skipped. Always returns an empty
+    /// list for in-memory cartridges and for cartridges created without page
+    /// checksums.
+    pub fn verify(&self) -> Result<Vec<String>> {
+        let Some(file) = &self.file else {
+            return Ok(Vec::new());
+        };
+
+        let dirty_pages = self.dirty_pages.lock().clone();
+        let mut corrupted = Vec::new();
+        let mut file = file.lock();
+
+        for (path, metadata) in self.catalog.list_prefix("")? {
+            if !metadata.is_file() {
+                continue;
+            }
+            for block_id in metadata.data_blocks() {
+                if dirty_pages.contains(&block_id) {
+                    continue;
+                }
+                match file.read_page_data(block_id) {
+                    Ok(_) => {}
+                    Err(CartridgeError::ChecksumMismatch { .. }) => {
+                        corrupted.push(path);
+                        break;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
+        Ok(corrupted)
+    }
+
+    /// Verify up to `max_pages` content pages, starting where `start` says
+    ///
+    /// Pages are checked in block order, each read straight from the backing
+    /// file like [`verify`](Self::verify), and failures are reported with
+    /// every file that references the block. Pass the returned
+    /// [`ScrubResult::next`] to continue; `None` starts a new pass. Pages
+    /// written since the last flush are skipped. The block → path map is
+    /// rebuilt from the catalog on each call, so a pass sees files written
+    /// between calls.
+    pub fn scrub(&self, start: Option<ScrubToken>, max_pages: usize) -> Result<ScrubResult> {
+        let mut owners: std::collections::BTreeMap<u64, Vec<String>> =
+            std::collections::BTreeMap::new();
+        for (path, metadata) in self.catalog.list_prefix("")? {
+            if !metadata.is_file() {
+                continue;
+            }
+            for block in metadata.data_blocks() {
+                let paths = owners.entry(block).or_default();
+                if !paths.contains(&path) {
+                    paths.push(path.clone());
+                }
+            }
+        }
+
+        let mut result = ScrubResult::default();
+        let from = start.map_or(0, |token| token.next_block);
+        let mut pending = owners.range(from..).peekable();
+        let Some(file) = &self.file else {
+            return Ok(result);
+        };
+        let dirty_pages = self.dirty_pages.lock().clone();
+        let mut file = file.lock();
+        let mut budget = max_pages;
+        while budget > 0 {
+            let Some((&block, paths)) = pending.next() else {
+                break;
+            };
+            budget -= 1;
+            if dirty_pages.contains(&block) {
+                result.pages_skipped += 1;
+                continue;
+            }
+            result.pages_checked += 1;
+            if let Err(e) = file.read_page_data(block) {
+                result.errors.push(ScrubError {
+                    block,
+                    paths: paths.clone(),
+                    error: e.to_string(),
+                });
+            }
+        }
+        result.next = pending.peek().map(|(&block, _)| ScrubToken { next_block: block });
+        Ok(result)
+    }
+
+    /// Check catalog, allocator and header consistency
+    ///
+    /// Walks every file's block list and cross-references it against the
+    /// allocator, looking for blocks referenced but not allocated, blocks
+    /// referenced twice, allocated blocks nothing references, and header
+    /// counts that disagree with the allocator. Nothing is modified.
+    pub fn check(&self) -> Result<ConsistencyReport> {
+        let total_blocks = self.allocator.total_blocks() as u64;
+        let mut report = ConsistencyReport {
+            header_total_blocks: self.header.total_blocks,
+            allocator_total_blocks: total_blocks,
+            header_free_blocks: self.header.free_blocks,
+            allocator_free_blocks: self.allocator.free_blocks() as u64,
+            bitmap_free_blocks: self.allocator.count_free() as u64,
+            ..Default::default()
+        };
+
+        let mut owners: std::collections::BTreeMap<u64, Vec<String>> =
+            std::collections::BTreeMap::new();
+        for (path, metadata) in self.catalog.list_prefix("")? {
+            for block in metadata.data_blocks() {
+                if block >= total_blocks {
+                    report.out_of_range_refs.push(BlockRef {
+                        path: path.clone(),
+                        block,
+                    });
+                    continue;
+                }
+                if !self.allocator.is_allocated(block) {
+                    report.unallocated_refs.push(BlockRef {
+                        path: path.clone(),
+                        block,
+                    });
+                }
+                owners.entry(block).or_default().push(path.clone());
+            }
+        }
+
+        let refs = self.counted_refs();
+        for (&block, paths) in &owners {
+            // Deduplicated blocks are meant to be shared, as often as counted
+            let counted = refs.refs(block).unwrap_or(1) as usize;
+            if paths.len() > 1 && paths.len() != counted {
+                report.shared_blocks.push(SharedBlock {
+                    block,
+                    paths: paths.clone(),
+                });
+            }
+        }
+
+        let reserved = self.reserved_blocks();
+        for block in 0..total_blocks {
+            if self.allocator.is_allocated(block)
+                && !owners.contains_key(&block)
+                && !reserved.contains(&block)
+            {
+                report.orphaned_blocks.push(block);
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Rebuild the allocator from the catalog and fix the header counts
+    ///
+    /// The catalog is treated as the source of truth: every block it
+    /// references (plus the header, catalog and allocator pages) is marked
+    /// allocated and everything else is freed, and shared block reference
+    /// counts are recounted from it. Out-of-range blocks can't be fixed
+    /// this way and are still reported afterwards.
+    ///
+    /// Returns the report from re-checking the repaired cartridge. The result
+    /// is flushed to disk for disk-backed cartridges.
+    pub fn repair(&mut self) -> Result<ConsistencyReport> {
+        self.ensure_writable()?;
+        // The allocator's capacity tracks the file through growth and shrink
+        let total_blocks = self.allocator.total_blocks();
+        let before = self.header.free_blocks;
+        self.rebuild_allocator(total_blocks)?;
+        self.recount_block_refs();
+        tracing::info!(
+            "Rebuilt allocator from catalog: free blocks {} -> {}",
+            before,
+            self.header.free_blocks
+        );
+
+        self.flush()?;
+        self.check()
+    }
+
+    /// Replace the allocator with a fresh one sized to `total_blocks` that
+    /// marks exactly the reserved and catalog-referenced blocks as allocated
+    ///
+    /// Leaves free space as freshly coalesced extents. Blocks at or beyond
+    /// `total_blocks` are ignored.
+    fn rebuild_allocator(&mut self, total_blocks: usize) -> Result<()> {
+        let mut in_use = self.reserved_blocks();
+        for (_, metadata) in self.catalog.list_prefix("")? {
+            in_use.extend(metadata.data_blocks().filter(|&b| b < total_blocks as u64));
         }
+
+        let mut allocator = HybridAllocator::new(total_blocks);
+        allocator.set_block_size(self.page_size());
+        allocator.mark_pages_allocated(&in_use.into_iter().collect::<Vec<_>>())?;
+        allocator.recalibrate();
+
+        self.allocator = allocator;
+        self.header.total_blocks = total_blocks as u64;
+        self.header.free_blocks = self.allocator.free_blocks() as u64;
+        self.metadata_dirty = true;
+        Ok(())
+    }
+
+    /// Blocks in use by the archive itself rather than by any file
+    fn reserved_blocks(&self) -> std::collections::BTreeSet<u64> {
+        let mut reserved: std::collections::BTreeSet<u64> = (0..MIN_BLOCKS as u64).collect();
+        reserved.extend(&self.catalog_overflow_pages);
+        reserved.extend(&self.allocator_overflow_pages);
+        reserved
     }
 
     /// Write content to existing file (replace)
+    ///
+    /// The new content is stored in fresh blocks and the old ones are freed
+    /// only once the catalog points at the new ones, so an error part way
+    /// leaves the file as it was. Needs room for both copies at once.
     pub fn write_file(&mut self, path: &str, content: &[u8]) -> Result<()> {
+        self.replace_content(path, content, None)
+    }
+
+    /// Replace an existing file's content so readers see either all of the
+    /// old content or all of the new
+    ///
+    /// The new content goes to freshly allocated blocks while the old ones
+    /// are still in use, the catalog entry is switched to the new block list
+    /// in one step, and only then are the old blocks freed. An error part way
+    /// through, such as running out of space, leaves the file as it was, and
+    /// no page of the old content is overwritten before the switch is
+    /// flushed. Needs room for both copies at once.
+    ///
+    /// [`write_file`](Self::write_file) now gives the same guarantees; this
+    /// is kept for callers that ask for them by name.
+    pub fn write_file_atomic(&mut self, path: &str, content: &[u8]) -> Result<()> {
+        self.replace_content(path, content, None)
+    }
+
+    /// Shared body of [`write_file`](Self::write_file),
+    /// [`write_file_atomic`](Self::write_file_atomic) and
+    /// [`write_encrypted`](Self::write_encrypted)
+    fn replace_content(
+        &mut self,
+        path: &str,
+        content: &[u8],
+        file_key: Option<&EncryptionKey>,
+    ) -> Result<()> {
+        let path = &normalize_path(path)?;
+        self.ensure_writable()?;
+        self.check_protected(path)?;
         // Check IAM policy
-        self.check_access(&Action::Write, path)?;
+        self.check_access_with(&Action::Write, path, &self.write_context(path, content))?;
 
         let mut metadata = self
             .catalog
             .get(path)?
-            .ok_or_else(|| CartridgeError::Allocation(format!("File not found: {}", path)))?;
+            .ok_or_else(|| CartridgeError::NotFound {
+                path: path.to_string(),
+            })?;
 
         if !metadata.is_file() {
-            return Err(CartridgeError::Allocation(format!("Not a file: {}", path)));
+            return Err(CartridgeError::NotAFile {
+                path: path.to_string(),
+            });
         }
+        let old_size = metadata.size;
+        self.quotas.check(path, old_size, content.len() as u64)?;
 
-        // Encrypt content if encryption is enabled
-        let (final_content, was_encrypted) = if let Some(config) = &self.encryption_config {
-            use crate::encryption::encrypt_if_enabled;
-            encrypt_if_enabled(content, config)?
-        } else {
-            (content.to_vec(), false)
-        };
-
-        // Ensure capacity before allocating (using final content size after encryption)
-        if !final_content.is_empty() {
-            self.ensure_capacity(final_content.len())?;
-        }
+        // Encrypt content if encryption is enabled or a file key was given
+        let sealed = self.seal(content, file_key)?;
 
-        // Free old blocks
-        if !metadata.blocks.is_empty() {
-            self.allocator.free(&metadata.blocks)?;
-        }
+        let inline = self.should_inline(&sealed.content);
+        let mut digest = sealed.digest();
 
-        // Allocate new blocks
-        let new_blocks = if final_content.is_empty() {
+        // Allocate new blocks and write new content (encrypted if enabled);
+        // the old blocks stay in use until the catalog switches over
+        let new_blocks = if inline {
+            if let Some(digest) = &mut digest {
+                digest.update(&sealed.content);
+            }
             Vec::new()
         } else {
-            self.allocator.allocate(final_content.len() as u64)?
+            self.store_content_hashed(path, &sealed.content, digest.as_mut())?
         };
 
-        // Write new content (encrypted if enabled)
-        self.write_content(&new_blocks, &final_content)?;
-
         // Update metadata (store original size and encryption flag)
         metadata.size = content.len() as u64;
-        metadata.blocks = new_blocks;
+        metadata.content_hash = digest.as_ref().map(|d| d.clone().finalize().into());
+        let old_blocks = std::mem::replace(&mut metadata.blocks, new_blocks);
         metadata.touch();
-        if was_encrypted {
-            metadata.user_metadata.insert("encrypted".to_string(), "true".to_string());
-            metadata.user_metadata.insert("encrypted_size".to_string(), final_content.len().to_string());
-        } else {
-            metadata.user_metadata.remove("encrypted");
-            metadata.user_metadata.remove("encrypted_size");
-        }
+        sealed.mark(&mut metadata);
+        metadata.inline_data = inline.then_some(sealed.content);
 
         // Update catalog
-        self.catalog.insert(path, metadata)?;
+        self.insert_staged(path, metadata)?;
+        self.keep_hasher(path, digest);
+        self.release_blocks(&old_blocks)?;
+        self.quotas.record(path, old_size, content.len() as u64);
+        telemetry::bytes_written(content.len());
 
         // Update header
         self.header.free_blocks = self.allocator.free_blocks() as u64;
 
         // Audit log
         self.audit_log(Operation::Update, path);
+        self.watchers.notify(path, ChangeKind::Modified);
 
         Ok(())
     }
 
     /// Append content to existing file
+    ///
+    /// Only the tail is touched: the free space in the last block is filled
+    /// and new blocks are allocated for the rest, leaving the other blocks
+    /// as they are. Inline and encrypted files, and files written while
+    /// encryption is enabled, are small or opaque and are rewritten whole.
     pub fn append_file(&mut self, path: &str, content: &[u8]) -> Result<()> {
-        // Audit log (append is an update operation)
-        self.audit_log(Operation::Update, path);
-        let mut existing = self.read_file(path)?;
-        existing.extend_from_slice(content);
-        self.write_file(path, &existing)
-    }
-
-    /// Delete a file
-    pub fn delete_file(&mut self, path: &str) -> Result<()> {
+        let path = &normalize_path(path)?;
+        self.ensure_writable()?;
+        self.check_protected(path)?;
         // Check IAM policy
-        self.check_access(&Action::Delete, path)?;
+        self.check_access_with(&Action::Write, path, &self.write_context(path, content))?;
 
-        let metadata = self
-            .catalog
-            .delete(path)?
-            .ok_or_else(|| CartridgeError::Allocation(format!("File not found: {}", path)))?;
-
-        // Free blocks
-        if !metadata.blocks.is_empty() {
-            self.allocator.free(&metadata.blocks)?;
-        }
-
-        // Update header
-        self.header.free_blocks = self.allocator.free_blocks() as u64;
+        let metadata = self.file_for_update(path)?;
+        let size = metadata.size;
+        self.update_range(path, metadata, size, content)
+    }
 
-        // Audit log
-        self.audit_log(Operation::Delete, path);
+    /// Overwrite `data.len()` bytes of a file starting at `offset`
+    ///
+    /// Only the pages the range touches are rewritten. Writing past the end
+    /// grows the file, and any gap between the old end and `offset` reads
+    /// back as zeros; whole pages of the gap are left as holes that take no
+    /// blocks. Blocks shared with other files, through dedup or
+    /// [`clone_file`](Self::clone_file), are copied, not changed.
+    pub fn write_at(&mut self, path: &str, offset: u64, data: &[u8]) -> Result<()> {
+        let path = &normalize_path(path)?;
+        self.ensure_writable()?;
+        self.check_protected(path)?;
+        // Check IAM policy
+        self.check_access_with(&Action::Write, path, &self.write_context(path, data))?;
 
-        Ok(())
+        let metadata = self.file_for_update(path)?;
+        self.update_range(path, metadata, offset, data)
     }
 
-    /// Create a directory
-    pub fn create_dir(&mut self, path: &str) -> Result<()> {
-        // Check if already exists
-        if self.catalog.get(path)?.is_some() {
-            return Err(CartridgeError::Allocation(format!(
-                "Path already exists: {}",
-                path
-            )));
+    /// Shrink or grow a file to `new_len` bytes
+    ///
+    /// Shrinking frees the blocks past the new end. Growing fills the new
+    /// space with zeros, as holes where it spans whole pages.
+    pub fn truncate(&mut self, path: &str, new_len: u64) -> Result<()> {
+        let path = &normalize_path(path)?;
+        self.ensure_writable()?;
+        self.check_protected(path)?;
+        // Check IAM policy
+        self.check_access(&Action::Write, path)?;
+
+        let mut metadata = self.file_for_update(path)?;
+        let old_size = metadata.size;
+        if new_len >= old_size {
+            return self.update_range(path, metadata, new_len, &[]);
+        }
+        if self.needs_rewrite(&metadata) {
+            let mut content = self.read_file_nofollow(path)?;
+            content.truncate(new_len as usize);
+            return self.write_file(path, &content);
         }
 
-        let metadata = FileMetadata::directory();
-        self.catalog.insert(path, metadata)?;
+        let keep = new_len.div_ceil(self.page_size() as u64) as usize;
+        self.release_blocks(&metadata.blocks[keep..])?;
+        metadata.blocks.truncate(keep);
+        metadata.size = new_len;
+        metadata.content_hash = Some(self.rehash(path, &metadata)?);
+        metadata.touch();
+        self.catalog_mut().insert(path, metadata)?;
+        self.quotas.record(path, old_size, new_len);
+        self.header.free_blocks = self.allocator.free_blocks() as u64;
 
+        self.audit_log(Operation::Update, path);
+        self.watchers.notify(path, ChangeKind::Modified);
         Ok(())
     }
 
-    /// List directory contents
-    pub fn list_dir(&self, path: &str) -> Result<Vec<String>> {
-        // Empty path means list all files (no prefix filter)
-        let prefix = if path.is_empty() {
-            String::new()
-        } else if path.ends_with('/') {
-            path.to_string()
+    /// Write `content` to `path`, creating or replacing the file, and leave
+    /// every page of it that is all zeros as a hole
+    ///
+    /// With encryption enabled the content is sealed as a whole instead,
+    /// like any other write.
+    pub fn write_sparse(&mut self, path: &str, content: &[u8]) -> Result<()> {
+        let path = &normalize_path(path)?;
+        let sealed = self.encryption_config.is_some();
+        let initial: &[u8] = if sealed { content } else { &[] };
+        if self.exists(path)? {
+            self.write_file(path, initial)?;
         } else {
-            format!("{}/", path)
-        };
-
-        let entries = self.catalog.list_prefix(&prefix)?;
-        Ok(entries.into_iter().map(|(path, _)| path).collect())
-    }
+            self.create_file(path, initial)?;
+        }
+        if sealed {
+            return Ok(());
+        }
 
-    /// Check if a path exists
-    pub fn exists(&self, path: &str) -> Result<bool> {
-        Ok(self.catalog.get(path)?.is_some())
+        // Write each run of pages with data in one go, in order, so every
+        // write lands past the end: zero pages skipped over become holes and
+        // the content hash is extended rather than recomputed
+        let page_size = self.page_size();
+        let mut run_start = None;
+        for (page, chunk) in content.chunks(page_size).enumerate() {
+            let zero = chunk.iter().all(|&b| b == 0);
+            match run_start {
+                None if !zero => run_start = Some(page),
+                Some(first) if zero => {
+                    let range = first * page_size..page * page_size;
+                    self.write_at(path, range.start as u64, &content[range])?;
+                    run_start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(first) = run_start {
+            let from = first * page_size;
+            self.write_at(path, from as u64, &content[from..])?;
+        }
+        self.truncate(path, content.len() as u64)
     }
 
-    /// Get file metadata
-    pub fn metadata(&self, path: &str) -> Result<FileMetadata> {
-        self.catalog
+    /// Catalog entry of the regular file at `path`, for a partial update
+    fn file_for_update(&self, path: &str) -> Result<FileMetadata> {
+        let metadata = self
+            .catalog
             .get(path)?
-            .ok_or_else(|| CartridgeError::Allocation(format!("Path not found: {}", path)))
+            .ok_or_else(|| CartridgeError::NotFound {
+                path: path.to_string(),
+            })?;
+
+        if !metadata.is_file() {
+            return Err(CartridgeError::NotAFile {
+                path: path.to_string(),
+            });
+        }
+        Ok(metadata)
     }
 
-    /// Get a reference to the cartridge header
-    pub fn header(&self) -> &Header {
-        &self.header
+    /// Whether a partial update has to go through a whole-file rewrite
+    ///
+    /// Inline content is small and encrypted content is one sealed blob, so
+    /// neither can be patched page by page; the same goes for files that
+    /// would come out encrypted.
+    fn needs_rewrite(&self, metadata: &FileMetadata) -> bool {
+        self.is_sealed(metadata)
+            || metadata.blocks.is_empty()
+            || metadata.blocks.len() as u64 != metadata.size.div_ceil(self.page_size() as u64)
     }
 
-    /// Get a mutable reference to the cartridge header
-    pub fn header_mut(&mut self) -> &mut Header {
-        &mut self.header
+    /// Whether the file's content is, or would be written, encrypted as a whole
+    fn is_sealed(&self, metadata: &FileMetadata) -> bool {
+        self.encryption_config.is_some() || metadata.user_metadata.contains_key("encrypted")
     }
 
-    /// Update file user metadata
+    /// Write `data` at `offset` into the file described by `metadata`,
+    /// touching only the affected pages, and save the updated entry
+    ///
+    /// Whole pages between the last block and `offset` become holes rather
+    /// than blocks of zeros.
+    fn update_range(
+        &mut self,
+        path: &str,
+        mut metadata: FileMetadata,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<()> {
+        let old_size = metadata.size;
+        let new_size = old_size.max(offset + data.len() as u64);
+        let page_size = self.page_size() as u64;
+
+        // Landing a page or more past the last page leaves a hole, which
+        // even an empty or inline file can take
+        let leaves_hole =
+            !self.is_sealed(&metadata) && offset / page_size > old_size.div_ceil(page_size);
+        if !leaves_hole && self.needs_rewrite(&metadata) {
+            let mut content = self.read_file_nofollow(path)?;
+            content.resize(new_size as usize, 0);
+            content[offset as usize..offset as usize + data.len()].copy_from_slice(data);
+            return self.write_file(path, &content);
+        }
+        self.quotas.check(path, old_size, new_size)?;
+
+        if leaves_hole {
+            if let Some(inline) = metadata.inline_data.take() {
+                metadata.blocks = self.store_content(path, &inline)?;
+            }
+            // Zero the rest of the last page so stale page bytes never show
+            let existing = metadata.blocks.len() as u64 * page_size;
+            if old_size < existing {
+                let zeros = vec![0u8; (existing - old_size) as usize];
+                self.patch_range(path, &mut metadata, old_size, &zeros)?;
+            }
+            let holes = if data.is_empty() {
+                offset.div_ceil(page_size)
+            } else {
+                offset / page_size
+            };
+            metadata.blocks.resize(holes as usize, HOLE_BLOCK);
+            metadata.size = (holes * page_size).min(offset);
+        }
+        self.patch_range(path, &mut metadata, offset, data)?;
+
+        metadata.size = new_size;
+        metadata.content_hash = Some(if offset >= old_size {
+            self.extend_hash(path, &metadata, old_size, offset, data)?
+        } else {
+            self.rehash(path, &metadata)?
+        });
+        metadata.touch();
+        self.catalog_mut().insert(path, metadata)?;
+        self.quotas.record(path, old_size, new_size);
+        self.header.free_blocks = self.allocator.free_blocks() as u64;
+        telemetry::bytes_written(data.len());
+
+        // Audit log (partial writes are update operations)
+        self.audit_log(Operation::Update, path);
+        self.watchers.notify(path, ChangeKind::Modified);
+
+        Ok(())
+    }
+
+    /// Write `data` at `offset` into the pages of `metadata`, allocating
+    /// pages past the last block and filling holes it overlaps, and grow
+    /// `metadata.size` to cover it
+    ///
+    /// A gap between the current end and `offset` is zero-filled.
+    fn patch_range(
+        &mut self,
+        path: &str,
+        metadata: &mut FileMetadata,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<()> {
+        let old_size = metadata.size;
+
+        // Zero-fill a gap past the old end so stale page bytes never show
+        let (start, data) = if offset > old_size {
+            let mut padded = vec![0u8; (offset - old_size) as usize];
+            padded.extend_from_slice(data);
+            (old_size as usize, std::borrow::Cow::Owned(padded))
+        } else {
+            (offset as usize, std::borrow::Cow::Borrowed(data))
+        };
+        let end = start + data.len();
+        if start == end {
+            return Ok(());
+        }
+
+        // Allocate pages past the current last block first, so running out
+        // of space leaves the file untouched
+        let page_size = self.page_size();
+        let existing = metadata.blocks.len() * page_size;
+        if end > existing {
+            let from = existing.max(start);
+            let mut tail = vec![0u8; end - existing];
+            tail[from - existing..].copy_from_slice(&data[from - start..]);
+            let new_blocks = self.store_content(path, &tail)?;
+            metadata.blocks.extend(new_blocks);
+        }
+
+        // Patch the existing pages the range overlaps
+        let first_page = start / page_size;
+        let last_page = end.min(existing).div_ceil(page_size);
+        for page in first_page..last_page {
+            let page_start = page * page_size;
+            let valid = (old_size as usize - page_start).min(page_size);
+            let block = metadata.blocks[page];
+            let mut bytes = self.read_content(path, &[block], valid)?;
+            let from = start.max(page_start);
+            let to = end.min(page_start + page_size);
+            bytes.resize(bytes.len().max(to - page_start), 0);
+            bytes[from - page_start..to - page_start].copy_from_slice(&data[from - start..to - start]);
+
+            if block == HOLE_BLOCK || self.block_refs().refs(block).is_some() {
+                // Copy-on-write: the block's hash or other files depend on
+                // it, or there is no block yet
+                let copy = self.store_content(path, &bytes)?;
+                self.release_blocks(&[block])?;
+                metadata.blocks[page] = copy[0];
+            } else {
+                self.write_content(path, &[block], &bytes, None)?;
+            }
+        }
+
+        metadata.size = metadata.size.max(end as u64);
+        Ok(())
+    }
+
+    /// Delete a file
+    pub fn delete_file(&mut self, path: &str) -> Result<()> {
+        let path = &normalize_path(path)?;
+        self.ensure_writable()?;
+        self.check_protected(path)?;
+        // Check IAM policy
+        self.check_access(&Action::Delete, path)?;
+
+        let metadata = self
+            .catalog_mut()
+            .delete(path)?
+            .ok_or_else(|| CartridgeError::NotFound {
+                path: path.to_string(),
+            })?;
+        if metadata.is_file() {
+            self.quotas.record(path, metadata.size, 0);
+        }
+
+        // Free blocks
+        self.release_blocks(&metadata.blocks)?;
+
+        // Update header
+        self.header.free_blocks = self.allocator.free_blocks() as u64;
+
+        // Audit log
+        self.audit_log(Operation::Delete, path);
+        self.watchers.notify(path, ChangeKind::Deleted);
+
+        Ok(())
+    }
+
+    /// Rename a file or directory entry
+    ///
+    /// Only the catalog entry moves; content blocks stay where they are.
+    /// Fails if `to` already exists.
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<()> {
+        let (from, to) = (&normalize_path(from)?, &normalize_path(to)?);
+        self.ensure_writable()?;
+        self.check_protected(from)?;
+        self.check_protected(to)?;
+        // Check IAM policy (a rename removes one path and creates another)
+        self.check_access(&Action::Delete, from)?;
+        self.check_access(&Action::Create, to)?;
+
+        if to == "/" || self.catalog.get(to)?.is_some() {
+            return Err(CartridgeError::AlreadyExists {
+                path: to.to_string(),
+            });
+        }
+
+        let metadata = self
+            .catalog
+            .get(from)?
+            .ok_or_else(|| CartridgeError::NotFound {
+                path: from.to_string(),
+            })?;
+        let size = if metadata.is_file() { metadata.size } else { 0 };
+        self.quotas.check_rename(from, to, size)?;
+
+        self.catalog_mut().delete(from)?;
+        self.catalog_mut().insert(to, metadata)?;
+        self.quotas.record(from, size, 0);
+        self.quotas.record(to, 0, size);
+
+        // Audit log
+        self.audit_log(Operation::Delete, from);
+        self.audit_log(Operation::Create, to);
+        self.watchers.notify(
+            to,
+            ChangeKind::Renamed {
+                from: from.to_string(),
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Create `dst` as a copy of the file at `src` that shares its blocks
+    ///
+    /// No content is copied: both entries point at the same blocks, which
+    /// are freed only when neither uses them. Writing to either copies the
+    /// pages it changes first, so the other is unaffected. Fails if `dst`
+    /// already exists.
+    pub fn clone_file(&mut self, src: &str, dst: &str) -> Result<()> {
+        let (src, dst) = (&normalize_path(src)?, &normalize_path(dst)?);
+        self.ensure_writable()?;
+        self.check_protected(dst)?;
+        // Check IAM policy (a clone reads one path and creates another)
+        self.check_access(&Action::Read, src)?;
+        self.check_access(&Action::Create, dst)?;
+
+        let mut metadata = self.file_for_update(src)?;
+        if dst == "/" || self.catalog.get(dst)?.is_some() {
+            return Err(CartridgeError::AlreadyExists {
+                path: dst.to_string(),
+            });
+        }
+        self.quotas.check(dst, 0, metadata.size)?;
+
+        metadata.touch();
+        metadata.created_at = metadata.modified_at;
+        self.catalog_mut().insert(dst, metadata.clone())?;
+        let refs = self.block_refs();
+        for block in metadata.data_blocks() {
+            refs.add_ref(block);
+        }
+        self.quotas.record(dst, 0, metadata.size);
+
+        // Audit log
+        self.audit_log(Operation::Create, dst);
+        self.watchers.notify(dst, ChangeKind::Created);
+
+        Ok(())
+    }
+
+    /// The in-memory catalog, for sibling modules that extend `Cartridge`
+    pub(crate) fn catalog(&self) -> &Catalog {
+        &self.catalog
+    }
+
+    /// Whether `block` is allocated to a file or to the archive itself
+    pub(crate) fn is_block_allocated(&self, block: u64) -> bool {
+        block < self.header.total_blocks && self.allocator.is_allocated(block)
+    }
+
+    /// Whether an IAM policy is checked on every access
+    pub(crate) fn has_policy(&self) -> bool {
+        self.policy.is_some()
+    }
+
+    /// Whether the cartridge was opened with [`open_read_only`](Self::open_read_only)
+    /// or was written by a newer minor format version
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Fail with [`CartridgeError::ReadOnly`] on a read-only cartridge
+    pub(crate) fn ensure_writable(&self) -> Result<()> {
+        if self.read_only {
+            return Err(CartridgeError::ReadOnly);
+        }
+        Ok(())
+    }
+
+    /// Fail with [`CartridgeError::ProtectedPath`] for a normalized path
+    /// under `.cartridge/`, unless called through [`Self::privileged`]
+    ///
+    /// On a case-insensitive cartridge `/.CARTRIDGE/...` reaches the same
+    /// entries, so it is protected too.
+    pub(crate) fn check_protected(&self, path: &str) -> Result<()> {
+        let internal = if self.catalog.is_case_insensitive() {
+            crate::transfer::is_internal(&crate::catalog::fold_case(path))
+        } else {
+            crate::transfer::is_internal(path)
+        };
+        if !self.system_access && internal {
+            return Err(CartridgeError::ProtectedPath {
+                path: path.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Run `f` with `.cartridge/` paths writable
+    pub(crate) fn privileged<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let outer = std::mem::replace(&mut self.system_access, true);
+        let result = f(self);
+        self.system_access = outer;
+        result
+    }
+
+    /// Mutable access to the in-memory catalog
+    ///
+    /// Marks the catalog for rewriting on the next flush.
+    pub(crate) fn catalog_mut(&mut self) -> &mut Catalog {
+        // Counts taken after a change would miss the references it dropped
+        self.block_refs();
+        self.metadata_dirty = true;
+        &mut self.catalog
+    }
+
+    pub(crate) fn event_listener_mut(&mut self) -> &mut Option<EventListener> {
+        &mut self.event_listener
+    }
+
+    pub(crate) fn watchers(&self) -> &Watchers {
+        &self.watchers
+    }
+
+    /// Hand an event to the listener; `event` is only built if one is installed
+    ///
+    /// Callers must not hold the page cache or file locks.
+    pub(crate) fn emit(&self, event: impl FnOnce() -> CartridgeEvent) {
+        if let Some(listener) = &self.event_listener {
+            listener(event());
+        }
+    }
+
+    /// Capture the in-memory metadata so a multi-step change can be undone
+    ///
+    /// Content pages written after the checkpoint land in blocks that were
+    /// free at the time, so restoring the allocator is enough to discard
+    /// them.
+    pub(crate) fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            header: self.header,
+            catalog: self.catalog.clone(),
+            allocator: self.allocator.clone(),
+            quotas: self.quotas.clone(),
+            dedup_index: self.dedup_index.clone(),
+        }
+    }
+
+    /// Restore metadata captured by [`Cartridge::checkpoint`]
+    pub(crate) fn rollback(&mut self, checkpoint: Checkpoint) {
+        self.header = checkpoint.header;
+        self.catalog = checkpoint.catalog;
+        self.catalog.forget_segments();
+        self.allocator = checkpoint.allocator;
+        self.quotas = checkpoint.quotas;
+        self.dedup_index = checkpoint.dedup_index;
+        self.metadata_dirty = true;
+    }
+
+    /// Create a directory
+    pub fn create_dir(&mut self, path: &str) -> Result<()> {
+        let path = &normalize_path(path)?;
+        self.ensure_writable()?;
+        self.check_protected(path)?;
+        // Check if already exists
+        if path == "/" || self.catalog.get(path)?.is_some() {
+            return Err(CartridgeError::AlreadyExists {
+                path: path.to_string(),
+            });
+        }
+
+        let metadata = FileMetadata::directory();
+        self.catalog_mut().insert(path, metadata)?;
+        self.watchers.notify(path, ChangeKind::Created);
+
+        Ok(())
+    }
+
+    /// List directory contents
+    ///
+    /// Fails with [`CartridgeError::NotADirectory`] if `path` is a file.
+    pub fn list_dir(&self, path: &str) -> Result<Vec<String>> {
+        let path = &normalize_path(path)?;
+        if self.catalog.get(path)?.is_some_and(|metadata| metadata.is_file()) {
+            return Err(CartridgeError::NotADirectory {
+                path: path.to_string(),
+            });
+        }
+        let entries = self.list_dir_with_metadata(path)?;
+        Ok(entries.into_iter().map(|(path, _)| path).collect())
+    }
+
+    /// List directory contents together with each path's metadata
+    ///
+    /// Same paths as [`list_dir`](Self::list_dir), in the same order, from a
+    /// single catalog scan rather than one lookup per path.
+    pub fn list_dir_with_metadata(&self, path: &str) -> Result<Vec<(String, FileMetadata)>> {
+        let path = &normalize_path(path)?;
+        self.catalog.list_prefix(&Self::dir_prefix(path))
+    }
+
+    /// Lazily iterate the same paths as [`list_dir`](Self::list_dir)
+    ///
+    /// Entries are borrowed from the catalog in key order, so nothing is
+    /// copied for entries the caller never reaches. An invalid path (see
+    /// [`normalize_path`]) yields nothing.
+    pub fn walk_dir(&self, path: &str) -> impl Iterator<Item = (&String, &FileMetadata)> {
+        // No catalog key contains a NUL, so the fallback prefix matches nothing
+        let prefix = normalize_path(path)
+            .map(|path| Self::dir_prefix(&path))
+            .unwrap_or_else(|_| "\0".to_string());
+        self.catalog.iter_prefix(prefix)
+    }
+
+    /// Catalog key prefix for listing the normalized `path`; the root lists
+    /// everything
+    fn dir_prefix(path: &str) -> String {
+        if path.ends_with('/') {
+            path.to_string()
+        } else {
+            format!("{}/", path)
+        }
+    }
+
+    /// Check if a path exists
+    pub fn exists(&self, path: &str) -> Result<bool> {
+        let path = &normalize_path(path)?;
+        Ok(self.catalog.get(path)?.is_some())
+    }
+
+    /// What `path` is, without following symlinks; `None` if it doesn't exist
+    ///
+    /// A path with no catalog entry of its own is a directory when something
+    /// is stored below it. That probe stops at the first key under the
+    /// prefix, so it costs the same however large the directory is.
+    pub fn path_type(&self, path: &str) -> Result<Option<FileType>> {
+        let path = &normalize_path(path)?;
+        if path == "/" {
+            return Ok(Some(FileType::Directory));
+        }
+        if let Some(metadata) = self.catalog.get(path)? {
+            return Ok(Some(metadata.file_type));
+        }
+        let has_children = self.walk_dir(path).next().is_some();
+        Ok(has_children.then_some(FileType::Directory))
+    }
+
+    /// Get file metadata, following symlinks
+    pub fn metadata(&self, path: &str) -> Result<FileMetadata> {
+        let path = &normalize_path(path)?;
+        self.follow(path)?
+            .map(|(_, metadata)| metadata)
+            .ok_or_else(|| CartridgeError::NotFound {
+                path: path.to_string(),
+            })
+    }
+
+    /// Get metadata for `path` itself, without following symlinks
+    ///
+    /// Works on dangling links, like `lstat`.
+    pub fn metadata_nofollow(&self, path: &str) -> Result<FileMetadata> {
+        let path = &normalize_path(path)?;
+        self.catalog
+            .get(path)?
+            .ok_or_else(|| CartridgeError::NotFound {
+                path: path.to_string(),
+            })
+    }
+
+    /// Get a reference to the cartridge header
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Get a mutable reference to the cartridge header
+    pub fn header_mut(&mut self) -> &mut Header {
+        &mut self.header
+    }
+
+    /// Update file user metadata
     ///
     /// Adds or updates a key-value pair in the file's user metadata.
     /// This is useful for storing S3-compatible metadata like ACLs or SSE headers.
@@ -1165,21 +3308,455 @@ impl Cartridge {
         key: impl Into<String>,
         value: impl Into<String>,
     ) -> Result<()> {
-        let mut metadata = self.metadata(path)?;
+        let path = &normalize_path(path)?;
+        let mut metadata = self.metadata_nofollow(path)?;
         metadata.user_metadata.insert(key.into(), value.into());
-        self.catalog.insert(path, metadata)?;
+        self.catalog_mut().insert(path, metadata)?;
+        Ok(())
+    }
+
+    /// Get a file's user metadata
+    ///
+    /// Keys the cartridge manages itself (such as the encryption markers)
+    /// are left out.
+    pub fn user_metadata(&self, path: &str) -> Result<HashMap<String, String>> {
+        let path = &normalize_path(path)?;
+        let mut user_metadata = self.metadata_nofollow(path)?.user_metadata;
+        user_metadata.retain(|key, _| !RESERVED_METADATA_KEYS.contains(&key.as_str()));
+        Ok(user_metadata)
+    }
+
+    /// Add or replace several user metadata keys at once
+    ///
+    /// Every key is validated, and the resulting metadata is checked against
+    /// the size cap, before anything is changed. Keys not in `entries` are
+    /// left alone.
+    pub fn set_user_metadata(&mut self, path: &str, entries: HashMap<String, String>) -> Result<()> {
+        let path = &normalize_path(path)?;
+        self.ensure_writable()?;
+        for key in entries.keys() {
+            validate_metadata_key(key)?;
+        }
+
+        let mut metadata = self.metadata_nofollow(path)?;
+        metadata.user_metadata.extend(entries);
+        self.check_user_metadata_size(path, &metadata)?;
+
+        self.catalog_mut().insert(path, metadata)?;
+        Ok(())
+    }
+
+    /// Fail if `metadata`'s user metadata is over the size cap
+    fn check_user_metadata_size(&self, path: &str, metadata: &FileMetadata) -> Result<()> {
+        let size: usize = metadata
+            .user_metadata
+            .iter()
+            .filter(|(key, _)| !RESERVED_METADATA_KEYS.contains(&key.as_str()))
+            .map(|(key, value)| key.len() + value.len())
+            .sum();
+        if size > self.max_user_metadata_bytes {
+            return Err(CartridgeError::InvalidMetadata(format!(
+                "{} bytes of metadata on {} exceeds the {} byte limit",
+                size, path, self.max_user_metadata_bytes
+            )));
+        }
+        Ok(())
+    }
+
+    /// Remove one user metadata key, returning its old value
+    pub fn remove_user_metadata(&mut self, path: &str, key: &str) -> Result<Option<String>> {
+        let path = &normalize_path(path)?;
+        self.ensure_writable()?;
+        validate_metadata_key(key)?;
+        let mut metadata = self.metadata_nofollow(path)?;
+        let removed = metadata.user_metadata.remove(key);
+        if removed.is_some() {
+            self.catalog_mut().insert(path, metadata)?;
+        }
+        Ok(removed)
+    }
+
+    /// Set the cap on each file's user metadata (default: 2048 bytes)
+    pub fn set_max_user_metadata_bytes(&mut self, bytes: usize) {
+        self.max_user_metadata_bytes = bytes;
+    }
+
+    /// Store files of at most `bytes` in the catalog instead of in blocks
+    /// (default: 512, 0 turns inlining off)
+    ///
+    /// Only affects later writes; a file moves between inline and block
+    /// storage the next time it is written.
+    pub fn set_inline_threshold(&mut self, bytes: usize) {
+        self.inline_threshold = bytes;
+    }
+
+    /// Share blocks between pages with identical content (default: off)
+    ///
+    /// Only affects later writes. Blocks already shared stay reference
+    /// counted whether or not dedup is on, and the setting itself isn't
+    /// saved; turn it on again after reopening.
+    pub fn set_dedup(&mut self, enabled: bool) {
+        self.dedup = enabled;
+    }
+
+    /// Turn automatic growth on or off
+    pub fn set_auto_grow(&mut self, enabled: bool) {
+        self.auto_grow = enabled;
+    }
+
+    /// Change how far each automatic growth step goes (not saved in the file)
+    pub fn set_growth_policy(&mut self, growth: GrowthPolicy) {
+        self.growth = growth;
+    }
+
+    /// Change when flushes fsync (not saved in the file)
+    ///
+    /// See [`SyncPolicy`] for what each policy risks on power loss.
+    pub fn set_sync_policy(&mut self, policy: SyncPolicy) {
+        self.sync_policy = policy;
+    }
+
+    /// When flushes fsync
+    pub fn sync_policy(&self) -> SyncPolicy {
+        self.sync_policy
+    }
+
+    /// Whether new writes are deduplicated
+    pub fn dedup_enabled(&self) -> bool {
+        self.dedup
+    }
+
+    /// Whether `content` (as it will be stored) is small enough to inline
+    fn should_inline(&self, content: &[u8]) -> bool {
+        !content.is_empty() && content.len() <= self.inline_threshold
+    }
+
+    /// Limit the total size of files under `prefix` to `bytes`
+    ///
+    /// `prefix` matches whole path segments: a quota on `users/alice`
+    /// covers `users/alice/notes.txt` but not `users/alice2/notes.txt`.
+    /// An empty prefix covers the whole cartridge. Writes that would take
+    /// usage past the limit fail with [`CartridgeError::QuotaExceeded`];
+    /// setting a limit below current usage only blocks further growth.
+    ///
+    /// The limit is saved in the manifest (cartridges without one keep it
+    /// in memory only). Replaces any existing quota on the same prefix.
+    pub fn set_quota(&mut self, prefix: &str, bytes: u64) -> Result<()> {
+        let prefix = &normalize_path(prefix)?;
+        self.ensure_writable()?;
+        let used = match self.quotas.get(prefix) {
+            Some(usage) => usage.used,
+            None => self.usage_under(prefix)?,
+        };
+        let previous = self.quotas.insert(prefix, QuotaUsage { limit: bytes, used });
+
+        let key = quota::normalize_prefix(prefix);
+        if let Err(e) = self.persist_quotas(|quotas| {
+            quotas.insert(key, bytes);
+        }) {
+            match previous {
+                Some(usage) => self.quotas.insert(prefix, usage),
+                None => self.quotas.remove(prefix),
+            };
+            return Err(e);
+        }
         Ok(())
     }
 
+    /// Remove the quota on `prefix`, returning whether there was one
+    pub fn remove_quota(&mut self, prefix: &str) -> Result<bool> {
+        let prefix = &normalize_path(prefix)?;
+        self.ensure_writable()?;
+        let Some(previous) = self.quotas.remove(prefix) else {
+            return Ok(false);
+        };
+
+        let key = quota::normalize_prefix(prefix);
+        if let Err(e) = self.persist_quotas(|quotas| {
+            quotas.remove(&key);
+        }) {
+            self.quotas.insert(prefix, previous);
+            return Err(e);
+        }
+        Ok(true)
+    }
+
+    /// Limit and current usage of the quota on `prefix`, if any
+    pub fn quota_usage(&self, prefix: &str) -> Option<QuotaUsage> {
+        self.quotas.get(prefix)
+    }
+
+    /// Every quota with its current usage, ordered by prefix
+    pub fn quotas(&self) -> Vec<(String, QuotaUsage)> {
+        self.quotas
+            .iter()
+            .map(|(prefix, usage)| (prefix.clone(), *usage))
+            .collect()
+    }
+
+    /// Cap how large auto-growth may make the container
+    ///
+    /// Rounded down to whole pages. A container already larger than the
+    /// cap keeps its size but won't grow further. Not persisted; apply it
+    /// each time the cartridge is opened.
+    pub fn set_max_size_bytes(&mut self, bytes: u64) {
+        self.max_blocks = ((bytes / self.page_size() as u64) as usize).max(MIN_BLOCKS);
+    }
+
+    /// The current auto-growth cap in bytes
+    pub fn max_size_bytes(&self) -> u64 {
+        self.max_blocks as u64 * self.page_size() as u64
+    }
+
+    /// Roughly how many more bytes of content can be written
+    ///
+    /// Free space inside the container plus what auto-growth could still
+    /// add before reaching the size cap, or the free space on the disk if
+    /// that runs out first. Catalog updates take a little of it too.
+    pub fn headroom(&self) -> u64 {
+        let page_size = self.page_size() as u64;
+        let free = self.header.free_blocks;
+        if !self.auto_grow {
+            return free * page_size;
+        }
+
+        let mut growable = (self.max_blocks as u64).saturating_sub(self.header.total_blocks);
+        if let Some(file) = &self.file {
+            let f = file.lock();
+            if let Some(available) = f.available_space() {
+                growable = growable.min(available / f.slot_size() as u64);
+            }
+        }
+        (free + growable) * page_size
+    }
+
+    /// Apply `f` to the manifest's quota table, if there is a manifest
+    fn persist_quotas<F>(&mut self, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut std::collections::BTreeMap<String, u64>),
+    {
+        if !self.exists(MANIFEST_PATH)? {
+            return Ok(());
+        }
+        self.update_manifest(|manifest| f(&mut manifest.quotas))
+    }
+
+    /// Bytes of regular files under `prefix`, from a catalog scan
+    fn usage_under(&self, prefix: &str) -> Result<u64> {
+        let prefix = quota::normalize_prefix(prefix);
+        Ok(self
+            .catalog
+            .list_prefix(&prefix)?
+            .iter()
+            .filter(|(path, metadata)| metadata.is_file() && quota::covers(&prefix, path))
+            .map(|(_, metadata)| metadata.size)
+            .sum())
+    }
+
+    /// Rebuild quota counters from the manifest and the catalog
+    ///
+    /// Cartridges without a readable manifest simply have no quotas.
+    fn load_quotas(&mut self) {
+        let limits = self
+            .read_manifest()
+            .map(|manifest| manifest.quotas)
+            .unwrap_or_default();
+        if limits.is_empty() {
+            self.quotas = Quotas::default();
+            return;
+        }
+        match self.catalog.list_prefix("") {
+            Ok(files) => {
+                self.quotas = Quotas::count(
+                    &limits,
+                    files.iter().map(|(path, metadata)| (path.as_str(), metadata)),
+                );
+            }
+            Err(e) => tracing::warn!("Failed to count quota usage: {e}"),
+        }
+    }
+
+    /// Set or clear the content type (MIME type) of an existing entry
+    pub fn set_content_type(&mut self, path: &str, content_type: Option<String>) -> Result<()> {
+        let path = &normalize_path(path)?;
+        self.ensure_writable()?;
+        let mut metadata = self.metadata_nofollow(path)?;
+        metadata.content_type = content_type;
+        self.catalog_mut().insert(path, metadata)?;
+        Ok(())
+    }
+
+    /// Set an entry's creation and/or modification time, in seconds since
+    /// the Unix epoch, without touching its content
+    ///
+    /// `None` keeps the current value. Symlinks are changed themselves, not
+    /// followed.
+    pub fn set_times(
+        &mut self,
+        path: &str,
+        created: Option<u64>,
+        modified: Option<u64>,
+    ) -> Result<()> {
+        let path = &normalize_path(path)?;
+        self.ensure_writable()?;
+        let mut metadata = self.metadata_nofollow(path)?;
+        MetadataPatch::times(created, modified).apply(&mut metadata);
+        self.catalog_mut().insert(path, metadata)?;
+        Ok(())
+    }
+
+    /// Bump an entry's modification time to now, or create an empty file
+    /// if nothing is at `path`
+    pub fn touch(&mut self, path: &str) -> Result<()> {
+        let path = &normalize_path(path)?;
+        self.ensure_writable()?;
+        match self.catalog.get(path)? {
+            Some(mut metadata) => {
+                metadata.touch();
+                self.catalog_mut().insert(path, metadata)?;
+                Ok(())
+            }
+            None => self.create_file(path, b""),
+        }
+    }
+
+    /// SHA-256 of a file's content, following symlinks
+    ///
+    /// Writes keep the stored digest current, so this is usually a catalog
+    /// lookup. Entries from older versions are hashed on demand and the
+    /// result stored (unless the cartridge is read-only). Encrypted files
+    /// have no stored digest and are hashed on every call.
+    pub fn hash(&mut self, path: &str) -> Result<[u8; 32]> {
+        let path = &normalize_path(path)?;
+        // Check IAM policy
+        self.check_access(&Action::Read, path)?;
+
+        let target = match self.follow(path)? {
+            Some((target, _)) => target,
+            None => path.clone(),
+        };
+        let metadata = self.file_for_update(&target)?;
+        if let Some(hash) = metadata.content_hash {
+            return Ok(hash);
+        }
+
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut self.open_reader(&target)?, &mut hasher)?;
+        let hash = hasher.finalize().into();
+        if !self.read_only && !metadata.is_encrypted() {
+            self.record_hash(&target, hash)?;
+        }
+        Ok(hash)
+    }
+
+    /// Store the digest of a file's current content, leaving its times alone
+    pub(crate) fn record_hash(&mut self, path: &str, hash: [u8; 32]) -> Result<()> {
+        let mut metadata = self.file_for_update(path)?;
+        metadata.content_hash = Some(hash);
+        self.catalog_mut().insert(path, metadata)
+    }
+
+    /// Remember the hasher that just took in all of `path`'s content, for
+    /// [`extend_hash`](Self::extend_hash)
+    fn keep_hasher(&mut self, path: &str, digest: Option<Sha256>) {
+        self.content_hasher = digest.map(|hasher| ContentHasher {
+            path: path.to_string(),
+            hasher,
+        });
+    }
+
+    /// Content hash of a file that grew from `old_size` by writing `data`
+    /// at `offset`, with zeros in between
+    ///
+    /// `metadata` is the updated entry, still carrying the old hash. When
+    /// the last write left a hasher for this file that matches it, only the
+    /// new bytes are hashed; otherwise the whole file is.
+    fn extend_hash(
+        &mut self,
+        path: &str,
+        metadata: &FileMetadata,
+        old_size: u64,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<[u8; 32]> {
+        let mut hasher = match self.content_hasher.take() {
+            Some(kept)
+                if kept.path == path
+                    && metadata.content_hash == Some(kept.hasher.clone().finalize().into()) =>
+            {
+                kept.hasher
+            }
+            _ => return self.rehash(path, metadata),
+        };
+        let zeros = vec![0u8; self.page_size()];
+        let mut gap = offset - old_size;
+        while gap > 0 {
+            let n = gap.min(zeros.len() as u64) as usize;
+            hasher.update(&zeros[..n]);
+            gap -= n as u64;
+        }
+        hasher.update(data);
+        let hash = hasher.clone().finalize().into();
+        self.keep_hasher(path, Some(hasher));
+        Ok(hash)
+    }
+
+    /// Content hash of the plaintext file `metadata` describes, read page
+    /// by page
+    fn rehash(&mut self, path: &str, metadata: &FileMetadata) -> Result<[u8; 32]> {
+        let mut hasher = Sha256::new();
+        match &metadata.inline_data {
+            Some(data) => hasher.update(data),
+            None => {
+                let page_size = self.page_size() as u64;
+                let mut remaining = metadata.size;
+                for &block in &metadata.blocks {
+                    let len = remaining.min(page_size);
+                    hasher.update(&self.load_page(path, block)?[..len as usize]);
+                    remaining -= len;
+                }
+            }
+        }
+        let hash = hasher.clone().finalize().into();
+        self.keep_hasher(path, Some(hasher));
+        Ok(hash)
+    }
+
+    /// Apply metadata changes to many entries in one pass, then flush once
+    ///
+    /// Every path must exist, every user metadata key must be valid and
+    /// every result must fit the metadata size cap; otherwise nothing is
+    /// changed. Content is never rewritten.
+    pub fn update_metadata_bulk(&mut self, updates: Vec<(String, MetadataPatch)>) -> Result<()> {
+        self.ensure_writable()?;
+        let mut patched = Vec::with_capacity(updates.len());
+        for (path, patch) in &updates {
+            let path = normalize_path(path)?;
+            for key in patch.user_metadata.keys() {
+                validate_metadata_key(key)?;
+            }
+            let mut metadata = self.metadata_nofollow(&path)?;
+            patch.apply(&mut metadata);
+            self.check_user_metadata_size(&path, &metadata)?;
+            patched.push((path, metadata));
+        }
+
+        let catalog = self.catalog_mut();
+        for (path, metadata) in patched {
+            catalog.insert(&path, metadata)?;
+        }
+        self.flush()
+    }
+
     /// Get archive statistics
     pub fn stats(&self) -> CartridgeStats {
-        let (path, file_size_bytes) = if let Some(file) = &self.file {
-            let f = file.lock();
-            let p = f.path().to_path_buf();
-            let size = std::fs::metadata(&p).map(|m| m.len()).unwrap_or(0);
-            (Some(p), size)
-        } else {
-            (None, 0)
+        let (path, file_size_bytes) = match &self.file {
+            Some(file) => {
+                let f = file.lock();
+                let path = (!f.is_in_memory()).then(|| f.path().to_path_buf());
+                (path, f.size_bytes().unwrap_or(0))
+            }
+            None => (None, 0),
         };
         CartridgeStats {
             total_blocks: self.header.total_blocks,
@@ -1188,14 +3765,64 @@ impl Cartridge {
             fragmentation: self.allocator.fragmentation_score(),
             path,
             file_size_bytes,
+            allocator: self.allocator.stats(),
+            generation: self.header.generation(),
+            last_flush_us: self.header.last_flush_us(),
         }
     }
 
+    /// Capacity and usage figures for dashboards
+    ///
+    /// Unlike [`stats`](Self::stats) this walks the whole catalog (once),
+    /// so avoid calling it on every operation.
+    pub fn detailed_stats(&self) -> Result<DetailedStats> {
+        let basic = self.stats();
+        let mut stats = DetailedStats {
+            total_blocks: basic.total_blocks,
+            free_blocks: basic.free_blocks,
+            used_blocks: basic.used_blocks,
+            fragmentation: basic.fragmentation,
+            logical_bytes: 0,
+            physical_bytes: basic.used_blocks * self.page_size() as u64,
+            hole_bytes: 0,
+            file_count: 0,
+            dir_count: 0,
+            symlink_count: 0,
+            shared_blocks: self.counted_refs().shared_count() as u64,
+            cached_pages: self.pages.lock().len(),
+            dirty_pages: self.dirty_pages.lock().len(),
+            max_blocks: self.max_blocks as u64,
+            file_size_bytes: basic.file_size_bytes,
+            policy_cache: self.policy_cache_stats(),
+        };
+
+        for (_, metadata) in self.catalog.list_prefix("")? {
+            match metadata.file_type {
+                FileType::File => {
+                    stats.file_count += 1;
+                    stats.logical_bytes += metadata.size;
+                    let page_size = self.page_size() as u64;
+                    let mut holes = metadata.hole_count() as u64 * page_size;
+                    if metadata.blocks.last() == Some(&HOLE_BLOCK) {
+                        // Only part of a trailing hole page is inside the file
+                        holes -= metadata.blocks.len() as u64 * page_size - metadata.size;
+                    }
+                    stats.hole_bytes += holes;
+                }
+                FileType::Directory => stats.dir_count += 1,
+                FileType::Symlink => stats.symlink_count += 1,
+            }
+        }
+
+        Ok(stats)
+    }
+
     /// Copy all live files to a new cartridge at `dest`, producing a compact
     /// copy with no free (unallocated) blocks.
     ///
     /// The original cartridge is left unchanged. After this call the caller
     /// can atomically replace the original file with `dest` to reclaim space.
+    #[cfg(not(feature = "no-fs"))]
     pub fn vacuum_into(&self, dest: &Path) -> Result<()> {
         if dest.exists() {
             std::fs::remove_file(dest)?;
@@ -1204,11 +3831,32 @@ impl Cartridge {
             std::fs::create_dir_all(parent)?;
         }
 
-        let mut new_cart = Cartridge::create_at(dest, "vacuum", "vacuum")?;
+        // Keep the copy encrypted under the same key and KDF parameters
+        let encryption = match (&self.file, self.header.encryption_params()) {
+            (Some(file), Some(params)) => file.lock().cipher().cloned().map(|c| (params, c)),
+            _ => None,
+        };
+        let options = CreateOptions {
+            page_checksums: self.header.has_feature(FEATURE_PAGE_CHECKSUMS),
+            growth: self.growth,
+            page_size: PageSize::from_bytes(self.header.block_size).unwrap_or_default(),
+            ..Default::default()
+        };
+        let mut new_cart = Cartridge::create_inner(dest, "vacuum", "vacuum", encryption, &options)?;
 
-        for path in self.list_dir("")? {
+        for (path, metadata) in self.list_dir_with_metadata("")? {
             // Skip internal container entries — new_cart creates its own manifest.
-            if path == ".cartridge" || path.starts_with(".cartridge/") {
+            if path == "/.cartridge" || path.starts_with("/.cartridge/") {
+                continue;
+            }
+            if metadata.is_directory() {
+                if !new_cart.exists(&path)? {
+                    new_cart.create_dir(&path)?;
+                }
+                continue;
+            }
+            if metadata.is_sparse() {
+                self.copy_sparse_into(&path, &metadata, &mut new_cart)?;
                 continue;
             }
             let data = self.read_file(&path)?;
@@ -1223,33 +3871,84 @@ impl Cartridge {
         Ok(())
     }
 
+    /// Copy the sparse file at `path` into `dest` a run of pages at a time,
+    /// leaving its holes as holes
+    #[cfg(not(feature = "no-fs"))]
+    fn copy_sparse_into(
+        &self,
+        path: &str,
+        metadata: &FileMetadata,
+        dest: &mut Cartridge,
+    ) -> Result<()> {
+        self.check_access(&Action::Read, path)?;
+        if dest.exists(path)? {
+            dest.write_file(path, &[])?;
+        } else {
+            dest.create_file(path, &[])?;
+        }
+
+        // Runs go in order, each past the end, so the gaps become holes
+        let page_size = self.page_size();
+        let size = metadata.size as usize;
+        let mut run = Vec::new();
+        let mut run_start = 0;
+        for (page, &block) in metadata.blocks.iter().enumerate() {
+            if block != HOLE_BLOCK {
+                if run.is_empty() {
+                    run_start = page * page_size;
+                }
+                let len = (size - page * page_size).min(page_size);
+                run.extend_from_slice(&self.load_page(path, block)?[..len]);
+            } else if !run.is_empty() {
+                dest.write_at(path, run_start as u64, &std::mem::take(&mut run))?;
+            }
+        }
+        if !run.is_empty() {
+            dest.write_at(path, run_start as u64, &run)?;
+        }
+        dest.truncate(path, metadata.size)
+    }
+
     /// Read container manifest
     ///
-    /// Returns an error if the manifest doesn't exist or is invalid.
+    /// Returns [`CartridgeError::ManifestNotFound`] if the manifest doesn't
+    /// exist, or another error if it is invalid. Older schema versions are
+    /// upgraded with [`Manifest::migrate`].
     pub fn read_manifest(&self) -> Result<Manifest> {
-        let manifest_data = self.read_file(MANIFEST_PATH)?;
-        let manifest: Manifest = serde_json::from_slice(&manifest_data)?;
+        let manifest_data = self.read_file(MANIFEST_PATH).map_err(|e| match e {
+            CartridgeError::NotFound { .. } => CartridgeError::ManifestNotFound,
+            e => e,
+        })?;
+        let mut manifest: Manifest = serde_json::from_slice(&manifest_data)?;
+        manifest.migrate();
         Ok(manifest)
     }
 
+    /// Rewrite a manifest with an older schema version in the current one
+    ///
+    /// [`read_manifest`](Self::read_manifest) migrates in memory anyway;
+    /// this brings the stored copy up to date as well.
+    fn save_migrated_manifest(&mut self) -> Result<()> {
+        let manifest_data = match self.read_file(MANIFEST_PATH) {
+            Ok(data) => data,
+            Err(CartridgeError::NotFound { .. }) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let mut manifest: Manifest = serde_json::from_slice(&manifest_data)?;
+        if manifest.migrate() {
+            tracing::info!("Migrated manifest to schema version {}", manifest.schema_version);
+            self.write_manifest(&manifest)?;
+        }
+        Ok(())
+    }
+
     /// Write/update container manifest
     ///
     /// Overwrites the existing manifest at .cartridge/manifest.json
     pub fn write_manifest(&mut self, manifest: &Manifest) -> Result<()> {
+        self.ensure_writable()?;
         let manifest_json = serde_json::to_vec_pretty(manifest)?;
-
-        // Check if manifest file exists
-        if self.exists(MANIFEST_PATH)? {
-            self.write_file(MANIFEST_PATH, &manifest_json)?;
-        } else {
-            // Ensure directory exists
-            if !self.exists(".cartridge")? {
-                self.create_dir(".cartridge")?;
-            }
-            self.create_file(MANIFEST_PATH, &manifest_json)?;
-        }
-
-        Ok(())
+        self.system().write(MANIFEST_PATH, &manifest_json)
     }
 
     /// Get container slug from manifest
@@ -1275,7 +3974,7 @@ impl Cartridge {
     /// # Examples
     ///
     /// ```no_run
-    /// # use cartridge_core::Cartridge;
+    /// # use cartridge_rs::core::Cartridge;
     /// # fn example(mut cart: Cartridge) -> Result<(), Box<dyn std::error::Error>> {
     /// cart.update_manifest(|manifest| {
     ///     manifest.description = Some("Updated description".to_string());
@@ -1293,79 +3992,414 @@ impl Cartridge {
         Ok(())
     }
 
-    /// Ensure sufficient capacity, growing if needed
+    /// Declare a dependency on another cartridge, e.g. `("base-assets", ">=1.2")`
+    ///
+    /// Replaces any existing requirement on the same slug.
+    pub fn add_dependency(&mut self, slug: &str, req: &str) -> Result<()> {
+        let dependency = Dependency::new(slug, req)?;
+        if dependency.slug.as_str() == self.slug()? {
+            return Err(CartridgeError::ManifestValidation(format!(
+                "{} depends on itself",
+                slug
+            )));
+        }
+        self.update_manifest(|manifest| manifest.set_dependency(dependency))
+    }
+
+    /// Dependencies declared in the manifest
+    pub fn dependencies(&self) -> Result<Vec<Dependency>> {
+        Ok(self.read_manifest()?.dependencies)
+    }
+
+    /// Increment the manifest version and return the new one
+    pub fn bump_version(&mut self, bump: Bump) -> Result<semver::Version> {
+        let mut version = None;
+        self.update_manifest(|manifest| version = Some(manifest.bump_version(bump).clone()))?;
+        Ok(version.expect("set by update_manifest"))
+    }
+
+    /// Ensure sufficient capacity, growing if needed
+    ///
+    /// This method is called before allocating space for file operations.
+    /// If auto-growth is enabled and free space is insufficient, works out
+    /// the size the growth policy would step up to and grows there at once,
+    /// so a huge write costs one extension rather than a chain of them.
+    /// Fails with `OutOfSpace` before growing at all if even `max_blocks`
+    /// wouldn't be enough.
+    ///
+    /// Returns the block count from before growing, if it grew.
+    fn ensure_capacity(&mut self, bytes_needed: usize) -> Result<Option<usize>> {
+        if !self.auto_grow {
+            return Ok(None); // Manual management
+        }
+
+        let blocks_needed = bytes_needed.div_ceil(self.page_size());
+        let free = self.header.free_blocks as usize;
+        if free >= blocks_needed {
+            return Ok(None);
+        }
+
+        let current = self.header.total_blocks as usize;
+        let wanted = current + (blocks_needed - free);
+        if wanted > self.max_blocks {
+            return Err(CartridgeError::OutOfSpace);
+        }
+        let mut new_total = current;
+        while new_total < wanted {
+            new_total = self.growth.next_size(new_total, self.max_blocks);
+        }
+
+        tracing::info!("Growing container: {} -> {} blocks", current, new_total);
+        self.extend_to(current, new_total)?;
+        Ok(Some(current))
+    }
+
+    /// Allocate blocks for `bytes`, growing first if needed
+    ///
+    /// If the allocation fails anyway, growth made for it is undone rather
+    /// than leaving the file larger for nothing.
+    fn allocate_growing(&mut self, bytes: usize) -> Result<Vec<u64>> {
+        let grown_from = self.ensure_capacity(bytes)?;
+        self.allocator.allocate(bytes as u64).inspect_err(|_| {
+            if let Some(total) = grown_from {
+                self.shrink_to(total);
+            }
+        })
+    }
+
+    /// Grow once, directly to a size with at least `bytes` free
+    ///
+    /// Used by bulk operations that know their total size up front, to avoid
+    /// a chain of doublings (each of which extends the file). Respects
+    /// `max_blocks` and does nothing when auto-growth is disabled.
+    pub(crate) fn reserve_bytes(&mut self, bytes: u64) -> Result<()> {
+        if !self.auto_grow {
+            return Ok(());
+        }
+        self.reserve(bytes)
+    }
+
+    /// Grow the container now so at least `bytes` are free
+    ///
+    /// One extension to the final size instead of a series of doublings
+    /// mid-import. Works whether or not auto-growth is on; fails with
+    /// `OutOfSpace` past the size cap, or `InsufficientDiskSpace` if the
+    /// filesystem can't hold the larger file.
+    pub fn reserve(&mut self, bytes: u64) -> Result<()> {
+        self.ensure_writable()?;
+        let blocks_needed = bytes.div_ceil(self.page_size() as u64) as usize;
+        let free = self.header.free_blocks as usize;
+        if free >= blocks_needed {
+            return Ok(());
+        }
+
+        let current = self.header.total_blocks as usize;
+        let new_total = current + (blocks_needed - free);
+        if new_total > self.max_blocks {
+            return Err(CartridgeError::OutOfSpace);
+        }
+
+        tracing::info!("Reserving capacity: {} -> {} blocks", current, new_total);
+        self.extend_to(current, new_total)
+    }
+
+    /// Extend the file, header and allocator from `current` to `new_total` blocks
+    ///
+    /// Checks the filesystem has room first, failing with
+    /// `InsufficientDiskSpace` instead of running the disk dry part way.
+    /// A step that fails undoes the ones before it.
+    fn extend_to(&mut self, current: usize, new_total: usize) -> Result<()> {
+        let (old_blocks, new_blocks) = (current as u64, new_total as u64);
+
+        if let Some(file) = &self.file {
+            let f = file.lock();
+            let needed = (new_blocks - old_blocks) * f.slot_size() as u64;
+            if let Some(available) = f.available_space().filter(|&a| a < needed) {
+                return Err(CartridgeError::InsufficientDiskSpace { needed, available });
+            }
+        }
+
+        self.emit(|| CartridgeEvent::GrowStarted { old_blocks, new_blocks });
+
+        // Extend file (if disk-backed)
+        if let Some(file) = &self.file {
+            let mut f = file.lock();
+            if let Err(e) = f.extend(new_total) {
+                // Don't leave a partly extended file behind
+                if let Err(undo) = f.truncate(current) {
+                    tracing::warn!("Failed to undo file extension: {}", undo);
+                }
+                return Err(e);
+            }
+        }
+
+        // Update header total_blocks
+        self.header.total_blocks = new_blocks;
+
+        // Extend allocator capacity (this updates allocator's free_blocks)
+        if let Err(e) = self.allocator.extend_capacity(new_total) {
+            self.shrink_to(current);
+            return Err(e);
+        }
+
+        // Sync header free_blocks from allocator
+        self.header.free_blocks = self.allocator.free_blocks() as u64;
+        self.metadata_dirty = true;
+        telemetry::grew(new_blocks);
+
+        self.emit(|| CartridgeEvent::GrowFinished { old_blocks, new_blocks });
+        Ok(())
+    }
+
+    /// Undo growth, back down to `total` blocks
+    ///
+    /// Everything above `total` must still be free. Best effort: a failure
+    /// is logged and leaves the container at its larger size.
+    fn shrink_to(&mut self, total: usize) {
+        if let Err(e) = self.allocator.shrink_capacity(total) {
+            tracing::warn!("Failed to undo growth: {}", e);
+            self.header.free_blocks = self.allocator.free_blocks() as u64;
+            return;
+        }
+        if let Some(file) = &self.file {
+            if let Err(e) = file.lock().truncate(total) {
+                tracing::warn!("Failed to undo file extension: {}", e);
+            }
+        }
+        self.header.total_blocks = total as u64;
+        self.header.free_blocks = self.allocator.free_blocks() as u64;
+        telemetry::grew(total as u64);
+    }
+
+    /// Allocate blocks for `content` and write it to the page cache
     ///
-    /// This method is called before allocating space for file operations.
-    /// If auto-growth is enabled and free space is insufficient,
-    /// the container will automatically grow (potentially multiple times).
-    fn ensure_capacity(&mut self, bytes_needed: usize) -> Result<()> {
-        if !self.auto_grow {
-            return Ok(()); // Manual management
+    /// With dedup on, pages that are already stored reuse the existing
+    /// block; only new pages are allocated and written. Internal
+    /// `.cartridge/` files are never deduplicated.
+    fn store_content(&mut self, path: &str, content: &[u8]) -> Result<Vec<u64>> {
+        self.store_content_hashed(path, content, None)
+    }
+
+    /// [`store_content`](Self::store_content), feeding each page to
+    /// `digest` as it goes
+    fn store_content_hashed(
+        &mut self,
+        path: &str,
+        content: &[u8],
+        mut digest: Option<&mut Sha256>,
+    ) -> Result<Vec<u64>> {
+        if content.is_empty() {
+            return Ok(Vec::new());
+        }
+        if !self.dedup || path.starts_with("/.cartridge/") {
+            let blocks = self.allocate_growing(content.len())?;
+            self.write_content(path, &blocks, content, digest)?;
+            return Ok(blocks);
         }
 
-        let blocks_needed = (bytes_needed + PAGE_SIZE - 1) / PAGE_SIZE;
+        // Work out which pages are new before touching any counts, so a
+        // failed allocation leaves the index as it was
+        enum Source {
+            Existing(u64),
+            Fresh(usize),
+        }
+        let page_size = self.page_size();
+        let mut sources = Vec::with_capacity(content.len().div_ceil(page_size));
+        let mut fresh: Vec<dedup::PageHash> = Vec::new();
+        let mut fresh_by_hash: HashMap<dedup::PageHash, usize> = HashMap::new();
+        let mut fresh_content = Vec::new();
+        for chunk in content.chunks(page_size) {
+            if let Some(digest) = digest.as_deref_mut() {
+                digest.update(chunk);
+            }
+            let hash = dedup::page_hash(chunk, page_size);
+            if let Some(block) = self.dedup_index.lookup(&hash) {
+                sources.push(Source::Existing(block));
+            } else if let Some(&i) = fresh_by_hash.get(&hash) {
+                sources.push(Source::Fresh(i));
+            } else {
+                fresh_by_hash.insert(hash, fresh.len());
+                sources.push(Source::Fresh(fresh.len()));
+                fresh.push(hash);
+                fresh_content.extend_from_slice(chunk);
+                fresh_content.resize(fresh.len() * page_size, 0);
+            }
+        }
 
-        // Keep growing until we have enough free space
-        while (self.header.free_blocks as usize) < blocks_needed {
-            self.grow()?;
+        let new_blocks = if fresh.is_empty() {
+            Vec::new()
+        } else {
+            self.allocate_growing(fresh_content.len())?
+        };
+        self.write_content(path, &new_blocks, &fresh_content, None)?;
+
+        let refs = self.block_refs();
+        let mut inserted = vec![false; fresh.len()];
+        let mut blocks = Vec::with_capacity(sources.len());
+        for source in sources {
+            let block = match source {
+                Source::Existing(block) => {
+                    refs.add_ref(block);
+                    block
+                }
+                Source::Fresh(i) if !inserted[i] => {
+                    inserted[i] = true;
+                    refs.insert(new_blocks[i], fresh[i]);
+                    new_blocks[i]
+                }
+                Source::Fresh(i) => {
+                    refs.add_ref(new_blocks[i]);
+                    new_blocks[i]
+                }
+            };
+            blocks.push(block);
         }
+        Ok(blocks)
+    }
 
+    /// Insert a catalog entry whose blocks were just stored
+    ///
+    /// If the catalog refuses it, nothing references the new blocks, so
+    /// they are released again before the error is returned rather than
+    /// leaking until the next repair.
+    fn insert_staged(&mut self, path: &str, metadata: FileMetadata) -> Result<()> {
+        let staged = metadata.blocks.clone();
+        if let Err(e) = self.catalog_mut().insert(path, metadata) {
+            if let Err(free_err) = self.release_blocks(&staged) {
+                tracing::warn!("Failed to release blocks staged for {}: {}", path, free_err);
+            }
+            return Err(e);
+        }
         Ok(())
     }
 
-    /// Grow container capacity
+    /// Give up a file's claim on `blocks`, freeing those no other file shares
     ///
-    /// Doubles the container size (or grows to max_blocks limit).
-    /// Updates header, extends file, and extends allocator capacity.
-    fn grow(&mut self) -> Result<()> {
-        let current = self.header.total_blocks as usize;
-        let new_total = (current * GROW_FACTOR).min(self.max_blocks);
-
-        if new_total == current {
-            return Err(CartridgeError::OutOfSpace);
+    /// Holes have no block to free and are skipped.
+    fn release_blocks(&mut self, blocks: &[u64]) -> Result<()> {
+        let refs = self.block_refs();
+        let unused: Vec<u64> = blocks
+            .iter()
+            .copied()
+            .filter(|&block| block != HOLE_BLOCK && refs.release(block))
+            .collect();
+        if !unused.is_empty() {
+            self.allocator.free(&unused)?;
         }
+        Ok(())
+    }
 
-        tracing::info!("Growing container: {} -> {} blocks", current, new_total);
-
-        // Extend file (if disk-backed)
-        if let Some(file) = &self.file {
-            let mut f = file.lock();
-            f.extend(new_total)?;
+    /// Load the dedup hash index
+    ///
+    /// Cartridges that never used dedup have no index to load, but may
+    /// still share blocks between cloned files. Reference counts are left
+    /// for [`block_refs`](Self::block_refs) to take from the catalog.
+    fn load_dedup_index(&mut self) {
+        self.dedup_index = DedupIndex::default();
+        if self.exists(DEDUP_INDEX_PATH).unwrap_or(false) {
+            match self
+                .read_file(DEDUP_INDEX_PATH)
+                .and_then(|data| DedupIndex::from_bytes(&data))
+            {
+                Ok(index) => self.dedup_index = index,
+                Err(e) => tracing::warn!("Failed to load dedup index: {e}"),
+            }
         }
+    }
 
-        // Update header total_blocks
-        self.header.total_blocks = new_total as u64;
+    /// The dedup index with its reference counts, counting them from the
+    /// catalog the first time they're needed
+    ///
+    /// Anything that adds, drops or consults a reference goes through
+    /// here, so no block is freed on counts that were never taken.
+    fn block_refs(&mut self) -> &mut DedupIndex {
+        if !self.dedup_index.is_counted() {
+            self.recount_block_refs();
+        }
+        &mut self.dedup_index
+    }
 
-        // Extend allocator capacity (this updates allocator's free_blocks)
-        self.allocator.extend_capacity(new_total)?;
+    /// Reference counts for callers that only look: the index itself once
+    /// counted, otherwise a counted copy
+    fn counted_refs(&self) -> std::borrow::Cow<'_, DedupIndex> {
+        if self.dedup_index.is_counted() {
+            return std::borrow::Cow::Borrowed(&self.dedup_index);
+        }
+        let mut index = self.dedup_index.clone();
+        index.recount(self.catalog.iter_prefix(String::new()).map(|(_, metadata)| metadata));
+        std::borrow::Cow::Owned(index)
+    }
 
-        // Sync header free_blocks from allocator
-        self.header.free_blocks = self.allocator.free_blocks() as u64;
+    /// Recount references to shared blocks from the catalog's block lists
+    fn recount_block_refs(&mut self) {
+        let files = self.catalog.iter_prefix(String::new());
+        self.dedup_index.recount(files.map(|(_, metadata)| metadata));
+    }
 
+    /// Save the dedup hash index if it changed since it was last saved
+    fn persist_dedup_index(&mut self) -> Result<()> {
+        if !self.dedup_index.is_dirty() {
+            return Ok(());
+        }
+        let data = self.dedup_index.to_bytes()?;
+        self.system().write(DEDUP_INDEX_PATH, &data)?;
+        self.dedup_index.mark_saved();
         Ok(())
     }
 
-    /// Write content to blocks
-    fn write_content(&mut self, blocks: &[u64], content: &[u8]) -> Result<()> {
+    /// Write content to blocks, feeding each page to `digest` if given
+    ///
+    /// Writes of at least [`WRITE_PROGRESS_THRESHOLD`] bytes report progress
+    /// for `path` every [`WRITE_PROGRESS_BLOCKS`] blocks, with the cache
+    /// locks released in between.
+    fn write_content(
+        &mut self,
+        path: &str,
+        blocks: &[u64],
+        content: &[u8],
+        mut digest: Option<&mut Sha256>,
+    ) -> Result<()> {
+        let report = self.event_listener.is_some() && content.len() >= WRITE_PROGRESS_THRESHOLD;
+        let batch = if report { WRITE_PROGRESS_BLOCKS } else { blocks.len().max(1) };
+        let page_size = self.page_size();
         let mut offset = 0;
-        let mut pages = self.pages.lock();
-        let mut dirty_pages = self.dirty_pages.lock();
 
-        for &block_id in blocks {
-            let chunk_size = (content.len() - offset).min(PAGE_SIZE);
-            let chunk = &content[offset..offset + chunk_size];
+        for batch_blocks in blocks.chunks(batch) {
+            {
+                let mut pages = self.pages.lock();
+                let mut dirty_pages = self.dirty_pages.lock();
+
+                for &block_id in batch_blocks {
+                    let chunk_size = (content.len() - offset).min(page_size);
+                    let chunk = &content[offset..offset + chunk_size];
+                    if let Some(digest) = digest.as_deref_mut() {
+                        digest.update(chunk);
+                    }
+
+                    // Create page with content
+                    let mut page_data = vec![0u8; page_size];
+                    page_data[..chunk.len()].copy_from_slice(chunk);
 
-            // Create page with content
-            let mut page_data = vec![0u8; PAGE_SIZE];
-            page_data[..chunk.len()].copy_from_slice(chunk);
+                    // Store in cache
+                    pages.insert(block_id, Arc::new(page_data));
 
-            // Store in cache
-            pages.insert(block_id, page_data);
+                    // Mark as dirty for later flush
+                    dirty_pages.insert(block_id);
 
-            // Mark as dirty for later flush
-            dirty_pages.insert(block_id);
+                    offset += chunk_size;
+                    if offset >= content.len() {
+                        break;
+                    }
+                }
+            }
 
-            offset += chunk_size;
+            if report {
+                self.emit(|| CartridgeEvent::WriteProgress {
+                    path: path.to_string(),
+                    written: offset as u64,
+                    total: content.len() as u64,
+                });
+            }
             if offset >= content.len() {
                 break;
             }
@@ -1375,28 +4409,17 @@ impl Cartridge {
     }
 
     /// Read content from blocks
-    fn read_content(&self, blocks: &[u64], total_size: usize) -> Result<Vec<u8>> {
+    ///
+    /// `path` is only used to annotate checksum errors.
+    fn read_content(&self, path: &str, blocks: &[u64], total_size: usize) -> Result<Vec<u8>> {
         let mut content = Vec::with_capacity(total_size);
         let mut remaining = total_size;
-        let mut pages = self.pages.lock();
+        let page_size = self.page_size();
 
         for &block_id in blocks {
-            // Try to get from cache first, otherwise load from disk
-            let page_data = if let Some(data) = pages.get(&block_id) {
-                data.clone()
-            } else if let Some(ref file) = self.file {
-                // Load from disk and cache it
-                let data = file.lock().read_page_data(block_id)?;
-                pages.insert(block_id, data.clone());
-                data
-            } else {
-                return Err(CartridgeError::Allocation(format!(
-                    "Block {} not found in memory and no disk backing",
-                    block_id
-                )));
-            };
+            let page_data = self.load_page(path, block_id)?;
 
-            let chunk_size = remaining.min(PAGE_SIZE);
+            let chunk_size = remaining.min(page_size);
             content.extend_from_slice(&page_data[..chunk_size]);
 
             remaining -= chunk_size;
@@ -1408,12 +4431,47 @@ impl Cartridge {
         Ok(content)
     }
 
+    /// One content page of `path`, from the cache or else from disk
+    ///
+    /// A hole reads as a page of zeros.
+    pub(crate) fn load_page(&self, path: &str, block_id: u64) -> Result<Vec<u8>> {
+        if block_id == HOLE_BLOCK {
+            return Ok(vec![0u8; self.page_size()]);
+        }
+        Ok(self.cached_page(path, block_id)?.to_vec())
+    }
+
+    /// The cache's own buffer for one content page, loading it from disk
+    /// into the cache if needed
+    pub(crate) fn cached_page(&self, path: &str, block_id: u64) -> Result<Arc<Vec<u8>>> {
+        let mut pages = self.pages.lock();
+        if let Some(data) = pages.get(&block_id) {
+            telemetry::page_cache(true);
+            return Ok(Arc::clone(data));
+        }
+        telemetry::page_cache(false);
+        let Some(file) = &self.file else {
+            return Err(CartridgeError::Corruption(format!(
+                "Block {} not found in memory and no disk backing",
+                block_id
+            )));
+        };
+        // Load from disk and cache it
+        let data = Arc::new(
+            file.lock()
+                .read_page_data(block_id)
+                .map_err(|e| with_path(e, path))?,
+        );
+        pages.insert(block_id, Arc::clone(&data));
+        Ok(data)
+    }
+
     // =====================================================================
     // WAL + Incremental Vacuum
     // =====================================================================
 
     /// Path of the vacuum WAL file inside the VFS.
-    const VACUUM_WAL_PATH: &'static str = "wal/vacuum/wal.log";
+    pub(crate) const VACUUM_WAL_PATH: &'static str = "/wal/vacuum/wal.log";
     /// Directory containing WAL files.
     const WAL_DIR: &'static str = "wal";
     /// Subdirectory for vacuum WAL.
@@ -1433,7 +4491,7 @@ impl Cartridge {
         let total = self.header.total_blocks;
         let free = self.header.free_blocks;
         let entries = self.catalog.len();
-        let file_bytes = total * PAGE_SIZE as u64;
+        let file_bytes = total * self.page_size() as u64;
 
         // Dud detection: empty catalog in a non-empty file
         if entries == 0 && total > MIN_BLOCKS as u64 {
@@ -1488,7 +4546,7 @@ impl Cartridge {
 
         // Free blocks are reclaimable by truncation.
         let waste = free;
-        let waste_bytes = waste * PAGE_SIZE as u64;
+        let waste_bytes = waste * self.page_size() as u64;
         let waste_ratio = waste as f64 / total as f64;
 
         waste_ratio > 0.5 || waste_bytes > 10 * 1024 * 1024
@@ -1521,7 +4579,7 @@ impl Cartridge {
         }
 
         // Pre-allocate pages for the WAL file
-        let wal_size = wal::DEFAULT_WAL_PAGES * PAGE_SIZE;
+        let wal_size = wal::DEFAULT_WAL_PAGES * self.page_size();
         let zero_content = vec![0u8; wal_size];
         self.create_file(Self::VACUUM_WAL_PATH, &zero_content)?;
 
@@ -1543,24 +4601,37 @@ impl Cartridge {
     /// stale cached page content.
     fn apply_wal_write(&self, write: &crate::wal::WalWrite) -> Result<()> {
         // Update page cache — ensures flush() won't clobber WAL data
-        {
+        let page_size = self.page_size();
+        let updated_page = {
             let mut pages = self.pages.lock();
             let mut dirty = self.dirty_pages.lock();
-            let page = pages.entry(write.page_id).or_insert_with(|| {
-                if let Some(file) = &self.file {
-                    file.lock().read_page_data(write.page_id).unwrap_or_else(|_| vec![0u8; PAGE_SIZE])
+            let cached = pages.entry(write.page_id).or_insert_with(|| {
+                Arc::new(if let Some(file) = &self.file {
+                    file.lock()
+                        .read_page_data(write.page_id)
+                        .unwrap_or_else(|_| vec![0u8; page_size])
                 } else {
-                    vec![0u8; PAGE_SIZE]
-                }
+                    vec![0u8; page_size]
+                })
             });
+            // Copies the page first if a guard still holds it
+            let page = Arc::make_mut(cached);
             let end = write.offset_in_page + write.data.len();
             page[write.offset_in_page..end].copy_from_slice(&write.data);
             dirty.insert(write.page_id);
-        }
+            page.clone()
+        };
 
-        // Also write directly to disk for immediate durability
+        // Also write directly to disk for immediate durability. Encrypted or
+        // checksummed pages can't be patched in place, and the on-disk copy
+        // may not exist yet, so write the whole cached page instead.
         if let Some(file) = &self.file {
-            file.lock().write_at(write.page_id, write.offset_in_page, &write.data)?;
+            let mut file = file.lock();
+            if !file.patches_in_place() {
+                file.write_page_data(write.page_id, &updated_page)?;
+            } else {
+                file.write_at(write.page_id, write.offset_in_page, &write.data)?;
+            }
         }
         Ok(())
     }
@@ -1574,10 +4645,10 @@ impl Cartridge {
     }
 
     /// Read raw page data (bypass page cache, direct from disk or cache).
-    fn read_page_data_raw(&self, page_id: u64) -> Result<Vec<u8>> {
+    pub(crate) fn read_page_data_raw(&self, page_id: u64) -> Result<Vec<u8>> {
         let pages = self.pages.lock();
         if let Some(data) = pages.get(&page_id) {
-            return Ok(data.clone());
+            return Ok(data.to_vec());
         }
         drop(pages);
 
@@ -1585,7 +4656,7 @@ impl Cartridge {
             return file.lock().read_page_data(page_id);
         }
 
-        Err(CartridgeError::Allocation(format!(
+        Err(CartridgeError::Corruption(format!(
             "Page {} not found and no disk backing",
             page_id
         )))
@@ -1595,7 +4666,8 @@ impl Cartridge {
     ///
     /// This tells us which catalog entry owns each content page so we can
     /// update the `blocks` vec after relocating.
-    fn build_page_owner_map(&self) -> Result<std::collections::HashMap<u64, (String, usize)>> {
+    fn build_page_owner_map(&mut self) -> Result<std::collections::HashMap<u64, (String, usize)>> {
+        self.block_refs();
         let mut map = std::collections::HashMap::new();
 
         for (path, meta) in self.catalog.list_prefix("")? {
@@ -1604,13 +4676,75 @@ impl Cartridge {
                 continue;
             }
             for (idx, &page_id) in meta.blocks.iter().enumerate() {
-                map.insert(page_id, (path.clone(), idx));
+                if page_id != HOLE_BLOCK {
+                    map.insert(page_id, (path.clone(), idx));
+                }
             }
         }
 
+        // A block shared by several files (or several times by one) has no
+        // single owner to repoint, so it stays where it is
+        map.retain(|page_id, _| self.dedup_index.refs(*page_id).is_none_or(|refs| refs <= 1));
+
         Ok(map)
     }
 
+    /// Compact the cartridge and shrink the backing file
+    ///
+    /// Runs [`vacuum_step`](Self::vacuum_step) until every live page has been
+    /// moved toward the front of the file, then [`vacuum_finish`](Self::vacuum_finish)
+    /// to truncate the freed tail. Interrupting it is safe: relocation is
+    /// WAL-journaled and the truncate happens last.
+    ///
+    /// In-memory cartridges have nothing to reclaim and return an empty report.
+    pub fn vacuum(&mut self) -> Result<VacuumReport> {
+        self.ensure_writable()?;
+        let bytes_before = self.backing_file_len()?;
+        let blocks_before = self.header.total_blocks;
+
+        let mut pages_relocated = 0;
+        loop {
+            let progress = self.vacuum_step(VACUUM_BATCH_SIZE)?;
+            pages_relocated += progress.pages_relocated;
+            if progress.done || progress.pages_relocated == 0 {
+                break;
+            }
+        }
+        self.vacuum_finish()?;
+
+        let bytes_after = self.backing_file_len()?;
+        Ok(VacuumReport {
+            pages_relocated,
+            blocks_before,
+            blocks_after: self.header.total_blocks,
+            bytes_before,
+            bytes_after,
+            bytes_reclaimed: bytes_before.saturating_sub(bytes_after),
+        })
+    }
+
+    /// Length of the backing file in bytes, or 0 for in-memory cartridges
+    fn backing_file_len(&self) -> Result<u64> {
+        match &self.file {
+            Some(file) => file.lock().size_bytes(),
+            None => Ok(0),
+        }
+    }
+
+    /// SHA-256 of the whole `.cart` image, as [`to_bytes`](Self::to_bytes)
+    /// would return it
+    ///
+    /// Flushes first. Disk-backed cartridges are hashed straight from the
+    /// file without loading it.
+    pub(crate) fn image_sha256(&mut self) -> Result<[u8; 32]> {
+        if self.file.is_none() {
+            let bytes = self.to_bytes()?;
+            return Ok(Sha256::digest(bytes).into());
+        }
+        self.flush()?;
+        self.file.as_ref().expect("backing checked above").lock().sha256()
+    }
+
     /// Run one incremental vacuum step, relocating up to `batch_size` pages.
     ///
     /// Returns progress information. Call repeatedly until `done` is true,
@@ -1618,6 +4752,7 @@ impl Cartridge {
     ///
     /// Each page relocation is WAL-journaled. Crash at any point is safe.
     pub fn vacuum_step(&mut self, batch_size: usize) -> Result<VacuumProgress> {
+        self.ensure_writable()?;
         use crate::wal::{WalOp, WalState, fnv1a_hash};
 
         if self.file.is_none() {
@@ -1653,9 +4788,7 @@ impl Cartridge {
         }
         // Content pages (everything tracked by catalog, including WAL files in VFS)
         for (_, meta) in self.catalog.list_prefix("")? {
-            for &p in &meta.blocks {
-                live_pages.insert(p);
-            }
+            live_pages.extend(meta.data_blocks());
         }
 
         // Find the compact boundary: the smallest total_blocks where all live
@@ -1758,7 +4891,7 @@ impl Cartridge {
             {
                 let mut pages = self.pages.lock();
                 let mut dirty = self.dirty_pages.lock();
-                pages.insert(dest, page_content);
+                pages.insert(dest, Arc::new(page_content));
                 dirty.insert(dest);
                 // Write dest page to disk immediately
             }
@@ -1777,12 +4910,13 @@ impl Cartridge {
             // 3. Update catalog: point file's block from high_page to dest
             if let Some(mut meta) = self.catalog.get(path)? {
                 meta.blocks[block_index] = dest;
-                self.catalog.insert(path, meta)?;
+                self.catalog_mut().insert(path, meta)?;
             }
 
             // Free the old page in the allocator
             self.allocator.free(&[high_page])?;
             self.header.free_blocks = self.allocator.free_blocks() as u64;
+            self.dedup_index.relocate(high_page, dest);
 
             // Remove old page from cache
             {
@@ -1804,7 +4938,7 @@ impl Cartridge {
             .count()
             .saturating_sub(moves_planned);
 
-        let reclaimable = (current_total - min_boundary) * PAGE_SIZE as u64;
+        let reclaimable = (current_total - min_boundary) * self.page_size() as u64;
 
         // If no more work to do, clear the WAL
         let done = remaining == 0 && moves_planned == 0;
@@ -1816,12 +4950,14 @@ impl Cartridge {
             self.sync_file()?;
         }
 
-        Ok(VacuumProgress {
+        let progress = VacuumProgress {
             pages_relocated: moves_planned,
             pages_remaining: remaining,
             bytes_reclaimable: reclaimable,
             done: remaining == 0,
-        })
+        };
+        self.emit(|| CartridgeEvent::VacuumProgress(progress.clone()));
+        Ok(progress)
     }
 
     /// Finish vacuum: truncate the file to reclaim disk space.
@@ -1829,6 +4965,7 @@ impl Cartridge {
     /// Call this after `vacuum_step()` returns `done: true`.
     /// Shrinks the allocator, truncates the backing file, and flushes.
     pub fn vacuum_finish(&mut self) -> Result<u64> {
+        self.ensure_writable()?;
         // Recompute the high water mark
         let mut max_live: u64 = 2; // minimum: pages 0, 1, 2
         for &p in &self.catalog_overflow_pages {
@@ -1838,7 +4975,7 @@ impl Cartridge {
             max_live = max_live.max(p);
         }
         for (_, meta) in self.catalog.list_prefix("")? {
-            for &p in &meta.blocks {
+            for p in meta.data_blocks() {
                 max_live = max_live.max(p);
             }
         }
@@ -1850,7 +4987,11 @@ impl Cartridge {
             return Ok(0); // Nothing to truncate
         }
 
-        let bytes_freed = ((old_total - new_total) * PAGE_SIZE) as u64;
+        let slot_size = self
+            .file
+            .as_ref()
+            .map_or(self.page_size(), |f| f.lock().slot_size());
+        let bytes_freed = ((old_total - new_total) * slot_size) as u64;
 
         tracing::info!(
             "Vacuum truncate: {} -> {} blocks ({} bytes reclaimed)",
@@ -1859,26 +5000,94 @@ impl Cartridge {
             bytes_freed
         );
 
-        // Shrink allocator
-        self.allocator.shrink_capacity(new_total)?;
-        self.header.total_blocks = new_total as u64;
-        self.header.free_blocks = self.allocator.free_blocks() as u64;
+        // Shrink the allocator, rebuilding it so the remaining free space
+        // collapses into clean extents instead of inheriting old fragments
+        self.rebuild_allocator(new_total)?;
 
-        // Flush catalog + allocator + header to their (now lower) pages
+        // Flush catalog + allocator + header to their (now lower) pages.
+        // The truncate comes last: a crash before it only leaves unused
+        // trailing pages behind.
         self.flush()?;
 
         // Truncate the backing file
         if let Some(file) = &self.file {
-            file.lock().shrink(new_total)?;
+            file.lock().truncate(new_total)?;
         }
 
         Ok(bytes_freed)
     }
 
+    /// Move up to `budget_blocks` file blocks to coalesce free space
+    ///
+    /// Each move takes the highest-numbered file block that has a free block
+    /// below it and puts it in the lowest free block, so repeated calls pack
+    /// live data toward the front and leave free space as one run at the
+    /// end. Unlike [`vacuum`](Self::vacuum) the file isn't truncated, which
+    /// keeps each call cheap enough to spread over idle periods.
+    ///
+    /// Catalog and allocator pages, vacuum journal pages and blocks shared
+    /// through dedup stay where they are. The moves reach disk on the next
+    /// flush, as one atomic commit on journaled cartridges.
+    pub fn defragment(&mut self, budget_blocks: usize) -> Result<DefragReport> {
+        self.ensure_writable()?;
+
+        let owner_map = self.build_page_owner_map()?;
+        let mut movable: Vec<u64> = owner_map.keys().copied().collect();
+        movable.sort_unstable_by(|a, b| b.cmp(a)); // highest first
+
+        // Lowest block not yet checked for being free
+        let mut cursor = 3;
+        let mut blocks_moved = 0;
+        let mut blocks_remaining = 0;
+        for src in movable {
+            while cursor < src && self.allocator.is_allocated(cursor) {
+                cursor += 1;
+            }
+            if cursor >= src {
+                break;
+            }
+            let dest = cursor;
+            cursor += 1;
+            if blocks_moved == budget_blocks {
+                blocks_remaining += 1;
+                continue;
+            }
+
+            let content = self.read_page_data_raw(src)?;
+            {
+                let mut pages = self.pages.lock();
+                pages.insert(dest, Arc::new(content));
+                pages.remove(&src);
+                self.dirty_pages.lock().insert(dest);
+            }
+
+            let (ref path, block_index) = owner_map[&src];
+            if let Some(mut meta) = self.catalog.get(path)? {
+                meta.blocks[block_index] = dest;
+                self.catalog_mut().insert(path, meta)?;
+            }
+            self.allocator.mark_pages_allocated(&[dest])?;
+            self.allocator.free(&[src])?;
+            self.dedup_index.relocate(src, dest);
+            blocks_moved += 1;
+        }
+        self.header.free_blocks = self.allocator.free_blocks() as u64;
+
+        let stats = self.allocator.stats();
+        Ok(DefragReport {
+            blocks_moved,
+            blocks_remaining,
+            free_extent_count: stats.free_extent_count,
+            largest_free_extent: stats.largest_free_extent,
+            done: blocks_remaining == 0,
+        })
+    }
+
     /// Recover from a crashed vacuum by replaying or discarding WAL entries.
     ///
     /// Called automatically by `open()` if a dirty WAL is found.
     pub fn recover_vacuum_wal(&mut self) -> Result<usize> {
+        self.ensure_writable()?;
         use crate::wal::WalState;
 
         if !self.exists(Self::VACUUM_WAL_PATH)? {
@@ -1928,6 +5137,7 @@ impl Cartridge {
                         if !owner_map.contains_key(&entry.dest_page) {
                             self.allocator.free(&[entry.dest_page])?;
                             self.header.free_blocks = self.allocator.free_blocks() as u64;
+                            self.metadata_dirty = true;
                         }
                     }
                     recovered += 1;
@@ -1953,6 +5163,48 @@ impl Cartridge {
     }
 }
 
+/// Allocator and catalog as loaded from disk
+struct Structures {
+    allocator: HybridAllocator,
+    allocator_overflow_pages: Vec<u64>,
+    catalog: Catalog,
+    catalog_overflow_pages: Vec<u64>,
+    /// Loaded from an older layout that the next flush should replace
+    needs_rewrite: bool,
+}
+
+/// A header's flush generation and time, which change on every flush that
+/// writes anything
+fn stamp(header: &Header) -> (u64, u64) {
+    (header.generation(), header.last_flush_us())
+}
+
+/// Result of [`Cartridge::refresh`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefreshReport {
+    /// Whether another handle had flushed since this one last looked
+    pub changed: bool,
+    /// Flush generation of the state this handle now reflects
+    pub generation: u64,
+    /// Cached pages dropped because they may be stale
+    pub pages_dropped: usize,
+}
+
+/// Result of one [`Cartridge::defragment`] call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefragReport {
+    /// File blocks relocated by this call
+    pub blocks_moved: usize,
+    /// Moves still left after this call
+    pub blocks_remaining: usize,
+    /// Separate runs of free blocks afterwards
+    pub free_extent_count: usize,
+    /// Longest run of free blocks afterwards
+    pub largest_free_extent: u64,
+    /// True when no file block has a free block below it
+    pub done: bool,
+}
+
 /// Progress of an incremental vacuum operation.
 #[derive(Debug, Clone)]
 pub struct VacuumProgress {
@@ -1966,6 +5218,109 @@ pub struct VacuumProgress {
     pub done: bool,
 }
 
+/// Reject empty keys, control characters and keys the cartridge reserves
+fn validate_metadata_key(key: &str) -> Result<()> {
+    if key.is_empty() {
+        return Err(CartridgeError::InvalidMetadata("empty key".to_string()));
+    }
+    if key.chars().any(char::is_control) {
+        return Err(CartridgeError::InvalidMetadata(format!(
+            "key {:?} contains control characters",
+            key
+        )));
+    }
+    if RESERVED_METADATA_KEYS.contains(&key) {
+        return Err(CartridgeError::InvalidMetadata(format!(
+            "key {:?} is reserved",
+            key
+        )));
+    }
+    Ok(())
+}
+
+/// A file's content as it is to be stored, and how it was encrypted
+struct Sealed {
+    content: Vec<u8>,
+    encrypted: bool,
+    /// Nonce of content encrypted with a key of the file's own
+    nonce: Option<[u8; NONCE_SIZE]>,
+}
+
+impl Sealed {
+    /// Hasher for the content hash, which only plaintext gets
+    ///
+    /// A digest of the plaintext in the unencrypted catalog would let anyone
+    /// who can read the catalog confirm a guess at an encrypted file.
+    fn digest(&self) -> Option<Sha256> {
+        (!self.encrypted).then(Sha256::new)
+    }
+
+    /// Record how the content was encrypted in the file's user metadata
+    fn mark(&self, metadata: &mut FileMetadata) {
+        let user_metadata = &mut metadata.user_metadata;
+        if self.encrypted {
+            user_metadata.insert("encrypted".to_string(), "true".to_string());
+            user_metadata.insert("encrypted_size".to_string(), self.content.len().to_string());
+        } else {
+            user_metadata.remove("encrypted");
+            user_metadata.remove("encrypted_size");
+        }
+        match self.nonce {
+            Some(nonce) => {
+                let hex = nonce.iter().map(|b| format!("{:02x}", b)).collect();
+                user_metadata.insert(FILE_NONCE_KEY.to_string(), hex);
+            }
+            None => {
+                user_metadata.remove(FILE_NONCE_KEY);
+            }
+        }
+    }
+}
+
+/// SHA-256 state after the whole content of `path`, as last written
+struct ContentHasher {
+    path: String,
+    hasher: Sha256,
+}
+
+/// Parse a nonce stored by [`Sealed::mark`]
+fn parse_nonce(hex: &str) -> Option<[u8; NONCE_SIZE]> {
+    if hex.len() != NONCE_SIZE * 2 || !hex.is_ascii() {
+        return None;
+    }
+    let mut nonce = [0u8; NONCE_SIZE];
+    for (byte, pair) in nonce.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(nonce)
+}
+
+/// Metadata snapshot taken by [`Cartridge::checkpoint`]
+pub(crate) struct Checkpoint {
+    header: Header,
+    catalog: Catalog,
+    allocator: HybridAllocator,
+    quotas: Quotas,
+    dedup_index: DedupIndex,
+}
+
+/// Result of a full [`Cartridge::vacuum`].
+#[derive(Debug, Clone)]
+pub struct VacuumReport {
+    /// Pages moved toward the front of the file.
+    pub pages_relocated: usize,
+    /// Total blocks before vacuum.
+    pub blocks_before: u64,
+    /// Total blocks after vacuum.
+    pub blocks_after: u64,
+    /// Backing file size before vacuum.
+    pub bytes_before: u64,
+    /// Backing file size after vacuum.
+    pub bytes_after: u64,
+    /// Bytes returned to the filesystem.
+    pub bytes_reclaimed: u64,
+}
+
 impl Drop for Cartridge {
     fn drop(&mut self) {
         // Automatically flush on drop to prevent data loss
@@ -1973,6 +5328,12 @@ impl Drop for Cartridge {
             if let Err(e) = self.flush() {
                 tracing::warn!("Failed to flush cartridge on drop: {}", e);
             }
+            let mut file = self.file.as_ref().unwrap().lock();
+            if file.needs_sync() {
+                if let Err(e) = file.sync() {
+                    tracing::warn!("Failed to sync cartridge on drop: {}", e);
+                }
+            }
         }
     }
 }
@@ -1986,8 +5347,71 @@ pub struct CartridgeStats {
     pub fragmentation: f64,
     /// Disk path of the backing file, or `None` for in-memory cartridges.
     pub path: Option<std::path::PathBuf>,
-    /// Size of the backing file in bytes, or 0 for in-memory cartridges.
+    /// Size of the backing file (or memory image) in bytes, or 0 for
+    /// in-memory cartridges that have neither.
+    pub file_size_bytes: u64,
+    /// Usage and fragmentation per allocation strategy
+    pub allocator: AllocatorStats,
+    /// Generation of the last flush that wrote the catalog and allocator,
+    /// or 0 if it hasn't been tracked yet
+    pub generation: u64,
+    /// When the cartridge was last flushed, in microseconds since the Unix
+    /// epoch, or 0 if unknown
+    pub last_flush_us: u64,
+}
+
+/// Usage figures from [`Cartridge::detailed_stats`]
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct DetailedStats {
+    pub total_blocks: u64,
+    pub free_blocks: u64,
+    pub used_blocks: u64,
+    pub fragmentation: f64,
+    /// Sum of the sizes of all regular files
+    pub logical_bytes: u64,
+    /// `used_blocks * PAGE_SIZE`, including catalog and allocator pages
+    pub physical_bytes: u64,
+    /// Part of `logical_bytes` in holes of sparse files, which take no blocks
+    pub hole_bytes: u64,
+    pub file_count: u64,
+    pub dir_count: u64,
+    pub symlink_count: u64,
+    /// Blocks used by more than one file, through dedup or clones; each is
+    /// counted once in `used_blocks`
+    pub shared_blocks: u64,
+    /// Pages held in the in-memory page cache
+    pub cached_pages: usize,
+    /// Cached pages not yet flushed to disk
+    pub dirty_pages: usize,
+    /// Auto-growth ceiling
+    pub max_blocks: u64,
+    /// Size of the backing file in bytes, or 0 for in-memory cartridges
     pub file_size_bytes: u64,
+    /// IAM decision cache counts, if a policy is set
+    pub policy_cache: Option<CacheStats>,
+}
+
+/// Attach the file path to a checksum error raised while reading its pages
+fn with_path(err: CartridgeError, path: &str) -> CartridgeError {
+    match err {
+        CartridgeError::ChecksumMismatch { page, path: None } => CartridgeError::ChecksumMismatch {
+            page,
+            path: Some(path.to_string()),
+        },
+        other => other,
+    }
+}
+
+/// Helper function to convert Action enum to lowercase string for capabilities
+fn action_to_string_lower(action: &crate::iam::Action) -> &'static str {
+    match action {
+        crate::iam::Action::Read => "read",
+        crate::iam::Action::Write => "write",
+        crate::iam::Action::Delete => "delete",
+        crate::iam::Action::List => "list",
+        crate::iam::Action::Create => "create",
+        crate::iam::Action::All => "*",
+    }
 }
 
 #[cfg(test)]
@@ -2017,14 +5441,246 @@ mod tests {
     }
 
     #[test]
-    fn test_append_file() {
-        let mut cart = Cartridge::new(1000);
+    fn test_write_file_atomic_frees_old_blocks_last() {
+        let mut cart = Cartridge::new(1000);
+        cart.set_inline_threshold(0);
+        let old = vec![1u8; 3 * PAGE_SIZE];
+        cart.create_file("data.bin", &old).unwrap();
+        let old_blocks = cart.catalog.get("/data.bin").unwrap().unwrap().blocks;
+        let free_before = cart.allocator.free_blocks();
+
+        let new = vec![2u8; 3 * PAGE_SIZE];
+        cart.write_file_atomic("data.bin", &new).unwrap();
+        assert_eq!(cart.read_file("data.bin").unwrap(), new);
+
+        // The new content went to blocks that were free while the old ones
+        // were still allocated, and the old ones are free now
+        let new_blocks = cart.catalog.get("/data.bin").unwrap().unwrap().blocks;
+        assert!(new_blocks.iter().all(|b| !old_blocks.contains(b)));
+        assert!(old_blocks.iter().all(|&b| !cart.allocator.is_allocated(b)));
+        assert_eq!(cart.allocator.free_blocks(), free_before);
+    }
+
+    #[test]
+    fn test_write_file_atomic_keeps_old_content_when_out_of_space() {
+        let mut cart = Cartridge::new(1000);
+        cart.set_auto_grow(false);
+        cart.set_inline_threshold(0);
+        let free = cart.allocator.free_blocks();
+        let old = vec![1u8; (free / 2 + 1) * PAGE_SIZE];
+        cart.create_file("data.bin", &old).unwrap();
+
+        // Both copies don't fit at once, so nothing changes, with either
+        // kind of write
+        let new = vec![2u8; old.len()];
+        assert!(cart.write_file_atomic("data.bin", &new).is_err());
+        assert_eq!(cart.read_file("data.bin").unwrap(), old);
+        assert!(cart.write_file("data.bin", &new).is_err());
+        assert_eq!(cart.read_file("data.bin").unwrap(), old);
+        assert_eq!(cart.allocator.free_blocks(), free - old.len() / PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_failed_catalog_insert_frees_staged_blocks() {
+        let mut cart = Cartridge::new(1000);
+        cart.set_inline_threshold(0);
+        let free = cart.allocator.free_blocks();
+
+        // A create whose catalog insert fails leaves no trace
+        cart.catalog.fail_next_insert();
+        assert!(cart.create_file("new.bin", &[7u8; 3 * PAGE_SIZE]).is_err());
+        assert!(!cart.exists("new.bin").unwrap());
+        assert_eq!(cart.allocator.free_blocks(), free);
+        assert_eq!(cart.header.free_blocks, free as u64);
+
+        // A replace whose insert fails keeps the original and its blocks
+        let old = vec![1u8; 2 * PAGE_SIZE];
+        cart.create_file("data.bin", &old).unwrap();
+        let free = cart.allocator.free_blocks();
+        cart.catalog.fail_next_insert();
+        assert!(cart.write_file("data.bin", &[2u8; 5 * PAGE_SIZE]).is_err());
+        assert_eq!(cart.read_file("data.bin").unwrap(), old);
+        assert_eq!(cart.allocator.free_blocks(), free);
+
+        // With dedup, shared pages get their references back too
+        cart.set_dedup(true);
+        cart.write_file("data.bin", &old).unwrap();
+        let free = cart.allocator.free_blocks();
+        cart.catalog.fail_next_insert();
+        assert!(cart.create_file("copy.bin", &old).is_err());
+        assert_eq!(cart.allocator.free_blocks(), free);
+        cart.delete_file("data.bin").unwrap();
+        assert_eq!(cart.allocator.free_blocks(), free + 1);
+    }
+
+    #[test]
+    fn test_append_file() {
+        let mut cart = Cartridge::new(1000);
+
+        cart.create_file("test.txt", b"Hello").unwrap();
+        cart.append_file("test.txt", b", World!").unwrap();
+
+        let read = cart.read_file("test.txt").unwrap();
+        assert_eq!(read, b"Hello, World!");
+    }
+
+    #[test]
+    fn test_append_extends_tail_in_place() {
+        let mut cart = Cartridge::new(1000);
+        cart.set_inline_threshold(0);
+
+        // Empty file: nothing to extend yet
+        cart.create_file("log", b"").unwrap();
+        cart.append_file("log", b"first line\n").unwrap();
+        let blocks = cart.metadata("log").unwrap().blocks;
+        assert_eq!(blocks.len(), 1);
+
+        // Fits in the tail block
+        cart.append_file("log", b"second line\n").unwrap();
+        assert_eq!(cart.metadata("log").unwrap().blocks, blocks);
+
+        // Spans several new blocks; the old ones stay put
+        let big = vec![b'x'; 3 * PAGE_SIZE];
+        cart.append_file("log", &big).unwrap();
+        let metadata = cart.metadata("log").unwrap();
+        assert_eq!(metadata.blocks.len(), 4);
+        assert_eq!(metadata.blocks[0], blocks[0]);
+
+        let mut expected = b"first line\nsecond line\n".to_vec();
+        expected.extend_from_slice(&big);
+        assert_eq!(metadata.size, expected.len() as u64);
+        assert_eq!(cart.read_file("log").unwrap(), expected);
+        assert!(cart.check().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_append_matches_rewrite_randomized() {
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0x5EED);
+        let mut cart = Cartridge::new(100);
+        cart.create_file("fast", b"").unwrap();
+        cart.create_file("naive", b"").unwrap();
+
+        for _ in 0..60 {
+            let len = match rng.gen_range(0..4) {
+                0 => rng.gen_range(0..16),
+                1 => rng.gen_range(0..PAGE_SIZE),
+                2 => PAGE_SIZE - cart.metadata("fast").unwrap().size as usize % PAGE_SIZE,
+                _ => rng.gen_range(PAGE_SIZE..3 * PAGE_SIZE),
+            };
+            let chunk: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+
+            cart.append_file("fast", &chunk).unwrap();
+            let mut naive = cart.read_file("naive").unwrap();
+            naive.extend_from_slice(&chunk);
+            cart.write_file("naive", &naive).unwrap();
+
+            assert_eq!(cart.read_file("fast").unwrap(), naive);
+        }
+        assert!(cart.check().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_write_at_patches_only_touched_pages() {
+        let mut cart = Cartridge::new(100);
+        cart.set_inline_threshold(0);
+        let mut expected: Vec<u8> = (0..2 * PAGE_SIZE + 100).map(|i| (i % 251) as u8).collect();
+        cart.create_file("data", &expected).unwrap();
+        let blocks = cart.metadata("data").unwrap().blocks;
+
+        // Entirely within one block
+        cart.write_at("data", 10, b"patch").unwrap();
+        expected[10..15].copy_from_slice(b"patch");
+
+        // Straddling the final partial block and the end of the file
+        let straddle = vec![0xEE; 300];
+        let at = 2 * PAGE_SIZE - 100;
+        cart.write_at("data", at as u64, &straddle).unwrap();
+        expected.resize(at + 300, 0);
+        expected[at..].copy_from_slice(&straddle);
+
+        assert_eq!(cart.read_file("data").unwrap(), expected);
+        assert_eq!(cart.metadata("data").unwrap().blocks, blocks);
+
+        // Past the end: the gap reads as zeros and new blocks are added
+        let far = 4 * PAGE_SIZE + 7;
+        cart.write_at("data", far as u64, b"end").unwrap();
+        expected.resize(far, 0);
+        expected.extend_from_slice(b"end");
+        let metadata = cart.metadata("data").unwrap();
+        assert_eq!(metadata.size, expected.len() as u64);
+        assert_eq!(metadata.blocks[..3], blocks[..]);
+        assert_eq!(cart.read_file("data").unwrap(), expected);
+        assert!(cart.check().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_truncate_shrinks_and_extends() {
+        let mut cart = Cartridge::new(100);
+        cart.set_inline_threshold(0);
+        let content = vec![0xAB; 3 * PAGE_SIZE];
+        cart.create_file("log", &content).unwrap();
+        let used = cart.stats().used_blocks;
+
+        cart.truncate("log", PAGE_SIZE as u64 + 10).unwrap();
+        assert_eq!(cart.stats().used_blocks, used - 1);
+        assert_eq!(cart.read_file("log").unwrap(), content[..PAGE_SIZE + 10]);
+
+        // Growing again must not bring back the cut-off bytes
+        cart.truncate("log", 2 * PAGE_SIZE as u64 + 5).unwrap();
+        let mut expected = content[..PAGE_SIZE + 10].to_vec();
+        expected.resize(2 * PAGE_SIZE + 5, 0);
+        assert_eq!(cart.read_file("log").unwrap(), expected);
+
+        cart.truncate("log", 0).unwrap();
+        assert!(cart.read_file("log").unwrap().is_empty());
+        assert!(cart.metadata("log").unwrap().blocks.is_empty());
+        assert!(cart.check().unwrap().is_consistent());
+
+        // Inline files go through a rewrite
+        cart.set_inline_threshold(DEFAULT_INLINE_THRESHOLD);
+        cart.create_file("small", b"hello world").unwrap();
+        cart.truncate("small", 5).unwrap();
+        cart.write_at("small", 7, b"!").unwrap();
+        assert_eq!(cart.read_file("small").unwrap(), b"hello\0\0!");
+    }
+
+    #[test]
+    fn test_partial_writes_are_policy_checked() {
+        use crate::iam::{Effect, Statement};
 
-        cart.create_file("test.txt", b"Hello").unwrap();
-        cart.append_file("test.txt", b", World!").unwrap();
+        let mut cart = Cartridge::new(100);
+        cart.create_file("/logs/app.log", b"line\n").unwrap();
+        let mut policy = Policy::new();
+        policy.add_statement(Statement::new(
+            Effect::Allow,
+            vec![Action::Read],
+            vec!["/logs/**".to_string()],
+        ));
+        cart.set_policy(policy);
 
-        let read = cart.read_file("test.txt").unwrap();
-        assert_eq!(read, b"Hello, World!");
+        assert!(cart.write_at("/logs/app.log", 0, b"x").is_err());
+        assert!(cart.truncate("/logs/app.log", 0).is_err());
+        assert!(cart.append_file("/logs/app.log", b"x").is_err());
+        assert_eq!(cart.read_file("/logs/app.log").unwrap(), b"line\n");
+    }
+
+    #[test]
+    fn test_append_copies_shared_tail() {
+        let mut cart = Cartridge::new(100);
+        cart.set_dedup(true);
+        let content = vec![9u8; PAGE_SIZE + 100];
+        cart.create_file("a", &content).unwrap();
+        cart.create_file("b", &content).unwrap();
+
+        cart.append_file("a", b"more").unwrap();
+
+        let mut appended = content.clone();
+        appended.extend_from_slice(b"more");
+        assert_eq!(cart.read_file("a").unwrap(), appended);
+        assert_eq!(cart.read_file("b").unwrap(), content);
+        assert!(cart.check().unwrap().is_consistent());
     }
 
     #[test]
@@ -2063,6 +5719,84 @@ mod tests {
         assert_eq!(other_files.len(), 1);
     }
 
+    #[test]
+    fn test_path_type() {
+        let mut cart = Cartridge::new(1000);
+        cart.create_dir("empty").unwrap();
+        cart.create_file("docs/a.txt", b"a").unwrap();
+        cart.symlink("docs/a.txt", "link").unwrap();
+
+        assert_eq!(cart.path_type("/").unwrap(), Some(FileType::Directory));
+        assert_eq!(cart.path_type("empty").unwrap(), Some(FileType::Directory));
+        assert_eq!(cart.path_type("docs").unwrap(), Some(FileType::Directory));
+        assert_eq!(cart.path_type("docs/a.txt").unwrap(), Some(FileType::File));
+        assert_eq!(cart.path_type("link").unwrap(), Some(FileType::Symlink));
+        assert_eq!(cart.path_type("missing").unwrap(), None);
+        // A sibling sharing the name as a prefix isn't a child
+        assert_eq!(cart.path_type("doc").unwrap(), None);
+    }
+
+    #[test]
+    fn test_path_type_does_not_scan_large_directories() {
+        let mut cart = Cartridge::new(1000);
+        for i in 0..50_000 {
+            cart.catalog_mut()
+                .insert(&format!("/big/{:05}", i), FileMetadata::new(FileType::File, 0, vec![]))
+                .unwrap();
+        }
+
+        // Listing the directory each time would copy 50M catalog entries
+        let start = std::time::Instant::now();
+        for _ in 0..1000 {
+            assert_eq!(cart.path_type("big").unwrap(), Some(FileType::Directory));
+        }
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_list_dir_with_metadata() {
+        let mut cart = Cartridge::new(1000);
+
+        cart.create_file("/home/file1.txt", b"1").unwrap();
+        cart.create_file("/home/file2.txt", b"22").unwrap();
+        cart.create_dir("/home/sub").unwrap();
+
+        let listed = cart.list_dir_with_metadata("/home").unwrap();
+        let paths: Vec<&str> = listed.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(paths, cart.list_dir("/home").unwrap());
+        assert_eq!(listed[1].1.size, 2);
+        assert!(listed[2].1.is_directory());
+    }
+
+    #[test]
+    fn test_errors_are_typed() {
+        let mut cart = Cartridge::new(1000);
+        cart.create_file("/home/file.txt", b"1").unwrap();
+        cart.create_dir("/home/sub").unwrap();
+
+        assert!(matches!(
+            cart.read_file("/home/missing.txt"),
+            Err(CartridgeError::NotFound { path }) if path == "/home/missing.txt"
+        ));
+        assert!(matches!(
+            cart.create_file("/home/file.txt", b"2"),
+            Err(CartridgeError::AlreadyExists { .. })
+        ));
+        assert!(matches!(
+            cart.rename("/home/sub", "/home/file.txt"),
+            Err(CartridgeError::AlreadyExists { .. })
+        ));
+        assert!(matches!(
+            cart.read_file("/home/sub"),
+            Err(CartridgeError::NotAFile { .. })
+        ));
+        assert!(matches!(
+            cart.list_dir("/home/file.txt"),
+            Err(CartridgeError::NotADirectory { .. })
+        ));
+        assert!(matches!(cart.read_manifest(), Err(CartridgeError::ManifestNotFound)));
+    }
+
     #[test]
     fn test_large_file() {
         let mut cart = Cartridge::new(1000);
@@ -2079,6 +5813,7 @@ mod tests {
     #[test]
     fn test_stats() {
         let mut cart = Cartridge::new(1000);
+        cart.set_inline_threshold(0);
 
         let stats = cart.stats();
         assert_eq!(stats.total_blocks, 1000);
@@ -2095,7 +5830,7 @@ mod tests {
 
     #[test]
     fn test_file_not_found() {
-        let mut cart = Cartridge::new(1000);
+        let cart = Cartridge::new(1000);
         let result = cart.read_file("nonexistent.txt");
         assert!(result.is_err());
     }
@@ -2127,7 +5862,7 @@ mod tests {
 
         // Try to reopen
         {
-            let mut cart = Cartridge::open(&path).unwrap();
+            let cart = Cartridge::open(&path).unwrap();
             let content = cart.read_file("test.txt").unwrap();
             assert_eq!(content, b"Hello, Disk!");
         }
@@ -2150,7 +5885,7 @@ mod tests {
 
         // Reopen and verify
         {
-            let mut cart = Cartridge::open(&path).unwrap();
+            let cart = Cartridge::open(&path).unwrap();
 
             let content = cart.read_file("test.txt").unwrap();
             assert_eq!(content, b"Hello, World!");
@@ -2182,7 +5917,7 @@ mod tests {
 
         // Reopen and verify
         {
-            let mut cart = Cartridge::open(&path).unwrap();
+            let cart = Cartridge::open(&path).unwrap();
             let read_content = cart.read_file("large.bin").unwrap();
             assert_eq!(read_content.len(), 100 * 1024);
             assert_eq!(read_content, large_content);
@@ -2211,7 +5946,7 @@ mod tests {
 
         // Verify modification persisted
         {
-            let mut cart = Cartridge::open(&path).unwrap();
+            let cart = Cartridge::open(&path).unwrap();
             let content = cart.read_file("test.txt").unwrap();
             assert_eq!(content, b"modified content");
         }
@@ -2241,7 +5976,7 @@ mod tests {
 
         // Verify deletion persisted
         {
-            let mut cart = Cartridge::open(&path).unwrap();
+            let cart = Cartridge::open(&path).unwrap();
             assert!(cart.exists("file1.txt").unwrap());
             assert!(!cart.exists("file2.txt").unwrap());
             assert!(cart.exists("file3.txt").unwrap());
@@ -2297,6 +6032,67 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_flush_generation() {
+        use tempfile::TempDir;
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("generation.cart");
+
+        let mut cart = Cartridge::create_at(&path, "test", "Test Container").unwrap();
+        let first = cart.stats().generation;
+        assert!(first > 0);
+        assert!(cart.stats().last_flush_us > 0);
+
+        cart.create_file("test.txt", b"Hello").unwrap();
+        cart.flush().unwrap();
+        assert_eq!(cart.stats().generation, first + 1);
+        // Nothing changed, so the catalog and allocator aren't rewritten
+        cart.flush().unwrap();
+        assert_eq!(cart.stats().generation, first + 1);
+        cart.close().unwrap();
+
+        let cart = Cartridge::open(&path).unwrap();
+        assert_eq!(cart.stats().generation, first + 1);
+    }
+
+    #[test]
+    fn test_torn_write_detected_on_open() {
+        use tempfile::TempDir;
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("torn.cart");
+
+        let mut cart = Cartridge::create_at(&path, "test", "Test Container").unwrap();
+        cart.create_file("test.txt", b"Hello").unwrap();
+        cart.close().unwrap();
+        let generation = Cartridge::open_read_only(&path).unwrap().stats().generation;
+
+        // The generation sits in the header's reserved field, which starts
+        // 40 bytes into page 0
+        let offset = 40 + crate::header::GENERATION_OFFSET as u64;
+        let set_header_generation = |value: u64| {
+            use std::io::{Seek, SeekFrom, Write};
+            let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+            file.seek(SeekFrom::Start(offset)).unwrap();
+            file.write_all(&value.to_le_bytes()).unwrap();
+        };
+
+        // As if the flush died before the header was rewritten
+        set_header_generation(generation - 1);
+        let torn = Cartridge::open(&path);
+        assert!(matches!(
+            torn,
+            Err(CartridgeError::TornWrite { header_gen, catalog_gen, allocator_gen })
+                if header_gen == generation - 1
+                    && catalog_gen == generation
+                    && allocator_gen == generation
+        ));
+
+        // A header from before generations were tracked skips the check
+        set_header_generation(0);
+        let cart = Cartridge::open_read_only(&path).unwrap();
+        assert_eq!(cart.read_file("test.txt").unwrap(), b"Hello");
+    }
+
     #[test]
     fn test_iam_policy_enforcement() {
         use crate::iam::{Effect, Statement};
@@ -2336,8 +6132,13 @@ mod tests {
 
         // Test denied operations
         let result = cart.create_file("/secret/key.pem", b"secret");
-        assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("Access denied"));
+        assert!(matches!(
+            result,
+            Err(CartridgeError::AccessDenied {
+                action: Action::Create,
+                ..
+            })
+        ));
 
         // Test read denial on non-public paths
         cart.create_file("/data/private.txt", b"private").unwrap();
@@ -2374,55 +6175,628 @@ mod tests {
     }
 
     #[test]
-    fn test_manifest_creation_and_read() {
-        use tempfile::TempDir;
-        let temp_dir = TempDir::new().unwrap();
-        let path = temp_dir.path().join("manifest-test.cart");
+    fn test_coalesced_flush_with_gaps_survives_reopen() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("coalesce.cart");
+
+        let mut cart = Cartridge::create_at(&path, "coalesce", "Coalesce").unwrap();
+        for i in 0..16 {
+            cart.create_file(&format!("f{}", i), &[i as u8; 100]).unwrap();
+        }
+        cart.flush().unwrap();
+
+        // Free every other page, then refill the holes so the dirty set is
+        // a gap pattern plus one long contiguous run
+        for i in (1..16).step_by(2) {
+            cart.delete_file(&format!("f{}", i)).unwrap();
+        }
+        cart.flush().unwrap();
+        for i in (1..16).step_by(2) {
+            cart.create_file(&format!("g{}", i), &[100 + i as u8; 100]).unwrap();
+        }
+        let big: Vec<u8> = (0..PAGE_SIZE * 20).map(|i| (i % 251) as u8).collect();
+        cart.create_file("big", &big).unwrap();
+        cart.flush().unwrap();
+        assert!(!cart.metadata_dirty);
+
+        // A flush with nothing new leaves the catalog alone
+        cart.flush().unwrap();
+        drop(cart);
+        let cart = Cartridge::open(&path).unwrap();
+        for i in (0..16).step_by(2) {
+            assert_eq!(cart.read_file(&format!("f{}", i)).unwrap(), vec![i as u8; 100]);
+        }
+        for i in (1..16).step_by(2) {
+            assert_eq!(cart.read_file(&format!("g{}", i)).unwrap(), vec![100 + i as u8; 100]);
+        }
+        assert_eq!(cart.read_file("big").unwrap(), big);
+        assert!(cart.check().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_inline_files_move_between_catalog_and_blocks() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("inline.cart");
+        let mut cart = Cartridge::create_at(&path, "inline", "Inline").unwrap();
+        let used = cart.stats().used_blocks;
+
+        cart.create_file("flag", b"1").unwrap();
+        let small = cart.metadata("flag").unwrap();
+        assert!(small.is_inline() && small.blocks.is_empty());
+        assert_eq!(cart.stats().used_blocks, used);
+
+        // Growing past the threshold moves the content into blocks...
+        let big = vec![9u8; DEFAULT_INLINE_THRESHOLD + 1];
+        cart.write_file("flag", &big).unwrap();
+        let grown = cart.metadata("flag").unwrap();
+        assert!(!grown.is_inline());
+        assert_eq!(grown.blocks.len(), 1);
+        assert_eq!(cart.read_file("flag").unwrap(), big);
+
+        // ...and shrinking frees them again
+        cart.write_file("flag", b"0").unwrap();
+        assert!(cart.metadata("flag").unwrap().is_inline());
+        assert_eq!(cart.stats().used_blocks, used);
+
+        cart.set_inline_threshold(0);
+        cart.create_file("blocky", b"x").unwrap();
+        assert_eq!(cart.metadata("blocky").unwrap().blocks.len(), 1);
+
+        cart.flush().unwrap();
+        drop(cart);
+        let cart = Cartridge::open(&path).unwrap();
+        assert_eq!(cart.read_file("flag").unwrap(), b"0");
+        assert_eq!(cart.read_file("blocky").unwrap(), b"x");
+        assert!(cart.check().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_clone_file_shares_blocks_until_written() {
+        let mut cart = Cartridge::new(100);
+        cart.set_inline_threshold(0);
+        let original: Vec<u8> = (0..3 * PAGE_SIZE).map(|i| (i % 251) as u8).collect();
+        cart.create_file("original", &original).unwrap();
+        let used = cart.stats().used_blocks;
+
+        cart.clone_file("original", "clone").unwrap();
+        assert_eq!(
+            cart.metadata("clone").unwrap().blocks,
+            cart.metadata("original").unwrap().blocks
+        );
+        assert_eq!(cart.stats().used_blocks, used);
+        assert_eq!(cart.detailed_stats().unwrap().shared_blocks, 3);
+        assert!(matches!(
+            cart.clone_file("original", "clone"),
+            Err(CartridgeError::AlreadyExists { .. })
+        ));
+
+        // Only the page written to is copied
+        cart.write_at("clone", PAGE_SIZE as u64, b"changed").unwrap();
+        let mut changed = original.clone();
+        changed[PAGE_SIZE..PAGE_SIZE + 7].copy_from_slice(b"changed");
+        assert_eq!(cart.read_file("original").unwrap(), original);
+        assert_eq!(cart.read_file("clone").unwrap(), changed);
+        assert_eq!(cart.stats().used_blocks, used + 1);
+        assert_eq!(cart.detailed_stats().unwrap().shared_blocks, 2);
+
+        cart.delete_file("original").unwrap();
+        assert_eq!(cart.read_file("clone").unwrap(), changed);
+        assert_eq!(cart.stats().used_blocks, used);
+        assert_eq!(cart.detailed_stats().unwrap().shared_blocks, 0);
+        assert!(cart.check().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_clone_file_refs_survive_reopen() {
+        use tempfile::TempDir;
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("clones.cart");
+        let content = vec![0x5A; 2 * PAGE_SIZE];
+
+        let mut cart = Cartridge::create_at(&path, "test", "Test Container").unwrap();
+        cart.set_inline_threshold(0);
+        cart.create_file("a", &content).unwrap();
+        cart.clone_file("a", "b").unwrap();
+        cart.close().unwrap();
+
+        let mut cart = Cartridge::open(&path).unwrap();
+        assert!(cart.check().unwrap().is_consistent());
+        assert_eq!(cart.repair().unwrap().shared_blocks, []);
+        cart.delete_file("a").unwrap();
+        // The freed space mustn't be handed out while "b" still uses it
+        cart.create_file("c", &vec![0xA5; 2 * PAGE_SIZE]).unwrap();
+        assert_eq!(cart.read_file("b").unwrap(), content);
+        assert!(cart.check().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_clone_refs_counted_on_first_change_after_open() {
+        use tempfile::TempDir;
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("lazy.cart");
+        let content = vec![0x3C; 2 * PAGE_SIZE];
+
+        let mut cart = Cartridge::create_at(&path, "test", "Test Container").unwrap();
+        cart.set_inline_threshold(0);
+        cart.create_file("a", &content).unwrap();
+        cart.clone_file("a", "b").unwrap();
+        cart.close().unwrap();
+
+        // Opening and reading leave the counts untaken
+        let mut cart = Cartridge::open(&path).unwrap();
+        assert_eq!(cart.read_file("a").unwrap(), content);
+        assert!(!cart.dedup_index.is_counted());
+
+        // The delete is the first change, so counting must come before it
+        cart.delete_file("a").unwrap();
+        assert!(cart.dedup_index.is_counted());
+        cart.create_file("c", &vec![0xC3; 2 * PAGE_SIZE]).unwrap();
+        assert_eq!(cart.read_file("b").unwrap(), content);
+        assert!(cart.check().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_dedup_shares_identical_payloads() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("dedup.cart");
+        let mut cart = Cartridge::create_at(&path, "dedup", "Dedup").unwrap();
+        cart.set_dedup(true);
+
+        let payload: Vec<u8> = (0..1024 * 1024).map(|i| (i * 7 % 251) as u8).collect();
+        let payload_blocks = (payload.len() / PAGE_SIZE) as u64;
+        let before = cart.stats().used_blocks;
+        for i in 0..10 {
+            cart.create_file(&format!("copy{}", i), &payload).unwrap();
+        }
+        let grown = cart.stats().used_blocks - before;
+        assert!(grown < payload_blocks * 2, "grew by {} blocks", grown);
+        assert!(cart.check().unwrap().is_consistent());
+
+        // Shared blocks survive until the last copy goes
+        for i in 0..9 {
+            cart.delete_file(&format!("copy{}", i)).unwrap();
+        }
+        assert_eq!(cart.read_file("copy9").unwrap(), payload);
+        cart.flush().unwrap();
+        drop(cart);
+
+        // Counts come back from the catalog after reopening
+        let mut cart = Cartridge::open(&path).unwrap();
+        cart.set_dedup(true);
+        cart.create_file("again", &payload).unwrap();
+        cart.delete_file("copy9").unwrap();
+        assert_eq!(cart.read_file("again").unwrap(), payload);
+        cart.delete_file("again").unwrap();
+        // Nothing orphaned: the last release freed the shared blocks
+        assert!(cart.stats().used_blocks < before + payload_blocks);
+        assert!(cart.check().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_detailed_stats_tracks_changes() {
+        let mut cart = Cartridge::new(100);
+        let empty = cart.detailed_stats().unwrap();
+        assert_eq!((empty.file_count, empty.logical_bytes), (0, 0));
+        assert_eq!(empty.physical_bytes, 3 * PAGE_SIZE as u64);
+        assert_eq!(empty.max_blocks, DEFAULT_MAX_BLOCKS as u64);
+
+        cart.create_dir("docs").unwrap();
+        cart.create_file("docs/a.txt", &[1u8; 5000]).unwrap();
+        cart.create_file("b.txt", b"hello").unwrap();
+        let stats = cart.detailed_stats().unwrap();
+        assert_eq!((stats.file_count, stats.dir_count), (2, 1));
+        assert_eq!(stats.logical_bytes, 5005);
+        // b.txt is small enough to live in the catalog
+        assert_eq!(stats.used_blocks, empty.used_blocks + 2);
+        assert_eq!(stats.physical_bytes, stats.used_blocks * PAGE_SIZE as u64);
+        assert_eq!(stats.dirty_pages, 2);
+        assert!(stats.cached_pages >= 2);
+
+        cart.write_file("b.txt", b"hi").unwrap();
+        assert_eq!(cart.detailed_stats().unwrap().logical_bytes, 5002);
+
+        cart.delete_file("docs/a.txt").unwrap();
+        let stats = cart.detailed_stats().unwrap();
+        assert_eq!((stats.file_count, stats.logical_bytes), (1, 2));
+        assert_eq!(stats.used_blocks, empty.used_blocks);
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["logical_bytes"], 2);
+    }
+
+    #[test]
+    fn test_max_size_bytes_caps_growth() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("capped.cart");
+
+        let mut cart = Cartridge::create_at(&path, "capped", "Capped").unwrap();
+        cart.set_max_size_bytes(16 * PAGE_SIZE as u64);
+        assert_eq!(cart.max_size_bytes(), 16 * PAGE_SIZE as u64);
+
+        cart.create_file("fits.bin", &[1u8; 4 * PAGE_SIZE]).unwrap();
+        assert!(matches!(
+            cart.create_file("too-big.bin", &[2u8; 16 * PAGE_SIZE]),
+            Err(CartridgeError::OutOfSpace)
+        ));
+        assert!(cart.header().total_blocks <= 16);
+    }
+
+    #[test]
+    fn test_growth_checks_disk_space_first() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("full.cart");
+
+        let mut cart = Cartridge::create_at(&path, "full", "Full").unwrap();
+        cart.flush().unwrap();
+        let total_blocks = cart.header().total_blocks;
+        let file_len = std::fs::metadata(&path).unwrap().len();
+        let disk_free = 10 * PAGE_SIZE as u64;
+        cart.file.as_ref().unwrap().lock().set_available_space(Some(disk_free));
+        let free = cart.header().free_blocks * PAGE_SIZE as u64;
+        assert!(cart.headroom() > free);
+        assert!(cart.headroom() <= free + disk_free);
+
+        match cart.create_file("big.bin", &[1u8; 64 * PAGE_SIZE]) {
+            Err(CartridgeError::InsufficientDiskSpace { needed, available }) => {
+                assert!(needed > available);
+                assert_eq!(available, disk_free);
+            }
+            other => panic!("expected InsufficientDiskSpace, got {:?}", other),
+        }
+        assert!(matches!(
+            cart.reserve(64 * PAGE_SIZE as u64),
+            Err(CartridgeError::InsufficientDiskSpace { .. })
+        ));
+        assert_eq!(cart.header().total_blocks, total_blocks);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), file_len);
+        assert!(!cart.exists("big.bin").unwrap());
+
+        // Room on disk again
+        cart.file.as_ref().unwrap().lock().set_available_space(None);
+        cart.create_file("big.bin", &[1u8; 64 * PAGE_SIZE]).unwrap();
+    }
+
+    #[test]
+    fn test_failed_allocation_undoes_growth() {
+        let mut cart = Cartridge::new(10);
+        let total_blocks = cart.header().total_blocks as usize;
+        let grown_from = cart.ensure_capacity(20 * PAGE_SIZE).unwrap();
+        assert_eq!(grown_from, Some(total_blocks));
+        assert!(cart.header().total_blocks as usize > total_blocks);
+
+        cart.shrink_to(total_blocks);
+        assert_eq!(cart.header().total_blocks as usize, total_blocks);
+        assert_eq!(cart.header().free_blocks, cart.allocator.free_blocks() as u64);
+        cart.create_file("a.bin", &[1u8; 20 * PAGE_SIZE]).unwrap();
+    }
+
+    #[test]
+    fn test_quota_enforced_and_persisted() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("quota.cart");
+
+        let mut cart = Cartridge::create_at(&path, "quota", "Quota").unwrap();
+        cart.create_file("users/alice/a.txt", &[0u8; 60]).unwrap();
+        cart.create_file("users/alice2/big.txt", &[0u8; 500]).unwrap();
+        cart.set_quota("users/alice/", 100).unwrap();
+        assert_eq!(
+            cart.quota_usage("users/alice"),
+            Some(QuotaUsage { limit: 100, used: 60 })
+        );
+
+        // create, overwrite and append all count against the quota
+        assert!(matches!(
+            cart.create_file("users/alice/b.txt", &[0u8; 41]),
+            Err(CartridgeError::QuotaExceeded { limit: 100, attempted: 101, .. })
+        ));
+        cart.create_file("users/alice/b.txt", &[0u8; 40]).unwrap();
+        assert!(cart.write_file("users/alice/a.txt", &[0u8; 61]).is_err());
+        assert!(cart.append_file("users/alice/b.txt", b"x").is_err());
+        assert!(cart.rename("users/alice2/big.txt", "users/alice/big.txt").is_err());
+
+        // Failed writes leave the file and the counter alone
+        assert_eq!(cart.read_file("users/alice/a.txt").unwrap().len(), 60);
+        assert_eq!(cart.quota_usage("users/alice").unwrap().used, 100);
+
+        cart.delete_file("users/alice/b.txt").unwrap();
+        cart.rename("users/alice/a.txt", "users/alice/renamed.txt").unwrap();
+        assert_eq!(cart.quota_usage("users/alice").unwrap().used, 60);
+        cart.flush().unwrap();
+        drop(cart);
+
+        // Limits come back from the manifest, usage from the catalog
+        let mut cart = Cartridge::open(&path).unwrap();
+        assert_eq!(
+            cart.quotas(),
+            vec![("/users/alice".to_string(), QuotaUsage { limit: 100, used: 60 })]
+        );
+        assert!(cart.create_file("users/alice/c.txt", &[0u8; 41]).is_err());
+
+        assert!(cart.remove_quota("users/alice").unwrap());
+        cart.create_file("users/alice/c.txt", &[0u8; 41]).unwrap();
+        assert!(cart.read_manifest().unwrap().quotas.is_empty());
+    }
+
+    #[test]
+    fn test_manifest_creation_and_read() {
+        use tempfile::TempDir;
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("manifest-test.cart");
+
+        // Create with slug and title
+        {
+            let cart = Cartridge::create_at(&path, "us-const", "U.S. Constitution").unwrap();
+
+            // Read manifest
+            let manifest = cart.read_manifest().unwrap();
+            assert_eq!(manifest.slug.as_str(), "us-const");
+            assert_eq!(manifest.title, "U.S. Constitution");
+            assert_eq!(manifest.version, semver::Version::new(0, 1, 0));
+
+            // Test convenience methods
+            assert_eq!(cart.slug().unwrap(), "us-const");
+            assert_eq!(cart.title().unwrap(), "U.S. Constitution");
+
+            cart.close().unwrap();
+        }
+
+        // Reopen and verify manifest persists
+        {
+            let cart = Cartridge::open(&path).unwrap();
+            let manifest = cart.read_manifest().unwrap();
+            assert_eq!(manifest.slug.as_str(), "us-const");
+            assert_eq!(manifest.title, "U.S. Constitution");
+        }
+    }
+
+    #[test]
+    fn test_manifest_update() {
+        let mut cart = Cartridge::new(100);
+
+        // Manually create a manifest for in-memory cartridge
+        let manifest = Manifest::new("test", "Test", semver::Version::new(1, 0, 0)).unwrap();
+        cart.write_manifest(&manifest).unwrap();
+
+        // Update using the closure API
+        cart.update_manifest(|m| {
+            m.description = Some("Updated description".to_string());
+        })
+        .unwrap();
+
+        // Verify update persisted
+        let updated = cart.read_manifest().unwrap();
+        assert_eq!(updated.description, Some("Updated description".to_string()));
+    }
+
+    /// Count the fsyncs `cart`'s backing file issues from now on
+    fn sync_counter(cart: &Cartridge) -> impl Fn() -> usize {
+        use std::sync::atomic::Ordering;
+        let syncs = cart.file.as_ref().unwrap().lock().syncs.clone();
+        let start = syncs.load(Ordering::Relaxed);
+        move || syncs.load(Ordering::Relaxed) - start
+    }
+
+    #[test]
+    fn test_sync_policy_always_syncs_every_flush() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut cart = Cartridge::create_at(dir.path().join("always"), "always", "Always").unwrap();
+        assert_eq!(cart.sync_policy(), SyncPolicy::Always);
+        let syncs = sync_counter(&cart);
+
+        for i in 0..3 {
+            cart.create_file(&format!("{i}.txt"), b"data").unwrap();
+            let before = syncs();
+            cart.flush().unwrap();
+            assert!(syncs() > before);
+        }
+    }
+
+    #[test]
+    fn test_sync_policy_on_close_defers_to_close() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("on-close");
+        let mut cart = Cartridge::create_at(&path, "on-close", "On Close").unwrap();
+        cart.set_sync_policy(SyncPolicy::OnClose);
+        let syncs = sync_counter(&cart);
+
+        for i in 0..3 {
+            cart.create_file(&format!("{i}.txt"), b"data").unwrap();
+            cart.flush().unwrap();
+        }
+        assert_eq!(syncs(), 0);
+        assert!(cart.file.as_ref().unwrap().lock().needs_sync());
+
+        cart.close().unwrap();
+        assert!(syncs() > 0);
+        let cart = Cartridge::open_read_only(&path).unwrap();
+        assert_eq!(cart.read_file("2.txt").unwrap(), b"data");
+    }
+
+    #[test]
+    fn test_sync_policy_periodic_and_explicit_sync() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut cart = Cartridge::create_at(dir.path().join("periodic"), "periodic", "P").unwrap();
+        cart.set_sync_policy(SyncPolicy::Periodic(Duration::from_secs(3600)));
+        let syncs = sync_counter(&cart);
+        // The first flush syncs and starts the interval; the next hour's don't
+        cart.create_file("a.txt", b"a").unwrap();
+        cart.flush().unwrap();
+        let first = syncs();
+        assert!(first > 0);
+        cart.create_file("b.txt", b"b").unwrap();
+        cart.flush().unwrap();
+        assert_eq!(syncs(), first);
+
+        cart.sync().unwrap();
+        assert!(syncs() > first);
+        assert!(!cart.file.as_ref().unwrap().lock().needs_sync());
+
+        let synced = syncs();
+        cart.set_sync_policy(SyncPolicy::Periodic(Duration::ZERO));
+        cart.create_file("c.txt", b"c").unwrap();
+        cart.flush().unwrap();
+        assert!(syncs() > synced);
+    }
+
+    #[test]
+    fn test_scrub_skips_unflushed_pages() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut cart = Cartridge::create_at(dir.path().join("scrub"), "scrub", "Scrub").unwrap();
+        cart.create_file("flushed.bin", &[1u8; 5000]).unwrap();
+        cart.flush().unwrap();
+        cart.set_inline_threshold(0);
+        cart.create_file("pending.bin", &[2u8; 100]).unwrap();
+
+        let result = cart.scrub(None, 100).unwrap();
+        assert_eq!(result.pages_checked, 2);
+        assert_eq!(result.pages_skipped, 1);
+        assert!(result.errors.is_empty());
+        assert!(result.is_complete());
+
+        let first = cart.scrub(None, 1).unwrap();
+        assert_eq!(first.pages_checked, 1);
+        let rest = cart.scrub(first.next, 100).unwrap();
+        assert_eq!(rest.pages_checked + rest.pages_skipped, 2);
+    }
+
+    #[test]
+    fn test_manifest_keeps_unknown_fields() {
+        let mut cart = Cartridge::new(100);
+        // Written by a newer version, with fields this one doesn't know
+        let future = serde_json::json!({
+            "schema_version": 7,
+            "slug": "future",
+            "title": "Future",
+            "version": "1.0.0",
+            "signing": {"key": "ed25519:abc", "at": [1, 2]},
+            "tags": ["a", "b"],
+        });
+        cart.system()
+            .write(MANIFEST_PATH, &serde_json::to_vec(&future).unwrap())
+            .unwrap();
+
+        cart.update_manifest(|m| m.title = "Renamed".to_string()).unwrap();
+
+        let stored: serde_json::Value =
+            serde_json::from_slice(&cart.read_file(MANIFEST_PATH).unwrap()).unwrap();
+        let mut expected = future;
+        expected["title"] = "Renamed".into();
+        assert_eq!(stored, expected);
+    }
 
-        // Create with slug and title
+    #[test]
+    fn test_old_manifest_migrated_on_open() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("old-schema");
         {
-            let mut cart = Cartridge::create_at(&path, "us-const", "U.S. Constitution").unwrap();
-
-            // Read manifest
-            let manifest = cart.read_manifest().unwrap();
-            assert_eq!(manifest.slug.as_str(), "us-const");
-            assert_eq!(manifest.title, "U.S. Constitution");
-            assert_eq!(manifest.version, semver::Version::new(0, 1, 0));
-
-            // Test convenience methods
-            assert_eq!(cart.slug().unwrap(), "us-const");
-            assert_eq!(cart.title().unwrap(), "U.S. Constitution");
-
+            let mut cart = Cartridge::create_at(&path, "old-schema", "Old").unwrap();
+            let old = r#"{"schema_version": 0, "slug": "old-schema", "title": "Old",
+                "version": "0.1.0"}"#;
+            cart.system().write(MANIFEST_PATH, old.as_bytes()).unwrap();
             cart.close().unwrap();
         }
 
-        // Reopen and verify manifest persists
-        {
-            let cart = Cartridge::open(&path).unwrap();
-            let manifest = cart.read_manifest().unwrap();
-            assert_eq!(manifest.slug.as_str(), "us-const");
-            assert_eq!(manifest.title, "U.S. Constitution");
-        }
+        let stored = |cart: &Cartridge| -> serde_json::Value {
+            serde_json::from_slice(&cart.read_file(MANIFEST_PATH).unwrap()).unwrap()
+        };
+        let cart = Cartridge::open_read_only(&path).unwrap();
+        assert_eq!(cart.read_manifest().unwrap().schema_version, Manifest::SCHEMA_VERSION);
+        assert_eq!(stored(&cart)["schema_version"], 0);
+        drop(cart);
+
+        let cart = Cartridge::open(&path).unwrap();
+        assert_eq!(stored(&cart)["schema_version"], Manifest::SCHEMA_VERSION);
     }
 
     #[test]
-    fn test_manifest_update() {
-        let mut cart = Cartridge::new(100);
+    fn test_iam_principal() {
+        use crate::iam::{Effect, Statement};
 
-        // Manually create a manifest for in-memory cartridge
-        let manifest = Manifest::new("test", "Test", semver::Version::new(1, 0, 0)).unwrap();
-        cart.create_dir(".cartridge").unwrap();
-        cart.write_manifest(&manifest).unwrap();
+        let mut cart = Cartridge::new(1000);
+        let mut policy = Policy::new();
+        policy.add_statement(
+            Statement::new(
+                Effect::Allow,
+                vec![Action::Read, Action::Write, Action::Create],
+                vec!["/a/**".to_string()],
+            )
+            .with_principal(vec!["alice".to_string()]),
+        );
+        policy.add_statement(
+            Statement::new(Effect::Allow, vec![Action::Read], vec!["/a/**".to_string()])
+                .with_principal(vec!["bob".to_string()]),
+        );
+        cart.set_policy(policy);
 
-        // Update using the closure API
-        cart.update_manifest(|m| {
-            m.description = Some("Updated description".to_string());
-        })
-        .unwrap();
+        cart.set_principal("alice");
+        cart.create_file("/a/notes.txt", b"alice").unwrap();
 
-        // Verify update persisted
-        let updated = cart.read_manifest().unwrap();
-        assert_eq!(updated.description, Some("Updated description".to_string()));
+        cart.set_principal("bob");
+        assert_eq!(cart.principal(), Some("bob"));
+        assert_eq!(cart.read_file("/a/notes.txt").unwrap(), b"alice");
+        assert!(matches!(
+            cart.write_file("/a/notes.txt", b"bob"),
+            Err(CartridgeError::AccessDenied { .. })
+        ));
+
+        cart.clear_principal();
+        assert!(cart.read_file("/a/notes.txt").is_err());
+    }
+
+    #[test]
+    fn test_iam_request_conditions() {
+        use crate::iam::Statement;
+        use chrono::Timelike;
+
+        let allow_all = || {
+            let mut policy = Policy::new();
+            policy.add_statement(Statement::new(
+                crate::iam::Effect::Allow,
+                vec![Action::All],
+                vec!["/**".to_string()],
+            ));
+            policy
+        };
+        let deny = |condition: serde_json::Value| {
+            let mut statement = Statement::new(
+                crate::iam::Effect::Deny,
+                vec![Action::Create, Action::Write],
+                vec!["/**".to_string()],
+            );
+            statement.condition = Some(condition);
+            statement
+        };
+
+        // No writes over 1 MiB
+        let mut cart = Cartridge::new(2000);
+        let mut policy = allow_all();
+        policy.add_statement(deny(serde_json::json!({
+            "NumericGreaterThan": {"cartridge:ContentLength": 1_048_576}
+        })));
+        cart.set_policy(policy);
+        cart.create_file("/small.bin", &[0; 1024]).unwrap();
+        assert!(matches!(
+            cart.write_file("/small.bin", &vec![0; 2 * 1024 * 1024]),
+            Err(CartridgeError::AccessDenied { .. })
+        ));
+        assert!(cart.create_file("/large.bin", &vec![0; 2 * 1024 * 1024]).is_err());
+        cart.append_file("/small.bin", &[1; 1024]).unwrap();
+
+        // No writes outside 09:00-17:00 UTC
+        let mut cart = Cartridge::new(100);
+        let mut policy = allow_all();
+        policy.add_statement(deny(serde_json::json!({
+            "NumericLessThan": {"cartridge:CurrentHour": 9}
+        })));
+        policy.add_statement(deny(serde_json::json!({
+            "NumericGreaterThanEquals": {"cartridge:CurrentHour": 17}
+        })));
+        cart.set_policy(policy);
+        let before = chrono::Utc::now().hour();
+        let result = cart.create_file("/report.txt", b"numbers");
+        if chrono::Utc::now().hour() == before {
+            assert_eq!(result.is_ok(), (9..17).contains(&before), "{result:?}");
+        }
     }
 
     #[test]
@@ -2455,6 +6829,66 @@ mod tests {
         cart.read_file("/test.txt").unwrap();
     }
 
+    #[test]
+    fn test_set_policy_drops_cached_decisions() {
+        use crate::iam::{Effect, Statement};
+
+        let mut cart = Cartridge::new(1000);
+        cart.create_file("/test.txt", b"test").unwrap();
+        let allow = |actions| {
+            let mut policy = Policy::new();
+            policy.add_statement(Statement::new(Effect::Allow, actions, vec!["/**".to_string()]));
+            policy
+        };
+
+        cart.set_policy(allow(vec![Action::Read]));
+        cart.read_file("/test.txt").unwrap();
+        cart.read_file("/test.txt").unwrap();
+        // Only the first check evaluates the policy
+        let stats = cart.detailed_stats().unwrap().policy_cache.unwrap();
+        assert_eq!((stats.misses, stats.entries), (1, 1));
+        assert!(stats.hits > 0);
+
+        // The cached allow must not outlive the policy that granted it
+        cart.set_policy(allow(vec![Action::List]));
+        assert!(matches!(
+            cart.read_file("/test.txt"),
+            Err(CartridgeError::AccessDenied { .. })
+        ));
+    }
+
+    #[test]
+    fn test_policy_cache_capacity_and_ttl() {
+        use crate::iam::{Effect, Statement};
+
+        let mut cart = Cartridge::new(1000);
+        assert!(cart.policy_cache_stats().is_none());
+        for name in ["/a", "/b", "/c"] {
+            cart.create_file(name, b"x").unwrap();
+        }
+        cart.set_policy_cache(2, Some(Duration::from_millis(50)));
+        let mut policy = Policy::new();
+        policy.add_statement(Statement::new(
+            Effect::Allow,
+            vec![Action::Read],
+            vec!["/**".to_string()],
+        ));
+        cart.set_policy(policy);
+
+        for name in ["/a", "/b", "/c"] {
+            cart.read_file(name).unwrap();
+        }
+        let stats = cart.policy_cache_stats().unwrap();
+        assert_eq!((stats.evictions, stats.entries), (1, 2));
+
+        assert_eq!(stats.misses, 3);
+
+        // Expired decisions are evaluated again
+        std::thread::sleep(Duration::from_millis(60));
+        cart.read_file("/c").unwrap();
+        assert_eq!(cart.policy_cache_stats().unwrap().misses, 4);
+    }
+
     #[test]
     fn test_multi_page_catalog_flush_and_reload() {
         // Create a disk-backed cartridge with many files to overflow the catalog
@@ -2672,6 +7106,105 @@ mod tests {
             missing.len(), &missing[..missing.len().min(20)]);
     }
 
+    #[test]
+    fn test_flush_rewrites_only_changed_catalog_segment() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("segments");
+
+        let mut cart = Cartridge::create_at(&path, "segments", "Segments").unwrap();
+        for i in 0..3000 {
+            cart.create_file(&format!("d/f-{:05}.dat", i), b"x").unwrap();
+        }
+        cart.flush().unwrap();
+        assert!(cart.catalog.is_segmented());
+        let total = cart.catalog.segment_pages().len();
+
+        // Only the segment holding the changed entry is left to rewrite
+        cart.write_file("d/f-01500.dat", b"changed").unwrap();
+        let stale = total - cart.catalog.clean_segment_pages().len();
+        assert!(stale > 0 && stale < total);
+        assert!(
+            stale <= crate::catalog::SEGMENT_TARGET_BYTES.div_ceil(PAGE_SIZE) + 1,
+            "{} of {} segment pages stale",
+            stale,
+            total
+        );
+        cart.flush().unwrap();
+        assert_eq!(cart.catalog.clean_segment_pages().len(), cart.catalog.segment_pages().len());
+
+        drop(cart);
+        let cart = Cartridge::open(&path).unwrap();
+        assert_eq!(cart.read_file("d/f-01500.dat").unwrap(), b"changed");
+        assert_eq!(cart.read_file("d/f-02999.dat").unwrap(), b"x");
+    }
+
+    #[test]
+    fn test_legacy_catalog_keys_are_normalized_on_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("legacy-keys");
+
+        // Keys as they were stored before paths were normalized
+        let mut cart = Cartridge::create_at(&path, "legacy", "Legacy").unwrap();
+        cart.create_file("docs/a.txt", b"a").unwrap();
+        cart.create_file("b.txt", b"b").unwrap();
+        for (normalized, legacy) in [("/docs/a.txt", "docs//a.txt"), ("/b.txt", "b.txt")] {
+            let metadata = cart.catalog.delete(normalized).unwrap().unwrap();
+            cart.catalog.insert(legacy, metadata).unwrap();
+        }
+        cart.metadata_dirty = true;
+        cart.flush().unwrap();
+        drop(cart);
+
+        let mut cart = Cartridge::open(&path).unwrap();
+        assert_eq!(cart.read_file("docs/a.txt").unwrap(), b"a");
+        assert_eq!(cart.read_file("/b.txt").unwrap(), b"b");
+        assert!(cart.catalog.get("b.txt").unwrap().is_none());
+        cart.flush().unwrap();
+        drop(cart);
+
+        // The new keys were written back
+        let cart = Cartridge::open_read_only(&path).unwrap();
+        assert!(cart.catalog.get("/docs/a.txt").unwrap().is_some());
+        assert!(cart.catalog.get("docs//a.txt").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_segmented_catalog_survives_inserts_and_deletes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("segments-churn");
+
+        let mut cart = Cartridge::create_at(&path, "churn", "Churn").unwrap();
+        for i in 0..2000 {
+            cart.create_file(&format!("d/f-{:05}.dat", i), b"x").unwrap();
+        }
+        cart.flush().unwrap();
+
+        // Empty out a whole run of keys and add keys before, between and after
+        for i in 500..1500 {
+            cart.delete_file(&format!("d/f-{:05}.dat", i)).unwrap();
+        }
+        for name in ["a.txt", "d/f-00999-b.dat", "z.txt"] {
+            cart.create_file(name, b"new").unwrap();
+        }
+        cart.flush().unwrap();
+        drop(cart);
+
+        let mut cart = Cartridge::open(&path).unwrap();
+        assert!(cart.catalog.is_segmented());
+        assert_eq!(cart.catalog.list_prefix("/d/f-").unwrap().len(), 1001);
+        assert!(!cart.exists("d/f-01000.dat").unwrap());
+        assert_eq!(cart.read_file("d/f-00999-b.dat").unwrap(), b"new");
+
+        // Keys landing in a folded segment go to its predecessor
+        cart.create_file("d/f-01200.dat", b"back").unwrap();
+        cart.flush().unwrap();
+        drop(cart);
+        let cart = Cartridge::open(&path).unwrap();
+        assert_eq!(cart.read_file("d/f-01200.dat").unwrap(), b"back");
+        assert_eq!(cart.read_file("z.txt").unwrap(), b"new");
+        assert!(cart.check().unwrap().is_consistent());
+    }
+
     #[test]
     fn test_multi_page_with_delete_and_reopen() {
         // Test that delete+create+flush+reopen preserves all data
@@ -2760,7 +7293,7 @@ mod tests {
             let mut cart = Cartridge::create_at(&path, "vacuum-test", "Vacuum Test").unwrap();
             for i in 0..50 {
                 let name = format!("file-{:03}.dat", i);
-                let data = vec![(i as u8); PAGE_SIZE]; // exactly 1 page each
+                let data = vec![i as u8; PAGE_SIZE]; // exactly 1 page each
                 cart.create_file(&name, &data).unwrap();
             }
             cart.flush().unwrap();
@@ -2789,6 +7322,8 @@ mod tests {
                 }
             }
 
+            assert!(total_relocated > 0, "Should have relocated some pages");
+
             // Finish vacuum — truncate
             let bytes_freed = cart.vacuum_finish().unwrap();
             assert!(bytes_freed > 0, "Should have freed some bytes");
@@ -2825,7 +7360,7 @@ mod tests {
             let mut cart = Cartridge::create_at(&path, "crash-test", "Crash Test").unwrap();
             for i in 0..20 {
                 let name = format!("data-{:03}.dat", i);
-                let data = vec![(i as u8); PAGE_SIZE];
+                let data = vec![i as u8; PAGE_SIZE];
                 cart.create_file(&name, &data).unwrap();
             }
             cart.flush().unwrap();
@@ -2864,6 +7399,336 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_check_clean_cartridge() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("check-clean.cart");
+
+        let mut cart = Cartridge::create_at(&path, "check-clean", "Check Clean").unwrap();
+        for i in 0..20 {
+            cart.create_file(&format!("f{}.dat", i), &vec![i as u8; 3 * PAGE_SIZE])
+                .unwrap();
+        }
+        cart.flush().unwrap();
+        let report = cart.check().unwrap();
+        assert!(report.is_consistent(), "{:?}", report);
+
+        drop(cart);
+        let cart = Cartridge::open(&path).unwrap();
+        assert!(cart.check().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_check_detects_and_repair_fixes_allocator_drift() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("check-drift.cart");
+
+        let mut cart = Cartridge::create_at(&path, "check-drift", "Check Drift").unwrap();
+        // Leave free blocks beyond the files so a stray one can be planted
+        cart.reserve_bytes(8 * PAGE_SIZE as u64).unwrap();
+        cart.create_file("a.dat", &vec![1u8; 2 * PAGE_SIZE]).unwrap();
+        cart.create_file("b.dat", &vec![2u8; PAGE_SIZE]).unwrap();
+        cart.flush().unwrap();
+
+        // Simulate a crash mid-flush: a referenced block is freed, a stray
+        // block is left allocated, and the header count is stale
+        let a_blocks = cart.metadata("a.dat").unwrap().blocks;
+        cart.allocator.free(&a_blocks[..1]).unwrap();
+        let stray = (0..cart.allocator.total_blocks() as u64)
+            .rfind(|&b| !cart.allocator.is_allocated(b))
+            .unwrap();
+        cart.allocator.mark_pages_allocated(&[stray]).unwrap();
+        cart.header.free_blocks += 7;
+
+        let report = cart.check().unwrap();
+        assert!(!report.is_consistent());
+        assert!(report.is_repairable());
+        assert!(!report.counts_match());
+        assert_eq!(
+            report.unallocated_refs,
+            vec![BlockRef {
+                path: "/a.dat".to_string(),
+                block: a_blocks[0],
+            }]
+        );
+        assert_eq!(report.orphaned_blocks, vec![stray]);
+
+        let repaired = cart.repair().unwrap();
+        assert!(repaired.is_consistent(), "{:?}", repaired);
+
+        // Repair is persisted
+        drop(cart);
+        let cart = Cartridge::open(&path).unwrap();
+        assert!(cart.check().unwrap().is_consistent());
+        assert_eq!(cart.read_file("a.dat").unwrap(), vec![1u8; 2 * PAGE_SIZE]);
+    }
+
+    #[test]
+    fn test_check_detects_shared_blocks() {
+        let mut cart = Cartridge::new(100);
+        cart.set_inline_threshold(0);
+        cart.create_file("a.dat", b"aaaa").unwrap();
+        cart.create_file("b.dat", b"bbbb").unwrap();
+
+        let mut b = cart.metadata("b.dat").unwrap();
+        b.blocks = cart.metadata("a.dat").unwrap().blocks;
+        cart.catalog.insert("/b.dat", b).unwrap();
+
+        let report = cart.check().unwrap();
+        assert_eq!(report.shared_blocks.len(), 1);
+        assert_eq!(report.shared_blocks[0].paths, vec!["/a.dat", "/b.dat"]);
+        assert!(!report.is_repairable());
+
+        let json = serde_json::to_string(&report).unwrap();
+        let parsed: ConsistencyReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, report);
+    }
+
+    /// Flush an update to `a.dat` that fails at `point`, then simulate a
+    /// crash by skipping the flush-on-drop
+    #[test]
+    fn test_user_metadata_hides_and_protects_reserved_keys() {
+        let mut cart = Cartridge::new(100);
+        cart.enable_encryption(&[7u8; 32]).unwrap();
+        cart.create_file("secret.txt", b"classified").unwrap();
+        let raw = cart.metadata("secret.txt").unwrap().user_metadata;
+        assert!(raw.contains_key("encrypted"));
+
+        cart.set_user_metadata(
+            "secret.txt",
+            HashMap::from([("owner".to_string(), "ops".to_string())]),
+        )
+        .unwrap();
+        let visible = cart.user_metadata("secret.txt").unwrap();
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible["owner"], "ops");
+
+        // Reserved keys can't be overwritten or removed through the API
+        let clobber = HashMap::from([("encrypted".to_string(), "false".to_string())]);
+        assert!(cart.set_user_metadata("secret.txt", clobber).is_err());
+        assert!(cart.remove_user_metadata("secret.txt", "encrypted_size").is_err());
+        let empty_key = HashMap::from([(String::new(), "x".to_string())]);
+        assert!(cart.set_user_metadata("secret.txt", empty_key).is_err());
+        assert_eq!(cart.read_file("secret.txt").unwrap(), b"classified");
+    }
+
+    #[test]
+    fn test_set_times_and_touch() {
+        let mut cart = Cartridge::new(100);
+        cart.create_file("a.txt", b"a").unwrap();
+        let created = cart.metadata("a.txt").unwrap().created_at;
+
+        cart.set_times("a.txt", None, Some(1_000)).unwrap();
+        let metadata = cart.metadata("a.txt").unwrap();
+        assert_eq!((metadata.created_at, metadata.modified_at), (created, 1_000));
+        cart.set_times("a.txt", Some(10), None).unwrap();
+        let metadata = cart.metadata("a.txt").unwrap();
+        assert_eq!((metadata.created_at, metadata.modified_at), (10, 1_000));
+        assert!(cart.set_times("missing.txt", None, Some(1)).is_err());
+
+        // Touch bumps an existing file without changing it, and creates a
+        // missing one empty
+        cart.touch("a.txt").unwrap();
+        assert!(cart.metadata("a.txt").unwrap().modified_at > 1_000);
+        assert_eq!(cart.read_file("a.txt").unwrap(), b"a");
+        cart.touch("dir/new.txt").unwrap();
+        assert_eq!(cart.read_file("dir/new.txt").unwrap(), b"");
+    }
+
+    #[test]
+    fn test_hash_fills_in_legacy_entries() {
+        let mut cart = Cartridge::new(100);
+        cart.create_file("old.txt", b"written before hashes").unwrap();
+        let mut metadata = cart.metadata("old.txt").unwrap();
+        metadata.content_hash = None;
+        cart.catalog_mut().insert("/old.txt", metadata).unwrap();
+
+        let expected: [u8; 32] = Sha256::digest(b"written before hashes").into();
+        assert_eq!(cart.hash("old.txt").unwrap(), expected);
+        assert_eq!(cart.metadata("old.txt").unwrap().content_hash, Some(expected));
+        assert!(matches!(cart.hash("missing.txt"), Err(CartridgeError::NotFound { .. })));
+    }
+
+    fn crash_during_flush(path: &Path, point: crate::io::FailPoint) {
+        {
+            let mut cart = Cartridge::create_at(path, "journal-test", "Journal Test").unwrap();
+            cart.create_file("a.dat", &vec![1u8; 3 * PAGE_SIZE]).unwrap();
+            cart.flush().unwrap();
+        }
+
+        let mut cart = Cartridge::open(path).unwrap();
+        cart.write_file("a.dat", &vec![2u8; 5 * PAGE_SIZE]).unwrap();
+        for i in 0..40 {
+            cart.create_file(&format!("new-{}.dat", i), &[i as u8; 64]).unwrap();
+        }
+        cart.file.as_ref().unwrap().lock().set_fail_point(Some(point));
+        assert!(cart.flush().is_err());
+        // A crashed process loses its file handle, and with it the lock
+        drop(cart.file.take());
+        std::mem::forget(cart);
+    }
+
+    #[test]
+    fn test_flush_crash_after_journal_replays_new_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal-after.cart");
+        crash_during_flush(&path, crate::io::FailPoint::AfterJournal);
+        assert!(crate::io::journal_path(&path).exists());
+
+        let cart = Cartridge::open(&path).unwrap();
+        assert!(!crate::io::journal_path(&path).exists());
+        assert_eq!(cart.read_file("a.dat").unwrap(), vec![2u8; 5 * PAGE_SIZE]);
+        assert_eq!(cart.read_file("new-39.dat").unwrap(), vec![39u8; 64]);
+        assert!(cart.check().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_flush_crash_mid_apply_replays_new_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal-mid.cart");
+        crash_during_flush(&path, crate::io::FailPoint::MidApply);
+
+        let cart = Cartridge::open(&path).unwrap();
+        assert_eq!(cart.read_file("a.dat").unwrap(), vec![2u8; 5 * PAGE_SIZE]);
+        assert_eq!(cart.read_file("new-0.dat").unwrap(), vec![0u8; 64]);
+        assert!(cart.check().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_read_only_open_sees_journal_without_replaying() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal-ro.cart");
+        crash_during_flush(&path, crate::io::FailPoint::AfterJournal);
+        let before = std::fs::read(&path).unwrap();
+
+        let mut cart = Cartridge::open_read_only(&path).unwrap();
+        assert_eq!(cart.read_file("a.dat").unwrap(), vec![2u8; 5 * PAGE_SIZE]);
+        assert_eq!(cart.read_file("new-39.dat").unwrap(), vec![39u8; 64]);
+        assert!(matches!(cart.delete_file("a.dat"), Err(CartridgeError::ReadOnly)));
+        drop(cart);
+
+        // Recovery is left to the next writer
+        assert!(crate::io::journal_path(&path).exists());
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+    }
+
+    #[test]
+    fn test_flush_torn_journal_keeps_old_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal-torn.cart");
+        crash_during_flush(&path, crate::io::FailPoint::AfterJournal);
+
+        // Tear the journal as if the crash hit before it was fully written
+        let journal = crate::io::journal_path(&path);
+        let len = std::fs::metadata(&journal).unwrap().len();
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&journal)
+            .unwrap()
+            .set_len(len - 100)
+            .unwrap();
+
+        let cart = Cartridge::open(&path).unwrap();
+        assert!(!journal.exists());
+        assert_eq!(cart.read_file("a.dat").unwrap(), vec![1u8; 3 * PAGE_SIZE]);
+        assert!(!cart.exists("new-0.dat").unwrap());
+        assert!(cart.check().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_vacuum_reclaims_space_after_deletes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vacuum-full.cart");
+
+        let mut cart = Cartridge::create_at(&path, "vacuum-full", "Vacuum Full").unwrap();
+        for i in 0..60 {
+            cart.create_file(&format!("f{:02}.dat", i), &vec![i as u8; 2 * PAGE_SIZE])
+                .unwrap();
+        }
+        cart.flush().unwrap();
+        for i in 0..50 {
+            cart.delete_file(&format!("f{:02}.dat", i)).unwrap();
+        }
+        cart.flush().unwrap();
+
+        let report = cart.vacuum().unwrap();
+        assert!(report.pages_relocated > 0);
+        assert!(report.blocks_after < report.blocks_before);
+        assert!(report.bytes_reclaimed > 0);
+        assert_eq!(report.bytes_after, std::fs::metadata(&path).unwrap().len());
+        assert_eq!(report.bytes_before - report.bytes_after, report.bytes_reclaimed);
+        assert!(cart.check().unwrap().is_consistent());
+
+        drop(cart);
+        let cart = Cartridge::open(&path).unwrap();
+        for i in 50..60 {
+            assert_eq!(
+                cart.read_file(&format!("f{:02}.dat", i)).unwrap(),
+                vec![i as u8; 2 * PAGE_SIZE]
+            );
+        }
+        assert!(cart.check().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_defragment_within_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("defrag.cart");
+
+        let mut cart = Cartridge::create_at(&path, "defrag", "Defrag").unwrap();
+        for i in 0..60 {
+            cart.create_file(&format!("f{:02}.dat", i), &vec![i as u8; 2 * PAGE_SIZE])
+                .unwrap();
+        }
+        cart.flush().unwrap();
+        for i in (0..60).step_by(2) {
+            cart.delete_file(&format!("f{:02}.dat", i)).unwrap();
+        }
+        let before = cart.stats().allocator;
+        assert!(before.free_extent_count > 10);
+
+        let first = cart.defragment(5).unwrap();
+        assert_eq!(first.blocks_moved, 5);
+        assert!(first.blocks_remaining > 0);
+        assert!(!first.done);
+
+        let mut passes = 1;
+        while !cart.defragment(5).unwrap().done {
+            passes += 1;
+            assert!(passes < 100, "defragment never finished");
+        }
+        let after = cart.stats().allocator;
+        assert!(after.free_extent_count < before.free_extent_count);
+        assert_eq!(after.free_blocks, before.free_blocks);
+        assert!(cart.check().unwrap().is_consistent());
+
+        // Nothing left to move; the flush moves the catalog's own pages
+        // into the packed region too, leaving a single free run
+        assert_eq!(cart.defragment(5).unwrap().blocks_moved, 0);
+        cart.flush().unwrap();
+        let flushed = cart.stats().allocator;
+        assert_eq!(flushed.free_extent_count, 1);
+        assert_eq!(flushed.largest_free_extent as usize, flushed.free_blocks);
+        drop(cart);
+
+        let cart = Cartridge::open(&path).unwrap();
+        for i in (1..60).step_by(2) {
+            assert_eq!(
+                cart.read_file(&format!("f{:02}.dat", i)).unwrap(),
+                vec![i as u8; 2 * PAGE_SIZE]
+            );
+        }
+        assert!(cart.check().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_vacuum_in_memory_is_noop() {
+        let mut cart = Cartridge::new(100);
+        let report = cart.vacuum().unwrap();
+        assert_eq!(report.pages_relocated, 0);
+        assert_eq!(report.bytes_reclaimed, 0);
+    }
+
     #[test]
     fn test_vacuum_no_work_needed() {
         let dir = tempfile::tempdir().unwrap();
@@ -2918,14 +7783,3 @@ mod tests {
     }
 }
 
-/// Helper function to convert Action enum to lowercase string for capabilities
-fn action_to_string_lower(action: &crate::iam::Action) -> &'static str {
-    match action {
-        crate::iam::Action::Read => "read",
-        crate::iam::Action::Write => "write",
-        crate::iam::Action::Delete => "delete",
-        crate::iam::Action::List => "list",
-        crate::iam::Action::Create => "create",
-        crate::iam::Action::All => "*",
-    }
-}
@@ -1,8 +1,13 @@
-//! B-tree implementation for the catalog
+//! B-tree the catalog used to be stored as
 //!
-//! Full B+ tree with:
+//! Catalogs are now a `BTreeMap` serialized with bincode (see the parent
+//! module). This tree is only kept so cartridges whose catalog was written
+//! as JSON by older releases can still be parsed and converted with
+//! [`BTree::into_catalog`]. Nothing deletes from a tree loaded that way, so
+//! deletes leave underflowing nodes as they are rather than rebalancing.
+//!
+//! B+ tree with:
 //! - Node splitting on overflow
-//! - Node merging/redistribution on underflow
 //! - Multi-level tree traversal
 //! - All values in leaf nodes
 //! - Linked leaf nodes for range queries
@@ -156,13 +161,13 @@ impl BTree {
     fn get_node(&self, page_id: u64) -> Result<&BTreeNode> {
         self.nodes
             .get(&page_id)
-            .ok_or_else(|| CartridgeError::Allocation(format!("Node {} not found", page_id)))
+            .ok_or_else(|| CartridgeError::Corruption(format!("Node {} not found", page_id)))
     }
 
     fn get_node_mut(&mut self, page_id: u64) -> Result<&mut BTreeNode> {
         self.nodes
             .get_mut(&page_id)
-            .ok_or_else(|| CartridgeError::Allocation(format!("Node {} not found", page_id)))
+            .ok_or_else(|| CartridgeError::Corruption(format!("Node {} not found", page_id)))
     }
 
     /// Find the leaf node that should contain a key
@@ -202,7 +207,7 @@ impl BTree {
             }
 
             current_page = found_child.ok_or_else(|| {
-                CartridgeError::Allocation("Internal node missing child pointer".to_string())
+                CartridgeError::Corruption("Internal node missing child pointer".to_string())
             })?;
         }
     }
@@ -368,8 +373,8 @@ impl BTree {
 
     /// Handle underflow by borrowing or merging
     fn handle_underflow(&mut self, _page_id: u64) -> Result<()> {
-        // Simplified: Phase 3 could add redistribution and merging
-        // For now, we allow underflow (minimum not strictly enforced)
+        // Legacy trees are only read and converted (see the module docs),
+        // so underflow is allowed rather than rebalanced
         Ok(())
     }
 
@@ -3,6 +3,17 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// `user_metadata` key holding a symlink's target path
+pub(crate) const SYMLINK_TARGET_KEY: &str = "symlink_target";
+
+/// `user_metadata` key holding the hex nonce of a file encrypted with its
+/// own key, as opposed to the cartridge's content key
+pub(crate) const FILE_NONCE_KEY: &str = "encryption_nonce";
+
+/// Entry in [`FileMetadata::blocks`] for a hole: a page of a sparse file
+/// that reads as zeros and has no block behind it
+pub const HOLE_BLOCK: u64 = u64::MAX;
+
 /// File type
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FileType {
@@ -29,7 +40,10 @@ pub struct FileMetadata {
     /// File size in bytes
     pub size: u64,
 
-    /// Block IDs where content is stored
+    /// Block IDs where content is stored, one per page
+    ///
+    /// Pages of a sparse file that were never written are [`HOLE_BLOCK`].
+    #[serde(with = "hole_runs")]
     pub blocks: Vec<u64>,
 
     /// Creation timestamp (Unix epoch seconds)
@@ -44,7 +58,13 @@ pub struct FileMetadata {
     /// File owner (for future IAM integration)
     pub owner: String,
 
-    /// Content hash (SHA-256) for integrity verification
+    /// SHA-256 of the content
+    ///
+    /// Kept current by every write. `None` for encrypted files, whose digest
+    /// would give away their plaintext, and for entries written before it
+    /// was tracked, until `Cartridge::hash` fills it in. The field has been
+    /// in this position since the first catalog format, so old catalogs
+    /// decode with it as stored.
     pub content_hash: Option<[u8; 32]>,
 
     /// MIME content type (for S3 compatibility)
@@ -56,15 +76,105 @@ pub struct FileMetadata {
     /// Maps to S3 x-amz-meta-* headers
     #[serde(default)]
     pub user_metadata: HashMap<String, String>,
+
+    /// Content of a small file stored in the catalog instead of in blocks
+    ///
+    /// When set, `blocks` is empty. Holds the bytes as they would have been
+    /// written to blocks, so encrypted files stay encrypted here.
+    #[serde(default)]
+    pub inline_data: Option<Vec<u8>>,
+}
+
+/// Serde for [`FileMetadata::blocks`] that stores each run of holes as
+/// [`HOLE_BLOCK`] followed by the run's length
+///
+/// A mostly empty sparse file then costs a few words in the catalog rather
+/// than one per page. Lists without holes are written exactly as before.
+mod hole_runs {
+    use super::HOLE_BLOCK;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(blocks: &[u64], serializer: S) -> Result<S::Ok, S::Error> {
+        if !blocks.contains(&HOLE_BLOCK) {
+            return blocks.serialize(serializer);
+        }
+        let mut packed = Vec::new();
+        let mut rest = blocks;
+        while let Some(&block) = rest.first() {
+            if block == HOLE_BLOCK {
+                let run = rest.iter().take_while(|&&b| b == HOLE_BLOCK).count();
+                packed.extend([HOLE_BLOCK, run as u64]);
+                rest = &rest[run..];
+            } else {
+                packed.push(block);
+                rest = &rest[1..];
+            }
+        }
+        packed.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u64>, D::Error> {
+        let packed = Vec::<u64>::deserialize(deserializer)?;
+        if !packed.contains(&HOLE_BLOCK) {
+            return Ok(packed);
+        }
+        let mut blocks = Vec::with_capacity(packed.len());
+        let mut packed = packed.into_iter();
+        while let Some(block) = packed.next() {
+            if block == HOLE_BLOCK {
+                let run = packed
+                    .next()
+                    .ok_or_else(|| D::Error::custom("hole run without a length"))?;
+                blocks.resize(blocks.len() + run as usize, HOLE_BLOCK);
+            } else {
+                blocks.push(block);
+            }
+        }
+        Ok(blocks)
+    }
+}
+
+/// `FileMetadata` as written by catalogs from before `inline_data` existed
+///
+/// Bincode has no field names or lengths, so old catalogs can only be read
+/// with the exact old layout.
+#[derive(Deserialize)]
+pub(crate) struct FileMetadataV1 {
+    file_type: FileType,
+    size: u64,
+    blocks: Vec<u64>,
+    created_at: u64,
+    modified_at: u64,
+    permissions: u32,
+    owner: String,
+    content_hash: Option<[u8; 32]>,
+    content_type: Option<String>,
+    user_metadata: HashMap<String, String>,
+}
+
+impl From<FileMetadataV1> for FileMetadata {
+    fn from(old: FileMetadataV1) -> Self {
+        FileMetadata {
+            file_type: old.file_type,
+            size: old.size,
+            blocks: old.blocks,
+            created_at: old.created_at,
+            modified_at: old.modified_at,
+            permissions: old.permissions,
+            owner: old.owner,
+            content_hash: old.content_hash,
+            content_type: old.content_type,
+            user_metadata: old.user_metadata,
+            inline_data: None,
+        }
+    }
 }
 
 impl FileMetadata {
     /// Create new file metadata
     pub fn new(file_type: FileType, size: u64, blocks: Vec<u64>) -> Self {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        let now = crate::clock::unix_secs();
 
         FileMetadata {
             file_type,
@@ -74,12 +184,14 @@ impl FileMetadata {
             modified_at: now,
             permissions: match file_type {
                 FileType::Directory => 0o755,
-                _ => 0o644,
+                FileType::Symlink => 0o777,
+                FileType::File => 0o644,
             },
             owner: String::from("default"),
             content_hash: None,
             content_type: None,
             user_metadata: HashMap::new(),
+            inline_data: None,
         }
     }
 
@@ -88,12 +200,17 @@ impl FileMetadata {
         Self::new(FileType::Directory, 0, Vec::new())
     }
 
+    /// Create a symlink metadata entry pointing at `target`
+    ///
+    /// Like `lstat`, the size is the length of the target path.
+    pub fn symlink(target: &str) -> Self {
+        Self::new(FileType::Symlink, target.len() as u64, Vec::new())
+            .with_user_metadata(SYMLINK_TARGET_KEY, target)
+    }
+
     /// Update the modification timestamp
     pub fn touch(&mut self) {
-        self.modified_at = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        self.modified_at = crate::clock::unix_secs();
     }
 
     /// Check if this is a directory
@@ -106,6 +223,56 @@ impl FileMetadata {
         self.file_type == FileType::File
     }
 
+    /// Check if this is a symbolic link
+    pub fn is_symlink(&self) -> bool {
+        self.file_type == FileType::Symlink
+    }
+
+    /// [`content_hash`](Self::content_hash) as lowercase hex
+    pub fn content_hash_hex(&self) -> Option<String> {
+        let hash = self.content_hash?;
+        Some(hash.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    /// Check if the content is stored encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.user_metadata.get("encrypted").is_some_and(|v| v == "true")
+    }
+
+    /// Check if the content is encrypted with a key of its own, to be read
+    /// with `read_encrypted`
+    pub fn has_file_key(&self) -> bool {
+        self.is_encrypted() && self.user_metadata.contains_key(FILE_NONCE_KEY)
+    }
+
+    /// Check if the content lives in the catalog rather than in blocks
+    pub fn is_inline(&self) -> bool {
+        self.inline_data.is_some()
+    }
+
+    /// Blocks that hold content, skipping holes
+    pub fn data_blocks(&self) -> impl Iterator<Item = u64> + '_ {
+        self.blocks.iter().copied().filter(|&block| block != HOLE_BLOCK)
+    }
+
+    /// Number of pages that are holes
+    pub fn hole_count(&self) -> usize {
+        self.blocks.iter().filter(|&&block| block == HOLE_BLOCK).count()
+    }
+
+    /// Check if any page of the file is a hole
+    pub fn is_sparse(&self) -> bool {
+        self.blocks.contains(&HOLE_BLOCK)
+    }
+
+    /// The path a symlink points at, as stored (`None` for other types)
+    pub fn symlink_target(&self) -> Option<&str> {
+        if !self.is_symlink() {
+            return None;
+        }
+        self.user_metadata.get(SYMLINK_TARGET_KEY).map(String::as_str)
+    }
+
     /// Set content type (for S3 compatibility)
     pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
         self.content_type = Some(content_type.into());
@@ -119,6 +286,70 @@ impl FileMetadata {
     }
 }
 
+/// Metadata changes for one entry, applied without touching its content
+///
+/// Fields left as `None` (and keys not listed) are kept. Used by
+/// `Cartridge::update_metadata_bulk`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetadataPatch {
+    /// New creation time, in seconds since the Unix epoch
+    pub created_at: Option<u64>,
+    /// New modification time, in seconds since the Unix epoch
+    pub modified_at: Option<u64>,
+    /// New content type; `Some(None)` clears it
+    pub content_type: Option<Option<String>>,
+    /// User metadata keys to set, or to remove when the value is `None`
+    pub user_metadata: HashMap<String, Option<String>>,
+}
+
+impl MetadataPatch {
+    /// Set both timestamps
+    pub fn times(created_at: Option<u64>, modified_at: Option<u64>) -> Self {
+        MetadataPatch {
+            created_at,
+            modified_at,
+            ..Default::default()
+        }
+    }
+
+    /// Also set the content type
+    pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(Some(content_type.into()));
+        self
+    }
+
+    /// Also set a user metadata key
+    pub fn with_user_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.user_metadata.insert(key.into(), Some(value.into()));
+        self
+    }
+
+    /// Also remove a user metadata key
+    pub fn without_user_metadata(mut self, key: impl Into<String>) -> Self {
+        self.user_metadata.insert(key.into(), None);
+        self
+    }
+
+    /// Apply the changes to `metadata`
+    pub(crate) fn apply(&self, metadata: &mut FileMetadata) {
+        if let Some(created_at) = self.created_at {
+            metadata.created_at = created_at;
+        }
+        if let Some(modified_at) = self.modified_at {
+            metadata.modified_at = modified_at;
+        }
+        if let Some(content_type) = &self.content_type {
+            metadata.content_type = content_type.clone();
+        }
+        for (key, value) in &self.user_metadata {
+            match value {
+                Some(value) => metadata.user_metadata.insert(key.clone(), value.clone()),
+                None => metadata.user_metadata.remove(key),
+            };
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -154,6 +385,32 @@ mod tests {
         assert!(meta.modified_at >= original_modified);
     }
 
+    #[test]
+    fn test_holes() {
+        let meta = FileMetadata::new(FileType::File, 4 * 4096, vec![7, HOLE_BLOCK, HOLE_BLOCK, 9]);
+        assert!(meta.is_sparse());
+        assert_eq!(meta.hole_count(), 2);
+        assert_eq!(meta.data_blocks().collect::<Vec<_>>(), vec![7, 9]);
+        assert!(!FileMetadata::new(FileType::File, 4096, vec![7]).is_sparse());
+    }
+
+    #[test]
+    fn test_hole_runs_serialize_compactly() {
+        let mut blocks = vec![HOLE_BLOCK; 100_000];
+        blocks[0] = 3;
+        blocks[50_000] = 4;
+        let meta = FileMetadata::new(FileType::File, 100_000 * 4096, blocks.clone());
+        let bytes = bincode::serialize(&meta).unwrap();
+        assert!(bytes.len() < 200);
+        let decoded: FileMetadata = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded.blocks, blocks);
+
+        let plain = FileMetadata::new(FileType::File, 3 * 4096, vec![5, 6, 7]);
+        let bytes = bincode::serialize(&plain).unwrap();
+        let decoded: FileMetadata = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded.blocks, vec![5, 6, 7]);
+    }
+
     #[test]
     fn test_serialization() {
         let meta = FileMetadata::new(FileType::File, 2048, vec![10, 20, 30]);
@@ -3,15 +3,74 @@
 //! Maps file paths to their metadata and block locations.
 //! Uses a standard BTreeMap for ordered lookups, inserts, and prefix queries.
 //! Serialized with bincode for compact binary storage.
+//!
+//! A catalog too big for one page is stored in segments: runs of entries in
+//! key order, each about [`SEGMENT_TARGET_BYTES`] long, with a directory of
+//! them in the catalog page. The catalog remembers which segments changed
+//! since they were written, so a flush only rewrites those. Segments only
+//! make flushing incremental: opening still reads and decodes every one,
+//! and the whole catalog stays in memory.
+//!
+//! In case-insensitive mode an index of lowercased keys sits beside the
+//! entries, which keep the case they were created with.
 
 pub mod btree;
 pub mod metadata;
 
-pub use metadata::{FileMetadata, FileType};
+pub use metadata::{FileMetadata, FileType, MetadataPatch, HOLE_BLOCK};
 
-use crate::error::Result;
+use metadata::FileMetadataV1;
+
+use crate::error::{CartridgeError, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::ops::Bound;
+
+/// Prefix of catalogs whose entries carry `inline_data`
+///
+/// Older catalogs start straight with the bincode `root_page`, which is
+/// always a small integer, so they can't be mistaken for this.
+const CATALOG_V2_MAGIC: &[u8; 4] = b"CAT2";
+
+/// Prefix of a segment directory
+const CATALOG_SEGMENTED_MAGIC: &[u8; 4] = b"CAT3";
+
+/// Serialized size at which a segment is cut
+pub(crate) const SEGMENT_TARGET_BYTES: usize = 32 * 1024;
+
+/// One segment as listed in the directory
+///
+/// A segment holds the entries from `first_key` up to the next segment's
+/// `first_key`, as consecutive bincode `(path, metadata)` pairs `len`
+/// bytes long spread over `pages`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SegmentRef {
+    pub first_key: String,
+    pub pages: Vec<u64>,
+    pub len: u64,
+}
+
+/// Segment directory as stored in the catalog page
+#[derive(Serialize, Deserialize)]
+struct SegmentDirectory {
+    root_page: u64,
+    segments: Vec<SegmentRef>,
+}
+
+/// Where a segment was last written, and whether it changed since
+#[derive(Debug, Clone)]
+struct Segment {
+    pages: Vec<u64>,
+    len: u64,
+    dirty: bool,
+}
+
+/// Catalog layout from before `inline_data` existed
+#[derive(Deserialize)]
+struct CatalogV1 {
+    root_page: u64,
+    entries: BTreeMap<String, FileMetadataV1>,
+}
 
 /// Catalog for managing file metadata
 ///
@@ -25,6 +84,30 @@ pub struct Catalog {
 
     /// The actual file index
     entries: BTreeMap<String, FileMetadata>,
+
+    /// On-disk segments keyed by first key; empty until the catalog is
+    /// first stored segmented. The first segment's key is always `""`.
+    #[serde(skip)]
+    segments: BTreeMap<String, Segment>,
+
+    /// Case-folded key to stored key; only in case-insensitive mode
+    #[serde(skip)]
+    folded: Option<BTreeMap<String, String>>,
+
+    /// An entry with holes was inserted since the catalog was created or
+    /// loaded
+    #[serde(skip)]
+    has_holes: bool,
+
+    /// An entry with inline content was inserted since the catalog was
+    /// created or loaded
+    #[serde(skip)]
+    has_inline_data: bool,
+
+    /// Fail the next insert, to test that callers clean up after it
+    #[cfg(test)]
+    #[serde(skip)]
+    fail_next_insert: bool,
 }
 
 impl Catalog {
@@ -33,35 +116,125 @@ impl Catalog {
         Catalog {
             root_page,
             entries: BTreeMap::new(),
+            segments: BTreeMap::new(),
+            folded: None,
+            has_holes: false,
+            has_inline_data: false,
+            #[cfg(test)]
+            fail_next_insert: false,
+        }
+    }
+
+    /// Match paths regardless of case from now on
+    ///
+    /// Keys keep the case they were stored with; [`insert`](Self::insert)
+    /// under a path differing only in case updates the existing entry.
+    /// Prefix listings come in case-folded key order.
+    pub fn set_case_insensitive(&mut self) {
+        let mut folded = BTreeMap::new();
+        for key in self.entries.keys() {
+            folded.entry(fold_case(key)).or_insert_with(|| key.clone());
+        }
+        self.folded = Some(folded);
+    }
+
+    /// Whether lookups ignore case
+    pub fn is_case_insensitive(&self) -> bool {
+        self.folded.is_some()
+    }
+
+    /// The stored key `path` refers to
+    fn resolve<'a>(&'a self, path: &'a str) -> &'a str {
+        match &self.folded {
+            Some(folded) => folded.get(&fold_case(path)).map_or(path, String::as_str),
+            None => path,
         }
     }
 
     /// Insert or update file metadata
     pub fn insert(&mut self, path: &str, metadata: FileMetadata) -> Result<()> {
-        self.entries.insert(path.to_string(), metadata);
+        #[cfg(test)]
+        if std::mem::take(&mut self.fail_next_insert) {
+            return Err(std::io::Error::other("injected catalog insert failure").into());
+        }
+        let key = self.resolve(path).to_string();
+        if let Some(folded) = &mut self.folded {
+            folded.entry(fold_case(&key)).or_insert_with(|| key.clone());
+        }
+        self.mark_dirty(&key);
+        self.has_holes = self.has_holes || metadata.blocks.contains(&HOLE_BLOCK);
+        self.has_inline_data = self.has_inline_data || metadata.inline_data.is_some();
+        self.entries.insert(key, metadata);
         Ok(())
     }
 
+    /// Whether an entry with holes was inserted since the catalog was
+    /// created or loaded
+    pub(crate) fn has_holes(&self) -> bool {
+        self.has_holes
+    }
+
+    /// Whether an entry with inline content was inserted since the catalog
+    /// was created or loaded
+    pub(crate) fn has_inline_data(&self) -> bool {
+        self.has_inline_data
+    }
+
+    /// Make the next [`insert`](Self::insert) fail without changing anything
+    #[cfg(test)]
+    pub(crate) fn fail_next_insert(&mut self) {
+        self.fail_next_insert = true;
+    }
+
     /// Look up file metadata by path
     pub fn get(&self, path: &str) -> Result<Option<FileMetadata>> {
-        Ok(self.entries.get(path).cloned())
+        Ok(self.entries.get(self.resolve(path)).cloned())
     }
 
     /// Delete a file from the catalog
     pub fn delete(&mut self, path: &str) -> Result<Option<FileMetadata>> {
-        Ok(self.entries.remove(path))
+        let key = self.resolve(path).to_string();
+        let removed = self.entries.remove(&key);
+        if removed.is_some() {
+            self.mark_dirty(&key);
+            if let Some(folded) = &mut self.folded {
+                folded.remove(&fold_case(&key));
+            }
+        }
+        Ok(removed)
     }
 
     /// List all files with a given prefix (directory listing)
     pub fn list_prefix(&self, prefix: &str) -> Result<Vec<(String, FileMetadata)>> {
         Ok(self
-            .entries
-            .range(prefix.to_string()..)
-            .take_while(|(k, _)| k.starts_with(prefix))
+            .iter_prefix(prefix.to_string())
             .map(|(k, v)| (k.clone(), v.clone()))
             .collect())
     }
 
+    /// Iterate the entries under a prefix in key order without copying them
+    pub fn iter_prefix(
+        &self,
+        prefix: String,
+    ) -> Box<dyn Iterator<Item = (&String, &FileMetadata)> + '_> {
+        match &self.folded {
+            Some(folded) => {
+                let prefix = fold_case(&prefix);
+                Box::new(
+                    folded
+                        .range(prefix.clone()..)
+                        .take_while(move |(k, _)| k.starts_with(&prefix))
+                        .filter_map(|(_, key)| self.entries.get_key_value(key)),
+                )
+            }
+            None => Box::new(
+                self.entries
+                    .range(prefix.clone()..)
+                    .take_while(move |(k, _)| k.starts_with(&prefix)),
+            ),
+        }
+    }
+
     /// Get the root page ID
     pub fn root_page(&self) -> u64 {
         self.root_page
@@ -69,18 +242,218 @@ impl Catalog {
 
     /// Serialize to bincode bytes
     pub fn to_bytes(&self) -> Result<Vec<u8>> {
-        bincode::serialize(self).map_err(|e| {
+        let mut bytes = CATALOG_V2_MAGIC.to_vec();
+        bincode::serialize_into(&mut bytes, self).map_err(|e| {
             crate::error::CartridgeError::Corruption(format!("catalog serialize: {e}"))
-        })
+        })?;
+        Ok(bytes)
     }
 
-    /// Deserialize from bincode bytes
+    /// Deserialize from bincode bytes, in either the current or the original layout
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
-        bincode::deserialize(data).map_err(|e| {
-            crate::error::CartridgeError::Corruption(format!("catalog deserialize: {e}"))
+        let corruption =
+            |e| crate::error::CartridgeError::Corruption(format!("catalog deserialize: {e}"));
+
+        if let Some(current) = data.strip_prefix(CATALOG_V2_MAGIC) {
+            return bincode::deserialize(current).map_err(corruption);
+        }
+
+        let old: CatalogV1 = bincode::deserialize(data).map_err(corruption)?;
+        Ok(Catalog {
+            root_page: old.root_page,
+            entries: old
+                .entries
+                .into_iter()
+                .map(|(path, metadata)| (path, metadata.into()))
+                .collect(),
+            segments: BTreeMap::new(),
+            folded: None,
+            has_holes: false,
+            has_inline_data: false,
+            #[cfg(test)]
+            fail_next_insert: false,
         })
     }
 
+    /// Whether the catalog was last stored (or loaded) as segments
+    pub(crate) fn is_segmented(&self) -> bool {
+        !self.segments.is_empty()
+    }
+
+    /// Pages of every segment
+    pub(crate) fn segment_pages(&self) -> Vec<u64> {
+        self.segments.values().flat_map(|s| s.pages.iter().copied()).collect()
+    }
+
+    /// Pages of segments that haven't changed since they were written
+    pub(crate) fn clean_segment_pages(&self) -> Vec<u64> {
+        self.segments
+            .values()
+            .filter(|s| !s.dirty)
+            .flat_map(|s| s.pages.iter().copied())
+            .collect()
+    }
+
+    /// Forget where segments live so the next flush stores everything anew
+    ///
+    /// Needed whenever the layout may no longer match the allocator, e.g.
+    /// after a failed flush or a rollback.
+    pub(crate) fn forget_segments(&mut self) {
+        self.segments.clear();
+    }
+
+    /// Encode every changed segment, dropping it from the layout
+    ///
+    /// Returns `(first_key, payload)` for each segment to write, re-cut at
+    /// [`SEGMENT_TARGET_BYTES`]. A catalog that isn't segmented yet comes
+    /// back whole. Each payload must be written and handed back through
+    /// [`add_segment`](Self::add_segment) before the directory is built.
+    pub(crate) fn take_dirty_segments(&mut self) -> Result<Vec<(String, Vec<u8>)>> {
+        if self.segments.is_empty() {
+            self.segments.insert(String::new(), Segment { pages: vec![], len: 0, dirty: true });
+        }
+
+        let starts: Vec<String> = self.segments.keys().cloned().collect();
+        let mut out = Vec::new();
+        for (i, start) in starts.iter().enumerate() {
+            if !self.segments[start].dirty {
+                continue;
+            }
+            self.segments.remove(start);
+
+            let end = match starts.get(i + 1) {
+                Some(next) => Bound::Excluded(next.as_str()),
+                None => Bound::Unbounded,
+            };
+            let range = self
+                .entries
+                .range::<str, _>((Bound::Included(start.as_str()), end));
+
+            let mut first_key = start.clone();
+            let mut payload = Vec::new();
+            for (path, metadata) in range {
+                if payload.len() >= SEGMENT_TARGET_BYTES {
+                    out.push((std::mem::replace(&mut first_key, path.clone()), payload));
+                    payload = Vec::new();
+                }
+                bincode::serialize_into(&mut payload, &(path, metadata))
+                    .map_err(|e| CartridgeError::Corruption(format!("catalog serialize: {e}")))?;
+            }
+            // An emptied segment folds into the one before it, except the
+            // first, which has to stay so every key has a segment
+            if !payload.is_empty() || first_key.is_empty() {
+                out.push((first_key, payload));
+            }
+        }
+        Ok(out)
+    }
+
+    /// Record where a segment from [`take_dirty_segments`](Self::take_dirty_segments)
+    /// was written
+    pub(crate) fn add_segment(&mut self, first_key: String, pages: Vec<u64>, len: u64) {
+        self.segments.insert(first_key, Segment { pages, len, dirty: false });
+    }
+
+    /// Serialize the segment directory for the catalog page
+    pub(crate) fn segment_directory(&self) -> Result<Vec<u8>> {
+        let directory = SegmentDirectory {
+            root_page: self.root_page,
+            segments: self
+                .segments
+                .iter()
+                .map(|(first_key, s)| SegmentRef {
+                    first_key: first_key.clone(),
+                    pages: s.pages.clone(),
+                    len: s.len,
+                })
+                .collect(),
+        };
+        let mut bytes = CATALOG_SEGMENTED_MAGIC.to_vec();
+        bincode::serialize_into(&mut bytes, &directory)
+            .map_err(|e| CartridgeError::Corruption(format!("catalog serialize: {e}")))?;
+        Ok(bytes)
+    }
+
+    /// Parse a segment directory, or `None` if `data` is a whole catalog
+    pub(crate) fn parse_segment_directory(data: &[u8]) -> Option<Result<(u64, Vec<SegmentRef>)>> {
+        let directory = data.strip_prefix(CATALOG_SEGMENTED_MAGIC)?;
+        Some(
+            bincode::deserialize::<SegmentDirectory>(directory)
+                .map(|d| (d.root_page, d.segments))
+                .map_err(|e| CartridgeError::Corruption(format!("catalog directory: {e}"))),
+        )
+    }
+
+    /// Rebuild a catalog from its segments and their payloads
+    pub(crate) fn from_segments(
+        root_page: u64,
+        segments: impl IntoIterator<Item = (SegmentRef, Vec<u8>)>,
+    ) -> Result<Self> {
+        let mut catalog = Catalog::new(root_page);
+        for (segment, payload) in segments {
+            let mut reader = payload.get(..segment.len as usize).ok_or_else(|| {
+                CartridgeError::Corruption(format!(
+                    "catalog segment {:?} is shorter than its length",
+                    segment.first_key
+                ))
+            })?;
+            while !reader.is_empty() {
+                let (path, metadata): (String, FileMetadata) =
+                    bincode::deserialize_from(&mut reader).map_err(|e| {
+                        CartridgeError::Corruption(format!("catalog segment: {e}"))
+                    })?;
+                catalog.entries.insert(path, metadata);
+            }
+            catalog.add_segment(segment.first_key, segment.pages, segment.len);
+        }
+        if !catalog.segments.contains_key("") {
+            return Err(CartridgeError::Corruption(
+                "catalog directory has no first segment".to_string(),
+            ));
+        }
+        Ok(catalog)
+    }
+
+    /// Flag the segment holding `path` as changed
+    fn mark_dirty(&mut self, path: &str) {
+        if let Some((_, segment)) = self
+            .segments
+            .range_mut::<str, _>((Bound::Unbounded, Bound::Included(path)))
+            .next_back()
+        {
+            segment.dirty = true;
+        }
+    }
+
+    /// Re-key entries stored under paths that predate path normalization
+    /// ("docs/a.txt", "a//b") to their canonical form, returning how many
+    /// moved. A key whose canonical form is already taken, or which can't be
+    /// normalized at all, is left where it is.
+    pub fn normalize_keys(&mut self) -> usize {
+        let legacy: Vec<(String, String)> = self
+            .entries
+            .keys()
+            .filter_map(|key| match crate::validation::normalize_path(key) {
+                Ok(normalized) if normalized != *key => Some((key.clone(), normalized)),
+                _ => None,
+            })
+            .collect();
+
+        let mut moved = 0;
+        for (key, normalized) in legacy {
+            if self.entries.contains_key(&normalized) {
+                continue;
+            }
+            if let Some(metadata) = self.entries.remove(&key) {
+                self.mark_dirty(&key);
+                self.mark_dirty(&normalized);
+                self.entries.insert(normalized, metadata);
+                moved += 1;
+            }
+        }
+        moved
+    }
+
     /// Number of entries
     pub fn len(&self) -> usize {
         self.entries.len()
@@ -91,3 +464,151 @@ impl Catalog {
         self.entries.is_empty()
     }
 }
+
+/// Key under which case-insensitive lookups compare paths
+pub(crate) fn fold_case(path: &str) -> String {
+    path.to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_round_trip_keeps_inline_data() {
+        let mut catalog = Catalog::new(1);
+        let mut metadata = FileMetadata::new(FileType::File, 5, Vec::new());
+        metadata.inline_data = Some(b"hello".to_vec());
+        catalog.insert("small.txt", metadata).unwrap();
+
+        let restored = Catalog::from_bytes(&catalog.to_bytes().unwrap()).unwrap();
+        let metadata = restored.get("small.txt").unwrap().unwrap();
+        assert_eq!(metadata.inline_data.as_deref(), Some(&b"hello"[..]));
+    }
+
+    #[test]
+    fn test_case_insensitive_keys_keep_their_case() {
+        let mut catalog = Catalog::new(1);
+        catalog
+            .insert("/Docs/Readme.MD", FileMetadata::new(FileType::File, 1, Vec::new()))
+            .unwrap();
+        catalog.set_case_insensitive();
+
+        assert!(catalog.get("/docs/readme.md").unwrap().is_some());
+        catalog
+            .insert("/DOCS/README.md", FileMetadata::new(FileType::File, 2, Vec::new()))
+            .unwrap();
+        catalog
+            .insert("/docs/other.md", FileMetadata::new(FileType::File, 3, Vec::new()))
+            .unwrap();
+        assert_eq!(catalog.len(), 2);
+
+        let listed: Vec<(String, u64)> = catalog
+            .list_prefix("/DOCS/")
+            .unwrap()
+            .into_iter()
+            .map(|(path, metadata)| (path, metadata.size))
+            .collect();
+        assert_eq!(
+            listed,
+            [("/docs/other.md".to_string(), 3), ("/Docs/Readme.MD".to_string(), 2)]
+        );
+
+        assert!(catalog.delete("/docs/README.MD").unwrap().is_some());
+        assert!(catalog.get("/Docs/Readme.MD").unwrap().is_none());
+        assert_eq!(catalog.list_prefix("/").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_segments_round_trip_and_track_changes() {
+        let mut catalog = Catalog::new(1);
+        for i in 0..2000 {
+            let metadata = FileMetadata::new(FileType::File, 4096, vec![i]);
+            catalog.insert(&format!("f-{i:05}"), metadata).unwrap();
+        }
+
+        // Every segment goes to a "page" of its own
+        let mut payloads = HashMap::new();
+        let write = |catalog: &mut Catalog, payloads: &mut HashMap<u64, Vec<u8>>| {
+            for (first_key, payload) in catalog.take_dirty_segments().unwrap() {
+                let page = payloads.len() as u64;
+                catalog.add_segment(first_key, vec![page], payload.len() as u64);
+                payloads.insert(page, payload);
+            }
+        };
+        write(&mut catalog, &mut payloads);
+        assert!(catalog.segments.len() > 1);
+        assert!(catalog.segments.contains_key(""));
+
+        // A change dirties one segment; emptying one folds it away
+        catalog.delete("f-00000").unwrap();
+        assert_eq!(catalog.segments.values().filter(|s| s.dirty).count(), 1);
+        let second_key = catalog.segments.keys().nth(1).unwrap().clone();
+        let third_key = catalog.segments.keys().nth(2).unwrap().clone();
+        let emptied: Vec<String> =
+            catalog.entries.range(second_key.clone()..third_key).map(|(k, _)| k.clone()).collect();
+        for path in &emptied {
+            catalog.delete(path).unwrap();
+        }
+        let before = catalog.segments.len();
+        write(&mut catalog, &mut payloads);
+        assert_eq!(catalog.segments.len(), before - 1);
+        assert!(!catalog.segments.contains_key(&second_key));
+
+        let (root_page, segments) =
+            Catalog::parse_segment_directory(&catalog.segment_directory().unwrap())
+                .unwrap()
+                .unwrap();
+        let loaded = Catalog::from_segments(
+            root_page,
+            segments.into_iter().map(|s| {
+                let payload = payloads[&s.pages[0]].clone();
+                (s, payload)
+            }),
+        )
+        .unwrap();
+        assert_eq!(loaded.len(), 2000 - 1 - emptied.len());
+        assert_eq!(loaded.get("f-01999").unwrap().unwrap().blocks, vec![1999]);
+        assert!(Catalog::parse_segment_directory(&catalog.to_bytes().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_reads_catalog_without_inline_data() {
+        // Field-for-field the layout bincode produced before inline_data
+        type OldMetadata = (
+            FileType,
+            u64,
+            Vec<u64>,
+            u64,
+            u64,
+            u32,
+            String,
+            Option<[u8; 32]>,
+            Option<String>,
+            HashMap<String, String>,
+        );
+        let old_entry: OldMetadata = (
+            FileType::File,
+            4096,
+            vec![3],
+            1,
+            2,
+            0o644,
+            "default".to_string(),
+            Some([9; 32]),
+            Some("text/plain".to_string()),
+            HashMap::new(),
+        );
+        let old_catalog: (u64, BTreeMap<String, OldMetadata>) =
+            (1, BTreeMap::from([("a.txt".to_string(), old_entry)]));
+        let bytes = bincode::serialize(&old_catalog).unwrap();
+
+        let catalog = Catalog::from_bytes(&bytes).unwrap();
+        let metadata = catalog.get("a.txt").unwrap().unwrap();
+        assert_eq!(metadata.blocks, vec![3]);
+        assert_eq!(metadata.content_hash, Some([9; 32]));
+        assert_eq!(metadata.content_type.as_deref(), Some("text/plain"));
+        assert!(!metadata.is_inline());
+    }
+}
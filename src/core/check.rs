@@ -0,0 +1,142 @@
+//! Consistency checking (fsck) for cartridge archives
+//!
+//! [`Cartridge::check`](crate::Cartridge::check) cross-references the catalog
+//! against the allocator and header and collects every disagreement into a
+//! [`ConsistencyReport`]. [`Cartridge::repair`](crate::Cartridge::repair)
+//! rebuilds the allocator from the catalog, which is treated as the source of
+//! truth.
+//!
+//! [`Cartridge::scrub`](crate::Cartridge::scrub) checks the content pages
+//! themselves, a slice at a time, so it can run in idle periods without
+//! holding the cartridge for a whole pass. A [`ScrubToken`] says where the
+//! next call picks up, and [`ScrubResult`]s from successive calls are
+//! combined with [`ScrubResult::merge`].
+
+use serde::{Deserialize, Serialize};
+
+/// A single block referenced by a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockRef {
+    /// Path of the file referencing the block
+    pub path: String,
+    /// Block ID
+    pub block: u64,
+}
+
+/// A block claimed by more than one file (or more than once by one file)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharedBlock {
+    /// Block ID
+    pub block: u64,
+    /// Every path referencing the block, in catalog order
+    pub paths: Vec<String>,
+}
+
+/// Result of a catalog/allocator consistency check
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsistencyReport {
+    /// Total blocks recorded in the header
+    pub header_total_blocks: u64,
+    /// Total blocks tracked by the allocator
+    pub allocator_total_blocks: u64,
+    /// Free block count recorded in the header
+    pub header_free_blocks: u64,
+    /// Free block count cached by the allocator
+    pub allocator_free_blocks: u64,
+    /// Free blocks actually marked free in the allocator bitmap
+    pub bitmap_free_blocks: u64,
+
+    /// Blocks referenced by a file but marked free in the allocator
+    pub unallocated_refs: Vec<BlockRef>,
+    /// Blocks referenced by a file that lie beyond the end of the archive
+    pub out_of_range_refs: Vec<BlockRef>,
+    /// Blocks referenced more than once
+    pub shared_blocks: Vec<SharedBlock>,
+    /// Blocks marked allocated that nothing references
+    pub orphaned_blocks: Vec<u64>,
+}
+
+impl ConsistencyReport {
+    /// True if the header and allocator agree on block counts
+    pub fn counts_match(&self) -> bool {
+        self.header_total_blocks == self.allocator_total_blocks
+            && self.header_free_blocks == self.bitmap_free_blocks
+            && self.allocator_free_blocks == self.bitmap_free_blocks
+    }
+
+    /// True if no problems were found
+    pub fn is_consistent(&self) -> bool {
+        self.counts_match()
+            && self.unallocated_refs.is_empty()
+            && self.out_of_range_refs.is_empty()
+            && self.shared_blocks.is_empty()
+            && self.orphaned_blocks.is_empty()
+    }
+
+    /// True if every problem found can be fixed by
+    /// [`Cartridge::repair`](crate::Cartridge::repair)
+    ///
+    /// Shared and out-of-range blocks mean file contents are already lost or
+    /// aliased; rebuilding the allocator can't recover them.
+    pub fn is_repairable(&self) -> bool {
+        self.out_of_range_refs.is_empty() && self.shared_blocks.is_empty()
+    }
+}
+
+/// Where a scrub pass continues; returned in [`ScrubResult::next`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScrubToken {
+    /// Lowest block ID not yet checked
+    pub next_block: u64,
+}
+
+/// A content page that failed to read back
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScrubError {
+    /// Block ID
+    pub block: u64,
+    /// Every file referencing the block
+    pub paths: Vec<String>,
+    /// Why the page couldn't be read
+    pub error: String,
+}
+
+/// Outcome of one or more [`Cartridge::scrub`](crate::Cartridge::scrub) calls
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScrubResult {
+    /// Content pages read back
+    pub pages_checked: u64,
+    /// Pages skipped because they haven't been flushed yet
+    pub pages_skipped: u64,
+    /// Pages that failed their checksum or couldn't be read
+    pub errors: Vec<ScrubError>,
+    /// Where to continue, or `None` once the pass reached the last block
+    pub next: Option<ScrubToken>,
+}
+
+impl ScrubResult {
+    /// True once the pass has covered every content page
+    pub fn is_complete(&self) -> bool {
+        self.next.is_none()
+    }
+
+    /// Fold in the result of the next call in the same pass
+    pub fn merge(&mut self, later: ScrubResult) {
+        self.pages_checked += later.pages_checked;
+        self.pages_skipped += later.pages_skipped;
+        self.errors.extend(later.errors);
+        self.next = later.next;
+    }
+
+    /// Paths of every file with at least one bad page, sorted
+    pub fn damaged_paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = self
+            .errors
+            .iter()
+            .flat_map(|error| error.paths.iter().cloned())
+            .collect();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+}
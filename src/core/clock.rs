@@ -0,0 +1,63 @@
+//! Wall-clock time, from a source the embedder can replace
+//!
+//! Every timestamp a cartridge records (file times, audit entries, change
+//! events, the last flush time) is read through [`now`]. Native builds use
+//! the system clock. On wasm32-unknown-unknown `SystemTime::now` panics,
+//! so there the clock stands at the Unix epoch until the embedder installs
+//! one with [`set_clock`], typically wrapping JavaScript's `Date.now()`.
+//!
+//! ```
+//! use cartridge_rs::core::clock;
+//! use std::time::{Duration, SystemTime, UNIX_EPOCH};
+//!
+//! fn fixed() -> SystemTime {
+//!     UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+//! }
+//!
+//! clock::set_clock(fixed);
+//! assert_eq!(clock::unix_secs(), 1_700_000_000);
+//! # clock::reset_clock();
+//! ```
+
+use parking_lot::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A source of the current time
+pub type Clock = fn() -> SystemTime;
+
+static CLOCK: RwLock<Clock> = RwLock::new(default_clock);
+
+#[cfg(not(target_arch = "wasm32"))]
+fn default_clock() -> SystemTime {
+    SystemTime::now()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn default_clock() -> SystemTime {
+    UNIX_EPOCH
+}
+
+/// Read the time from `clock` from now on, in every cartridge
+pub fn set_clock(clock: Clock) {
+    *CLOCK.write() = clock;
+}
+
+/// Go back to the platform's default clock
+pub fn reset_clock() {
+    set_clock(default_clock);
+}
+
+/// The current time
+pub fn now() -> SystemTime {
+    (CLOCK.read())()
+}
+
+/// Whole seconds since the Unix epoch, or 0 for a clock set before it
+pub fn unix_secs() -> u64 {
+    now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Microseconds since the Unix epoch, or 0 for a clock set before it
+pub fn unix_micros() -> u64 {
+    now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_micros() as u64)
+}
@@ -98,6 +98,9 @@ pub fn compress(data: &[u8], method: CompressionMethod) -> Result<Vec<u8>> {
             let compressed = lz4_flex::compress_prepend_size(data);
             Ok(compressed)
         }
+        #[cfg(feature = "no-fs")]
+        CompressionMethod::Zstd => Err(zstd_unavailable()),
+        #[cfg(not(feature = "no-fs"))]
         CompressionMethod::Zstd => {
             let compressed = zstd::bulk::compress(data, 3).map_err(|e| {
                 CartridgeError::Allocation(format!("Zstd compression failed: {}", e))
@@ -117,6 +120,9 @@ pub fn decompress(data: &[u8], method: CompressionMethod) -> Result<Vec<u8>> {
             })?;
             Ok(decompressed)
         }
+        #[cfg(feature = "no-fs")]
+        CompressionMethod::Zstd => Err(zstd_unavailable()),
+        #[cfg(not(feature = "no-fs"))]
         CompressionMethod::Zstd => {
             // For Zstd, we need to provide a max size. Use a generous limit for general use.
             // For page data, this will be (PAGE_SIZE - header) but for benchmarks and other uses
@@ -130,6 +136,16 @@ pub fn decompress(data: &[u8], method: CompressionMethod) -> Result<Vec<u8>> {
     }
 }
 
+/// Zstd is C code, which no-fs builds leave out so they can target wasm32
+#[cfg(feature = "no-fs")]
+fn zstd_unavailable() -> CartridgeError {
+    std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "Zstd compression isn't available with the no-fs feature",
+    )
+    .into()
+}
+
 /// Compress data if beneficial, returns (data, method_used)
 pub fn compress_if_beneficial(
     data: &[u8],
@@ -182,6 +198,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "no-fs"))]
     fn test_zstd_compression() {
         let data = b"Zstandard compression test data! ".repeat(100);
         let compressed = compress(&data, CompressionMethod::Zstd).unwrap();
@@ -0,0 +1,110 @@
+//! Content type (MIME) detection from file extensions
+//!
+//! A small built-in table covering the formats cartridges commonly hold.
+//! Unknown extensions return `None` rather than guessing.
+
+/// Content type of files written with `write_json`
+pub const JSON: &str = "application/json";
+
+/// Content type of files written with `write_string`
+pub const TEXT_UTF8: &str = "text/plain; charset=utf-8";
+
+/// Extension → MIME type table (extensions are lowercase, without the dot)
+const CONTENT_TYPES: &[(&str, &str)] = &[
+    // Text
+    ("txt", "text/plain"),
+    ("md", "text/markdown"),
+    ("csv", "text/csv"),
+    ("tsv", "text/tab-separated-values"),
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("css", "text/css"),
+    ("xml", "application/xml"),
+    ("yaml", "application/yaml"),
+    ("yml", "application/yaml"),
+    ("toml", "application/toml"),
+    ("json", "application/json"),
+    ("cml", "application/xml"),
+    // Code
+    ("js", "text/javascript"),
+    ("mjs", "text/javascript"),
+    ("ts", "text/x-typescript"),
+    ("rs", "text/x-rust"),
+    ("py", "text/x-python"),
+    ("sh", "application/x-sh"),
+    ("wasm", "application/wasm"),
+    // Images
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("svg", "image/svg+xml"),
+    ("ico", "image/x-icon"),
+    ("bmp", "image/bmp"),
+    // Audio / video
+    ("mp3", "audio/mpeg"),
+    ("wav", "audio/wav"),
+    ("ogg", "audio/ogg"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+    // Documents
+    ("pdf", "application/pdf"),
+    // Archives and databases
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("tar", "application/x-tar"),
+    ("zst", "application/zstd"),
+    ("db", "application/vnd.sqlite3"),
+    ("sqlite", "application/vnd.sqlite3"),
+    ("eng", "application/x-engram"),
+    ("cart", "application/x-cartridge"),
+    // Fonts
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("ttf", "font/ttf"),
+    // Binary
+    ("bin", "application/octet-stream"),
+];
+
+/// Guess a content type from the extension of `path`
+///
+/// Matching is case-insensitive. Returns `None` for paths without an
+/// extension or with one not in the table.
+///
+/// # Examples
+///
+/// ```
+/// use cartridge_rs::core::content_type::from_path;
+///
+/// assert_eq!(from_path("docs/readme.md"), Some("text/markdown"));
+/// assert_eq!(from_path("IMAGE.PNG"), Some("image/png"));
+/// assert_eq!(from_path("Makefile"), None);
+/// ```
+pub fn from_path(path: &str) -> Option<&'static str> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let (stem, ext) = name.rsplit_once('.')?;
+    if stem.is_empty() {
+        return None; // dotfile like ".gitignore"
+    }
+    let ext = ext.to_ascii_lowercase();
+    CONTENT_TYPES
+        .iter()
+        .find(|(e, _)| *e == ext)
+        .map(|(_, mime)| *mime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(from_path("a/b/data.json"), Some("application/json"));
+        assert_eq!(from_path("photo.JPG"), Some("image/jpeg"));
+        assert_eq!(from_path("archive.tar.gz"), Some("application/gzip"));
+        assert_eq!(from_path("dir.d/noext"), None);
+        assert_eq!(from_path(".gitignore"), None);
+        assert_eq!(from_path("file.unknownext"), None);
+    }
+}
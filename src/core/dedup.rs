@@ -0,0 +1,291 @@
+//! Content-addressed block deduplication
+//!
+//! With dedup turned on, every content page written is hashed (SHA-256)
+//! and a page whose hash is already indexed reuses the existing block
+//! instead of getting a new one. Shared blocks carry a reference count;
+//! deleting or overwriting a file only frees blocks whose count drops to
+//! zero.
+//!
+//! The hash index is saved to `.cartridge/dedup.idx` on flush. Reference
+//! counts are not stored separately: a shared block appears once in the
+//! block list of every file using it, so the catalog already records them
+//! and they are counted from it the first time an operation needs them.
+//! Sessions that only read never pay for the count.
+//!
+//! Blocks can also be shared without a hash, by cloning a file. Those are
+//! tracked only while more than one reference remains; a block with a
+//! single owner and no hash can be changed in place.
+
+use crate::catalog::FileMetadata;
+use crate::error::{CartridgeError, Result};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+
+/// Hash of one page of content, as stored (short pages are zero-padded)
+pub(crate) type PageHash = [u8; 32];
+
+/// A block that may be shared between files
+#[derive(Debug, Clone)]
+struct SharedBlock {
+    /// Content hash, for blocks found through the index; cloned blocks
+    /// have none
+    hash: Option<PageHash>,
+    refs: u32,
+}
+
+/// Hash index and reference counts of deduplicated blocks
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DedupIndex {
+    by_hash: HashMap<PageHash, u64>,
+    blocks: HashMap<u64, SharedBlock>,
+    /// Counts reflect the catalog; false until the first recount
+    counted: bool,
+    dirty: bool,
+}
+
+impl DedupIndex {
+    /// Load a saved index; counts are zero until [`recount`](Self::recount)
+    pub(crate) fn from_bytes(data: &[u8]) -> Result<Self> {
+        let saved: BTreeMap<u64, PageHash> = bincode::deserialize(data)
+            .map_err(|e| CartridgeError::Corruption(format!("dedup index: {e}")))?;
+        let mut index = DedupIndex::default();
+        for (block, hash) in saved {
+            index.by_hash.insert(hash, block);
+            index.blocks.insert(
+                block,
+                SharedBlock {
+                    hash: Some(hash),
+                    refs: 0,
+                },
+            );
+        }
+        Ok(index)
+    }
+
+    /// Serialize the block → hash map (counts are rebuilt on load)
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>> {
+        let saved: BTreeMap<u64, PageHash> = self
+            .blocks
+            .iter()
+            .filter_map(|(&block, shared)| Some((block, shared.hash?)))
+            .collect();
+        bincode::serialize(&saved)
+            .map_err(|e| CartridgeError::Corruption(format!("dedup index: {e}")))
+    }
+
+    /// Set every count from the block lists in the catalog, dropping
+    /// blocks nothing references any more and tracking any block that
+    /// more than one reference shares
+    pub(crate) fn recount<'a>(&mut self, files: impl IntoIterator<Item = &'a FileMetadata>) {
+        let mut counts: HashMap<u64, u32> = HashMap::new();
+        for metadata in files {
+            for block in metadata.data_blocks() {
+                *counts.entry(block).or_default() += 1;
+            }
+        }
+        for shared in self.blocks.values_mut() {
+            shared.refs = 0;
+        }
+        for (block, refs) in counts {
+            match self.blocks.get_mut(&block) {
+                Some(shared) => shared.refs = refs,
+                None if refs > 1 => {
+                    self.blocks.insert(block, SharedBlock { hash: None, refs });
+                }
+                None => {}
+            }
+        }
+        let unshared: Vec<u64> = self
+            .blocks
+            .iter()
+            .filter(|(_, shared)| shared.refs == 0 || (shared.hash.is_none() && shared.refs < 2))
+            .map(|(&block, _)| block)
+            .collect();
+        for block in unshared {
+            self.forget(block);
+        }
+        self.counted = true;
+    }
+
+    /// Whether [`recount`](Self::recount) has run since the index was loaded
+    pub(crate) fn is_counted(&self) -> bool {
+        self.counted
+    }
+
+    /// Changed since the last [`mark_saved`](Self::mark_saved)
+    pub(crate) fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub(crate) fn mark_saved(&mut self) {
+        self.dirty = false;
+    }
+
+    /// Block already holding a page with this hash
+    pub(crate) fn lookup(&self, hash: &PageHash) -> Option<u64> {
+        self.by_hash.get(hash).copied()
+    }
+
+    /// Number of references to `block`, or `None` if it isn't tracked
+    pub(crate) fn refs(&self, block: u64) -> Option<u32> {
+        self.blocks.get(&block).map(|shared| shared.refs)
+    }
+
+    /// Track a newly written block with one reference
+    pub(crate) fn insert(&mut self, block: u64, hash: PageHash) {
+        self.by_hash.insert(hash, block);
+        self.blocks.insert(
+            block,
+            SharedBlock {
+                hash: Some(hash),
+                refs: 1,
+            },
+        );
+        self.dirty = true;
+    }
+
+    /// Add a reference to `block`
+    ///
+    /// An untracked block had a single owner, so it is tracked from here
+    /// on with two references.
+    pub(crate) fn add_ref(&mut self, block: u64) {
+        self.blocks
+            .entry(block)
+            .and_modify(|shared| shared.refs += 1)
+            .or_insert(SharedBlock { hash: None, refs: 2 });
+    }
+
+    /// Drop one reference to `block`; true when the block should be freed
+    ///
+    /// Untracked blocks have a single owner and are always freed. A block
+    /// without a hash stops being tracked once one owner is left.
+    pub(crate) fn release(&mut self, block: u64) -> bool {
+        let Some(shared) = self.blocks.get_mut(&block) else {
+            return true;
+        };
+        shared.refs = shared.refs.saturating_sub(1);
+        match shared.refs {
+            0 => {
+                self.forget(block);
+                true
+            }
+            1 if shared.hash.is_none() => {
+                self.forget(block);
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Number of blocks with more than one reference
+    pub(crate) fn shared_count(&self) -> usize {
+        self.blocks.values().filter(|shared| shared.refs > 1).count()
+    }
+
+    /// Follow a block that vacuum moved from `from` to `to`
+    pub(crate) fn relocate(&mut self, from: u64, to: u64) {
+        if let Some(shared) = self.blocks.remove(&from) {
+            if let Some(hash) = shared.hash {
+                self.by_hash.insert(hash, to);
+                self.dirty = true;
+            }
+            self.blocks.insert(to, shared);
+        }
+    }
+
+    /// Stop tracking `block`; only hashed blocks are saved, so only they
+    /// make the index dirty
+    fn forget(&mut self, block: u64) {
+        if let Some(shared) = self.blocks.remove(&block) {
+            if let Some(hash) = shared.hash {
+                if self.by_hash.get(&hash) == Some(&block) {
+                    self.by_hash.remove(&hash);
+                }
+                self.dirty = true;
+            }
+        }
+    }
+}
+
+/// SHA-256 of a page of content, zero-padded to `page_size` like the
+/// stored page
+pub(crate) fn page_hash(chunk: &[u8], page_size: usize) -> PageHash {
+    let mut hasher = Sha256::new();
+    hasher.update(chunk);
+    if chunk.len() < page_size {
+        hasher.update(vec![0u8; page_size - chunk.len()]);
+    }
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::PAGE_SIZE;
+    use crate::catalog::FileType;
+
+    #[test]
+    fn test_refcounts_and_release() {
+        let mut index = DedupIndex::default();
+        let hash = page_hash(b"shared", PAGE_SIZE);
+        index.insert(10, hash);
+        index.add_ref(10);
+        assert_eq!(index.lookup(&hash), Some(10));
+
+        assert!(!index.release(10));
+        assert_eq!(index.refs(10), Some(1));
+        assert!(index.release(10));
+        assert_eq!(index.lookup(&hash), None);
+
+        // Blocks never indexed are single-owner
+        assert!(index.release(99));
+    }
+
+    #[test]
+    fn test_cloned_blocks_tracked_while_shared() {
+        let mut index = DedupIndex::default();
+        index.add_ref(7);
+        assert_eq!(index.refs(7), Some(2));
+        assert_eq!(index.shared_count(), 1);
+
+        // Back to one owner: untracked, and nothing to save
+        assert!(!index.release(7));
+        assert_eq!(index.refs(7), None);
+        assert!(!index.is_dirty());
+
+        let a = FileMetadata::new(FileType::File, 4096, vec![7]);
+        let b = FileMetadata::new(FileType::File, 8192, vec![7, 8]);
+        index.recount([&a, &b]);
+        assert_eq!(index.refs(7), Some(2));
+        assert_eq!(index.refs(8), None);
+        assert!(DedupIndex::from_bytes(&index.to_bytes().unwrap())
+            .unwrap()
+            .refs(7)
+            .is_none());
+    }
+
+    #[test]
+    fn test_short_page_hashes_like_padded_page() {
+        let mut padded = vec![0u8; PAGE_SIZE];
+        padded[..3].copy_from_slice(b"abc");
+        assert_eq!(page_hash(b"abc", PAGE_SIZE), page_hash(&padded, PAGE_SIZE));
+        assert_ne!(page_hash(b"abc", PAGE_SIZE), page_hash(b"abd", PAGE_SIZE));
+    }
+
+    #[test]
+    fn test_round_trip_and_recount() {
+        let mut index = DedupIndex::default();
+        index.insert(5, page_hash(b"five", PAGE_SIZE));
+        index.insert(6, page_hash(b"six", PAGE_SIZE));
+
+        let mut loaded = DedupIndex::from_bytes(&index.to_bytes().unwrap()).unwrap();
+        let a = FileMetadata::new(FileType::File, 8192, vec![5, 5]);
+        let b = FileMetadata::new(FileType::File, 4096, vec![5]);
+        loaded.recount([&a, &b]);
+
+        assert_eq!(loaded.refs(5), Some(3));
+        // Nothing references 6 any more
+        assert_eq!(loaded.refs(6), None);
+        assert_eq!(loaded.lookup(&page_hash(b"six", PAGE_SIZE)), None);
+    }
+}
@@ -11,15 +11,22 @@
 //! - Format: [nonce: 12 bytes][ciphertext][tag: 16 bytes]
 //! - Master key must be 32 bytes (256 bits)
 //! - Authenticated encryption prevents tampering
+//!
+//! **At-rest encryption**: whole-cartridge encryption derives the master key
+//! from a passphrase with PBKDF2-HMAC-SHA256 and encrypts every page except
+//! the header through [`PageCipher`]. The page ID is bound as associated data,
+//! so pages cannot be swapped around on disk without detection.
 
 #![allow(dead_code)] // Module reserved for future use
 
 use crate::error::{CartridgeError, Result};
+use crate::header::{EncryptionParams, CIPHER_AES_256_GCM, KDF_PBKDF2_SHA256, PAGE_SIZE};
 use aes_gcm::{
-    aead::{Aead, KeyInit, OsRng},
+    aead::{Aead, KeyInit, OsRng, Payload},
     Aes256Gcm, Nonce,
 };
 use rand::RngCore;
+use sha2::{Digest, Sha256};
 
 /// Encryption key (32 bytes for AES-256)
 pub type EncryptionKey = [u8; 32];
@@ -33,6 +40,21 @@ pub const TAG_SIZE: usize = 16;
 /// Overhead added by encryption (nonce + tag)
 pub const ENCRYPTION_OVERHEAD: usize = NONCE_SIZE + TAG_SIZE;
 
+/// Default PBKDF2 iteration count for passphrase-derived keys
+pub const DEFAULT_KDF_ITERATIONS: u32 = 100_000;
+
+/// Iteration count written into new cartridges
+///
+/// Unit tests use a much lower count so that unoptimized builds don't spend
+/// their time in PBKDF2. Opening always uses the count stored in the header.
+#[cfg(not(test))]
+const NEW_KDF_ITERATIONS: u32 = DEFAULT_KDF_ITERATIONS;
+#[cfg(test)]
+const NEW_KDF_ITERATIONS: u32 = 1_000;
+
+/// Domain separator for the key check value stored in the header
+const KEY_CHECK_CONTEXT: &[u8] = b"cartridge-key-check-v1";
+
 /// Encryption configuration
 #[derive(Debug, Clone)]
 pub struct EncryptionConfig {
@@ -92,7 +114,7 @@ pub fn encrypt(data: &[u8], key: &EncryptionKey) -> Result<Vec<u8>> {
     // Encrypt data
     let ciphertext = cipher
         .encrypt(nonce, data)
-        .map_err(|e| CartridgeError::Allocation(format!("Encryption failed: {}", e)))?;
+        .map_err(|e| CartridgeError::EncryptionFailed(e.to_string()))?;
 
     // Build output: nonce + ciphertext (which includes tag)
     let mut result = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
@@ -107,7 +129,7 @@ pub fn encrypt(data: &[u8], key: &EncryptionKey) -> Result<Vec<u8>> {
 /// Expects data in format: [nonce][ciphertext][tag]
 pub fn decrypt(data: &[u8], key: &EncryptionKey) -> Result<Vec<u8>> {
     if data.len() < ENCRYPTION_OVERHEAD {
-        return Err(CartridgeError::Allocation(
+        return Err(CartridgeError::EncryptionFailed(
             "Encrypted data too short".to_string(),
         ));
     }
@@ -123,11 +145,41 @@ pub fn decrypt(data: &[u8], key: &EncryptionKey) -> Result<Vec<u8>> {
     // Decrypt and verify
     let plaintext = cipher
         .decrypt(nonce, ciphertext)
-        .map_err(|e| CartridgeError::Allocation(format!("Decryption failed: {}", e)))?;
+        .map_err(|e| CartridgeError::EncryptionFailed(format!("Decryption failed: {}", e)))?;
 
     Ok(plaintext)
 }
 
+/// Encrypt data using AES-256-GCM under a fresh random nonce, returned
+/// separately
+///
+/// For callers that keep the nonce elsewhere: the output is just
+/// [ciphertext][tag].
+pub fn encrypt_detached(data: &[u8], key: &EncryptionKey) -> Result<([u8; NONCE_SIZE], Vec<u8>)> {
+    let cipher = Aes256Gcm::new(key.into());
+    let mut nonce = [0u8; NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), data)
+        .map_err(|e| CartridgeError::EncryptionFailed(e.to_string()))?;
+    Ok((nonce, ciphertext))
+}
+
+/// Decrypt output of [`encrypt_detached`]
+///
+/// Returns `None` if the tag doesn't verify: the key or nonce is wrong, or
+/// the data was altered.
+pub fn decrypt_detached(
+    data: &[u8],
+    nonce: &[u8; NONCE_SIZE],
+    key: &EncryptionKey,
+) -> Option<Vec<u8>> {
+    Aes256Gcm::new(key.into())
+        .decrypt(Nonce::from_slice(nonce), data)
+        .ok()
+}
+
 /// Encrypt data if encryption is enabled
 pub fn encrypt_if_enabled(data: &[u8], config: &EncryptionConfig) -> Result<(Vec<u8>, bool)> {
     if config.is_enabled() {
@@ -151,6 +203,157 @@ pub fn decrypt_if_encrypted(
     }
 }
 
+/// Generate fresh encryption parameters for a new encrypted cartridge
+///
+/// Returns the header parameters (random salt, KDF settings, key check)
+/// together with the derived master key.
+pub fn new_encryption_params(passphrase: &str) -> Result<(EncryptionParams, EncryptionKey)> {
+    if passphrase.is_empty() {
+        return Err(CartridgeError::InvalidPassphrase);
+    }
+
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+
+    let key = derive_key(passphrase, &salt, NEW_KDF_ITERATIONS);
+    let params = EncryptionParams {
+        cipher: CIPHER_AES_256_GCM,
+        kdf: KDF_PBKDF2_SHA256,
+        kdf_iterations: NEW_KDF_ITERATIONS,
+        salt,
+        key_check: key_check(&key),
+    };
+
+    Ok((params, key))
+}
+
+/// Derive the master key for an existing cartridge and verify it
+///
+/// Fails with [`CartridgeError::InvalidPassphrase`] if the passphrase does
+/// not match the key check value stored in the header.
+pub fn unlock(passphrase: &str, params: &EncryptionParams) -> Result<EncryptionKey> {
+    if params.cipher != CIPHER_AES_256_GCM || params.kdf != KDF_PBKDF2_SHA256 {
+        return Err(CartridgeError::Corruption(format!(
+            "Unsupported encryption parameters (cipher {}, kdf {})",
+            params.cipher, params.kdf
+        )));
+    }
+
+    let key = derive_key(passphrase, &params.salt, params.kdf_iterations);
+    if key_check(&key) != params.key_check {
+        return Err(CartridgeError::InvalidPassphrase);
+    }
+
+    Ok(key)
+}
+
+/// Derive a 256-bit key from a passphrase with PBKDF2-HMAC-SHA256
+pub fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> EncryptionKey {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+/// Compute the key check value (truncated SHA-256 over a fixed context)
+fn key_check(key: &EncryptionKey) -> [u8; 16] {
+    let mut hasher = Sha256::new();
+    hasher.update(KEY_CHECK_CONTEXT);
+    hasher.update(key);
+    let digest = hasher.finalize();
+
+    let mut check = [0u8; 16];
+    check.copy_from_slice(&digest[..16]);
+    check
+}
+
+/// Page-level cipher for at-rest encryption
+///
+/// Encrypts a page into a slot `ENCRYPTION_OVERHEAD` bytes longer:
+/// `[nonce][ciphertext][tag]`, with the page ID as associated data.
+#[derive(Clone)]
+pub struct PageCipher {
+    cipher: Aes256Gcm,
+}
+
+impl PageCipher {
+    /// Size of an encrypted 4KB page on disk
+    pub const SLOT_SIZE: usize = PAGE_SIZE + ENCRYPTION_OVERHEAD;
+
+    /// Create a page cipher from a master key
+    pub fn new(key: &EncryptionKey) -> Self {
+        PageCipher {
+            cipher: Aes256Gcm::new(key.into()),
+        }
+    }
+
+    /// Encrypt one page
+    pub fn encrypt_page(&self, page_id: u64, page: &[u8]) -> Result<Vec<u8>> {
+        self.seal(&page_id.to_le_bytes(), page)
+    }
+
+    /// Decrypt one page, verifying its authentication tag
+    pub fn decrypt_page(&self, page_id: u64, slot: &[u8]) -> Result<Vec<u8>> {
+        if slot.len() < ENCRYPTION_OVERHEAD {
+            return Err(CartridgeError::Corruption(format!(
+                "Encrypted page {} too short",
+                page_id
+            )));
+        }
+        self.open(&page_id.to_le_bytes(), slot).map_err(|_| {
+            CartridgeError::Corruption(format!("Page {} failed authentication", page_id))
+        })
+    }
+
+    /// Encrypt data of any length into `[nonce][ciphertext][tag]`, bound to
+    /// `context` as associated data
+    pub(crate) fn seal(&self, context: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce_bytes = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce_bytes);
+
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce_bytes),
+                Payload {
+                    msg: data,
+                    aad: context,
+                },
+            )
+            .map_err(|e| CartridgeError::EncryptionFailed(e.to_string()))?;
+
+        let mut sealed = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
+        sealed.extend_from_slice(&nonce_bytes);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypt output of [`seal`](Self::seal) under the same `context`
+    pub(crate) fn open(&self, context: &[u8], sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < ENCRYPTION_OVERHEAD {
+            return Err(CartridgeError::Corruption(
+                "Encrypted data too short".to_string(),
+            ));
+        }
+        self.cipher
+            .decrypt(
+                Nonce::from_slice(&sealed[..NONCE_SIZE]),
+                Payload {
+                    msg: &sealed[NONCE_SIZE..],
+                    aad: context,
+                },
+            )
+            .map_err(|_| {
+                CartridgeError::Corruption("Encrypted data failed authentication".to_string())
+            })
+    }
+}
+
+impl std::fmt::Debug for PageCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PageCipher").finish_non_exhaustive()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -202,7 +405,7 @@ mod tests {
         let result = decrypt(&ciphertext, &key2);
 
         // Decryption with wrong key should fail
-        assert!(result.is_err());
+        assert!(matches!(result, Err(CartridgeError::EncryptionFailed(_))));
     }
 
     #[test]
@@ -222,6 +425,20 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_detached_nonce_round_trip() {
+        let key = EncryptionConfig::generate_key();
+        let (nonce, ciphertext) = encrypt_detached(b"per-file secret", &key).unwrap();
+        assert_eq!(ciphertext.len(), b"per-file secret".len() + TAG_SIZE);
+        assert_eq!(decrypt_detached(&ciphertext, &nonce, &key).unwrap(), b"per-file secret");
+
+        let other = EncryptionConfig::generate_key();
+        assert!(decrypt_detached(&ciphertext, &nonce, &other).is_none());
+        let mut wrong_nonce = nonce;
+        wrong_nonce[0] ^= 1;
+        assert!(decrypt_detached(&ciphertext, &wrong_nonce, &key).is_none());
+    }
+
     #[test]
     fn test_encryption_config() {
         let key = EncryptionConfig::generate_key();
@@ -310,4 +527,28 @@ mod tests {
         assert_eq!(decrypt(&ciphertext1, &key).unwrap(), plaintext);
         assert_eq!(decrypt(&ciphertext2, &key).unwrap(), plaintext);
     }
+
+    #[test]
+    fn test_unlock_with_correct_and_wrong_passphrase() {
+        let (params, key) = new_encryption_params("correct horse").unwrap();
+
+        assert_eq!(unlock("correct horse", &params).unwrap(), key);
+        assert!(matches!(
+            unlock("battery staple", &params),
+            Err(CartridgeError::InvalidPassphrase)
+        ));
+    }
+
+    #[test]
+    fn test_page_cipher_binds_page_id() {
+        let cipher = PageCipher::new(&EncryptionConfig::generate_key());
+        let page = vec![0xABu8; PAGE_SIZE];
+
+        let slot = cipher.encrypt_page(5, &page).unwrap();
+        assert_eq!(slot.len(), PageCipher::SLOT_SIZE);
+        assert_eq!(cipher.decrypt_page(5, &slot).unwrap(), page);
+
+        // The same ciphertext must not decrypt as a different page
+        assert!(cipher.decrypt_page(6, &slot).is_err());
+    }
 }
@@ -10,11 +10,76 @@
 //!   + metadata         →           →    + access control preserved
 //! ```
 
-use super::cartridge::Cartridge;
+use super::cartridge::{Cartridge, VacuumReport};
 use crate::error::{CartridgeError, Result};
-use engram_rs::{ArchiveWriter, CompressionMethod};
+use engram_rs::ArchiveWriter;
 use serde_json::json;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+pub use ed25519_dalek::SigningKey;
+pub use engram_rs::CompressionMethod;
+
+/// Engram path under which bundled snapshots are stored
+const SNAPSHOT_PREFIX: &str = ".cartridge/snapshots";
+
+/// Options for [`Cartridge::freeze`]
+///
+/// Name, version, author and description default to the values in the
+/// container manifest when left as `None`.
+#[derive(Debug, Clone)]
+pub struct FreezeOptions {
+    /// Engram name (default: manifest slug)
+    pub name: Option<String>,
+    /// Engram version (default: manifest version)
+    pub version: Option<String>,
+    /// Author (default: manifest author)
+    pub author: Option<String>,
+    /// Description (default: manifest description)
+    pub description: Option<String>,
+    /// Compression applied to file contents (default: Zstd)
+    pub compression: CompressionMethod,
+    /// Vacuum before freezing so the source file is compact too
+    pub vacuum: bool,
+    /// Snapshot directory to bundle under `.cartridge/snapshots/`;
+    /// `None` leaves snapshots out
+    pub snapshot_dir: Option<PathBuf>,
+    /// Ed25519 key to sign the archive with; `None` leaves it unsigned
+    pub signing_key: Option<SigningKey>,
+}
+
+impl Default for FreezeOptions {
+    fn default() -> Self {
+        FreezeOptions {
+            name: None,
+            version: None,
+            author: None,
+            description: None,
+            compression: CompressionMethod::Zstd,
+            vacuum: false,
+            snapshot_dir: None,
+            signing_key: None,
+        }
+    }
+}
+
+/// Summary of a [`Cartridge::freeze`] call
+#[derive(Debug, Clone)]
+pub struct FreezeReport {
+    /// Files written into the engram
+    pub files_frozen: usize,
+    /// Uncompressed content bytes
+    pub bytes_in: u64,
+    /// Size of the finished engram on disk
+    pub archive_bytes: u64,
+    /// Snapshots bundled into the engram
+    pub snapshots_included: usize,
+    /// Capabilities recorded in the engram manifest
+    pub capabilities: Vec<String>,
+    /// Public half of the signing key, if the archive was signed
+    pub public_key: Option<[u8; 32]>,
+    /// Result of the pre-freeze vacuum, if one was requested
+    pub vacuum: Option<VacuumReport>,
+}
 
 /// Engram freezer for cartridges
 pub struct EngramFreezer {
@@ -32,6 +97,9 @@ pub struct EngramFreezer {
 
     /// Optional description
     description: Option<String>,
+
+    /// Optional Ed25519 signing key
+    signing_key: Option<SigningKey>,
 }
 
 impl EngramFreezer {
@@ -49,9 +117,16 @@ impl EngramFreezer {
             version,
             author,
             description,
+            signing_key: None,
         }
     }
 
+    /// Sign archives produced by this freezer with `key`
+    pub fn with_signing_key(mut self, key: SigningKey) -> Self {
+        self.signing_key = Some(key);
+        self
+    }
+
     /// Create with default settings (Zstd compression)
     pub fn new_default(name: String, version: String, author: String) -> Self {
         Self::new(name, version, author, None, CompressionMethod::Zstd)
@@ -62,8 +137,22 @@ impl EngramFreezer {
     /// Creates an immutable, compressed archive from the cartridge.
     /// Includes IAM policy in the manifest as capabilities.
     pub fn freeze(&self, cartridge: &mut Cartridge, output_path: &Path) -> Result<()> {
+        self.write_archive(cartridge, output_path, None)?;
+        Ok(())
+    }
+
+    /// Write the engram, bundling snapshots from `snapshot_dir` if given
+    fn write_archive(
+        &self,
+        cartridge: &Cartridge,
+        output_path: &Path,
+        snapshot_dir: Option<&Path>,
+    ) -> Result<FreezeReport> {
         let mut writer = ArchiveWriter::create(output_path)
             .map_err(|e| CartridgeError::Allocation(format!("Failed to create engram: {}", e)))?;
+        if let Some(key) = &self.signing_key {
+            writer = writer.with_signing_key(key);
+        }
 
         // Get all files from catalog
         let files = list_all_files_recursive(cartridge)?;
@@ -82,8 +171,18 @@ impl EngramFreezer {
             }
         }
 
-        // Extract IAM capabilities from policy (if any)
-        let capabilities = cartridge.extract_iam_capabilities()?;
+        // Capabilities declared in the container manifest, then those
+        // derived from the IAM policy (if any)
+        let container_manifest = cartridge.read_manifest().ok();
+        let mut capabilities = container_manifest
+            .as_ref()
+            .map(|m| m.capabilities.clone())
+            .unwrap_or_default();
+        for capability in cartridge.extract_iam_capabilities()? {
+            if !capabilities.contains(&capability) {
+                capabilities.push(capability);
+            }
+        }
 
         // Create engram manifest
         let manifest = json!({
@@ -99,6 +198,7 @@ impl EngramFreezer {
             "metadata": {
                 "compression": format!("{:?}", self.compression),
                 "source": "cartridge",
+                "title": container_manifest.as_ref().map(|m| m.title.clone()),
             }
         });
 
@@ -121,8 +221,10 @@ impl EngramFreezer {
         }
 
         // Add each file to the engram with specified compression
-        for file_path in files {
-            let content = cartridge.read_file(&file_path)?;
+        let mut bytes_in = 0u64;
+        for file_path in &files {
+            let content = cartridge.read_file(file_path)?;
+            bytes_in += content.len() as u64;
             // Strip leading slash for engram paths
             let engram_path = file_path.trim_start_matches('/');
             writer
@@ -132,12 +234,72 @@ impl EngramFreezer {
                 })?;
         }
 
+        let snapshots_included = match snapshot_dir {
+            Some(dir) => self.add_snapshots(&mut writer, dir)?,
+            None => 0,
+        };
+
         // Finalize the archive
         writer
             .finalize()
             .map_err(|e| CartridgeError::Allocation(format!("Failed to finalize engram: {}", e)))?;
 
-        Ok(())
+        Ok(FreezeReport {
+            files_frozen: files.len(),
+            bytes_in,
+            archive_bytes: std::fs::metadata(output_path)?.len(),
+            snapshots_included,
+            capabilities,
+            public_key: self
+                .signing_key
+                .as_ref()
+                .map(|key| key.verifying_key().to_bytes()),
+            vacuum: None,
+        })
+    }
+
+    /// Copy every `snapshot_<id>` directory under `dir` into the engram
+    fn add_snapshots(&self, writer: &mut ArchiveWriter, dir: &Path) -> Result<usize> {
+        let mut snapshots: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.is_dir()
+                    && path
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| n.starts_with("snapshot_"))
+            })
+            .collect();
+        snapshots.sort();
+
+        for snapshot in &snapshots {
+            let snapshot_name = snapshot.file_name().unwrap().to_string_lossy();
+            let mut files: Vec<PathBuf> = std::fs::read_dir(snapshot)?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.is_file())
+                .collect();
+            files.sort();
+
+            for file in files {
+                let engram_path = format!(
+                    "{}/{}/{}",
+                    SNAPSHOT_PREFIX,
+                    snapshot_name,
+                    file.file_name().unwrap().to_string_lossy()
+                );
+                let content = std::fs::read(&file)?;
+                writer
+                    .add_file_with_compression(&engram_path, &content, self.compression)
+                    .map_err(|e| {
+                        CartridgeError::Allocation(format!(
+                            "Failed to add snapshot file {}: {}",
+                            engram_path, e
+                        ))
+                    })?;
+            }
+        }
+
+        Ok(snapshots.len())
     }
 
     /// Freeze with vacuum (removes deleted files, optimizes storage)
@@ -154,8 +316,8 @@ impl EngramFreezer {
 
 /// List all files in a cartridge recursively
 fn list_all_files_recursive(cartridge: &Cartridge) -> Result<Vec<String>> {
-    // Get all entries from the catalog
-    let all_entries = cartridge.list_dir("/")?;
+    // Get all entries from the catalog, with or without a leading slash
+    let all_entries = cartridge.list_dir("")?;
 
     // Filter for files only (exclude directories)
     let mut files = Vec::new();
@@ -176,6 +338,53 @@ impl Cartridge {
     pub fn list_all_files(&self) -> Result<Vec<String>> {
         list_all_files_recursive(self)
     }
+
+    /// Freeze this cartridge into an engram archive at `output_path`
+    ///
+    /// Flushes pending writes, vacuums if asked to, then writes every file
+    /// (including `.cartridge/manifest.json`) along with the IAM policy and
+    /// the capabilities derived from it. The archive is signed when
+    /// [`FreezeOptions::signing_key`] is set.
+    pub fn freeze(&mut self, output_path: &Path, options: &FreezeOptions) -> Result<FreezeReport> {
+        self.flush()?;
+        let vacuum = if options.vacuum {
+            Some(self.vacuum()?)
+        } else {
+            None
+        };
+
+        let manifest = self.read_manifest().ok();
+        let name = options
+            .name
+            .clone()
+            .or_else(|| manifest.as_ref().map(|m| m.slug.as_str().to_string()))
+            .unwrap_or_else(|| "cartridge".to_string());
+        let version = options
+            .version
+            .clone()
+            .or_else(|| manifest.as_ref().map(|m| m.version.to_string()))
+            .unwrap_or_else(|| "0.1.0".to_string());
+        let author = options
+            .author
+            .clone()
+            .or_else(|| manifest.as_ref().and_then(|m| m.author.clone()))
+            .unwrap_or_else(|| "unknown".to_string());
+        let description = options
+            .description
+            .clone()
+            .or_else(|| manifest.as_ref().and_then(|m| m.description.clone()));
+
+        let mut freezer =
+            EngramFreezer::new(name, version, author, description, options.compression);
+        if let Some(key) = &options.signing_key {
+            freezer = freezer.with_signing_key(key.clone());
+        }
+
+        let mut report =
+            freezer.write_archive(self, output_path, options.snapshot_dir.as_deref())?;
+        report.vacuum = vacuum;
+        Ok(report)
+    }
 }
 
 #[cfg(test)]
@@ -295,6 +504,52 @@ mod tests {
         assert!(lz4_size < 10000, "LZ4 should compress");
     }
 
+    #[test]
+    fn test_freeze_signed_with_options() {
+        use crate::iam::{Action, Effect, Policy, Statement};
+        use engram_rs::ArchiveReader;
+
+        let temp_dir = TempDir::new().unwrap();
+        let engram_path = temp_dir.path().join("signed.eng");
+
+        let mut cart = Cartridge::new(1000);
+        cart.create_file("/notes.txt", b"signed content").unwrap();
+        cart.set_policy(Policy {
+            version: "2012-10-17".to_string(),
+            statement: vec![Statement::new(
+                Effect::Allow,
+                vec![Action::Read],
+                vec!["**".to_string()],
+            )],
+        });
+
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let options = FreezeOptions {
+            name: Some("signed".to_string()),
+            compression: CompressionMethod::Lz4,
+            signing_key: Some(key.clone()),
+            ..Default::default()
+        };
+        let report = cart.freeze(&engram_path, &options).unwrap();
+        assert_eq!(report.files_frozen, 1);
+        assert_eq!(report.bytes_in, 14);
+        assert_eq!(report.capabilities, vec!["read:**".to_string()]);
+        assert_eq!(report.public_key, Some(key.verifying_key().to_bytes()));
+        assert!(report.vacuum.is_none());
+
+        let mut reader = ArchiveReader::open(&engram_path).unwrap();
+        reader.initialize().unwrap();
+        assert!(reader.is_signed());
+        assert!(reader
+            .verify_archive_signature(&key.verifying_key())
+            .unwrap());
+        let other = SigningKey::from_bytes(&[8u8; 32]);
+        assert!(!reader
+            .verify_archive_signature(&other.verifying_key())
+            .unwrap());
+        assert_eq!(reader.read_file("notes.txt").unwrap(), b"signed content");
+    }
+
     #[test]
     fn test_freeze_with_iam_policy() {
         use crate::iam::{Action, Effect, Policy, Statement};
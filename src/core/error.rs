@@ -1,3 +1,6 @@
+use crate::find::CaseSensitivity;
+use crate::iam::Action;
+use crate::validation::{PathError, SlugError};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -8,18 +11,27 @@ pub enum CartridgeError {
     #[error("Unsupported format version: {major}.{minor}")]
     UnsupportedVersion { major: u16, minor: u16 },
 
+    #[error("Format {major}.{minor} cartridge uses unsupported required features: {flags:#x}")]
+    UnsupportedFeatures { major: u16, minor: u16, flags: u64 },
+
     #[error("Invalid block size: {0}")]
     InvalidBlockSize(u32),
 
     #[error("Invalid page type: {0}")]
     InvalidPageType(u8),
 
-    #[error("Page checksum verification failed")]
-    ChecksumMismatch,
+    #[error(
+        "Page checksum verification failed: page {page}{}",
+        path.as_ref().map(|p| format!(" ({})", p)).unwrap_or_default()
+    )]
+    ChecksumMismatch { page: u64, path: Option<String> },
 
     #[error("Out of space: no free blocks available")]
     OutOfSpace,
 
+    #[error("Not enough disk space to grow: {needed} bytes needed, {available} available")]
+    InsufficientDiskSpace { needed: u64, available: u64 },
+
     #[error("Invalid block ID: {0}")]
     InvalidBlockId(u64),
 
@@ -32,17 +44,82 @@ pub enum CartridgeError {
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
+    #[error("Invalid JSON in {path}: {source}")]
+    InvalidJson {
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("Invalid UTF-8 in {path} at byte {valid_up_to}")]
+    InvalidUtf8 { path: String, valid_up_to: usize },
+
     #[error("VFS registration failed: {0}")]
     VFSRegistrationFailed(i32),
 
     #[error("Allocation error: {0}")]
     Allocation(String),
 
+    #[error("Not found: {path}")]
+    NotFound { path: String },
+
+    #[error("Already exists: {path}")]
+    AlreadyExists { path: String },
+
+    #[error("Not a file: {path}")]
+    NotAFile { path: String },
+
+    #[error("Not a directory: {path}")]
+    NotADirectory { path: String },
+
+    #[error("Access denied: {action:?} on {path}")]
+    AccessDenied { action: Action, path: String },
+
+    #[error("{path} is reserved for cartridge metadata; change it through Cartridge::system()")]
+    ProtectedPath { path: String },
+
+    #[error("Snapshot not found: {id}")]
+    SnapshotNotFound { id: u64 },
+
+    #[error("Snapshot {id} has no saved catalog: it was taken before snapshots recorded one")]
+    SnapshotCatalogMissing { id: u64 },
+
+    #[error("Snapshot {id} can't be restored: its parent snapshot {missing} is missing")]
+    SnapshotChainBroken { id: u64, missing: u64 },
+
+    #[error("Snapshot {id} can't be deleted: snapshots {dependents:?} are based on it")]
+    SnapshotHasDependents { id: u64, dependents: Vec<u64> },
+
+    #[error(
+        "Torn write: header, catalog and allocator are from different flushes \
+         (generations {header_gen}, {catalog_gen}, {allocator_gen})"
+    )]
+    TornWrite {
+        header_gen: u64,
+        catalog_gen: u64,
+        allocator_gen: u64,
+    },
+
+    #[error(
+        "Cartridge was changed on disk (now generation {generation}) while this handle \
+         has unflushed changes"
+    )]
+    Conflict { generation: u64 },
+
+    #[error(
+        "Can't shrink to {new_total_blocks} blocks: {} blocks past that are in use",
+        blocks.len()
+    )]
+    BlocksInUse {
+        new_total_blocks: u64,
+        blocks: Vec<u64>,
+    },
+
     #[error("Fragmentation score calculation failed")]
     FragmentationError,
 
     #[error("Invalid container slug: {0} (must be kebab-case: lowercase, hyphens, no spaces)")]
-    InvalidContainerSlug(String),
+    InvalidContainerSlug(SlugError),
 
     #[error("Invalid version: {0} (must be valid semver: e.g., 1.0.0)")]
     InvalidVersion(String),
@@ -50,6 +127,9 @@ pub enum CartridgeError {
     #[error("Invalid path: path does not contain a valid file name")]
     InvalidPath,
 
+    #[error("Invalid path '{path}': {reason}")]
+    InvalidFilePath { path: String, reason: PathError },
+
     #[error("Manifest not found: container does not contain .cartridge/manifest.json")]
     ManifestNotFound,
 
@@ -58,6 +138,73 @@ pub enum CartridgeError {
 
     #[error("Data corruption detected: {0}")]
     Corruption(String),
+
+    #[error("Cartridge is encrypted: open it with a passphrase")]
+    EncryptionRequired,
+
+    #[error("Invalid passphrase: the key does not match this cartridge")]
+    InvalidPassphrase,
+
+    #[error("Cartridge is not encrypted")]
+    NotEncrypted,
+
+    #[error("File is encrypted with its own key: {path}")]
+    EncryptedFile { path: String },
+
+    #[error("File is not encrypted with its own key: {path}")]
+    FileNotEncrypted { path: String },
+
+    #[error("Failed to decrypt {path}: wrong key or tampered content")]
+    DecryptionFailed { path: String },
+
+    #[error("Encryption failed: {0}")]
+    EncryptionFailed(String),
+
+    #[error("Invalid user metadata: {0}")]
+    InvalidMetadata(String),
+
+    #[error("Invalid search pattern: {0}")]
+    InvalidPattern(String),
+
+    #[error("Dangling symlink: {0} (target does not exist)")]
+    DanglingSymlink(String),
+
+    #[error("Too many levels of symbolic links: {0}")]
+    SymlinkLoop(String),
+
+    #[error("Quota exceeded for '{prefix}': {attempted} bytes would exceed the {limit} byte limit")]
+    QuotaExceeded {
+        prefix: String,
+        limit: u64,
+        attempted: u64,
+    },
+
+    #[error("Cartridge is open read-only")]
+    ReadOnly,
+
+    #[error("Cartridge paths are {found:?} case, but it was opened expecting {expected:?}")]
+    CaseSensitivityMismatch {
+        expected: CaseSensitivity,
+        found: CaseSensitivity,
+    },
+
+    #[error("Cartridge slug is '{found}', but it was opened expecting '{expected}'")]
+    SlugMismatch { expected: String, found: String },
+
+    #[error(
+        "Cartridge is locked by another handle: {path}{}",
+        holder_pid.map(|pid| format!(" (pid {})", pid)).unwrap_or_default()
+    )]
+    Locked {
+        path: String,
+        holder_pid: Option<u32>,
+    },
+
+    #[error("Invalid zip archive: {0}")]
+    InvalidZip(String),
+
+    #[error("SQLite error: {0}")]
+    Sqlite(String),
 }
 
 pub type Result<T> = std::result::Result<T, CartridgeError>;
@@ -0,0 +1,142 @@
+//! Progress and event hooks for long operations
+//!
+//! A cartridge can carry one [`EventListener`], installed with
+//! [`Cartridge::set_event_listener`]. Growth, large writes, flushes and
+//! vacuum steps report through it, so a UI can show progress on a
+//! multi-gigabyte import instead of appearing hung. With no listener
+//! installed, emitting an event is a single `Option` check.
+//!
+//! ## Reentrancy
+//!
+//! Listeners run on the thread doing the work, after the cartridge has
+//! released its page cache and file locks, so they may block or log freely.
+//! They are called from inside a `&mut self` method, though, so a listener
+//! must not call back into the same cartridge (through a `SharedCartridge`
+//! handle, say); that would deadlock on the outer lock.
+
+use crate::cartridge::{Cartridge, VacuumProgress};
+
+/// Writes at least this large report [`CartridgeEvent::WriteProgress`]
+pub const WRITE_PROGRESS_THRESHOLD: usize = 1024 * 1024;
+
+/// Blocks written between two [`CartridgeEvent::WriteProgress`] reports
+pub const WRITE_PROGRESS_BLOCKS: usize = 256;
+
+/// Something a long-running cartridge operation wants to report
+#[derive(Debug, Clone)]
+pub enum CartridgeEvent {
+    /// The container is about to be extended
+    GrowStarted { old_blocks: u64, new_blocks: u64 },
+    /// The container was extended
+    GrowFinished { old_blocks: u64, new_blocks: u64 },
+    /// A large write copied another batch of blocks into the page cache
+    WriteProgress {
+        path: String,
+        written: u64,
+        total: u64,
+    },
+    /// A flush is about to write `dirty_pages` content pages to disk
+    FlushStarted { dirty_pages: usize },
+    /// A flush reached disk
+    FlushFinished { dirty_pages: usize },
+    /// One incremental vacuum step completed
+    VacuumProgress(VacuumProgress),
+}
+
+/// Callback receiving [`CartridgeEvent`]s
+pub type EventListener = Box<dyn Fn(CartridgeEvent) + Send + Sync>;
+
+impl Cartridge {
+    /// Install `listener`, replacing any previous one
+    ///
+    /// See the [module docs](self) for when listeners run and what they
+    /// must not do.
+    pub fn set_event_listener(&mut self, listener: EventListener) {
+        *self.event_listener_mut() = Some(listener);
+    }
+
+    /// Remove the event listener, if any
+    pub fn clear_event_listener(&mut self) {
+        *self.event_listener_mut() = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::PAGE_SIZE;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    fn record(cart: &mut Cartridge) -> Arc<Mutex<Vec<CartridgeEvent>>> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        cart.set_event_listener(Box::new(move |event| sink.lock().push(event)));
+        events
+    }
+
+    #[test]
+    fn test_grow_and_write_progress_events() {
+        let mut cart = Cartridge::new(3);
+        let events = record(&mut cart);
+
+        let content = vec![7u8; WRITE_PROGRESS_THRESHOLD * 2];
+        cart.create_file("big.bin", &content).unwrap();
+
+        let events = events.lock();
+        assert!(events.iter().any(|e| matches!(
+            e,
+            CartridgeEvent::GrowFinished { old_blocks: 3, .. }
+        )));
+
+        let progress: Vec<(u64, u64)> = events
+            .iter()
+            .filter_map(|e| match e {
+                CartridgeEvent::WriteProgress {
+                    path,
+                    written,
+                    total,
+                } if path == "/big.bin" => Some((*written, *total)),
+                _ => None,
+            })
+            .collect();
+        assert!(progress.len() > 1);
+        assert!(progress.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(*progress.last().unwrap(), (content.len() as u64, content.len() as u64));
+    }
+
+    #[test]
+    fn test_small_writes_are_quiet() {
+        let mut cart = Cartridge::new(100);
+        let events = record(&mut cart);
+
+        cart.create_file("small.txt", b"hello").unwrap();
+        assert!(events.lock().is_empty());
+
+        cart.clear_event_listener();
+        cart.create_file("big.bin", &vec![0u8; WRITE_PROGRESS_THRESHOLD]).unwrap();
+        assert!(events.lock().is_empty());
+    }
+
+    #[test]
+    fn test_flush_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.cart");
+        let mut cart = Cartridge::create_at(&path, "events", "Events").unwrap();
+        cart.create_file("a.dat", &[1u8; PAGE_SIZE]).unwrap();
+        let events = record(&mut cart);
+
+        cart.flush().unwrap();
+
+        let events = events.lock();
+        let flushed: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                CartridgeEvent::FlushStarted { dirty_pages } => Some(("started", *dirty_pages)),
+                CartridgeEvent::FlushFinished { dirty_pages } => Some(("finished", *dirty_pages)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(flushed, vec![("started", 1), ("finished", 1)]);
+    }
+}
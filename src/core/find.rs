@@ -0,0 +1,182 @@
+//! Pattern search over the catalog
+//!
+//! [`Cartridge::find`] takes a glob in the same syntax as IAM resources
+//! (`*` for one segment or part of one, `**` for any depth). The literal
+//! directory prefix before the first wildcard bounds the catalog scan, so
+//! `docs/**/*.md` only visits paths under `docs/`.
+//! [`Cartridge::find_regex`] matches whole paths against a regular
+//! expression and always scans the full catalog.
+//!
+//! Paths under `.cartridge/` are never returned.
+
+use crate::cartridge::Cartridge;
+use crate::catalog::FileMetadata;
+use crate::error::{CartridgeError, Result};
+use crate::iam::PatternMatcher;
+use crate::transfer::is_internal;
+use regex::RegexBuilder;
+
+/// How patterns compare letter case
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CaseSensitivity {
+    /// `Docs/*.MD` only matches paths spelled exactly that way
+    #[default]
+    Exact,
+    /// ASCII and Unicode letters match regardless of case
+    Insensitive,
+}
+
+impl Cartridge {
+    /// Find paths matching a glob pattern
+    ///
+    /// Returns matching paths with their metadata in path order. With
+    /// [`CaseSensitivity::Insensitive`] the literal prefix can't bound the
+    /// scan, so the whole catalog is visited.
+    pub fn find(
+        &self,
+        pattern: &str,
+        case: CaseSensitivity,
+    ) -> Result<Vec<(String, FileMetadata)>> {
+        let prefix = match case {
+            CaseSensitivity::Exact => literal_prefix(pattern),
+            CaseSensitivity::Insensitive => "",
+        };
+        let pattern = match case {
+            CaseSensitivity::Exact => pattern.to_string(),
+            CaseSensitivity::Insensitive => pattern.to_lowercase(),
+        };
+
+        let listing = self.list_dir_with_metadata(prefix)?;
+        Ok(listing
+            .into_iter()
+            .filter(|(path, _)| !is_internal(path))
+            .filter(|(path, _)| match case {
+                CaseSensitivity::Exact => PatternMatcher::matches(&pattern, path),
+                CaseSensitivity::Insensitive => {
+                    PatternMatcher::matches(&pattern, &path.to_lowercase())
+                }
+            })
+            .collect())
+    }
+
+    /// Find paths matching a regular expression
+    ///
+    /// The expression is searched for anywhere in the path; anchor it with
+    /// `^` and `$` to match whole paths. Paths are matched without their
+    /// leading `/`, so `^docs/` finds everything under `/docs`.
+    pub fn find_regex(
+        &self,
+        pattern: &str,
+        case: CaseSensitivity,
+    ) -> Result<Vec<(String, FileMetadata)>> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(case == CaseSensitivity::Insensitive)
+            .build()
+            .map_err(|e| CartridgeError::InvalidPattern(e.to_string()))?;
+
+        let listing = self.list_dir_with_metadata("")?;
+        Ok(listing
+            .into_iter()
+            .filter(|(path, _)| !is_internal(path) && regex.is_match(&path[1..]))
+            .collect())
+    }
+}
+
+/// The directory part of a glob before its first wildcard
+///
+/// `"docs/api/*.md"` → `"docs/api/"`, `"**/*.json"` → `""`. Always empty or
+/// ending in `/`, so it can be handed to `list_dir_with_metadata` as is.
+fn literal_prefix(pattern: &str) -> &str {
+    let literal = &pattern[..pattern.find('*').unwrap_or(pattern.len())];
+    match literal.rfind('/') {
+        Some(slash) => &pattern[..=slash],
+        None => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Cartridge {
+        let mut cart = Cartridge::new(100);
+        for path in [
+            "config.json",
+            "docs/readme.md",
+            "docs/api/Index.MD",
+            "docs/api/data.json",
+            "src/main.rs",
+        ] {
+            cart.create_file(path, b"x").unwrap();
+        }
+        cart.system().write(".cartridge/manifest.json", b"x").unwrap();
+        cart
+    }
+
+    fn paths(found: Vec<(String, FileMetadata)>) -> Vec<String> {
+        found.into_iter().map(|(path, _)| path).collect()
+    }
+
+    #[test]
+    fn test_literal_prefix() {
+        assert_eq!(literal_prefix("docs/api/*.md"), "docs/api/");
+        assert_eq!(literal_prefix("docs/**"), "docs/");
+        assert_eq!(literal_prefix("**/*.json"), "");
+        assert_eq!(literal_prefix("*.rs"), "");
+        assert_eq!(literal_prefix("docs/readme.md"), "docs/");
+    }
+
+    #[test]
+    fn test_find_glob() {
+        let cart = sample();
+
+        assert_eq!(
+            paths(cart.find("**/*.json", CaseSensitivity::Exact).unwrap()),
+            ["/config.json", "/docs/api/data.json"]
+        );
+        assert_eq!(
+            paths(cart.find("docs/*.md", CaseSensitivity::Exact).unwrap()),
+            ["/docs/readme.md"]
+        );
+        assert_eq!(
+            paths(cart.find("docs/**", CaseSensitivity::Exact).unwrap()).len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_find_case_sensitivity() {
+        let cart = sample();
+
+        assert_eq!(
+            paths(cart.find("docs/**/*.md", CaseSensitivity::Exact).unwrap()),
+            ["/docs/readme.md"]
+        );
+        assert_eq!(
+            paths(cart.find("DOCS/**/*.md", CaseSensitivity::Insensitive).unwrap()),
+            ["/docs/api/Index.MD", "/docs/readme.md"]
+        );
+    }
+
+    #[test]
+    fn test_find_regex() {
+        let cart = sample();
+
+        assert_eq!(
+            paths(cart.find_regex(r"\.(rs|md)$", CaseSensitivity::Exact).unwrap()),
+            ["/docs/readme.md", "/src/main.rs"]
+        );
+        assert_eq!(
+            paths(cart.find_regex(r"manifest", CaseSensitivity::Exact).unwrap()),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            paths(cart.find_regex(r"^docs/[a-z]+\.md$", CaseSensitivity::Exact).unwrap()),
+            ["/docs/readme.md"]
+        );
+        assert!(matches!(
+            cart.find_regex("(unclosed", CaseSensitivity::Exact),
+            Err(CartridgeError::InvalidPattern(_))
+        ));
+    }
+}
@@ -4,9 +4,44 @@ use std::mem::size_of;
 
 pub const MAGIC: [u8; 8] = *b"CART\x00\x01\x00\x00";
 pub const VERSION_MAJOR: u16 = 1;
-pub const VERSION_MINOR: u16 = 0;
+pub const VERSION_MINOR: u16 = 1;
+/// Default page size, and the size of the serialized header
 pub const PAGE_SIZE: usize = 4096;
 
+/// Page size a cartridge is created with, stored in [`Header::block_size`]
+///
+/// Fixed for the cartridge's life: every page in one file has the same size.
+/// Bigger pages mean fewer blocks to track and longer sequential runs for
+/// large files, at the cost of more slack at the end of small ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PageSize {
+    /// 4KB pages (default)
+    #[default]
+    Kb4,
+    /// 16KB pages
+    Kb16,
+    /// 64KB pages, for large media files
+    Kb64,
+}
+
+impl PageSize {
+    /// Size of one page in bytes
+    pub const fn bytes(self) -> usize {
+        match self {
+            PageSize::Kb4 => 4096,
+            PageSize::Kb16 => 16 * 1024,
+            PageSize::Kb64 => 64 * 1024,
+        }
+    }
+
+    /// The page size with this many bytes, if it is a supported one
+    pub fn from_bytes(bytes: u32) -> Option<Self> {
+        [PageSize::Kb4, PageSize::Kb16, PageSize::Kb64]
+            .into_iter()
+            .find(|size| size.bytes() == bytes as usize)
+    }
+}
+
 /// Cartridge archive header (Page 0)
 ///
 /// The header occupies the first 4KB page and contains critical metadata
@@ -137,7 +172,13 @@ impl S3SseMode {
 /// Byte 0 (offset 40): S3VersioningMode
 /// Byte 1 (offset 41): S3AclMode
 /// Byte 2 (offset 42): S3SseMode
-/// Bytes 3-255: Reserved for future use
+/// Bytes 3-7:   Reserved
+/// Bytes 8-15:  Incompatible feature flags (u64 LE)
+/// Bytes 16-23: Compatible feature flags (u64 LE)
+/// Bytes 32-71: Encryption parameters (when FEATURE_ENCRYPTED is set)
+/// Bytes 72-79: Flush generation (u64 LE)
+/// Bytes 80-87: Last flush time (u64 LE)
+/// Bytes 88-255: Reserved for future use
 /// ```
 ///
 /// # Default Behavior
@@ -190,6 +231,181 @@ impl Default for S3FeatureFuses {
     }
 }
 
+/// Offset of the incompatible feature flags word (u64 LE) within the
+/// reserved field
+pub const FEATURE_FLAGS_OFFSET: usize = 8;
+
+/// Offset of the compatible feature flags word (u64 LE) within the reserved
+/// field
+pub const COMPAT_FEATURE_FLAGS_OFFSET: usize = 16;
+
+/// A feature flag bit, in either the compatible or the incompatible set
+///
+/// A reader that doesn't know a *compatible* feature can still read and
+/// write the cartridge correctly and ignores the bit. An *incompatible*
+/// feature changes how pages or paths must be read, so a reader that
+/// doesn't know one refuses the cartridge with
+/// [`CartridgeError::UnsupportedFeatures`], whatever its version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Feature {
+    /// Whether readers must understand the feature to open the cartridge
+    pub incompatible: bool,
+    /// The feature's bit within its set
+    pub mask: u64,
+}
+
+impl Feature {
+    /// A feature older readers may ignore, at `bit` of the compatible set
+    pub const fn compatible(bit: u32) -> Self {
+        Feature {
+            incompatible: false,
+            mask: 1 << bit,
+        }
+    }
+
+    /// A feature readers must understand, at `bit` of the incompatible set
+    pub const fn incompatible(bit: u32) -> Self {
+        Feature {
+            incompatible: true,
+            mask: 1 << bit,
+        }
+    }
+
+    fn offset(self) -> usize {
+        if self.incompatible {
+            FEATURE_FLAGS_OFFSET
+        } else {
+            COMPAT_FEATURE_FLAGS_OFFSET
+        }
+    }
+}
+
+/// Feature flag: content pages are encrypted at rest (see [`EncryptionParams`])
+pub const FEATURE_ENCRYPTED: Feature = Feature::incompatible(0);
+
+/// Feature flag: every page except the header carries a CRC32 trailer
+pub const FEATURE_PAGE_CHECKSUMS: Feature = Feature::incompatible(1);
+
+/// Feature flag: flushes are committed through a sidecar journal
+pub const FEATURE_JOURNAL: Feature = Feature::incompatible(2);
+
+/// Feature flag: paths are looked up regardless of case
+pub const FEATURE_CASE_INSENSITIVE: Feature = Feature::incompatible(3);
+
+/// Feature flag: the catalog page holds a directory of segments (`CAT3`)
+/// rather than the whole catalog
+///
+/// Follows the layout each flush writes the catalog in.
+pub const FEATURE_SEGMENTED_CATALOG: Feature = Feature::incompatible(4);
+
+/// Feature flag: some file has holes, block lists carrying
+/// [`HOLE_BLOCK`](crate::catalog::HOLE_BLOCK) where no block was written
+///
+/// Set the first time a hole is stored and kept from then on.
+pub const FEATURE_SPARSE: Feature = Feature::incompatible(5);
+
+/// Feature flag: some file keeps its content in its catalog entry
+///
+/// Set the first time content is stored inline and kept from then on.
+/// The `CAT2` catalog layout predates feature flags, so every reader that
+/// checks them can at least parse the entries.
+pub const FEATURE_INLINE_DATA: Feature = Feature::incompatible(6);
+
+/// Every compatible feature this version understands
+pub const KNOWN_COMPAT_FEATURES: u64 = 0;
+
+/// Every incompatible feature this version understands
+pub const KNOWN_INCOMPAT_FEATURES: u64 = FEATURE_ENCRYPTED.mask
+    | FEATURE_PAGE_CHECKSUMS.mask
+    | FEATURE_JOURNAL.mask
+    | FEATURE_CASE_INSENSITIVE.mask
+    | FEATURE_SEGMENTED_CATALOG.mask
+    | FEATURE_SPARSE.mask
+    | FEATURE_INLINE_DATA.mask;
+
+/// Offset of the encryption parameters within the reserved field
+pub const ENCRYPTION_PARAMS_OFFSET: usize = 32;
+
+/// Serialized size of [`EncryptionParams`]
+pub const ENCRYPTION_PARAMS_SIZE: usize = 40;
+
+/// Offset of the flush generation (u64 LE) within the reserved field
+///
+/// Zero in cartridges written before generations were tracked.
+pub const GENERATION_OFFSET: usize = 72;
+
+/// Offset of the last flush time (u64 LE, microseconds since the Unix
+/// epoch) within the reserved field
+pub const LAST_FLUSH_OFFSET: usize = 80;
+
+/// Cipher identifier: AES-256-GCM
+pub const CIPHER_AES_256_GCM: u8 = 1;
+
+/// KDF identifier: PBKDF2-HMAC-SHA256
+pub const KDF_PBKDF2_SHA256: u8 = 1;
+
+/// At-rest encryption parameters
+///
+/// Stored in the reserved header field when [`FEATURE_ENCRYPTED`] is set.
+/// The header itself is never encrypted, so `open` can always read these
+/// and tell the caller a passphrase is required.
+///
+/// # Layout
+///
+/// ```text
+/// Byte 32:     Cipher id (1 = AES-256-GCM)
+/// Byte 33:     KDF id (1 = PBKDF2-HMAC-SHA256)
+/// Bytes 34-35: Reserved (zero)
+/// Bytes 36-39: KDF iterations (u32 LE)
+/// Bytes 40-55: KDF salt
+/// Bytes 56-71: Key check value (detects a wrong passphrase)
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncryptionParams {
+    pub cipher: u8,
+    pub kdf: u8,
+    pub kdf_iterations: u32,
+    pub salt: [u8; 16],
+    pub key_check: [u8; 16],
+}
+
+impl EncryptionParams {
+    /// Parse encryption parameters from reserved field
+    pub fn from_reserved(reserved: &[u8; 256]) -> Self {
+        let base = ENCRYPTION_PARAMS_OFFSET;
+        let mut salt = [0u8; 16];
+        salt.copy_from_slice(&reserved[base + 8..base + 24]);
+        let mut key_check = [0u8; 16];
+        key_check.copy_from_slice(&reserved[base + 24..base + 40]);
+
+        Self {
+            cipher: reserved[base],
+            kdf: reserved[base + 1],
+            kdf_iterations: u32::from_le_bytes([
+                reserved[base + 4],
+                reserved[base + 5],
+                reserved[base + 6],
+                reserved[base + 7],
+            ]),
+            salt,
+            key_check,
+        }
+    }
+
+    /// Write encryption parameters into the reserved field
+    ///
+    /// Only touches bytes 32-71; other reserved data is preserved.
+    pub fn write_reserved(&self, reserved: &mut [u8; 256]) {
+        let base = ENCRYPTION_PARAMS_OFFSET;
+        reserved[base..base + ENCRYPTION_PARAMS_SIZE].fill(0);
+        reserved[base] = self.cipher;
+        reserved[base + 1] = self.kdf;
+        reserved[base + 4..base + 8].copy_from_slice(&self.kdf_iterations.to_le_bytes());
+        reserved[base + 8..base + 24].copy_from_slice(&self.salt);
+        reserved[base + 24..base + 40].copy_from_slice(&self.key_check);
+    }
+}
+
 impl Header {
     /// Create a new header with default values
     pub fn new() -> Self {
@@ -205,23 +421,44 @@ impl Header {
         }
     }
 
-    /// Validate the header magic and version
+    /// Validate the header magic, version and feature flags
+    ///
+    /// Any minor version of our major version is accepted; a newer minor
+    /// one can only be opened read-only (see
+    /// [`requires_read_only`](Self::requires_read_only)). Unknown
+    /// incompatible features are refused even then.
     pub fn validate(&self) -> Result<()> {
+        self.validate_for(KNOWN_INCOMPAT_FEATURES)
+    }
+
+    /// Validate as a reader understanding only the `known` incompatible
+    /// features would, e.g. to tell whether an older release can open the
+    /// cartridge
+    pub fn validate_for(&self, known: u64) -> Result<()> {
         // Check magic number
         if self.magic != MAGIC {
             return Err(CartridgeError::InvalidMagic);
         }
 
-        // Check version compatibility (exact match for now)
-        if self.version_major != VERSION_MAJOR || self.version_minor != VERSION_MINOR {
+        // Minor versions only add things older readers can skip
+        if self.version_major != VERSION_MAJOR {
             return Err(CartridgeError::UnsupportedVersion {
                 major: self.version_major,
                 minor: self.version_minor,
             });
         }
 
+        let unknown = self.incompat_features() & !known;
+        if unknown != 0 {
+            return Err(CartridgeError::UnsupportedFeatures {
+                major: self.version_major,
+                minor: self.version_minor,
+                flags: unknown,
+            });
+        }
+
         // Check block size
-        if self.block_size != PAGE_SIZE as u32 {
+        if PageSize::from_bytes(self.block_size).is_none() {
             return Err(CartridgeError::InvalidBlockSize(self.block_size));
         }
 
@@ -236,12 +473,25 @@ impl Header {
         Ok(())
     }
 
+    /// Size of every page in the file, in bytes
+    pub fn page_size(&self) -> usize {
+        self.block_size as usize
+    }
+
+    /// Whether a newer minor version wrote the cartridge
+    ///
+    /// We can read everything it might contain, but writing could clobber
+    /// structures we don't know about, so such cartridges open read-only.
+    pub fn requires_read_only(&self) -> bool {
+        self.version_minor > VERSION_MINOR
+    }
+
     /// Get S3 feature fuses from reserved field
     ///
     /// # Examples
     ///
     /// ```
-    /// use cartridge::header::Header;
+    /// use cartridge_rs::core::header::Header;
     ///
     /// let header = Header::new();
     /// let fuses = header.get_s3_fuses();
@@ -259,7 +509,7 @@ impl Header {
     /// # Examples
     ///
     /// ```
-    /// use cartridge::header::{Header, S3FeatureFuses, S3VersioningMode, S3AclMode, S3SseMode};
+    /// use cartridge_rs::core::header::{Header, S3FeatureFuses, S3VersioningMode, S3AclMode, S3SseMode};
     ///
     /// let mut header = Header::new();
     /// let fuses = S3FeatureFuses {
@@ -270,7 +520,82 @@ impl Header {
     /// header.set_s3_fuses(fuses);
     /// ```
     pub fn set_s3_fuses(&mut self, fuses: S3FeatureFuses) {
-        self.reserved = fuses.to_reserved();
+        // Only the fuse bytes are replaced; the rest of the reserved field
+        // carries feature flags and encryption parameters.
+        self.reserved[..3].copy_from_slice(&fuses.to_reserved()[..3]);
+    }
+
+    /// Get the compatible feature flags word from the reserved field
+    pub fn compat_features(&self) -> u64 {
+        self.reserved_u64(COMPAT_FEATURE_FLAGS_OFFSET)
+    }
+
+    /// Get the incompatible feature flags word from the reserved field
+    pub fn incompat_features(&self) -> u64 {
+        self.reserved_u64(FEATURE_FLAGS_OFFSET)
+    }
+
+    /// Check whether a feature flag is set
+    pub fn has_feature(&self, feature: Feature) -> bool {
+        self.reserved_u64(feature.offset()) & feature.mask != 0
+    }
+
+    /// Set or clear a feature flag
+    pub fn set_feature(&mut self, feature: Feature, enabled: bool) {
+        let flags = self.reserved_u64(feature.offset());
+        let flags = if enabled {
+            flags | feature.mask
+        } else {
+            flags & !feature.mask
+        };
+        self.set_reserved_u64(feature.offset(), flags);
+    }
+
+    /// The generation of the last flush that wrote the catalog and
+    /// allocator, or 0 if unknown
+    pub fn generation(&self) -> u64 {
+        self.reserved_u64(GENERATION_OFFSET)
+    }
+
+    /// Set the flush generation
+    pub fn set_generation(&mut self, generation: u64) {
+        self.set_reserved_u64(GENERATION_OFFSET, generation);
+    }
+
+    /// When the cartridge was last flushed, in microseconds since the Unix
+    /// epoch, or 0 if unknown
+    pub fn last_flush_us(&self) -> u64 {
+        self.reserved_u64(LAST_FLUSH_OFFSET)
+    }
+
+    /// Set the last flush time
+    pub fn set_last_flush_us(&mut self, micros: u64) {
+        self.set_reserved_u64(LAST_FLUSH_OFFSET, micros);
+    }
+
+    fn reserved_u64(&self, offset: usize) -> u64 {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&self.reserved[offset..offset + 8]);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn set_reserved_u64(&mut self, offset: usize, value: u64) {
+        self.reserved[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// Get the at-rest encryption parameters, if the cartridge is encrypted
+    pub fn encryption_params(&self) -> Option<EncryptionParams> {
+        if self.has_feature(FEATURE_ENCRYPTED) {
+            Some(EncryptionParams::from_reserved(&self.reserved))
+        } else {
+            None
+        }
+    }
+
+    /// Store at-rest encryption parameters and set [`FEATURE_ENCRYPTED`]
+    pub fn set_encryption_params(&mut self, params: EncryptionParams) {
+        params.write_reserved(&mut self.reserved);
+        self.set_feature(FEATURE_ENCRYPTED, true);
     }
 
     /// Serialize header to bytes
@@ -412,6 +737,44 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_minor_versions() {
+        let mut header = Header::new();
+        assert!(!header.requires_read_only());
+
+        // Newer minor versions validate, but only for reading
+        header.version_minor = VERSION_MINOR + 1;
+        header.validate().unwrap();
+        assert!(header.requires_read_only());
+    }
+
+    #[test]
+    fn test_feature_sets() {
+        let future_compat = Feature::compatible(5);
+        let future_incompat = Feature::incompatible(40);
+
+        let mut header = Header::new();
+        header.set_feature(FEATURE_JOURNAL, true);
+        header.set_feature(future_compat, true);
+        assert!(header.has_feature(future_compat));
+        // Same bit, other set
+        assert!(!header.has_feature(Feature::incompatible(5)));
+        assert_eq!(header.compat_features(), 1 << 5);
+        assert_eq!(header.incompat_features(), FEATURE_JOURNAL.mask);
+
+        // Unknown compatible features are ignored
+        let mut header = Header::from_bytes(&header.to_bytes()).unwrap();
+        assert!(header.has_feature(future_compat) && header.has_feature(FEATURE_JOURNAL));
+
+        header.set_feature(future_incompat, true);
+        assert!(matches!(
+            Header::from_bytes(&header.to_bytes()),
+            Err(CartridgeError::UnsupportedFeatures { flags, .. }) if flags == 1 << 40
+        ));
+        header.set_feature(future_incompat, false);
+        header.validate().unwrap();
+    }
+
     #[test]
     fn test_invalid_block_size() {
         let mut header = Header::new();
@@ -422,6 +785,18 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_supported_block_sizes() {
+        for size in [PageSize::Kb4, PageSize::Kb16, PageSize::Kb64] {
+            let mut header = Header::new();
+            header.block_size = size.bytes() as u32;
+            header.validate().unwrap();
+            let loaded = Header::from_bytes(&header.to_bytes()).unwrap();
+            assert_eq!(loaded.page_size(), size.bytes());
+            assert_eq!(PageSize::from_bytes(loaded.block_size), Some(size));
+        }
+    }
+
     #[test]
     fn test_header_serialization() {
         let mut header = Header::new();
@@ -438,6 +813,27 @@ mod tests {
         assert_eq!(deserialized.btree_root_page, 42);
     }
 
+    #[test]
+    fn test_flush_stamp_round_trip() {
+        let mut header = Header::new();
+        assert_eq!((header.generation(), header.last_flush_us()), (0, 0));
+
+        header.set_encryption_params(EncryptionParams {
+            cipher: CIPHER_AES_256_GCM,
+            kdf: KDF_PBKDF2_SHA256,
+            kdf_iterations: 1000,
+            salt: [0xAA; 16],
+            key_check: [0xBB; 16],
+        });
+        header.set_generation(7);
+        header.set_last_flush_us(1_700_000_000_000_000);
+
+        let header = Header::from_bytes(&header.to_bytes()).unwrap();
+        assert_eq!(header.generation(), 7);
+        assert_eq!(header.last_flush_us(), 1_700_000_000_000_000);
+        assert_eq!(header.encryption_params().unwrap().key_check, [0xBB; 16]);
+    }
+
     #[test]
     fn test_free_blocks_exceeds_total() {
         let mut header = Header::new();
@@ -517,8 +913,8 @@ mod tests {
         assert_eq!(reserved[1], 1); // Record
         assert_eq!(reserved[2], 0); // Ignore
                                     // Rest should be zeros
-        for i in 3..256 {
-            assert_eq!(reserved[i], 0);
+        for &byte in &reserved[3..] {
+            assert_eq!(byte, 0);
         }
     }
 
@@ -598,6 +994,47 @@ mod tests {
         assert!(header.validate().is_ok());
     }
 
+    #[test]
+    fn test_set_s3_fuses_preserves_other_reserved_bytes() {
+        let mut header = Header::new();
+        header.set_feature(FEATURE_ENCRYPTED, true);
+
+        header.set_s3_fuses(S3FeatureFuses {
+            versioning_mode: S3VersioningMode::SnapshotBacked,
+            acl_mode: S3AclMode::Record,
+            sse_mode: S3SseMode::Transparent,
+        });
+
+        assert!(header.has_feature(FEATURE_ENCRYPTED));
+        assert_eq!(
+            header.get_s3_fuses().versioning_mode,
+            S3VersioningMode::SnapshotBacked
+        );
+    }
+
+    #[test]
+    fn test_encryption_params_round_trip() {
+        let mut header = Header::new();
+        header.total_blocks = 10;
+        assert!(header.encryption_params().is_none());
+
+        let params = EncryptionParams {
+            cipher: CIPHER_AES_256_GCM,
+            kdf: KDF_PBKDF2_SHA256,
+            kdf_iterations: 100_000,
+            salt: [7u8; 16],
+            key_check: [9u8; 16],
+        };
+        header.set_encryption_params(params);
+
+        let deserialized = Header::from_bytes(&header.to_bytes()).unwrap();
+        assert!(deserialized.has_feature(FEATURE_ENCRYPTED));
+        assert_eq!(deserialized.encryption_params(), Some(params));
+
+        // Fuses are untouched by encryption params
+        assert_eq!(deserialized.get_s3_fuses().acl_mode, S3AclMode::Ignore);
+    }
+
     #[test]
     fn test_s3_versioning_mode_from_u8() {
         assert_eq!(S3VersioningMode::from_u8(0), S3VersioningMode::None);
@@ -1,61 +1,165 @@
 //! LRU cache for IAM policy evaluation results
 //!
 //! Caches evaluation results to achieve 10,000+ evals/sec performance.
+//! Entries can be given a time to live, so long-lived processes pick up
+//! policy changes made behind the cache's back.
 
 use lru::LruCache;
 use std::num::NonZeroUsize;
+use std::time::{Duration, SystemTime};
 
 /// Cache key for policy evaluation
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct CacheKey {
+    principal: Option<String>,
     action: String,
     resource: String,
 }
 
+impl CacheKey {
+    fn new(principal: Option<&str>, action: &str, resource: &str) -> Self {
+        CacheKey {
+            principal: principal.map(str::to_string),
+            action: action.to_string(),
+            resource: resource.to_string(),
+        }
+    }
+}
+
+/// Hit and miss counts for a [`PolicyCache`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct CacheStats {
+    /// Lookups answered from the cache
+    pub hits: u64,
+    /// Lookups that had to evaluate the policy, expired entries included
+    pub misses: u64,
+    /// Entries dropped to make room for new ones
+    pub evictions: u64,
+    /// Entries currently cached
+    pub entries: usize,
+}
+
 /// LRU cache for policy evaluation results
 pub struct PolicyCache {
-    cache: LruCache<CacheKey, bool>,
+    /// Results and when they were cached; `None` when caching is disabled
+    cache: Option<LruCache<CacheKey, (bool, SystemTime)>>,
+    ttl: Option<Duration>,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
 }
 
 impl PolicyCache {
     /// Create a new policy cache with given capacity
+    ///
+    /// A capacity of 0 disables caching: every lookup misses.
     pub fn new(capacity: usize) -> Self {
+        Self::with_ttl(capacity, None)
+    }
+
+    /// Create a policy cache whose entries expire `ttl` after being cached
+    pub fn with_ttl(capacity: usize, ttl: Option<Duration>) -> Self {
         PolicyCache {
-            cache: LruCache::new(NonZeroUsize::new(capacity).unwrap()),
+            cache: NonZeroUsize::new(capacity).map(LruCache::new),
+            ttl,
+            hits: 0,
+            misses: 0,
+            evictions: 0,
         }
     }
 
-    /// Get cached evaluation result
+    /// Get cached evaluation result for an anonymous caller
     pub fn get(&mut self, action: &str, resource: &str) -> Option<bool> {
-        let key = CacheKey {
-            action: action.to_string(),
-            resource: resource.to_string(),
-        };
-        self.cache.get(&key).copied()
+        self.get_as(None, action, resource)
     }
 
-    /// Put evaluation result in cache
+    /// Put evaluation result for an anonymous caller in cache
     pub fn put(&mut self, action: &str, resource: &str, result: bool) {
-        let key = CacheKey {
-            action: action.to_string(),
-            resource: resource.to_string(),
+        self.put_as(None, action, resource, result);
+    }
+
+    /// Get cached evaluation result for `principal`
+    pub fn get_as(
+        &mut self,
+        principal: Option<&str>,
+        action: &str,
+        resource: &str,
+    ) -> Option<bool> {
+        let Some(cache) = &mut self.cache else {
+            self.misses += 1;
+            return None;
+        };
+        let key = CacheKey::new(principal, action, resource);
+        let cached = match cache.get(&key) {
+            Some(&(result, cached_at)) => match self.ttl {
+                Some(ttl)
+                    if crate::clock::now().duration_since(cached_at).unwrap_or_default() >= ttl =>
+                {
+                    cache.pop(&key);
+                    None
+                }
+                _ => Some(result),
+            },
+            None => None,
+        };
+        match cached {
+            Some(_) => self.hits += 1,
+            None => self.misses += 1,
+        }
+        cached
+    }
+
+    /// Put evaluation result for `principal` in cache
+    pub fn put_as(&mut self, principal: Option<&str>, action: &str, resource: &str, result: bool) {
+        let Some(cache) = &mut self.cache else {
+            return;
         };
-        self.cache.put(key, result);
+        let key = CacheKey::new(principal, action, resource);
+        // `push` also hands back the old value when replacing the same key
+        if let Some((evicted, _)) = cache.push(key.clone(), (result, crate::clock::now())) {
+            if evicted != key {
+                self.evictions += 1;
+            }
+        }
     }
 
     /// Clear the cache
+    ///
+    /// The hit, miss and eviction counts carry on.
     pub fn clear(&mut self) {
-        self.cache.clear();
+        if let Some(cache) = &mut self.cache {
+            cache.clear();
+        }
     }
 
     /// Get cache statistics
     pub fn len(&self) -> usize {
-        self.cache.len()
+        self.cache.as_ref().map_or(0, LruCache::len)
     }
 
     /// Check if cache is empty
     pub fn is_empty(&self) -> bool {
-        self.cache.is_empty()
+        self.len() == 0
+    }
+
+    /// How many entries the cache holds at most
+    pub fn capacity(&self) -> usize {
+        self.cache.as_ref().map_or(0, |cache| cache.cap().get())
+    }
+
+    /// How long entries stay valid, if they expire at all
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    /// Hit, miss and eviction counts since the cache was created
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            evictions: self.evictions,
+            entries: self.len(),
+        }
     }
 }
 
@@ -111,4 +215,55 @@ mod tests {
         assert_eq!(cache.get("read", "/test"), Some(true));
         assert_eq!(cache.get("write", "/test"), Some(false));
     }
+
+    #[test]
+    fn test_cache_stats() {
+        let mut cache = PolicyCache::new(2);
+
+        assert!(cache.get("read", "/a").is_none());
+        cache.put("read", "/a", true);
+        cache.put("read", "/a", false); // Replaces, not an eviction
+        assert_eq!(cache.get("read", "/a"), Some(false));
+        cache.put("read", "/b", true);
+        cache.put("read", "/c", true); // Evicts /a
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (1, 1, 1));
+        assert_eq!(stats.entries, 2);
+
+        cache.clear();
+        assert_eq!(cache.stats().entries, 0);
+        assert_eq!(cache.stats().hits, 1);
+    }
+
+    #[test]
+    fn test_cache_ttl() {
+        let mut cache = PolicyCache::with_ttl(10, Some(Duration::from_millis(20)));
+        cache.put("read", "/a", true);
+        assert_eq!(cache.get("read", "/a"), Some(true));
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(cache.get("read", "/a").is_none());
+        assert!(cache.is_empty());
+        assert_eq!(cache.stats().misses, 1);
+    }
+
+    #[test]
+    fn test_cache_disabled() {
+        let mut cache = PolicyCache::new(0);
+        cache.put("read", "/a", true);
+        assert!(cache.get("read", "/a").is_none());
+        assert_eq!(cache.capacity(), 0);
+        assert_eq!(cache.stats().misses, 1);
+    }
+
+    #[test]
+    fn test_cache_different_principals() {
+        let mut cache = PolicyCache::new(10);
+
+        cache.put_as(Some("alice"), "write", "/a", true);
+        assert_eq!(cache.get_as(Some("alice"), "write", "/a"), Some(true));
+        assert!(cache.get_as(Some("bob"), "write", "/a").is_none());
+        assert!(cache.get("write", "/a").is_none());
+    }
 }
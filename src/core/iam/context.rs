@@ -0,0 +1,139 @@
+//! Request context for condition evaluation
+//!
+//! Each operation describes itself with a [`RequestContext`], which is
+//! turned into the well-known condition keys below. A statement such as
+//!
+//! ```json
+//! {
+//!   "Effect": "Deny",
+//!   "Action": ["write", "create"],
+//!   "Resource": ["/**"],
+//!   "Condition": { "NumericGreaterThan": { "cartridge:ContentLength": 1048576 } }
+//! }
+//! ```
+//!
+//! then only applies to requests whose values satisfy its conditions.
+
+use super::ConditionValue;
+use chrono::{DateTime, SecondsFormat, Timelike, Utc};
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+/// Time of the request, as an ISO 8601 UTC string (`2024-01-01T09:30:00Z`)
+pub const CURRENT_TIME: &str = "cartridge:CurrentTime";
+/// Hour of the request in UTC, `0` to `23`
+pub const CURRENT_HOUR: &str = "cartridge:CurrentHour";
+/// Number of bytes being written
+pub const CONTENT_LENGTH: &str = "cartridge:ContentLength";
+/// Content type of the data being written
+pub const CONTENT_TYPE: &str = "cartridge:ContentType";
+/// Tag naming where the request came from
+pub const SOURCE: &str = "cartridge:Source";
+/// Audit session the request belongs to
+pub const SESSION_ID: &str = "cartridge:SessionId";
+
+/// What an operation tells the policy engine about itself
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    /// When the request was made
+    pub timestamp: SystemTime,
+    /// Bytes being written, for writes
+    pub content_length: Option<u64>,
+    /// Content type of the data being written, if known
+    pub content_type: Option<String>,
+    /// Where the request came from (e.g. `"fuse"`)
+    pub source: Option<String>,
+    /// Audit session id
+    pub session_id: u32,
+}
+
+impl RequestContext {
+    /// Context for a request made now in `session_id`
+    pub fn new(session_id: u32) -> Self {
+        RequestContext {
+            timestamp: crate::clock::now(),
+            content_length: None,
+            content_type: None,
+            source: None,
+            session_id,
+        }
+    }
+
+    /// Describe the data a write carries
+    pub fn with_content(mut self, length: u64, content_type: Option<&str>) -> Self {
+        self.content_length = Some(length);
+        self.content_type = content_type.map(str::to_string);
+        self
+    }
+
+    /// Tag where the request came from
+    pub fn with_source(mut self, source: Option<&str>) -> Self {
+        self.source = source.map(str::to_string);
+        self
+    }
+
+    /// The condition keys this context provides
+    ///
+    /// Keys for values the request doesn't have are left out, so conditions
+    /// on them fail.
+    pub fn condition_values(&self) -> HashMap<String, ConditionValue> {
+        let time = DateTime::<Utc>::from(self.timestamp);
+        let mut values = HashMap::from([
+            (
+                CURRENT_TIME.to_string(),
+                ConditionValue::String(time.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            ),
+            (
+                CURRENT_HOUR.to_string(),
+                ConditionValue::Number(time.hour() as f64),
+            ),
+            (
+                SESSION_ID.to_string(),
+                ConditionValue::Number(self.session_id as f64),
+            ),
+        ]);
+        if let Some(length) = self.content_length {
+            values.insert(CONTENT_LENGTH.to_string(), ConditionValue::Number(length as f64));
+        }
+        if let Some(content_type) = &self.content_type {
+            values.insert(
+                CONTENT_TYPE.to_string(),
+                ConditionValue::String(content_type.clone()),
+            );
+        }
+        if let Some(source) = &self.source {
+            values.insert(SOURCE.to_string(), ConditionValue::String(source.clone()));
+        }
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_condition_values() {
+        // 2024-01-01T09:30:00Z
+        let context = RequestContext {
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(1_704_101_400),
+            ..RequestContext::new(7)
+        }
+        .with_content(2048, Some("text/plain"));
+
+        let values = context.condition_values();
+        assert_eq!(
+            values[CURRENT_TIME],
+            ConditionValue::String("2024-01-01T09:30:00Z".to_string())
+        );
+        assert_eq!(values[CURRENT_HOUR], ConditionValue::Number(9.0));
+        assert_eq!(values[CONTENT_LENGTH], ConditionValue::Number(2048.0));
+        assert_eq!(
+            values[CONTENT_TYPE],
+            ConditionValue::String("text/plain".to_string())
+        );
+        assert_eq!(values[SESSION_ID], ConditionValue::Number(7.0));
+        assert!(!values.contains_key(SOURCE));
+    }
+}
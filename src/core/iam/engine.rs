@@ -7,8 +7,11 @@
 //! - Condition-based evaluation
 //! - Pattern matching for resources
 
-use super::{Action, ConditionValue, Effect, Policy, PolicyCache};
+use super::{
+    Action, CacheStats, Condition, ConditionOperator, ConditionValue, Effect, Policy, PolicyCache,
+};
 use std::collections::HashMap;
+use std::time::Duration;
 
 /// Policy evaluation engine
 pub struct PolicyEngine {
@@ -28,8 +31,34 @@ impl PolicyEngine {
         Self::new(1000)
     }
 
+    /// Create a policy engine caching up to `capacity` decisions, each for
+    /// at most `ttl`
+    ///
+    /// A capacity of 0 turns caching off, for workloads touching so many
+    /// distinct paths that the cache would only thrash. Without a TTL,
+    /// decisions stay cached until evicted or [`clear_cache`](Self::clear_cache)
+    /// is called.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cartridge_rs::core::iam::PolicyEngine;
+    /// use std::time::Duration;
+    ///
+    /// let engine = PolicyEngine::with_cache(100_000, Some(Duration::from_secs(60)));
+    /// assert_eq!(engine.cache_stats().entries, 0);
+    /// ```
+    pub fn with_cache(capacity: usize, ttl: Option<Duration>) -> Self {
+        PolicyEngine {
+            cache: PolicyCache::with_ttl(capacity, ttl),
+        }
+    }
+
     /// Evaluate if an action on a resource is allowed by the policy
     ///
+    /// The caller is anonymous, so only statements whose principal list
+    /// contains `"*"` apply; see [`evaluate_as`](Self::evaluate_as).
+    ///
     /// # Arguments
     ///
     /// * `policy` - The IAM policy to evaluate
@@ -44,7 +73,7 @@ impl PolicyEngine {
     /// # Examples
     ///
     /// ```
-    /// use cartridge::iam::{PolicyEngine, Policy, Statement, Effect, Action};
+    /// use cartridge_rs::core::iam::{PolicyEngine, Policy, Statement, Effect, Action};
     ///
     /// let mut engine = PolicyEngine::new_default();
     /// let mut policy = Policy::new();
@@ -63,20 +92,57 @@ impl PolicyEngine {
         action: &Action,
         resource: &str,
         context: Option<&HashMap<String, ConditionValue>>,
+    ) -> bool {
+        self.evaluate_as(policy, None, action, resource, context)
+    }
+
+    /// Evaluate if `principal` may perform an action on a resource
+    ///
+    /// Statements only apply if one of their principal patterns matches;
+    /// `None` is an anonymous caller. Cached decisions are kept per
+    /// principal. Policies with conditions depend on the context, so their
+    /// decisions aren't cached.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cartridge_rs::core::iam::{PolicyEngine, Policy, Statement, Effect, Action};
+    ///
+    /// let mut engine = PolicyEngine::new_default();
+    /// let mut policy = Policy::new();
+    /// policy.add_statement(
+    ///     Statement::new(Effect::Allow, vec![Action::Write], vec!["/a/**".to_string()])
+    ///         .with_principal(vec!["alice".to_string()]),
+    /// );
+    ///
+    /// assert!(engine.evaluate_as(&policy, Some("alice"), &Action::Write, "/a/x", None));
+    /// assert!(!engine.evaluate_as(&policy, Some("bob"), &Action::Write, "/a/x", None));
+    /// ```
+    pub fn evaluate_as(
+        &mut self,
+        policy: &Policy,
+        principal: Option<&str>,
+        action: &Action,
+        resource: &str,
+        context: Option<&HashMap<String, ConditionValue>>,
     ) -> bool {
         // Convert action to string for caching
         let action_str = action_to_string(action);
 
+        if policy.statement.iter().any(|s| s.condition.is_some()) {
+            return self.evaluate_uncached(policy, principal, action, resource, context);
+        }
+
         // Check cache first
-        if let Some(cached) = self.cache.get(&action_str, resource) {
+        if let Some(cached) = self.cache.get_as(principal, &action_str, resource) {
             return cached;
         }
 
         // Evaluate policy
-        let result = self.evaluate_uncached(policy, action, resource, context);
+        let result = self.evaluate_uncached(policy, principal, action, resource, context);
 
         // Store in cache
-        self.cache.put(&action_str, resource, result);
+        self.cache.put_as(principal, &action_str, resource, result);
 
         result
     }
@@ -85,6 +151,7 @@ impl PolicyEngine {
     fn evaluate_uncached(
         &self,
         policy: &Policy,
+        principal: Option<&str>,
         action: &Action,
         resource: &str,
         context: Option<&HashMap<String, ConditionValue>>,
@@ -93,8 +160,10 @@ impl PolicyEngine {
 
         // Evaluate all statements
         for statement in &policy.statement {
-            // Check if statement applies to this action and resource
-            if !statement.applies_to(action, resource) {
+            // Check if statement applies to this principal, action and resource
+            if !statement.applies_to_principal(principal)
+                || !statement.applies_to(action, resource)
+            {
                 continue;
             }
 
@@ -128,25 +197,45 @@ impl PolicyEngine {
     }
 
     /// Evaluate conditions from JSON
+    ///
+    /// Conditions are written `{"Operator": {"key": value}}`. Every
+    /// operator/key pair must hold; a list of values holds if any of them
+    /// does, or for `StringNotEquals` if all of them do. Unknown operators
+    /// and malformed conditions never hold.
     fn evaluate_conditions(
         &self,
         condition_json: &serde_json::Value,
-        _context: &HashMap<String, ConditionValue>,
+        context: &HashMap<String, ConditionValue>,
     ) -> bool {
-        // Simplified condition evaluation
-        // Real implementation would parse complex condition structures
-        // For now, we just check if the condition object is present
-        // This is a placeholder for more complex logic
-
-        // If condition is an empty object, consider it as passing
-        if condition_json.is_object() && condition_json.as_object().unwrap().is_empty() {
-            return true;
-        }
-
-        // For non-empty conditions, we'd need to parse and evaluate
-        // This would involve converting JSON to Condition objects
-        // For now, return true (conditions are optional feature)
-        true
+        let Some(operators) = condition_json.as_object() else {
+            return false;
+        };
+        operators.iter().all(|(operator, keys)| {
+            let operator = serde_json::from_value::<ConditionOperator>(operator.as_str().into());
+            let Ok(operator) = operator else {
+                return false;
+            };
+            let Some(keys) = keys.as_object() else {
+                return false;
+            };
+            keys.iter().all(|(key, expected)| {
+                let expected = match expected {
+                    serde_json::Value::Array(values) => values.clone(),
+                    value => vec![value.clone()],
+                };
+                let mut holds = expected.into_iter().map(|value| {
+                    serde_json::from_value::<ConditionValue>(value).is_ok_and(|value| {
+                        Condition::new(operator.clone(), key.clone(), value).evaluate(context)
+                    })
+                });
+                // A negated operator must hold against every value
+                if operator == ConditionOperator::StringNotEquals {
+                    holds.all(|h| h)
+                } else {
+                    holds.any(|h| h)
+                }
+            })
+        })
     }
 
     /// Clear the evaluation cache
@@ -158,6 +247,14 @@ impl PolicyEngine {
     pub fn cache_size(&self) -> usize {
         self.cache.len()
     }
+
+    /// Hit, miss and eviction counts for the evaluation cache
+    ///
+    /// Evaluations of policies with conditions bypass the cache and aren't
+    /// counted.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
 }
 
 /// Convert Action to string for caching
@@ -303,6 +400,25 @@ mod tests {
         assert_eq!(engine.cache_size(), 0);
     }
 
+    #[test]
+    fn test_cache_stats() {
+        let mut engine = PolicyEngine::with_cache(2, None);
+        let mut policy = Policy::new();
+        policy.add_statement(Statement::new(
+            Effect::Allow,
+            vec![Action::Read],
+            vec!["/**".to_string()],
+        ));
+
+        for path in ["/a", "/a", "/b", "/c", "/a"] {
+            assert!(engine.evaluate(&policy, &Action::Read, path, None));
+        }
+        // /a hit once; /c evicted /a, and /a evicted /b
+        let stats = engine.cache_stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (1, 4, 2));
+        assert_eq!(stats.entries, 2);
+    }
+
     #[test]
     fn test_recursive_wildcard() {
         let mut engine = PolicyEngine::new_default();
@@ -9,12 +9,14 @@
 
 mod cache;
 mod condition;
+pub mod context;
 mod engine;
 mod pattern;
 mod policy;
 
-pub use cache::PolicyCache;
+pub use cache::{CacheStats, PolicyCache};
 pub use condition::{Condition, ConditionOperator, ConditionValue};
+pub use context::RequestContext;
 pub use engine::PolicyEngine;
 pub use pattern::PatternMatcher;
 pub use policy::{Action, Effect, Policy, Statement};
@@ -12,7 +12,7 @@ impl PatternMatcher {
     ///
     /// # Examples
     /// ```
-    /// use cartridge::iam::PatternMatcher;
+    /// use cartridge_rs::core::iam::PatternMatcher;
     ///
     /// assert!(PatternMatcher::matches("/users/*", "/users/alice"));
     /// assert!(PatternMatcher::matches("/admin/**", "/admin/users/bob"));
@@ -61,6 +61,12 @@ pub struct Statement {
     /// Resources this statement applies to (supports wildcards)
     pub resource: Vec<String>,
 
+    /// Principals this statement applies to (supports wildcards)
+    ///
+    /// Defaults to `["*"]`, which also covers callers with no principal set.
+    #[serde(default = "any_principal", skip_serializing_if = "is_any_principal")]
+    pub principal: Vec<String>,
+
     /// Optional conditions for when this statement applies
     #[serde(skip_serializing_if = "Option::is_none")]
     pub condition: Option<serde_json::Value>,
@@ -74,10 +80,17 @@ impl Statement {
             effect,
             action,
             resource,
+            principal: any_principal(),
             condition: None,
         }
     }
 
+    /// Restrict this statement to the given principals
+    pub fn with_principal(mut self, principal: Vec<String>) -> Self {
+        self.principal = principal;
+        self
+    }
+
     /// Check if this statement applies to the given action and resource
     pub fn applies_to(&self, action: &Action, resource: &str) -> bool {
         // Check if action matches
@@ -91,6 +104,24 @@ impl Statement {
             .iter()
             .any(|pattern| crate::iam::PatternMatcher::matches(pattern, resource))
     }
+
+    /// Check if this statement applies to the given principal
+    ///
+    /// `None` is an anonymous caller, which only `"*"` covers.
+    pub fn applies_to_principal(&self, principal: Option<&str>) -> bool {
+        self.principal.iter().any(|pattern| {
+            pattern == "*"
+                || principal.is_some_and(|p| crate::iam::PatternMatcher::matches(pattern, p))
+        })
+    }
+}
+
+fn any_principal() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
+fn is_any_principal(principal: &[String]) -> bool {
+    principal == ["*"]
 }
 
 /// Complete IAM policy document
@@ -141,6 +172,9 @@ impl Policy {
             if stmt.resource.is_empty() {
                 return Err(format!("Statement {} has no resources", i));
             }
+            if stmt.principal.is_empty() {
+                return Err(format!("Statement {} has no principals", i));
+            }
         }
 
         Ok(())
@@ -221,4 +255,35 @@ mod tests {
         assert!(stmt.applies_to(&Action::Read, "/public/file.txt"));
         assert!(!stmt.applies_to(&Action::Write, "/public/file.txt"));
     }
+
+    #[test]
+    fn test_statement_principal() {
+        let stmt = Statement::new(Effect::Allow, vec![Action::Read], vec!["/*".to_string()]);
+        assert!(stmt.applies_to_principal(None));
+        assert!(stmt.applies_to_principal(Some("alice")));
+
+        let stmt = stmt.with_principal(vec!["alice".to_string(), "svc-*".to_string()]);
+        assert!(stmt.applies_to_principal(Some("alice")));
+        assert!(stmt.applies_to_principal(Some("svc-backup")));
+        assert!(!stmt.applies_to_principal(Some("bob")));
+        assert!(!stmt.applies_to_principal(None));
+    }
+
+    #[test]
+    fn test_principal_json_compatible() {
+        // Policies written before principals existed apply to everyone
+        let json = r#"{"Version":"2024-01-01","Statement":[
+            {"Effect":"Allow","Action":["read"],"Resource":["/**"]}]}"#;
+        let policy = Policy::from_json(json).unwrap();
+        assert_eq!(policy.statement[0].principal, ["*"]);
+        assert!(!policy.to_json().unwrap().contains("Principal"));
+
+        let mut policy = Policy::new();
+        policy.add_statement(
+            Statement::new(Effect::Allow, vec![Action::Read], vec!["/**".to_string()])
+                .with_principal(vec!["alice".to_string()]),
+        );
+        let parsed = Policy::from_json(&policy.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.statement[0].principal, ["alice"]);
+    }
 }
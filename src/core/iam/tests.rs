@@ -2,7 +2,6 @@
 
 use super::*;
 use serde_json::json;
-use std::collections::HashMap;
 
 #[test]
 fn test_complex_policy_scenario() {
@@ -403,3 +402,126 @@ fn test_statement_ordering() {
     let mut engine2 = PolicyEngine::new_default();
     assert!(!engine2.evaluate(&policy2, &Action::Read, "/secret/key.txt", None));
 }
+
+#[test]
+fn test_per_principal_access() {
+    let mut engine = PolicyEngine::new_default();
+    let mut policy = Policy::new();
+
+    // Alice writes /a, Bob can only read it
+    policy.add_statement(
+        Statement::new(
+            Effect::Allow,
+            vec![Action::Read, Action::Write],
+            vec!["/a/**".to_string()],
+        )
+        .with_principal(vec!["alice".to_string()]),
+    );
+    policy.add_statement(
+        Statement::new(Effect::Allow, vec![Action::Read], vec!["/a/**".to_string()])
+            .with_principal(vec!["bob".to_string()]),
+    );
+
+    assert!(engine.evaluate_as(&policy, Some("alice"), &Action::Write, "/a/f.txt", None));
+    assert!(engine.evaluate_as(&policy, Some("bob"), &Action::Read, "/a/f.txt", None));
+    assert!(!engine.evaluate_as(&policy, Some("bob"), &Action::Write, "/a/f.txt", None));
+    assert!(!engine.evaluate_as(&policy, Some("carol"), &Action::Read, "/a/f.txt", None));
+    assert!(!engine.evaluate(&policy, &Action::Read, "/a/f.txt", None));
+}
+
+#[test]
+fn test_principal_deny_precedence() {
+    let mut engine = PolicyEngine::new_default();
+    let mut policy = Policy::new();
+
+    // Everyone reads everything, except contractors on /internal
+    policy.add_statement(Statement::new(
+        Effect::Allow,
+        vec![Action::Read],
+        vec!["/**".to_string()],
+    ));
+    policy.add_statement(
+        Statement::new(Effect::Deny, vec![Action::All], vec!["/internal/**".to_string()])
+            .with_principal(vec!["contractor-*".to_string()]),
+    );
+
+    // The cached allow for one principal must not leak to another
+    assert!(engine.evaluate_as(&policy, Some("alice"), &Action::Read, "/internal/plan", None));
+    assert!(!engine.evaluate_as(
+        &policy,
+        Some("contractor-bob"),
+        &Action::Read,
+        "/internal/plan",
+        None
+    ));
+    assert!(engine.evaluate(&policy, &Action::Read, "/internal/plan", None));
+    assert!(engine.evaluate_as(&policy, Some("contractor-bob"), &Action::Read, "/pub", None));
+}
+
+#[test]
+fn test_request_context_conditions() {
+    let mut engine = PolicyEngine::new_default();
+    let policy = Policy::from_json(
+        &json!({
+            "Version": "2024-01-01",
+            "Statement": [
+                {"Effect": "Allow", "Action": ["*"], "Resource": ["/**"]},
+                {
+                    "Effect": "Deny",
+                    "Action": ["write", "create"],
+                    "Resource": ["/**"],
+                    "Condition": {
+                        "NumericGreaterThan": {"cartridge:ContentLength": 1_048_576}
+                    }
+                },
+                {
+                    "Effect": "Deny",
+                    "Action": ["create"],
+                    "Resource": ["/images/**"],
+                    "Condition": {
+                        "StringNotEquals": {"cartridge:ContentType": ["image/png", "image/jpeg"]}
+                    }
+                },
+                {
+                    "Effect": "Deny",
+                    "Action": ["*"],
+                    "Resource": ["/archive/**"],
+                    "Condition": {"DateGreaterThan": {"cartridge:CurrentTime": "2030-01-01"}}
+                }
+            ]
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    let write = |length: u64, content_type: &str| {
+        RequestContext::new(0)
+            .with_content(length, Some(content_type))
+            .condition_values()
+    };
+    let small = write(1024, "image/png");
+    let large = write(2 * 1024 * 1024, "image/png");
+    let text = write(1024, "text/plain");
+
+    assert!(engine.evaluate(&policy, &Action::Write, "/a.bin", Some(&small)));
+    assert!(!engine.evaluate(&policy, &Action::Write, "/a.bin", Some(&large)));
+    assert!(engine.evaluate(&policy, &Action::Create, "/images/a.png", Some(&small)));
+    assert!(!engine.evaluate(&policy, &Action::Create, "/images/a.txt", Some(&text)));
+    assert!(engine.evaluate(&policy, &Action::Read, "/archive/old", Some(&small)));
+
+    // Decisions depend on the context, so none were cached
+    assert_eq!(engine.cache_size(), 0);
+}
+
+#[test]
+fn test_malformed_condition_never_holds() {
+    let mut engine = PolicyEngine::new_default();
+    let mut policy = Policy::new();
+    let mut statement = Statement::new(Effect::Allow, vec![Action::Read], vec!["/**".to_string()]);
+    statement.condition = Some(json!({"NumericSomething": {"cartridge:ContentLength": 1}}));
+    policy.add_statement(statement);
+
+    let context = RequestContext::new(0).condition_values();
+    assert!(!engine.evaluate(&policy, &Action::Read, "/a", Some(&context)));
+    assert!(!engine.evaluate(&policy, &Action::Read, "/a", None));
+}
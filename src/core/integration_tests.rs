@@ -193,7 +193,7 @@ mod tests {
         let snapshot_dir = temp_dir.path().join("snapshots");
 
         let mut snap_mgr = SnapshotManager::new(&snapshot_dir).unwrap();
-        let cart = Cartridge::new(100);
+        let _cart = Cartridge::new(100);
         let pages = std::collections::HashMap::new();
 
         // Create 5 snapshots
@@ -280,7 +280,7 @@ mod tests {
         let snapshot_dir = temp_dir.path().join("snapshots");
 
         let mut snap_mgr = SnapshotManager::new(&snapshot_dir).unwrap();
-        let cart = Cartridge::new(100);
+        let _cart = Cartridge::new(100);
         let mut pages = std::collections::HashMap::new();
         pages.insert(0, vec![1, 2, 3]);
 
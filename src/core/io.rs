@@ -1,165 +1,957 @@
 //! Disk I/O operations for cartridge archives
+//!
+//! When a [`PageCipher`] is attached, every page except the header is
+//! encrypted on write and decrypted on read. Encrypted pages occupy a
+//! slightly larger slot on disk (page + nonce + tag); page 0 always holds
+//! the plaintext header at offset 0.
+//!
+//! With page checksums enabled, each slot instead ends in a CRC32 over the
+//! page ID and page data, verified on every read. Encrypted pages don't need
+//! the extra trailer: the GCM tag already authenticates them.
+//!
+//! Between [`CartridgeFile::begin_batch`] and [`CartridgeFile::commit_batch`]
+//! writes are buffered and committed through a sidecar journal
+//! (`<file>-journal`), so a crash leaves either the old or the new state on
+//! disk. [`CartridgeFile::open`] replays a complete journal and discards a
+//! torn one.
+//!
+//! Read-write opens take an exclusive advisory [`FileLock`], released when
+//! the `CartridgeFile` drops. Read-only opens take none.
+//!
+//! [`CartridgeFile::in_memory`] and [`CartridgeFile::from_bytes`] keep the
+//! same layout in a byte buffer instead of a file. Memory images take no
+//! lock and need no journal: a batch is applied in one go on commit.
 
+use crate::encryption::{PageCipher, ENCRYPTION_OVERHEAD};
 use crate::error::{CartridgeError, Result};
 use crate::header::{Header, PAGE_SIZE};
+#[cfg(not(feature = "no-fs"))]
+use crate::lock::FileLock;
 use crate::page::Page;
+use crate::telemetry;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+#[cfg(not(feature = "no-fs"))]
 use std::fs::{File, OpenOptions};
-use std::io::{Read, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 
-/// Disk-backed cartridge storage
+/// Size of the CRC32 trailer on checksummed pages
+pub const CHECKSUM_SIZE: usize = 4;
+
+/// CRC32 over the page ID and page data
+///
+/// Including the page ID catches misdirected writes, not just bit flips.
+fn page_checksum(page_id: u64, data: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&page_id.to_le_bytes());
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Journal file magic
+#[cfg(not(feature = "no-fs"))]
+const JOURNAL_MAGIC: &[u8; 8] = b"CARTJRNL";
+
+/// Path of the sidecar journal for a cartridge file
+#[cfg(not(feature = "no-fs"))]
+pub fn journal_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push("-journal");
+    PathBuf::from(name)
+}
+
+/// A caller passed data that doesn't fit the page layout
+fn invalid_input(message: String) -> CartridgeError {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, message).into()
+}
+
+/// Fsync the directory holding `path`
+///
+/// Creating or removing a file only lasts through a power loss once its
+/// directory is synced. Windows can't open a directory as a file, and NTFS
+/// journals its own metadata, so there this does nothing.
+#[cfg(not(feature = "no-fs"))]
+fn sync_parent_dir(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        File::open(parent)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Where an injected failure interrupts [`CartridgeFile::commit_batch`]
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FailPoint {
+    /// After the journal is durable, before anything touches the main file
+    AfterJournal,
+    /// After half of the journaled writes have been applied
+    MidApply,
+}
+
+/// Where the slots of a [`CartridgeFile`] live
+#[derive(Debug)]
+enum Backing {
+    /// A file on disk
+    #[cfg(not(feature = "no-fs"))]
+    Disk(File),
+    /// An image of the file held in memory
+    Memory(Cursor<Vec<u8>>),
+}
+
+impl Backing {
+    fn set_len(&mut self, len: u64) -> std::io::Result<()> {
+        match self {
+            #[cfg(not(feature = "no-fs"))]
+            Backing::Disk(file) => file.set_len(len),
+            Backing::Memory(image) => {
+                image.get_mut().resize(len as usize, 0);
+                Ok(())
+            }
+        }
+    }
+
+    fn sync_all(&mut self) -> std::io::Result<()> {
+        match self {
+            #[cfg(not(feature = "no-fs"))]
+            Backing::Disk(file) => file.sync_all(),
+            Backing::Memory(_) => Ok(()),
+        }
+    }
+
+    fn len(&self) -> std::io::Result<u64> {
+        match self {
+            #[cfg(not(feature = "no-fs"))]
+            Backing::Disk(file) => Ok(file.metadata()?.len()),
+            Backing::Memory(image) => Ok(image.get_ref().len() as u64),
+        }
+    }
+}
+
+impl Read for Backing {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            #[cfg(not(feature = "no-fs"))]
+            Backing::Disk(file) => file.read(buf),
+            Backing::Memory(image) => image.read(buf),
+        }
+    }
+}
+
+impl Write for Backing {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            #[cfg(not(feature = "no-fs"))]
+            Backing::Disk(file) => file.write(buf),
+            Backing::Memory(image) => image.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            #[cfg(not(feature = "no-fs"))]
+            Backing::Disk(file) => file.flush(),
+            Backing::Memory(_) => Ok(()),
+        }
+    }
+}
+
+impl Seek for Backing {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            #[cfg(not(feature = "no-fs"))]
+            Backing::Disk(file) => file.seek(pos),
+            Backing::Memory(image) => image.seek(pos),
+        }
+    }
+}
+
+/// Cartridge storage, on disk or in memory
 pub struct CartridgeFile {
-    file: File,
+    file: Backing,
     path: std::path::PathBuf,
+    cipher: Option<PageCipher>,
+    checksums: bool,
+    /// Bytes per page, from the header's `block_size`
+    page_size: usize,
+    /// Buffered writes (file offset -> bytes) while a batch is open
+    batch: Option<BTreeMap<u64, Vec<u8>>>,
+    /// `None` for memory images
+    #[cfg(not(feature = "no-fs"))]
+    _lock: Option<FileLock>,
+    /// Whether commits fsync; see [`set_durable`](Self::set_durable)
+    durable: bool,
+    /// Commits written without an fsync since the last [`sync`](Self::sync)
+    unsynced: bool,
+    #[cfg(test)]
+    fail_point: Option<FailPoint>,
+    /// Stands in for the filesystem's free space
+    #[cfg(test)]
+    available_space: Option<u64>,
+    /// fsyncs issued, shared so tests can count past `close`
+    #[cfg(test)]
+    pub(crate) syncs: std::sync::Arc<std::sync::atomic::AtomicUsize>,
 }
 
 impl CartridgeFile {
+    #[cfg(not(feature = "no-fs"))]
     /// Create a new cartridge file
+    ///
+    /// Fails with [`CartridgeError::Locked`] rather than truncating a file
+    /// another handle has open.
     pub fn create<P: AsRef<Path>>(path: P, header: &Header) -> Result<Self> {
         let mut file = OpenOptions::new()
             .read(true)
             .write(true)
             .create(true)
-            .truncate(true)
+            .truncate(false)
             .open(&path)?;
+        let lock = FileLock::acquire(&file, path.as_ref())?;
+        file.set_len(0)?;
 
         // Write header to page 0
         file.write_all(&header.to_bytes())?;
         file.flush()?;
 
         Ok(CartridgeFile {
-            file,
+            file: Backing::Disk(file),
             path: path.as_ref().to_path_buf(),
+            cipher: None,
+            checksums: false,
+            page_size: header.page_size(),
+            batch: None,
+            _lock: Some(lock),
+            durable: true,
+            unsynced: false,
+            #[cfg(test)]
+            fail_point: None,
+            #[cfg(test)]
+            available_space: None,
+            #[cfg(test)]
+            syncs: Default::default(),
         })
     }
 
+    /// Create an in-memory image holding just the header
+    pub fn in_memory(header: &Header) -> Self {
+        let mut file = Self::from_bytes(header.to_bytes());
+        file.set_page_size(header.page_size());
+        file
+    }
+
+    /// Wrap the bytes of a cartridge file, as returned by
+    /// [`to_bytes`](Self::to_bytes), without touching the filesystem
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        CartridgeFile {
+            file: Backing::Memory(Cursor::new(bytes)),
+            path: PathBuf::new(),
+            cipher: None,
+            checksums: false,
+            page_size: PAGE_SIZE,
+            batch: None,
+            #[cfg(not(feature = "no-fs"))]
+            _lock: None,
+            durable: true,
+            unsynced: false,
+            #[cfg(test)]
+            fail_point: None,
+            #[cfg(test)]
+            available_space: None,
+            #[cfg(test)]
+            syncs: Default::default(),
+        }
+    }
+
+    #[cfg(not(feature = "no-fs"))]
     /// Open an existing cartridge file
+    ///
+    /// Replays a committed journal left behind by an interrupted batch.
+    /// Fails with [`CartridgeError::Locked`] if any other handle has it open.
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let file = OpenOptions::new().read(true).write(true).open(&path)?;
+        let mut file = OpenOptions::new().read(true).write(true).open(&path)?;
+        let lock = FileLock::acquire(&file, path.as_ref())?;
+        Self::recover_journal(&mut file, path.as_ref())?;
 
         Ok(CartridgeFile {
-            file,
+            file: Backing::Disk(file),
             path: path.as_ref().to_path_buf(),
+            cipher: None,
+            checksums: false,
+            page_size: PAGE_SIZE,
+            batch: None,
+            _lock: Some(lock),
+            durable: true,
+            unsynced: false,
+            #[cfg(test)]
+            fail_point: None,
+            #[cfg(test)]
+            available_space: None,
+            #[cfg(test)]
+            syncs: Default::default(),
+        })
+    }
+
+    #[cfg(not(feature = "no-fs"))]
+    /// Open a cartridge file, creating an empty one if there is none
+    ///
+    /// The second value is `true` when the file is empty and still needs a
+    /// header. The lock is taken before the length is checked, so of two
+    /// racing callers exactly one sees the empty file; the other fails with
+    /// [`CartridgeError::Locked`] instead of truncating it.
+    pub fn open_or_create<P: AsRef<Path>>(path: P) -> Result<(Self, bool)> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        let lock = FileLock::acquire(&file, path.as_ref())?;
+        let created = file.metadata()?.len() == 0;
+        if !created {
+            Self::recover_journal(&mut file, path.as_ref())?;
+        }
+
+        let cartridge_file = CartridgeFile {
+            file: Backing::Disk(file),
+            path: path.as_ref().to_path_buf(),
+            cipher: None,
+            checksums: false,
+            page_size: PAGE_SIZE,
+            batch: None,
+            _lock: Some(lock),
+            durable: true,
+            unsynced: false,
+            #[cfg(test)]
+            fail_point: None,
+            #[cfg(test)]
+            available_space: None,
+            #[cfg(test)]
+            syncs: Default::default(),
+        };
+        Ok((cartridge_file, created))
+    }
+
+    #[cfg(not(feature = "no-fs"))]
+    /// Open an existing cartridge file without write access
+    ///
+    /// Needs only read permission on the file. A committed journal from an
+    /// interrupted batch can't be replayed into the file, so its records are
+    /// overlaid in memory instead and reads see the committed state. Takes no
+    /// lock, so it works while a writer has the file open.
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = OpenOptions::new().read(true).open(&path)?;
+        let overlay = match std::fs::read(journal_path(path.as_ref())) {
+            Ok(bytes) => decode_journal(&bytes).map(|records| {
+                records
+                    .into_iter()
+                    .map(|(offset, data)| (offset, data.to_vec()))
+                    .collect::<BTreeMap<_, _>>()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(CartridgeFile {
+            file: Backing::Disk(file),
+            path: path.as_ref().to_path_buf(),
+            cipher: None,
+            checksums: false,
+            page_size: PAGE_SIZE,
+            batch: overlay,
+            _lock: None,
+            durable: true,
+            unsynced: false,
+            #[cfg(test)]
+            fail_point: None,
+            #[cfg(test)]
+            available_space: None,
+            #[cfg(test)]
+            syncs: Default::default(),
         })
     }
 
     /// Read the header (page 0)
     pub fn read_header(&mut self) -> Result<Header> {
-        self.file.seek(SeekFrom::Start(0))?;
-        let mut buffer = vec![0u8; PAGE_SIZE];
-        self.file.read_exact(&mut buffer)?;
+        let buffer = self.read_raw(0, PAGE_SIZE)?;
         Header::from_bytes(&buffer)
     }
 
     /// Write the header (page 0)
     pub fn write_header(&mut self, header: &Header) -> Result<()> {
-        self.file.seek(SeekFrom::Start(0))?;
-        self.file.write_all(&header.to_bytes())?;
+        self.write_raw(0, header.to_bytes())
+    }
+
+    /// Read bytes at a file offset, seeing through any open batch
+    fn read_raw(&mut self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        if let Some(data) = self.batch.as_ref().and_then(|b| b.get(&offset)) {
+            if data.len() == len {
+                return Ok(data.clone());
+            }
+        }
+        self.file.seek(SeekFrom::Start(offset))?;
+        let mut buffer = vec![0u8; len];
+        self.file.read_exact(&mut buffer)?;
+        telemetry::disk_bytes_read(len);
+        Ok(buffer)
+    }
+
+    /// Write bytes at a file offset, or buffer them if a batch is open
+    fn write_raw(&mut self, offset: u64, data: Vec<u8>) -> Result<()> {
+        if let Some(batch) = &mut self.batch {
+            batch.insert(offset, data);
+            return Ok(());
+        }
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(&data)?;
         self.file.flush()?;
+        telemetry::disk_bytes_written(data.len());
         Ok(())
     }
 
-    /// Read a page
-    pub fn read_page(&mut self, page_id: u64) -> Result<Page> {
-        let offset = page_id * PAGE_SIZE as u64;
-        self.file.seek(SeekFrom::Start(offset))?;
+    /// Start buffering writes for an atomic commit
+    ///
+    /// Discards any batch left open by an earlier failed commit.
+    pub fn begin_batch(&mut self) {
+        self.batch = Some(BTreeMap::new());
+    }
 
-        let mut buffer = vec![0u8; PAGE_SIZE];
-        self.file.read_exact(&mut buffer)?;
+    /// Drop all writes buffered since [`begin_batch`](Self::begin_batch)
+    pub fn abort_batch(&mut self) {
+        self.batch = None;
+    }
 
-        Page::from_bytes(&buffer)
+    /// Whether a batch is open
+    pub fn in_batch(&self) -> bool {
+        self.batch.is_some()
     }
 
-    /// Write a page
-    pub fn write_page(&mut self, page_id: u64, page: &Page) -> Result<()> {
-        let offset = page_id * PAGE_SIZE as u64;
-        self.file.seek(SeekFrom::Start(offset))?;
+    /// Atomically apply the open batch
+    ///
+    /// The buffered writes are first written to the sidecar journal and
+    /// fsynced, then applied to the main file and fsynced again, and only
+    /// then is the journal removed. The directory is fsynced after the
+    /// journal is created and again after it is removed. Memory images apply
+    /// the writes directly. With [`set_durable(false)`](Self::set_durable)
+    /// all of these fsyncs are skipped.
+    pub fn commit_batch(&mut self) -> Result<()> {
+        let Some(batch) = self.batch.take() else {
+            return Ok(());
+        };
+        if batch.is_empty() {
+            return self.sync_or_defer();
+        }
+        #[cfg(not(feature = "no-fs"))]
+        if !self.is_in_memory() {
+            return self.commit_journaled(batch);
+        }
+        let records: Vec<_> = batch.iter().collect();
+        self.apply_records(&records)
+    }
 
-        self.file.write_all(&page.to_bytes())?;
-        self.file.flush()?;
+    /// Commit a batch to disk through the sidecar journal
+    #[cfg(not(feature = "no-fs"))]
+    fn commit_journaled(&mut self, batch: BTreeMap<u64, Vec<u8>>) -> Result<()> {
+        let journal = journal_path(&self.path);
+        let mut out = File::create(&journal)?;
+        out.write_all(&encode_journal(&batch))?;
+        if self.durable {
+            out.sync_all()?;
+            sync_parent_dir(&journal)?;
+            self.count_sync();
+        }
+        drop(out);
+
+        #[cfg(test)]
+        if self.fail_point == Some(FailPoint::AfterJournal) {
+            return Err(injected_failure());
+        }
+
+        let records: Vec<_> = batch.iter().collect();
+        let (first, second) = records.split_at(records.len() / 2);
+        self.apply_records(first)?;
+
+        #[cfg(test)]
+        if self.fail_point == Some(FailPoint::MidApply) {
+            return Err(injected_failure());
+        }
+
+        self.apply_records(second)?;
+        self.sync_or_defer()?;
+        std::fs::remove_file(&journal)?;
+        // A journal that came back after a power loss would be replayed over
+        // later commits
+        if self.durable {
+            sync_parent_dir(&journal)?;
+            self.count_sync();
+        }
+        Ok(())
+    }
+
+    fn apply_records(&mut self, records: &[(&u64, &Vec<u8>)]) -> Result<()> {
+        for &(&offset, data) in records {
+            self.file.seek(SeekFrom::Start(offset))?;
+            self.file.write_all(data)?;
+            telemetry::disk_bytes_written(data.len());
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "no-fs"))]
+    /// Replay or discard a journal left by an interrupted commit
+    ///
+    /// A journal that fails validation was torn before it became durable, so
+    /// the main file was never touched and still holds the old state.
+    fn recover_journal(file: &mut File, path: &Path) -> Result<()> {
+        let journal = journal_path(path);
+        let bytes = match std::fs::read(&journal) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
 
+        match decode_journal(&bytes) {
+            Some(records) => {
+                tracing::info!(
+                    "Replaying {} journaled writes into {}",
+                    records.len(),
+                    path.display()
+                );
+                for (offset, data) in records {
+                    file.seek(SeekFrom::Start(offset))?;
+                    file.write_all(data)?;
+                }
+                file.sync_all()?;
+            }
+            None => tracing::warn!("Discarding incomplete journal {}", journal.display()),
+        }
+
+        std::fs::remove_file(&journal)?;
+        sync_parent_dir(&journal)?;
         Ok(())
     }
 
+    #[cfg(test)]
+    pub(crate) fn set_fail_point(&mut self, point: Option<FailPoint>) {
+        self.fail_point = point;
+    }
+
+    #[cfg(test)]
+    pub(crate) fn set_available_space(&mut self, bytes: Option<u64>) {
+        self.available_space = bytes;
+    }
+
+    /// Attach a page cipher for at-rest encryption
+    ///
+    /// Must be set before any page other than the header is read or written.
+    pub fn set_cipher(&mut self, cipher: PageCipher) {
+        self.cipher = Some(cipher);
+    }
+
+    /// Whether pages are encrypted at rest
+    pub fn is_encrypted(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    /// The attached page cipher, if any
+    pub fn cipher(&self) -> Option<&PageCipher> {
+        self.cipher.as_ref()
+    }
+
+    /// Enable CRC32 page checksums
+    ///
+    /// Must be set before any page other than the header is read or written.
+    pub fn set_checksums(&mut self, enabled: bool) {
+        self.checksums = enabled;
+    }
+
+    /// Set the page size, in bytes, from the header's `block_size`
+    ///
+    /// Must be set before any page other than the header is read or written.
+    /// The header itself always takes the first [`PAGE_SIZE`] bytes.
+    pub fn set_page_size(&mut self, bytes: usize) {
+        self.page_size = bytes;
+    }
+
+    /// Bytes per page, before any checksum or encryption overhead
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Whether pages carry a CRC32 trailer
+    pub fn has_checksums(&self) -> bool {
+        self.checksums && self.cipher.is_none()
+    }
+
+    /// Whether `write_at` can patch bytes in place
+    ///
+    /// False when pages are encrypted or checksummed, since any change
+    /// requires re-sealing the whole page, and while a batch is open, since
+    /// batches buffer whole slots.
+    pub fn patches_in_place(&self) -> bool {
+        self.cipher.is_none() && !self.checksums && self.batch.is_none()
+    }
+
+    /// Size of one page slot on disk
+    pub fn slot_size(&self) -> usize {
+        if self.cipher.is_some() {
+            self.page_size + ENCRYPTION_OVERHEAD
+        } else if self.checksums {
+            self.page_size + CHECKSUM_SIZE
+        } else {
+            self.page_size
+        }
+    }
+
+    /// Read a page
+    pub fn read_page(&mut self, page_id: u64) -> Result<Page> {
+        let buffer = self.read_page_data(page_id)?;
+        Page::from_bytes(&buffer)
+    }
+
+    /// Write a page
+    pub fn write_page(&mut self, page_id: u64, page: &Page) -> Result<()> {
+        self.write_page_data(page_id, &page.to_bytes())
+    }
+
     /// Read raw page data (for content blocks)
     pub fn read_page_data(&mut self, page_id: u64) -> Result<Vec<u8>> {
-        let offset = page_id * PAGE_SIZE as u64;
-        self.file.seek(SeekFrom::Start(offset))?;
+        let slot_size = self.slot_size();
+        let offset = page_id * slot_size as u64;
+        let mut buffer = self.read_raw(offset, slot_size)?;
 
-        let mut buffer = vec![0u8; PAGE_SIZE];
-        self.file.read_exact(&mut buffer)?;
+        if let Some(cipher) = &self.cipher {
+            return cipher.decrypt_page(page_id, &buffer).map_err(|_| {
+                CartridgeError::ChecksumMismatch {
+                    page: page_id,
+                    path: None,
+                }
+            });
+        }
+
+        if self.checksums {
+            let page_size = self.page_size;
+            let stored = u32::from_le_bytes([
+                buffer[page_size],
+                buffer[page_size + 1],
+                buffer[page_size + 2],
+                buffer[page_size + 3],
+            ]);
+            buffer.truncate(page_size);
+            // Slots added by `extend` are all zeroes until first written
+            let never_written = stored == 0 && buffer.iter().all(|&b| b == 0);
+            if !never_written && page_checksum(page_id, &buffer) != stored {
+                return Err(CartridgeError::ChecksumMismatch {
+                    page: page_id,
+                    path: None,
+                });
+            }
+        }
 
         Ok(buffer)
     }
 
     /// Write raw page data (for content blocks)
     pub fn write_page_data(&mut self, page_id: u64, data: &[u8]) -> Result<()> {
-        if data.len() != PAGE_SIZE {
-            return Err(CartridgeError::Allocation(format!(
+        if data.len() != self.page_size {
+            return Err(invalid_input(format!(
                 "Page data must be exactly {} bytes, got {}",
-                PAGE_SIZE,
+                self.page_size,
                 data.len()
             )));
         }
 
-        let offset = page_id * PAGE_SIZE as u64;
-        self.file.seek(SeekFrom::Start(offset))?;
+        let offset = page_id * self.slot_size() as u64;
+        let slot = self.encode_slot(page_id, data)?;
+        self.write_raw(offset, slot)
+    }
 
-        self.file.write_all(data)?;
-        self.file.flush()?;
+    /// Write a run of consecutive pages starting at `start_page` in one call
+    ///
+    /// `data` must be a whole number of pages. Outside a batch the run goes
+    /// to disk as a single positioned write instead of one per page.
+    pub fn write_pages_contiguous(&mut self, start_page: u64, data: &[u8]) -> Result<()> {
+        let page_size = self.page_size;
+        if !data.len().is_multiple_of(page_size) {
+            return Err(invalid_input(format!(
+                "Page run must be a multiple of {} bytes, got {}",
+                page_size,
+                data.len()
+            )));
+        }
 
-        Ok(())
+        // Batches are keyed per slot so reads can see through them
+        if self.batch.is_some() {
+            for (page_id, page) in (start_page..).zip(data.chunks(page_size)) {
+                self.write_page_data(page_id, page)?;
+            }
+            return Ok(());
+        }
+
+        let mut run = Vec::with_capacity(data.len() / page_size * self.slot_size());
+        for (page_id, page) in (start_page..).zip(data.chunks(page_size)) {
+            run.extend_from_slice(&self.encode_slot(page_id, page)?);
+        }
+        self.write_raw(start_page * self.slot_size() as u64, run)
     }
 
-    /// Get file path
+    /// On-disk slot for a page: encrypted, checksummed or raw
+    fn encode_slot(&self, page_id: u64, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(if let Some(cipher) = &self.cipher {
+            cipher.encrypt_page(page_id, data)?
+        } else if self.checksums {
+            let mut slot = Vec::with_capacity(data.len() + CHECKSUM_SIZE);
+            slot.extend_from_slice(data);
+            slot.extend_from_slice(&page_checksum(page_id, data).to_le_bytes());
+            slot
+        } else {
+            data.to_vec()
+        })
+    }
+
+    /// Get file path (empty for memory images)
     pub fn path(&self) -> &Path {
         &self.path
     }
 
+    /// Whether this is a memory image rather than a file on disk
+    pub fn is_in_memory(&self) -> bool {
+        matches!(self.file, Backing::Memory(_))
+    }
+
+    /// Current size of the file or image in bytes
+    pub fn size_bytes(&self) -> Result<u64> {
+        Ok(self.file.len()?)
+    }
+
+    /// The complete contents, as they would be read back
+    ///
+    /// Includes writes still buffered in an open batch.
+    pub fn to_bytes(&mut self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.read_to_end(&mut bytes)?;
+        for (&offset, data) in self.batch.iter().flatten() {
+            let end = offset as usize + data.len();
+            if bytes.len() < end {
+                bytes.resize(end, 0);
+            }
+            bytes[offset as usize..end].copy_from_slice(data);
+        }
+        Ok(bytes)
+    }
+
+    /// SHA-256 of the complete contents, read in chunks
+    ///
+    /// Includes writes still buffered in an open batch, like
+    /// [`to_bytes`](Self::to_bytes).
+    pub fn sha256(&mut self) -> Result<[u8; 32]> {
+        if self.batch.is_some() {
+            return Ok(Sha256::digest(self.to_bytes()?).into());
+        }
+        let mut hasher = Sha256::new();
+        let mut chunk = vec![0u8; 64 * 1024];
+        self.file.seek(SeekFrom::Start(0))?;
+        loop {
+            let n = self.file.read(&mut chunk)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&chunk[..n]);
+        }
+        Ok(hasher.finalize().into())
+    }
+
     /// Sync all writes to disk
+    ///
+    /// Always issues the fsync, whatever [`set_durable`](Self::set_durable)
+    /// says.
     pub fn sync(&mut self) -> Result<()> {
         self.file.sync_all()?;
+        self.unsynced = false;
+        self.count_sync();
         Ok(())
     }
 
+    /// Fsync if commits are durable, otherwise note the writes as unsynced
+    pub fn sync_or_defer(&mut self) -> Result<()> {
+        if self.durable {
+            self.sync()
+        } else {
+            self.unsynced = true;
+            Ok(())
+        }
+    }
+
+    /// Choose whether commits fsync (the default) or leave that to [`sync`]
+    ///
+    /// Without the fsyncs a commit still goes through the journal, so a
+    /// process crash loses nothing; a power loss can drop every commit since
+    /// the last fsync, or leave the file torn so that opening it reports
+    /// [`CartridgeError::TornWrite`].
+    ///
+    /// [`sync`]: Self::sync
+    pub fn set_durable(&mut self, durable: bool) {
+        self.durable = durable;
+    }
+
+    /// Whether commits have been written since the last fsync
+    pub fn needs_sync(&self) -> bool {
+        self.unsynced
+    }
+
+    #[inline]
+    fn count_sync(&self) {
+        #[cfg(test)]
+        self.syncs.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
     /// Extend file to new block count
     ///
     /// Used by auto-growth to expand the container size.
     pub fn extend(&mut self, new_total_blocks: usize) -> Result<()> {
-        let new_size = new_total_blocks * PAGE_SIZE;
+        let new_size = new_total_blocks * self.slot_size();
         self.file.set_len(new_size as u64)?;
         Ok(())
     }
 
-    /// Shrink file to new block count (truncate).
+    /// Bytes the filesystem holding the file still has free for it
+    ///
+    /// `None` for memory images and on platforms without a way to ask.
+    pub fn available_space(&self) -> Option<u64> {
+        #[cfg(test)]
+        if self.available_space.is_some() {
+            return self.available_space;
+        }
+        match &self.file {
+            #[cfg(not(feature = "no-fs"))]
+            Backing::Disk(file) => disk_space_available(file),
+            Backing::Memory(_) => None,
+        }
+    }
+
+    /// Truncate the file to `blocks` pages and sync.
     ///
     /// Used by vacuum to reclaim disk space after compaction.
-    pub fn shrink(&mut self, new_total_blocks: usize) -> Result<()> {
-        let new_size = new_total_blocks * PAGE_SIZE;
+    pub fn truncate(&mut self, blocks: usize) -> Result<()> {
+        let new_size = blocks * self.slot_size();
         self.file.set_len(new_size as u64)?;
+        self.file.sync_all()?;
         Ok(())
     }
 
+    /// Shrink file to new block count (truncate).
+    #[deprecated(note = "use `truncate`")]
+    pub fn shrink(&mut self, new_total_blocks: usize) -> Result<()> {
+        self.truncate(new_total_blocks)
+    }
+
     /// Write a partial page — bytes at an arbitrary offset within a page.
     ///
     /// Used by the WAL to overwrite individual entries without rewriting
-    /// the full 4KB page. The caller is responsible for fsyncing afterward.
+    /// the full page. The caller is responsible for fsyncing afterward.
+    ///
+    /// Encrypted or checksummed pages can't be patched in place, so for
+    /// those this becomes a read-modify-write of the whole page.
     pub fn write_at(&mut self, page_id: u64, offset_in_page: usize, data: &[u8]) -> Result<()> {
-        if offset_in_page + data.len() > PAGE_SIZE {
-            return Err(CartridgeError::Allocation(format!(
+        if offset_in_page + data.len() > self.page_size {
+            return Err(invalid_input(format!(
                 "write_at: offset {} + len {} exceeds page size {}",
                 offset_in_page,
                 data.len(),
-                PAGE_SIZE
+                self.page_size
             )));
         }
-        let file_offset = page_id * PAGE_SIZE as u64 + offset_in_page as u64;
+        if !self.patches_in_place() {
+            let mut page = self.read_page_data(page_id)?;
+            page[offset_in_page..offset_in_page + data.len()].copy_from_slice(data);
+            return self.write_page_data(page_id, &page);
+        }
+
+        let file_offset = page_id * self.slot_size() as u64 + offset_in_page as u64;
         self.file.seek(SeekFrom::Start(file_offset))?;
         self.file.write_all(data)?;
         Ok(())
     }
 }
 
+/// Free bytes an unprivileged writer may use on `file`'s filesystem
+#[cfg(all(unix, not(feature = "no-fs")))]
+fn disk_space_available(file: &File) -> Option<u64> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: the descriptor is open for as long as `file` is borrowed, and
+    // fstatvfs only writes into `stat`
+    if unsafe { libc::fstatvfs(file.as_raw_fd(), stat.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    // SAFETY: fstatvfs succeeded, so it filled `stat` in
+    let stat = unsafe { stat.assume_init() };
+    #[allow(clippy::unnecessary_cast)] // The field types differ between platforms
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(all(not(unix), not(feature = "no-fs")))]
+fn disk_space_available(_file: &File) -> Option<u64> {
+    None
+}
+
+#[cfg(not(feature = "no-fs"))]
+/// Serialize journal records
+///
+/// Layout: `[magic: 8][count: u32]` then per record
+/// `[offset: u64][len: u32][bytes]`, then a CRC32 of everything before it.
+fn encode_journal(records: &BTreeMap<u64, Vec<u8>>) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(JOURNAL_MAGIC);
+    out.extend_from_slice(&(records.len() as u32).to_le_bytes());
+    for (&offset, data) in records {
+        out.extend_from_slice(&offset.to_le_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(data);
+    }
+    let crc = crc32fast::hash(&out);
+    out.extend_from_slice(&crc.to_le_bytes());
+    out
+}
+
+#[cfg(not(feature = "no-fs"))]
+/// Parse journal records, or `None` if the journal is torn or corrupt
+fn decode_journal(bytes: &[u8]) -> Option<Vec<(u64, &[u8])>> {
+    let body_len = bytes.len().checked_sub(4)?;
+    let (body, crc) = bytes.split_at(body_len);
+    if crc32fast::hash(body).to_le_bytes() != crc || !body.starts_with(JOURNAL_MAGIC) {
+        return None;
+    }
+
+    let mut pos = JOURNAL_MAGIC.len();
+    let mut take = |n: usize| {
+        let slice = body.get(pos..pos + n)?;
+        pos += n;
+        Some(slice)
+    };
+    let count = u32::from_le_bytes(take(4)?.try_into().ok()?);
+    let mut records = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let offset = u64::from_le_bytes(take(8)?.try_into().ok()?);
+        let len = u32::from_le_bytes(take(4)?.try_into().ok()?) as usize;
+        records.push((offset, take(len)?));
+    }
+    Some(records)
+}
+
+#[cfg(test)]
+fn injected_failure() -> CartridgeError {
+    CartridgeError::Io(std::io::Error::other("injected failure"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -200,6 +992,40 @@ mod tests {
         assert_eq!(&read_data[0..5], b"Hello");
     }
 
+    #[test]
+    fn test_write_pages_contiguous() {
+        let temp = NamedTempFile::new().unwrap();
+        let mut cart_file = CartridgeFile::create(temp.path(), &Header::new()).unwrap();
+        cart_file.set_checksums(true);
+
+        let run: Vec<u8> = (1..=3u8).flat_map(|i| vec![i; PAGE_SIZE]).collect();
+        cart_file.write_pages_contiguous(4, &run).unwrap();
+        for (page_id, fill) in [(4, 1u8), (5, 2), (6, 3)] {
+            assert_eq!(cart_file.read_page_data(page_id).unwrap(), vec![fill; PAGE_SIZE]);
+        }
+
+        // Inside a batch the run is buffered page by page and still readable
+        cart_file.begin_batch();
+        cart_file.write_pages_contiguous(5, &vec![9u8; PAGE_SIZE * 2]).unwrap();
+        assert_eq!(cart_file.read_page_data(6).unwrap(), vec![9u8; PAGE_SIZE]);
+        cart_file.abort_batch();
+
+        assert!(cart_file.write_pages_contiguous(1, &[0u8; 10]).is_err());
+    }
+
+    #[test]
+    fn test_misshapen_writes_are_invalid_input() {
+        let temp = NamedTempFile::new().unwrap();
+        let mut cart_file = CartridgeFile::create(temp.path(), &Header::new()).unwrap();
+        let invalid_input = |result: Result<()>| match result {
+            Err(CartridgeError::Io(e)) => e.kind() == std::io::ErrorKind::InvalidInput,
+            _ => false,
+        };
+        assert!(invalid_input(cart_file.write_page_data(1, &[0u8; 10])));
+        assert!(invalid_input(cart_file.write_pages_contiguous(1, &[0u8; 10])));
+        assert!(invalid_input(cart_file.write_at(1, PAGE_SIZE - 2, &[0u8; 4])));
+    }
+
     #[test]
     fn test_open_existing() {
         let temp = NamedTempFile::new().unwrap();
@@ -216,4 +1042,106 @@ mod tests {
         let header = cart_file.read_header().unwrap();
         assert_eq!(header.total_blocks, 999);
     }
+
+    #[test]
+    fn test_memory_image_round_trip() {
+        let mut header = Header::new();
+        header.total_blocks = 4;
+        let mut image = CartridgeFile::in_memory(&header);
+        image.set_checksums(true);
+        image.extend(4).unwrap();
+
+        image.write_page_data(1, &vec![1u8; PAGE_SIZE]).unwrap();
+        image.begin_batch();
+        image.write_page_data(3, &vec![3u8; PAGE_SIZE]).unwrap();
+        // Buffered writes are part of the bytes even before the commit
+        let pending = image.to_bytes().unwrap();
+        image.commit_batch().unwrap();
+        let bytes = image.to_bytes().unwrap();
+        assert_eq!(pending, bytes);
+        assert_eq!(bytes.len(), 4 * image.slot_size());
+
+        let mut loaded = CartridgeFile::from_bytes(bytes);
+        loaded.set_checksums(true);
+        assert!(loaded.is_in_memory());
+        assert_eq!(loaded.read_header().unwrap().total_blocks, 4);
+        assert_eq!(loaded.read_page_data(3).unwrap(), vec![3u8; PAGE_SIZE]);
+    }
+
+    #[test]
+    fn test_encrypted_page_round_trip() {
+        use crate::encryption::EncryptionConfig;
+
+        let temp = NamedTempFile::new().unwrap();
+        let path = temp.path();
+
+        let header = Header::new();
+        let mut cart_file = CartridgeFile::create(path, &header).unwrap();
+        cart_file.set_cipher(PageCipher::new(&EncryptionConfig::generate_key()));
+
+        let mut data = vec![0u8; PAGE_SIZE];
+        data[0..5].copy_from_slice(b"Hello");
+        cart_file.write_page_data(1, &data).unwrap();
+        cart_file.write_at(1, 5, b" world").unwrap();
+
+        let read_data = cart_file.read_page_data(1).unwrap();
+        assert_eq!(&read_data[0..11], b"Hello world");
+
+        // Plaintext must not appear in the backing file
+        let raw = std::fs::read(path).unwrap();
+        assert!(!raw.windows(5).any(|w| w == b"Hello"));
+
+        // Header stays readable without the key
+        assert!(cart_file.read_header().is_ok());
+    }
+
+    #[test]
+    fn test_checksummed_page_detects_bit_flip() {
+        let temp = NamedTempFile::new().unwrap();
+        let path = temp.path();
+
+        let header = Header::new();
+        let mut cart_file = CartridgeFile::create(path, &header).unwrap();
+        cart_file.set_checksums(true);
+
+        let data = vec![0x5Au8; PAGE_SIZE];
+        cart_file.write_page_data(1, &data).unwrap();
+        assert_eq!(cart_file.read_page_data(1).unwrap(), data);
+
+        // Flip one bit in the middle of page 1
+        let offset = cart_file.slot_size() as u64 + 100;
+        cart_file.file.seek(SeekFrom::Start(offset)).unwrap();
+        cart_file.file.write_all(&[0x5B]).unwrap();
+
+        assert!(matches!(
+            cart_file.read_page_data(1),
+            Err(CartridgeError::ChecksumMismatch { page: 1, path: None })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_journaled_commit_syncs_directory() {
+        use std::sync::atomic::Ordering;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("journaled.cart");
+        let mut cart_file = CartridgeFile::create(&path, &Header::new()).unwrap();
+        let syncs = cart_file.syncs.clone();
+
+        // Journal and its directory entry, main file, journal removal
+        cart_file.begin_batch();
+        cart_file.write_page_data(1, &vec![1u8; PAGE_SIZE]).unwrap();
+        let before = syncs.load(Ordering::Relaxed);
+        cart_file.commit_batch().unwrap();
+        assert_eq!(syncs.load(Ordering::Relaxed) - before, 3);
+        assert!(!journal_path(&path).exists());
+
+        cart_file.set_durable(false);
+        cart_file.begin_batch();
+        cart_file.write_page_data(1, &vec![2u8; PAGE_SIZE]).unwrap();
+        let before = syncs.load(Ordering::Relaxed);
+        cart_file.commit_batch().unwrap();
+        assert_eq!(syncs.load(Ordering::Relaxed), before);
+    }
 }
@@ -0,0 +1,97 @@
+//! Advisory locks on cartridge files
+//!
+//! Read-write handles hold an exclusive lock on the backing file, so two
+//! writers can't have the same file open at once and clobber each other's
+//! catalog. Read-only handles take no lock: they never write, so they can
+//! open a file a writer has open and see it as of their last refresh. The
+//! OS lock (`flock` on Unix, `LockFileEx` on Windows) belongs to the open
+//! file and goes away with it, including when the process dies.
+//!
+//! Locks are advisory: they only stop other handles that also take them,
+//! i.e. other cartridges. A conflict is reported as
+//! [`CartridgeError::Locked`]. The holder's PID is known only when the
+//! conflicting handle lives in the same process.
+
+use crate::error::{CartridgeError, Result};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fs::{File, TryLockError};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// Files this process holds a lock on, with the number of handles
+static HELD: LazyLock<Mutex<HashMap<PathBuf, usize>>> = LazyLock::new(Default::default);
+
+/// Lock held on an open cartridge file; released when dropped with the file
+#[derive(Debug)]
+pub(crate) struct FileLock {
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Take an exclusive lock without waiting
+    pub(crate) fn acquire(file: &File, path: &Path) -> Result<Self> {
+        let attempt = file.try_lock();
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        match attempt {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let holder_pid = HELD.lock().contains_key(&path).then(std::process::id);
+                return Err(CartridgeError::Locked {
+                    path: path.display().to_string(),
+                    holder_pid,
+                });
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+
+        *HELD.lock().entry(path.clone()).or_default() += 1;
+        Ok(FileLock { path })
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let mut held = HELD.lock();
+        if let Some(count) = held.get_mut(&self.path) {
+            *count -= 1;
+            if *count == 0 {
+                held.remove(&self.path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exclusive_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("locked.cart");
+        let open = || {
+            File::options()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)
+                .unwrap()
+        };
+
+        let (first, second) = (open(), open());
+        let lock = FileLock::acquire(&first, &path).unwrap();
+        match FileLock::acquire(&second, &path) {
+            Err(CartridgeError::Locked { holder_pid, .. }) => {
+                assert_eq!(holder_pid, Some(std::process::id()))
+            }
+            other => panic!("expected Locked, got {:?}", other),
+        }
+
+        // Released with the file
+        drop(lock);
+        drop(first);
+        let _lock = FileLock::acquire(&second, &path).unwrap();
+    }
+}
@@ -4,12 +4,89 @@
 //! The manifest distinguishes between:
 //! - **Slug**: Kebab-case identifier (filename, registry key, canonical reference)
 //! - **Title**: Human-readable display name
+//!
+//! Dependencies on other cartridges are semver requirements on their slug,
+//! checked against a set of installed manifests with [`check_dependencies`].
+//!
+//! Top-level fields this version doesn't know are kept in
+//! [`Manifest::extra`] and written back unchanged, so rewriting a manifest
+//! from newer code doesn't lose anything. `schema_version` records the
+//! layout; [`Manifest::migrate`] upgrades older ones when they are read.
 
-use crate::error::Result;
+use crate::error::{CartridgeError, Result};
+use crate::retention::RetentionPolicy;
 use crate::validation::ContainerSlug;
-use semver::Version;
+use semver::{Version, VersionReq};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+/// Top-level keys with a field of their own, which can't be used as extras
+const FIELDS: &[&str] = &[
+    "schema_version",
+    "slug",
+    "title",
+    "version",
+    "description",
+    "author",
+    "license",
+    "created",
+    "repository",
+    "dependencies",
+    "capabilities",
+    "quotas",
+    "snapshot_retention",
+    "metadata",
+];
+
+/// A requirement on another cartridge
+///
+/// Serialized as `{ "slug": "base-assets", "req": ">=1.2" }`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dependency {
+    /// Slug of the required cartridge
+    #[serde(
+        serialize_with = "serialize_slug",
+        deserialize_with = "deserialize_slug"
+    )]
+    pub slug: ContainerSlug,
+
+    /// Versions of it that satisfy the requirement
+    pub req: VersionReq,
+}
+
+impl Dependency {
+    /// Parse a dependency from a slug and a requirement such as `">=1.2"`
+    pub fn new(slug: impl Into<String>, req: &str) -> Result<Self> {
+        Ok(Dependency {
+            slug: ContainerSlug::new(slug)?,
+            req: VersionReq::parse(req)
+                .map_err(|e| CartridgeError::InvalidVersion(format!("{}: {}", req, e)))?,
+        })
+    }
+}
+
+/// A dependency not satisfied by the installed cartridges
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnmetDependency {
+    /// Slug of the cartridge that declares the dependency
+    pub dependent: ContainerSlug,
+    /// The requirement that isn't met
+    pub dependency: Dependency,
+    /// Highest installed version with the required slug, if any
+    pub installed: Option<Version>,
+}
+
+/// Which part of a version [`Manifest::bump_version`] increments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bump {
+    /// `1.2.3` -> `2.0.0`
+    Major,
+    /// `1.2.3` -> `1.3.0`
+    Minor,
+    /// `1.2.3` -> `1.2.4`
+    Patch,
+}
 
 /// Cartridge container manifest
 ///
@@ -28,7 +105,7 @@ use std::collections::HashMap;
 /// # Examples
 ///
 /// ```
-/// use cartridge_core::manifest::Manifest;
+/// use cartridge_rs::core::manifest::Manifest;
 /// use semver::Version;
 ///
 /// let manifest = Manifest::new(
@@ -42,6 +119,13 @@ use std::collections::HashMap;
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Manifest {
+    /// Layout of this manifest, [`Manifest::SCHEMA_VERSION`] when written by
+    /// this version
+    ///
+    /// Manifests from before it was recorded read as 1.
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32,
+
     /// Container slug (kebab-case identifier)
     ///
     /// Used for filenames, registry keys, and canonical references.
@@ -95,11 +179,16 @@ pub struct Manifest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub repository: Option<String>,
 
-    /// Dependencies (slug -> version requirement)
+    /// Other cartridges this one requires
     ///
-    /// Example: { "other-container": "^1.0.0" }
-    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
-    pub dependencies: HashMap<String, String>,
+    /// Example: [{ "slug": "base-assets", "req": ">=1.2" }]. The older
+    /// `{ "slug": "req" }` map form is still accepted when reading.
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "deserialize_dependencies"
+    )]
+    pub dependencies: Vec<Dependency>,
 
     /// IAM capabilities
     ///
@@ -107,14 +196,37 @@ pub struct Manifest {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub capabilities: Vec<String>,
 
+    /// Byte quotas (path prefix -> limit), enforced on every write
+    ///
+    /// Example: { "users/alice": 10485760 }
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub quotas: BTreeMap<String, u64>,
+
+    /// Which snapshots to keep, applied by
+    /// [`Cartridge::apply_snapshot_retention`](crate::Cartridge::apply_snapshot_retention)
+    ///
+    /// Example: { "keep_last": 3, "keep_daily": 7, "min_age_secs": 3600 }
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot_retention: Option<RetentionPolicy>,
+
     /// Custom metadata
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub metadata: HashMap<String, serde_json::Value>,
+
+    /// Top-level fields without a field of their own
+    ///
+    /// Holds third-party data set with [`set_extra`](Self::set_extra), and
+    /// fields added by newer versions, which are written back as they were.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl Manifest {
     /// Manifest file path inside container
-    pub const PATH: &'static str = ".cartridge/manifest.json";
+    pub const PATH: &'static str = "/.cartridge/manifest.json";
+
+    /// Schema version written by this version of the crate
+    pub const SCHEMA_VERSION: u32 = 1;
 
     /// Create a new manifest with required fields
     ///
@@ -131,7 +243,7 @@ impl Manifest {
     /// # Examples
     ///
     /// ```
-    /// use cartridge_core::manifest::Manifest;
+    /// use cartridge_rs::core::manifest::Manifest;
     /// use semver::Version;
     ///
     /// let manifest = Manifest::new(
@@ -146,9 +258,10 @@ impl Manifest {
         version: Version,
     ) -> Result<Self> {
         let slug = ContainerSlug::new(slug)?;
-        let now = chrono::Utc::now().to_rfc3339();
+        let now = chrono::DateTime::<chrono::Utc>::from(crate::clock::now()).to_rfc3339();
 
         Ok(Self {
+            schema_version: Self::SCHEMA_VERSION,
             slug,
             title: title.into(),
             version,
@@ -157,25 +270,86 @@ impl Manifest {
             license: None,
             created: Some(now),
             repository: None,
-            dependencies: HashMap::new(),
+            dependencies: Vec::new(),
             capabilities: Vec::new(),
+            quotas: BTreeMap::new(),
+            snapshot_retention: None,
             metadata: HashMap::new(),
+            extra: serde_json::Map::new(),
         })
     }
 
+    /// Upgrade a manifest with an older `schema_version` to the current one
+    ///
+    /// Run on every manifest read; a read-write open saves the result.
+    /// Returns whether anything changed. Manifests from newer versions are
+    /// left alone.
+    pub fn migrate(&mut self) -> bool {
+        if self.schema_version >= Self::SCHEMA_VERSION {
+            return false;
+        }
+        // Schema 0 only differs in not recording its version. Later schema
+        // changes add a step here for each version they replace.
+        self.schema_version = Self::SCHEMA_VERSION;
+        true
+    }
+
+    /// Deserialize the extra field `key`, or `None` if it isn't set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cartridge_rs::core::manifest::Manifest;
+    /// use semver::Version;
+    ///
+    /// let mut manifest = Manifest::new("assets", "Assets", Version::new(1, 0, 0)).unwrap();
+    /// manifest.set_extra("pipeline", &vec!["resize", "compress"]).unwrap();
+    ///
+    /// let steps: Option<Vec<String>> = manifest.get_extra("pipeline").unwrap();
+    /// assert_eq!(steps.unwrap(), ["resize", "compress"]);
+    /// ```
+    pub fn get_extra<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        match self.extra.get(key) {
+            Some(value) => Ok(Some(T::deserialize(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Set the extra field `key` to `value`, replacing any previous value
+    ///
+    /// Fails with [`CartridgeError::ManifestValidation`] if `key` is one of
+    /// the manifest's own fields.
+    pub fn set_extra<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<()> {
+        if FIELDS.contains(&key) {
+            return Err(CartridgeError::ManifestValidation(format!(
+                "{} is a manifest field, not an extra",
+                key
+            )));
+        }
+        self.extra.insert(key.to_string(), serde_json::to_value(value)?);
+        Ok(())
+    }
+
     /// Validate all fields
     ///
-    /// Checks:
-    /// - Slug is valid kebab-case
-    /// - Dependencies have valid slugs
-    /// - Dependencies have valid version requirements
+    /// Slugs and version requirements are validated when parsed, so this
+    /// checks what they can't:
+    /// - No dependency on the container itself
+    /// - No slug listed twice in dependencies
     pub fn validate(&self) -> Result<()> {
-        // Slug is validated in ContainerSlug::new()
-
-        // Validate dependency slugs
-        for (dep_slug, _dep_version) in &self.dependencies {
-            ContainerSlug::new(dep_slug)?;
-            // TODO: Validate version requirement syntax
+        for (i, dep) in self.dependencies.iter().enumerate() {
+            if dep.slug == self.slug {
+                return Err(CartridgeError::ManifestValidation(format!(
+                    "{} depends on itself",
+                    self.slug
+                )));
+            }
+            if self.dependencies[..i].iter().any(|d| d.slug == dep.slug) {
+                return Err(CartridgeError::ManifestValidation(format!(
+                    "duplicate dependency: {}",
+                    dep.slug
+                )));
+            }
         }
 
         Ok(())
@@ -206,17 +380,34 @@ impl Manifest {
     }
 
     /// Add a dependency
-    pub fn add_dependency(
-        mut self,
-        slug: impl Into<String>,
-        version_req: impl Into<String>,
-    ) -> Result<Self> {
-        let slug = slug.into();
-        ContainerSlug::new(&slug)?; // Validate dependency slug
-        self.dependencies.insert(slug, version_req.into());
+    ///
+    /// Replaces any existing requirement on the same slug.
+    pub fn add_dependency(mut self, slug: impl Into<String>, version_req: &str) -> Result<Self> {
+        self.set_dependency(Dependency::new(slug, version_req)?);
         Ok(self)
     }
 
+    /// Add `dependency`, replacing any existing requirement on its slug
+    pub fn set_dependency(&mut self, dependency: Dependency) {
+        match self.dependencies.iter_mut().find(|d| d.slug == dependency.slug) {
+            Some(existing) => *existing = dependency,
+            None => self.dependencies.push(dependency),
+        }
+    }
+
+    /// Increment the version, resetting the lower parts
+    ///
+    /// Pre-release and build identifiers are dropped.
+    pub fn bump_version(&mut self, bump: Bump) -> &Version {
+        let v = &self.version;
+        self.version = match bump {
+            Bump::Major => Version::new(v.major + 1, 0, 0),
+            Bump::Minor => Version::new(v.major, v.minor + 1, 0),
+            Bump::Patch => Version::new(v.major, v.minor, v.patch + 1),
+        };
+        &self.version
+    }
+
     /// Add a capability
     pub fn add_capability(mut self, capability: impl Into<String>) -> Self {
         self.capabilities.push(capability.into());
@@ -230,6 +421,58 @@ impl Manifest {
     }
 }
 
+/// Check every manifest's dependencies against the others
+///
+/// A dependency is met when some manifest in `installed` has the required
+/// slug and a version matching the requirement.
+pub fn check_dependencies(installed: &[Manifest]) -> Vec<UnmetDependency> {
+    let mut unmet = Vec::new();
+    for manifest in installed {
+        for dep in &manifest.dependencies {
+            let versions = installed
+                .iter()
+                .filter(|m| m.slug == dep.slug)
+                .map(|m| &m.version);
+            if versions.clone().any(|v| dep.req.matches(v)) {
+                continue;
+            }
+            unmet.push(UnmetDependency {
+                dependent: manifest.slug.clone(),
+                dependency: dep.clone(),
+                installed: versions.max().cloned(),
+            });
+        }
+    }
+    unmet
+}
+
+fn default_schema_version() -> u32 {
+    1
+}
+
+// Dependencies as a list, or as the older slug -> requirement map
+fn deserialize_dependencies<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Vec<Dependency>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Dependencies {
+        List(Vec<Dependency>),
+        Map(BTreeMap<String, String>),
+    }
+
+    match Dependencies::deserialize(deserializer)? {
+        Dependencies::List(list) => Ok(list),
+        Dependencies::Map(map) => map
+            .into_iter()
+            .map(|(slug, req)| Dependency::new(slug, &req).map_err(serde::de::Error::custom))
+            .collect(),
+    }
+}
+
 // Custom serialization for ContainerSlug
 fn serialize_slug<S>(slug: &ContainerSlug, serializer: S) -> std::result::Result<S::Ok, S::Error>
 where
@@ -290,7 +533,8 @@ mod tests {
             manifest.author,
             Some("Test Author <test@example.com>".to_string())
         );
-        assert!(manifest.dependencies.contains_key("other-container"));
+        assert_eq!(manifest.dependencies[0].slug.as_str(), "other-container");
+        assert_eq!(manifest.dependencies[0].req, VersionReq::parse("^1.0.0").unwrap());
 
         Ok(())
     }
@@ -323,6 +567,126 @@ mod tests {
 
         assert!(manifest.validate().is_ok());
 
+        let mut manifest = manifest;
+        manifest.dependencies.push(Dependency::new("valid-dep", "^2")?);
+        assert!(manifest.validate().is_err());
+        assert!(Manifest::new("test", "Test", Version::new(1, 0, 0))?
+            .add_dependency("test", "*")?
+            .validate()
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_dependency_replaces_and_rejects_bad_req() -> Result<()> {
+        let manifest = Manifest::new("pack", "Pack", Version::new(1, 0, 0))?
+            .add_dependency("base-assets", ">=1.2")?
+            .add_dependency("base-assets", ">=1.4")?;
+        assert_eq!(manifest.dependencies.len(), 1);
+        assert_eq!(manifest.dependencies[0].req.to_string(), ">=1.4");
+
+        assert!(matches!(
+            manifest.add_dependency("base-assets", "not a version"),
+            Err(CartridgeError::InvalidVersion(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_old_manifests() {
+        // Written before dependencies existed
+        let json = r#"{"slug": "legacy", "title": "Legacy", "version": "0.3.0"}"#;
+        let manifest: Manifest = serde_json::from_str(json).unwrap();
+        assert!(manifest.dependencies.is_empty());
+
+        // Written with the old slug -> requirement map
+        let json = r#"{"slug": "legacy", "title": "Legacy", "version": "0.3.0",
+            "dependencies": {"base-assets": "^1.0.0"}}"#;
+        let manifest: Manifest = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            manifest.dependencies,
+            [Dependency::new("base-assets", "^1.0.0").unwrap()]
+        );
+
+        // Re-serialized in the list form
+        let json = serde_json::to_value(&manifest).unwrap();
+        assert_eq!(
+            json["dependencies"],
+            serde_json::json!([{"slug": "base-assets", "req": "^1.0.0"}])
+        );
+    }
+
+    #[test]
+    fn test_check_dependencies() -> Result<()> {
+        let base = Manifest::new("base-assets", "Base", Version::new(1, 3, 0))?;
+        let pack = Manifest::new("content-pack", "Content", Version::new(0, 1, 0))?
+            .add_dependency("base-assets", ">=1.2")?
+            .add_dependency("music", "^2")?;
+        let strict = Manifest::new("strict-pack", "Strict", Version::new(0, 1, 0))?
+            .add_dependency("base-assets", ">=1.4")?;
+
+        let unmet = check_dependencies(&[base, pack, strict]);
+        assert_eq!(unmet.len(), 2);
+        assert_eq!(unmet[0].dependent.as_str(), "content-pack");
+        assert_eq!(unmet[0].dependency.slug.as_str(), "music");
+        assert_eq!(unmet[0].installed, None);
+        assert_eq!(unmet[1].dependent.as_str(), "strict-pack");
+        assert_eq!(unmet[1].installed, Some(Version::new(1, 3, 0)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_extra_fields() -> Result<()> {
+        let json = r#"{"slug": "pack", "title": "Pack", "version": "1.0.0",
+            "provenance": {"tool": "baker", "build": 42}}"#;
+        let mut manifest: Manifest = serde_json::from_str(json).unwrap();
+        assert_eq!(manifest.schema_version, 1);
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Provenance {
+            tool: String,
+            build: u32,
+        }
+        let provenance: Option<Provenance> = manifest.get_extra("provenance")?;
+        assert_eq!(provenance.unwrap().build, 42);
+        assert_eq!(manifest.get_extra::<Provenance>("missing")?, None);
+        assert!(manifest.get_extra::<u32>("provenance").is_err());
+
+        manifest.set_extra("stage", "baked")?;
+        assert!(matches!(
+            manifest.set_extra("title", "Other"),
+            Err(CartridgeError::ManifestValidation(_))
+        ));
+        let json = serde_json::to_value(&manifest).unwrap();
+        assert_eq!(json["stage"], "baked");
+        assert_eq!(json["provenance"]["tool"], "baker");
+        assert_eq!(json["title"], "Pack");
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate() -> Result<()> {
+        let mut manifest = Manifest::new("old", "Old", Version::new(1, 0, 0))?;
+        assert!(!manifest.migrate());
+
+        manifest.schema_version = 0;
+        assert!(manifest.migrate());
+        assert_eq!(manifest.schema_version, Manifest::SCHEMA_VERSION);
+
+        // Left alone for a newer version to deal with
+        manifest.schema_version = Manifest::SCHEMA_VERSION + 1;
+        assert!(!manifest.migrate());
+        assert_eq!(manifest.schema_version, Manifest::SCHEMA_VERSION + 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bump_version() -> Result<()> {
+        let mut manifest = Manifest::new("bump", "Bump", Version::parse("1.2.3-beta.1").unwrap())?;
+        assert_eq!(*manifest.bump_version(Bump::Patch), Version::new(1, 2, 4));
+        assert_eq!(*manifest.bump_version(Bump::Minor), Version::new(1, 3, 0));
+        assert_eq!(*manifest.bump_version(Bump::Major), Version::new(2, 0, 0));
         Ok(())
     }
 }
@@ -27,9 +27,9 @@
 //! ## Example Usage
 //!
 //! ```rust,no_run
-//! use cartridge::allocator::{BlockAllocator, hybrid::HybridAllocator};
-//! use cartridge::header::Header;
-//! use cartridge::page::{Page, PageType};
+//! use cartridge_rs::core::allocator::{BlockAllocator, hybrid::HybridAllocator};
+//! use cartridge_rs::core::header::Header;
+//! use cartridge_rs::core::page::{Page, PageType};
 //!
 //! // Create a new cartridge header
 //! let mut header = Header::new();
@@ -113,43 +113,88 @@
 // Core modules (public - users need direct access)
 pub mod allocator;
 pub mod audit;
+pub mod batch;
+pub mod buffer_pool;
 pub mod cartridge;
 pub mod catalog;
+pub mod check;
+pub mod clock;
+pub mod compression;
+pub mod content_type;
+pub mod dedup;
+pub mod encryption;
+#[cfg(not(feature = "no-fs"))]
 pub mod engram_integration;
 pub mod error;
+pub mod events;
+pub mod find;
 pub mod header;
 pub mod iam;
 pub mod io;
+#[cfg(not(feature = "no-fs"))]
+pub mod lock;
 pub mod manifest;
+pub mod pack;
 pub mod page;
+pub mod quota;
+#[cfg(not(feature = "no-fs"))]
+pub mod recovery;
+pub mod retention;
+#[cfg(not(feature = "no-fs"))]
 pub mod snapshot;
+pub mod stream;
+pub mod symlink;
+pub mod system;
+pub mod telemetry;
+pub mod transfer;
 pub mod validation;
+#[cfg(not(feature = "no-fs"))]
 pub mod vfs;
 pub mod wal;
+pub mod watch;
+#[cfg(feature = "zip")]
+pub mod zip;
 
 // Internal modules (private - implementation details)
-pub(crate) mod buffer_pool;
-pub(crate) mod compression;
-pub(crate) mod encryption;
+#[cfg(not(feature = "no-fs"))]
 mod integration_tests;
 
 // Re-export commonly used types
 pub use allocator::{
     bitmap::BitmapAllocator, extent::ExtentAllocator, hybrid::HybridAllocator, BlockAllocator,
 };
-pub use cartridge::{Cartridge, CartridgeStats, VacuumProgress};
-pub use catalog::{Catalog, FileMetadata, FileType};
-pub use engram_integration::EngramFreezer;
+pub use batch::WriteBatch;
+pub use buffer_pool::PageGuard;
+pub use cartridge::{
+    Cartridge, CartridgeStats, CreateOptions, DetailedStats, GrowthPolicy, RefreshReport,
+    SyncPolicy, VacuumProgress, VacuumReport,
+};
+pub use catalog::{Catalog, FileMetadata, FileType, MetadataPatch, HOLE_BLOCK};
+pub use check::{BlockRef, ConsistencyReport, ScrubError, ScrubResult, ScrubToken, SharedBlock};
+#[cfg(not(feature = "no-fs"))]
+pub use engram_integration::{EngramFreezer, FreezeOptions, FreezeReport};
 pub use error::{CartridgeError, Result};
-pub use header::{Header, PAGE_SIZE};
+pub use events::{CartridgeEvent, EventListener};
+pub use find::CaseSensitivity;
+pub use header::{Header, PageSize, PAGE_SIZE};
 pub use iam::{
-    Action, Condition, ConditionOperator, ConditionValue, Effect, Policy, PolicyCache,
+    Action, CacheStats, Condition, ConditionOperator, ConditionValue, Effect, Policy, PolicyCache,
     PolicyEngine, Statement,
 };
 pub use io::CartridgeFile;
+pub use pack::{DigestManifest, DigestMismatch, FileDigest, PackOptions, PackReport};
 pub use page::{Page, PageHeader, PageType};
-pub use snapshot::{SnapshotManager, SnapshotMetadata};
+pub use quota::QuotaUsage;
+#[cfg(not(feature = "no-fs"))]
+pub use recovery::{RecoverySession, SalvageReport};
+pub use retention::RetentionPolicy;
+pub use stream::FileReader;
+pub use system::SystemFiles;
+#[cfg(not(feature = "no-fs"))]
+pub use snapshot::{SnapshotDiff, SnapshotManager, SnapshotMetadata};
+pub use transfer::{ExportReport, ImportOptions, ImportReport, SkippedEntry, SymlinkPolicy};
 pub use wal::{WalEntry, WalFile, WalOp, WalState, WalWrite};
+pub use watch::{ChangeEvent, ChangeKind};
 
 /// Cartridge format version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
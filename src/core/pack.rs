@@ -0,0 +1,433 @@
+//! Checksummed export for distribution
+//!
+//! [`Cartridge::pack`] turns an archive into a canonical artifact for
+//! transport: it flushes and vacuums, records a SHA-256 of every file in
+//! `.cartridge/digests.json` (optionally signed with Ed25519), then hashes
+//! the finished `.cart` image. Unlike [`Cartridge::freeze`] the archive
+//! stays mutable.
+//!
+//! On the receiving side [`Cartridge::verify_digests`] recomputes every
+//! digest and lists whatever no longer matches. Files are hashed a page at a
+//! time rather than read whole.
+//!
+//! Digests cover regular files. Internal `.cartridge/` files and the vacuum
+//! WAL are left out, since flushes and vacuums rewrite them; the container
+//! manifest is included.
+
+use super::cartridge::{Cartridge, VacuumReport};
+use crate::catalog::{FileMetadata, HOLE_BLOCK};
+use crate::error::{CartridgeError, Result};
+use crate::iam::Action;
+use crate::validation::normalize_path;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// Where [`Cartridge::pack`] stores the digest manifest
+pub const DIGESTS_PATH: &str = "/.cartridge/digests.json";
+
+/// The only `.cartridge/` file covered by the digests
+const MANIFEST_PATH: &str = "/.cartridge/manifest.json";
+
+/// Options for [`Cartridge::pack`]
+#[derive(Debug, Clone)]
+pub struct PackOptions {
+    /// Vacuum before computing digests so the image is compact (default: true)
+    pub vacuum: bool,
+    /// Ed25519 key to sign the digest manifest with; `None` leaves it unsigned
+    pub signing_key: Option<SigningKey>,
+}
+
+impl Default for PackOptions {
+    fn default() -> Self {
+        PackOptions {
+            vacuum: true,
+            signing_key: None,
+        }
+    }
+}
+
+/// Summary of a [`Cartridge::pack`] call
+#[derive(Debug, Clone)]
+pub struct PackReport {
+    /// Files recorded in the digest manifest
+    pub files_digested: usize,
+    /// Content bytes hashed
+    pub bytes_digested: u64,
+    /// Size of the packed `.cart` image
+    pub archive_bytes: u64,
+    /// Hex SHA-256 of the whole packed `.cart` image
+    pub archive_sha256: String,
+    /// Public half of the signing key, if the digests were signed
+    pub public_key: Option<[u8; 32]>,
+    /// Result of the pre-pack vacuum, if one was requested
+    pub vacuum: Option<VacuumReport>,
+}
+
+/// Size and SHA-256 of one file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDigest {
+    /// Logical size in bytes
+    pub size: u64,
+    /// Hex SHA-256 of the content
+    pub sha256: String,
+}
+
+/// Contents of `.cartridge/digests.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DigestManifest {
+    /// Digest algorithm, always `"sha256"`
+    pub algorithm: String,
+    /// When the digests were computed (RFC 3339)
+    pub created: String,
+    /// Digest of every covered file, keyed by path
+    pub files: BTreeMap<String, FileDigest>,
+    /// Hex Ed25519 public key the manifest was signed with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    /// Hex Ed25519 signature over the serialized `files` map
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl DigestManifest {
+    fn new(files: BTreeMap<String, FileDigest>) -> Self {
+        DigestManifest {
+            algorithm: "sha256".to_string(),
+            created: chrono::DateTime::<chrono::Utc>::from(crate::clock::now()).to_rfc3339(),
+            files,
+            public_key: None,
+            signature: None,
+        }
+    }
+
+    /// Bytes covered by the signature
+    fn signed_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&self.files)?)
+    }
+
+    fn sign(&mut self, key: &SigningKey) -> Result<()> {
+        let signature = key.sign(&self.signed_bytes()?);
+        self.public_key = Some(to_hex(key.verifying_key().as_bytes()));
+        self.signature = Some(to_hex(&signature.to_bytes()));
+        Ok(())
+    }
+
+    /// Whether the manifest carries a signature
+    pub fn is_signed(&self) -> bool {
+        self.signature.is_some()
+    }
+
+    /// Check the signature against the embedded public key
+    ///
+    /// Returns `false` for unsigned manifests. This only proves the digests
+    /// weren't altered after signing; compare [`public_key`](Self::public_key)
+    /// against a key you trust to know who signed them.
+    pub fn verify_signature(&self) -> bool {
+        let (Some(key), Some(signature)) = (&self.public_key, &self.signature) else {
+            return false;
+        };
+        let Some(key) = from_hex::<32>(key).and_then(|k| VerifyingKey::from_bytes(&k).ok()) else {
+            return false;
+        };
+        let Some(signature) = from_hex::<64>(signature).map(|s| Signature::from_bytes(&s)) else {
+            return false;
+        };
+        self.signed_bytes()
+            .is_ok_and(|bytes| key.verify(&bytes, &signature).is_ok())
+    }
+}
+
+/// A difference found by [`Cartridge::verify_digests`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DigestMismatch {
+    /// The file's content no longer matches its recorded digest
+    Changed {
+        path: String,
+        expected: FileDigest,
+        actual: FileDigest,
+    },
+    /// A file listed in the digest manifest is gone
+    Missing { path: String },
+    /// A file the digest manifest doesn't list
+    Unlisted { path: String },
+    /// A page of the file fails its checksum, so it can't be hashed
+    Corrupt { path: String },
+    /// The digest manifest is signed but the signature doesn't verify
+    BadSignature,
+}
+
+impl Cartridge {
+    /// Produce a verified artifact for distribution
+    ///
+    /// Flushes, vacuums (unless [`PackOptions::vacuum`] is off), writes the
+    /// per-file digests to `.cartridge/digests.json`, signing them when
+    /// [`PackOptions::signing_key`] is set, and flushes again. The report
+    /// carries the SHA-256 of the resulting `.cart` image, which can't be
+    /// stored inside it.
+    pub fn pack(&mut self, options: &PackOptions) -> Result<PackReport> {
+        self.ensure_writable()?;
+        self.flush()?;
+        let vacuum = if options.vacuum {
+            Some(self.vacuum()?)
+        } else {
+            None
+        };
+
+        let files = self.compute_digests()?;
+        let bytes_digested = files.values().map(|d| d.size).sum();
+        let mut digests = DigestManifest::new(files);
+        if let Some(key) = &options.signing_key {
+            digests.sign(key)?;
+        }
+        let json = serde_json::to_vec_pretty(&digests)?;
+        self.system().write(DIGESTS_PATH, &json)?;
+
+        let archive_sha256 = to_hex(&self.image_sha256()?);
+        Ok(PackReport {
+            files_digested: digests.files.len(),
+            bytes_digested,
+            archive_bytes: self.stats().file_size_bytes,
+            archive_sha256,
+            public_key: options
+                .signing_key
+                .as_ref()
+                .map(|key| key.verifying_key().to_bytes()),
+            vacuum,
+        })
+    }
+
+    /// Read the digest manifest written by the last [`pack`](Self::pack)
+    pub fn read_digests(&self) -> Result<DigestManifest> {
+        Ok(serde_json::from_slice(&self.read_file(DIGESTS_PATH)?)?)
+    }
+
+    /// Recompute every digest and compare it with the digest manifest
+    ///
+    /// Returns an empty list when the archive matches what was packed. Fails
+    /// if the archive was never packed.
+    pub fn verify_digests(&self) -> Result<Vec<DigestMismatch>> {
+        let digests = self.read_digests()?;
+        let mut mismatches = Vec::new();
+        if digests.is_signed() && !digests.verify_signature() {
+            mismatches.push(DigestMismatch::BadSignature);
+        }
+
+        // Digests packed before path normalization are keyed "a.txt"
+        let listed: BTreeMap<String, &FileDigest> = digests
+            .files
+            .iter()
+            .map(|(path, digest)| (normalize_path(path).unwrap_or_else(|_| path.clone()), digest))
+            .collect();
+
+        let mut seen = 0;
+        for (path, metadata) in self.list_dir_with_metadata("")? {
+            if !is_digested(&path, &metadata) {
+                continue;
+            }
+            match listed.get(&path) {
+                Some(expected) => {
+                    seen += 1;
+                    match self.file_digest(&path, &metadata) {
+                        Ok(actual) if actual != **expected => {
+                            mismatches.push(DigestMismatch::Changed {
+                                expected: (*expected).clone(),
+                                actual,
+                                path,
+                            });
+                        }
+                        Ok(_) => {}
+                        Err(
+                            CartridgeError::ChecksumMismatch { .. } | CartridgeError::Corruption(_),
+                        ) => mismatches.push(DigestMismatch::Corrupt { path }),
+                        Err(e) => return Err(e),
+                    }
+                }
+                None => mismatches.push(DigestMismatch::Unlisted { path }),
+            }
+        }
+        if seen < digests.files.len() {
+            for path in digests.files.keys() {
+                if !self.exists(path)? {
+                    mismatches.push(DigestMismatch::Missing { path: path.clone() });
+                }
+            }
+        }
+
+        Ok(mismatches)
+    }
+
+    /// Digest of every covered file
+    fn compute_digests(&self) -> Result<BTreeMap<String, FileDigest>> {
+        let mut files = BTreeMap::new();
+        for (path, metadata) in self.list_dir_with_metadata("")? {
+            if is_digested(&path, &metadata) {
+                let digest = self.file_digest(&path, &metadata)?;
+                files.insert(path, digest);
+            }
+        }
+        Ok(files)
+    }
+
+    /// Hash one file a page at a time, bypassing the page cache
+    ///
+    /// Encrypted files have to be decrypted whole, so they are read in full.
+    fn file_digest(&self, path: &str, metadata: &FileMetadata) -> Result<FileDigest> {
+        self.check_access(&Action::Read, path)?;
+        let mut hasher = Sha256::new();
+        let encrypted = metadata
+            .user_metadata
+            .get("encrypted")
+            .is_some_and(|v| v == "true");
+
+        if encrypted {
+            hasher.update(self.read_file_nofollow(path)?);
+        } else if let Some(data) = &metadata.inline_data {
+            hasher.update(data);
+        } else {
+            let mut remaining = metadata.size as usize;
+            for &block in &metadata.blocks {
+                if remaining == 0 {
+                    break;
+                }
+                let page = if block == HOLE_BLOCK {
+                    vec![0u8; self.page_size()]
+                } else {
+                    self.read_page_data_raw(block)?
+                };
+                let len = remaining.min(self.page_size());
+                hasher.update(&page[..len]);
+                remaining -= len;
+            }
+            if remaining > 0 {
+                return Err(CartridgeError::Corruption(format!(
+                    "File {} is shorter than its recorded size",
+                    path
+                )));
+            }
+        }
+
+        Ok(FileDigest {
+            size: metadata.size,
+            sha256: to_hex(&hasher.finalize()),
+        })
+    }
+}
+
+/// Whether `path` is covered by the digest manifest
+fn is_digested(path: &str, metadata: &FileMetadata) -> bool {
+    metadata.is_file()
+        && path != Cartridge::VACUUM_WAL_PATH
+        && (path == MANIFEST_PATH || !(path == "/.cartridge" || path.starts_with("/.cartridge/")))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex<const N: usize>(hex: &str) -> Option<[u8; N]> {
+    if hex.len() != N * 2 || !hex.is_ascii() {
+        return None;
+    }
+    let mut bytes = [0u8; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::PAGE_SIZE;
+
+    fn packed() -> Cartridge {
+        let mut cart = Cartridge::in_memory("packed", "Packed").unwrap();
+        cart.create_file("small.txt", b"inline").unwrap();
+        cart.create_file("big.bin", &vec![7u8; 3 * PAGE_SIZE + 100]).unwrap();
+        cart.pack(&PackOptions::default()).unwrap();
+        cart
+    }
+
+    #[test]
+    fn test_pack_then_verify_is_clean() {
+        let cart = packed();
+        let digests = cart.read_digests().unwrap();
+        assert_eq!(
+            digests.files.keys().collect::<Vec<_>>(),
+            [MANIFEST_PATH, "/big.bin", "/small.txt"]
+        );
+        assert_eq!(
+            digests.files["/big.bin"].sha256,
+            to_hex(&Sha256::digest(vec![7u8; 3 * PAGE_SIZE + 100]))
+        );
+        assert!(!digests.is_signed());
+        assert!(cart.verify_digests().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_verify_reports_changes() {
+        let mut cart = packed();
+        cart.write_at("big.bin", 5000, b"tampered").unwrap();
+        cart.delete_file("small.txt").unwrap();
+        cart.create_file("added.txt", b"new").unwrap();
+
+        let mismatches = cart.verify_digests().unwrap();
+        assert_eq!(mismatches.len(), 3, "{mismatches:?}");
+        assert!(matches!(
+            &mismatches[0],
+            DigestMismatch::Unlisted { path } if path == "/added.txt"
+        ));
+        assert!(matches!(
+            &mismatches[1],
+            DigestMismatch::Changed { path, expected, actual }
+                if path == "/big.bin" && expected.size == actual.size
+        ));
+        assert_eq!(
+            mismatches[2],
+            DigestMismatch::Missing {
+                path: "/small.txt".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_signed_digests_detect_forgery() {
+        let mut cart = Cartridge::in_memory("signed", "Signed").unwrap();
+        cart.create_file("a.txt", b"original").unwrap();
+        let key = SigningKey::from_bytes(&[9u8; 32]);
+        let options = PackOptions {
+            signing_key: Some(key.clone()),
+            ..Default::default()
+        };
+        let report = cart.pack(&options).unwrap();
+        assert_eq!(report.public_key, Some(key.verifying_key().to_bytes()));
+        assert!(cart.read_digests().unwrap().verify_signature());
+
+        // Change a file and rewrite its digest to match, keeping the old
+        // signature: the content checks pass but the signature doesn't
+        cart.write_file("a.txt", b"replaced").unwrap();
+        let mut forged = cart.read_digests().unwrap();
+        forged.files.get_mut("/a.txt").unwrap().sha256 = to_hex(&Sha256::digest(b"replaced"));
+        cart.system()
+            .write(DIGESTS_PATH, &serde_json::to_vec(&forged).unwrap())
+            .unwrap();
+        assert_eq!(cart.verify_digests().unwrap(), [DigestMismatch::BadSignature]);
+    }
+
+    #[test]
+    fn test_verify_without_pack_fails() {
+        let cart = Cartridge::in_memory("unpacked", "Unpacked").unwrap();
+        assert!(cart.verify_digests().is_err());
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        let bytes = [0x00, 0x7f, 0xab, 0xff];
+        assert_eq!(to_hex(&bytes), "007fabff");
+        assert_eq!(from_hex::<4>("007fabff"), Some(bytes));
+        assert_eq!(from_hex::<4>("007fab"), None);
+        assert_eq!(from_hex::<2>("zz00"), None);
+    }
+}
@@ -0,0 +1,178 @@
+//! Per-prefix byte quotas
+//!
+//! Limits are saved in the manifest so they survive reopening. Usage is
+//! counted once, when the cartridge is opened or a quota is set, and then
+//! kept current by every write, delete and rename; enforcing a quota never
+//! rescans the catalog.
+//!
+//! Usage is the logical size of regular files under the prefix, the same
+//! number `metadata().size` reports. Directories and symlinks don't count.
+
+use crate::catalog::FileMetadata;
+use crate::error::{CartridgeError, Result};
+use crate::validation::normalize_path;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Limit and current usage of one quota
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaUsage {
+    /// Most bytes allowed under the prefix
+    pub limit: u64,
+    /// Bytes currently stored under the prefix
+    pub used: u64,
+}
+
+/// Every quota on a cartridge with its running usage
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Quotas {
+    entries: BTreeMap<String, QuotaUsage>,
+}
+
+impl Quotas {
+    /// Build usage counters for `limits` from one pass over the catalog
+    pub(crate) fn count<'a>(
+        limits: &BTreeMap<String, u64>,
+        files: impl IntoIterator<Item = (&'a str, &'a FileMetadata)>,
+    ) -> Self {
+        let mut quotas = Quotas::default();
+        for (prefix, &limit) in limits {
+            quotas
+                .entries
+                .insert(normalize_prefix(prefix), QuotaUsage { limit, used: 0 });
+        }
+        if !quotas.entries.is_empty() {
+            for (path, metadata) in files {
+                if metadata.is_file() {
+                    quotas.record(path, 0, metadata.size);
+                }
+            }
+        }
+        quotas
+    }
+
+    pub(crate) fn get(&self, prefix: &str) -> Option<QuotaUsage> {
+        self.entries.get(&normalize_prefix(prefix)).copied()
+    }
+
+    pub(crate) fn insert(&mut self, prefix: &str, usage: QuotaUsage) -> Option<QuotaUsage> {
+        self.entries.insert(normalize_prefix(prefix), usage)
+    }
+
+    pub(crate) fn remove(&mut self, prefix: &str) -> Option<QuotaUsage> {
+        self.entries.remove(&normalize_prefix(prefix))
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&String, &QuotaUsage)> {
+        self.entries.iter()
+    }
+
+    /// Fail if a file at `path` growing from `old` to `new` bytes would
+    /// push any quota covering it over its limit
+    ///
+    /// Shrinking is always allowed, even under a quota that's already over.
+    pub(crate) fn check(&self, path: &str, old: u64, new: u64) -> Result<()> {
+        if new <= old {
+            return Ok(());
+        }
+        self.check_growth(path, new - old, |_| true)
+    }
+
+    /// Like [`check`](Self::check) for `size` bytes moving from `from` to
+    /// `to`; quotas covering both paths are unaffected
+    pub(crate) fn check_rename(&self, from: &str, to: &str, size: u64) -> Result<()> {
+        self.check_growth(to, size, |prefix| !covers(prefix, from))
+    }
+
+    /// Apply a size change at `path` to every quota covering it
+    pub(crate) fn record(&mut self, path: &str, old: u64, new: u64) {
+        for (prefix, usage) in self.entries.iter_mut() {
+            if covers(prefix, path) {
+                usage.used = usage.used.saturating_sub(old) + new;
+            }
+        }
+    }
+
+    fn check_growth(&self, path: &str, added: u64, applies: impl Fn(&str) -> bool) -> Result<()> {
+        for (prefix, usage) in &self.entries {
+            if !covers(prefix, path) || !applies(prefix) {
+                continue;
+            }
+            let attempted = usage.used + added;
+            if attempted > usage.limit {
+                return Err(CartridgeError::QuotaExceeded {
+                    prefix: prefix.clone(),
+                    limit: usage.limit,
+                    attempted,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Quota prefixes are stored as normalized paths (see
+/// [`normalize_path`]), except that `""` (or `"/"`) covers the whole
+/// cartridge
+pub(crate) fn normalize_prefix(prefix: &str) -> String {
+    match normalize_path(prefix) {
+        Ok(path) if path == "/" => String::new(),
+        Ok(path) => path,
+        Err(_) => prefix.trim_end_matches('/').to_string(),
+    }
+}
+
+/// Whether `path` is `prefix` itself or lies underneath it
+pub(crate) fn covers(prefix: &str, path: &str) -> bool {
+    prefix.is_empty()
+        || path
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_covers_whole_segments_only() {
+        assert!(covers("users/alice", "users/alice/notes.txt"));
+        assert!(covers("users/alice", "users/alice"));
+        assert!(!covers("users/alice", "users/alice2/notes.txt"));
+        assert!(covers("", "anything"));
+        assert_eq!(normalize_prefix("users/alice/"), "/users/alice");
+        assert_eq!(normalize_prefix("/"), "");
+    }
+
+    #[test]
+    fn test_check_and_record() {
+        let mut quotas = Quotas::default();
+        quotas.insert("/a", QuotaUsage { limit: 100, used: 0 });
+        quotas.insert("/a/b", QuotaUsage { limit: 10, used: 0 });
+
+        match quotas.check("/a/b/y", 0, 11) {
+            Err(CartridgeError::QuotaExceeded {
+                prefix,
+                limit,
+                attempted,
+            }) => assert_eq!((prefix.as_str(), limit, attempted), ("/a/b", 10, 11)),
+            other => panic!("expected QuotaExceeded, got {:?}", other),
+        }
+
+        quotas.check("/a/x", 0, 90).unwrap();
+        quotas.record("/a/x", 0, 90);
+        assert!(matches!(
+            quotas.check("/a/y", 0, 11),
+            Err(CartridgeError::QuotaExceeded { limit: 100, attempted: 101, .. })
+        ));
+
+        // Shrinking or replacing with the same size is fine
+        quotas.check("/a/x", 90, 90).unwrap();
+        quotas.record("/a/x", 90, 40);
+        assert_eq!(quotas.get("/a").unwrap().used, 40);
+
+        // Moving within the quota doesn't count twice
+        quotas.check_rename("/a/x", "/a/z", 40).unwrap();
+        assert!(quotas.check_rename("/a/x", "/a/b/z", 40).is_err());
+    }
+}
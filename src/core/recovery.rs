@@ -0,0 +1,589 @@
+//! Salvaging files from a cartridge whose catalog or allocator is damaged
+//!
+//! [`Cartridge::open_recovery`] opens a cartridge that [`Cartridge::open`]
+//! refuses, without ever writing to it. It loads what still parses and
+//! rebuilds the rest on a best-effort basis:
+//!
+//! - A catalog that can't be read is pieced back together from catalog
+//!   segments still sitting in their pages. A catalog too small to be
+//!   segmented lives only in the catalog page, so nothing of it survives.
+//! - Allocated pages that no recovered entry references are carved out as
+//!   files under [`LOST_AND_FOUND`], one per run of consecutive pages, with
+//!   trailing zeros trimmed. Their names and exact sizes are lost.
+//! - An allocator that can't be read is ignored, and every page is a
+//!   candidate.
+//!
+//! The [`RecoverySession`] reads recovered files like a read-only cartridge,
+//! exposes raw pages for tools that want to carve data themselves, and
+//! copies everything it found into a healthy cartridge with
+//! [`salvage_to`](RecoverySession::salvage_to).
+//!
+//! ```rust,no_run
+//! use cartridge_rs::core::Cartridge;
+//!
+//! let session = Cartridge::open_recovery("damaged.cart")?;
+//! let mut target = Cartridge::create_at("rescued.cart", "rescued", "Rescued")?;
+//! let report = session.salvage_to(&mut target)?;
+//! println!("{} files salvaged", report.files_salvaged);
+//! # Ok::<(), cartridge_rs::CartridgeError>(())
+//! ```
+
+use crate::allocator::hybrid::HybridAllocator;
+use crate::cartridge::Cartridge;
+use crate::catalog::{Catalog, FileMetadata, FileType, HOLE_BLOCK};
+use crate::error::{CartridgeError, Result};
+use crate::header::{FEATURE_CASE_INSENSITIVE, FEATURE_PAGE_CHECKSUMS};
+use crate::io::CartridgeFile;
+use crate::transfer::SkippedEntry;
+use crate::validation::{self, normalize_path, MAX_PATH_LENGTH};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Directory that carved, unnamed content is recovered into
+pub const LOST_AND_FOUND: &str = "/lost+found";
+
+/// Internal entries that salvaging leaves out; the target keeps its own
+const INTERNAL_PREFIX: &str = "/.cartridge/";
+
+/// Pages 0-2 hold the header, catalog and allocator
+const RESERVED_PAGES: u64 = 3;
+
+/// Longest catalog entry a segment scan follows across pages
+///
+/// Enough for the block list of a multi-gigabyte file; stops a garbage
+/// length prefix from dragging the scan through the rest of the file.
+const MAX_ENTRY_BYTES: usize = 8 << 20;
+
+/// Summary of a [`RecoverySession::salvage_to`] call
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SalvageReport {
+    /// Files (and symlinks) written into the target
+    pub files_salvaged: usize,
+    /// Directories created in the target
+    pub directories_created: usize,
+    /// Total content bytes written
+    pub bytes_written: u64,
+    /// Recovered entries that couldn't be copied, and why
+    pub skipped: Vec<SkippedEntry>,
+}
+
+/// A damaged cartridge opened for salvage
+///
+/// Holds the source open read-only; nothing is ever written back to it.
+pub struct RecoverySession {
+    /// Read-only cartridge over the damaged file and the rebuilt catalog
+    cart: Cartridge,
+    catalog_intact: bool,
+    allocator_intact: bool,
+    /// Entries pieced together from catalog segments
+    entries_recovered: usize,
+    /// Paths of the carved files under [`LOST_AND_FOUND`]
+    carved: Vec<String>,
+}
+
+impl Cartridge {
+    /// Open a damaged cartridge to salvage what's left of it
+    ///
+    /// Unlike [`open`](Self::open), an unreadable catalog or allocator
+    /// doesn't fail the open; see [`crate::recovery`] for how they are
+    /// rebuilt. The file is opened read-only and never written. The header
+    /// still has to be readable, and encrypted cartridges fail with
+    /// [`CartridgeError::EncryptionRequired`].
+    pub fn open_recovery<P: AsRef<Path>>(path: P) -> Result<RecoverySession> {
+        let path = validation::normalize_container_path(path.as_ref())?;
+        RecoverySession::from_file(CartridgeFile::open_read_only(path)?)
+    }
+}
+
+impl RecoverySession {
+    fn from_file(mut file: CartridgeFile) -> Result<Self> {
+        let header = file.read_header()?;
+        if header.encryption_params().is_some() {
+            return Err(CartridgeError::EncryptionRequired);
+        }
+        file.set_checksums(header.has_feature(FEATURE_PAGE_CHECKSUMS));
+        file.set_page_size(header.page_size());
+
+        // A header claiming more pages than the file holds would send every
+        // scan past the end
+        let stored = file.size_bytes()? / file.slot_size() as u64;
+        let total_blocks = header.total_blocks.min(stored);
+
+        // An allocator that can't account for its own overflow pages is as
+        // untrustworthy as an unreadable one
+        let read = Cartridge::load_allocator_multi(&mut file, total_blocks as usize).and_then(
+            |(mut allocator, overflow, _)| {
+                allocator.mark_pages_allocated(&overflow)?;
+                allocator.recalibrate();
+                Ok((allocator, overflow))
+            },
+        );
+        let allocator = match read {
+            Ok(read) => Some(read),
+            Err(e) => {
+                tracing::warn!("Allocator unreadable, scanning every page: {e}");
+                None
+            }
+        };
+
+        let loaded = match Cartridge::load_catalog_multi(&mut file, header.btree_root_page) {
+            // A zeroed catalog page reads as an empty catalog
+            Ok((catalog, _, _)) if !catalog.is_empty() => Some(catalog),
+            Ok(_) => {
+                tracing::warn!("Catalog is empty, scanning pages for entries");
+                None
+            }
+            Err(e) => {
+                tracing::warn!("Catalog unreadable, scanning pages for entries: {e}");
+                None
+            }
+        };
+        let catalog_intact = loaded.is_some();
+        let allocator_intact = allocator.is_some();
+
+        let (mut catalog, entries_recovered, carved) = match loaded {
+            Some(catalog) => (catalog, 0, Vec::new()),
+            None => {
+                let mut scan = Scan {
+                    file: &mut file,
+                    total_blocks,
+                    allocator: allocator.as_ref(),
+                };
+                let (entries, metadata_pages) = scan.find_entries();
+                let count = entries.len();
+                let carved = scan.carve(&entries, &metadata_pages);
+
+                let mut catalog = Catalog::new(header.btree_root_page);
+                for (path, metadata) in entries.into_iter().chain(carved.iter().cloned()) {
+                    catalog.insert(&path, metadata)?;
+                }
+                let carved = carved.into_iter().map(|(path, _)| path).collect();
+                (catalog, count, carved)
+            }
+        };
+        if header.has_feature(FEATURE_CASE_INSENSITIVE) {
+            catalog.set_case_insensitive();
+        }
+
+        let allocator = match allocator {
+            Some((allocator, _)) => allocator,
+            None => HybridAllocator::new(header.total_blocks as usize),
+        };
+        Ok(RecoverySession {
+            cart: Cartridge::recovered(file, header, allocator, catalog),
+            catalog_intact,
+            allocator_intact,
+            entries_recovered,
+            carved,
+        })
+    }
+
+    /// Whether the catalog loaded as it was, with nothing rebuilt
+    pub fn catalog_intact(&self) -> bool {
+        self.catalog_intact
+    }
+
+    /// Whether the allocator loaded as it was
+    pub fn allocator_intact(&self) -> bool {
+        self.allocator_intact
+    }
+
+    /// Number of named entries pieced together from catalog segments
+    pub fn entries_recovered(&self) -> usize {
+        self.entries_recovered
+    }
+
+    /// Paths of the unnamed files carved out under [`LOST_AND_FOUND`]
+    pub fn carved_files(&self) -> &[String] {
+        &self.carved
+    }
+
+    /// Every recovered entry, in path order
+    pub fn entries(&self) -> Vec<(String, FileMetadata)> {
+        self.cart
+            .walk_dir("/")
+            .map(|(path, metadata)| (path.clone(), metadata.clone()))
+            .collect()
+    }
+
+    /// The read-only cartridge over the recovered catalog
+    ///
+    /// Reads go to the damaged file, so a file whose pages are corrupt
+    /// fails like it would in a healthy cartridge.
+    pub fn cartridge(&self) -> &Cartridge {
+        &self.cart
+    }
+
+    /// Read a recovered file
+    pub fn read_file(&self, path: &str) -> Result<Vec<u8>> {
+        self.cart.read_file_nofollow(path)
+    }
+
+    /// One page as stored, without going through the catalog
+    ///
+    /// Checksummed pages are still verified and fail with
+    /// [`CartridgeError::ChecksumMismatch`] when damaged.
+    pub fn raw_page(&self, page_id: u64) -> Result<Vec<u8>> {
+        self.cart.read_disk_page(page_id)
+    }
+
+    /// Ids of the readable pages `predicate` accepts, in page order
+    ///
+    /// Covers every page, allocated or not, including the header. Pages
+    /// that can't be read are passed over.
+    pub fn scan_pages(&self, mut predicate: impl FnMut(u64, &[u8]) -> bool) -> Vec<u64> {
+        (0..self.cart.header().total_blocks)
+            .filter(|&page_id| match self.raw_page(page_id) {
+                Ok(data) => predicate(page_id, &data),
+                Err(_) => false,
+            })
+            .collect()
+    }
+
+    /// Copy every recovered entry into `target`
+    ///
+    /// Files keep their times, content type and user metadata, and replace
+    /// files already at the same path. Internal files under `/.cartridge`
+    /// are left out, as the target has its own. Entries that can't be read,
+    /// e.g. because their pages are damaged or they were encrypted, are
+    /// reported as skipped rather than failing the salvage. The target is
+    /// flushed at the end.
+    pub fn salvage_to(&self, target: &mut Cartridge) -> Result<SalvageReport> {
+        let mut report = SalvageReport::default();
+        for (path, metadata) in self.entries() {
+            if path.starts_with(INTERNAL_PREFIX) {
+                continue;
+            }
+            let skip = |reason: String| SkippedEntry { path: path.clone(), reason };
+
+            match metadata.file_type {
+                FileType::Directory => {
+                    if !target.exists(&path)? {
+                        target.create_dir(&path)?;
+                        report.directories_created += 1;
+                    }
+                    continue;
+                }
+                FileType::Symlink => {
+                    let Some(link) = metadata.symlink_target() else {
+                        report.skipped.push(skip("symlink has no target".to_string()));
+                        continue;
+                    };
+                    if target.exists(&path)? {
+                        target.delete_file(&path)?;
+                    }
+                    target.symlink(link, &path)?;
+                }
+                FileType::File => {
+                    let content = match self.cart.read_file_nofollow(&path) {
+                        Ok(content) => content,
+                        Err(e) => {
+                            report.skipped.push(skip(e.to_string()));
+                            continue;
+                        }
+                    };
+                    if target.exists(&path)? {
+                        target.write_file(&path, &content)?;
+                    } else {
+                        target.create_file(&path, &content)?;
+                    }
+                    report.bytes_written += content.len() as u64;
+                }
+            }
+
+            if metadata.content_type.is_some() {
+                target.set_content_type(&path, metadata.content_type.clone())?;
+            }
+            let user_metadata = self.cart.user_metadata(&path)?;
+            if !user_metadata.is_empty() {
+                if let Err(e) = target.set_user_metadata(&path, user_metadata) {
+                    report.skipped.push(skip(format!("user metadata not copied: {e}")));
+                }
+            }
+            target.set_times(&path, Some(metadata.created_at), Some(metadata.modified_at))?;
+            report.files_salvaged += 1;
+        }
+        target.flush()?;
+        Ok(report)
+    }
+}
+
+/// Page scan over the damaged file
+struct Scan<'a> {
+    file: &'a mut CartridgeFile,
+    total_blocks: u64,
+    /// The loaded allocator and its own overflow pages
+    allocator: Option<&'a (HybridAllocator, Vec<u64>)>,
+}
+
+impl Scan<'_> {
+    /// Whether `page_id` may hold catalog segments or content
+    fn candidate(&self, page_id: u64) -> bool {
+        if page_id < RESERVED_PAGES || page_id >= self.total_blocks {
+            return false;
+        }
+        match self.allocator {
+            Some((allocator, overflow)) => {
+                allocator.is_allocated(page_id) && !overflow.contains(&page_id)
+            }
+            None => true,
+        }
+    }
+
+    fn read(&mut self, page_id: u64) -> Option<Vec<u8>> {
+        self.file.read_page_data(page_id).ok()
+    }
+
+    /// Catalog entries from every page that starts a segment
+    ///
+    /// A segment is consecutive `(path, metadata)` pairs; an entry running
+    /// off the end of a page continues on the next. When the same path
+    /// turns up more than once, the most recently modified copy wins.
+    /// Also returns the pages the entries came from.
+    fn find_entries(&mut self) -> (BTreeMap<String, FileMetadata>, BTreeSet<u64>) {
+        let mut entries: BTreeMap<String, FileMetadata> = BTreeMap::new();
+        let mut pages = BTreeSet::new();
+        let mut page_id = RESERVED_PAGES;
+        while page_id < self.total_blocks {
+            if !self.candidate(page_id) {
+                page_id += 1;
+                continue;
+            }
+            let (found, span) = self.read_segment(page_id);
+            if found.is_empty() {
+                page_id += 1;
+                continue;
+            }
+            for (path, metadata) in found {
+                match entries.get(&path) {
+                    Some(existing) if existing.modified_at > metadata.modified_at => {}
+                    _ => {
+                        entries.insert(path, metadata);
+                    }
+                }
+            }
+            pages.extend(page_id..page_id + span);
+            page_id += span;
+        }
+        (entries, pages)
+    }
+
+    /// Parse entries from `start` on, returning them and the pages spanned
+    fn read_segment(&mut self, start: u64) -> (Vec<(String, FileMetadata)>, u64) {
+        let Some(mut buf) = self.read(start) else {
+            return (Vec::new(), 1);
+        };
+        let mut span = 1;
+        let mut offset = 0;
+        let mut entry_start = 0;
+        let mut found = Vec::new();
+        loop {
+            let rest = &buf[offset..];
+            if rest.iter().all(|&b| b == 0) || !looks_like_entry(rest) {
+                break;
+            }
+            let mut reader = rest;
+            match bincode::deserialize_from::<_, (String, FileMetadata)>(&mut reader) {
+                Ok((path, metadata)) => {
+                    if !self.plausible(&path, &metadata) {
+                        break;
+                    }
+                    offset = buf.len() - reader.len();
+                    entry_start = offset;
+                    found.push((path, metadata));
+                }
+                Err(e) if is_eof(&e) && buf.len() - entry_start < MAX_ENTRY_BYTES => {
+                    let next = start + span;
+                    if !self.candidate(next) {
+                        break;
+                    }
+                    let Some(page) = self.read(next) else {
+                        break;
+                    };
+                    buf.extend_from_slice(&page);
+                    span += 1;
+                }
+                Err(_) => break,
+            }
+        }
+        // Pages read only for an entry that never parsed aren't the segment's
+        let used = offset.div_ceil(self.file.page_size()).max(1) as u64;
+        (found, used.min(span))
+    }
+
+    /// Whether a parsed entry could have come from a real catalog
+    fn plausible(&self, path: &str, metadata: &FileMetadata) -> bool {
+        if normalize_path(path).ok().as_deref() != Some(path) || path == "/" {
+            return false;
+        }
+        let in_range = |block: &u64| *block == HOLE_BLOCK || *block < self.total_blocks;
+        if !metadata.blocks.iter().all(in_range) {
+            return false;
+        }
+        match metadata.file_type {
+            FileType::Directory => metadata.blocks.is_empty(),
+            _ => {
+                let capacity = metadata.blocks.len() as u64 * self.file.page_size() as u64;
+                metadata.inline_data.is_some() || metadata.size <= capacity
+            }
+        }
+    }
+
+    /// Turn allocated pages that nothing references into files
+    ///
+    /// Each run of consecutive unreferenced, non-zero pages becomes
+    /// `/lost+found/block-<first page>`.
+    fn carve(
+        &mut self,
+        entries: &BTreeMap<String, FileMetadata>,
+        metadata_pages: &BTreeSet<u64>,
+    ) -> Vec<(String, FileMetadata)> {
+        let referenced: BTreeSet<u64> = entries.values().flat_map(|m| m.data_blocks()).collect();
+        let page_size = self.file.page_size();
+        let mut carved = Vec::new();
+        let mut run: Vec<u64> = Vec::new();
+        let mut last_len = 0;
+        for page_id in RESERVED_PAGES..=self.total_blocks {
+            let data = (page_id < self.total_blocks
+                && self.candidate(page_id)
+                && !referenced.contains(&page_id)
+                && !metadata_pages.contains(&page_id))
+                .then(|| self.read(page_id))
+                .flatten()
+                .filter(|data| data.iter().any(|&b| b != 0));
+
+            if let Some(data) = &data {
+                if run.last().is_some_and(|&last| last + 1 == page_id) {
+                    run.push(page_id);
+                    last_len = data.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+                    continue;
+                }
+            }
+            if let Some(&first) = run.first() {
+                let size = ((run.len() - 1) * page_size + last_len) as u64;
+                let path = format!("{LOST_AND_FOUND}/block-{first}");
+                carved.push((path, FileMetadata::new(FileType::File, size, std::mem::take(&mut run))));
+            }
+            if let Some(data) = data {
+                run.push(page_id);
+                last_len = data.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+            }
+        }
+        carved
+    }
+}
+
+/// Cheap check that `bytes` starts with a length-prefixed absolute path
+fn looks_like_entry(bytes: &[u8]) -> bool {
+    let Some(len) = bytes.get(..8) else {
+        return false;
+    };
+    let len = u64::from_le_bytes(len.try_into().unwrap());
+    (1..=MAX_PATH_LENGTH as u64).contains(&len) && bytes.get(8) == Some(&b'/')
+}
+
+fn is_eof(error: &bincode::Error) -> bool {
+    matches!(&**error, bincode::ErrorKind::Io(e) if e.kind() == std::io::ErrorKind::UnexpectedEof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Overwrite one page slot of a closed cartridge with junk
+    fn smash_page(path: &Path, page_id: u64) {
+        use std::io::{Seek, SeekFrom, Write};
+        let slot = (crate::header::PAGE_SIZE + crate::io::CHECKSUM_SIZE) as u64;
+        let mut file = std::fs::OpenOptions::new().write(true).open(path).unwrap();
+        file.seek(SeekFrom::Start(page_id * slot)).unwrap();
+        file.write_all(&vec![0xA5; slot as usize]).unwrap();
+    }
+
+    #[test]
+    fn test_recovers_named_files_from_segments() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("damaged.cart");
+        let mut cart = Cartridge::create_at(&path, "damaged", "Damaged").unwrap();
+        cart.create_dir("/docs").unwrap();
+        for i in 0..300 {
+            let content = format!("file number {i} ").repeat(60);
+            cart.create_file(&format!("/docs/report-{i:03}.txt"), content.as_bytes()).unwrap();
+        }
+        cart.set_times("/docs/report-007.txt", Some(1_000), Some(2_000)).unwrap();
+        cart.close().unwrap();
+
+        smash_page(&path, 1);
+        assert!(Cartridge::open(&path).is_err());
+
+        let before = std::fs::read(&path).unwrap();
+        let session = Cartridge::open_recovery(&path).unwrap();
+        assert!(!session.catalog_intact());
+        assert!(session.allocator_intact());
+        assert!(session.entries_recovered() >= 301);
+        assert_eq!(
+            session.read_file("/docs/report-042.txt").unwrap(),
+            "file number 42 ".repeat(60).into_bytes()
+        );
+
+        let target_path = dir.path().join("rescued.cart");
+        let mut target = Cartridge::create_at(&target_path, "rescued", "Rescued").unwrap();
+        let report = session.salvage_to(&mut target).unwrap();
+        assert_eq!(report.files_salvaged, 300);
+        assert!(report.skipped.is_empty());
+        drop(session);
+        assert_eq!(std::fs::read(&path).unwrap(), before, "source was written to");
+
+        for i in 0..300 {
+            let content = format!("file number {i} ").repeat(60);
+            let read = target.read_file(&format!("/docs/report-{i:03}.txt")).unwrap();
+            assert_eq!(read, content.as_bytes());
+        }
+        let kept = target.metadata("/docs/report-007.txt").unwrap();
+        assert_eq!((kept.created_at, kept.modified_at), (1_000, 2_000));
+        assert_eq!(target.slug().unwrap(), "rescued");
+    }
+
+    #[test]
+    fn test_carves_content_when_catalog_is_lost() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("small.cart");
+        let mut cart = Cartridge::create_at(&path, "small", "Small").unwrap();
+        let content: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8 + 1).collect();
+        cart.create_file("/data.bin", &content).unwrap();
+        cart.close().unwrap();
+
+        smash_page(&path, 1);
+
+        let session = Cartridge::open_recovery(&path).unwrap();
+        assert_eq!(session.entries_recovered(), 0);
+        let carved = session
+            .carved_files()
+            .iter()
+            .map(|path| session.read_file(path).unwrap())
+            .find(|data| *data == content);
+        assert!(carved.is_some(), "content not among {:?}", session.carved_files());
+
+        let mut target = Cartridge::new(64);
+        let report = session.salvage_to(&mut target).unwrap();
+        assert_eq!(report.files_salvaged, session.carved_files().len());
+        assert!(!target.list_dir(LOST_AND_FOUND).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_raw_pages_and_scan() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("scan.cart");
+        let mut cart = Cartridge::create_at(&path, "scan", "Scan").unwrap();
+        cart.create_file("/marker.bin", &[0x42; 5000]).unwrap();
+        cart.close().unwrap();
+
+        let session = Cartridge::open_recovery(&path).unwrap();
+        assert!(session.catalog_intact());
+        let marked = session.scan_pages(|_, data| data[0] == 0x42);
+        let blocks = session.cartridge().metadata("/marker.bin").unwrap().blocks;
+        assert_eq!(marked, blocks);
+        assert_eq!(session.raw_page(blocks[0]).unwrap()[..4], [0x42; 4]);
+    }
+}
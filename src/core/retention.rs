@@ -0,0 +1,170 @@
+//! Which snapshots to keep as they age
+//!
+//! A [`RetentionPolicy`] keeps the newest few snapshots outright, then one
+//! per day, week and month going back as far as configured, in the style of
+//! grandfather-father-son backup rotation. Days, weeks and months are UTC
+//! calendar periods; weeks are ISO weeks.
+
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Rules for which snapshots survive
+/// `SnapshotManager::apply_retention`
+///
+/// A snapshot is kept if any rule keeps it. The newest snapshot is always
+/// kept, so a zeroed policy deletes everything else.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Keep this many of the newest snapshots
+    #[serde(default)]
+    pub keep_last: usize,
+
+    /// Keep the newest snapshot of each of this many most recent days
+    /// that have one
+    #[serde(default)]
+    pub keep_daily: usize,
+
+    /// Keep the newest snapshot of each of this many most recent weeks
+    /// that have one
+    #[serde(default)]
+    pub keep_weekly: usize,
+
+    /// Keep the newest snapshot of each of this many most recent months
+    /// that have one
+    #[serde(default)]
+    pub keep_monthly: usize,
+
+    /// Snapshots younger than this many seconds are never deleted
+    #[serde(default)]
+    pub min_age_secs: u64,
+}
+
+impl RetentionPolicy {
+    /// Ids of the snapshots this policy would delete, newest first
+    ///
+    /// `snapshots` are `(id, created_at)` pairs. Times, `now` included, are
+    /// microseconds since the Unix epoch, as in snapshot metadata.
+    pub fn expired(&self, snapshots: &[(u64, u64)], now: u64) -> Vec<u64> {
+        let mut newest_first = snapshots.to_vec();
+        newest_first.sort_by_key(|&(id, created_at)| std::cmp::Reverse((created_at, id)));
+
+        let mut keep: HashSet<u64> = newest_first
+            .iter()
+            .take(self.keep_last.max(1))
+            .map(|&(id, _)| id)
+            .collect();
+        let min_age = self.min_age_secs.saturating_mul(1_000_000);
+        keep.extend(
+            newest_first
+                .iter()
+                .filter(|&&(_, created_at)| now.saturating_sub(created_at) < min_age)
+                .map(|&(id, _)| id),
+        );
+
+        let buckets: [(usize, Bucket); 3] = [
+            (self.keep_daily, |t| (t.year(), t.ordinal())),
+            (self.keep_weekly, |t| {
+                let week = t.iso_week();
+                (week.year(), week.week())
+            }),
+            (self.keep_monthly, |t| (t.year(), t.month())),
+        ];
+        for (count, bucket) in buckets {
+            let mut seen = HashSet::new();
+            for &(id, created_at) in &newest_first {
+                if seen.len() == count {
+                    break;
+                }
+                let Some(time) = DateTime::from_timestamp_micros(created_at as i64) else {
+                    continue;
+                };
+                if seen.insert(bucket(time)) {
+                    keep.insert(id);
+                }
+            }
+        }
+
+        newest_first
+            .into_iter()
+            .map(|(id, _)| id)
+            .filter(|id| !keep.contains(id))
+            .collect()
+    }
+}
+
+/// Maps a time to the calendar period it falls in
+type Bucket = fn(DateTime<Utc>) -> (i32, u32);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 3_600 * 1_000_000;
+    const DAY: u64 = 24 * HOUR;
+
+    /// 2024-01-01T12:00:00Z, a Monday
+    const START: u64 = 1_704_110_400 * 1_000_000;
+
+    fn expired(policy: RetentionPolicy, snapshots: &[(u64, u64)], now: u64) -> Vec<u64> {
+        let mut ids = policy.expired(snapshots, now);
+        ids.sort_unstable();
+        ids
+    }
+
+    #[test]
+    fn test_keep_last_never_drops_newest() {
+        let snapshots: Vec<_> = (0..5).map(|i| (i, START + i * HOUR)).collect();
+        let now = START + DAY;
+
+        assert_eq!(expired(RetentionPolicy::default(), &snapshots, now), [0, 1, 2, 3]);
+        let policy = RetentionPolicy {
+            keep_last: 2,
+            ..Default::default()
+        };
+        assert_eq!(expired(policy, &snapshots, now), [0, 1, 2]);
+    }
+
+    #[test]
+    fn test_calendar_buckets() {
+        // Two snapshots a day for 60 days
+        let snapshots: Vec<_> = (0..120)
+            .map(|i| (i, START + (i / 2) * DAY + (i % 2) * HOUR))
+            .collect();
+        let now = START + 60 * DAY;
+        let kept = |policy| {
+            let expired = expired(policy, &snapshots, now);
+            (0..120).filter(|id| !expired.contains(id)).collect::<Vec<u64>>()
+        };
+
+        let daily = RetentionPolicy {
+            keep_daily: 3,
+            ..Default::default()
+        };
+        assert_eq!(kept(daily), [115, 117, 119]);
+
+        // Day 59 is a Thursday; the week before ends on day 55
+        let weekly = RetentionPolicy {
+            keep_weekly: 2,
+            ..Default::default()
+        };
+        assert_eq!(kept(weekly), [111, 119]);
+
+        // The last of January is day 30; day 59 is February 29th
+        let monthly = RetentionPolicy {
+            keep_monthly: 12,
+            ..Default::default()
+        };
+        assert_eq!(kept(monthly), [61, 119]);
+    }
+
+    #[test]
+    fn test_min_age_protects_recent_snapshots() {
+        let snapshots: Vec<_> = (0..4).map(|i| (i, START + i * DAY)).collect();
+        let policy = RetentionPolicy {
+            min_age_secs: 36 * 3_600,
+            ..Default::default()
+        };
+        assert_eq!(expired(policy, &snapshots, START + 3 * DAY + HOUR), [0, 1]);
+    }
+}
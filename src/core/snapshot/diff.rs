@@ -0,0 +1,113 @@
+//! What changed between two catalog states
+//!
+//! Snapshots save the catalog alongside their pages, so two snapshots, or a
+//! snapshot and the live cartridge, can be compared from metadata alone
+//! without reading any file content.
+
+use crate::catalog::{Catalog, FileMetadata};
+use crate::transfer::is_internal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Paths that differ between an older and a newer catalog state
+///
+/// Each list is sorted. Internal `/.cartridge` files are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotDiff {
+    /// Paths only in the newer state
+    pub added: Vec<String>,
+
+    /// Paths only in the older state
+    pub removed: Vec<String>,
+
+    /// Paths in both whose content or type differs
+    pub modified: Vec<String>,
+}
+
+impl SnapshotDiff {
+    /// Compare two catalogs
+    ///
+    /// A path counts as modified when its type, size, modification time,
+    /// content hash, symlink target or stored blocks (or inline content)
+    /// changed. Metadata-only edits such as permissions or content type
+    /// don't count.
+    pub fn between(old: &Catalog, new: &Catalog) -> Self {
+        let mut remaining: HashMap<&String, &FileMetadata> = old
+            .iter_prefix(String::new())
+            .filter(|(path, _)| !is_internal(path))
+            .collect();
+
+        let mut diff = SnapshotDiff::default();
+        for (path, metadata) in new.iter_prefix(String::new()) {
+            if is_internal(path) {
+                continue;
+            }
+            match remaining.remove(path) {
+                None => diff.added.push(path.clone()),
+                Some(before) if content_changed(before, metadata) => {
+                    diff.modified.push(path.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        diff.removed = remaining.into_keys().cloned().collect();
+
+        diff.added.sort();
+        diff.removed.sort();
+        diff.modified.sort();
+        diff
+    }
+
+    /// Whether nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    /// Number of changed paths
+    pub fn len(&self) -> usize {
+        self.added.len() + self.removed.len() + self.modified.len()
+    }
+}
+
+fn content_changed(old: &FileMetadata, new: &FileMetadata) -> bool {
+    old.file_type != new.file_type
+        || old.size != new.size
+        || old.modified_at != new.modified_at
+        || matches!((old.content_hash, new.content_hash), (Some(a), Some(b)) if a != b)
+        || old.blocks != new.blocks
+        || old.inline_data != new.inline_data
+        || old.symlink_target() != new.symlink_target()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::FileType;
+
+    fn file(size: u64) -> FileMetadata {
+        FileMetadata::new(FileType::File, size, vec![])
+    }
+
+    #[test]
+    fn test_between() {
+        let mut old = Catalog::new(1);
+        old.insert("/same", file(1)).unwrap();
+        old.insert("/gone", file(1)).unwrap();
+        old.insert("/grown", file(1)).unwrap();
+        old.insert("/.cartridge/audit", file(1)).unwrap();
+
+        let mut new = old.clone();
+        new.delete("/gone").unwrap();
+        new.insert("/grown", file(2)).unwrap();
+        new.insert("/b-new", file(1)).unwrap();
+        new.insert("/a-new", file(1)).unwrap();
+        new.insert("/.cartridge/audit", file(9)).unwrap();
+
+        let diff = SnapshotDiff::between(&old, &new);
+        assert_eq!(diff.added, ["/a-new", "/b-new"]);
+        assert_eq!(diff.removed, ["/gone"]);
+        assert_eq!(diff.modified, ["/grown"]);
+        assert_eq!(diff.len(), 4);
+        assert!(SnapshotDiff::between(&new, &new).is_empty());
+    }
+}
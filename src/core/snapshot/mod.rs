@@ -6,11 +6,34 @@
 //! - Concurrent read access to stable versions
 //!
 //! Snapshots are lightweight and share unchanged pages with the parent.
+//! Each also saves the catalog as it stood, which [`SnapshotManager::diff`]
+//! compares.
+//!
+//! An incremental snapshot stores only the pages that differ from its parent
+//! snapshot, which may itself be incremental. Restoring one applies the chain
+//! from its full base snapshot down, so a snapshot can't be deleted while
+//! another depends on it. [`SnapshotManager::export_snapshot`] and
+//! [`SnapshotManager::import_snapshot`] move single snapshots between
+//! snapshot directories as one stream. [`SnapshotManager::apply_retention`]
+//! thins old snapshots out according to a [`RetentionPolicy`].
+//!
+//! Snapshots of a cartridge that is encrypted at rest are encrypted too:
+//! given a [`PageCipher`] through [`SnapshotManager::set_cipher`], the
+//! manager seals every page and the saved catalog with it, and marks the
+//! snapshot [`encrypted`](SnapshotMetadata::encrypted).
+
+mod diff;
 
+pub use diff::SnapshotDiff;
+
+use crate::catalog::Catalog;
+use crate::encryption::PageCipher;
 use crate::error::{CartridgeError, Result};
 use crate::header::Header;
+use crate::retention::RetentionPolicy;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -36,10 +59,25 @@ pub struct SnapshotMetadata {
     pub header: Header,
 
     /// Modified pages since snapshot (for COW)
+    ///
+    /// For an incremental snapshot, the pages it stores: those that changed
+    /// since its parent.
     pub modified_pages: HashSet<u64>,
 
     /// Snapshot size in bytes
     pub size_bytes: u64,
+
+    /// Snapshot this one stores changes against; `None` for a full snapshot
+    #[serde(default)]
+    pub parent_id: Option<u64>,
+
+    /// Pages the parent had that this snapshot no longer has
+    #[serde(default)]
+    pub removed_pages: Vec<u64>,
+
+    /// Pages and catalog are sealed with the cartridge's page cipher
+    #[serde(default)]
+    pub encrypted: bool,
 }
 
 impl SnapshotMetadata {
@@ -59,9 +97,17 @@ impl SnapshotMetadata {
             header,
             modified_pages: HashSet::new(),
             size_bytes: 0,
+            parent_id: None,
+            removed_pages: Vec::new(),
+            encrypted: false,
         }
     }
 
+    /// Whether this snapshot stores only changes against a parent
+    pub fn is_incremental(&self) -> bool {
+        self.parent_id.is_some()
+    }
+
     /// Get snapshot age in seconds
     pub fn age_seconds(&self) -> u64 {
         let now = SystemTime::now()
@@ -80,24 +126,84 @@ pub struct SnapshotManager {
 
     /// Snapshot data directory
     snapshot_dir: PathBuf,
+
+    /// Snapshot directories the last scan couldn't load
+    warnings: Vec<String>,
+
+    /// Cipher for encrypted snapshots (see [`set_cipher`](Self::set_cipher))
+    cipher: Option<PageCipher>,
 }
 
 impl SnapshotManager {
     /// Create a new snapshot manager
+    ///
+    /// Snapshots already in `snapshot_dir` are loaded, so they can be listed
+    /// straight away. Directories whose metadata is missing or unreadable
+    /// are skipped and reported by [`warnings`](Self::warnings).
     pub fn new<P: AsRef<Path>>(snapshot_dir: P) -> Result<Self> {
         let snapshot_dir = snapshot_dir.as_ref().to_path_buf();
 
         // Create snapshot directory if it doesn't exist
         if !snapshot_dir.exists() {
-            std::fs::create_dir_all(&snapshot_dir).map_err(|e| {
-                CartridgeError::Allocation(format!("Failed to create snapshot dir: {}", e))
-            })?;
+            std::fs::create_dir_all(&snapshot_dir)?;
         }
 
-        Ok(SnapshotManager {
+        let mut manager = SnapshotManager {
             snapshots: HashMap::new(),
             snapshot_dir,
-        })
+            warnings: Vec::new(),
+            cipher: None,
+        };
+        manager.reload()?;
+        Ok(manager)
+    }
+
+    /// Rescan the snapshot directory, replacing what is loaded
+    ///
+    /// Picks up snapshots created or deleted by another manager or
+    /// process since this one was created.
+    pub fn reload(&mut self) -> Result<()> {
+        self.snapshots.clear();
+        self.warnings.clear();
+        for id in self.ids_on_disk()? {
+            match self.read_metadata(id) {
+                Ok(metadata) => {
+                    self.snapshots.insert(id, metadata);
+                }
+                Err(e) => {
+                    let warning = format!("snapshot_{}: {}", id, e);
+                    tracing::warn!("Skipping snapshot: {}", warning);
+                    self.warnings.push(warning);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Snapshot directories skipped by the last scan, and why
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Encrypt the snapshots this manager creates, and decrypt encrypted
+    /// ones, with `cipher`
+    ///
+    /// Without a cipher, reading an encrypted snapshot's pages or catalog
+    /// fails with [`CartridgeError::EncryptionRequired`].
+    pub fn set_cipher(&mut self, cipher: PageCipher) {
+        self.cipher = Some(cipher);
+    }
+
+    /// The cipher for `metadata`'s pages and catalog: `None` for a
+    /// plaintext snapshot
+    fn cipher_for(&self, metadata: &SnapshotMetadata) -> Result<Option<&PageCipher>> {
+        if !metadata.encrypted {
+            return Ok(None);
+        }
+        self.cipher
+            .as_ref()
+            .map(Some)
+            .ok_or(CartridgeError::EncryptionRequired)
     }
 
     /// Create a new snapshot
@@ -110,6 +216,8 @@ impl SnapshotManager {
         pages: &HashMap<u64, Vec<u8>>,
     ) -> Result<u64> {
         let mut metadata = SnapshotMetadata::new(name, description, parent_path, header);
+        metadata.id = self.unused_id(metadata.id);
+        metadata.encrypted = self.cipher.is_some();
 
         // Calculate snapshot size
         let mut total_size = 0;
@@ -127,6 +235,64 @@ impl SnapshotManager {
         Ok(snapshot_id)
     }
 
+    /// Create a snapshot that stores only the pages that differ from
+    /// snapshot `parent_id`
+    ///
+    /// `pages` is the full page set, as for
+    /// [`create_snapshot`](Self::create_snapshot); it is compared against
+    /// the parent's restored pages. Fails with
+    /// [`CartridgeError::SnapshotNotFound`] if the parent doesn't exist.
+    pub fn create_snapshot_incremental(
+        &mut self,
+        parent_id: u64,
+        name: String,
+        description: String,
+        parent_path: PathBuf,
+        header: Header,
+        pages: &HashMap<u64, Vec<u8>>,
+    ) -> Result<u64> {
+        let base = self.restore_snapshot(parent_id)?;
+
+        let mut metadata = SnapshotMetadata::new(name, description, parent_path, header);
+        metadata.id = self.unused_id(metadata.id);
+        metadata.parent_id = Some(parent_id);
+        metadata.encrypted = self.cipher.is_some();
+
+        let changed: HashMap<u64, Vec<u8>> = pages
+            .iter()
+            .filter(|(page_id, data)| base.get(page_id) != Some(*data))
+            .map(|(&page_id, data)| (page_id, data.clone()))
+            .collect();
+        metadata.modified_pages = changed.keys().copied().collect();
+        metadata.removed_pages =
+            base.keys().filter(|id| !pages.contains_key(id)).copied().collect();
+        metadata.removed_pages.sort_unstable();
+        metadata.size_bytes = changed.values().map(|data| data.len() as u64).sum();
+
+        self.write_snapshot(&metadata, &changed)?;
+
+        let snapshot_id = metadata.id;
+        self.snapshots.insert(snapshot_id, metadata);
+
+        Ok(snapshot_id)
+    }
+
+    /// Directory holding snapshot `snapshot_id`
+    fn snapshot_path(&self, snapshot_id: u64) -> PathBuf {
+        self.snapshot_dir.join(format!("snapshot_{}", snapshot_id))
+    }
+
+    /// `id`, or the next id after it not already taken on disk
+    ///
+    /// Ids are creation times in microseconds, so two snapshots taken in
+    /// quick succession could otherwise collide.
+    fn unused_id(&self, mut id: u64) -> u64 {
+        while self.snapshot_path(id).exists() {
+            id += 1;
+        }
+        id
+    }
+
     /// Write snapshot to disk
     fn write_snapshot(
         &self,
@@ -135,17 +301,12 @@ impl SnapshotManager {
     ) -> Result<()> {
         // Create snapshot directory
         let snapshot_path = self.snapshot_dir.join(format!("snapshot_{}", metadata.id));
-        std::fs::create_dir_all(&snapshot_path).map_err(|e| {
-            CartridgeError::Allocation(format!("Failed to create snapshot dir: {}", e))
-        })?;
+        std::fs::create_dir_all(&snapshot_path)?;
 
         // Write metadata
         let metadata_path = snapshot_path.join("metadata.json");
-        let metadata_json = serde_json::to_string_pretty(metadata).map_err(|e| {
-            CartridgeError::Allocation(format!("Failed to serialize metadata: {}", e))
-        })?;
-        std::fs::write(&metadata_path, metadata_json)
-            .map_err(|e| CartridgeError::Allocation(format!("Failed to write metadata: {}", e)))?;
+        let metadata_json = serde_json::to_string_pretty(metadata)?;
+        std::fs::write(&metadata_path, metadata_json)?;
 
         // Write pages
         let pages_path = snapshot_path.join("pages.bin");
@@ -155,39 +316,85 @@ impl SnapshotManager {
         pages_data.extend_from_slice(&(pages.len() as u64).to_le_bytes());
 
         // Write each page
+        let cipher = self.cipher_for(metadata)?;
         for (&page_id, page_data) in pages.iter() {
+            let sealed;
+            let page_data = match cipher {
+                Some(cipher) => {
+                    sealed = cipher.encrypt_page(page_id, page_data)?;
+                    &sealed
+                }
+                None => page_data,
+            };
             pages_data.extend_from_slice(&page_id.to_le_bytes());
             pages_data.extend_from_slice(&(page_data.len() as u64).to_le_bytes());
             pages_data.extend_from_slice(page_data);
         }
 
-        std::fs::write(&pages_path, pages_data)
-            .map_err(|e| CartridgeError::Allocation(format!("Failed to write pages: {}", e)))?;
+        std::fs::write(&pages_path, pages_data)?;
 
         Ok(())
     }
 
     /// Load snapshot from disk
     pub fn load_snapshot(&mut self, snapshot_id: u64) -> Result<SnapshotMetadata> {
-        let snapshot_path = self.snapshot_dir.join(format!("snapshot_{}", snapshot_id));
+        let metadata = self.read_metadata(snapshot_id)?;
+        self.snapshots.insert(snapshot_id, metadata.clone());
+        Ok(metadata)
+    }
 
-        // Read metadata
-        let metadata_path = snapshot_path.join("metadata.json");
-        let metadata_json = std::fs::read_to_string(&metadata_path)
-            .map_err(|e| CartridgeError::Allocation(format!("Failed to read metadata: {}", e)))?;
+    /// Read a snapshot's metadata from disk
+    fn read_metadata(&self, snapshot_id: u64) -> Result<SnapshotMetadata> {
+        let metadata_path = self.snapshot_path(snapshot_id).join("metadata.json");
+        if !metadata_path.exists() {
+            return Err(CartridgeError::SnapshotNotFound { id: snapshot_id });
+        }
+        let metadata_json = std::fs::read_to_string(&metadata_path)?;
 
-        let metadata: SnapshotMetadata = serde_json::from_str(&metadata_json)
-            .map_err(|e| CartridgeError::Allocation(format!("Failed to parse metadata: {}", e)))?;
+        serde_json::from_str(&metadata_json).map_err(|e| {
+            CartridgeError::Corruption(format!(
+                "Snapshot {} has unreadable metadata: {}",
+                snapshot_id, e
+            ))
+        })
+    }
 
-        self.snapshots.insert(snapshot_id, metadata.clone());
+    /// Ids of every snapshot in the snapshot directory, loaded or not
+    fn ids_on_disk(&self) -> Result<Vec<u64>> {
+        let mut ids = Vec::new();
+        for entry in std::fs::read_dir(&self.snapshot_dir)? {
+            let name = entry?.file_name();
+            let id = name
+                .to_str()
+                .and_then(|name| name.strip_prefix("snapshot_"))
+                .and_then(|id| id.parse::<u64>().ok());
+            ids.extend(id);
+        }
+        ids.sort_unstable();
+        Ok(ids)
+    }
 
-        Ok(metadata)
+    /// Snapshots whose parent is `snapshot_id`
+    ///
+    /// Read from disk, so snapshots this manager hasn't loaded count.
+    /// Directories with unreadable metadata are ignored.
+    pub fn dependents(&self, snapshot_id: u64) -> Result<Vec<u64>> {
+        let mut dependents = Vec::new();
+        for id in self.ids_on_disk()? {
+            let Ok(metadata) = self.read_metadata(id) else {
+                continue;
+            };
+            if metadata.parent_id == Some(snapshot_id) {
+                dependents.push(id);
+            }
+        }
+        Ok(dependents)
     }
 
     /// List all snapshots
     pub fn list_snapshots(&self) -> Vec<&SnapshotMetadata> {
         let mut snapshots: Vec<_> = self.snapshots.values().collect();
-        snapshots.sort_by_key(|s| s.created_at);
+        snapshots.sort_by_key(|s| (s.created_at, s.id));
         snapshots
     }
 
@@ -197,36 +404,88 @@ impl SnapshotManager {
     }
 
     /// Delete snapshot
+    ///
+    /// Fails with [`CartridgeError::SnapshotHasDependents`] while incremental
+    /// snapshots are based on it; delete those first.
     pub fn delete_snapshot(&mut self, snapshot_id: u64) -> Result<()> {
+        let dependents = self.dependents(snapshot_id)?;
+        if !dependents.is_empty() {
+            return Err(CartridgeError::SnapshotHasDependents {
+                id: snapshot_id,
+                dependents,
+            });
+        }
+
         // Remove from memory
         self.snapshots.remove(&snapshot_id);
 
         // Remove from disk
         let snapshot_path = self.snapshot_dir.join(format!("snapshot_{}", snapshot_id));
         if snapshot_path.exists() {
-            std::fs::remove_dir_all(&snapshot_path).map_err(|e| {
-                CartridgeError::Allocation(format!("Failed to delete snapshot: {}", e))
-            })?;
+            std::fs::remove_dir_all(&snapshot_path)?;
         }
 
         Ok(())
     }
 
     /// Restore snapshot (returns pages)
+    ///
+    /// An incremental snapshot is resolved through its parents: the full
+    /// base snapshot's pages, then each snapshot's changes in turn. Fails
+    /// with [`CartridgeError::SnapshotChainBroken`] if a parent is missing.
     pub fn restore_snapshot(&self, snapshot_id: u64) -> Result<HashMap<u64, Vec<u8>>> {
-        let snapshot_path = self.snapshot_dir.join(format!("snapshot_{}", snapshot_id));
-        let pages_path = snapshot_path.join("pages.bin");
+        // Walk up to the base, newest first
+        let mut chain = vec![self.read_metadata(snapshot_id)?];
+        while let Some(parent_id) = chain.last().and_then(|s| s.parent_id) {
+            if chain.iter().any(|s| s.id == parent_id) {
+                return Err(CartridgeError::Corruption(format!(
+                    "Snapshot {} is its own ancestor",
+                    parent_id
+                )));
+            }
+            match self.read_metadata(parent_id) {
+                Ok(parent) => chain.push(parent),
+                Err(CartridgeError::SnapshotNotFound { .. }) => {
+                    return Err(CartridgeError::SnapshotChainBroken {
+                        id: snapshot_id,
+                        missing: parent_id,
+                    })
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut pages = HashMap::new();
+        for snapshot in chain.iter().rev() {
+            for page_id in &snapshot.removed_pages {
+                pages.remove(page_id);
+            }
+            pages.extend(self.read_pages(snapshot)?);
+        }
+        Ok(pages)
+    }
+
+    /// Read the pages stored in one snapshot's `pages.bin`
+    fn read_pages(&self, snapshot: &SnapshotMetadata) -> Result<HashMap<u64, Vec<u8>>> {
+        let snapshot_id = snapshot.id;
+        let cipher = self.cipher_for(snapshot)?;
+        let pages_path = self.snapshot_path(snapshot_id).join("pages.bin");
+        if !pages_path.exists() {
+            return Err(CartridgeError::SnapshotNotFound { id: snapshot_id });
+        }
 
         // Read pages
-        let pages_data = std::fs::read(&pages_path)
-            .map_err(|e| CartridgeError::Allocation(format!("Failed to read pages: {}", e)))?;
+        let pages_data = std::fs::read(&pages_path)?;
+        let truncated = || {
+            CartridgeError::Corruption(format!("Snapshot {} has truncated pages", snapshot_id))
+        };
 
         let mut offset = 0;
         let mut pages = HashMap::new();
 
         // Read page count
         if pages_data.len() < 8 {
-            return Err(CartridgeError::Allocation("Invalid pages data".to_string()));
+            return Err(truncated());
         }
         let page_count = u64::from_le_bytes(pages_data[0..8].try_into().unwrap());
         offset += 8;
@@ -234,9 +493,7 @@ impl SnapshotManager {
         // Read each page
         for _ in 0..page_count {
             if offset + 16 > pages_data.len() {
-                return Err(CartridgeError::Allocation(
-                    "Truncated pages data".to_string(),
-                ));
+                return Err(truncated());
             }
 
             let page_id = u64::from_le_bytes(pages_data[offset..offset + 8].try_into().unwrap());
@@ -247,12 +504,14 @@ impl SnapshotManager {
             offset += 8;
 
             if offset + page_len > pages_data.len() {
-                return Err(CartridgeError::Allocation(
-                    "Truncated page data".to_string(),
-                ));
+                return Err(truncated());
             }
 
-            let page_data = pages_data[offset..offset + page_len].to_vec();
+            let page_data = &pages_data[offset..offset + page_len];
+            let page_data = match cipher {
+                Some(cipher) => cipher.decrypt_page(page_id, page_data)?,
+                None => page_data.to_vec(),
+            };
             offset += page_len;
 
             pages.insert(page_id, page_data);
@@ -261,12 +520,53 @@ impl SnapshotManager {
         Ok(pages)
     }
 
+    /// Save the catalog as it stood when a snapshot was taken
+    pub fn save_catalog(&self, snapshot_id: u64, catalog: &Catalog) -> Result<()> {
+        let metadata = self.read_metadata(snapshot_id)?;
+        let mut data = catalog.to_bytes()?;
+        if let Some(cipher) = self.cipher_for(&metadata)? {
+            data = cipher.seal(&catalog_context(snapshot_id), &data)?;
+        }
+        std::fs::write(self.snapshot_path(snapshot_id).join("catalog.bin"), data)?;
+        Ok(())
+    }
+
+    /// Load the catalog saved with a snapshot
+    ///
+    /// Fails with [`CartridgeError::SnapshotCatalogMissing`] for snapshots
+    /// taken before catalogs were saved.
+    pub fn load_catalog(&self, snapshot_id: u64) -> Result<Catalog> {
+        let metadata = self.read_metadata(snapshot_id)?;
+        let catalog_path = self.snapshot_path(snapshot_id).join("catalog.bin");
+        if !catalog_path.exists() {
+            return Err(CartridgeError::SnapshotCatalogMissing { id: snapshot_id });
+        }
+        let data = std::fs::read(&catalog_path)?;
+        let data = match self.cipher_for(&metadata)? {
+            Some(cipher) => cipher.open(&catalog_context(snapshot_id), &data)?,
+            None => data,
+        };
+        let mut catalog = Catalog::from_bytes(&data)?;
+        catalog.normalize_keys();
+        Ok(catalog)
+    }
+
+    /// Paths added, removed and modified from snapshot `older` to `newer`
+    pub fn diff(&self, older: u64, newer: u64) -> Result<SnapshotDiff> {
+        Ok(SnapshotDiff::between(
+            &self.load_catalog(older)?,
+            &self.load_catalog(newer)?,
+        ))
+    }
+
     /// Get total snapshot storage size
     pub fn total_size_bytes(&self) -> u64 {
         self.snapshots.values().map(|s| s.size_bytes).sum()
     }
 
     /// Prune old snapshots (keep only N most recent)
+    ///
+    /// Snapshots that a kept incremental snapshot depends on are kept too.
     pub fn prune_old_snapshots(&mut self, keep_count: usize) -> Result<Vec<u64>> {
         let mut snapshots: Vec<_> = self.snapshots.values().cloned().collect();
         snapshots.sort_by_key(|s| std::cmp::Reverse(s.created_at));
@@ -274,12 +574,140 @@ impl SnapshotManager {
         let mut deleted = Vec::new();
 
         for snapshot in snapshots.iter().skip(keep_count) {
+            if !self.dependents(snapshot.id)?.is_empty() {
+                continue;
+            }
             self.delete_snapshot(snapshot.id)?;
             deleted.push(snapshot.id);
         }
 
         Ok(deleted)
     }
+
+    /// Delete the snapshots `policy` doesn't keep, returning their ids
+    ///
+    /// Considers the loaded snapshots; [`reload`](Self::reload) first to
+    /// include any created elsewhere since. The newest is never deleted,
+    /// and neither is one that a kept incremental snapshot depends on.
+    pub fn apply_retention(&mut self, policy: &RetentionPolicy) -> Result<Vec<u64>> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_micros() as u64;
+        let snapshots: Vec<_> = self.snapshots.values().map(|s| (s.id, s.created_at)).collect();
+        let expired = policy.expired(&snapshots, now);
+
+        // Newest first, so a chain is deleted from its tip down
+        let mut deleted = Vec::new();
+        for id in expired {
+            if !self.dependents(id)?.is_empty() {
+                continue;
+            }
+            self.delete_snapshot(id)?;
+            deleted.push(id);
+        }
+        Ok(deleted)
+    }
+
+    /// Write one snapshot's files to `writer` as a single stream
+    ///
+    /// An incremental snapshot is exported on its own; export its parents
+    /// too, or it can't be restored where it is imported.
+    pub fn export_snapshot(&self, snapshot_id: u64, writer: &mut dyn Write) -> Result<()> {
+        let snapshot_path = self.snapshot_path(snapshot_id);
+        self.read_metadata(snapshot_id)?;
+
+        writer.write_all(EXPORT_MAGIC)?;
+        for name in EXPORT_FILES {
+            let path = snapshot_path.join(name);
+            if !path.exists() {
+                continue;
+            }
+            let data = std::fs::read(&path)?;
+            writer.write_all(&(name.len() as u8).to_le_bytes())?;
+            writer.write_all(name.as_bytes())?;
+            writer.write_all(&(data.len() as u64).to_le_bytes())?;
+            writer.write_all(&data)?;
+        }
+        writer.write_all(&[0])?;
+        Ok(())
+    }
+
+    /// Add a snapshot written by [`export_snapshot`](Self::export_snapshot)
+    ///
+    /// Returns its id, which is kept from the export. Fails with
+    /// [`CartridgeError::AlreadyExists`] if a snapshot with that id is
+    /// already here.
+    pub fn import_snapshot(&mut self, reader: &mut dyn Read) -> Result<u64> {
+        let invalid = |reason: &str| {
+            CartridgeError::Corruption(format!("Invalid snapshot export: {}", reason))
+        };
+
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != EXPORT_MAGIC {
+            return Err(invalid("bad magic"));
+        }
+
+        let mut files = HashMap::new();
+        loop {
+            let mut name_len = [0u8; 1];
+            reader.read_exact(&mut name_len)?;
+            if name_len[0] == 0 {
+                break;
+            }
+            let mut name = vec![0u8; name_len[0] as usize];
+            reader.read_exact(&mut name)?;
+            let name = String::from_utf8(name).map_err(|_| invalid("file name is not UTF-8"))?;
+            let &name = EXPORT_FILES
+                .iter()
+                .find(|known| **known == name)
+                .ok_or_else(|| invalid(&format!("unexpected file {}", name)))?;
+
+            let mut len = [0u8; 8];
+            reader.read_exact(&mut len)?;
+            let mut data = Vec::new();
+            reader.take(u64::from_le_bytes(len)).read_to_end(&mut data)?;
+            if data.len() as u64 != u64::from_le_bytes(len) {
+                return Err(invalid("truncated"));
+            }
+            files.insert(name, data);
+        }
+
+        let metadata: SnapshotMetadata = files
+            .get("metadata.json")
+            .and_then(|json| serde_json::from_slice(json).ok())
+            .ok_or_else(|| invalid("missing or unreadable metadata.json"))?;
+        if !files.contains_key("pages.bin") {
+            return Err(invalid("missing pages.bin"));
+        }
+
+        let snapshot_path = self.snapshot_path(metadata.id);
+        if snapshot_path.exists() {
+            return Err(CartridgeError::AlreadyExists {
+                path: snapshot_path.display().to_string(),
+            });
+        }
+        std::fs::create_dir_all(&snapshot_path)?;
+        for (name, data) in &files {
+            std::fs::write(snapshot_path.join(name), data)?;
+        }
+
+        let snapshot_id = metadata.id;
+        self.snapshots.insert(snapshot_id, metadata);
+        Ok(snapshot_id)
+    }
+}
+
+/// Leading bytes of an exported snapshot
+const EXPORT_MAGIC: &[u8; 8] = b"CARTSNP1";
+
+/// Files an exported snapshot carries, in export order
+const EXPORT_FILES: [&str; 3] = ["metadata.json", "pages.bin", "catalog.bin"];
+
+/// Associated data binding an encrypted catalog to its snapshot
+fn catalog_context(snapshot_id: u64) -> Vec<u8> {
+    [b"snapshot-catalog".as_slice(), &snapshot_id.to_le_bytes()].concat()
 }
 
 #[cfg(test)]
@@ -367,6 +795,10 @@ mod tests {
         manager.delete_snapshot(snapshot_id).unwrap();
 
         assert_eq!(manager.list_snapshots().len(), 0);
+        assert!(matches!(
+            manager.load_snapshot(snapshot_id),
+            Err(CartridgeError::SnapshotNotFound { id }) if id == snapshot_id
+        ));
     }
 
     #[test]
@@ -387,7 +819,7 @@ mod tests {
                     format!("snapshot_{}", i),
                     format!("desc_{}", i),
                     PathBuf::from("/test"),
-                    header.clone(),
+                    header,
                     &pages,
                 )
                 .unwrap();
@@ -402,6 +834,174 @@ mod tests {
         assert_eq!(manager.list_snapshots().len(), 2);
     }
 
+    fn create(
+        manager: &mut SnapshotManager,
+        parent: Option<u64>,
+        pages: &HashMap<u64, Vec<u8>>,
+    ) -> u64 {
+        let (name, description, path) = ("s".to_string(), String::new(), PathBuf::from("/test"));
+        match parent {
+            Some(parent) => manager
+                .create_snapshot_incremental(parent, name, description, path, Header::new(), pages)
+                .unwrap(),
+            None => manager
+                .create_snapshot(name, description, path, Header::new(), pages)
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_incremental_chain() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = SnapshotManager::new(temp_dir.path()).unwrap();
+
+        let mut pages: HashMap<u64, Vec<u8>> =
+            (0..10).map(|id| (id, vec![id as u8; 100])).collect();
+        let base = create(&mut manager, None, &pages);
+
+        pages.insert(3, vec![33; 100]);
+        let first = create(&mut manager, Some(base), &pages);
+        let metadata = manager.get_snapshot(first).unwrap();
+        assert_eq!(metadata.parent_id, Some(base));
+        assert_eq!(metadata.modified_pages, HashSet::from([3]));
+        assert_eq!(metadata.size_bytes, 100);
+
+        pages.remove(&5);
+        pages.insert(10, vec![10; 100]);
+        let second = create(&mut manager, Some(first), &pages);
+        assert_eq!(manager.get_snapshot(second).unwrap().removed_pages, [5]);
+
+        assert_eq!(manager.restore_snapshot(second).unwrap(), pages);
+        assert_eq!(manager.restore_snapshot(first).unwrap()[&5], vec![5; 100]);
+    }
+
+    #[test]
+    fn test_chain_dependencies() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = SnapshotManager::new(temp_dir.path()).unwrap();
+        let pages = HashMap::from([(0, vec![1, 2, 3])]);
+        let base = create(&mut manager, None, &pages);
+        let child = create(&mut manager, Some(base), &pages);
+
+        // The base can't go while the child needs it, and pruning keeps it
+        assert!(matches!(
+            manager.delete_snapshot(base),
+            Err(CartridgeError::SnapshotHasDependents { id, ref dependents })
+                if id == base && dependents == &[child]
+        ));
+        assert!(manager.prune_old_snapshots(1).unwrap().is_empty());
+
+        // A missing parent is reported as such
+        std::fs::remove_dir_all(temp_dir.path().join(format!("snapshot_{}", base))).unwrap();
+        assert!(matches!(
+            manager.restore_snapshot(child),
+            Err(CartridgeError::SnapshotChainBroken { id, missing })
+                if id == child && missing == base
+        ));
+        assert!(matches!(
+            manager.create_snapshot_incremental(
+                base,
+                "s".into(),
+                String::new(),
+                PathBuf::from("/test"),
+                Header::new(),
+                &pages
+            ),
+            Err(CartridgeError::SnapshotNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_snapshots_survive_restart() {
+        let temp_dir = TempDir::new().unwrap();
+        let pages = HashMap::from([(0, vec![1, 2, 3]), (1, vec![4; 50])]);
+        let (base, child) = {
+            let mut manager = SnapshotManager::new(temp_dir.path()).unwrap();
+            let base = create(&mut manager, None, &pages);
+            (base, create(&mut manager, Some(base), &pages))
+        };
+
+        // A half-written snapshot and one with garbage metadata
+        std::fs::create_dir(temp_dir.path().join("snapshot_1")).unwrap();
+        std::fs::create_dir(temp_dir.path().join("snapshot_2")).unwrap();
+        std::fs::write(temp_dir.path().join("snapshot_2/metadata.json"), b"{").unwrap();
+
+        let mut manager = SnapshotManager::new(temp_dir.path()).unwrap();
+        let listed: Vec<u64> = manager.list_snapshots().iter().map(|s| s.id).collect();
+        assert_eq!(listed, [base, child]);
+        assert_eq!(manager.warnings().len(), 2);
+        assert_eq!(manager.get_snapshot(child).unwrap().parent_id, Some(base));
+        assert_eq!(manager.restore_snapshot(child).unwrap(), pages);
+
+        // Changes made through another manager show up on reload
+        let mut other = SnapshotManager::new(temp_dir.path()).unwrap();
+        let third = create(&mut other, None, &pages);
+        assert!(manager.get_snapshot(third).is_none());
+        manager.reload().unwrap();
+        assert!(manager.get_snapshot(third).is_some());
+    }
+
+    #[test]
+    fn test_apply_retention() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = SnapshotManager::new(temp_dir.path()).unwrap();
+        let pages = HashMap::from([(0, vec![1, 2, 3])]);
+        let base = create(&mut manager, None, &pages);
+        let other = create(&mut manager, None, &pages);
+        let child = create(&mut manager, Some(base), &pages);
+
+        let day = 24 * 3_600 * 1_000_000;
+        for (id, days_old) in [(base, 10), (other, 5), (child, 0)] {
+            let snapshot = manager.snapshots.get_mut(&id).unwrap();
+            snapshot.created_at -= days_old * day;
+        }
+
+        // Only the newest is kept, but the base it depends on survives too
+        let deleted = manager.apply_retention(&RetentionPolicy::default()).unwrap();
+        assert_eq!(deleted, [other]);
+        assert!(manager.restore_snapshot(child).is_ok());
+        assert!(manager.get_snapshot(other).is_none());
+    }
+
+    #[test]
+    fn test_export_import() {
+        let source_dir = TempDir::new().unwrap();
+        let mut source = SnapshotManager::new(source_dir.path()).unwrap();
+        let pages = HashMap::from([(0, vec![1, 2, 3]), (1, vec![4; 50])]);
+        let base = create(&mut source, None, &pages);
+        let child = create(&mut source, Some(base), &HashMap::from([(0, vec![9])]));
+
+        let target_dir = TempDir::new().unwrap();
+        let mut target = SnapshotManager::new(target_dir.path()).unwrap();
+        let mut exported = Vec::new();
+        source.export_snapshot(child, &mut exported).unwrap();
+        assert_eq!(target.import_snapshot(&mut exported.as_slice()).unwrap(), child);
+
+        // Without its parent the child can't be restored yet
+        assert!(matches!(
+            target.restore_snapshot(child),
+            Err(CartridgeError::SnapshotChainBroken { .. })
+        ));
+        let mut exported_base = Vec::new();
+        source.export_snapshot(base, &mut exported_base).unwrap();
+        target.import_snapshot(&mut exported_base.as_slice()).unwrap();
+        assert_eq!(
+            target.restore_snapshot(child).unwrap(),
+            source.restore_snapshot(child).unwrap()
+        );
+
+        // Importing twice, or garbage, fails
+        assert!(matches!(
+            target.import_snapshot(&mut exported.as_slice()),
+            Err(CartridgeError::AlreadyExists { .. })
+        ));
+        assert!(target.import_snapshot(&mut &b"not a snapshot"[..]).is_err());
+        exported.truncate(exported.len() - 10);
+        let other_dir = TempDir::new().unwrap();
+        let mut other = SnapshotManager::new(other_dir.path()).unwrap();
+        assert!(other.import_snapshot(&mut exported.as_slice()).is_err());
+    }
+
     #[test]
     fn test_snapshot_size_tracking() {
         let temp_dir = TempDir::new().unwrap();
@@ -426,4 +1026,30 @@ mod tests {
 
         assert_eq!(manager.total_size_bytes(), 3000);
     }
+
+    #[test]
+    fn test_damaged_catalog_is_corruption() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = SnapshotManager::new(temp_dir.path()).unwrap();
+
+        let snapshot_id = manager
+            .create_snapshot(
+                "test".to_string(),
+                "desc".to_string(),
+                PathBuf::from("/test"),
+                Header::new(),
+                &HashMap::new(),
+            )
+            .unwrap();
+        std::fs::write(
+            manager.snapshot_path(snapshot_id).join("catalog.bin"),
+            b"not a catalog",
+        )
+        .unwrap();
+
+        assert!(matches!(
+            manager.load_catalog(snapshot_id),
+            Err(CartridgeError::Corruption(_))
+        ));
+    }
 }
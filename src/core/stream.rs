@@ -0,0 +1,265 @@
+//! Partial and streaming file access
+//!
+//! [`Cartridge::read_range`] and [`FileReader`] load only the pages a read
+//! touches, so a large file can be processed without holding all of it in
+//! memory. [`Cartridge::write_stream`] fills a file from any [`Read`] a
+//! chunk at a time.
+//!
+//! Content encrypted with [`Cartridge::enable_encryption`] is sealed as a
+//! whole, so such files are decrypted in full when the reader is opened.
+
+use crate::audit::Operation;
+use crate::cartridge::Cartridge;
+use crate::catalog::FileMetadata;
+use crate::error::{CartridgeError, Result};
+use crate::header::PAGE_SIZE;
+use crate::iam::Action;
+use crate::validation::normalize_path;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Bytes [`Cartridge::write_stream`] takes from its source per write
+pub const STREAM_CHUNK_SIZE: usize = 64 * PAGE_SIZE;
+
+impl Cartridge {
+    /// Read up to `len` bytes of a file starting at `offset`
+    ///
+    /// Follows symlinks like [`read_file`](Self::read_file). Only the pages
+    /// the range covers are loaded. The result is short when the range runs
+    /// past the end of the file, and empty when `offset` is at or beyond it.
+    pub fn read_range(&self, path: &str, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let mut reader = self.open_reader(path)?;
+        reader.position = offset;
+        let len = len.min(reader.len().saturating_sub(offset)) as usize;
+        let mut content = vec![0u8; len];
+        let mut filled = 0;
+        while filled < len {
+            filled += reader.fill(&mut content[filled..])?;
+        }
+        Ok(content)
+    }
+
+    /// Open a file for reading a page at a time
+    ///
+    /// Follows symlinks like [`read_file`](Self::read_file). Access is
+    /// checked and the read audited once, here; the reader borrows the
+    /// cartridge, so the file can't change underneath it.
+    pub fn open_reader(&self, path: &str) -> Result<FileReader<'_>> {
+        let path = &normalize_path(path)?;
+        self.check_access(&Action::Read, path)?;
+        let target = match self.follow(path)? {
+            Some((target, _)) => target,
+            None => path.clone(),
+        };
+        self.check_access(&Action::Read, &target)?;
+
+        let metadata = self
+            .catalog()
+            .get(&target)?
+            .ok_or_else(|| CartridgeError::NotFound {
+                path: target.clone(),
+            })?;
+        if !metadata.is_file() {
+            return Err(CartridgeError::NotAFile { path: target });
+        }
+
+        let encrypted = metadata.user_metadata.get("encrypted").is_some_and(|v| v == "true");
+        let content = if encrypted {
+            Some(self.read_file_nofollow(&target)?)
+        } else {
+            self.audit_log(Operation::Read, &target);
+            metadata.inline_data.clone()
+        };
+        Ok(FileReader {
+            cartridge: self,
+            path: target,
+            metadata,
+            content,
+            position: 0,
+        })
+    }
+
+    /// Write a file from `reader`, creating it or replacing its content
+    ///
+    /// The source is read [`STREAM_CHUNK_SIZE`] bytes at a time; the first
+    /// chunk replaces the content and the rest are appended, so memory use
+    /// stays flat however long the stream is. Returns the bytes written. If
+    /// `reader` fails part way, the file keeps what was written before.
+    pub fn write_stream(&mut self, path: &str, reader: &mut dyn Read) -> Result<u64> {
+        let path = &normalize_path(path)?;
+        let mut chunk = vec![0u8; STREAM_CHUNK_SIZE];
+        let mut written = 0u64;
+        loop {
+            let n = read_chunk(reader, &mut chunk)?;
+            if written == 0 {
+                if self.exists(path)? {
+                    self.write_file(path, &chunk[..n])?;
+                } else {
+                    self.create_file(path, &chunk[..n])?;
+                }
+            } else if n > 0 {
+                self.append_file(path, &chunk[..n])?;
+            }
+            written += n as u64;
+            if n < chunk.len() {
+                return Ok(written);
+            }
+        }
+    }
+}
+
+/// Fill `buf` from `reader`, short only at end of stream
+fn read_chunk(reader: &mut dyn Read, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(filled)
+}
+
+/// A file opened with [`Cartridge::open_reader`]
+///
+/// Implements [`Read`] and [`Seek`]; each read loads at most one page.
+pub struct FileReader<'a> {
+    cartridge: &'a Cartridge,
+    path: String,
+    metadata: FileMetadata,
+    /// Whole content when it isn't read from pages (inline or encrypted)
+    content: Option<Vec<u8>>,
+    position: u64,
+}
+
+impl FileReader<'_> {
+    /// The file's length in bytes
+    pub fn len(&self) -> u64 {
+        match &self.content {
+            Some(content) => content.len() as u64,
+            None => self.metadata.size,
+        }
+    }
+
+    /// Whether the file is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The path being read, after following symlinks
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Copy from the current position into `buf`, stopping at a page
+    /// boundary; 0 at end of file
+    fn fill(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let remaining = self.len().saturating_sub(self.position);
+        let n = (buf.len() as u64).min(remaining) as usize;
+        if n == 0 {
+            return Ok(0);
+        }
+        let start = self.position as usize;
+        let n = match &self.content {
+            Some(content) => {
+                buf[..n].copy_from_slice(&content[start..start + n]);
+                n
+            }
+            None => {
+                let page_size = self.cartridge.page_size();
+                let (block, within) = (start / page_size, start % page_size);
+                let page = self.cartridge.load_page(&self.path, self.metadata.blocks[block])?;
+                let n = n.min(page_size - within);
+                buf[..n].copy_from_slice(&page[within..within + n]);
+                n
+            }
+        };
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Read for FileReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.fill(buf).map_err(io::Error::other)
+    }
+}
+
+impl Seek for FileReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.len().checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        };
+        self.position = position.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before start of file")
+        })?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> (Cartridge, Vec<u8>) {
+        let mut cart = Cartridge::new(100);
+        let content: Vec<u8> = (0..3 * PAGE_SIZE + 100).map(|i| (i % 251) as u8).collect();
+        cart.create_file("big.bin", &content).unwrap();
+        cart.create_file("small.txt", b"hello world").unwrap();
+        (cart, content)
+    }
+
+    #[test]
+    fn test_read_range() {
+        let (cart, content) = sample();
+
+        let across = cart.read_range("big.bin", PAGE_SIZE as u64 - 10, 20).unwrap();
+        assert_eq!(across, &content[PAGE_SIZE - 10..PAGE_SIZE + 10]);
+        let tail = cart.read_range("big.bin", content.len() as u64 - 5, 100).unwrap();
+        assert_eq!(tail, &content[content.len() - 5..]);
+        assert!(cart.read_range("big.bin", 10 * PAGE_SIZE as u64, 1).unwrap().is_empty());
+
+        // Inline content
+        assert_eq!(cart.read_range("small.txt", 6, 5).unwrap(), b"world");
+        assert!(matches!(
+            cart.read_range("missing", 0, 1),
+            Err(CartridgeError::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_reader_reads_and_seeks() {
+        let (cart, content) = sample();
+
+        let mut reader = cart.open_reader("big.bin").unwrap();
+        assert_eq!(reader.len(), content.len() as u64);
+        let mut all = Vec::new();
+        reader.read_to_end(&mut all).unwrap();
+        assert_eq!(all, content);
+
+        reader.seek(SeekFrom::End(-3)).unwrap();
+        let mut last = Vec::new();
+        reader.read_to_end(&mut last).unwrap();
+        assert_eq!(last, &content[content.len() - 3..]);
+        assert!(reader.seek(SeekFrom::Current(-(content.len() as i64) - 1)).is_err());
+    }
+
+    #[test]
+    fn test_write_stream() {
+        let mut cart = Cartridge::new(100);
+        let content: Vec<u8> = (0..2 * STREAM_CHUNK_SIZE + 7).map(|i| (i % 13) as u8).collect();
+
+        let written = cart.write_stream("streamed.bin", &mut content.as_slice()).unwrap();
+        assert_eq!(written, content.len() as u64);
+        assert_eq!(cart.read_file("streamed.bin").unwrap(), content);
+
+        // Replaces existing content, including with nothing
+        assert_eq!(cart.write_stream("streamed.bin", &mut &b"short"[..]).unwrap(), 5);
+        assert_eq!(cart.read_file("streamed.bin").unwrap(), b"short");
+        assert_eq!(cart.write_stream("streamed.bin", &mut io::empty()).unwrap(), 0);
+        assert!(cart.read_file("streamed.bin").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,215 @@
+//! Symbolic links
+//!
+//! A symlink is a catalog entry of type [`FileType::Symlink`] with no
+//! content blocks; its target path lives in the entry's `user_metadata`
+//! and is read back with [`FileMetadata::symlink_target`]. Targets are
+//! never checked when the link is created, so dangling links are fine to
+//! store. [`Cartridge::read_file`] and [`Cartridge::metadata`] follow links,
+//! giving up after [`MAX_SYMLINK_DEPTH`] hops; the `_nofollow` variants
+//! look at the link itself.
+//!
+//! Only the final path component is resolved: a link to a directory does
+//! not make paths *through* it reachable.
+
+use crate::audit::Operation;
+use crate::cartridge::Cartridge;
+use crate::catalog::{FileMetadata, FileType};
+use crate::error::{CartridgeError, Result};
+use crate::iam::Action;
+use crate::validation::normalize_path;
+use crate::watch::ChangeKind;
+
+/// Most links followed while resolving one path (same as Linux)
+pub const MAX_SYMLINK_DEPTH: usize = 40;
+
+impl Cartridge {
+    /// Create a symbolic link at `link_path` pointing to `target`
+    ///
+    /// The target is stored as given and doesn't have to exist. Targets
+    /// starting with `/` are catalog paths; anything else is resolved
+    /// against the link's directory when followed.
+    pub fn symlink(&mut self, target: &str, link_path: &str) -> Result<()> {
+        let link_path = &normalize_path(link_path)?;
+        self.ensure_writable()?;
+        self.check_protected(link_path)?;
+        // Check IAM policy
+        self.check_access(&Action::Create, link_path)?;
+
+        if target.is_empty() {
+            return Err(CartridgeError::InvalidPath);
+        }
+        if link_path == "/" || self.exists(link_path)? {
+            return Err(CartridgeError::AlreadyExists {
+                path: link_path.to_string(),
+            });
+        }
+
+        self.catalog_mut().insert(link_path, FileMetadata::symlink(target))?;
+        self.audit_log(Operation::Create, link_path);
+        self.watchers().notify(link_path, ChangeKind::Created);
+
+        Ok(())
+    }
+
+    /// The target of a symbolic link, exactly as it was stored
+    ///
+    /// Fails with [`CartridgeError::InvalidPath`] if `path` isn't a symlink.
+    pub fn read_link(&self, path: &str) -> Result<String> {
+        let path = &normalize_path(path)?;
+        let metadata = self.metadata_nofollow(path)?;
+        metadata
+            .symlink_target()
+            .map(str::to_string)
+            .ok_or(CartridgeError::InvalidPath)
+    }
+
+    /// Resolve `path` through any chain of symlinks
+    ///
+    /// Returns the path finally reached and its metadata, or `None` if
+    /// `path` itself doesn't exist.
+    pub(crate) fn follow(&self, path: &str) -> Result<Option<(String, FileMetadata)>> {
+        let mut current = path.to_string();
+        for _ in 0..=MAX_SYMLINK_DEPTH {
+            let Some(metadata) = self.catalog().get(&current)? else {
+                if current == path {
+                    return Ok(None);
+                }
+                return Err(CartridgeError::DanglingSymlink(format!(
+                    "{} -> {}",
+                    path, current
+                )));
+            };
+            if metadata.file_type != FileType::Symlink {
+                return Ok(Some((current, metadata)));
+            }
+            let target = metadata.symlink_target().unwrap_or_default();
+            current = resolve_link_target(&current, target);
+        }
+        Err(CartridgeError::SymlinkLoop(path.to_string()))
+    }
+}
+
+/// Catalog path that a link at `link` pointing to `target` refers to
+///
+/// `.` and `..` are resolved lexically; `..` never climbs above the root.
+/// A relative target keeps the link's own leading-slash style, so
+/// `/docs/latest -> v2.md` resolves to `/docs/v2.md`.
+fn resolve_link_target(link: &str, target: &str) -> String {
+    let (base, rooted) = if target.starts_with('/') {
+        ("", true)
+    } else {
+        let dir = link.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+        (dir, link.starts_with('/'))
+    };
+
+    let mut parts: Vec<&str> = Vec::new();
+    for part in base.split('/').chain(target.split('/')) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+
+    let joined = parts.join("/");
+    if rooted {
+        format!("/{}", joined)
+    } else {
+        joined
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_link_target() {
+        assert_eq!(resolve_link_target("docs/latest", "v2.md"), "docs/v2.md");
+        assert_eq!(resolve_link_target("/docs/latest", "v2.md"), "/docs/v2.md");
+        assert_eq!(resolve_link_target("docs/a/link", "../b.txt"), "docs/b.txt");
+        assert_eq!(resolve_link_target("docs/link", "/etc/hosts"), "/etc/hosts");
+        assert_eq!(resolve_link_target("link", "./x/../y"), "y");
+        assert_eq!(resolve_link_target("link", "../../y"), "y");
+    }
+
+    #[test]
+    fn test_symlink_read_and_metadata_follow() {
+        let mut cart = Cartridge::new(100);
+        cart.create_file("docs/v2.md", b"version two").unwrap();
+        cart.symlink("v2.md", "docs/latest").unwrap();
+        cart.symlink("docs/latest", "current").unwrap();
+
+        assert_eq!(cart.read_file("current").unwrap(), b"version two");
+        assert_eq!(cart.metadata("current").unwrap().size, 11);
+        assert_eq!(cart.read_link("current").unwrap(), "docs/latest");
+
+        let link = cart.metadata_nofollow("docs/latest").unwrap();
+        assert_eq!(link.file_type, FileType::Symlink);
+        assert_eq!(link.symlink_target(), Some("v2.md"));
+        assert!(cart.read_file_nofollow("docs/latest").is_err());
+        assert!(matches!(cart.read_link("docs/v2.md"), Err(CartridgeError::InvalidPath)));
+
+        // The target is internal bookkeeping, not user metadata
+        assert!(cart.user_metadata("docs/latest").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_dangling_symlink() {
+        let mut cart = Cartridge::new(100);
+        cart.symlink("missing.txt", "link").unwrap();
+
+        assert!(cart.exists("link").unwrap());
+        assert!(cart.metadata_nofollow("link").unwrap().is_symlink());
+        assert!(matches!(
+            cart.read_file("link"),
+            Err(CartridgeError::DanglingSymlink(_))
+        ));
+        assert!(matches!(
+            cart.metadata("link"),
+            Err(CartridgeError::DanglingSymlink(_))
+        ));
+
+        // Creating the target brings the link to life
+        cart.create_file("missing.txt", b"here").unwrap();
+        assert_eq!(cart.read_file("link").unwrap(), b"here");
+    }
+
+    #[test]
+    fn test_symlink_loop() {
+        let mut cart = Cartridge::new(100);
+        cart.symlink("b", "a").unwrap();
+        cart.symlink("a", "b").unwrap();
+        cart.symlink("self", "self").unwrap();
+
+        assert!(matches!(cart.read_file("a"), Err(CartridgeError::SymlinkLoop(_))));
+        assert!(matches!(cart.metadata("self"), Err(CartridgeError::SymlinkLoop(_))));
+
+        // A chain of exactly MAX_SYMLINK_DEPTH links still resolves
+        cart.create_file("end", b"end").unwrap();
+        let mut previous = "end".to_string();
+        for i in 0..MAX_SYMLINK_DEPTH {
+            let link = format!("chain{}", i);
+            cart.symlink(&previous, &link).unwrap();
+            previous = link;
+        }
+        assert_eq!(cart.read_file(&previous).unwrap(), b"end");
+        cart.symlink(&previous, "one-too-many").unwrap();
+        assert!(cart.read_file("one-too-many").is_err());
+    }
+
+    #[test]
+    fn test_delete_removes_link_not_target() {
+        let mut cart = Cartridge::new(100);
+        cart.create_file("target.txt", b"keep me").unwrap();
+        cart.symlink("target.txt", "link").unwrap();
+
+        assert!(cart.symlink("elsewhere", "link").is_err());
+        cart.delete_file("link").unwrap();
+
+        assert!(!cart.exists("link").unwrap());
+        assert_eq!(cart.read_file("target.txt").unwrap(), b"keep me");
+    }
+}
@@ -0,0 +1,155 @@
+//! The internal `.cartridge/` directory
+//!
+//! The manifest, dedup index, audit trail and pack digests live under
+//! `/.cartridge/`. Listings leave them out, and the ordinary mutating API
+//! refuses them with [`CartridgeError::ProtectedPath`](crate::CartridgeError::ProtectedPath):
+//! a stray delete of the manifest would leave the cartridge without its slug
+//! and title. Code that maintains these files goes through
+//! [`Cartridge::system`], and backup tooling enumerates them with
+//! [`Cartridge::list_system_entries`].
+
+use crate::cartridge::Cartridge;
+use crate::catalog::FileMetadata;
+use crate::error::Result;
+use crate::transfer::is_internal;
+
+/// The directory internal files live in
+pub const SYSTEM_DIR: &str = "/.cartridge";
+
+/// Privileged access to `.cartridge/` files, from [`Cartridge::system`]
+///
+/// Everything else about a write still applies: read-only cartridges refuse
+/// it and IAM policies are checked as usual.
+pub struct SystemFiles<'a> {
+    cartridge: &'a mut Cartridge,
+}
+
+impl Cartridge {
+    /// Change files under `.cartridge/`, which the ordinary API refuses
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::core::Cartridge;
+    /// # let mut cart = Cartridge::in_memory("my-data", "My Data")?;
+    /// let manifest = cart.read_file("/.cartridge/manifest.json")?;
+    /// cart.system().write("/.cartridge/manifest.json", &manifest)?;
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn system(&mut self) -> SystemFiles<'_> {
+        SystemFiles { cartridge: self }
+    }
+
+    /// Every entry under `.cartridge/` with its metadata, in path order
+    pub fn list_system_entries(&self) -> Result<Vec<(String, FileMetadata)>> {
+        self.catalog().list_prefix(&format!("{}/", SYSTEM_DIR))
+    }
+}
+
+impl SystemFiles<'_> {
+    /// Create `path` or replace its content, creating `.cartridge/` first
+    /// if needed
+    pub fn write(&mut self, path: &str, content: &[u8]) -> Result<()> {
+        self.cartridge.privileged(|cart| {
+            if cart.exists(path)? {
+                return cart.write_file(path, content);
+            }
+            if is_internal(path) && !cart.exists(SYSTEM_DIR)? {
+                cart.create_dir(SYSTEM_DIR)?;
+            }
+            cart.create_file(path, content)
+        })
+    }
+
+    /// Append to `path`, creating it like [`write`](Self::write) if it
+    /// doesn't exist
+    pub fn append(&mut self, path: &str, content: &[u8]) -> Result<()> {
+        if !self.cartridge.exists(path)? {
+            return self.write(path, content);
+        }
+        self.cartridge
+            .privileged(|cart| cart.append_file(path, content))
+    }
+
+    /// Delete `path`
+    pub fn delete(&mut self, path: &str) -> Result<()> {
+        self.cartridge.privileged(|cart| cart.delete_file(path))
+    }
+
+    /// Rename `from` to `to`; either may be outside `.cartridge/`
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<()> {
+        self.cartridge.privileged(|cart| cart.rename(from, to))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CartridgeError;
+
+    const MANIFEST: &str = "/.cartridge/manifest.json";
+
+    fn protected(result: Result<impl std::fmt::Debug>) -> bool {
+        matches!(result, Err(CartridgeError::ProtectedPath { .. }))
+    }
+
+    #[test]
+    fn test_ordinary_api_refuses_system_paths() {
+        let mut cart = Cartridge::in_memory("guarded", "Guarded").unwrap();
+        cart.create_file("/notes.txt", b"notes").unwrap();
+
+        assert!(protected(cart.delete_file(MANIFEST)));
+        assert!(protected(cart.write_file(MANIFEST, b"{}")));
+        assert!(protected(cart.append_file(MANIFEST, b"x")));
+        assert!(protected(cart.truncate(MANIFEST, 0)));
+        assert!(protected(cart.rename(MANIFEST, "/manifest.json")));
+        assert!(protected(cart.rename("/notes.txt", ".cartridge/notes.txt")));
+        assert!(protected(cart.clone_file("/notes.txt", "/.cartridge/copy")));
+        assert!(protected(cart.create_file(".cartridge/new", b"x")));
+        assert!(protected(cart.create_dir("/.cartridge/sub")));
+        assert!(protected(cart.symlink("/notes.txt", "/.cartridge/link")));
+        assert!(protected(cart.begin_batch().delete(MANIFEST)));
+
+        assert_eq!(cart.slug().unwrap(), "guarded");
+        assert_eq!(cart.read_file("/notes.txt").unwrap(), b"notes");
+    }
+
+    #[test]
+    fn test_system_route_changes_system_files() {
+        let mut cart = Cartridge::in_memory("privileged", "Privileged").unwrap();
+
+        cart.system()
+            .write("/.cartridge/backup.json", b"[1]")
+            .unwrap();
+        cart.system()
+            .append("/.cartridge/backup.json", b"[2]")
+            .unwrap();
+        assert_eq!(
+            cart.read_file("/.cartridge/backup.json").unwrap(),
+            b"[1][2]"
+        );
+        cart.system()
+            .rename("/.cartridge/backup.json", "/.cartridge/old.json")
+            .unwrap();
+        cart.system().delete("/.cartridge/old.json").unwrap();
+        assert!(!cart.exists("/.cartridge/old.json").unwrap());
+
+        // The privilege ends with the call
+        assert!(protected(cart.create_file("/.cartridge/after", b"x")));
+    }
+
+    #[test]
+    fn test_list_system_entries() {
+        let mut cart = Cartridge::in_memory("listing", "Listing").unwrap();
+        cart.create_file("/data.txt", b"data").unwrap();
+        cart.system().write("/.cartridge/extra.bin", b"x").unwrap();
+
+        let paths: Vec<_> = cart
+            .list_system_entries()
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(paths, ["/.cartridge/extra.bin", MANIFEST]);
+    }
+}
@@ -0,0 +1,114 @@
+//! Counters and gauges reported through the `metrics` crate facade
+//!
+//! With the `metrics` feature enabled, the call sites in the cartridge, the
+//! I/O layer, the allocator and the audit logger record into whatever
+//! recorder the application installed (a Prometheus exporter, say). Without
+//! it every function here is empty and compiles away. Values from every
+//! cartridge in the process are added together; the names below are the
+//! metric keys.
+
+/// Counter: content bytes written to files
+pub const BYTES_WRITTEN: &str = "cartridge_bytes_written_total";
+/// Counter: content bytes returned by whole-file reads
+pub const BYTES_READ: &str = "cartridge_bytes_read_total";
+/// Counter: bytes written to backing files, journal excluded
+pub const DISK_BYTES_WRITTEN: &str = "cartridge_disk_bytes_written_total";
+/// Counter: bytes read from backing files
+pub const DISK_BYTES_READ: &str = "cartridge_disk_bytes_read_total";
+/// Counter: content pages written to disk by flushes
+pub const PAGES_FLUSHED: &str = "cartridge_pages_flushed_total";
+/// Counter: times a container was extended
+pub const GROW_EVENTS: &str = "cartridge_grow_events_total";
+/// Counter: allocations refused for lack of free blocks
+pub const ALLOCATION_FAILURES: &str = "cartridge_allocation_failures_total";
+/// Counter: operations denied by the IAM policy
+pub const POLICY_DENIALS: &str = "cartridge_policy_denials_total";
+/// Counter: content pages served from the page cache
+pub const PAGE_CACHE_HITS: &str = "cartridge_page_cache_hits_total";
+/// Counter: content pages loaded from disk
+pub const PAGE_CACHE_MISSES: &str = "cartridge_page_cache_misses_total";
+/// Counter: audit entries overwritten before they were flushed
+pub const AUDIT_ENTRIES_DROPPED: &str = "cartridge_audit_entries_dropped_total";
+/// Gauge: blocks in the container, as of the last flush or growth
+pub const TOTAL_BLOCKS: &str = "cartridge_total_blocks";
+/// Gauge: free blocks, as of the last flush
+pub const FREE_BLOCKS: &str = "cartridge_free_blocks";
+/// Gauge: allocator fragmentation score (0.0 - 1.0), as of the last flush
+pub const FRAGMENTATION: &str = "cartridge_fragmentation";
+
+#[cfg(feature = "metrics")]
+fn count(key: &'static str, n: u64) {
+    metrics::counter!(key).increment(n);
+}
+
+#[cfg(not(feature = "metrics"))]
+#[inline(always)]
+fn count(_key: &'static str, _n: u64) {}
+
+#[cfg(feature = "metrics")]
+fn set(key: &'static str, value: f64) {
+    metrics::gauge!(key).set(value);
+}
+
+#[cfg(not(feature = "metrics"))]
+#[inline(always)]
+fn set(_key: &'static str, _value: f64) {}
+
+#[inline]
+pub(crate) fn bytes_written(bytes: usize) {
+    count(BYTES_WRITTEN, bytes as u64);
+}
+
+#[inline]
+pub(crate) fn bytes_read(bytes: usize) {
+    count(BYTES_READ, bytes as u64);
+}
+
+#[inline]
+pub(crate) fn disk_bytes_written(bytes: usize) {
+    count(DISK_BYTES_WRITTEN, bytes as u64);
+}
+
+#[inline]
+pub(crate) fn disk_bytes_read(bytes: usize) {
+    count(DISK_BYTES_READ, bytes as u64);
+}
+
+#[inline]
+pub(crate) fn pages_flushed(pages: usize) {
+    count(PAGES_FLUSHED, pages as u64);
+}
+
+#[inline]
+pub(crate) fn grew(total_blocks: u64) {
+    count(GROW_EVENTS, 1);
+    set(TOTAL_BLOCKS, total_blocks as f64);
+}
+
+#[inline]
+pub(crate) fn allocation_failed() {
+    count(ALLOCATION_FAILURES, 1);
+}
+
+#[inline]
+pub(crate) fn policy_denied() {
+    count(POLICY_DENIALS, 1);
+}
+
+#[inline]
+pub(crate) fn page_cache(hit: bool) {
+    count(if hit { PAGE_CACHE_HITS } else { PAGE_CACHE_MISSES }, 1);
+}
+
+#[inline]
+pub(crate) fn audit_dropped(entries: usize) {
+    count(AUDIT_ENTRIES_DROPPED, entries as u64);
+}
+
+/// Only called with the feature on: the fragmentation score isn't free
+#[cfg(feature = "metrics")]
+pub(crate) fn space(total_blocks: u64, free_blocks: u64, fragmentation: f64) {
+    set(TOTAL_BLOCKS, total_blocks as f64);
+    set(FREE_BLOCKS, free_blocks as f64);
+    set(FRAGMENTATION, fragmentation);
+}
@@ -0,0 +1,783 @@
+//! Bulk transfer between a cartridge and the host filesystem
+//!
+//! [`Cartridge::import_dir`] walks a host directory tree into the catalog,
+//! sizing the container once up front instead of letting every write trigger
+//! its own growth. [`Cartridge::export_dir`] extracts a prefix back out.
+//!
+//! [`Cartridge::export_tar`] and [`Cartridge::import_tar`] do the same over a
+//! tar stream, without touching the host filesystem at all.
+
+use crate::cartridge::Cartridge;
+use crate::content_type;
+use crate::error::Result;
+use crate::iam::PatternMatcher;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// A host file's creation and modification times, in Unix seconds, where
+/// the platform reports them
+fn host_times(metadata: &std::fs::Metadata) -> (Option<u64>, Option<u64>) {
+    let secs = |time: std::io::Result<std::time::SystemTime>| {
+        time.ok()?.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+    };
+    (secs(metadata.created()), secs(metadata.modified()))
+}
+
+/// Internal entries that imports must never overwrite and exports skip
+const INTERNAL_PREFIX: &str = ".cartridge";
+
+/// How [`Cartridge::import_dir`] treats symbolic links
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SymlinkPolicy {
+    /// Record symlinks as skipped entries (default)
+    #[default]
+    Skip,
+    /// Import whatever the link points to; link cycles are reported as skipped
+    Follow,
+    /// Store each link as a cartridge symlink with its target unchanged
+    Preserve,
+}
+
+/// Options for [`Cartridge::import_dir`]
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    /// Only import files whose path relative to the host root matches this
+    /// glob (`*` for one segment, `**` for any depth), e.g. `"**/*.json"`
+    pub include: Option<String>,
+
+    /// What to do with symbolic links
+    pub symlinks: SymlinkPolicy,
+
+    /// Set `content_type` from each file's extension (default: true)
+    pub detect_content_type: bool,
+
+    /// Replace files that already exist in the cartridge (default: true)
+    pub overwrite: bool,
+
+    /// Store pages that are all zeros as holes, like
+    /// [`Cartridge::write_sparse`] (default: false)
+    pub sparse: bool,
+
+    /// Keep each file's modification time, and creation time where the
+    /// host records one, instead of the time of the import (default: true)
+    pub preserve_times: bool,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        ImportOptions {
+            include: None,
+            symlinks: SymlinkPolicy::Skip,
+            detect_content_type: true,
+            overwrite: true,
+            sparse: false,
+            preserve_times: true,
+        }
+    }
+}
+
+/// A host or cartridge entry that a transfer left out, and why
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedEntry {
+    /// Host path (imports) or cartridge path (exports)
+    pub path: String,
+    /// Human-readable reason
+    pub reason: String,
+}
+
+/// Summary of a [`Cartridge::import_dir`] or [`Cartridge::import_tar`] call
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportReport {
+    /// Files written into the cartridge
+    pub files_imported: usize,
+    /// Total content bytes written
+    pub bytes_written: u64,
+    /// Files that didn't match the `include` glob
+    pub files_filtered: usize,
+    /// Symlinks stored as cartridge symlinks
+    pub symlinks_imported: usize,
+    /// Entries that couldn't or shouldn't be imported
+    pub skipped: Vec<SkippedEntry>,
+}
+
+/// Summary of a [`Cartridge::export_dir`] or [`Cartridge::export_tar`] call
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportReport {
+    /// Files written to the host
+    pub files_exported: usize,
+    /// Total content bytes written
+    pub bytes_written: u64,
+    /// Symlinks written as tar link entries
+    pub symlinks_exported: usize,
+    /// Entries that couldn't be exported safely
+    pub skipped: Vec<SkippedEntry>,
+}
+
+impl Cartridge {
+    /// Import a host directory tree
+    ///
+    /// Each regular file under `host_path` is written to
+    /// `dest_prefix/<relative path>` (or just `<relative path>` when
+    /// `dest_prefix` is empty). The container is grown once, before any
+    /// writes, to fit everything being imported.
+    ///
+    /// Unreadable entries, symlinks (unless followed or preserved) and
+    /// anything that would land under `.cartridge/` are recorded in
+    /// [`ImportReport::skipped`]
+    /// rather than failing the import. Empty directories aren't recorded;
+    /// the catalog only tracks files.
+    pub fn import_dir(
+        &mut self,
+        host_path: &Path,
+        dest_prefix: &str,
+        options: &ImportOptions,
+    ) -> Result<ImportReport> {
+        let mut report = ImportReport::default();
+        let mut pending = Vec::new();
+        let mut links = Vec::new();
+        let mut reserve = 0u64;
+        let page_size = self.page_size() as u64;
+
+        let walker = walkdir::WalkDir::new(host_path)
+            .follow_links(options.symlinks == SymlinkPolicy::Follow)
+            .sort_by_file_name();
+
+        for entry in walker {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    let path = e.path().unwrap_or(host_path);
+                    report.skipped.push(skipped(path.display(), e.to_string()));
+                    continue;
+                }
+            };
+
+            let is_link = entry.file_type().is_symlink();
+            if is_link && options.symlinks != SymlinkPolicy::Preserve {
+                report.skipped.push(skipped(entry.path().display(), "symlink"));
+                continue;
+            }
+            if !is_link && !entry.file_type().is_file() {
+                continue;
+            }
+
+            let Some(rel) = relative_path(entry.path(), host_path) else {
+                report
+                    .skipped
+                    .push(skipped(entry.path().display(), "path is not valid UTF-8"));
+                continue;
+            };
+            if let Some(pattern) = &options.include {
+                if !PatternMatcher::matches(pattern, &rel) {
+                    report.files_filtered += 1;
+                    continue;
+                }
+            }
+
+            let dest = join_prefix(dest_prefix, &rel);
+            if is_internal(&dest) {
+                report.skipped.push(skipped(
+                    entry.path().display(),
+                    "destination is reserved for cartridge metadata",
+                ));
+                continue;
+            }
+            if !options.overwrite && self.exists(&dest)? {
+                report
+                    .skipped
+                    .push(skipped(entry.path().display(), "already exists"));
+                continue;
+            }
+
+            if is_link {
+                match std::fs::read_link(entry.path()) {
+                    Ok(target) => match target.to_str() {
+                        Some(target) => links.push((dest, target.replace('\\', "/"))),
+                        None => report.skipped.push(skipped(
+                            entry.path().display(),
+                            "link target is not valid UTF-8",
+                        )),
+                    },
+                    Err(e) => report.skipped.push(skipped(entry.path().display(), e.to_string())),
+                }
+                continue;
+            }
+
+            let (size, times) = match entry.metadata() {
+                Ok(metadata) => (metadata.len(), host_times(&metadata)),
+                Err(e) => {
+                    report.skipped.push(skipped(entry.path().display(), e.to_string()));
+                    continue;
+                }
+            };
+            reserve += size.div_ceil(page_size) * page_size;
+            pending.push((entry.into_path(), dest, times));
+        }
+
+        self.reserve_bytes(reserve)?;
+
+        for (host, dest, (created, modified)) in pending {
+            let data = match std::fs::read(&host) {
+                Ok(data) => data,
+                Err(e) => {
+                    report.skipped.push(skipped(host.display(), e.to_string()));
+                    continue;
+                }
+            };
+
+            if options.sparse {
+                self.write_sparse(&dest, &data)?;
+            } else if self.exists(&dest)? {
+                self.write_file(&dest, &data)?;
+            } else {
+                self.create_file(&dest, &data)?;
+            }
+            if options.detect_content_type {
+                if let Some(mime) = content_type::from_path(&dest) {
+                    self.set_content_type(&dest, Some(mime.to_string()))?;
+                }
+            }
+            if options.preserve_times {
+                self.set_times(&dest, created, modified)?;
+            }
+
+            report.files_imported += 1;
+            report.bytes_written += data.len() as u64;
+        }
+
+        for (dest, target) in links {
+            self.replace_with_symlink(&target, &dest)?;
+            report.symlinks_imported += 1;
+        }
+
+        Ok(report)
+    }
+
+    /// Extract every file under `prefix` into `host_path`
+    ///
+    /// Paths are written relative to `prefix`, creating directories as
+    /// needed, and each file's modification time is restored from the
+    /// catalog. Internal `.cartridge/` entries are never exported, and paths
+    /// that would escape `host_path` (via `..` components) are skipped.
+    /// Symlinks are skipped too: recreating them on the host could let later
+    /// files in the export be written through them.
+    pub fn export_dir(&self, prefix: &str, host_path: &Path) -> Result<ExportReport> {
+        let mut report = ExportReport::default();
+        std::fs::create_dir_all(host_path)?;
+
+        let strip = prefix.trim_matches('/');
+        for path in self.list_dir(prefix)? {
+            if is_internal(&path) {
+                continue;
+            }
+            let metadata = self.metadata_nofollow(&path)?;
+            if metadata.is_symlink() {
+                report.skipped.push(skipped(&path, "symlink"));
+                continue;
+            }
+            if !metadata.is_file() {
+                continue;
+            }
+
+            let trimmed = path.trim_start_matches('/');
+            let rel = trimmed
+                .strip_prefix(strip)
+                .unwrap_or(trimmed)
+                .trim_start_matches('/');
+            let Some(target) = safe_join(host_path, rel) else {
+                report
+                    .skipped
+                    .push(skipped(&path, "path would escape the export directory"));
+                continue;
+            };
+
+            let data = self.read_file(&path)?;
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&target, &data)?;
+            std::fs::File::options()
+                .write(true)
+                .open(&target)?
+                .set_modified(UNIX_EPOCH + Duration::from_secs(metadata.modified_at))?;
+
+            report.files_exported += 1;
+            report.bytes_written += data.len() as u64;
+        }
+
+        Ok(report)
+    }
+}
+
+impl Cartridge {
+    /// Stream every file under `prefix` into a tar archive
+    ///
+    /// Entry paths are relative to `prefix`. Each file carries its catalog
+    /// size, permissions and modification time, and a directory entry is
+    /// written for every parent directory the first time it appears.
+    /// Symlinks become tar symlink entries with their stored target. Long
+    /// paths use the GNU long-name extension. Internal `.cartridge/` entries
+    /// are left out.
+    pub fn export_tar<W: Write>(&self, writer: W, prefix: &str) -> Result<ExportReport> {
+        let mut report = ExportReport::default();
+        let mut builder = tar::Builder::new(writer);
+        let mut dirs_written: HashSet<String> = HashSet::new();
+
+        let strip = prefix.trim_matches('/');
+        let mut paths = self.list_dir(prefix)?;
+        paths.sort();
+        for path in paths {
+            if is_internal(&path) {
+                continue;
+            }
+            let metadata = self.metadata_nofollow(&path)?;
+            if !metadata.is_file() && !metadata.is_symlink() {
+                continue;
+            }
+
+            let trimmed = path.trim_start_matches('/');
+            let rel = trimmed
+                .strip_prefix(strip)
+                .unwrap_or(trimmed)
+                .trim_start_matches('/');
+            if safe_join(Path::new(""), rel).is_none() {
+                report
+                    .skipped
+                    .push(skipped(&path, "path is not a safe relative path"));
+                continue;
+            }
+
+            // Parent directories, outermost first
+            let mut parent = String::new();
+            for part in rel
+                .split('/')
+                .rev()
+                .skip(1)
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+            {
+                parent.push_str(part);
+                parent.push('/');
+                if dirs_written.insert(parent.clone()) {
+                    let mut header = tar::Header::new_gnu();
+                    header.set_entry_type(tar::EntryType::Directory);
+                    header.set_mode(0o755);
+                    header.set_size(0);
+                    header.set_mtime(metadata.modified_at);
+                    builder.append_data(&mut header, &parent, std::io::empty())?;
+                }
+            }
+
+            if let Some(target) = metadata.symlink_target() {
+                let mut header = tar::Header::new_gnu();
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_mode(metadata.permissions);
+                header.set_size(0);
+                header.set_mtime(metadata.modified_at);
+                builder.append_link(&mut header, rel, target)?;
+                report.symlinks_exported += 1;
+                continue;
+            }
+
+            let data = self.read_file(&path)?;
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_mode(metadata.permissions);
+            header.set_size(data.len() as u64);
+            header.set_mtime(metadata.modified_at);
+            builder.append_data(&mut header, rel, data.as_slice())?;
+
+            report.files_exported += 1;
+            report.bytes_written += data.len() as u64;
+        }
+
+        builder.into_inner()?.flush()?;
+        Ok(report)
+    }
+
+    /// Ingest a tar stream, placing its files under `dest_prefix`
+    ///
+    /// Regular files are written (replacing existing ones) and get a content
+    /// type from their extension. Symlink entries become cartridge symlinks.
+    /// Directory entries are implied by the file paths and need no catalog
+    /// entry. Hard links, special files, paths that climb out with `..`, and
+    /// anything that would land under `.cartridge/` are recorded as skipped.
+    pub fn import_tar<R: Read>(&mut self, reader: R, dest_prefix: &str) -> Result<ImportReport> {
+        let mut report = ImportReport::default();
+        let mut archive = tar::Archive::new(reader);
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let raw_path = entry.path()?.to_string_lossy().into_owned();
+
+            let entry_type = entry.header().entry_type();
+            match entry_type {
+                tar::EntryType::Regular | tar::EntryType::Continuous => {}
+                tar::EntryType::Symlink => {}
+                tar::EntryType::Directory => continue,
+                other => {
+                    report.skipped.push(skipped(
+                        &raw_path,
+                        format!("unsupported entry type {:?}", other),
+                    ));
+                    continue;
+                }
+            }
+
+            let rel = match safe_join(Path::new(""), &raw_path)
+                .and_then(|p| relative_path(&p, Path::new("")))
+            {
+                Some(rel) => rel,
+                None => {
+                    report
+                        .skipped
+                        .push(skipped(&raw_path, "path is not a safe relative path"));
+                    continue;
+                }
+            };
+
+            let dest = join_prefix(dest_prefix, &rel);
+            if is_internal(&dest) {
+                report.skipped.push(skipped(
+                    &raw_path,
+                    "destination is reserved for cartridge metadata",
+                ));
+                continue;
+            }
+
+            if entry_type == tar::EntryType::Symlink {
+                let target = entry
+                    .link_name()?
+                    .map(|target| target.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                if target.is_empty() {
+                    report.skipped.push(skipped(&raw_path, "symlink has no target"));
+                    continue;
+                }
+                self.replace_with_symlink(&target, &dest)?;
+                report.symlinks_imported += 1;
+                continue;
+            }
+
+            let mut data = Vec::with_capacity(entry.size() as usize);
+            entry.read_to_end(&mut data)?;
+
+            if self.exists(&dest)? {
+                self.write_file(&dest, &data)?;
+            } else {
+                self.create_file(&dest, &data)?;
+            }
+            if let Some(mime) = content_type::from_path(&dest) {
+                self.set_content_type(&dest, Some(mime.to_string()))?;
+            }
+
+            report.files_imported += 1;
+            report.bytes_written += data.len() as u64;
+        }
+
+        Ok(report)
+    }
+
+    /// Create a symlink at `dest`, replacing whatever is there
+    pub(crate) fn replace_with_symlink(&mut self, target: &str, dest: &str) -> Result<()> {
+        if self.exists(dest)? {
+            self.delete_file(dest)?;
+        }
+        self.symlink(target, dest)
+    }
+}
+
+pub(crate) fn skipped(path: impl std::fmt::Display, reason: impl Into<String>) -> SkippedEntry {
+    SkippedEntry {
+        path: path.to_string(),
+        reason: reason.into(),
+    }
+}
+
+/// `/`-separated path of `path` relative to `root`, or `None` if not UTF-8
+pub(crate) fn relative_path(path: &Path, root: &Path) -> Option<String> {
+    let rel = path.strip_prefix(root).ok()?;
+    let parts: Option<Vec<&str>> = rel.components().map(|c| c.as_os_str().to_str()).collect();
+    Some(parts?.join("/"))
+}
+
+pub(crate) fn join_prefix(prefix: &str, rel: &str) -> String {
+    let prefix = prefix.trim_end_matches('/');
+    if prefix.is_empty() {
+        rel.to_string()
+    } else {
+        format!("{}/{}", prefix, rel)
+    }
+}
+
+pub(crate) fn is_internal(path: &str) -> bool {
+    let path = path.trim_start_matches('/');
+    path == INTERNAL_PREFIX || path.starts_with(".cartridge/")
+}
+
+/// Join a cartridge-relative path onto `root`, refusing anything that could
+/// land outside it
+pub(crate) fn safe_join(root: &Path, rel: &str) -> Option<PathBuf> {
+    let rel = Path::new(rel);
+    let mut out = root.to_path_buf();
+    for component in rel.components() {
+        match component {
+            Component::Normal(part) => out.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    (out != root).then_some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::PAGE_SIZE;
+
+    #[test]
+    fn test_safe_join_rejects_escapes() {
+        let root = Path::new("/tmp/out");
+        assert_eq!(safe_join(root, "a/b.txt"), Some(root.join("a/b.txt")));
+        assert_eq!(safe_join(root, "./a.txt"), Some(root.join("a.txt")));
+        assert_eq!(safe_join(root, "../etc/passwd"), None);
+        assert_eq!(safe_join(root, "a/../../x"), None);
+        assert_eq!(safe_join(root, ""), None);
+    }
+
+    #[test]
+    fn test_import_export_round_trip() {
+        let src = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(src.path().join("docs/nested")).unwrap();
+        std::fs::write(src.path().join("readme.md"), b"# hello").unwrap();
+        std::fs::write(src.path().join("docs/data.json"), b"{}").unwrap();
+        std::fs::write(src.path().join("docs/nested/blob.bin"), vec![7u8; 3 * PAGE_SIZE + 1])
+            .unwrap();
+        std::fs::create_dir_all(src.path().join(".cartridge")).unwrap();
+        std::fs::write(src.path().join(".cartridge/manifest.json"), b"{}").unwrap();
+
+        let mut cart = Cartridge::new(10);
+        let report = cart
+            .import_dir(src.path(), "", &ImportOptions::default())
+            .unwrap();
+        assert_eq!(report.files_imported, 3);
+        assert_eq!(report.bytes_written, 7 + 2 + 3 * PAGE_SIZE as u64 + 1);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(
+            cart.metadata("docs/data.json").unwrap().content_type.as_deref(),
+            Some("application/json")
+        );
+
+        let out = tempfile::tempdir().unwrap();
+        let exported = cart.export_dir("docs", out.path()).unwrap();
+        assert_eq!(exported.files_exported, 2);
+        assert_eq!(std::fs::read(out.path().join("data.json")).unwrap(), b"{}");
+        assert_eq!(
+            std::fs::read(out.path().join("nested/blob.bin")).unwrap(),
+            vec![7u8; 3 * PAGE_SIZE + 1]
+        );
+        assert!(!out.path().join("readme.md").exists());
+    }
+
+    #[test]
+    fn test_import_glob_prefix_and_single_growth() {
+        let src = tempfile::tempdir().unwrap();
+        for i in 0..20 {
+            std::fs::write(src.path().join(format!("f{}.json", i)), vec![1u8; PAGE_SIZE]).unwrap();
+            std::fs::write(src.path().join(format!("f{}.txt", i)), b"skip me").unwrap();
+        }
+
+        let mut cart = Cartridge::new(3);
+        let options = ImportOptions {
+            include: Some("**/*.json".to_string()),
+            ..Default::default()
+        };
+        let report = cart.import_dir(src.path(), "imported/", &options).unwrap();
+        assert_eq!(report.files_imported, 20);
+        assert_eq!(report.files_filtered, 20);
+        assert!(cart.exists("imported/f0.json").unwrap());
+        assert!(!cart.exists("imported/f0.txt").unwrap());
+
+        // Grown exactly once to fit, not doubled repeatedly
+        assert_eq!(cart.header().total_blocks, 3 + 20);
+    }
+
+    #[test]
+    fn test_import_preserves_host_times() {
+        let src = tempfile::tempdir().unwrap();
+        let host = src.path().join("old.txt");
+        std::fs::write(&host, b"old").unwrap();
+        let mtime = UNIX_EPOCH + std::time::Duration::from_secs(1_500_000_000);
+        std::fs::File::options()
+            .write(true)
+            .open(&host)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+
+        let mut cart = Cartridge::new(10);
+        cart.import_dir(src.path(), "kept", &ImportOptions::default())
+            .unwrap();
+        assert_eq!(cart.metadata("kept/old.txt").unwrap().modified_at, 1_500_000_000);
+
+        let options = ImportOptions {
+            preserve_times: false,
+            ..Default::default()
+        };
+        cart.import_dir(src.path(), "fresh", &options).unwrap();
+        assert!(cart.metadata("fresh/old.txt").unwrap().modified_at > 1_500_000_000);
+    }
+
+    #[test]
+    fn test_tar_round_trip() {
+        let long_name = format!("deep/{}/file.txt", "x".repeat(150));
+
+        let mut cart = Cartridge::new(10);
+        cart.create_file("data/a.json", b"{\"a\":1}").unwrap();
+        cart.create_file("data/sub/b.bin", &vec![9u8; 2 * PAGE_SIZE + 5])
+            .unwrap();
+        cart.create_file(&format!("data/{}", long_name), b"long")
+            .unwrap();
+        cart.create_file("other/c.txt", b"not exported").unwrap();
+        cart.system().write(".cartridge/manifest.json", b"{}").unwrap();
+
+        let mut tarball = Vec::new();
+        let report = cart.export_tar(&mut tarball, "data").unwrap();
+        assert_eq!(report.files_exported, 3);
+
+        // Directory entries precede their files
+        let mut archive = tar::Archive::new(tarball.as_slice());
+        let entries: Vec<(String, tar::EntryType)> = archive
+            .entries()
+            .unwrap()
+            .map(|e| {
+                let e = e.unwrap();
+                (
+                    e.path().unwrap().display().to_string(),
+                    e.header().entry_type(),
+                )
+            })
+            .collect();
+        let sub_dir = entries.iter().position(|(p, _)| p == "sub/").unwrap();
+        let sub_file = entries.iter().position(|(p, _)| p == "sub/b.bin").unwrap();
+        assert!(sub_dir < sub_file);
+        assert_eq!(entries[sub_dir].1, tar::EntryType::Directory);
+        assert!(entries.iter().all(|(p, _)| !p.contains(".cartridge")));
+
+        let mut restored = Cartridge::new(10);
+        let report = restored.import_tar(tarball.as_slice(), "restored").unwrap();
+        assert_eq!(report.files_imported, 3);
+        assert!(report.skipped.is_empty());
+        assert_eq!(restored.read_file("restored/a.json").unwrap(), b"{\"a\":1}");
+        assert_eq!(
+            restored.read_file("restored/sub/b.bin").unwrap(),
+            vec![9u8; 2 * PAGE_SIZE + 5]
+        );
+        assert_eq!(
+            restored
+                .read_file(&format!("restored/{}", long_name))
+                .unwrap(),
+            b"long"
+        );
+        assert_eq!(
+            restored
+                .metadata("restored/a.json")
+                .unwrap()
+                .content_type
+                .as_deref(),
+            Some("application/json")
+        );
+    }
+
+    #[test]
+    fn test_import_tar_skips_unsafe_entries() {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, data) in [
+            ("ok.txt", &b"fine"[..]),
+            (".cartridge/manifest.json", &b"{}"[..]),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, path, data).unwrap();
+        }
+        // `append_data` refuses `..`, so write the raw name directly
+        let mut header = tar::Header::new_gnu();
+        header.as_old_mut().name[..12].copy_from_slice(b"../evil.txt\0");
+        header.set_size(4);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append(&header, &b"evil"[..]).unwrap();
+        let tarball = builder.into_inner().unwrap();
+
+        let mut cart = Cartridge::new(10);
+        let report = cart.import_tar(tarball.as_slice(), "").unwrap();
+        assert_eq!(report.files_imported, 1);
+        assert_eq!(report.skipped.len(), 2);
+        assert!(!cart.exists(".cartridge/manifest.json").unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_import_symlink_policy() {
+        let src = tempfile::tempdir().unwrap();
+        std::fs::write(src.path().join("target.txt"), b"real").unwrap();
+        std::os::unix::fs::symlink(src.path().join("target.txt"), src.path().join("link.txt"))
+            .unwrap();
+
+        let mut cart = Cartridge::new(10);
+        let report = cart
+            .import_dir(src.path(), "", &ImportOptions::default())
+            .unwrap();
+        assert_eq!(report.files_imported, 1);
+        assert_eq!(report.skipped[0].reason, "symlink");
+
+        let options = ImportOptions {
+            symlinks: SymlinkPolicy::Follow,
+            ..Default::default()
+        };
+        let report = cart.import_dir(src.path(), "followed", &options).unwrap();
+        assert_eq!(report.files_imported, 2);
+        assert_eq!(cart.read_file("followed/link.txt").unwrap(), b"real");
+
+        std::os::unix::fs::symlink("target.txt", src.path().join("relative.txt")).unwrap();
+        let options = ImportOptions {
+            symlinks: SymlinkPolicy::Preserve,
+            ..Default::default()
+        };
+        let report = cart.import_dir(src.path(), "kept", &options).unwrap();
+        assert_eq!(report.files_imported, 1);
+        assert_eq!(report.symlinks_imported, 2);
+        assert_eq!(cart.read_link("kept/relative.txt").unwrap(), "target.txt");
+        assert_eq!(cart.read_file("kept/relative.txt").unwrap(), b"real");
+    }
+
+    #[test]
+    fn test_symlinks_through_tar_and_export_dir() {
+        let mut cart = Cartridge::new(10);
+        cart.create_file("data/v2.md", b"two").unwrap();
+        cart.symlink("v2.md", "data/latest").unwrap();
+        cart.symlink("gone.md", "data/dangling").unwrap();
+
+        let mut tarball = Vec::new();
+        let report = cart.export_tar(&mut tarball, "data").unwrap();
+        assert_eq!(report.files_exported, 1);
+        assert_eq!(report.symlinks_exported, 2);
+
+        let mut restored = Cartridge::new(10);
+        let report = restored.import_tar(tarball.as_slice(), "r").unwrap();
+        assert_eq!(report.symlinks_imported, 2);
+        assert_eq!(restored.read_file("r/latest").unwrap(), b"two");
+        assert_eq!(restored.read_link("r/dangling").unwrap(), "gone.md");
+
+        let out = tempfile::tempdir().unwrap();
+        let report = cart.export_dir("data", out.path()).unwrap();
+        assert_eq!(report.files_exported, 1);
+        assert_eq!(report.skipped.len(), 2);
+        assert!(!out.path().join("latest").exists());
+    }
+}
@@ -2,10 +2,73 @@
 //!
 //! This module provides strict validation for container slugs (kebab-case identifiers)
 //! and path normalization to ensure consistent naming across the ecosystem.
+//!
+//! Paths inside a cartridge go through [`normalize_path`] at every public
+//! entry point, so `"docs/a.txt"`, `"/docs/a.txt"` and `"docs//a.txt/"` all
+//! name the same catalog entry.
 
 use crate::error::{CartridgeError, Result};
-use regex::Regex;
 use std::path::{Path, PathBuf};
+use thiserror::Error;
+use unicode_normalization::UnicodeNormalization;
+
+/// Which slug rule was broken
+///
+/// Positions count characters from 0.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SlugError {
+    #[error("slug cannot be empty")]
+    Empty,
+
+    #[error("'{input}' has no characters a slug can be made from")]
+    EmptyAfterSanitize { input: String },
+
+    #[error("slug too long: {len} characters (max {max})")]
+    TooLong { len: usize, max: usize },
+
+    #[error("invalid character {ch:?} at position {position}")]
+    InvalidChar { ch: char, position: usize },
+
+    #[error("slug cannot start with a hyphen")]
+    LeadingHyphen,
+
+    #[error("slug cannot end with a hyphen")]
+    TrailingHyphen,
+
+    #[error("consecutive hyphens at position {position}")]
+    ConsecutiveHyphens { position: usize },
+}
+
+/// Why a path inside a cartridge was rejected
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PathError {
+    #[error("'{segment}' segments are not allowed")]
+    DotSegment { segment: String },
+
+    #[error("path contains a NUL byte")]
+    Nul,
+
+    #[error("path too long: {len} bytes (max {max})")]
+    TooLong { len: usize, max: usize },
+
+    #[error("path too deep: {depth} segments (max {max})")]
+    TooDeep { depth: usize, max: usize },
+
+    #[error("path leads outside the root directory")]
+    EscapesRoot,
+}
+
+/// Longest normalized path, in bytes
+pub const MAX_PATH_LENGTH: usize = 4096;
+
+/// Most segments in a normalized path
+pub const MAX_PATH_DEPTH: usize = 256;
+
+impl From<SlugError> for CartridgeError {
+    fn from(err: SlugError) -> Self {
+        CartridgeError::InvalidContainerSlug(err)
+    }
+}
 
 /// Validates a container slug (kebab-case identifier)
 ///
@@ -35,26 +98,26 @@ use std::path::{Path, PathBuf};
 /// - "test-" (trailing hyphen)
 /// - "my--container" (consecutive hyphens)
 /// - "test.container" (dots)
+///
+/// [`sanitize`](Self::sanitize) derives a valid slug from arbitrary text.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ContainerSlug(String);
 
 impl ContainerSlug {
-    /// Pattern for valid kebab-case slugs
-    const PATTERN: &'static str = r"^[a-z0-9]([a-z0-9-]*[a-z0-9])?$";
-
     /// Maximum length (npm package name limit)
-    const MAX_LENGTH: usize = 214;
+    pub const MAX_LENGTH: usize = 214;
 
     /// Create a new validated slug
     ///
     /// # Errors
     ///
-    /// Returns `InvalidContainerSlug` if the slug doesn't meet validation rules.
+    /// Returns `InvalidContainerSlug` with the [`SlugError`] for the first
+    /// rule the slug breaks.
     ///
     /// # Examples
     ///
     /// ```
-    /// use cartridge_core::validation::ContainerSlug;
+    /// use cartridge_rs::core::validation::ContainerSlug;
     ///
     /// let slug = ContainerSlug::new("my-container").unwrap();
     /// assert_eq!(slug.as_str(), "my-container");
@@ -68,36 +131,88 @@ impl ContainerSlug {
         Ok(ContainerSlug(slug))
     }
 
+    /// Whether `slug` is a valid slug as is
+    pub fn is_valid(slug: &str) -> bool {
+        Self::validate_slug(slug).is_ok()
+    }
+
+    /// Derive a slug from arbitrary text, such as a title
+    ///
+    /// Letters are lowercased and stripped of accents, a few letters without
+    /// a decomposition are spelled out (`ß` becomes `ss`), and every run of
+    /// other characters becomes a single hyphen. Leading and trailing hyphens
+    /// are dropped and the result is cut to [`MAX_LENGTH`](Self::MAX_LENGTH).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SlugError::EmptyAfterSanitize`] if nothing usable is left.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cartridge_rs::core::validation::ContainerSlug;
+    ///
+    /// let slug = ContainerSlug::sanitize("Überwachung 2024 ✅").unwrap();
+    /// assert_eq!(slug.as_str(), "uberwachung-2024");
+    /// ```
+    pub fn sanitize(input: &str) -> Result<Self> {
+        let mut slug = String::with_capacity(input.len());
+        for ch in input.nfkd() {
+            let ch = ch.to_ascii_lowercase();
+            if ch.is_ascii_alphanumeric() {
+                slug.push(ch);
+            } else if let Some(spelled) = transliterate(ch) {
+                slug.push_str(spelled);
+            } else if unicode_normalization::char::is_combining_mark(ch) {
+                // Accents split off by the decomposition
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+
+        // ASCII from here on, so byte lengths are character counts
+        slug.truncate(Self::MAX_LENGTH);
+        let slug = slug.trim_end_matches('-');
+        if slug.is_empty() {
+            return Err(SlugError::EmptyAfterSanitize {
+                input: input.to_string(),
+            }
+            .into());
+        }
+        Self::new(slug)
+    }
+
     /// Validate a slug string
-    fn validate_slug(slug: &str) -> Result<()> {
-        // Length check
+    fn validate_slug(slug: &str) -> std::result::Result<(), SlugError> {
         if slug.is_empty() {
-            return Err(CartridgeError::InvalidContainerSlug(
-                "slug cannot be empty".to_string(),
-            ));
+            return Err(SlugError::Empty);
         }
 
-        if slug.len() > Self::MAX_LENGTH {
-            return Err(CartridgeError::InvalidContainerSlug(format!(
-                "slug too long (max {} characters)",
-                Self::MAX_LENGTH
-            )));
+        let len = slug.chars().count();
+        if len > Self::MAX_LENGTH {
+            return Err(SlugError::TooLong {
+                len,
+                max: Self::MAX_LENGTH,
+            });
         }
 
-        // Pattern check (kebab-case)
-        let re = Regex::new(Self::PATTERN).unwrap();
-        if !re.is_match(slug) {
-            return Err(CartridgeError::InvalidContainerSlug(format!(
-                "slug '{}' must be kebab-case: lowercase letters, numbers, and hyphens only",
-                slug
-            )));
+        if slug.starts_with('-') {
+            return Err(SlugError::LeadingHyphen);
         }
 
-        // No consecutive hyphens
-        if slug.contains("--") {
-            return Err(CartridgeError::InvalidContainerSlug(
-                "slug cannot contain consecutive hyphens".to_string(),
-            ));
+        let mut previous = None;
+        for (position, ch) in slug.chars().enumerate() {
+            if !matches!(ch, 'a'..='z' | '0'..='9' | '-') {
+                return Err(SlugError::InvalidChar { ch, position });
+            }
+            if ch == '-' && previous == Some('-') {
+                return Err(SlugError::ConsecutiveHyphens { position });
+            }
+            previous = Some(ch);
+        }
+
+        if slug.ends_with('-') {
+            return Err(SlugError::TrailingHyphen);
         }
 
         Ok(())
@@ -126,6 +241,83 @@ impl std::fmt::Display for ContainerSlug {
     }
 }
 
+/// ASCII spelling of letters that don't decompose into one
+fn transliterate(ch: char) -> Option<&'static str> {
+    Some(match ch {
+        'ß' => "ss",
+        'æ' | 'Æ' => "ae",
+        'œ' | 'Œ' => "oe",
+        'ø' | 'Ø' => "o",
+        'đ' | 'Đ' | 'ð' | 'Ð' => "d",
+        'ł' | 'Ł' => "l",
+        'þ' | 'Þ' => "th",
+        'ı' => "i",
+        _ => return None,
+    })
+}
+
+/// Normalize a path inside a cartridge to its catalog key
+///
+/// The result always starts with `/`, has no empty segments and no trailing
+/// slash; the root is `"/"`. `.` and `..` segments and NUL bytes are
+/// rejected rather than resolved, so a key can never point outside the tree
+/// it is exported to.
+///
+/// # Errors
+///
+/// Returns `InvalidFilePath` if the path breaks one of the rules above or
+/// is longer than [`MAX_PATH_LENGTH`] or deeper than [`MAX_PATH_DEPTH`].
+///
+/// # Examples
+///
+/// ```
+/// use cartridge_rs::core::validation::normalize_path;
+///
+/// assert_eq!(normalize_path("docs/a.txt").unwrap(), "/docs/a.txt");
+/// assert_eq!(normalize_path("//docs///a.txt/").unwrap(), "/docs/a.txt");
+/// assert_eq!(normalize_path("").unwrap(), "/");
+/// assert!(normalize_path("docs/../etc/passwd").is_err());
+/// ```
+pub fn normalize_path(path: &str) -> Result<String> {
+    let invalid = |reason| CartridgeError::InvalidFilePath {
+        path: path.to_string(),
+        reason,
+    };
+    if path.contains('\0') {
+        return Err(invalid(PathError::Nul));
+    }
+
+    let mut normalized = String::with_capacity(path.len() + 1);
+    let mut depth = 0;
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        if segment == "." || segment == ".." {
+            return Err(invalid(PathError::DotSegment {
+                segment: segment.to_string(),
+            }));
+        }
+        normalized.push('/');
+        normalized.push_str(segment);
+        depth += 1;
+    }
+    if normalized.is_empty() {
+        normalized.push('/');
+    }
+
+    if depth > MAX_PATH_DEPTH {
+        return Err(invalid(PathError::TooDeep {
+            depth,
+            max: MAX_PATH_DEPTH,
+        }));
+    }
+    if normalized.len() > MAX_PATH_LENGTH {
+        return Err(invalid(PathError::TooLong {
+            len: normalized.len(),
+            max: MAX_PATH_LENGTH,
+        }));
+    }
+    Ok(normalized)
+}
+
 /// Normalize a container path
 ///
 /// Takes a slug/path input (WITHOUT .cart extension) and:
@@ -140,7 +332,7 @@ impl std::fmt::Display for ContainerSlug {
 /// # Examples
 ///
 /// ```
-/// use cartridge_core::validation::normalize_container_path;
+/// use cartridge_rs::core::validation::normalize_container_path;
 /// use std::path::Path;
 ///
 /// // User provides slug only
@@ -192,7 +384,7 @@ pub fn normalize_container_path(path: &Path) -> Result<PathBuf> {
 /// # Examples
 ///
 /// ```
-/// use cartridge_core::validation::extract_slug;
+/// use cartridge_rs::core::validation::extract_slug;
 /// use std::path::Path;
 ///
 /// // With .cart extension
@@ -242,6 +434,46 @@ mod tests {
         assert!(ContainerSlug::new("test container").is_err()); // space
     }
 
+    #[test]
+    fn test_slug_errors_name_the_rule() {
+        let err = |slug: &str| match ContainerSlug::new(slug) {
+            Err(CartridgeError::InvalidContainerSlug(err)) => err,
+            other => panic!("expected a slug error for {slug:?}, got {other:?}"),
+        };
+        assert_eq!(err(""), SlugError::Empty);
+        assert_eq!(err(&"a".repeat(215)), SlugError::TooLong { len: 215, max: 214 });
+        assert_eq!(err("my_data"), SlugError::InvalidChar { ch: '_', position: 2 });
+        assert_eq!(err("Data"), SlugError::InvalidChar { ch: 'D', position: 0 });
+        assert_eq!(err("-data"), SlugError::LeadingHyphen);
+        assert_eq!(err("data-"), SlugError::TrailingHyphen);
+        assert_eq!(err("my--data"), SlugError::ConsecutiveHyphens { position: 3 });
+
+        assert!(ContainerSlug::is_valid("my-data-2"));
+        assert!(!ContainerSlug::is_valid("my data"));
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let sanitize = |input: &str| ContainerSlug::sanitize(input).unwrap().into_string();
+        assert_eq!(sanitize("Überwachung 2024 ✅"), "uberwachung-2024");
+        assert_eq!(sanitize("  My  Data__Container! "), "my-data-container");
+        assert_eq!(sanitize("Straße café"), "strasse-cafe");
+        assert_eq!(sanitize("Ærø / Łódź"), "aero-lodz");
+        assert_eq!(sanitize("ﬁle №1"), "file-no1");
+        assert_eq!(sanitize("already-valid"), "already-valid");
+
+        let long = sanitize(&"word ".repeat(100));
+        assert!(long.len() <= ContainerSlug::MAX_LENGTH);
+        assert!(ContainerSlug::is_valid(&long));
+
+        for input in ["", "✅ 🚀", "---", "日本語"] {
+            assert!(matches!(
+                ContainerSlug::sanitize(input),
+                Err(CartridgeError::InvalidContainerSlug(SlugError::EmptyAfterSanitize { .. }))
+            ));
+        }
+    }
+
     #[test]
     fn test_normalize_path() {
         // User provides slug only - .cart is added
@@ -265,6 +497,45 @@ mod tests {
         assert_eq!(path, Path::new("my-container.cart")); // Corrects to .cart
     }
 
+    #[test]
+    fn test_normalize_file_path() {
+        for spelling in ["docs/a.txt", "/docs/a.txt", "docs//a.txt", "//docs/a.txt/"] {
+            assert_eq!(normalize_path(spelling).unwrap(), "/docs/a.txt");
+        }
+        assert_eq!(normalize_path("").unwrap(), "/");
+        assert_eq!(normalize_path("///").unwrap(), "/");
+        assert_eq!(normalize_path("a.b/..c/.d").unwrap(), "/a.b/..c/.d");
+
+        let reason = |path: &str| match normalize_path(path) {
+            Err(CartridgeError::InvalidFilePath { reason, .. }) => reason,
+            other => panic!("expected InvalidFilePath for {path:?}, got {other:?}"),
+        };
+        assert_eq!(
+            reason("a/../b"),
+            PathError::DotSegment {
+                segment: "..".to_string()
+            }
+        );
+        assert_eq!(
+            reason("./a"),
+            PathError::DotSegment {
+                segment: ".".to_string()
+            }
+        );
+        assert_eq!(reason("a\0b"), PathError::Nul);
+        assert_eq!(
+            reason(&"a/".repeat(MAX_PATH_DEPTH + 1)),
+            PathError::TooDeep {
+                depth: MAX_PATH_DEPTH + 1,
+                max: MAX_PATH_DEPTH
+            }
+        );
+        assert!(matches!(
+            reason(&"x".repeat(MAX_PATH_LENGTH)),
+            PathError::TooLong { len, .. } if len == MAX_PATH_LENGTH + 1
+        ));
+    }
+
     #[test]
     fn test_extract_slug() {
         let slug = extract_slug(Path::new("my-container.cart")).unwrap();
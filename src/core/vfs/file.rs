@@ -314,10 +314,8 @@ pub unsafe extern "C" fn file_open(
     // Create the file if it doesn't exist (for CREATE flag)
     if flags & ffi::SQLITE_OPEN_CREATE != 0 {
         let mut cartridge = vfs_impl.cartridge().lock();
-        if !cartridge.exists(&path).unwrap_or(false) {
-            if let Err(_) = cartridge.create_file(&path, &[]) {
-                return ffi::SQLITE_CANTOPEN;
-            }
+        if !cartridge.exists(&path).unwrap_or(false) && cartridge.create_file(&path, &[]).is_err() {
+            return ffi::SQLITE_CANTOPEN;
         }
     }
 
@@ -9,6 +9,7 @@
 //! - No extraction required - SQLite I/O goes straight to Cartridge pages
 
 mod file;
+#[allow(clippy::module_inception)]
 mod vfs;
 
 #[cfg(test)]
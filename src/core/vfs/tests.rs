@@ -112,7 +112,7 @@ fn test_vfs_full_sqlite_integration() {
     // Open database with our custom VFS
     // Use URI format to specify the VFS: file:test.db?vfs=cartridge
     let db_uri = format!("file:test.db?vfs={}", VFS_NAME);
-    let mut conn = Connection::open_with_flags(
+    let conn = Connection::open_with_flags(
         &db_uri,
         rusqlite::OpenFlags::SQLITE_OPEN_READ_WRITE
             | rusqlite::OpenFlags::SQLITE_OPEN_CREATE
@@ -197,12 +197,12 @@ fn test_vfs_full_sqlite_integration() {
 
     // Verify the database file exists in the cartridge
     {
-        let mut cart = cartridge.lock();
+        let cart = cartridge.lock();
         assert!(cart.exists("test.db").unwrap());
 
         // Verify the database file has content
         let db_content = cart.read_file("test.db").unwrap();
-        assert!(db_content.len() > 0);
+        assert!(!db_content.is_empty());
 
         // SQLite magic number check
         assert_eq!(&db_content[0..16], b"SQLite format 3\0");
@@ -83,7 +83,7 @@ pub fn register_named_vfs(name: &str, cartridge: Arc<Mutex<Cartridge>>) -> Resul
         szOsFile: std::mem::size_of::<super::file::CartridgeFile>() as c_int,
         mxPathname: 1024,
         pNext: ptr::null_mut(),
-        zName: (*unsafe { &*vfs_ptr }).name.as_ptr(),
+        zName: unsafe { &*vfs_ptr }.name.as_ptr(),
         pAppData: vfs_ptr as *mut c_void,
         xOpen: Some(vfs_open),
         xDelete: Some(vfs_delete),
@@ -144,7 +144,7 @@ pub fn unregister_named_vfs(name: &str) -> Result<()> {
         if !app_data.is_null() {
             drop(Box::from_raw(app_data as *mut CartridgeVFS));
         }
-        drop(Box::from_raw(vfs_ptr as *mut ffi::sqlite3_vfs));
+        drop(Box::from_raw(vfs_ptr));
     }
 
     Ok(())
@@ -38,6 +38,9 @@ pub const WAL_ENTRY_SIZE: usize = 64;
 const WAL_HEADER_SIZE: usize = 32;
 
 /// How many entries fit in a single 4KB page (after the header page).
+///
+/// The layout is the same whatever the cartridge's page size: with 16KB or
+/// 64KB pages only the first 4KB of each WAL page is used.
 const ENTRIES_PER_PAGE: usize = PAGE_SIZE / WAL_ENTRY_SIZE; // 64
 
 /// Default pre-allocated pages for a WAL file (1 header + 7 data = 8 pages).
@@ -723,7 +726,7 @@ mod tests {
 
         // Slots that fit in header page
         let header_slots = (PAGE_SIZE - WAL_HEADER_SIZE) / WAL_ENTRY_SIZE;
-        let (pid, off) = wal.slot_location(header_slots - 1);
+        let (pid, _off) = wal.slot_location(header_slots - 1);
         assert_eq!(pid, 0);
 
         // First slot in data page 1
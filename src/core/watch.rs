@@ -0,0 +1,289 @@
+//! Change notifications for live views of a cartridge
+//!
+//! [`Cartridge::watch`] subscribes to changes under a path prefix and hands
+//! back the receiving end of a bounded channel. Creating, writing, deleting
+//! and renaming files, and creating directories, send a [`ChangeEvent`] to
+//! every watcher whose prefix covers the path.
+//!
+//! ## Delivery
+//!
+//! Sending never blocks. When a watcher's channel is full the event is
+//! dropped for that watcher alone, so a slow subscriber misses changes
+//! rather than stalling writers; size the channel with
+//! [`Cartridge::watch_with_capacity`] for the bursts you expect, or re-list
+//! after falling behind. A watcher whose receiver was dropped is removed the
+//! next time an event is sent to it.
+//!
+//! Watchers hold only a channel sender, never the cartridge, so they can't
+//! keep it alive. Events from a [`WriteBatch`](crate::batch::WriteBatch) are
+//! held until it commits and discarded if it rolls back. Paths under
+//! `.cartridge/` only reach watchers whose prefix is inside `.cartridge/`.
+
+use crate::cartridge::Cartridge;
+use parking_lot::Mutex;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::time::SystemTime;
+
+/// Channel capacity used by [`Cartridge::watch`]
+pub const DEFAULT_WATCH_CAPACITY: usize = 1024;
+
+/// What happened to a path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// A file, directory or symlink was created
+    Created,
+    /// A file's content changed
+    Modified,
+    /// A file, directory or symlink was deleted
+    Deleted,
+    /// The entry was renamed to the event's path from `from`
+    Renamed { from: String },
+}
+
+/// One change delivered to a watcher
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeEvent {
+    /// Path that changed (the new path, for renames)
+    pub path: String,
+    /// What happened
+    pub kind: ChangeKind,
+    /// When the change was made
+    pub timestamp: SystemTime,
+}
+
+impl ChangeEvent {
+    /// Whether this event concerns a path under `prefix`
+    ///
+    /// Renames match on either side, so a watcher sees entries leave too.
+    fn is_under(&self, prefix: &str) -> bool {
+        is_under(&self.path, prefix)
+            || matches!(&self.kind, ChangeKind::Renamed { from } if is_under(from, prefix))
+    }
+}
+
+/// Whether `path` is `prefix` itself or inside it
+///
+/// Leading slashes are ignored and matching stops at path segments, so
+/// `/docs` covers `docs/a.txt` but not `docs2/a.txt`. An empty prefix
+/// covers everything except `.cartridge/`.
+fn is_under(path: &str, prefix: &str) -> bool {
+    let path = path.trim_start_matches('/');
+    let prefix = prefix.trim_start_matches('/').trim_end_matches('/');
+    if prefix.is_empty() {
+        return !(path == ".cartridge" || path.starts_with(".cartridge/"));
+    }
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+struct Watcher {
+    prefix: String,
+    sender: SyncSender<ChangeEvent>,
+}
+
+#[derive(Default)]
+struct WatcherState {
+    watchers: Vec<Watcher>,
+    /// Events held back while a batch commits
+    held: Option<Vec<ChangeEvent>>,
+}
+
+/// The watchers registered on one cartridge
+#[derive(Default)]
+pub(crate) struct Watchers {
+    state: Mutex<WatcherState>,
+}
+
+impl Watchers {
+    fn subscribe(&self, prefix: &str, capacity: usize) -> Receiver<ChangeEvent> {
+        let (sender, receiver) = sync_channel(capacity);
+        self.state.lock().watchers.push(Watcher {
+            prefix: prefix.to_string(),
+            sender,
+        });
+        receiver
+    }
+
+    /// Send a change to every interested watcher
+    pub(crate) fn notify(&self, path: &str, kind: ChangeKind) {
+        let mut state = self.state.lock();
+        if state.watchers.is_empty() {
+            return;
+        }
+        let event = ChangeEvent {
+            path: path.to_string(),
+            kind,
+            timestamp: crate::clock::now(),
+        };
+        match &mut state.held {
+            Some(held) => held.push(event),
+            None => deliver(&mut state.watchers, event),
+        }
+    }
+
+    /// Hold events back until [`release`](Self::release)
+    pub(crate) fn hold(&self) {
+        self.state.lock().held.get_or_insert_with(Vec::new);
+    }
+
+    /// Stop holding events, delivering them if `send` is set
+    pub(crate) fn release(&self, send: bool) {
+        let mut state = self.state.lock();
+        let held = state.held.take().unwrap_or_default();
+        if send {
+            for event in held {
+                deliver(&mut state.watchers, event);
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.state.lock().watchers.len()
+    }
+}
+
+/// Offer `event` to each matching watcher, dropping it for full channels and
+/// removing watchers whose receiver is gone
+fn deliver(watchers: &mut Vec<Watcher>, event: ChangeEvent) {
+    watchers.retain(|watcher| {
+        if !event.is_under(&watcher.prefix) {
+            return true;
+        }
+        !matches!(
+            watcher.sender.try_send(event.clone()),
+            Err(TrySendError::Disconnected(_))
+        )
+    });
+}
+
+impl Cartridge {
+    /// Subscribe to changes under `prefix` (`""` for everything)
+    ///
+    /// Uses a channel of [`DEFAULT_WATCH_CAPACITY`] events. See the
+    /// [module docs](self) for what happens when it fills up.
+    pub fn watch(&self, prefix: &str) -> Receiver<ChangeEvent> {
+        self.watch_with_capacity(prefix, DEFAULT_WATCH_CAPACITY)
+    }
+
+    /// Subscribe to changes under `prefix` with a channel of `capacity`
+    /// events
+    pub fn watch_with_capacity(&self, prefix: &str, capacity: usize) -> Receiver<ChangeEvent> {
+        self.watchers().subscribe(prefix, capacity)
+    }
+
+    /// Number of registered watchers, including any whose receiver was
+    /// dropped but who haven't been sent an event since
+    pub fn watcher_count(&self) -> usize {
+        self.watchers().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(receiver: &Receiver<ChangeEvent>) -> Vec<(String, ChangeKind)> {
+        receiver.try_iter().map(|e| (e.path, e.kind)).collect()
+    }
+
+    #[test]
+    fn test_prefix_matching() {
+        assert!(is_under("docs/a.txt", "/docs"));
+        assert!(is_under("/docs/a.txt", "docs/"));
+        assert!(is_under("docs", "docs"));
+        assert!(!is_under("docs2/a.txt", "docs"));
+        assert!(is_under("anything", ""));
+        assert!(!is_under(".cartridge/manifest.json", ""));
+        assert!(is_under(".cartridge/manifest.json", ".cartridge"));
+    }
+
+    #[test]
+    fn test_watchers_see_only_their_prefix() {
+        let mut cart = Cartridge::new(100);
+        let docs = cart.watch("/docs");
+        let all = cart.watch("");
+
+        cart.create_file("docs/a.txt", b"one").unwrap();
+        cart.create_file("other/b.txt", b"two").unwrap();
+        cart.write_file("docs/a.txt", b"three").unwrap();
+        cart.append_file("docs/a.txt", b"four").unwrap();
+        cart.create_dir("docs/sub").unwrap();
+        cart.rename("docs/a.txt", "other/a.txt").unwrap();
+        cart.delete_file("other/b.txt").unwrap();
+
+        let renamed = ChangeKind::Renamed {
+            from: "/docs/a.txt".to_string(),
+        };
+        assert_eq!(
+            drain(&docs),
+            [
+                ("/docs/a.txt".to_string(), ChangeKind::Created),
+                ("/docs/a.txt".to_string(), ChangeKind::Modified),
+                ("/docs/a.txt".to_string(), ChangeKind::Modified),
+                ("/docs/sub".to_string(), ChangeKind::Created),
+                ("/other/a.txt".to_string(), renamed.clone()),
+            ]
+        );
+        assert_eq!(drain(&all).len(), 7);
+    }
+
+    #[test]
+    fn test_dropped_receivers_are_removed() {
+        let mut cart = Cartridge::new(100);
+        let kept = cart.watch("");
+        drop(cart.watch("docs"));
+        drop(cart.watch("other"));
+        assert_eq!(cart.watcher_count(), 3);
+
+        cart.create_file("docs/a.txt", b"a").unwrap();
+        assert_eq!(cart.watcher_count(), 2);
+        cart.create_file("other/b.txt", b"b").unwrap();
+        assert_eq!(cart.watcher_count(), 1);
+        assert_eq!(drain(&kept).len(), 2);
+    }
+
+    #[test]
+    fn test_full_channel_drops_newest() {
+        let mut cart = Cartridge::new(100);
+        let slow = cart.watch_with_capacity("", 2);
+        for i in 0..5 {
+            cart.create_file(&format!("f{i}"), b"x").unwrap();
+        }
+        let paths: Vec<String> = drain(&slow).into_iter().map(|(path, _)| path).collect();
+        assert_eq!(paths, ["/f0", "/f1"]);
+
+        // Still subscribed once it catches up
+        cart.delete_file("f4").unwrap();
+        assert_eq!(drain(&slow), [("/f4".to_string(), ChangeKind::Deleted)]);
+    }
+
+    #[test]
+    fn test_batch_events_wait_for_commit() {
+        let mut cart = Cartridge::new(100);
+        cart.create_file("keep.txt", b"k").unwrap();
+        let watcher = cart.watch("");
+
+        let mut batch = cart.begin_batch();
+        batch.write("a.txt", b"a").unwrap();
+        batch.delete("keep.txt").unwrap();
+        batch.commit().unwrap();
+        assert_eq!(
+            drain(&watcher),
+            [
+                ("/a.txt".to_string(), ChangeKind::Created),
+                ("/keep.txt".to_string(), ChangeKind::Deleted),
+            ]
+        );
+
+        // Writing over a directory fails at commit, rolling the batch back
+        cart.create_dir("dir").unwrap();
+        drain(&watcher);
+        let mut batch = cart.begin_batch();
+        batch.write("b.txt", b"b").unwrap();
+        batch.write("dir", b"not a file").unwrap();
+        assert!(batch.commit().is_err());
+        assert!(drain(&watcher).is_empty());
+        cart.create_file("c.txt", b"c").unwrap();
+        assert_eq!(drain(&watcher), [("/c.txt".to_string(), ChangeKind::Created)]);
+    }
+}
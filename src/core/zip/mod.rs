@@ -0,0 +1,885 @@
+//! Zip archives: reading their entries and writing new ones
+//!
+//! Enough of the format to move files between zip archives and cartridges:
+//! the central directory (zip64 included), stored and deflated entries,
+//! directory entries, Unix modes and symlinks, and modification times from
+//! the DOS fields or the extended timestamp field. Deflated content is
+//! inflated by `flate2`; the container is simple enough to read here, which
+//! keeps [`ZipVfs`](crate::ZipVfs) reading entries in place and lets
+//! [`Cartridge::export_zip`] stream to a writer that can't seek.
+//! [`Cartridge::import_zip`] and [`Cartridge::export_zip`] convert.
+//!
+//! Archives are written with stored (uncompressed) entries. DOS times carry
+//! no zone and are read as UTC; written entries also get an extended
+//! timestamp, which is exact.
+
+use crate::cartridge::Cartridge;
+use crate::catalog::{FileMetadata, FileType};
+use crate::content_type;
+use crate::error::{CartridgeError, Result};
+use crate::transfer::{
+    is_internal, join_prefix, relative_path, safe_join, skipped, ExportReport, ImportReport,
+};
+use chrono::{DateTime, Datelike, NaiveDate, Timelike};
+use flate2::read::DeflateDecoder;
+use std::collections::HashSet;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const LOCAL_HEADER_SIG: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIG: u32 = 0x0201_4b50;
+const END_SIG: u32 = 0x0605_4b50;
+const ZIP64_END_SIG: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR_SIG: u32 = 0x0706_4b50;
+
+const END_LEN: usize = 22;
+const ZIP64_LOCATOR_LEN: usize = 20;
+const LOCAL_HEADER_LEN: usize = 30;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+const FLAG_ENCRYPTED: u16 = 1;
+const FLAG_UTF8: u16 = 1 << 11;
+
+const EXTRA_ZIP64: u16 = 0x0001;
+const EXTRA_TIMESTAMP: u16 = 0x5455;
+
+/// "Version made by" host for Unix, whose external attributes hold a mode
+const HOST_UNIX: u16 = 3;
+const VERSION: u16 = 20;
+const VERSION_ZIP64: u16 = 45;
+
+/// A 32-bit field that doesn't fit and is in the zip64 extra field instead
+const ZIP64_MARKER: u32 = u32::MAX;
+
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFLNK: u32 = 0o120000;
+const S_IFREG: u32 = 0o100000;
+
+/// MS-DOS directory attribute, set alongside the Unix mode for other tools
+const DOS_DIRECTORY: u32 = 0x10;
+
+fn invalid(reason: impl Into<String>) -> CartridgeError {
+    CartridgeError::InvalidZip(reason.into())
+}
+
+/// One entry of an archive's central directory
+#[derive(Debug, Clone)]
+pub(crate) struct ZipEntry {
+    /// Name as stored, `/`-separated; directories end with `/`
+    pub name: String,
+    pub method: u16,
+    pub flags: u16,
+    pub crc32: u32,
+    pub compressed_size: u64,
+    pub size: u64,
+    pub local_header_offset: u64,
+    /// Modification time, Unix epoch seconds
+    pub modified: u64,
+    /// Unix mode, when the archive was made on Unix
+    pub mode: Option<u32>,
+}
+
+impl ZipEntry {
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/') || self.mode.is_some_and(|mode| mode & S_IFMT == S_IFDIR)
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.mode.is_some_and(|mode| mode & S_IFMT == S_IFLNK)
+    }
+
+    /// Why the content can't be read, if it can't
+    pub fn unsupported(&self) -> Option<String> {
+        if self.flags & FLAG_ENCRYPTED != 0 {
+            return Some("entry is encrypted".to_string());
+        }
+        match self.method {
+            METHOD_STORED | METHOD_DEFLATED => None,
+            method => Some(format!("unsupported compression method {}", method)),
+        }
+    }
+
+    /// Catalog-style metadata: type, size, times and permissions
+    pub fn metadata(&self) -> FileMetadata {
+        let file_type = if self.is_dir() {
+            FileType::Directory
+        } else if self.is_symlink() {
+            FileType::Symlink
+        } else {
+            FileType::File
+        };
+        let size = if file_type == FileType::Directory { 0 } else { self.size };
+        let mut metadata = FileMetadata::new(file_type, size, Vec::new());
+        metadata.created_at = self.modified;
+        metadata.modified_at = self.modified;
+        if let Some(permissions) = self.mode.map(|mode| mode & 0o7777).filter(|&p| p != 0) {
+            metadata.permissions = permissions;
+        }
+        if file_type == FileType::File {
+            metadata.content_type = content_type::from_path(&self.name).map(str::to_string);
+        }
+        metadata
+    }
+}
+
+/// Little-endian fields read from a slice, failing instead of panicking
+/// when a malformed archive runs short
+struct Fields<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Fields { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.data.len());
+        let end = end.ok_or_else(|| invalid("central directory is truncated"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+}
+
+/// Read an archive's central directory
+///
+/// Only the directory is read, not the entries' content, so this is cheap
+/// however large the archive is.
+pub(crate) fn read_entries<R: Read + Seek>(reader: &mut R) -> Result<Vec<ZipEntry>> {
+    // The end record is last, after a comment of up to 64KB
+    let len = reader.seek(SeekFrom::End(0))?;
+    let tail_len = len.min((END_LEN + u16::MAX as usize) as u64);
+    reader.seek(SeekFrom::Start(len - tail_len))?;
+    let mut tail = vec![0u8; tail_len as usize];
+    reader.read_exact(&mut tail)?;
+    let end = (0..=tail.len().saturating_sub(END_LEN))
+        .rev()
+        .find(|&i| tail[i..].starts_with(&END_SIG.to_le_bytes()))
+        .filter(|_| tail.len() >= END_LEN)
+        .ok_or_else(|| invalid("no end of central directory record"))?;
+
+    let mut record = Fields::new(&tail[end + 10..]);
+    let mut count = u64::from(record.u16()?);
+    let mut dir_size = u64::from(record.u32()?);
+    let mut dir_offset = u64::from(record.u32()?);
+
+    // A zip64 archive has a locator just before the end record
+    if end >= ZIP64_LOCATOR_LEN
+        && tail[end - ZIP64_LOCATOR_LEN..].starts_with(&ZIP64_LOCATOR_SIG.to_le_bytes())
+    {
+        let mut locator = Fields::new(&tail[end - ZIP64_LOCATOR_LEN + 8..end]);
+        reader.seek(SeekFrom::Start(locator.u64()?))?;
+        let mut zip64_end = [0u8; 56];
+        reader.read_exact(&mut zip64_end)?;
+        let mut record = Fields::new(&zip64_end);
+        if record.u32()? != ZIP64_END_SIG {
+            return Err(invalid("bad zip64 end of central directory record"));
+        }
+        record.bytes(28)?;
+        count = record.u64()?;
+        dir_size = record.u64()?;
+        dir_offset = record.u64()?;
+    }
+
+    if dir_offset.checked_add(dir_size).is_none_or(|dir_end| dir_end > len) {
+        return Err(invalid("central directory lies outside the archive"));
+    }
+    reader.seek(SeekFrom::Start(dir_offset))?;
+    let mut directory = vec![0u8; dir_size as usize];
+    reader.read_exact(&mut directory)?;
+
+    let mut fields = Fields::new(&directory);
+    let mut entries = Vec::with_capacity(count.min(u16::MAX.into()) as usize);
+    for _ in 0..count {
+        if fields.u32()? != CENTRAL_HEADER_SIG {
+            return Err(invalid("bad central directory entry"));
+        }
+        let made_by = fields.u16()?;
+        fields.u16()?;
+        let flags = fields.u16()?;
+        let method = fields.u16()?;
+        let time = fields.u16()?;
+        let date = fields.u16()?;
+        let crc32 = fields.u32()?;
+        let mut compressed_size = u64::from(fields.u32()?);
+        let mut size = u64::from(fields.u32()?);
+        let name_len = usize::from(fields.u16()?);
+        let extra_len = usize::from(fields.u16()?);
+        let comment_len = usize::from(fields.u16()?);
+        // Disk number, internal attributes
+        fields.bytes(4)?;
+        let external = fields.u32()?;
+        let mut local_header_offset = u64::from(fields.u32()?);
+        let name = fields.bytes(name_len)?;
+        let extra = fields.bytes(extra_len)?;
+        fields.bytes(comment_len)?;
+
+        // Names without the UTF-8 flag are meant to be CP437, but in
+        // practice are UTF-8 or plain ASCII
+        let name = String::from_utf8_lossy(name).into_owned();
+        let mut modified = dos_to_unix(date, time);
+
+        let mut extra = Fields::new(extra);
+        while extra.pos + 4 <= extra.data.len() {
+            let id = extra.u16()?;
+            let len = usize::from(extra.u16()?);
+            let mut field = Fields::new(extra.bytes(len)?);
+            match id {
+                // Only the fields that overflowed are present, in this order
+                EXTRA_ZIP64 => {
+                    if size == u64::from(ZIP64_MARKER) {
+                        size = field.u64()?;
+                    }
+                    if compressed_size == u64::from(ZIP64_MARKER) {
+                        compressed_size = field.u64()?;
+                    }
+                    if local_header_offset == u64::from(ZIP64_MARKER) {
+                        local_header_offset = field.u64()?;
+                    }
+                }
+                // Flag bit 0: the modification time comes first
+                EXTRA_TIMESTAMP if field.bytes(1)?[0] & 1 != 0 => {
+                    modified = (field.u32()? as i32).max(0) as u64;
+                }
+                _ => {}
+            }
+        }
+
+        let mode = (made_by >> 8 == HOST_UNIX && external >> 16 != 0).then_some(external >> 16);
+        entries.push(ZipEntry {
+            name,
+            method,
+            flags,
+            crc32,
+            compressed_size,
+            size,
+            local_header_offset,
+            modified,
+            mode,
+        });
+    }
+    Ok(entries)
+}
+
+/// Stream an entry's content, inflating it if deflated
+///
+/// The CRC and length are checked as the stream ends; a mismatch fails the
+/// last read with [`io::ErrorKind::InvalidData`].
+pub(crate) fn open_entry<'a, R: Read + Seek>(
+    reader: &'a mut R,
+    entry: &ZipEntry,
+) -> Result<Box<dyn Read + 'a>> {
+    if let Some(reason) = entry.unsupported() {
+        return Err(invalid(format!("{}: {}", entry.name, reason)));
+    }
+    reader.seek(SeekFrom::Start(entry.local_header_offset))?;
+    let mut header = [0u8; LOCAL_HEADER_LEN];
+    reader.read_exact(&mut header)?;
+    let mut fields = Fields::new(&header);
+    if fields.u32()? != LOCAL_HEADER_SIG {
+        return Err(invalid(format!("{}: bad local header", entry.name)));
+    }
+    fields.bytes(22)?;
+    let skip = i64::from(fields.u16()?) + i64::from(fields.u16()?);
+    reader.seek(SeekFrom::Current(skip))?;
+
+    let data = reader.take(entry.compressed_size);
+    let content: Box<dyn Read + 'a> = match entry.method {
+        METHOD_DEFLATED => Box::new(DeflateDecoder::new(data)),
+        _ => Box::new(data),
+    };
+    Ok(Box::new(Checked {
+        inner: content,
+        name: entry.name.clone(),
+        hasher: crc32fast::Hasher::new(),
+        expected_crc: entry.crc32,
+        expected_len: entry.size,
+        len: 0,
+    }))
+}
+
+/// Checks an entry's CRC and length once its stream ends
+struct Checked<R> {
+    inner: R,
+    name: String,
+    hasher: crc32fast::Hasher,
+    expected_crc: u32,
+    expected_len: u64,
+    len: u64,
+}
+
+impl<R: Read> Read for Checked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.len += n as u64;
+        let corrupt = |what| {
+            let message = format!("{} mismatch in {}", what, self.name);
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        };
+        let ended = n == 0 && !buf.is_empty();
+        if self.len > self.expected_len || (ended && self.len != self.expected_len) {
+            return Err(corrupt("length"));
+        }
+        if ended && self.hasher.clone().finalize() != self.expected_crc {
+            return Err(corrupt("CRC"));
+        }
+        Ok(n)
+    }
+}
+
+/// DOS date and time fields as Unix seconds, read as UTC; 0 if invalid
+fn dos_to_unix(date: u16, time: u16) -> u64 {
+    NaiveDate::from_ymd_opt(
+        1980 + i32::from(date >> 9),
+        u32::from((date >> 5) & 0xf),
+        u32::from(date & 0x1f),
+    )
+    .and_then(|day| {
+        day.and_hms_opt(
+            u32::from(time >> 11),
+            u32::from((time >> 5) & 0x3f),
+            u32::from(time & 0x1f) * 2,
+        )
+    })
+    .map_or(0, |t| t.and_utc().timestamp().max(0) as u64)
+}
+
+/// Unix seconds as DOS `(date, time)`, clamped to the years DOS covers
+fn unix_to_dos(secs: u64) -> (u16, u16) {
+    let Some(t) = DateTime::from_timestamp(secs.min(i64::MAX as u64) as i64, 0) else {
+        return (0x21, 0);
+    };
+    if t.year() < 1980 {
+        return (0x21, 0);
+    }
+    let year = (t.year() - 1980).min(127) as u16;
+    let date = (year << 9) | ((t.month() as u16) << 5) | t.day() as u16;
+    let time = ((t.hour() as u16) << 11) | ((t.minute() as u16) << 5) | (t.second() as u16 / 2);
+    (date, time)
+}
+
+/// Writes an archive of stored entries to a plain [`Write`]
+///
+/// Each entry's size and CRC go in its local header, so no seeking or data
+/// descriptors are needed; the caller works them out first.
+pub(crate) struct ZipWriter<W: Write> {
+    out: W,
+    offset: u64,
+    central: Vec<u8>,
+    count: u64,
+}
+
+impl<W: Write> ZipWriter<W> {
+    pub fn new(out: W) -> Self {
+        ZipWriter {
+            out,
+            offset: 0,
+            central: Vec::new(),
+            count: 0,
+        }
+    }
+
+    /// Append an entry: a directory when `name` ends with `/`
+    ///
+    /// `content` must yield exactly `size` bytes hashing to `crc32`.
+    pub fn add(
+        &mut self,
+        name: &str,
+        mode: u32,
+        modified: u64,
+        size: u64,
+        crc32: u32,
+        content: &mut dyn Read,
+    ) -> Result<()> {
+        let big_size = size >= u64::from(ZIP64_MARKER);
+        let big_offset = self.offset >= u64::from(ZIP64_MARKER);
+        let version = if big_size || big_offset { VERSION_ZIP64 } else { VERSION };
+        let (date, time) = unix_to_dos(modified);
+        let size32 = if big_size { ZIP64_MARKER } else { size as u32 };
+        let mtime = modified.min(i32::MAX as u64) as u32;
+
+        let mut timestamp = Vec::with_capacity(9);
+        timestamp.extend_from_slice(&EXTRA_TIMESTAMP.to_le_bytes());
+        timestamp.extend_from_slice(&5u16.to_le_bytes());
+        timestamp.push(1);
+        timestamp.extend_from_slice(&mtime.to_le_bytes());
+
+        let mut local_extra = timestamp.clone();
+        if big_size {
+            local_extra.extend_from_slice(&EXTRA_ZIP64.to_le_bytes());
+            local_extra.extend_from_slice(&16u16.to_le_bytes());
+            local_extra.extend_from_slice(&size.to_le_bytes());
+            local_extra.extend_from_slice(&size.to_le_bytes());
+        }
+        let mut central_extra = timestamp;
+        if big_size || big_offset {
+            let mut zip64 = Vec::new();
+            if big_size {
+                zip64.extend_from_slice(&size.to_le_bytes());
+                zip64.extend_from_slice(&size.to_le_bytes());
+            }
+            if big_offset {
+                zip64.extend_from_slice(&self.offset.to_le_bytes());
+            }
+            central_extra.extend_from_slice(&EXTRA_ZIP64.to_le_bytes());
+            central_extra.extend_from_slice(&(zip64.len() as u16).to_le_bytes());
+            central_extra.extend_from_slice(&zip64);
+        }
+
+        let mut local = Vec::with_capacity(LOCAL_HEADER_LEN + name.len() + local_extra.len());
+        local.extend_from_slice(&LOCAL_HEADER_SIG.to_le_bytes());
+        local.extend_from_slice(&version.to_le_bytes());
+        local.extend_from_slice(&FLAG_UTF8.to_le_bytes());
+        local.extend_from_slice(&METHOD_STORED.to_le_bytes());
+        local.extend_from_slice(&time.to_le_bytes());
+        local.extend_from_slice(&date.to_le_bytes());
+        local.extend_from_slice(&crc32.to_le_bytes());
+        local.extend_from_slice(&size32.to_le_bytes());
+        local.extend_from_slice(&size32.to_le_bytes());
+        local.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        local.extend_from_slice(&(local_extra.len() as u16).to_le_bytes());
+        local.extend_from_slice(name.as_bytes());
+        local.extend_from_slice(&local_extra);
+        self.out.write_all(&local)?;
+
+        let copied = io::copy(&mut content.take(size), &mut self.out)?;
+        if copied != size {
+            let message = format!("{}: content ended after {} of {} bytes", name, copied, size);
+            return Err(invalid(message));
+        }
+
+        let dos_attributes = if name.ends_with('/') { DOS_DIRECTORY } else { 0 };
+        let offset32 = if big_offset { ZIP64_MARKER } else { self.offset as u32 };
+        let central = &mut self.central;
+        central.extend_from_slice(&CENTRAL_HEADER_SIG.to_le_bytes());
+        central.extend_from_slice(&((HOST_UNIX << 8) | version).to_le_bytes());
+        central.extend_from_slice(&version.to_le_bytes());
+        central.extend_from_slice(&FLAG_UTF8.to_le_bytes());
+        central.extend_from_slice(&METHOD_STORED.to_le_bytes());
+        central.extend_from_slice(&time.to_le_bytes());
+        central.extend_from_slice(&date.to_le_bytes());
+        central.extend_from_slice(&crc32.to_le_bytes());
+        central.extend_from_slice(&size32.to_le_bytes());
+        central.extend_from_slice(&size32.to_le_bytes());
+        central.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        central.extend_from_slice(&(central_extra.len() as u16).to_le_bytes());
+        // Comment length, disk number, internal attributes
+        central.extend_from_slice(&[0u8; 6]);
+        central.extend_from_slice(&((mode << 16) | dos_attributes).to_le_bytes());
+        central.extend_from_slice(&offset32.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+        central.extend_from_slice(&central_extra);
+
+        self.offset += local.len() as u64 + size;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Write the central directory and end records, returning the writer
+    pub fn finish(mut self) -> Result<W> {
+        let dir_offset = self.offset;
+        let dir_size = self.central.len() as u64;
+        self.out.write_all(&self.central)?;
+
+        let zip64 = self.count >= u64::from(u16::MAX)
+            || dir_offset >= u64::from(ZIP64_MARKER)
+            || dir_size >= u64::from(ZIP64_MARKER);
+        if zip64 {
+            let zip64_end_offset = dir_offset + dir_size;
+            let mut record = Vec::with_capacity(56 + ZIP64_LOCATOR_LEN);
+            record.extend_from_slice(&ZIP64_END_SIG.to_le_bytes());
+            record.extend_from_slice(&44u64.to_le_bytes());
+            record.extend_from_slice(&((HOST_UNIX << 8) | VERSION_ZIP64).to_le_bytes());
+            record.extend_from_slice(&VERSION_ZIP64.to_le_bytes());
+            // This disk, and the disk the directory starts on
+            record.extend_from_slice(&[0u8; 8]);
+            record.extend_from_slice(&self.count.to_le_bytes());
+            record.extend_from_slice(&self.count.to_le_bytes());
+            record.extend_from_slice(&dir_size.to_le_bytes());
+            record.extend_from_slice(&dir_offset.to_le_bytes());
+
+            record.extend_from_slice(&ZIP64_LOCATOR_SIG.to_le_bytes());
+            record.extend_from_slice(&0u32.to_le_bytes());
+            record.extend_from_slice(&zip64_end_offset.to_le_bytes());
+            record.extend_from_slice(&1u32.to_le_bytes());
+            self.out.write_all(&record)?;
+        }
+
+        let count16 = self.count.min(u64::from(u16::MAX)) as u16;
+        let clamp = |value: u64| value.min(u64::from(ZIP64_MARKER)) as u32;
+        let mut end = Vec::with_capacity(END_LEN);
+        end.extend_from_slice(&END_SIG.to_le_bytes());
+        end.extend_from_slice(&[0u8; 4]);
+        end.extend_from_slice(&count16.to_le_bytes());
+        end.extend_from_slice(&count16.to_le_bytes());
+        end.extend_from_slice(&clamp(dir_size).to_le_bytes());
+        end.extend_from_slice(&clamp(dir_offset).to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+        self.out.write_all(&end)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+impl Cartridge {
+    /// Stream every file under `prefix` into a zip archive
+    ///
+    /// Entry paths are relative to `prefix`. Each file is read twice, once
+    /// for its CRC and once to copy it, so nothing is held in memory and
+    /// `writer` needn't seek. Directories get their own entries, including
+    /// empty ones created with `create_dir`. Files carry their permissions
+    /// and modification time; symlinks are stored the Info-ZIP way, as an
+    /// entry with a symlink mode whose content is the target. Internal
+    /// `.cartridge/` entries are left out.
+    pub fn export_zip<W: Write>(&self, writer: W, prefix: &str) -> Result<ExportReport> {
+        let mut report = ExportReport::default();
+        let mut zip = ZipWriter::new(writer);
+        let mut dirs_written: HashSet<String> = HashSet::new();
+
+        let strip = prefix.trim_matches('/');
+        let mut paths = self.list_dir(prefix)?;
+        paths.sort();
+        for path in paths {
+            if is_internal(&path) {
+                continue;
+            }
+            let metadata = self.metadata_nofollow(&path)?;
+            let trimmed = path.trim_start_matches('/');
+            let rel = trimmed
+                .strip_prefix(strip)
+                .unwrap_or(trimmed)
+                .trim_start_matches('/');
+            if rel.is_empty() {
+                continue;
+            }
+            if safe_join(Path::new(""), rel).is_none() {
+                report
+                    .skipped
+                    .push(skipped(&path, "path is not a safe relative path"));
+                continue;
+            }
+
+            // Parent directories, outermost first, then the directory itself
+            let mut dir = String::new();
+            let parts: Vec<&str> = rel.split('/').collect();
+            let dir_parts = if metadata.is_directory() { parts.len() } else { parts.len() - 1 };
+            for part in &parts[..dir_parts] {
+                dir.push_str(part);
+                dir.push('/');
+                if dirs_written.insert(dir.clone()) {
+                    let mode = S_IFDIR | 0o755;
+                    zip.add(&dir, mode, metadata.modified_at, 0, 0, &mut io::empty())?;
+                }
+            }
+            if metadata.is_directory() {
+                continue;
+            }
+
+            if let Some(target) = metadata.symlink_target() {
+                let crc = crc32fast::hash(target.as_bytes());
+                let mode = S_IFLNK | 0o777;
+                let size = target.len() as u64;
+                let mut content = target.as_bytes();
+                zip.add(rel, mode, metadata.modified_at, size, crc, &mut content)?;
+                report.symlinks_exported += 1;
+                continue;
+            }
+
+            let mut hasher = crc32fast::Hasher::new();
+            let mut reader = self.open_reader(&path)?;
+            let mut chunk = vec![0u8; 64 * 1024];
+            loop {
+                let n = reader.read(&mut chunk)?;
+                if n == 0 {
+                    break;
+                }
+                hasher.update(&chunk[..n]);
+            }
+            let mode = S_IFREG | (metadata.permissions & 0o7777);
+            let (size, crc) = (metadata.size, hasher.finalize());
+            let mut content = self.open_reader(&path)?;
+            zip.add(rel, mode, metadata.modified_at, size, crc, &mut content)?;
+
+            report.files_exported += 1;
+            report.bytes_written += size;
+        }
+
+        zip.finish()?;
+        Ok(report)
+    }
+
+    /// Ingest a zip archive, placing its entries under `dest_prefix`
+    ///
+    /// Entries are streamed from `reader` one at a time, inflating deflated
+    /// ones as they are written, so the archive is never held in memory.
+    /// Files are written (replacing existing ones) and get a content type
+    /// from their extension; directory entries become directories, so empty
+    /// ones survive; Unix symlink entries become cartridge symlinks.
+    /// Encrypted entries, compression methods other than stored and
+    /// deflated, paths that climb out with `..`, and anything that would
+    /// land under `.cartridge/` are recorded as skipped. A CRC mismatch
+    /// fails the import with the entry's file part written.
+    pub fn import_zip<R: Read + Seek>(
+        &mut self,
+        mut reader: R,
+        dest_prefix: &str,
+    ) -> Result<ImportReport> {
+        let mut report = ImportReport::default();
+
+        for entry in read_entries(&mut reader)? {
+            let name = entry.name.trim_end_matches('/');
+            let rel = match safe_join(Path::new(""), name)
+                .and_then(|p| relative_path(&p, Path::new("")))
+            {
+                Some(rel) => rel,
+                None => {
+                    report
+                        .skipped
+                        .push(skipped(&entry.name, "path is not a safe relative path"));
+                    continue;
+                }
+            };
+
+            let dest = join_prefix(dest_prefix, &rel);
+            if is_internal(&dest) {
+                report.skipped.push(skipped(
+                    &entry.name,
+                    "destination is reserved for cartridge metadata",
+                ));
+                continue;
+            }
+
+            if entry.is_dir() {
+                if !self.exists(&dest)? {
+                    self.create_dir(&dest)?;
+                }
+                continue;
+            }
+            if let Some(reason) = entry.unsupported() {
+                report.skipped.push(skipped(&entry.name, reason));
+                continue;
+            }
+
+            if entry.is_symlink() {
+                let mut target = String::new();
+                open_entry(&mut reader, &entry)?.read_to_string(&mut target)?;
+                if target.is_empty() {
+                    report.skipped.push(skipped(&entry.name, "symlink has no target"));
+                    continue;
+                }
+                self.replace_with_symlink(&target, &dest)?;
+                report.symlinks_imported += 1;
+                continue;
+            }
+
+            let written = self.write_stream(&dest, &mut open_entry(&mut reader, &entry)?)?;
+            if let Some(mime) = content_type::from_path(&dest) {
+                self.set_content_type(&dest, Some(mime.to_string()))?;
+            }
+
+            report.files_imported += 1;
+            report.bytes_written += written;
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_dos_times() {
+        // 2024-02-29 13:45:58
+        let secs = 1_709_214_358;
+        let (date, time) = unix_to_dos(secs);
+        assert_eq!(dos_to_unix(date, time), secs);
+        // Before 1980 clamps to the DOS epoch
+        assert_eq!(dos_to_unix(unix_to_dos(0).0, unix_to_dos(0).1), 315_532_800);
+        assert_eq!(dos_to_unix(0, 0), 0);
+    }
+
+    #[test]
+    fn test_write_then_read() {
+        let mut zip = ZipWriter::new(Vec::new());
+        zip.add("dir/", S_IFDIR | 0o755, 1_000_000_000, 0, 0, &mut io::empty()).unwrap();
+        let data = b"hello zip";
+        let crc = crc32fast::hash(data);
+        zip.add("dir/a.txt", S_IFREG | 0o600, 1_000_000_000, 9, crc, &mut &data[..]).unwrap();
+        let bytes = zip.finish().unwrap();
+
+        let mut reader = Cursor::new(bytes);
+        let entries = read_entries(&mut reader).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].is_dir());
+        assert_eq!(entries[1].name, "dir/a.txt");
+        assert_eq!(entries[1].modified, 1_000_000_000);
+        assert_eq!(entries[1].metadata().permissions, 0o600);
+
+        let mut content = Vec::new();
+        open_entry(&mut reader, &entries[1]).unwrap().read_to_end(&mut content).unwrap();
+        assert_eq!(content, data);
+
+        // A wrong CRC fails the read that reaches the end
+        let mut bad = entries[1].clone();
+        bad.crc32 ^= 1;
+        let err = open_entry(&mut reader, &bad).unwrap().read_to_end(&mut content).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    /// An archive of one entry whose content is `data` deflated at `level`
+    fn deflated(data: &[u8], level: flate2::Compression) -> (Cursor<Vec<u8>>, ZipEntry) {
+        let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), level);
+        encoder.write_all(data).unwrap();
+        let stream = encoder.finish().unwrap();
+
+        // Written stored, then described as deflated
+        let mut zip = ZipWriter::new(Vec::new());
+        let crc = crc32fast::hash(&stream);
+        let len = stream.len() as u64;
+        zip.add("data.bin", S_IFREG | 0o644, 0, len, crc, &mut &stream[..]).unwrap();
+        let mut reader = Cursor::new(zip.finish().unwrap());
+        let mut entry = read_entries(&mut reader).unwrap().remove(0);
+        entry.method = METHOD_DEFLATED;
+        entry.crc32 = crc32fast::hash(data);
+        entry.size = data.len() as u64;
+        (reader, entry)
+    }
+
+    /// Where a `ZipWriter` entry's content starts, past its local header
+    /// and extended timestamp
+    fn content_start(entry: &ZipEntry) -> usize {
+        entry.local_header_offset as usize + LOCAL_HEADER_LEN + entry.name.len() + 9
+    }
+
+    fn read_entry(reader: &mut Cursor<Vec<u8>>, entry: &ZipEntry) -> io::Result<Vec<u8>> {
+        let mut content = Vec::new();
+        open_entry(reader, entry).unwrap().read_to_end(&mut content)?;
+        Ok(content)
+    }
+
+    #[test]
+    fn test_deflated_entries() {
+        // No compression writes stored blocks, a short input gets a fixed
+        // Huffman block, and a longer varied one gets a dynamic block
+        let short = b"aaaaabbbbb".to_vec();
+        let varied: Vec<u8> = (0..200_000u32)
+            .map(|i| b"the quick brown fox jumps over"[(i.wrapping_mul(i) % 29) as usize])
+            .collect();
+        let (stored, fixed, dynamic) = (0, 1, 2);
+        for (data, level, block_type) in [
+            (short.clone(), flate2::Compression::none(), stored),
+            (short, flate2::Compression::fast(), fixed),
+            (varied.clone(), flate2::Compression::best(), dynamic),
+            (varied, flate2::Compression::none(), stored),
+        ] {
+            let (mut reader, entry) = deflated(&data, level);
+            let first = reader.get_ref()[content_start(&entry)];
+            assert_eq!((first >> 1) & 3, block_type);
+            assert_eq!(read_entry(&mut reader, &entry).unwrap(), data);
+        }
+
+        let (mut reader, entry) = deflated(&[], flate2::Compression::default());
+        assert!(read_entry(&mut reader, &entry).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_corrupt_deflated_entries() {
+        let data = b"some content that deflates".repeat(100);
+        let (mut reader, entry) = deflated(&data, flate2::Compression::best());
+
+        // Cut short: the stream ends before its final block
+        let mut short = entry.clone();
+        short.compressed_size /= 2;
+        assert!(read_entry(&mut reader, &short).is_err());
+
+        // Reserved block type 3
+        let mut bytes = reader.get_ref().clone();
+        bytes[content_start(&entry)] = 0x07;
+        assert!(read_entry(&mut Cursor::new(bytes), &entry).is_err());
+
+        // Inflates fine but to more than the recorded size
+        let mut small = entry.clone();
+        small.size -= 1;
+        let err = read_entry(&mut reader, &small).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_malformed_archives() {
+        let mut zip = ZipWriter::new(Vec::new());
+        let data = b"hello zip";
+        let crc = crc32fast::hash(data);
+        zip.add("a.txt", S_IFREG | 0o644, 0, 9, crc, &mut &data[..]).unwrap();
+        let archive = zip.finish().unwrap();
+        let end = archive.len() - END_LEN;
+        let central = archive.len() - END_LEN - (46 + 5 + 9);
+
+        let entries_of = |bytes: Vec<u8>| read_entries(&mut Cursor::new(bytes));
+        assert!(entries_of(archive.clone()).is_ok());
+
+        // Every truncation fails cleanly rather than panicking
+        for len in 0..archive.len() {
+            assert!(entries_of(archive[..len].to_vec()).is_err(), "truncated to {}", len);
+        }
+
+        // Directory size pointing past the end of the archive
+        let mut bytes = archive.clone();
+        bytes[end + 12..end + 16].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(entries_of(bytes), Err(CartridgeError::InvalidZip(_))));
+
+        // More entries claimed than the directory holds
+        let mut bytes = archive.clone();
+        bytes[end + 10..end + 12].copy_from_slice(&2u16.to_le_bytes());
+        assert!(matches!(entries_of(bytes), Err(CartridgeError::InvalidZip(_))));
+
+        // A damaged central header signature
+        let mut bytes = archive.clone();
+        bytes[central] ^= 0xff;
+        assert!(matches!(entries_of(bytes), Err(CartridgeError::InvalidZip(_))));
+
+        // An extra field longer than the space left for it
+        let mut bytes = archive.clone();
+        bytes[central + 30..central + 32].copy_from_slice(&100u16.to_le_bytes());
+        assert!(matches!(entries_of(bytes), Err(CartridgeError::InvalidZip(_))));
+
+        // A damaged local header is caught when the entry is opened
+        let mut bytes = archive.clone();
+        bytes[0] ^= 0xff;
+        let mut reader = Cursor::new(bytes);
+        let entry = read_entries(&mut reader).unwrap().remove(0);
+        assert!(matches!(open_entry(&mut reader, &entry), Err(CartridgeError::InvalidZip(_))));
+
+        // So is an unsupported compression method
+        let mut reader = Cursor::new(archive);
+        let mut entry = read_entries(&mut reader).unwrap().remove(0);
+        entry.method = 12;
+        assert!(matches!(open_entry(&mut reader, &entry), Err(CartridgeError::InvalidZip(_))));
+    }
+
+    #[test]
+    fn test_not_a_zip() {
+        let mut reader = Cursor::new(b"definitely not a zip archive".to_vec());
+        assert!(matches!(read_entries(&mut reader), Err(CartridgeError::InvalidZip(_))));
+    }
+}
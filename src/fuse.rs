@@ -0,0 +1,651 @@
+//! FUSE mounting (requires the `fuse` feature, Linux only)
+//!
+//! [`mount`] exposes a [`SharedCartridge`] as a local filesystem so tools
+//! that only understand real paths can work on its contents. Paths map
+//! one-to-one: `docs/report.txt` in the cartridge is `<mountpoint>/docs/report.txt`.
+//! Directories are inferred from path prefixes the same way
+//! [`Cartridge::list_children`] does, and the internal `.cartridge/` tree is
+//! hidden.
+//!
+//! Every request takes the shared handle's lock, so the mounted cartridge
+//! can keep being used from Rust while it is mounted. Reads load the whole
+//! file and return the requested range; writes go through
+//! [`Cartridge::write_at`]. Changes are flushed on `fsync` and when the
+//! filesystem is unmounted.
+//!
+//! Mounting needs `/dev/fuse` and the `fusermount` helper from the fuse
+//! package on the host.
+
+use crate::transfer::is_internal;
+use crate::{Cartridge, CartridgeError, FileMetadata, FileType, Result, SharedCartridge};
+use fuser::{
+    FileAttr, Filesystem, MountOption, ReplyAttr, ReplyCreate, ReplyData, ReplyDirectory,
+    ReplyEmpty, ReplyEntry, ReplyOpen, ReplyWrite, Request, TimeOrNow,
+};
+use libc::c_int;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+/// How long the kernel may cache attributes and lookups
+const TTL: Duration = Duration::from_secs(1);
+
+/// Inode of the mount root (the cartridge path `/`)
+const ROOT_INO: u64 = 1;
+
+/// Options for [`mount_with_options`]
+#[derive(Debug, Clone, Default)]
+pub struct MountOptions {
+    /// Reject every change with `EROFS` (read-only cartridges are always
+    /// mounted this way)
+    pub read_only: bool,
+
+    /// Let users other than the one mounting access the files; needs
+    /// `user_allow_other` in `/etc/fuse.conf` unless mounting as root
+    pub allow_other: bool,
+}
+
+/// A mounted cartridge
+///
+/// The filesystem stays mounted until [`unmount`](Self::unmount) is called
+/// or the handle is dropped; either way pending changes are flushed.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use cartridge_rs::Cartridge;
+/// let shared = Cartridge::create("my-data", "My Data")?.into_shared();
+/// let handle = cartridge_rs::mount(shared.clone(), "/mnt/my-data".as_ref())?;
+///
+/// // /mnt/my-data now shows the cartridge's files
+/// std::fs::write("/mnt/my-data/hello.txt", b"hello")?;
+/// handle.unmount()?;
+///
+/// assert_eq!(shared.read("hello.txt")?, b"hello");
+/// # Ok::<(), cartridge_rs::CartridgeError>(())
+/// ```
+pub struct MountHandle {
+    session: Option<fuser::BackgroundSession>,
+    cart: SharedCartridge,
+}
+
+impl MountHandle {
+    /// Unmount the filesystem and flush the cartridge
+    pub fn unmount(mut self) -> Result<()> {
+        self.finish()
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if let Some(session) = self.session.take() {
+            // Dropping the session unmounts it
+            drop(session);
+            self.cart.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for MountHandle {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            warn!("Failed to flush cartridge after unmounting: {}", e);
+        }
+    }
+}
+
+/// Mount a cartridge at `mountpoint`
+///
+/// Read-write unless the cartridge itself is read-only. See
+/// [`mount_with_options`] to mount read-only or allow other users.
+pub fn mount(cart: SharedCartridge, mountpoint: &Path) -> Result<MountHandle> {
+    mount_with_options(cart, mountpoint, &MountOptions::default())
+}
+
+/// Mount a cartridge at `mountpoint` with the given options
+pub fn mount_with_options(
+    cart: SharedCartridge,
+    mountpoint: &Path,
+    options: &MountOptions,
+) -> Result<MountHandle> {
+    let read_only = options.read_only || cart.with_read(Cartridge::is_read_only);
+    let slug = cart.with_read(Cartridge::slug).unwrap_or_default();
+
+    let mut mount_options = vec![
+        MountOption::FSName(format!("cartridge:{}", slug)),
+        MountOption::Subtype("cartridge".to_string()),
+        MountOption::DefaultPermissions,
+        if read_only { MountOption::RO } else { MountOption::RW },
+    ];
+    if options.allow_other {
+        mount_options.push(MountOption::AllowOther);
+    }
+
+    debug!("Mounting cartridge {} at {}", slug, mountpoint.display());
+    let fs = CartridgeFs::new(cart.clone(), read_only);
+    let session = fuser::spawn_mount2(fs, mountpoint, &mount_options)?;
+    Ok(MountHandle {
+        session: Some(session),
+        cart,
+    })
+}
+
+/// Two-way map between inode numbers and cartridge paths
+///
+/// Inodes are handed out on first sight and kept for the life of the
+/// mount, so a path keeps its inode across lookups.
+#[derive(Debug)]
+struct InodeTable {
+    paths: HashMap<u64, String>,
+    inodes: HashMap<String, u64>,
+    next: u64,
+}
+
+impl InodeTable {
+    fn new() -> Self {
+        let mut table = InodeTable {
+            paths: HashMap::new(),
+            inodes: HashMap::new(),
+            next: ROOT_INO + 1,
+        };
+        table.paths.insert(ROOT_INO, "/".to_string());
+        table.inodes.insert("/".to_string(), ROOT_INO);
+        table
+    }
+
+    fn path(&self, ino: u64) -> Option<&str> {
+        self.paths.get(&ino).map(String::as_str)
+    }
+
+    /// Inode for `path`, assigning a new one if needed
+    fn inode(&mut self, path: &str) -> u64 {
+        if let Some(&ino) = self.inodes.get(path) {
+            return ino;
+        }
+        let ino = self.next;
+        self.next += 1;
+        self.paths.insert(ino, path.to_string());
+        self.inodes.insert(path.to_string(), ino);
+        ino
+    }
+
+    /// Drop a deleted path so a later file there gets a fresh inode
+    fn remove(&mut self, path: &str) {
+        if let Some(ino) = self.inodes.remove(path) {
+            self.paths.remove(&ino);
+        }
+    }
+}
+
+/// Normalized cartridge path of `name` inside the directory at `parent`,
+/// matching the [`Entry::path`](crate::Entry::path) listings return
+fn child_path(parent: &str, name: &OsStr) -> Option<String> {
+    let name = name.to_str()?;
+    if name.is_empty() || name.contains('/') {
+        return None;
+    }
+    crate::validation::normalize_path(&format!("{}/{}", parent, name)).ok()
+}
+
+/// errno for a failed cartridge operation
+fn errno(err: &CartridgeError) -> c_int {
+    match err {
+        CartridgeError::ReadOnly => libc::EROFS,
+        CartridgeError::OutOfSpace | CartridgeError::InsufficientDiskSpace { .. } => libc::ENOSPC,
+        CartridgeError::QuotaExceeded { .. } => libc::EDQUOT,
+        CartridgeError::DanglingSymlink(_) => libc::ENOENT,
+        CartridgeError::SymlinkLoop(_) => libc::ELOOP,
+        CartridgeError::InvalidPath => libc::EINVAL,
+        CartridgeError::Io(e) => e.raw_os_error().unwrap_or(libc::EIO),
+        CartridgeError::NotFound { .. } => libc::ENOENT,
+        CartridgeError::AlreadyExists { .. } => libc::EEXIST,
+        CartridgeError::NotAFile { .. } => libc::EISDIR,
+        CartridgeError::NotADirectory { .. } => libc::ENOTDIR,
+        CartridgeError::AccessDenied { .. } | CartridgeError::EncryptedFile { .. } => libc::EACCES,
+        _ => libc::EIO,
+    }
+}
+
+/// The FUSE side of a mount
+struct CartridgeFs {
+    cart: SharedCartridge,
+    inodes: InodeTable,
+    read_only: bool,
+    uid: u32,
+    gid: u32,
+    /// Timestamp for directories that only exist as path prefixes
+    mounted_at: SystemTime,
+    /// Reported as the preferred I/O size
+    page_size: u32,
+}
+
+impl CartridgeFs {
+    fn new(cart: SharedCartridge, read_only: bool) -> Self {
+        // SAFETY: getuid/getgid can't fail and touch no memory
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        let page_size = cart.with_read(|c| c.page_size()) as u32;
+        CartridgeFs {
+            cart,
+            inodes: InodeTable::new(),
+            read_only,
+            uid,
+            gid,
+            mounted_at: SystemTime::now(),
+            page_size,
+        }
+    }
+
+    fn path(&self, ino: u64) -> std::result::Result<String, c_int> {
+        self.inodes.path(ino).map(str::to_string).ok_or(libc::ENOENT)
+    }
+
+    fn writable(&self) -> std::result::Result<(), c_int> {
+        if self.read_only {
+            Err(libc::EROFS)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Metadata for `path` without following symlinks; `None` for
+    /// directories with no catalog entry of their own
+    fn stat(&self, path: &str) -> std::result::Result<Option<FileMetadata>, c_int> {
+        if path.is_empty() || path == "/" {
+            return Ok(None);
+        }
+        if is_internal(path) {
+            return Err(libc::ENOENT);
+        }
+        self.cart.with_read(|c| match c.metadata_nofollow(path) {
+            Ok(metadata) => Ok(Some(metadata)),
+            Err(_) => match c.is_dir(path) {
+                Ok(true) => Ok(None),
+                Ok(false) => Err(libc::ENOENT),
+                Err(e) => Err(errno(&e)),
+            },
+        })
+    }
+
+    fn attr(&self, ino: u64, metadata: Option<&FileMetadata>) -> FileAttr {
+        let (kind, size, perm, mtime, crtime) = match metadata {
+            Some(metadata) => {
+                let kind = match metadata.file_type {
+                    FileType::File => fuser::FileType::RegularFile,
+                    FileType::Directory => fuser::FileType::Directory,
+                    FileType::Symlink => fuser::FileType::Symlink,
+                };
+                let size = if metadata.is_directory() { 0 } else { metadata.size };
+                let time = |secs: u64| UNIX_EPOCH + Duration::from_secs(secs);
+                let perm = (metadata.permissions & 0o7777) as u16;
+                (kind, size, perm, time(metadata.modified_at), time(metadata.created_at))
+            }
+            None => (fuser::FileType::Directory, 0, 0o755, self.mounted_at, self.mounted_at),
+        };
+        let perm = if self.read_only { perm & !0o222 } else { perm };
+        // Holes of a sparse file take no space, as `du` expects
+        let allocated = match metadata {
+            Some(metadata) if metadata.is_sparse() => {
+                metadata.data_blocks().count() as u64 * self.page_size as u64
+            }
+            _ => size,
+        };
+
+        FileAttr {
+            ino,
+            size,
+            blocks: allocated.div_ceil(512),
+            atime: mtime,
+            mtime,
+            ctime: mtime,
+            crtime,
+            kind,
+            perm,
+            nlink: if kind == fuser::FileType::Directory { 2 } else { 1 },
+            uid: self.uid,
+            gid: self.gid,
+            rdev: 0,
+            blksize: self.page_size,
+            flags: 0,
+        }
+    }
+
+    /// Attributes of `path`, assigning it an inode
+    fn lookup_path(&mut self, path: &str) -> std::result::Result<FileAttr, c_int> {
+        let metadata = self.stat(path)?;
+        let ino = self.inodes.inode(path);
+        Ok(self.attr(ino, metadata.as_ref()))
+    }
+
+    fn remove(&mut self, parent: u64, name: &OsStr, dir: bool) -> std::result::Result<(), c_int> {
+        self.writable()?;
+        let path = child_path(&self.path(parent)?, name).ok_or(libc::ENOENT)?;
+        let metadata = self.stat(&path)?;
+        let is_dir = metadata.as_ref().is_none_or(FileMetadata::is_directory);
+        match (dir, is_dir) {
+            (true, false) => return Err(libc::ENOTDIR),
+            (false, true) => return Err(libc::EISDIR),
+            _ => {}
+        }
+        self.cart.with_write(|c| {
+            if dir && !c.list_children(&path).map_err(|e| errno(&e))?.is_empty() {
+                return Err(libc::ENOTEMPTY);
+            }
+            match metadata {
+                Some(_) => c.delete(&path).map_err(|e| errno(&e)),
+                None => Ok(()),
+            }
+        })?;
+        self.inodes.remove(&path);
+        Ok(())
+    }
+}
+
+impl Filesystem for CartridgeFs {
+    fn destroy(&mut self) {
+        if let Err(e) = self.cart.flush() {
+            warn!("Failed to flush cartridge on unmount: {}", e);
+        }
+    }
+
+    fn lookup(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        let result = self
+            .path(parent)
+            .and_then(|parent| child_path(&parent, name).ok_or(libc::ENOENT))
+            .and_then(|path| self.lookup_path(&path));
+        match result {
+            Ok(attr) => reply.entry(&TTL, &attr, 0),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    fn getattr(&mut self, _req: &Request<'_>, ino: u64, _fh: Option<u64>, reply: ReplyAttr) {
+        match self.path(ino).and_then(|path| self.stat(&path)) {
+            Ok(metadata) => reply.attr(&TTL, &self.attr(ino, metadata.as_ref())),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    fn setattr(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _mode: Option<u32>,
+        _uid: Option<u32>,
+        _gid: Option<u32>,
+        size: Option<u64>,
+        _atime: Option<TimeOrNow>,
+        _mtime: Option<TimeOrNow>,
+        _ctime: Option<SystemTime>,
+        _fh: Option<u64>,
+        _crtime: Option<SystemTime>,
+        _chgtime: Option<SystemTime>,
+        _bkuptime: Option<SystemTime>,
+        _flags: Option<u32>,
+        reply: ReplyAttr,
+    ) {
+        // Only size changes are stored; other attributes are accepted and ignored
+        let result = self.path(ino).and_then(|path| {
+            if let Some(size) = size {
+                self.writable()?;
+                self.cart
+                    .with_write(|c| c.truncate(&path, size))
+                    .map_err(|e| errno(&e))?;
+            }
+            self.stat(&path)
+        });
+        match result {
+            Ok(metadata) => reply.attr(&TTL, &self.attr(ino, metadata.as_ref())),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    fn readlink(&mut self, _req: &Request<'_>, ino: u64, reply: ReplyData) {
+        let result = self
+            .path(ino)
+            .and_then(|path| self.cart.with_read(|c| c.read_link(&path)).map_err(|e| errno(&e)));
+        match result {
+            Ok(target) => reply.data(target.as_bytes()),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    fn mkdir(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        reply: ReplyEntry,
+    ) {
+        let result = self.writable().and_then(|()| {
+            let path = child_path(&self.path(parent)?, name).ok_or(libc::EINVAL)?;
+            if self.stat(&path).is_ok() {
+                return Err(libc::EEXIST);
+            }
+            self.cart
+                .with_write(|c| c.create_dir(&path))
+                .map_err(|e| errno(&e))?;
+            self.lookup_path(&path)
+        });
+        match result {
+            Ok(attr) => reply.entry(&TTL, &attr, 0),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    fn unlink(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        match self.remove(parent, name, false) {
+            Ok(()) => reply.ok(),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    fn rmdir(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        match self.remove(parent, name, true) {
+            Ok(()) => reply.ok(),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    fn open(&mut self, _req: &Request<'_>, ino: u64, flags: i32, reply: ReplyOpen) {
+        let result = self.path(ino).and_then(|path| {
+            if flags & libc::O_ACCMODE != libc::O_RDONLY {
+                self.writable()?;
+            }
+            match self.stat(&path)? {
+                Some(metadata) if metadata.is_file() => Ok(()),
+                _ => Err(libc::EISDIR),
+            }
+        });
+        match result {
+            Ok(()) => reply.opened(0, 0),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    fn read(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        size: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyData,
+    ) {
+        let result = self
+            .path(ino)
+            .and_then(|path| self.cart.read(&path).map_err(|e| errno(&e)));
+        match result {
+            Ok(content) => {
+                let start = (offset.max(0) as usize).min(content.len());
+                let end = start.saturating_add(size as usize).min(content.len());
+                reply.data(&content[start..end]);
+            }
+            Err(e) => reply.error(e),
+        }
+    }
+
+    fn write(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        data: &[u8],
+        _write_flags: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyWrite,
+    ) {
+        let result = self.writable().and_then(|()| {
+            let path = self.path(ino)?;
+            self.cart
+                .with_write(|c| c.write_at(&path, offset.max(0) as u64, data))
+                .map_err(|e| errno(&e))
+        });
+        match result {
+            Ok(()) => reply.written(data.len() as u32),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    fn fsync(
+        &mut self,
+        _req: &Request<'_>,
+        _ino: u64,
+        _fh: u64,
+        _datasync: bool,
+        reply: ReplyEmpty,
+    ) {
+        match self.cart.flush() {
+            Ok(()) => reply.ok(),
+            Err(e) => reply.error(errno(&e)),
+        }
+    }
+
+    fn readdir(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        mut reply: ReplyDirectory,
+    ) {
+        let path = match self.path(ino) {
+            Ok(path) => path,
+            Err(e) => return reply.error(e),
+        };
+        let children = match self.cart.list_children(&path) {
+            Ok(children) => children,
+            Err(e) => return reply.error(errno(&e)),
+        };
+
+        let parent = match path.rfind('/') {
+            Some(idx) if idx > 0 => self.inodes.inode(&path[..idx]),
+            _ => ROOT_INO,
+        };
+        let mut listing = vec![
+            (ino, fuser::FileType::Directory, ".".to_string()),
+            (parent, fuser::FileType::Directory, "..".to_string()),
+        ];
+        for child in children {
+            if child.name.is_empty() {
+                continue;
+            }
+            let kind = match child.file_type {
+                FileType::File => fuser::FileType::RegularFile,
+                FileType::Directory => fuser::FileType::Directory,
+                FileType::Symlink => fuser::FileType::Symlink,
+            };
+            listing.push((self.inodes.inode(&child.path), kind, child.name));
+        }
+
+        for (i, (ino, kind, name)) in listing.into_iter().enumerate().skip(offset as usize) {
+            // The offset passed back to us is that of the next entry
+            if reply.add(ino, (i + 1) as i64, kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+
+    fn create(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        flags: i32,
+        reply: ReplyCreate,
+    ) {
+        let result = self.writable().and_then(|()| {
+            let path = child_path(&self.path(parent)?, name).ok_or(libc::EINVAL)?;
+            let exists = self.stat(&path).is_ok();
+            if exists && flags & libc::O_EXCL != 0 {
+                return Err(libc::EEXIST);
+            }
+            if !exists || flags & libc::O_TRUNC != 0 {
+                self.cart.write(&path, &[]).map_err(|e| errno(&e))?;
+            }
+            self.lookup_path(&path)
+        });
+        match result {
+            Ok(attr) => reply.created(&TTL, &attr, 0, 0, 0),
+            Err(e) => reply.error(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inode_table_and_paths() {
+        let mut table = InodeTable::new();
+        assert_eq!(table.path(ROOT_INO), Some("/"));
+
+        let docs = child_path("/", OsStr::new("docs")).unwrap();
+        let report = child_path(&docs, OsStr::new("report.txt")).unwrap();
+        assert_eq!(report, "/docs/report.txt");
+        assert_eq!(child_path("/docs", OsStr::new("a/b")), None);
+        assert_eq!(child_path("/docs", OsStr::new("..")), None);
+
+        let ino = table.inode(&report);
+        assert_eq!(table.inode(&report), ino);
+        assert_eq!(table.path(ino), Some("/docs/report.txt"));
+
+        // A deleted path gets a new inode when it comes back
+        table.remove(&report);
+        assert_eq!(table.path(ino), None);
+        assert_ne!(table.inode(&report), ino);
+    }
+
+    #[test]
+    fn test_stat_infers_directories_and_hides_internal_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cart = Cartridge::create_at(dir.path().join("fs.cart"), "fs", "FS").unwrap();
+        cart.write("docs/report.txt", b"hello").unwrap();
+        let mut fs = CartridgeFs::new(cart.into_shared(), false);
+
+        let file = fs.lookup_path("docs/report.txt").unwrap();
+        assert_eq!(file.kind, fuser::FileType::RegularFile);
+        assert_eq!(file.size, 5);
+        assert_eq!(file.perm, 0o644);
+
+        let dir = fs.lookup_path("docs").unwrap();
+        assert_eq!(dir.kind, fuser::FileType::Directory);
+        assert_eq!(fs.lookup_path("missing").unwrap_err(), libc::ENOENT);
+        assert_eq!(fs.lookup_path(".cartridge/manifest.json").unwrap_err(), libc::ENOENT);
+
+        let read_only = CartridgeFs::new(fs.cart.clone(), true);
+        assert_eq!(read_only.writable(), Err(libc::EROFS));
+        let metadata = read_only.stat("docs/report.txt").unwrap();
+        assert_eq!(read_only.attr(2, metadata.as_ref()).perm, 0o444);
+    }
+}
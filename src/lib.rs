@@ -53,31 +53,106 @@
 // Core implementation (merged from cartridge-core)
 pub mod core;
 
+#[cfg(all(target_arch = "wasm32", not(feature = "no-fs")))]
+compile_error!("wasm32 builds need the `no-fs` feature");
+
+// Async API over the blocking thread pool
+#[cfg(feature = "tokio")]
+mod async_cartridge;
+#[cfg(feature = "tokio")]
+pub use async_cartridge::{AsyncCartridge, AsyncVfs};
+
+// FUSE mounting
+#[cfg(all(feature = "fuse", target_os = "linux"))]
+mod fuse;
+#[cfg(all(feature = "fuse", target_os = "linux"))]
+pub use fuse::{mount, mount_with_options, MountHandle, MountOptions};
+
+// Copying trees between any Vfs and a cartridge
+mod copy_tree;
+pub use copy_tree::{CopyOptions, CopyReport, OverwritePolicy};
+
+// Operations attributed to a session
+mod session;
+pub use session::{Session, SessionGuard, SharedSession};
+
+#[cfg(not(feature = "no-fs"))]
+mod local_vfs;
+#[cfg(not(feature = "no-fs"))]
+pub use local_vfs::LocalVfs;
+
+#[cfg(not(feature = "no-fs"))]
+mod snapshot_view;
+#[cfg(not(feature = "no-fs"))]
+pub use snapshot_view::SnapshotView;
+
+#[cfg(feature = "zip")]
+mod zip_vfs;
+#[cfg(feature = "zip")]
+pub use zip_vfs::ZipVfs;
+
 // Re-export core modules internally so crate:: paths in core still work
 #[allow(unused_imports)]
 pub(crate) use core::{
-    allocator, audit, buffer_pool, catalog, compression, encryption, engram_integration, error,
-    header, iam, io, manifest, page, snapshot, validation, vfs, wal,
+    allocator, audit, batch, buffer_pool, cartridge, catalog, check, clock, compression,
+    content_type, dedup, encryption, error, events, find, header, iam, io, manifest, pack, page,
+    quota, retention, symlink, telemetry, transfer, validation, wal, watch,
 };
+#[cfg(not(feature = "no-fs"))]
+#[allow(unused_imports)]
+pub(crate) use core::{engram_integration, lock, snapshot, vfs};
 
 // Re-export core types that users need
 pub use crate::core::{
-    cartridge::CartridgeStats,
-    catalog::{FileMetadata, FileType},
-    encryption::EncryptionConfig,
+    allocator::hybrid::AllocatorStats,
+    audit::{AuditFilter, AuditRecord, Operation},
+    batch::WriteBatch,
+    buffer_pool::PageGuard,
+    cartridge::{
+        CartridgeStats, DefragReport, DetailedStats, GrowthPolicy, RefreshReport, SyncPolicy,
+        VacuumReport,
+    },
+    catalog::{FileMetadata, FileType, MetadataPatch, HOLE_BLOCK},
+    check::{BlockRef, ConsistencyReport, ScrubError, ScrubResult, ScrubToken, SharedBlock},
+    encryption::{EncryptionConfig, EncryptionKey},
     error::{CartridgeError, Result},
-    header::{Header, S3AclMode, S3FeatureFuses, S3SseMode, S3VersioningMode, PAGE_SIZE},
-    iam::{Action, Effect, Policy, PolicyEngine, Statement},
-    manifest::Manifest,
-    snapshot::{SnapshotManager, SnapshotMetadata},
-    validation::ContainerSlug,
+    events::{CartridgeEvent, EventListener},
+    find::CaseSensitivity,
+    header::{
+        Header, PageSize, S3AclMode, S3FeatureFuses, S3SseMode, S3VersioningMode, PAGE_SIZE,
+    },
+    iam::{Action, CacheStats, Effect, Policy, PolicyEngine, Statement},
+    manifest::{Bump, Dependency, Manifest, UnmetDependency},
+    pack::{DigestManifest, DigestMismatch, FileDigest, PackOptions, PackReport},
+    quota::QuotaUsage,
+    retention::RetentionPolicy,
+    stream::{FileReader, STREAM_CHUNK_SIZE},
+    system::SystemFiles,
+    transfer::{ExportReport, ImportOptions, ImportReport, SkippedEntry, SymlinkPolicy},
+    validation::{normalize_path, ContainerSlug, PathError, SlugError},
+    watch::{ChangeEvent, ChangeKind, DEFAULT_WATCH_CAPACITY},
+};
+pub use ed25519_dalek::SigningKey;
+#[cfg(not(feature = "no-fs"))]
+pub use crate::core::{
+    engram_integration::{FreezeOptions, FreezeReport},
     vfs::{register_vfs, register_named_vfs, unregister_vfs, unregister_named_vfs, generate_vfs_name, VFS_NAME},
 };
+#[cfg(not(feature = "no-fs"))]
+pub use crate::core::snapshot::{SnapshotDiff, SnapshotManager, SnapshotMetadata};
+#[cfg(not(feature = "no-fs"))]
+pub use crate::core::recovery::{RecoverySession, SalvageReport, LOST_AND_FOUND};
 
 use crate::core::Cartridge as CoreCartridge;
+#[cfg(not(feature = "no-fs"))]
+use crate::core::CreateOptions;
+use crate::transfer::is_internal;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::Read;
 use std::path::Path;
+use std::sync::mpsc::Receiver;
 use std::sync::Arc;
 use tracing::{debug, info};
 
@@ -107,20 +182,21 @@ use tracing::{debug, info};
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Entry {
-    /// Full path in the archive (e.g., "research/notes/overview.cml")
+    /// Full path in the archive (e.g., "/research/notes/overview.cml")
     pub path: String,
 
     /// Just the name (e.g., "overview.cml" or "notes")
     pub name: String,
 
-    /// Parent directory path (e.g., "research/notes")
-    /// Empty string for root-level entries
+    /// Parent directory path (e.g., "/research/notes")
+    /// "/" for root-level entries
     pub parent: String,
 
     /// True if this is a directory (has children under this prefix)
     pub is_dir: bool,
 
-    /// File size in bytes (None for directories or if unavailable)
+    /// File size in bytes (None if unavailable, and for directories unless
+    /// listed with [`Cartridge::list_entries_with_sizes`])
     pub size: Option<u64>,
 
     /// Creation timestamp as Unix epoch seconds (None if unavailable)
@@ -137,118 +213,209 @@ pub struct Entry {
 
     /// Compressed size on disk in bytes (None for directories or if unavailable)
     /// This is the actual space used in the container, which may be less than
-    /// `size` when compression is enabled.
+    /// `size` when compression is enabled or the file is sparse.
     pub compressed_size: Option<u64>,
+
+    /// True if the content is stored encrypted, whether with the content key
+    /// or with a key of its own (see [`Cartridge::write_encrypted`])
+    pub encrypted: bool,
+
+    /// SHA-256 of the content as lowercase hex (None for directories, and
+    /// for files whose digest isn't stored yet; see [`Cartridge::hash`])
+    pub content_hash: Option<String>,
 }
 
-/// Convert flat paths to Entry objects with rich metadata
+/// Convert a catalog listing to Entry objects with rich metadata
 ///
-/// This helper parses paths, infers directory structure, and fetches metadata
-/// from the cartridge for each entry. Internal .cartridge/ files are filtered out.
-fn paths_to_entries(cart: &CoreCartridge, paths: &[String], _prefix: &str) -> Result<Vec<Entry>> {
+/// This helper parses paths and infers directory structure, using the
+/// metadata that came back with the listing rather than looking each path up
+/// again. Inferred directories take their timestamps from an explicit
+/// directory entry when the listing has one. Internal .cartridge/ files are
+/// filtered out.
+fn listing_to_entries(listing: &[(String, FileMetadata)], page_size: usize) -> Vec<Entry> {
     let mut entries = Vec::new();
     let mut seen_dirs: HashSet<String> = HashSet::new();
 
-    // Process each file path
-    for path in paths {
-        // Skip internal .cartridge directory
-        if path.starts_with(".cartridge/") || path == ".cartridge" {
+    // Directories created with create_dir have their own catalog entries
+    let explicit_dirs: HashMap<&str, &FileMetadata> = listing
+        .iter()
+        .filter(|(_, metadata)| metadata.is_directory())
+        .map(|(path, metadata)| (path.as_str(), metadata))
+        .collect();
+
+    let dir_entry = |path: &str| match explicit_dirs.get(path) {
+        Some(metadata) => Entry::from_catalog(path, metadata, page_size),
+        None => Entry::inferred_dir(path),
+    };
+
+    // Process each catalog entry
+    for (path, metadata) in listing {
+        if is_internal(path) {
             continue;
         }
-        // Extract name and parent from path
-        let name = path.rsplit('/').next().unwrap_or(path).to_string();
-        let parent = if let Some(idx) = path.rfind('/') {
-            if idx == 0 {
-                // Root level: "/file.txt" -> parent is "/"
-                "/".to_string()
-            } else {
-                path[..idx].to_string()
-            }
-        } else {
-            String::new()
-        };
 
-        // Fetch metadata for the file
-        let metadata = cart.metadata(path).ok();
-
-        // Create entry for the file
-        entries.push(Entry {
-            path: path.clone(),
-            name,
-            parent: parent.clone(),
-            is_dir: false,
-            size: metadata.as_ref().map(|m| m.size),
-            created: metadata.as_ref().map(|m| m.created_at),
-            modified: metadata.as_ref().map(|m| m.modified_at),
-            content_type: metadata.as_ref().and_then(|m| m.content_type.clone()),
-            file_type: metadata
-                .as_ref()
-                .map(|m| m.file_type)
-                .unwrap_or(FileType::File),
-            compressed_size: metadata
-                .as_ref()
-                .map(|m| (m.blocks.len() as u64) * PAGE_SIZE as u64),
-        });
+        if !metadata.is_directory() {
+            entries.push(Entry::from_catalog(path, metadata, page_size));
+        } else if seen_dirs.insert(path.clone()) {
+            entries.push(dir_entry(path));
+        }
 
         // Add parent directories (if not already seen)
-        let mut current_parent = parent.as_str();
-        while !current_parent.is_empty() && current_parent != "/" {
-            if seen_dirs.insert(current_parent.to_string()) {
-                let parent_name = current_parent
-                    .rsplit('/')
-                    .next()
-                    .unwrap_or(current_parent)
-                    .to_string();
-                let grandparent = if let Some(idx) = current_parent.rfind('/') {
-                    if idx == 0 {
-                        "/".to_string()
-                    } else {
-                        current_parent[..idx].to_string()
-                    }
-                } else {
-                    String::new()
-                };
-
-                entries.push(Entry {
-                    path: current_parent.to_string(),
-                    name: parent_name,
-                    parent: grandparent,
-                    is_dir: true,
-                    size: None,
-                    created: None,
-                    modified: None,
-                    content_type: None,
-                    file_type: FileType::Directory,
-                    compressed_size: None,
-                });
-            }
-
-            // Move up the tree
-            if let Some(idx) = current_parent.rfind('/') {
-                current_parent = &current_parent[..idx];
-            } else {
-                break;
+        for parent in ancestors_of(path) {
+            if seen_dirs.insert(parent.to_string()) {
+                entries.push(dir_entry(parent));
             }
         }
     }
 
-    // Sort: directories first, then alphabetically by name
+    sort_entries(&mut entries);
+    entries
+}
+
+/// Listing order: directories first, then alphabetically by name
+fn sort_entries(entries: &mut [Entry]) {
     entries.sort_by(|a, b| match (a.is_dir, b.is_dir) {
         (true, false) => std::cmp::Ordering::Less,
         (false, true) => std::cmp::Ordering::Greater,
         _ => a.name.cmp(&b.name),
     });
+}
+
+/// Ancestor directories of an archive path, outermost first
+///
+/// `"/a/b/c.txt"` → `["/a", "/a/b"]`. The root is not an ancestor.
+fn ancestors_of(path: &str) -> Vec<&str> {
+    let mut ancestors = Vec::new();
+    let mut current = path;
+    while let Some(idx) = current.rfind('/') {
+        current = &current[..idx];
+        if current.is_empty() {
+            break;
+        }
+        ancestors.push(current);
+    }
+    ancestors.reverse();
+    ancestors
+}
+
+/// Whether `path`, which sorts after the directory `dir`, also sorts after
+/// every path under it
+fn past_subtree(dir: &str, path: &str) -> bool {
+    match path.strip_prefix(dir) {
+        Some(rest) => rest.bytes().next().is_some_and(|b| b > b'/'),
+        None => true,
+    }
+}
+
+/// Lazy counterpart of [`listing_to_entries`], returned by
+/// [`Cartridge::walk`]
+///
+/// Catalog keys are sorted, so everything under a directory is contiguous,
+/// but a sibling whose name continues with a character below `/` can sort
+/// between a directory and its children (`/a`, `/a-b`, `/a/x`). A directory
+/// is therefore remembered as emitted until the walk is past its subtree,
+/// not just until the next key that isn't inside it.
+struct Walk<'a, I> {
+    catalog: I,
+    /// Directories emitted whose subtree the walk may still reach
+    open_dirs: Vec<&'a str>,
+    /// Entries ready to yield, parents before children
+    pending: VecDeque<Entry>,
+    /// Catalog entries of the directories enclosing the prefix, which the
+    /// catalog iterator doesn't reach
+    enclosing: HashMap<String, FileMetadata>,
+    page_size: usize,
+}
+
+impl<'a, I> Iterator for Walk<'a, I>
+where
+    I: Iterator<Item = (&'a String, &'a FileMetadata)>,
+{
+    type Item = Result<Entry>;
+
+    fn next(&mut self) -> Option<Result<Entry>> {
+        while self.pending.is_empty() {
+            let (path, metadata) = self.catalog.find(|(path, _)| !is_internal(path))?;
+
+            self.open_dirs.retain(|dir| !past_subtree(dir, path));
+            for dir in ancestors_of(path) {
+                if self.open_dirs.contains(&dir) {
+                    continue;
+                }
+                self.pending.push_back(match self.enclosing.get(dir) {
+                    Some(metadata) => Entry::from_catalog(dir, metadata, self.page_size),
+                    None => Entry::inferred_dir(dir),
+                });
+                self.open_dirs.push(dir);
+            }
+
+            self.pending.push_back(Entry::from_catalog(path, metadata, self.page_size));
+            if metadata.is_directory() {
+                self.open_dirs.push(path);
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+impl Entry {
+    /// Build an entry for a path that has its own catalog entry
+    fn from_catalog(path: &str, metadata: &FileMetadata, page_size: usize) -> Self {
+        let is_dir = metadata.is_directory();
+        Entry {
+            path: path.to_string(),
+            name: path.rsplit('/').next().unwrap_or(path).to_string(),
+            parent: parent_of(path),
+            is_dir,
+            size: (!is_dir).then_some(metadata.size),
+            created: Some(metadata.created_at),
+            modified: Some(metadata.modified_at),
+            content_type: metadata.content_type.clone(),
+            file_type: metadata.file_type,
+            compressed_size: (!is_dir)
+                .then(|| (metadata.data_blocks().count() * page_size) as u64),
+            encrypted: metadata.is_encrypted(),
+            content_hash: metadata.content_hash_hex(),
+        }
+    }
+
+    /// Build an entry for a directory that only exists as a path prefix
+    fn inferred_dir(path: &str) -> Self {
+        Entry {
+            path: path.to_string(),
+            name: path.rsplit('/').next().unwrap_or(path).to_string(),
+            parent: parent_of(path),
+            is_dir: true,
+            size: None,
+            created: None,
+            modified: None,
+            content_type: None,
+            file_type: FileType::Directory,
+            compressed_size: None,
+            encrypted: false,
+            content_hash: None,
+        }
+    }
+}
 
-    Ok(entries)
+/// Parent directory of an archive path
+///
+/// `"a/b/c.txt"` → `"a/b"`, `"/file.txt"` → `"/"`, `"file.txt"` → `""`.
+fn parent_of(path: &str) -> String {
+    match path.rfind('/') {
+        Some(0) => "/".to_string(),
+        Some(idx) => path[..idx].to_string(),
+        None => String::new(),
+    }
 }
 
 /// High-level Cartridge archive API
 ///
-/// This is a wrapper around `cartridge_core::Cartridge` that provides:
+/// This is a wrapper around [`core::Cartridge`](crate::core::Cartridge) that provides:
 /// - Sensible defaults
 /// - Simpler method names
 /// - Automatic resource management
-/// - Better error messages
+/// - Typed errors such as [`CartridgeError::NotFound`] that can be matched on
 ///
 /// # Examples
 ///
@@ -285,15 +452,40 @@ impl Cartridge {
     /// let mut cart = Cartridge::create("my-data", "My Data Container")?;
     /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
+    #[cfg(not(feature = "no-fs"))]
     pub fn create(slug: &str, title: &str) -> Result<Self> {
         info!("Creating cartridge with slug '{}', title '{}'", slug, title);
         let inner = CoreCartridge::create(slug, title)?;
         Ok(Cartridge { inner, vfs_name: None })
     }
 
+    /// Create a new Cartridge archive named after `title`
+    ///
+    /// The slug is derived with [`ContainerSlug::sanitize`], so any title
+    /// with at least one letter or digit works.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use cartridge_rs::Cartridge;
+    ///
+    /// // Creates "quarterly-report-q3.cart"
+    /// let cart = Cartridge::create_from_title("Quarterly Report (Q3)")?;
+    /// assert_eq!(cart.slug()?, "quarterly-report-q3");
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    #[cfg(not(feature = "no-fs"))]
+    pub fn create_from_title(title: &str) -> Result<Self> {
+        let slug = ContainerSlug::sanitize(title)?;
+        Self::create(slug.as_str(), title)
+    }
+
     /// Create a new Cartridge archive at a specific path
     ///
-    /// Use this when you need to specify a custom directory or path.
+    /// Use this when you need to specify a custom directory or path. An
+    /// existing archive at `path` is replaced (it fails with
+    /// [`CartridgeError::Locked`] if another handle has it open); use
+    /// [`Cartridge::open_or_create_at`] to keep it.
     ///
     /// # Examples
     ///
@@ -304,6 +496,7 @@ impl Cartridge {
     /// let mut cart = Cartridge::create_at("/data/my-container", "my-container", "My Container")?;
     /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
+    #[cfg(not(feature = "no-fs"))]
     pub fn create_at<P: AsRef<Path>>(path: P, slug: &str, title: &str) -> Result<Self> {
         info!(
             "Creating cartridge at {:?} with slug '{}', title '{}'",
@@ -315,256 +508,1435 @@ impl Cartridge {
         Ok(Cartridge { inner, vfs_name: None })
     }
 
-    /// Open an existing Cartridge archive
+    /// Open the archive at `path`, creating it if there is none
+    ///
+    /// Safe to call from several processes at once: exactly one creates the
+    /// archive, and the file lock makes the others fail with
+    /// [`CartridgeError::Locked`] rather than truncate it. An existing
+    /// archive must have been created with `slug`, or this fails with
+    /// [`CartridgeError::SlugMismatch`]; its title is kept.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// use cartridge_rs::Cartridge;
     ///
-    /// let mut cart = Cartridge::open("existing.cart")?;
+    /// // Creates "/data/cache.cart" on the first run, reopens it afterwards
+    /// let mut cart = Cartridge::open_or_create_at("/data/cache", "cache", "Cache")?;
     /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
-    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        info!("Opening cartridge at {:?}", path.as_ref());
-        let inner = CoreCartridge::open(path)?;
+    #[cfg(not(feature = "no-fs"))]
+    pub fn open_or_create_at<P: AsRef<Path>>(path: P, slug: &str, title: &str) -> Result<Self> {
+        info!("Opening or creating cartridge at {:?} with slug '{}'", path.as_ref(), slug);
+        let options = CreateOptions::default();
+        let inner = CoreCartridge::open_or_create_with_options(path, slug, title, &options)?;
         Ok(Cartridge { inner, vfs_name: None })
     }
 
-    /// Write data to a file in the archive
+    /// Open an existing Cartridge archive
     ///
-    /// Creates the file if it doesn't exist, updates it if it does.
-    /// Automatically creates parent directories.
+    /// The file is locked while the cartridge is open; opening it again for
+    /// writing, in this process or another, fails with
+    /// [`CartridgeError::Locked`] until the cartridge is dropped or closed.
+    /// [`open_read_only`](Self::open_read_only) still works.
+    ///
+    /// Cartridges written by a newer minor format version open read-only;
+    /// check [`is_read_only`](Self::is_read_only) before writing.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
-    /// # use cartridge_rs::Cartridge;
-    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
-    /// cart.write("documents/report.txt", b"Hello, World!")?;
+    /// use cartridge_rs::Cartridge;
+    ///
+    /// let mut cart = Cartridge::open("existing.cart")?;
     /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
-    pub fn write<P: AsRef<str>>(&mut self, path: P, content: &[u8]) -> Result<()> {
-        let path = path.as_ref();
-        debug!("Writing {} bytes to {}", content.len(), path);
-
-        // Check if file exists, create or update accordingly
-        if self.inner.exists(path)? {
-            self.inner.write_file(path, content)
-        } else {
-            self.inner.create_file(path, content)
-        }
+    #[cfg(not(feature = "no-fs"))]
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        info!("Opening cartridge at {:?}", path.as_ref());
+        let inner = CoreCartridge::open(path)?;
+        Ok(Cartridge { inner, vfs_name: None })
     }
 
-    /// Read data from a file in the archive
+    /// Open an existing Cartridge archive, waiting up to `timeout` for
+    /// another handle to release it
     ///
     /// # Examples
     ///
     /// ```rust,no_run
-    /// # use cartridge_rs::Cartridge;
-    /// # let cart = Cartridge::create("my-data", "My Data")?;
-    /// let content = cart.read("documents/report.txt")?;
-    /// println!("Content: {}", String::from_utf8_lossy(&content));
+    /// use cartridge_rs::Cartridge;
+    /// use std::time::Duration;
+    ///
+    /// let cart = Cartridge::open_with_timeout("existing.cart", Duration::from_secs(5))?;
     /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
-    pub fn read<P: AsRef<str>>(&self, path: P) -> Result<Vec<u8>> {
-        let path = path.as_ref();
-        debug!("Reading {}", path);
-        self.inner.read_file(path)
+    #[cfg(not(feature = "no-fs"))]
+    pub fn open_with_timeout<P: AsRef<Path>>(
+        path: P,
+        timeout: std::time::Duration,
+    ) -> Result<Self> {
+        info!("Opening cartridge at {:?}", path.as_ref());
+        let inner = CoreCartridge::open_with_timeout(path, timeout)?;
+        Ok(Cartridge { inner, vfs_name: None })
     }
 
-    /// Delete a file from the archive
+    /// Open a Cartridge archive that is encrypted at rest
+    ///
+    /// Plain [`Cartridge::open`] on an encrypted archive fails with
+    /// [`CartridgeError::EncryptionRequired`]; a wrong passphrase fails with
+    /// [`CartridgeError::InvalidPassphrase`].
     ///
     /// # Examples
     ///
     /// ```rust,no_run
-    /// # use cartridge_rs::Cartridge;
-    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
-    /// cart.delete("old_file.txt")?;
+    /// use cartridge_rs::Cartridge;
+    ///
+    /// let cart = Cartridge::open_encrypted("secrets.cart", "correct horse battery staple")?;
     /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
-    pub fn delete<P: AsRef<str>>(&mut self, path: P) -> Result<()> {
-        let path = path.as_ref();
-        debug!("Deleting {}", path);
-        self.inner.delete_file(path)
+    #[cfg(not(feature = "no-fs"))]
+    pub fn open_encrypted<P: AsRef<Path>>(path: P, passphrase: &str) -> Result<Self> {
+        info!("Opening encrypted cartridge at {:?}", path.as_ref());
+        let inner = CoreCartridge::open_encrypted(path, passphrase)?;
+        Ok(Cartridge { inner, vfs_name: None })
     }
 
-    /// List all entries in a directory
+    /// Open an existing Cartridge archive without write access
+    ///
+    /// Safe to hand to code that should only read: `write`, `delete`,
+    /// `create_dir`, `update_manifest`, snapshot restore and every other
+    /// mutation fail with [`CartridgeError::ReadOnly`]. Only read permission
+    /// on the file is needed. Readers take no lock, so they can open a file
+    /// a writer has open; call [`refresh`](Self::refresh) to see what the
+    /// writer flushed after the reader opened.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
-    /// # use cartridge_rs::Cartridge;
-    /// # let cart = Cartridge::create("my-data", "My Data")?;
-    /// let files = cart.list("documents")?;
-    /// for file in files {
-    ///     println!("Found: {}", file);
-    /// }
+    /// use cartridge_rs::Cartridge;
+    ///
+    /// let cart = Cartridge::open_read_only("existing.cart")?;
+    /// let data = cart.read("/docs/readme.txt")?;
     /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
-    pub fn list<P: AsRef<str>>(&self, path: P) -> Result<Vec<String>> {
-        let path = path.as_ref();
-        debug!("Listing directory {}", path);
-        self.inner.list_dir(path)
+    #[cfg(not(feature = "no-fs"))]
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> Result<Self> {
+        info!("Opening cartridge read-only at {:?}", path.as_ref());
+        let inner = CoreCartridge::open_read_only(path)?;
+        Ok(Cartridge { inner, vfs_name: None })
     }
 
-    /// List all entries with rich metadata under a given prefix
+    /// Open a damaged archive to salvage its files
     ///
-    /// Returns Entry objects with parsed path components, file metadata,
-    /// and inferred directory information. This eliminates the need to
-    /// manually parse paths and build hierarchies.
+    /// Works when [`open`](Self::open) fails because the catalog or
+    /// allocator can't be read, and never writes to the file. See
+    /// [`RecoverySession`] for what can be recovered.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
-    /// # use cartridge_rs::Cartridge;
-    /// # let cart = Cartridge::create("my-data", "My Data")?;
-    /// let entries = cart.list_entries("documents")?;
-    /// for entry in entries {
-    ///     if entry.is_dir {
-    ///         println!("📁 {}", entry.name);
-    ///     } else {
-    ///         println!("📄 {} ({} bytes)", entry.name, entry.size.unwrap_or(0));
-    ///     }
-    /// }
+    /// use cartridge_rs::Cartridge;
+    ///
+    /// let session = Cartridge::open_recovery("damaged.cart")?;
+    /// let mut rescued = Cartridge::create_at("rescued", "rescued", "Rescued")?;
+    /// let report = session.salvage_to(rescued.inner_mut())?;
+    /// println!("salvaged {} files", report.files_salvaged);
     /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
-    pub fn list_entries<P: AsRef<str>>(&self, prefix: P) -> Result<Vec<Entry>> {
-        let prefix = prefix.as_ref();
-        debug!("Listing entries under prefix {}", prefix);
-        let paths = self.inner.list_dir(prefix)?;
-        paths_to_entries(&self.inner, &paths, prefix)
+    #[cfg(not(feature = "no-fs"))]
+    pub fn open_recovery<P: AsRef<Path>>(path: P) -> Result<RecoverySession> {
+        info!("Opening cartridge for recovery at {:?}", path.as_ref());
+        CoreCartridge::open_recovery(path)
     }
 
-    /// List immediate children of a directory
+    /// Open an existing Cartridge archive whose paths must compare as
+    /// `expected`
     ///
-    /// Like `list_entries()` but filters to only direct children,
-    /// providing a traditional directory-style listing.
+    /// Fails with [`CartridgeError::CaseSensitivityMismatch`] rather than
+    /// opening a case-sensitive archive as case-insensitive or the reverse.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
-    /// # use cartridge_rs::Cartridge;
-    /// # let cart = Cartridge::create("my-data", "My Data")?;
-    /// // List only immediate children of "documents"
-    /// let children = cart.list_children("documents")?;
-    /// for child in children {
-    ///     println!("{} - parent: {}", child.name, child.parent);
-    /// }
+    /// use cartridge_rs::{CaseSensitivity, Cartridge};
+    ///
+    /// let cart = Cartridge::open_with_case("uploads.cart", CaseSensitivity::Insensitive)?;
+    /// let logo = cart.read("images/logo.png")?; // stored as images/Logo.PNG
     /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
-    pub fn list_children<P: AsRef<str>>(&self, parent: P) -> Result<Vec<Entry>> {
-        let parent = parent.as_ref();
-        debug!("Listing immediate children of {}", parent);
-        let all_entries = self.list_entries(parent)?;
-
-        // Filter to immediate children only
-        Ok(all_entries
-            .into_iter()
-            .filter(|e| e.parent == parent)
-            .collect())
+    #[cfg(not(feature = "no-fs"))]
+    pub fn open_with_case<P: AsRef<Path>>(path: P, expected: CaseSensitivity) -> Result<Self> {
+        info!("Opening cartridge at {:?} expecting {:?} paths", path.as_ref(), expected);
+        let inner = CoreCartridge::open_with_case(path, expected)?;
+        Ok(Cartridge { inner, vfs_name: None })
     }
 
-    /// Check if a path is a directory
+    /// Create an archive that lives only in memory
     ///
-    /// Returns true if the path has children (i.e., is a directory),
-    /// false otherwise.
+    /// Nothing touches the filesystem; [`to_bytes`](Self::to_bytes) returns
+    /// the archive as the bytes of a `.cart` file, e.g. to send over the
+    /// network or store in a browser.
     ///
     /// # Examples
     ///
-    /// ```rust,no_run
-    /// # use cartridge_rs::Cartridge;
-    /// # let cart = Cartridge::create("my-data", "My Data")?;
-    /// if cart.is_dir("documents")? {
-    ///     println!("documents is a directory");
-    /// }
+    /// ```rust
+    /// use cartridge_rs::Cartridge;
+    ///
+    /// let mut cart = Cartridge::in_memory("scratch", "Scratch")?;
+    /// cart.write("notes.txt", b"hello")?;
+    /// let bytes = cart.to_bytes()?;
+    ///
+    /// let copy = Cartridge::from_bytes(&bytes)?;
+    /// assert_eq!(copy.read("notes.txt")?, b"hello");
     /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
-    pub fn is_dir<P: AsRef<str>>(&self, path: P) -> Result<bool> {
-        let path = path.as_ref();
-        debug!("Checking if {} is a directory", path);
+    pub fn in_memory(slug: &str, title: &str) -> Result<Self> {
+        let inner = CoreCartridge::in_memory(slug, title)?;
+        Ok(Cartridge { inner, vfs_name: None })
+    }
+
+    /// Load an archive from the bytes of a `.cart` file
+    ///
+    /// The archive stays in memory; see [`in_memory`](Self::in_memory).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let inner = CoreCartridge::from_bytes(bytes)?;
+        Ok(Cartridge { inner, vfs_name: None })
+    }
 
-        // A path is a directory if it has children
-        let prefix = format!("{}/", path);
+    /// Serialize the archive to the bytes of a `.cart` file
+    ///
+    /// Pending changes are flushed first. Works for disk-backed archives
+    /// too, returning the file's contents.
+    pub fn to_bytes(&mut self) -> Result<Vec<u8>> {
+        self.inner.to_bytes()
+    }
 
-        let paths = self.inner.list_dir(&prefix)?;
-        Ok(!paths.is_empty())
+    /// Whether the archive was opened with [`Cartridge::open_read_only`],
+    /// or was written by a newer minor format version
+    pub fn is_read_only(&self) -> bool {
+        self.inner.is_read_only()
     }
 
-    /// Check if a file or directory exists
+    /// Write data to a file in the archive
+    ///
+    /// Creates the file if it doesn't exist, updates it if it does.
+    /// Automatically creates parent directories.
+    ///
+    /// Files without a content type get one inferred from their extension
+    /// (`report.txt` → `text/plain`). A type set earlier, e.g. by
+    /// [`write_with_type`](Self::write_with_type), is kept on overwrite.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// # use cartridge_rs::Cartridge;
-    /// # let cart = Cartridge::create("my-data", "My Data")?;
-    /// if cart.exists("config.json")? {
-    ///     println!("Config file found!");
-    /// }
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// cart.write("documents/report.txt", b"Hello, World!")?;
     /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
-    pub fn exists<P: AsRef<str>>(&self, path: P) -> Result<bool> {
-        self.inner.exists(path.as_ref())
+    pub fn write<P: AsRef<str>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+        let path = path.as_ref();
+        debug!("Writing {} bytes to {}", content.len(), path);
+        self.write_typed(path, content, false)
     }
 
-    /// Get metadata for a file or directory
+    /// Write a file so that it holds either all of its old content or all
+    /// of the new, whatever fails part way
+    ///
+    /// Like [`write`](Self::write), but an existing file's new content is
+    /// stored in fresh blocks before the catalog is switched over to it, and
+    /// the old blocks are freed last. Running out of space leaves the old
+    /// content readable instead of a torn file. Needs room for both copies
+    /// while it runs.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// # use cartridge_rs::Cartridge;
-    /// # let cart = Cartridge::create("my-data", "My Data")?;
-    /// let meta = cart.metadata("file.txt")?;
-    /// println!("Size: {} bytes", meta.size);
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// cart.write_atomic("config/settings.json", br#"{"theme":"dark"}"#)?;
     /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
-    pub fn metadata<P: AsRef<str>>(&self, path: P) -> Result<FileMetadata> {
-        self.inner.metadata(path.as_ref())
+    pub fn write_atomic<P: AsRef<str>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+        let path = path.as_ref();
+        debug!("Atomically writing {} bytes to {}", content.len(), path);
+        self.write_typed(path, content, true)
     }
 
-    /// Create a directory
-    ///
-    /// Automatically creates parent directories if needed.
+    /// Body of [`write`](Self::write) and [`write_atomic`](Self::write_atomic):
+    /// store the content, then infer a content type if the file has none
+    fn write_typed(&mut self, path: &str, content: &[u8], atomic: bool) -> Result<()> {
+        let has_type = self.put(path, content, atomic)?;
+        if !has_type {
+            if let Some(mime) = content_type::from_path(path) {
+                self.inner.set_content_type(path, Some(mime.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write data to a file with an explicit content (MIME) type
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// # use cartridge_rs::Cartridge;
     /// # let mut cart = Cartridge::create("my-data", "My Data")?;
-    /// cart.create_dir("documents/reports/2025")?;
+    /// cart.write_with_type("events/latest", b"{}", "application/json")?;
+    /// let meta = cart.metadata("events/latest")?;
+    /// assert_eq!(meta.content_type.as_deref(), Some("application/json"));
     /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
-    pub fn create_dir<P: AsRef<str>>(&mut self, path: P) -> Result<()> {
-        self.inner.create_dir(path.as_ref())
+    pub fn write_with_type<P: AsRef<str>>(
+        &mut self,
+        path: P,
+        content: &[u8],
+        content_type: &str,
+    ) -> Result<()> {
+        let path = path.as_ref();
+        debug!("Writing {} bytes to {} as {}", content.len(), path, content_type);
+
+        self.put(path, content, false)?;
+        self.inner
+            .set_content_type(path, Some(content_type.to_string()))
     }
 
-    /// Flush all pending changes to disk
+    /// Serialize `value` as compact JSON and write it to `path`
+    ///
+    /// The file's content type is set to `application/json`; see
+    /// [`write_json_pretty`](Self::write_json_pretty) for indented output.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// # use cartridge_rs::Cartridge;
+    /// # use std::collections::HashMap;
     /// # let mut cart = Cartridge::create("my-data", "My Data")?;
-    /// cart.write("file.txt", b"data")?;
-    /// cart.flush()?;  // Ensure changes are persisted
+    /// let settings = HashMap::from([("theme", "dark")]);
+    /// cart.write_json("config/settings.json", &settings)?;
+    ///
+    /// let back: HashMap<String, String> = cart.read_json("config/settings.json")?;
+    /// assert_eq!(back["theme"], "dark");
     /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
-    pub fn flush(&mut self) -> Result<()> {
-        debug!("Flushing cartridge to disk");
-        self.inner.flush()
+    pub fn write_json<P: AsRef<str>, T: Serialize + ?Sized>(
+        &mut self,
+        path: P,
+        value: &T,
+    ) -> Result<()> {
+        let content = serde_json::to_vec(value)?;
+        self.write_with_type(path, &content, content_type::JSON)
     }
 
-    /// Get the container slug
+    /// Like [`write_json`](Self::write_json), but indented for people to read
+    pub fn write_json_pretty<P: AsRef<str>, T: Serialize + ?Sized>(
+        &mut self,
+        path: P,
+        value: &T,
+    ) -> Result<()> {
+        let content = serde_json::to_vec_pretty(value)?;
+        self.write_with_type(path, &content, content_type::JSON)
+    }
+
+    /// Read a file and deserialize it from JSON
+    ///
+    /// Fails with [`CartridgeError::InvalidJson`], naming the file, if the
+    /// content doesn't parse as a `T`.
+    pub fn read_json<P: AsRef<str>, T: DeserializeOwned>(&self, path: P) -> Result<T> {
+        let path = path.as_ref();
+        let content = self.read(path)?;
+        serde_json::from_slice(&content).map_err(|source| CartridgeError::InvalidJson {
+            path: path.to_string(),
+            source,
+        })
+    }
+
+    /// Write `text` to `path` as UTF-8
+    ///
+    /// The file's content type is set to `text/plain; charset=utf-8`.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// # use cartridge_rs::Cartridge;
-    /// # let cart = Cartridge::create("my-data", "My Data")?;
-    /// assert_eq!(cart.slug()?, "my-data");
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// cart.write_string("notes/todo", "buy milk")?;
+    /// assert_eq!(cart.read_string("notes/todo")?, "buy milk");
     /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
-    pub fn slug(&self) -> Result<String> {
+    pub fn write_string<P: AsRef<str>>(&mut self, path: P, text: &str) -> Result<()> {
+        self.write_with_type(path, text.as_bytes(), content_type::TEXT_UTF8)
+    }
+
+    /// Read a file as UTF-8 text
+    ///
+    /// Fails with [`CartridgeError::InvalidUtf8`] rather than replacing
+    /// invalid bytes.
+    pub fn read_string<P: AsRef<str>>(&self, path: P) -> Result<String> {
+        let path = path.as_ref();
+        let content = self.read(path)?;
+        String::from_utf8(content).map_err(|e| CartridgeError::InvalidUtf8 {
+            path: path.to_string(),
+            valid_up_to: e.utf8_error().valid_up_to(),
+        })
+    }
+
+    /// Create or replace a file; returns whether it already has a content type
+    fn put(&mut self, path: &str, content: &[u8], atomic: bool) -> Result<bool> {
+        // Check if file exists, create or update accordingly
+        if self.inner.exists(path)? {
+            if atomic {
+                self.inner.write_file_atomic(path, content)?;
+            } else {
+                self.inner.write_file(path, content)?;
+            }
+            Ok(self.inner.metadata(path)?.content_type.is_some())
+        } else {
+            self.inner.create_file(path, content)?;
+            Ok(false)
+        }
+    }
+
+    /// Overwrite part of an existing file, starting at byte `offset`
+    ///
+    /// Only the pages the range touches are rewritten. Writing past the end
+    /// grows the file; a gap between the old end and `offset` reads as zeros,
+    /// and whole pages of it are holes that take no space.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// cart.write("notes.txt", b"hello world")?;
+    /// cart.write_at("notes.txt", 6, b"there")?;
+    /// assert_eq!(cart.read("notes.txt")?, b"hello there");
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn write_at<P: AsRef<str>>(&mut self, path: P, offset: u64, data: &[u8]) -> Result<()> {
+        let path = path.as_ref();
+        debug!("Writing {} bytes to {} at offset {}", data.len(), path, offset);
+        self.inner.write_at(path, offset, data)
+    }
+
+    /// Shrink or grow an existing file to `len` bytes
+    ///
+    /// Shrinking frees the blocks past the new end; growing pads with zeros,
+    /// leaving whole pages of them as holes that take no space.
+    pub fn truncate<P: AsRef<str>>(&mut self, path: P, len: u64) -> Result<()> {
+        let path = path.as_ref();
+        debug!("Truncating {} to {} bytes", path, len);
+        self.inner.truncate(path, len)
+    }
+
+    /// Write a file, storing every page of it that is all zeros as a hole
+    ///
+    /// Creates the file if it doesn't exist, replaces it if it does. Holes
+    /// read back as zeros but take no space, which suits preallocated
+    /// database and disk image files. With encryption enabled the file is
+    /// stored whole, like any other write.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// let mut image = vec![0u8; 64 * 1024 * 1024];
+    /// image[..4].copy_from_slice(b"boot");
+    /// cart.write_sparse("vm/disk.img", &image)?;
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn write_sparse<P: AsRef<str>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+        let path = path.as_ref();
+        debug!("Writing {} bytes to {} sparsely", content.len(), path);
+        self.inner.write_sparse(path, content)
+    }
+
+    /// Copy a file without copying its content
+    ///
+    /// `dst` shares the blocks of `src` until either is written to, when
+    /// only the changed pages are copied. Deleting one leaves the other
+    /// intact. Fails if `dst` already exists.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// cart.write("template.bin", &[0u8; 1 << 20])?;
+    /// cart.clone_file("template.bin", "copy.bin")?;
+    /// cart.write_at("copy.bin", 0, b"changed")?;
+    /// assert_eq!(cart.read("template.bin")?[..7], [0u8; 7]);
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn clone_file<P: AsRef<str>, Q: AsRef<str>>(&mut self, src: P, dst: Q) -> Result<()> {
+        let (src, dst) = (src.as_ref(), dst.as_ref());
+        debug!("Cloning {} to {}", src, dst);
+        self.inner.clone_file(src, dst)
+    }
+
+    /// Read data from a file in the archive
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::create("my-data", "My Data")?;
+    /// let content = cart.read("documents/report.txt")?;
+    /// println!("Content: {}", String::from_utf8_lossy(&content));
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn read<P: AsRef<str>>(&self, path: P) -> Result<Vec<u8>> {
+        let path = path.as_ref();
+        debug!("Reading {}", path);
+        self.inner.read_file(path)
+    }
+
+    /// Read up to `len` bytes of a file starting at `offset`
+    ///
+    /// Only the pages the range covers are loaded. The result is short when
+    /// the range runs past the end of the file.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// cart.write("notes.txt", b"hello world")?;
+    /// assert_eq!(cart.read_range("notes.txt", 6, 5)?, b"world");
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn read_range<P: AsRef<str>>(&self, path: P, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let path = path.as_ref();
+        debug!("Reading {} bytes of {} at offset {}", len, path, offset);
+        self.inner.read_range(path, offset, len)
+    }
+
+    /// Open a file for streaming reads
+    ///
+    /// The returned [`FileReader`] implements [`Read`] and
+    /// [`Seek`](std::io::Seek) and loads one page at a time.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::create("my-data", "My Data")?;
+    /// let mut reader = cart.open_reader("videos/intro.mp4")?;
+    /// std::io::copy(&mut reader, &mut std::io::sink())?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn open_reader<P: AsRef<str>>(&self, path: P) -> Result<FileReader<'_>> {
+        let path = path.as_ref();
+        debug!("Opening reader for {}", path);
+        self.inner.open_reader(path)
+    }
+
+    /// Read a file page by page without copying it out of the page cache
+    ///
+    /// `visitor` gets each page's bytes in order, trimmed to the file's
+    /// length on the last page. See [`pin_page`](Self::pin_page) for
+    /// borrowing a single page.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::create("my-data", "My Data")?;
+    /// let mut hasher = 0u64;
+    /// cart.read_file_zero_copy("videos/intro.mp4", |page| {
+    ///     hasher = page.iter().fold(hasher, |h, &b| h.rotate_left(5) ^ b as u64);
+    /// })?;
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn read_file_zero_copy<P: AsRef<str>>(
+        &self,
+        path: P,
+        visitor: impl FnMut(&[u8]),
+    ) -> Result<()> {
+        let path = path.as_ref();
+        debug!("Reading {} without copying", path);
+        self.inner.read_file_zero_copy(path, visitor)
+    }
+
+    /// Borrow a content page from the page cache
+    ///
+    /// The [`PageGuard`] derefs to the page's bytes and keeps them alive
+    /// without holding a lock; it borrows the cartridge, so writes through
+    /// this handle wait until it is dropped. Refused with
+    /// [`CartridgeError::AccessDenied`] while an IAM policy is set.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::create("my-data", "My Data")?;
+    /// let metadata = cart.metadata("data.bin")?;
+    /// let first = metadata.data_blocks().next().expect("file has content pages");
+    /// let page = cart.pin_page(first)?;
+    /// println!("page {} starts with {:?}", page.page_id(), &page[..4]);
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn pin_page(&self, page_id: u64) -> Result<PageGuard<'_>> {
+        self.inner.pin_page(page_id)
+    }
+
+    /// Write a file from a reader, creating it or replacing its content
+    ///
+    /// The source is consumed [`STREAM_CHUNK_SIZE`] bytes at a time, so it
+    /// never has to fit in memory. Returns the number of bytes written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// let mut source = std::fs::File::open("intro.mp4")?;
+    /// cart.write_stream("videos/intro.mp4", &mut source)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn write_stream<P: AsRef<str>>(&mut self, path: P, reader: &mut dyn Read) -> Result<u64> {
+        let path = path.as_ref();
+        debug!("Streaming into {}", path);
+        let written = self.inner.write_stream(path, reader)?;
+        if self.inner.metadata(path)?.content_type.is_none() {
+            if let Some(mime) = content_type::from_path(path) {
+                self.inner.set_content_type(path, Some(mime.to_string()))?;
+            }
+        }
+        Ok(written)
+    }
+
+    /// Delete a file from the archive
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// cart.delete("old_file.txt")?;
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn delete<P: AsRef<str>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        debug!("Deleting {}", path);
+        self.inner.delete_file(path)
+    }
+
+    /// Create a symbolic link at `link` pointing to `target`
+    ///
+    /// [`read`](Self::read) and [`metadata`](Self::metadata) follow links;
+    /// [`delete`](Self::delete) removes the link itself. The target needn't
+    /// exist yet. Relative targets resolve against the link's directory.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// cart.write("releases/v2.json", b"{}")?;
+    /// cart.symlink("v2.json", "releases/latest")?;
+    /// assert_eq!(cart.read("releases/latest")?, b"{}");
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn symlink<T: AsRef<str>, L: AsRef<str>>(&mut self, target: T, link: L) -> Result<()> {
+        let (target, link) = (target.as_ref(), link.as_ref());
+        debug!("Linking {} -> {}", link, target);
+        self.inner.symlink(target, link)
+    }
+
+    /// Get the target of a symbolic link, as stored
+    pub fn read_link<P: AsRef<str>>(&self, path: P) -> Result<String> {
+        self.inner.read_link(path.as_ref())
+    }
+
+    /// Read a file without following symlinks; fails if `path` is a link
+    pub fn read_nofollow<P: AsRef<str>>(&self, path: P) -> Result<Vec<u8>> {
+        self.inner.read_file_nofollow(path.as_ref())
+    }
+
+    /// List all entries in a directory
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::create("my-data", "My Data")?;
+    /// let files = cart.list("documents")?;
+    /// for file in files {
+    ///     println!("Found: {}", file);
+    /// }
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn list<P: AsRef<str>>(&self, path: P) -> Result<Vec<String>> {
+        let path = path.as_ref();
+        debug!("Listing directory {}", path);
+        self.inner.list_dir(path)
+    }
+
+    /// List all entries with rich metadata under a given prefix
+    ///
+    /// Returns Entry objects with parsed path components, file metadata,
+    /// and inferred directory information. This eliminates the need to
+    /// manually parse paths and build hierarchies.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::create("my-data", "My Data")?;
+    /// let entries = cart.list_entries("documents")?;
+    /// for entry in entries {
+    ///     if entry.is_dir {
+    ///         println!("📁 {}", entry.name);
+    ///     } else {
+    ///         println!("📄 {} ({} bytes)", entry.name, entry.size.unwrap_or(0));
+    ///     }
+    /// }
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn list_entries<P: AsRef<str>>(&self, prefix: P) -> Result<Vec<Entry>> {
+        let prefix = prefix.as_ref();
+        debug!("Listing entries under prefix {}", prefix);
+        let mut listing = self.inner.list_dir_with_metadata(prefix)?;
+        if !listing.is_empty() {
+            listing.extend(self.enclosing_dirs(prefix));
+        }
+        Ok(listing_to_entries(&listing, self.inner.page_size()))
+    }
+
+    /// List entries like [`list_entries`](Self::list_entries), with totals
+    /// for each directory
+    ///
+    /// A directory's `size` is the combined size of the files below it, at
+    /// any depth, and its `modified` is the latest modification of anything
+    /// below it (or of the directory itself, if later). Totals only count
+    /// what the listing covers, so directories enclosing the prefix are
+    /// summed over the prefix alone. Computed in one pass over the listing.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::create("my-data", "My Data")?;
+    /// for entry in cart.list_entries_with_sizes("documents")? {
+    ///     println!("{} ({} bytes)", entry.path, entry.size.unwrap_or(0));
+    /// }
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn list_entries_with_sizes<P: AsRef<str>>(&self, prefix: P) -> Result<Vec<Entry>> {
+        let mut entries = self.list_entries(prefix)?;
+
+        // Directory path -> (total file size, latest modification below it)
+        let mut totals: HashMap<String, (u64, Option<u64>)> = HashMap::new();
+        for entry in &entries {
+            let size = match entry.file_type {
+                FileType::File => entry.size.unwrap_or(0),
+                _ => 0,
+            };
+            for dir in ancestors_of(&entry.path) {
+                let total = totals.entry(dir.to_string()).or_default();
+                total.0 += size;
+                total.1 = total.1.max(entry.modified);
+            }
+        }
+
+        for entry in entries.iter_mut().filter(|e| e.is_dir) {
+            let (size, modified) = totals.get(&entry.path).copied().unwrap_or_default();
+            entry.size = Some(size);
+            entry.modified = entry.modified.max(modified);
+        }
+        Ok(entries)
+    }
+
+    /// Lazily iterate the entries under a prefix
+    ///
+    /// Yields the same entries as [`list_entries`](Self::list_entries), but
+    /// one at a time straight from the catalog, so `.take()` and early
+    /// returns never touch the rest. Order differs: files come in sorted
+    /// path order, and each directory right before its first child.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::create("my-data", "My Data")?;
+    /// // Third page of 100 entries
+    /// for entry in cart.walk("logs").skip(200).take(100) {
+    ///     println!("{}", entry?.path);
+    /// }
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn walk<P: AsRef<str>>(&self, prefix: P) -> impl Iterator<Item = Result<Entry>> + '_ {
+        let prefix = prefix.as_ref();
+        Walk {
+            catalog: self.inner.walk_dir(prefix),
+            open_dirs: Vec::new(),
+            pending: VecDeque::new(),
+            enclosing: self.enclosing_dirs(prefix).into_iter().collect(),
+            page_size: self.inner.page_size(),
+        }
+    }
+
+    /// Catalog entries of `prefix` and its ancestors that are directories
+    ///
+    /// A listing only covers the paths below its prefix, so without these the
+    /// directories it is nested in would be reported as inferred even when
+    /// they were made with [`create_dir`](Self::create_dir).
+    fn enclosing_dirs(&self, prefix: &str) -> Vec<(String, FileMetadata)> {
+        let Ok(prefix) = crate::validation::normalize_path(prefix) else {
+            return Vec::new();
+        };
+        let mut dirs = ancestors_of(&prefix);
+        if prefix != "/" {
+            dirs.push(&prefix);
+        }
+        dirs.into_iter()
+            .filter_map(|dir| match self.inner.catalog().get(dir) {
+                Ok(Some(metadata)) if metadata.is_directory() => Some((dir.to_string(), metadata)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// List immediate children of a directory
+    ///
+    /// Like `list_entries()` but filters to only direct children,
+    /// providing a traditional directory-style listing.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::create("my-data", "My Data")?;
+    /// // List only immediate children of "documents"
+    /// let children = cart.list_children("documents")?;
+    /// for child in children {
+    ///     println!("{} - parent: {}", child.name, child.parent);
+    /// }
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn list_children<P: AsRef<str>>(&self, parent: P) -> Result<Vec<Entry>> {
+        let parent = crate::validation::normalize_path(parent.as_ref())?;
+        debug!("Listing immediate children of {}", parent);
+        if self.path_type(&parent)? != Some(FileType::Directory) {
+            return Ok(Vec::new());
+        }
+        let all_entries = self.list_entries(&parent)?;
+
+        // Filter to immediate children only
+        let insensitive = self.case_sensitivity() == CaseSensitivity::Insensitive;
+        Ok(all_entries
+            .into_iter()
+            .filter(|e| {
+                if insensitive {
+                    e.parent.to_lowercase() == parent.to_lowercase()
+                } else {
+                    e.parent == parent
+                }
+            })
+            .collect())
+    }
+
+    /// List the internal `.cartridge/` files that other listings leave out
+    ///
+    /// Meant for backup and inspection tooling. Paths keep their
+    /// `/.cartridge/` prefix; change these files through
+    /// [`system`](Self::system).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::create("my-data", "My Data")?;
+    /// for entry in cart.list_system_entries()? {
+    ///     println!("{} ({} bytes)", entry.path, entry.size.unwrap_or(0));
+    /// }
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn list_system_entries(&self) -> Result<Vec<Entry>> {
+        let page_size = self.inner.page_size();
+        Ok(self
+            .inner
+            .list_system_entries()?
+            .iter()
+            .map(|(path, metadata)| Entry::from_catalog(path, metadata, page_size))
+            .collect())
+    }
+
+    /// Change the internal files under `.cartridge/`
+    ///
+    /// [`write`](Self::write), [`delete`](Self::delete) and the other
+    /// mutating methods refuse those paths with
+    /// [`CartridgeError::ProtectedPath`], since losing the manifest leaves
+    /// the cartridge without its slug and title.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// let manifest = cart.read(".cartridge/manifest.json")?;
+    /// cart.system().write("/.cartridge/manifest.json", &manifest)?;
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn system(&mut self) -> SystemFiles<'_> {
+        self.inner.system()
+    }
+
+    /// Find files and directories matching a glob pattern
+    ///
+    /// `*` matches within one path segment and `**` across any number of
+    /// them. Matching is case-sensitive; see [`find_with_case`](Self::find_with_case).
+    /// Only paths with their own catalog entry are returned, so parent
+    /// directories are not inferred the way [`list_entries`](Self::list_entries)
+    /// does.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::create("my-data", "My Data")?;
+    /// for entry in cart.find("docs/**/*.md")? {
+    ///     println!("{} ({} bytes)", entry.path, entry.size.unwrap_or(0));
+    /// }
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn find(&self, pattern: &str) -> Result<Vec<Entry>> {
+        self.find_with_case(pattern, CaseSensitivity::Exact)
+    }
+
+    /// Find entries matching a glob pattern with explicit case handling
+    pub fn find_with_case(&self, pattern: &str, case: CaseSensitivity) -> Result<Vec<Entry>> {
+        debug!("Finding entries matching {}", pattern);
+        let found = self.inner.find(pattern, case)?;
+        Ok(found
+            .iter()
+            .map(|(path, metadata)| Entry::from_catalog(path, metadata, self.inner.page_size()))
+            .collect())
+    }
+
+    /// Find entries whose path matches a regular expression
+    ///
+    /// The expression may match anywhere in the path; anchor it with `^` and
+    /// `$` for whole-path matches. Matching is case-sensitive unless the
+    /// expression itself says otherwise (e.g. `(?i)`).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::create("my-data", "My Data")?;
+    /// let logs = cart.find_regex(r"^logs/\d{4}-\d{2}-\d{2}\.log$")?;
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn find_regex(&self, pattern: &str) -> Result<Vec<Entry>> {
+        debug!("Finding entries matching regex {}", pattern);
+        let found = self.inner.find_regex(pattern, CaseSensitivity::Exact)?;
+        Ok(found
+            .iter()
+            .map(|(path, metadata)| Entry::from_catalog(path, metadata, self.inner.page_size()))
+            .collect())
+    }
+
+    /// Check if a path is a directory
+    ///
+    /// True for directories made with [`create_dir`](Self::create_dir),
+    /// empty or not, and for paths that have children; false for files,
+    /// symlinks and missing paths. Never lists the directory.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::create("my-data", "My Data")?;
+    /// if cart.is_dir("documents")? {
+    ///     println!("documents is a directory");
+    /// }
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn is_dir<P: AsRef<str>>(&self, path: P) -> Result<bool> {
+        let path = path.as_ref();
+        debug!("Checking if {} is a directory", path);
+        Ok(self.path_type(path)? == Some(FileType::Directory))
+    }
+
+    /// What a path is: file, directory or symlink, or `None` if missing
+    ///
+    /// Symlinks are not followed. A path with children but no entry of its
+    /// own is a directory, as in listings.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::{Cartridge, FileType};
+    /// # let cart = Cartridge::create("my-data", "My Data")?;
+    /// match cart.path_type("documents")? {
+    ///     Some(FileType::Directory) => println!("directory"),
+    ///     Some(_) => println!("file or symlink"),
+    ///     None => println!("missing"),
+    /// }
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn path_type<P: AsRef<str>>(&self, path: P) -> Result<Option<FileType>> {
+        self.inner.path_type(path.as_ref())
+    }
+
+    /// Check if a file or directory exists
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::create("my-data", "My Data")?;
+    /// if cart.exists("config.json")? {
+    ///     println!("Config file found!");
+    /// }
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn exists<P: AsRef<str>>(&self, path: P) -> Result<bool> {
+        self.inner.exists(path.as_ref())
+    }
+
+    /// Get metadata for a file or directory
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::create("my-data", "My Data")?;
+    /// let meta = cart.metadata("file.txt")?;
+    /// println!("Size: {} bytes", meta.size);
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn metadata<P: AsRef<str>>(&self, path: P) -> Result<FileMetadata> {
+        self.inner.metadata(path.as_ref())
+    }
+
+    /// Get metadata for a path without following symlinks
+    ///
+    /// Works on dangling links; the link's target is available from
+    /// [`FileMetadata::symlink_target`].
+    pub fn metadata_nofollow<P: AsRef<str>>(&self, path: P) -> Result<FileMetadata> {
+        self.inner.metadata_nofollow(path.as_ref())
+    }
+
+    /// Create a directory
+    ///
+    /// Automatically creates parent directories if needed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// cart.create_dir("documents/reports/2025")?;
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn create_dir<P: AsRef<str>>(&mut self, path: P) -> Result<()> {
+        self.inner.create_dir(path.as_ref())
+    }
+
+    /// Import a host directory tree into the archive
+    ///
+    /// Files keep their paths relative to `host_path`, placed under
+    /// `dest_prefix`. The container is sized once for the whole import.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::{Cartridge, ImportOptions};
+    /// # use std::path::Path;
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// let options = ImportOptions {
+    ///     include: Some("**/*.json".to_string()),
+    ///     ..Default::default()
+    /// };
+    /// let report = cart.import_dir(Path::new("./fixtures"), "fixtures", &options)?;
+    /// println!("imported {} files ({} bytes)", report.files_imported, report.bytes_written);
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn import_dir<P: AsRef<Path>>(
+        &mut self,
+        host_path: P,
+        dest_prefix: &str,
+        options: &ImportOptions,
+    ) -> Result<ImportReport> {
+        debug!("Importing {} into {}", host_path.as_ref().display(), dest_prefix);
+        self.inner.import_dir(host_path.as_ref(), dest_prefix, options)
+    }
+
+    /// Extract every file under `prefix` into a host directory
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::open("my-data.cart")?;
+    /// let report = cart.export_dir("fixtures", "./out")?;
+    /// println!("exported {} files", report.files_exported);
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn export_dir<P: AsRef<Path>>(&self, prefix: &str, host_path: P) -> Result<ExportReport> {
+        debug!("Exporting {} to {}", prefix, host_path.as_ref().display());
+        self.inner.export_dir(prefix, host_path.as_ref())
+    }
+
+    /// Stream every file under `prefix` into a tar archive
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::open("my-data.cart")?;
+    /// let tarball = std::fs::File::create("fixtures.tar")?;
+    /// let report = cart.export_tar(tarball, "fixtures")?;
+    /// println!("archived {} files", report.files_exported);
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn export_tar<W: std::io::Write>(&self, writer: W, prefix: &str) -> Result<ExportReport> {
+        debug!("Exporting {} as tar", prefix);
+        self.inner.export_tar(writer, prefix)
+    }
+
+    /// Ingest a tar archive, placing its files under `dest_prefix`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// let tarball = std::fs::File::open("fixtures.tar")?;
+    /// let report = cart.import_tar(tarball, "fixtures")?;
+    /// println!("imported {} files, skipped {}", report.files_imported, report.skipped.len());
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn import_tar<R: std::io::Read>(
+        &mut self,
+        reader: R,
+        dest_prefix: &str,
+    ) -> Result<ImportReport> {
+        debug!("Importing tar into {}", dest_prefix);
+        self.inner.import_tar(reader, dest_prefix)
+    }
+
+    /// Create a cartridge holding the contents of a zip archive
+    ///
+    /// Creates `{slug}.cart` like [`create`](Self::create), then imports
+    /// the archive at the root with [`import_zip`](Self::import_zip) and
+    /// flushes. Entries `import_zip` would skip are left out; call it
+    /// directly for the report.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// let archive = std::fs::File::open("dataset.zip")?;
+    /// let cart = Cartridge::from_zip(archive, "dataset", "Dataset")?;
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    #[cfg(all(feature = "zip", not(feature = "no-fs")))]
+    pub fn from_zip<R: std::io::Read + std::io::Seek>(
+        reader: R,
+        slug: &str,
+        title: &str,
+    ) -> Result<Self> {
+        let mut cart = Self::create(slug, title)?;
+        cart.import_zip(reader, "")?;
+        cart.flush()?;
+        Ok(cart)
+    }
+
+    /// Ingest a zip archive, placing its entries under `dest_prefix`
+    ///
+    /// Entries are streamed one at a time; deflated ones are inflated as
+    /// they are written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// let archive = std::fs::File::open("fixtures.zip")?;
+    /// let report = cart.import_zip(archive, "fixtures")?;
+    /// println!("imported {} files, skipped {}", report.files_imported, report.skipped.len());
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    #[cfg(feature = "zip")]
+    pub fn import_zip<R: std::io::Read + std::io::Seek>(
+        &mut self,
+        reader: R,
+        dest_prefix: &str,
+    ) -> Result<ImportReport> {
+        debug!("Importing zip into {}", dest_prefix);
+        self.inner.import_zip(reader, dest_prefix)
+    }
+
+    /// Stream every file under `prefix` into a zip archive
+    ///
+    /// Entries are stored uncompressed and carry their permissions and
+    /// modification time. `writer` needn't seek.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::open("my-data.cart")?;
+    /// let archive = std::fs::File::create("fixtures.zip")?;
+    /// let report = cart.to_zip(archive, "fixtures")?;
+    /// println!("archived {} files", report.files_exported);
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    #[cfg(feature = "zip")]
+    pub fn to_zip<W: std::io::Write>(&self, writer: W, prefix: &str) -> Result<ExportReport> {
+        debug!("Exporting {} as zip", prefix);
+        self.inner.export_zip(writer, prefix)
+    }
+
+    /// Start a batch of writes, deletes and renames that commit atomically
+    ///
+    /// Nothing reaches the archive until [`WriteBatch::commit`]; dropping the
+    /// batch (or calling [`WriteBatch::abort`]) discards it. Reads through
+    /// the batch see its staged changes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// let mut batch = cart.begin_batch();
+    /// batch.write("data/part-2.bin", b"...")?;
+    /// batch.delete("data/part-1.bin")?;
+    /// batch.write("index.json", br#"{"parts":["part-2.bin"]}"#)?;
+    /// batch.commit()?;
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn begin_batch(&mut self) -> WriteBatch<'_> {
+        self.inner.begin_batch()
+    }
+
+    /// Flush all pending changes to disk
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// cart.write("file.txt", b"data")?;
+    /// cart.flush()?;  // Ensure changes are persisted
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn flush(&mut self) -> Result<()> {
+        debug!("Flushing cartridge to disk");
+        self.inner.flush()
+    }
+
+    /// Reload the catalog and drop cached pages if a writer has flushed to
+    /// the same file since this handle last looked
+    ///
+    /// Meant for handles from [`open_read_only`](Self::open_read_only),
+    /// which can be open alongside a writer. Fails with
+    /// [`CartridgeError::Conflict`] if this handle has unflushed changes and
+    /// the file was rewritten underneath it anyway, by something that
+    /// ignores the lock.
+    pub fn refresh(&mut self) -> Result<RefreshReport> {
+        self.inner.refresh()
+    }
+
+    /// Whether a writer has flushed changes this handle hasn't seen
+    pub fn is_stale(&self) -> Result<bool> {
+        self.inner.is_stale()
+    }
+
+    /// Flush, then fsync the backing file whatever the sync policy
+    ///
+    /// Under [`SyncPolicy::OnClose`] or [`SyncPolicy::Periodic`], call this
+    /// at the points that must survive a power loss.
+    pub fn sync(&mut self) -> Result<()> {
+        self.inner.sync()
+    }
+
+    /// Flush, fsync and close the cartridge
+    ///
+    /// Dropping it does the same but can only log a failure.
+    pub fn close(self) -> Result<()> {
+        self.inner.close()
+    }
+
+    /// Compact live data and shrink the backing file
+    ///
+    /// Deleting files frees blocks but never shrinks the `.cart` file on its
+    /// own. Vacuum moves live blocks toward the front of the file and
+    /// truncates the free tail.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use cartridge_rs::Cartridge;
+    /// let mut cart = Cartridge::open("artifacts.cart")?;
+    /// let report = cart.vacuum()?;
+    /// println!("reclaimed {} bytes", report.bytes_reclaimed);
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn vacuum(&mut self) -> Result<VacuumReport> {
+        debug!("Vacuuming cartridge");
+        self.inner.vacuum()
+    }
+
+    /// Move up to `budget_blocks` file blocks to coalesce free space
+    ///
+    /// Bounded so it can run a little at a time, e.g. while a device is
+    /// idle; call again until the report says `done`. The file keeps its
+    /// size, use [`vacuum`](Self::vacuum) to shrink it.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use cartridge_rs::Cartridge;
+    /// let mut cart = Cartridge::open("artifacts.cart")?;
+    /// while !cart.defragment(256)?.done {
+    ///     // let other work run between passes
+    /// }
+    /// cart.flush()?;
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn defragment(&mut self, budget_blocks: usize) -> Result<DefragReport> {
+        self.inner.defragment(budget_blocks)
+    }
+
+    /// Freeze the archive into an immutable, optionally signed Engram
+    ///
+    /// Pending writes are flushed first. The container manifest and the
+    /// capabilities derived from the IAM policy are carried into the engram
+    /// manifest.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use cartridge_rs::{Cartridge, FreezeOptions, SigningKey};
+    /// let mut cart = Cartridge::open("artifacts.cart")?;
+    /// let options = FreezeOptions {
+    ///     vacuum: true,
+    ///     signing_key: Some(SigningKey::from_bytes(&[7u8; 32])),
+    ///     ..Default::default()
+    /// };
+    /// let report = cart.freeze("artifacts.eng", &options)?;
+    /// println!("froze {} files into {} bytes", report.files_frozen, report.archive_bytes);
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    #[cfg(not(feature = "no-fs"))]
+    pub fn freeze<P: AsRef<Path>>(
+        &mut self,
+        output_path: P,
+        options: &FreezeOptions,
+    ) -> Result<FreezeReport> {
+        info!("Freezing cartridge to {}", output_path.as_ref().display());
+        self.inner.freeze(output_path.as_ref(), options)
+    }
+
+    /// Pack this cartridge for distribution
+    ///
+    /// Flushes, vacuums, and records a SHA-256 of every file in
+    /// `.cartridge/digests.json`, signed if a key is given. The report has
+    /// the SHA-256 of the finished `.cart` file to publish alongside it. The
+    /// cartridge stays mutable.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use cartridge_rs::{Cartridge, PackOptions, SigningKey};
+    /// let mut cart = Cartridge::open("release.cart")?;
+    /// let options = PackOptions {
+    ///     signing_key: Some(SigningKey::from_bytes(&[7u8; 32])),
+    ///     ..Default::default()
+    /// };
+    /// let report = cart.pack(&options)?;
+    /// println!("sha256 {}", report.archive_sha256);
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn pack(&mut self, options: &PackOptions) -> Result<PackReport> {
+        info!("Packing cartridge");
+        self.inner.pack(options)
+    }
+
+    /// Read the digest manifest written by [`pack`](Self::pack)
+    pub fn read_digests(&self) -> Result<DigestManifest> {
+        self.inner.read_digests()
+    }
+
+    /// Recompute file digests and compare them with the packed ones
+    ///
+    /// An empty list means nothing changed since [`pack`](Self::pack).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use cartridge_rs::Cartridge;
+    /// let cart = Cartridge::open_read_only("release.cart")?;
+    /// for mismatch in cart.verify_digests()? {
+    ///     eprintln!("{:?}", mismatch);
+    /// }
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn verify_digests(&self) -> Result<Vec<DigestMismatch>> {
+        self.inner.verify_digests()
+    }
+
+    /// Audit entries persisted by [`flush`](Self::flush) that pass `filter`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use cartridge_rs::{AuditFilter, CartridgeBuilder, Operation};
+    /// let mut cart = CartridgeBuilder::new()
+    ///     .slug("audited")
+    ///     .title("Audited")
+    ///     .with_audit_logging()
+    ///     .build()?;
+    /// cart.write("a.txt", b"data")?;
+    /// cart.flush()?;
+    /// let filter = AuditFilter {
+    ///     operations: vec![Operation::Create],
+    ///     ..Default::default()
+    /// };
+    /// for record in cart.query_audit(&filter)? {
+    ///     println!("{} {:?}", record.timestamp, record.path);
+    /// }
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn query_audit(&self, filter: &AuditFilter) -> Result<Vec<AuditRecord>> {
+        self.inner.query_audit(filter)
+    }
+
+    /// Write the audit entries passing `filter` as CSV
+    pub fn export_audit_csv<W: std::io::Write>(
+        &self,
+        filter: &AuditFilter,
+        writer: W,
+    ) -> Result<usize> {
+        self.inner.export_audit_csv(filter, writer)
+    }
+
+    /// Write the audit entries passing `filter` as JSON lines
+    pub fn export_audit_jsonl<W: std::io::Write>(
+        &self,
+        filter: &AuditFilter,
+        writer: W,
+    ) -> Result<usize> {
+        self.inner.export_audit_jsonl(filter, writer)
+    }
+
+    /// Get the container slug
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::create("my-data", "My Data")?;
+    /// assert_eq!(cart.slug()?, "my-data");
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn slug(&self) -> Result<String> {
         self.inner.slug()
     }
 
@@ -582,85 +1954,412 @@ impl Cartridge {
         self.inner.title()
     }
 
-    /// Read the container manifest
-    ///
-    /// # Examples
+    /// Size of every page in bytes, fixed when the cartridge was created
+    pub fn page_size(&self) -> usize {
+        self.inner.page_size()
+    }
+
+    /// Read the container manifest
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let cart = Cartridge::create("my-data", "My Container")?;
+    /// let manifest = cart.read_manifest()?;
+    /// println!("Version: {}", manifest.version);
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn read_manifest(&self) -> Result<Manifest> {
+        self.inner.read_manifest()
+    }
+
+    /// Update the container manifest
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let mut cart = Cartridge::create("my-data", "My Container")?;
+    /// cart.update_manifest(|manifest| {
+    ///     manifest.description = Some("Updated description".to_string());
+    /// })?;
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn update_manifest<F>(&mut self, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Manifest),
+    {
+        self.inner.update_manifest(f)
+    }
+
+    /// Declare a dependency on another cartridge
+    ///
+    /// `req` is a semver requirement such as `">=1.2"` or `"^2"`. Replaces
+    /// any existing requirement on the same slug.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// let mut pack = Cartridge::create("content-pack", "Content Pack")?;
+    /// pack.add_dependency("base-assets", ">=1.2")?;
+    /// assert_eq!(pack.dependencies()?[0].slug.as_str(), "base-assets");
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn add_dependency(&mut self, slug: &str, req: &str) -> Result<()> {
+        self.inner.add_dependency(slug, req)
+    }
+
+    /// Dependencies declared in the manifest
+    pub fn dependencies(&self) -> Result<Vec<Dependency>> {
+        self.inner.dependencies()
+    }
+
+    /// Increment the manifest version and return the new one
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::{Bump, Cartridge};
+    /// let mut cart = Cartridge::open("base-assets.cart")?;
+    /// let version = cart.bump_version(Bump::Minor)?;
+    /// println!("now at {}", version);
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn bump_version(&mut self, bump: Bump) -> Result<semver::Version> {
+        self.inner.bump_version(bump)
+    }
+
+    /// Get the cartridge header with S3 fuses and metadata
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # fn main() -> cartridge_rs::Result<()> {
+    /// let cart = Cartridge::create("data", "My Data")?;
+    /// let fuses = cart.header().get_s3_fuses();
+    /// println!("ACL mode: {:?}", fuses.acl_mode);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn header(&self) -> &Header {
+        self.inner.header()
+    }
+
+    /// Get mutable access to the cartridge header
+    pub fn header_mut(&mut self) -> &mut Header {
+        self.inner.header_mut()
+    }
+
+    /// Update user-defined metadata for a file
+    ///
+    /// Stores custom key-value pairs in file metadata without modifying content.
+    /// Useful for S3-compatible metadata, ACLs, SSE headers, etc.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # fn main() -> cartridge_rs::Result<()> {
+    /// let mut cart = Cartridge::create("data", "My Data")?;
+    /// cart.write("file.txt", b"content")?;
+    /// cart.update_user_metadata("file.txt", "s3:acl", r#"{"grants":[]}"#)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn update_user_metadata<P: AsRef<str>>(
+        &mut self,
+        path: P,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Result<()> {
+        self.inner.update_user_metadata(path.as_ref(), key, value)
+    }
+
+    /// Add or replace several user metadata keys at once
+    ///
+    /// Keys must be non-empty and free of control characters, and a file's
+    /// metadata is capped at 2 KB by default (see
+    /// [`set_max_metadata_bytes`](Self::set_max_metadata_bytes)). Nothing
+    /// changes if any key is rejected. Existing keys not in `metadata` are
+    /// kept.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # use std::collections::HashMap;
+    /// # let mut cart = Cartridge::create("data", "My Data")?;
+    /// cart.write("report.pdf", b"...")?;
+    /// cart.set_metadata("report.pdf", HashMap::from([
+    ///     ("author".to_string(), "Alice".to_string()),
+    ///     ("reviewed".to_string(), "true".to_string()),
+    /// ]))?;
+    /// assert_eq!(cart.get_metadata_keys("report.pdf")?["author"], "Alice");
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn set_metadata<P: AsRef<str>>(
+        &mut self,
+        path: P,
+        metadata: HashMap<String, String>,
+    ) -> Result<()> {
+        self.inner.set_user_metadata(path.as_ref(), metadata)
+    }
+
+    /// Get a file's user metadata as key/value pairs
+    pub fn get_metadata_keys<P: AsRef<str>>(&self, path: P) -> Result<HashMap<String, String>> {
+        self.inner.user_metadata(path.as_ref())
+    }
+
+    /// Remove one user metadata key, returning its old value
+    pub fn remove_metadata_key<P: AsRef<str>>(&mut self, path: P, key: &str) -> Result<Option<String>> {
+        self.inner.remove_user_metadata(path.as_ref(), key)
+    }
+
+    /// Set a file's creation and/or modification time, in seconds since
+    /// the Unix epoch, leaving its content alone
+    ///
+    /// `None` keeps the current value. Useful for keeping the times of files
+    /// synced in from elsewhere.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let mut cart = Cartridge::create("data", "My Data")?;
+    /// cart.write("photo.jpg", b"...")?;
+    /// cart.set_times("photo.jpg", None, Some(1_600_000_000))?;
+    /// assert_eq!(cart.metadata("photo.jpg")?.modified_at, 1_600_000_000);
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn set_times<P: AsRef<str>>(
+        &mut self,
+        path: P,
+        created: Option<u64>,
+        modified: Option<u64>,
+    ) -> Result<()> {
+        self.inner.set_times(path.as_ref(), created, modified)
+    }
+
+    /// SHA-256 of a file's content
+    ///
+    /// Stored with each whole-file write, so checking whether a file changed
+    /// doesn't mean reading it. Files without a stored digest (last changed
+    /// by a partial write, or from an older version) are hashed now and the
+    /// digest kept for next time.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let mut cart = Cartridge::create("data", "My Data")?;
+    /// cart.write("data.csv", b"a,b\n1,2\n")?;
+    /// let before = cart.hash("data.csv")?;
+    /// cart.write("data.csv", b"a,b\n1,3\n")?;
+    /// assert_ne!(cart.hash("data.csv")?, before);
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn hash<P: AsRef<str>>(&mut self, path: P) -> Result<[u8; 32]> {
+        self.inner.hash(path.as_ref())
+    }
+
+    /// Bump a file's modification time to now, creating it empty if it
+    /// doesn't exist, like `touch(1)`
+    pub fn touch<P: AsRef<str>>(&mut self, path: P) -> Result<()> {
+        self.inner.touch(path.as_ref())
+    }
+
+    /// Change the metadata of many files at once, flushing the catalog once
+    /// at the end
+    ///
+    /// Nothing changes unless every path exists and every patch is valid.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::{Cartridge, MetadataPatch};
+    /// # let mut cart = Cartridge::create("data", "My Data")?;
+    /// cart.update_metadata_bulk(vec![
+    ///     ("a.txt", MetadataPatch::times(None, Some(1_600_000_000))),
+    ///     ("b.bin", MetadataPatch::default().with_content_type("application/x-custom")),
+    ///     ("c.txt", MetadataPatch::default().with_user_metadata("reviewed", "true")),
+    /// ])?;
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn update_metadata_bulk<P: AsRef<str>>(
+        &mut self,
+        updates: Vec<(P, MetadataPatch)>,
+    ) -> Result<()> {
+        let updates = updates
+            .into_iter()
+            .map(|(path, patch)| (path.as_ref().to_string(), patch))
+            .collect();
+        self.inner.update_metadata_bulk(updates)
+    }
+
+    /// Set the cap on each file's user metadata, in bytes (default: 2048)
+    pub fn set_max_metadata_bytes(&mut self, bytes: usize) {
+        self.inner.set_max_user_metadata_bytes(bytes);
+    }
+
+    /// Store files of at most `bytes` inside the catalog instead of giving
+    /// each its own 4KB block (default: 512, 0 turns inlining off)
+    pub fn set_inline_threshold(&mut self, bytes: usize) {
+        self.inner.set_inline_threshold(bytes);
+    }
+
+    /// Share blocks between pages with identical content on later writes
+    ///
+    /// Costs a SHA-256 per page written. Not saved in the file; turn it on
+    /// again after reopening.
+    pub fn set_dedup(&mut self, enabled: bool) {
+        self.inner.set_dedup(enabled);
+    }
+
+    /// Change when flushes fsync; see [`SyncPolicy`] for the trade-offs
+    ///
+    /// Not saved in the file.
+    pub fn set_sync_policy(&mut self, policy: SyncPolicy) {
+        self.inner.set_sync_policy(policy);
+    }
+
+    /// When flushes fsync
+    pub fn sync_policy(&self) -> SyncPolicy {
+        self.inner.sync_policy()
+    }
+
+    /// Limit the total size of files under `prefix`
+    ///
+    /// The quota is stored in the manifest and enforced on every write;
+    /// going over fails with [`CartridgeError::QuotaExceeded`]. An empty
+    /// prefix limits the whole cartridge.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::{Cartridge, CartridgeError};
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// cart.set_quota("uploads", 1024)?;
+    /// match cart.write("uploads/big.bin", &vec![0u8; 4096]) {
+    ///     Err(CartridgeError::QuotaExceeded { limit, attempted, .. }) => {
+    ///         println!("{attempted} bytes is over the {limit} byte quota");
+    ///     }
+    ///     other => other?,
+    /// }
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn set_quota<P: AsRef<str>>(&mut self, prefix: P, bytes: u64) -> Result<()> {
+        self.inner.set_quota(prefix.as_ref(), bytes)
+    }
+
+    /// Remove the quota on `prefix`, returning whether there was one
+    pub fn remove_quota<P: AsRef<str>>(&mut self, prefix: P) -> Result<bool> {
+        self.inner.remove_quota(prefix.as_ref())
+    }
+
+    /// Limit and current usage of the quota on `prefix`, if any
+    pub fn quota_usage<P: AsRef<str>>(&self, prefix: P) -> Option<QuotaUsage> {
+        self.inner.quota_usage(prefix.as_ref())
+    }
+
+    /// Logical and physical usage, entry counts and cache figures
     ///
-    /// ```rust,no_run
-    /// # use cartridge_rs::Cartridge;
-    /// # let cart = Cartridge::create("my-data", "My Container")?;
-    /// let manifest = cart.read_manifest()?;
-    /// println!("Version: {}", manifest.version);
-    /// # Ok::<(), cartridge_rs::CartridgeError>(())
-    /// ```
-    pub fn read_manifest(&self) -> Result<Manifest> {
-        self.inner.read_manifest()
+    /// Walks the catalog once; the result serializes directly for monitoring.
+    pub fn detailed_stats(&self) -> Result<DetailedStats> {
+        self.inner.detailed_stats()
     }
 
-    /// Update the container manifest
+    /// Cap how large auto-growth may make the container, in bytes
+    pub fn set_max_size_bytes(&mut self, bytes: u64) {
+        self.inner.set_max_size_bytes(bytes);
+    }
+
+    /// Roughly how many more bytes of content can be written
+    ///
+    /// Counts free space in the container plus what auto-growth may still
+    /// add before hitting [`max_size_bytes`](CartridgeBuilder::max_size_bytes)
+    /// or filling the disk, whichever comes first.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// # use cartridge_rs::Cartridge;
-    /// # let mut cart = Cartridge::create("my-data", "My Container")?;
-    /// cart.update_manifest(|manifest| {
-    ///     manifest.description = Some("Updated description".to_string());
-    /// })?;
+    /// # let mut cart = Cartridge::create("data", "My Data")?;
+    /// let upload = vec![0u8; 64 * 1024 * 1024];
+    /// if cart.headroom() < upload.len() as u64 {
+    ///     eprintln!("not enough room for the upload");
+    /// }
     /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
-    pub fn update_manifest<F>(&mut self, f: F) -> Result<()>
-    where
-        F: FnOnce(&mut Manifest),
-    {
-        self.inner.update_manifest(f)
+    pub fn headroom(&self) -> u64 {
+        self.inner.headroom()
     }
 
-    /// Get the cartridge header with S3 fuses and metadata
+    /// Grow the container now so at least `bytes` are free
+    ///
+    /// Call before a large import to extend the file once instead of in
+    /// steps; works even with auto-growth off.
+    pub fn reserve(&mut self, bytes: u64) -> Result<()> {
+        self.inner.reserve(bytes)
+    }
+
+    /// Receive progress events from growth, large writes, flushes and vacuum
+    ///
+    /// The listener runs on the calling thread and must not call back into
+    /// this cartridge.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
-    /// # use cartridge_rs::Cartridge;
+    /// # use cartridge_rs::{Cartridge, CartridgeEvent};
     /// # fn main() -> cartridge_rs::Result<()> {
-    /// let cart = Cartridge::create("data", "My Data")?;
-    /// let fuses = cart.header().get_s3_fuses();
-    /// println!("ACL mode: {:?}", fuses.acl_mode);
+    /// let mut cart = Cartridge::create("data", "My Data")?;
+    /// cart.set_event_listener(Box::new(|event| {
+    ///     if let CartridgeEvent::WriteProgress { path, written, total } = event {
+    ///         println!("{}: {}/{} bytes", path, written, total);
+    ///     }
+    /// }));
     /// # Ok(())
     /// # }
     /// ```
-    pub fn header(&self) -> &Header {
-        self.inner.header()
+    pub fn set_event_listener(&mut self, listener: EventListener) {
+        self.inner.set_event_listener(listener);
     }
 
-    /// Get mutable access to the cartridge header
-    pub fn header_mut(&mut self) -> &mut Header {
-        self.inner.header_mut()
+    /// Stop sending events
+    pub fn clear_event_listener(&mut self) {
+        self.inner.clear_event_listener();
     }
 
-    /// Update user-defined metadata for a file
+    /// Subscribe to changes under `prefix` (`""` for everything)
     ///
-    /// Stores custom key-value pairs in file metadata without modifying content.
-    /// Useful for S3-compatible metadata, ACLs, SSE headers, etc.
+    /// Creates, writes, deletes, renames and new directories under the
+    /// prefix arrive on the returned channel. Sending never blocks: when the
+    /// channel holds [`DEFAULT_WATCH_CAPACITY`] unread events, further events
+    /// are dropped for this watcher until it catches up. Dropping the
+    /// receiver unsubscribes.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// # use cartridge_rs::Cartridge;
-    /// # fn main() -> cartridge_rs::Result<()> {
     /// let mut cart = Cartridge::create("data", "My Data")?;
-    /// cart.write("file.txt", b"content")?;
-    /// cart.update_user_metadata("file.txt", "s3:acl", r#"{"grants":[]}"#)?;
-    /// # Ok(())
-    /// # }
+    /// let changes = cart.watch("docs");
+    /// cart.write("docs/a.txt", b"hello")?;
+    /// for event in changes.try_iter() {
+    ///     println!("{:?} {}", event.kind, event.path);
+    /// }
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
     /// ```
-    pub fn update_user_metadata<P: AsRef<str>>(
-        &mut self,
-        path: P,
-        key: impl Into<String>,
-        value: impl Into<String>,
-    ) -> Result<()> {
-        self.inner.update_user_metadata(path.as_ref(), key, value)
+    pub fn watch(&self, prefix: &str) -> Receiver<ChangeEvent> {
+        self.inner.watch(prefix)
+    }
+
+    /// Subscribe with a channel of `capacity` events instead of the default
+    pub fn watch_with_capacity(&self, prefix: &str, capacity: usize) -> Receiver<ChangeEvent> {
+        self.inner.watch_with_capacity(prefix, capacity)
     }
 
     /// Create a snapshot of the current cartridge state
@@ -689,6 +2388,7 @@ impl Cartridge {
     /// # Ok(())
     /// # }
     /// ```
+    #[cfg(not(feature = "no-fs"))]
     pub fn create_snapshot(
         &self,
         name: String,
@@ -698,97 +2398,390 @@ impl Cartridge {
         self.inner.create_snapshot(name, description, snapshot_dir)
     }
 
-    /// Restore cartridge state from a snapshot
+    /// Create a snapshot storing only the pages changed since `parent_id`
+    ///
+    /// Much smaller than a full snapshot when little has changed. Restoring
+    /// it needs every snapshot in its parent chain, and a snapshot can't be
+    /// deleted while others are based on it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # use std::path::Path;
+    /// # fn main() -> cartridge_rs::Result<()> {
+    /// let mut cart = Cartridge::create("data", "My Data")?;
+    /// let snapshots = Path::new("./snapshots");
+    /// let base = cart.create_snapshot("base".into(), String::new(), snapshots)?;
+    /// cart.write("file.txt", b"version 2")?;
+    /// let nightly =
+    ///     cart.create_snapshot_incremental(base, "n1".into(), String::new(), snapshots)?;
+    /// cart.restore_snapshot(nightly, snapshots)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no-fs"))]
+    pub fn create_snapshot_incremental(
+        &self,
+        parent_id: u64,
+        name: String,
+        description: String,
+        snapshot_dir: &std::path::Path,
+    ) -> Result<u64> {
+        self.inner
+            .create_snapshot_incremental(parent_id, name, description, snapshot_dir)
+    }
+
+    /// Restore cartridge state from a snapshot
+    ///
+    /// # Arguments
+    /// * `snapshot_id` - Snapshot ID to restore
+    /// * `snapshot_dir` - Directory containing snapshot files
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # use std::path::Path;
+    /// # fn main() -> cartridge_rs::Result<()> {
+    /// let mut cart = Cartridge::create("data", "My Data")?;
+    /// let snapshot_id = 1234567890000000u64;
+    /// cart.restore_snapshot(snapshot_id, Path::new("./snapshots"))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no-fs"))]
+    pub fn restore_snapshot(&mut self, snapshot_id: u64, snapshot_dir: &std::path::Path) -> Result<()> {
+        self.inner.restore_snapshot(snapshot_id, snapshot_dir)
+    }
+
+    /// List the paths added, removed and modified since a snapshot
+    ///
+    /// Only catalog metadata is compared, so this is cheap however large
+    /// the files are. Compare two snapshots with [`SnapshotManager::diff`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # use std::path::Path;
+    /// # fn main() -> cartridge_rs::Result<()> {
+    /// let mut cart = Cartridge::create("data", "My Data")?;
+    /// let snapshots = Path::new("./snapshots");
+    /// let snapshot_id = cart.create_snapshot("v1".into(), String::new(), snapshots)?;
+    /// cart.write("new.txt", b"hello")?;
+    /// let diff = cart.diff_since(snapshot_id, snapshots)?;
+    /// assert_eq!(diff.added, ["/new.txt"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no-fs"))]
+    pub fn diff_since(
+        &self,
+        snapshot_id: u64,
+        snapshot_dir: &std::path::Path,
+    ) -> Result<SnapshotDiff> {
+        self.inner.diff_since(snapshot_id, snapshot_dir)
+    }
+
+    /// Open a snapshot for reading, side by side with the live cartridge
+    ///
+    /// Unlike [`restore_snapshot`](Self::restore_snapshot) this changes
+    /// nothing: the [`SnapshotView`] holds the snapshot's state in memory
+    /// and serves it through the read half of [`Vfs`], while this cartridge
+    /// carries on as before. Fails with
+    /// [`CartridgeError::SnapshotCatalogMissing`] for snapshots taken
+    /// before catalogs were saved.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::{Cartridge, Vfs};
+    /// # use std::path::Path;
+    /// # fn main() -> cartridge_rs::Result<()> {
+    /// let mut cart = Cartridge::create("data", "My Data")?;
+    /// let snapshots = Path::new("./snapshots");
+    /// cart.write("report.txt", b"draft")?;
+    /// let snapshot_id = cart.create_snapshot("v1".into(), String::new(), snapshots)?;
+    /// cart.write("report.txt", b"final")?;
+    ///
+    /// let v1 = cart.open_snapshot_view(snapshot_id, snapshots)?;
+    /// assert_eq!(v1.read("report.txt")?, b"draft");
+    /// assert_eq!(cart.read("report.txt")?, b"final");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no-fs"))]
+    pub fn open_snapshot_view(
+        &self,
+        snapshot_id: u64,
+        snapshot_dir: &std::path::Path,
+    ) -> Result<SnapshotView> {
+        let inner = self.inner.open_snapshot_view(snapshot_id, snapshot_dir)?;
+        let cart = Cartridge { inner, vfs_name: None };
+        Ok(SnapshotView::new(cart, snapshot_id))
+    }
+
+    /// Store a snapshot retention policy in the manifest, or clear it with
+    /// `None`
+    ///
+    /// The policy travels with the cartridge, so a maintenance task only
+    /// needs the snapshot directory to call
+    /// [`apply_snapshot_retention`](Self::apply_snapshot_retention).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::{Cartridge, RetentionPolicy};
+    /// # use std::path::Path;
+    /// # fn main() -> cartridge_rs::Result<()> {
+    /// let mut cart = Cartridge::create("data", "My Data")?;
+    /// cart.set_snapshot_retention(Some(RetentionPolicy {
+    ///     keep_last: 3,
+    ///     keep_daily: 7,
+    ///     keep_weekly: 4,
+    ///     ..Default::default()
+    /// }))?;
+    ///
+    /// // Later, from a scheduled job
+    /// let deleted = cart.apply_snapshot_retention(Path::new("./snapshots"))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_snapshot_retention(&mut self, policy: Option<RetentionPolicy>) -> Result<()> {
+        self.inner.set_snapshot_retention(policy)
+    }
+
+    /// The snapshot retention policy stored in the manifest, if any
+    pub fn snapshot_retention(&self) -> Result<Option<RetentionPolicy>> {
+        self.inner.snapshot_retention()
+    }
+
+    /// Delete the snapshots in `snapshot_dir` that the manifest's retention
+    /// policy doesn't keep, returning their ids
+    ///
+    /// Does nothing when no policy is set. The newest snapshot, and any
+    /// that a kept incremental snapshot depends on, always survive.
+    #[cfg(not(feature = "no-fs"))]
+    pub fn apply_snapshot_retention(&self, snapshot_dir: &std::path::Path) -> Result<Vec<u64>> {
+        self.inner.apply_snapshot_retention(snapshot_dir)
+    }
+
+    /// Enable encryption for all new files written to the cartridge
+    ///
+    /// Once enabled, all new files created or updated will be encrypted using AES-256-GCM.
+    /// Existing files are not automatically encrypted - they remain as-is.
+    ///
+    /// # Arguments
+    /// * `key` - 32-byte AES-256 encryption key
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::{Cartridge, EncryptionConfig};
+    /// # fn main() -> cartridge_rs::Result<()> {
+    /// let mut cart = Cartridge::create("secure-data", "Secure Data")?;
+    ///
+    /// // Generate a random encryption key
+    /// let key = EncryptionConfig::generate_key();
+    ///
+    /// // Enable encryption
+    /// cart.enable_encryption(&key)?;
+    ///
+    /// // All new files will be encrypted
+    /// cart.write("sensitive.txt", b"confidential data")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn enable_encryption(&mut self, key: &[u8; 32]) -> Result<()> {
+        self.inner.enable_encryption(key)
+    }
+
+    /// Disable encryption
+    ///
+    /// New files written after disabling encryption will not be encrypted.
+    /// Files that were already encrypted remain encrypted and require the key to read.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # fn main() -> cartridge_rs::Result<()> {
+    /// let mut cart = Cartridge::create("data", "My Data")?;
+    /// cart.disable_encryption()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn disable_encryption(&mut self) -> Result<()> {
+        self.inner.disable_encryption()
+    }
+
+    /// Check if encryption is currently enabled
+    ///
+    /// Returns `true` if new files will be encrypted, `false` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::{Cartridge, EncryptionConfig};
+    /// # fn main() -> cartridge_rs::Result<()> {
+    /// let mut cart = Cartridge::create("data", "My Data")?;
+    /// assert!(!cart.is_encrypted());
+    ///
+    /// let key = EncryptionConfig::generate_key();
+    /// cart.enable_encryption(&key)?;
+    /// assert!(cart.is_encrypted());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn is_encrypted(&self) -> bool {
+        self.inner.is_encrypted()
+    }
+
+    /// Check if the whole archive is encrypted at rest
+    ///
+    /// True for archives created with [`CartridgeBuilder::with_encryption`].
+    pub fn is_encrypted_at_rest(&self) -> bool {
+        self.inner.is_encrypted_at_rest()
+    }
+
+    /// Write a file encrypted with a key of its own
+    ///
+    /// For keeping some paths private in an otherwise readable cartridge,
+    /// with keys held outside it (a keystore, say). The file is sealed with
+    /// AES-256-GCM; plain [`read`](Self::read) fails with
+    /// [`CartridgeError::EncryptedFile`] instead of returning ciphertext,
+    /// and listings mark it [`encrypted`](Entry::encrypted).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::{Cartridge, EncryptionConfig};
+    /// # let mut cart = Cartridge::create("data", "My Data")?;
+    /// let key = EncryptionConfig::generate_key();
+    /// cart.write("public/logo.svg", b"<svg/>")?;
+    /// cart.write_encrypted("private/notes.txt", b"for my eyes only", &key)?;
+    ///
+    /// assert_eq!(cart.read_encrypted("private/notes.txt", &key)?, b"for my eyes only");
+    /// assert!(cart.read("private/notes.txt").is_err());
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn write_encrypted<P: AsRef<str>>(
+        &mut self,
+        path: P,
+        content: &[u8],
+        key: &EncryptionKey,
+    ) -> Result<()> {
+        self.inner.write_encrypted(path.as_ref(), content, key)
+    }
+
+    /// Read a file written with [`write_encrypted`](Self::write_encrypted)
+    ///
+    /// A wrong key fails with [`CartridgeError::DecryptionFailed`].
+    pub fn read_encrypted<P: AsRef<str>>(&self, path: P, key: &EncryptionKey) -> Result<Vec<u8>> {
+        self.inner.read_encrypted(path.as_ref(), key)
+    }
+
+    /// Re-encrypt a file written with [`write_encrypted`](Self::write_encrypted)
+    /// under `new_key`, for key rotation
+    pub fn reencrypt<P: AsRef<str>>(
+        &mut self,
+        path: P,
+        old_key: &EncryptionKey,
+        new_key: &EncryptionKey,
+    ) -> Result<()> {
+        self.inner.reencrypt(path.as_ref(), old_key, new_key)
+    }
+
+    /// How paths compare, as fixed at creation
+    ///
+    /// See [`CartridgeBuilder::case_insensitive`].
+    pub fn case_sensitivity(&self) -> CaseSensitivity {
+        self.inner.case_sensitivity()
+    }
+
+    /// Verify page checksums for every file in the archive
     ///
-    /// # Arguments
-    /// * `snapshot_id` - Snapshot ID to restore
-    /// * `snapshot_dir` - Directory containing snapshot files
+    /// Returns the paths of files whose on-disk pages fail verification. An
+    /// empty list means no corruption was found.
     ///
     /// # Examples
     ///
-    /// ```rust,no_run
-    /// # use cartridge_rs::Cartridge;
-    /// # use std::path::Path;
+    /// ```no_run
+    /// use cartridge_rs::Cartridge;
+    ///
     /// # fn main() -> cartridge_rs::Result<()> {
-    /// let mut cart = Cartridge::create("data", "My Data")?;
-    /// let snapshot_id = 1234567890000000u64;
-    /// cart.restore_snapshot(snapshot_id, Path::new("./snapshots"))?;
+    /// let cart = Cartridge::open("data.cart")?;
+    /// for path in cart.verify()? {
+    ///     eprintln!("corrupted: {}", path);
+    /// }
     /// # Ok(())
     /// # }
     /// ```
-    pub fn restore_snapshot(&mut self, snapshot_id: u64, snapshot_dir: &std::path::Path) -> Result<()> {
-        self.inner.restore_snapshot(snapshot_id, snapshot_dir)
+    pub fn verify(&self) -> Result<Vec<String>> {
+        self.inner.verify()
     }
 
-    /// Enable encryption for all new files written to the cartridge
-    ///
-    /// Once enabled, all new files created or updated will be encrypted using AES-256-GCM.
-    /// Existing files are not automatically encrypted - they remain as-is.
+    /// Verify the next `max_pages` content pages of a scrub pass
     ///
-    /// # Arguments
-    /// * `key` - 32-byte AES-256 encryption key
+    /// Like [`verify`](Self::verify) spread over many calls, so flash media
+    /// can be checked a slice at a time during idle periods. Pass `None` to
+    /// start a pass and [`ScrubResult::next`] to continue it; errors name
+    /// every file that uses the bad block.
     ///
     /// # Examples
     ///
-    /// ```rust,no_run
-    /// # use cartridge_rs::{Cartridge, EncryptionConfig};
-    /// # fn main() -> cartridge_rs::Result<()> {
-    /// let mut cart = Cartridge::create("secure-data", "Secure Data")?;
-    ///
-    /// // Generate a random encryption key
-    /// let key = EncryptionConfig::generate_key();
-    ///
-    /// // Enable encryption
-    /// cart.enable_encryption(&key)?;
+    /// ```no_run
+    /// use cartridge_rs::{Cartridge, ScrubResult};
     ///
-    /// // All new files will be encrypted
-    /// cart.write("sensitive.txt", b"confidential data")?;
+    /// # fn main() -> cartridge_rs::Result<()> {
+    /// let cart = Cartridge::open("data.cart")?;
+    /// let mut pass = ScrubResult::default();
+    /// loop {
+    ///     let step = cart.scrub(pass.next, 256)?;
+    ///     pass.merge(step);
+    ///     if pass.is_complete() {
+    ///         break;
+    ///     }
+    ///     // ...wait for an idle period...
+    /// }
+    /// for path in pass.damaged_paths() {
+    ///     eprintln!("damaged: {}", path);
+    /// }
     /// # Ok(())
     /// # }
     /// ```
-    pub fn enable_encryption(&mut self, key: &[u8; 32]) -> Result<()> {
-        self.inner.enable_encryption(key)
+    pub fn scrub(&self, start: Option<ScrubToken>, max_pages: usize) -> Result<ScrubResult> {
+        self.inner.scrub(start, max_pages)
     }
 
-    /// Disable encryption
+    /// Check catalog, allocator and header consistency (fsck)
     ///
-    /// New files written after disabling encryption will not be encrypted.
-    /// Files that were already encrypted remain encrypted and require the key to read.
+    /// Read-only; see [`ConsistencyReport`] for what is checked.
     ///
     /// # Examples
     ///
-    /// ```rust,no_run
-    /// # use cartridge_rs::Cartridge;
+    /// ```no_run
+    /// use cartridge_rs::Cartridge;
+    ///
     /// # fn main() -> cartridge_rs::Result<()> {
-    /// let mut cart = Cartridge::create("data", "My Data")?;
-    /// cart.disable_encryption()?;
+    /// let mut cart = Cartridge::open("data.cart")?;
+    /// let report = cart.check()?;
+    /// if !report.is_consistent() && report.is_repairable() {
+    ///     cart.repair()?;
+    /// }
     /// # Ok(())
     /// # }
     /// ```
-    pub fn disable_encryption(&mut self) -> Result<()> {
-        self.inner.disable_encryption()
+    pub fn check(&self) -> Result<ConsistencyReport> {
+        self.inner.check()
     }
 
-    /// Check if encryption is currently enabled
-    ///
-    /// Returns `true` if new files will be encrypted, `false` otherwise.
-    ///
-    /// # Examples
-    ///
-    /// ```rust,no_run
-    /// # use cartridge_rs::{Cartridge, EncryptionConfig};
-    /// # fn main() -> cartridge_rs::Result<()> {
-    /// let mut cart = Cartridge::create("data", "My Data")?;
-    /// assert!(!cart.is_encrypted());
+    /// Rebuild the allocator from the catalog and fix header counts
     ///
-    /// let key = EncryptionConfig::generate_key();
-    /// cart.enable_encryption(&key)?;
-    /// assert!(cart.is_encrypted());
-    /// # Ok(())
-    /// # }
-    /// ```
-    pub fn is_encrypted(&self) -> bool {
-        self.inner.is_encrypted()
+    /// Returns a fresh report for the repaired archive.
+    pub fn repair(&mut self) -> Result<ConsistencyReport> {
+        self.inner.repair()
     }
 
     /// Get the VFS name for this cartridge, if one has been registered.
@@ -797,10 +2790,63 @@ impl Cartridge {
     }
 
     /// Set the VFS name (called by `CartridgeDatabase::new` after registration).
+    #[allow(dead_code)]
     pub(crate) fn set_vfs_name(&mut self, name: String) {
         self.vfs_name = Some(name);
     }
 
+    /// Open a SQLite database stored at `db_path` inside this cartridge
+    ///
+    /// Creates the database if it doesn't exist yet (unless the cartridge
+    /// is read-only). A VFS for this cartridge is registered for as long as
+    /// the returned connection lives, and the connection borrows the
+    /// cartridge, so it can't be used for anything else meanwhile. Dropping
+    /// the connection (or calling [`CartridgeConnection::close`]) closes it,
+    /// unregisters the VFS and flushes, so the database survives closing
+    /// and reopening the cartridge.
+    ///
+    /// The default rollback journal (`journal_mode=DELETE`) is stored as
+    /// `<db_path>-journal` next to the database and deleted on commit. The
+    /// VFS has no shared-memory support, so `PRAGMA journal_mode=WAL` only
+    /// takes effect after `PRAGMA locking_mode=EXCLUSIVE`; SQLite then keeps
+    /// the WAL index in memory and checkpoints `<db_path>-wal` back into the
+    /// database when the connection closes. The database stays in WAL mode,
+    /// so later connections have to set exclusive locking before using it.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use cartridge_rs::Cartridge;
+    /// let mut cart = Cartridge::open("project.cart")?;
+    /// let conn = cart.open_database("/vcs.db")?;
+    /// conn.execute("CREATE TABLE IF NOT EXISTS blobs (id INTEGER PRIMARY KEY)", [])
+    ///     .expect("create table");
+    /// conn.close()?;
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    #[cfg(all(feature = "sqlite", not(feature = "no-fs")))]
+    pub fn open_database(&mut self, db_path: &str) -> Result<CartridgeConnection<'_>> {
+        let read_only = self.inner.is_read_only();
+        let vfs_name = crate::core::vfs::generate_vfs_name();
+
+        // The VFS needs shared ownership, so lend it the core cartridge and
+        // leave an empty stand-in until the connection hands it back
+        let core = std::mem::replace(&mut self.inner, CoreCartridge::new(3));
+        let shared = Arc::new(parking_lot::Mutex::new(core));
+        let mut connection = CartridgeConnection {
+            conn: None,
+            shared: Some(Arc::clone(&shared)),
+            vfs_name: None,
+            cartridge: self,
+        };
+
+        crate::core::vfs::register_named_vfs(&vfs_name, shared)?;
+        connection.vfs_name = Some(vfs_name.clone());
+        connection.conn = Some(open_vfs_connection(&vfs_name, db_path, read_only)?);
+        connection.cartridge.vfs_name = Some(vfs_name);
+        Ok(connection)
+    }
+
     /// Get access to the underlying core Cartridge for advanced operations
     ///
     /// Use this when you need features not exposed by the high-level API:
@@ -835,6 +2881,39 @@ impl Cartridge {
     pub fn into_inner(self) -> CoreCartridge {
         self.inner
     }
+
+    /// Convert into a cloneable handle that can be shared across threads
+    ///
+    /// See [`SharedCartridge`].
+    pub fn into_shared(self) -> SharedCartridge {
+        SharedCartridge {
+            inner: Arc::new(parking_lot::RwLock::new(self)),
+        }
+    }
+}
+
+/// Check the dependencies of a set of installed cartridges against each other
+///
+/// Returns every requirement that no cartridge in `installed` satisfies,
+/// either because none has the slug or because none has a matching version.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use cartridge_rs::{check_dependencies, Cartridge};
+/// let base = Cartridge::open("base-assets.cart")?;
+/// let pack = Cartridge::open("content-pack.cart")?;
+/// for unmet in check_dependencies(&[&base, &pack])? {
+///     eprintln!("{} needs {} {}", unmet.dependent, unmet.dependency.slug, unmet.dependency.req);
+/// }
+/// # Ok::<(), cartridge_rs::CartridgeError>(())
+/// ```
+pub fn check_dependencies(installed: &[&Cartridge]) -> Result<Vec<UnmetDependency>> {
+    let manifests = installed
+        .iter()
+        .map(|cart| cart.read_manifest())
+        .collect::<Result<Vec<_>>>()?;
+    Ok(core::manifest::check_dependencies(&manifests))
 }
 
 // ---------------------------------------------------------------------------
@@ -857,6 +2936,7 @@ impl Cartridge {
 /// let conn = db.open("vcs.db")?;
 /// conn.execute("CREATE TABLE blobs (id INTEGER PRIMARY KEY)", [])?;
 /// ```
+#[cfg(not(feature = "no-fs"))]
 pub struct CartridgeDatabase {
     /// Shared cartridge for VFS access
     inner: Arc<parking_lot::Mutex<CoreCartridge>>,
@@ -864,6 +2944,7 @@ pub struct CartridgeDatabase {
     vfs_name: String,
 }
 
+#[cfg(not(feature = "no-fs"))]
 impl CartridgeDatabase {
     /// Create a new CartridgeDatabase from a Cartridge.
     ///
@@ -881,15 +2962,7 @@ impl CartridgeDatabase {
     /// Open a SQLite database at the given path inside the cartridge.
     /// Creates the database if it doesn't exist.
     pub fn open(&self, db_path: &str) -> Result<rusqlite::Connection> {
-        let uri = format!("file:{db_path}?vfs={}", self.vfs_name);
-        let conn = rusqlite::Connection::open_with_flags(
-            &uri,
-            rusqlite::OpenFlags::SQLITE_OPEN_READ_WRITE
-                | rusqlite::OpenFlags::SQLITE_OPEN_CREATE
-                | rusqlite::OpenFlags::SQLITE_OPEN_URI,
-        ).map_err(|e| CartridgeError::Allocation(format!("SQLite open via cartridge VFS failed: {e}")))?;
-
-        Ok(conn)
+        open_vfs_connection(&self.vfs_name, db_path, false)
     }
 
     /// Get the VFS name for this cartridge's databases.
@@ -910,12 +2983,12 @@ impl CartridgeDatabase {
 
     /// Check if a file exists in the cartridge.
     pub fn exists(&self, path: &str) -> Result<bool> {
-        Ok(self.inner.lock().exists(path)?)
+        self.inner.lock().exists(path)
     }
 
     /// Read a file from the cartridge (non-database files).
     pub fn read(&self, path: &str) -> Result<Vec<u8>> {
-        Ok(self.inner.lock().read_file(path)?)
+        self.inner.lock().read_file(path)
     }
 
     /// Write a file to the cartridge (non-database files).
@@ -948,12 +3021,23 @@ impl CartridgeDatabase {
         self.inner.lock().vacuum_finish()
     }
 
+    /// Compact and truncate in one call. See [`Cartridge::vacuum`].
+    pub fn vacuum(&self) -> Result<VacuumReport> {
+        self.inner.lock().vacuum()
+    }
+
+    /// Move up to `budget_blocks` blocks. See [`Cartridge::defragment`].
+    pub fn defragment(&self, budget_blocks: usize) -> Result<DefragReport> {
+        self.inner.lock().defragment(budget_blocks)
+    }
+
     /// Run a health check. Returns warning messages (empty = healthy).
     pub fn health_check(&self) -> Vec<String> {
         self.inner.lock().health_check()
     }
 }
 
+#[cfg(not(feature = "no-fs"))]
 impl Drop for CartridgeDatabase {
     fn drop(&mut self) {
         // Best-effort flush and VFS cleanup
@@ -964,6 +3048,276 @@ impl Drop for CartridgeDatabase {
     }
 }
 
+#[cfg(not(feature = "no-fs"))]
+/// Open `db_path` through the registered cartridge VFS `vfs_name`
+fn open_vfs_connection(
+    vfs_name: &str,
+    db_path: &str,
+    read_only: bool,
+) -> Result<rusqlite::Connection> {
+    use rusqlite::OpenFlags;
+
+    // Characters with a meaning in SQLite URIs
+    let path = db_path.replace('%', "%25").replace('?', "%3f").replace('#', "%23");
+    let uri = format!("file:{path}?vfs={vfs_name}");
+    let flags = match read_only {
+        true => OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+        false => {
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+                | OpenFlags::SQLITE_OPEN_CREATE
+                | OpenFlags::SQLITE_OPEN_URI
+        }
+    };
+    rusqlite::Connection::open_with_flags(&uri, flags).map_err(|e| {
+        CartridgeError::Sqlite(format!("open via cartridge VFS failed: {e}"))
+    })
+}
+
+/// A SQLite connection to a database inside a [`Cartridge`]
+///
+/// Returned by [`Cartridge::open_database`]; derefs to
+/// [`rusqlite::Connection`]. Dropping it closes the connection, unregisters
+/// the VFS, flushes and gives the cartridge back. Use
+/// [`close`](Self::close) to see errors from those steps.
+#[cfg(all(feature = "sqlite", not(feature = "no-fs")))]
+pub struct CartridgeConnection<'a> {
+    conn: Option<rusqlite::Connection>,
+    shared: Option<Arc<parking_lot::Mutex<CoreCartridge>>>,
+    vfs_name: Option<String>,
+    cartridge: &'a mut Cartridge,
+}
+
+#[cfg(all(feature = "sqlite", not(feature = "no-fs")))]
+impl CartridgeConnection<'_> {
+    /// Close the connection and flush the cartridge
+    pub fn close(mut self) -> Result<()> {
+        self.finish()
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        // Closing the connection closes its VFS files, which hold the
+        // remaining references to the shared cartridge
+        let closed = match self.conn.take() {
+            Some(conn) => conn
+                .close()
+                .map_err(|(_, e)| CartridgeError::Sqlite(format!("close failed: {e}"))),
+            None => Ok(()),
+        };
+        let unregistered = match self.vfs_name.take() {
+            Some(name) => crate::core::vfs::unregister_named_vfs(&name),
+            None => Ok(()),
+        };
+        let Some(shared) = self.shared.take() else {
+            return Ok(());
+        };
+        let core = match Arc::try_unwrap(shared) {
+            Ok(mutex) => mutex.into_inner(),
+            Err(shared) => {
+                // SQLite kept the VFS; save what was written at least
+                shared.lock().flush()?;
+                return Err(CartridgeError::Sqlite(
+                    "cartridge is still in use by SQLite".to_string(),
+                ));
+            }
+        };
+        self.cartridge.inner = core;
+        self.cartridge.vfs_name = None;
+
+        closed?;
+        unregistered?;
+        self.cartridge.inner.flush()
+    }
+}
+
+#[cfg(all(feature = "sqlite", not(feature = "no-fs")))]
+impl std::ops::Deref for CartridgeConnection<'_> {
+    type Target = rusqlite::Connection;
+
+    fn deref(&self) -> &rusqlite::Connection {
+        self.conn.as_ref().expect("connection is open until dropped")
+    }
+}
+
+#[cfg(all(feature = "sqlite", not(feature = "no-fs")))]
+impl std::ops::DerefMut for CartridgeConnection<'_> {
+    fn deref_mut(&mut self) -> &mut rusqlite::Connection {
+        self.conn.as_mut().expect("connection is open until dropped")
+    }
+}
+
+#[cfg(all(feature = "sqlite", not(feature = "no-fs")))]
+impl Drop for CartridgeConnection<'_> {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            tracing::warn!("Failed to close cartridge database: {}", e);
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// SharedCartridge — cloneable, thread-safe handle
+// ---------------------------------------------------------------------------
+
+/// A cloneable, thread-safe handle to a [`Cartridge`]
+///
+/// Created by [`Cartridge::into_shared`]. Every method takes `&self`: reads
+/// take a shared lock and run concurrently, while writes, deletes and
+/// flushes take an exclusive one. Clones share the same cartridge, and the
+/// cartridge is flushed and closed when the last handle is dropped.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use cartridge_rs::Cartridge;
+/// let shared = Cartridge::create("my-data", "My Data")?.into_shared();
+///
+/// let writer = shared.clone();
+/// std::thread::spawn(move || writer.write("from-thread.txt", b"hello"))
+///     .join()
+///     .unwrap()?;
+///
+/// assert_eq!(shared.read("from-thread.txt")?, b"hello");
+/// shared.flush()?;
+/// # Ok::<(), cartridge_rs::CartridgeError>(())
+/// ```
+#[derive(Clone)]
+pub struct SharedCartridge {
+    inner: Arc<parking_lot::RwLock<Cartridge>>,
+}
+
+impl SharedCartridge {
+    /// Write a file, creating it if it doesn't exist
+    pub fn write<P: AsRef<str>>(&self, path: P, content: &[u8]) -> Result<()> {
+        self.inner.write().write(path, content)
+    }
+
+    /// Write a file all-or-nothing. See [`Cartridge::write_atomic`].
+    pub fn write_atomic<P: AsRef<str>>(&self, path: P, content: &[u8]) -> Result<()> {
+        self.inner.write().write_atomic(path, content)
+    }
+
+    /// Copy a file by sharing its blocks; see [`Cartridge::clone_file`]
+    pub fn clone_file<P: AsRef<str>, Q: AsRef<str>>(&self, src: P, dst: Q) -> Result<()> {
+        self.inner.write().clone_file(src, dst)
+    }
+
+    /// Read a file's contents
+    pub fn read<P: AsRef<str>>(&self, path: P) -> Result<Vec<u8>> {
+        self.inner.read().read(path)
+    }
+
+    /// Read up to `len` bytes of a file starting at `offset`
+    pub fn read_range<P: AsRef<str>>(&self, path: P, offset: u64, len: u64) -> Result<Vec<u8>> {
+        self.inner.read().read_range(path, offset, len)
+    }
+
+    /// Open a file for streaming reads
+    ///
+    /// The reader holds a handle rather than the lock: each read takes the
+    /// read lock for one [`STREAM_CHUNK_SIZE`] range, so writers aren't
+    /// blocked for the whole stream. A file changed mid-stream reads as a
+    /// mix of old and new content.
+    pub fn open_reader<P: AsRef<str>>(&self, path: P) -> Result<SharedReader> {
+        let (path, len) = {
+            let cart = self.inner.read();
+            let reader = cart.open_reader(path)?;
+            (reader.path().to_string(), reader.len())
+        };
+        Ok(SharedReader {
+            cartridge: self.clone(),
+            path,
+            len,
+            position: 0,
+        })
+    }
+
+    /// Write a file from a reader, holding the write lock throughout
+    pub fn write_stream<P: AsRef<str>>(&self, path: P, reader: &mut dyn Read) -> Result<u64> {
+        self.inner.write().write_stream(path, reader)
+    }
+
+    /// Delete a file
+    pub fn delete<P: AsRef<str>>(&self, path: P) -> Result<()> {
+        self.inner.write().delete(path)
+    }
+
+    /// List entries under a prefix. See [`Cartridge::list_entries`].
+    pub fn list_entries<P: AsRef<str>>(&self, prefix: P) -> Result<Vec<Entry>> {
+        self.inner.read().list_entries(prefix)
+    }
+
+    /// List entries with directory totals. See [`Cartridge::list_entries_with_sizes`].
+    pub fn list_entries_with_sizes<P: AsRef<str>>(&self, prefix: P) -> Result<Vec<Entry>> {
+        self.inner.read().list_entries_with_sizes(prefix)
+    }
+
+    /// List the immediate children of a directory. See [`Cartridge::list_children`].
+    pub fn list_children<P: AsRef<str>>(&self, parent: P) -> Result<Vec<Entry>> {
+        self.inner.read().list_children(parent)
+    }
+
+    /// Check if a path exists
+    pub fn exists<P: AsRef<str>>(&self, path: P) -> Result<bool> {
+        self.inner.read().exists(path)
+    }
+
+    /// Check if a path is a directory
+    pub fn is_dir<P: AsRef<str>>(&self, path: P) -> Result<bool> {
+        self.inner.read().is_dir(path)
+    }
+
+    /// What a path is, or `None` if missing. See [`Cartridge::path_type`].
+    pub fn path_type<P: AsRef<str>>(&self, path: P) -> Result<Option<FileType>> {
+        self.inner.read().path_type(path)
+    }
+
+    /// Get metadata for a path
+    pub fn metadata<P: AsRef<str>>(&self, path: P) -> Result<FileMetadata> {
+        self.inner.read().metadata(path)
+    }
+
+    /// Flush pending changes to disk
+    pub fn flush(&self) -> Result<()> {
+        self.inner.write().flush()
+    }
+
+    /// Verify the next slice of a scrub pass. See [`Cartridge::scrub`].
+    ///
+    /// Holds the read lock for one call, so keep `max_pages` small enough
+    /// that writers don't wait long.
+    pub fn scrub(&self, start: Option<ScrubToken>, max_pages: usize) -> Result<ScrubResult> {
+        self.inner.read().scrub(start, max_pages)
+    }
+
+    /// Subscribe to changes. See [`Cartridge::watch`].
+    pub fn watch(&self, prefix: &str) -> Receiver<ChangeEvent> {
+        self.inner.read().watch(prefix)
+    }
+
+    /// Run `f` with shared access to the cartridge
+    ///
+    /// For read-only operations that have no `SharedCartridge` method of
+    /// their own. Other readers can run at the same time.
+    pub fn with_read<T>(&self, f: impl FnOnce(&Cartridge) -> T) -> T {
+        f(&self.inner.read())
+    }
+
+    /// Run `f` with exclusive access to the cartridge
+    ///
+    /// Use this for operations without a `SharedCartridge` method of their
+    /// own (batches, vacuum, freeze, ...).
+    pub fn with_write<T>(&self, f: impl FnOnce(&mut Cartridge) -> T) -> T {
+        f(&mut self.inner.write())
+    }
+
+    /// Get the cartridge back if this is the only handle left
+    pub fn try_unwrap(self) -> std::result::Result<Cartridge, Self> {
+        Arc::try_unwrap(self.inner)
+            .map(parking_lot::RwLock::into_inner)
+            .map_err(|inner| SharedCartridge { inner })
+    }
+}
+
 /// Builder for customizing Cartridge creation
 ///
 /// Provides a fluent API for configuring advanced options.
@@ -988,6 +3342,18 @@ pub struct CartridgeBuilder {
     slug: Option<String>,
     title: Option<String>,
     enable_audit: bool,
+    passphrase: Option<String>,
+    page_checksums: bool,
+    max_metadata_bytes: Option<usize>,
+    max_size_bytes: Option<u64>,
+    inline_threshold: Option<usize>,
+    dedup: bool,
+    initial_blocks: Option<usize>,
+    growth: Option<GrowthPolicy>,
+    auto_grow: bool,
+    case_sensitivity: CaseSensitivity,
+    sync_policy: SyncPolicy,
+    page_size: PageSize,
 }
 
 impl CartridgeBuilder {
@@ -998,68 +3364,280 @@ impl CartridgeBuilder {
             slug: None,
             title: None,
             enable_audit: false,
+            passphrase: None,
+            page_checksums: true,
+            max_metadata_bytes: None,
+            max_size_bytes: None,
+            inline_threshold: None,
+            dedup: false,
+            initial_blocks: None,
+            growth: None,
+            auto_grow: true,
+            case_sensitivity: CaseSensitivity::Exact,
+            sync_policy: SyncPolicy::Always,
+            page_size: PageSize::Kb4,
         }
     }
 
-    /// Set the slug (kebab-case identifier)
-    pub fn slug<S: Into<String>>(mut self, slug: S) -> Self {
-        self.slug = Some(slug.into());
+    /// Set the slug (kebab-case identifier)
+    pub fn slug<S: Into<String>>(mut self, slug: S) -> Self {
+        self.slug = Some(slug.into());
+        self
+    }
+
+    /// Set the title (human-readable display name)
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Set a custom path (optional, defaults to slug in current directory)
+    pub fn path<P: Into<String>>(mut self, path: P) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Enable audit logging for all operations
+    ///
+    /// Entries, with the paths they were logged for, are persisted on
+    /// [`Cartridge::flush`] and read back with [`Cartridge::query_audit`].
+    pub fn with_audit_logging(mut self) -> Self {
+        self.enable_audit = true;
+        self
+    }
+
+    /// Encrypt the whole archive at rest with a passphrase-derived key
+    ///
+    /// Every page except the header is encrypted with AES-256-GCM. Reopen
+    /// the archive with [`Cartridge::open_encrypted`].
+    pub fn with_encryption<S: Into<String>>(mut self, passphrase: S) -> Self {
+        self.passphrase = Some(passphrase.into());
+        self
+    }
+
+    /// Enable or disable per-page CRC32 checksums (enabled by default)
+    ///
+    /// Checksums are verified on every read; see [`Cartridge::verify`].
+    pub fn page_checksums(mut self, enabled: bool) -> Self {
+        self.page_checksums = enabled;
+        self
+    }
+
+    /// Cap each file's user metadata at `bytes` (default: 2048)
+    pub fn max_metadata_bytes(mut self, bytes: usize) -> Self {
+        self.max_metadata_bytes = Some(bytes);
+        self
+    }
+
+    /// Store files of at most `bytes` in the catalog (default: 512, 0 disables)
+    pub fn inline_threshold(mut self, bytes: usize) -> Self {
+        self.inline_threshold = Some(bytes);
+        self
+    }
+
+    /// Deduplicate identical content pages (default: false)
+    ///
+    /// Worth it for many near-identical files; every page written is hashed.
+    pub fn dedup(mut self, enabled: bool) -> Self {
+        self.dedup = enabled;
+        self
+    }
+
+    /// Start the file at `blocks` 4KB blocks, including the 3 reserved ones
+    /// (default: 3)
+    ///
+    /// Size this for a known import to skip the growth steps along the way.
+    pub fn initial_blocks(mut self, blocks: usize) -> Self {
+        self.initial_blocks = Some(blocks);
+        self
+    }
+
+    /// Multiply the block count by `factor` each time the container fills
+    /// (default: 2.0)
+    pub fn growth_factor(mut self, factor: f64) -> Self {
+        self.growth = Some(GrowthPolicy::Factor(factor));
+        self
+    }
+
+    /// Add `blocks` each time the container fills instead of multiplying
+    ///
+    /// Linear growth avoids large doublings on flash storage.
+    pub fn growth_increment_blocks(mut self, blocks: usize) -> Self {
+        self.growth = Some(GrowthPolicy::Linear(blocks));
+        self
+    }
+
+    /// Grow automatically when full (default: true)
+    ///
+    /// With growth off, writes that don't fit fail with
+    /// [`CartridgeError::OutOfSpace`]; [`Cartridge::reserve`] still extends
+    /// the file explicitly.
+    pub fn auto_grow(mut self, enabled: bool) -> Self {
+        self.auto_grow = enabled;
         self
     }
 
-    /// Set the title (human-readable display name)
-    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
-        self.title = Some(title.into());
+    /// Size of every page (default: [`PageSize::Kb4`])
+    ///
+    /// Bigger pages suit cartridges of large media files: fewer blocks to
+    /// track and longer sequential runs, at the cost of more slack at the
+    /// end of each small file. Recorded in the header and fixed for the
+    /// cartridge's life; [`open_or_create`](Self::open_or_create) keeps an
+    /// existing cartridge's size.
+    pub fn page_size(mut self, size: PageSize) -> Self {
+        self.page_size = size;
         self
     }
 
-    /// Set a custom path (optional, defaults to slug in current directory)
-    pub fn path<P: Into<String>>(mut self, path: P) -> Self {
-        self.path = Some(path.into());
+    /// When flushes fsync (default: [`SyncPolicy::Always`])
+    ///
+    /// [`SyncPolicy::OnClose`] and [`SyncPolicy::Periodic`] spare flash
+    /// storage at the risk of losing recent flushes on power loss.
+    pub fn sync_policy(mut self, policy: SyncPolicy) -> Self {
+        self.sync_policy = policy;
         self
     }
 
-    /// Enable audit logging for all operations
-    pub fn with_audit_logging(mut self) -> Self {
-        self.enable_audit = true;
+    /// Look paths up regardless of case (default: exact)
+    ///
+    /// `Logo.PNG` can then be read, checked, deleted or overwritten as
+    /// `logo.png`; listings keep the case it was created with, and creating
+    /// `logo.png` next to it fails with [`CartridgeError::AlreadyExists`].
+    /// Recorded in the header, so it can't be changed later; see
+    /// [`Cartridge::open_with_case`].
+    pub fn case_insensitive(mut self) -> Self {
+        self.case_sensitivity = CaseSensitivity::Insensitive;
+        self
+    }
+
+    /// Never let auto-growth take the container past `bytes`
+    /// (default: about 40GB)
+    ///
+    /// Writes that would need more space fail with
+    /// [`CartridgeError::OutOfSpace`] before the container grows at all;
+    /// growth the disk can't hold fails with
+    /// [`CartridgeError::InsufficientDiskSpace`] instead. The cap isn't stored in the file;
+    /// call [`Cartridge::set_max_size_bytes`] again after reopening.
+    pub fn max_size_bytes(mut self, bytes: u64) -> Self {
+        self.max_size_bytes = Some(bytes);
         self
     }
 
     /// Build the Cartridge instance
+    #[cfg(not(feature = "no-fs"))]
     pub fn build(self) -> Result<Cartridge> {
-        let slug = self.slug.ok_or_else(|| {
+        let (path, slug, title) = self.names()?;
+        info!("Building cartridge with slug '{}', title '{}'", slug, title);
+
+        let inner =
+            CoreCartridge::create_with_options(&path, &slug, &title, &self.create_options())?;
+        Ok(self.configure(inner))
+    }
+
+    /// Open the cartridge if it exists, otherwise build it
+    ///
+    /// The runtime options (audit logging, size cap, metadata cap, inline
+    /// threshold, dedup, growth) are applied either way, and the passphrase
+    /// unlocks an existing encrypted cartridge. Options recorded in the file
+    /// (checksums, case sensitivity, initial size) only apply when it is
+    /// created. See [`Cartridge::open_or_create_at`] for the slug check and
+    /// how concurrent callers are handled.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use cartridge_rs::CartridgeBuilder;
+    ///
+    /// # fn main() -> cartridge_rs::Result<()> {
+    /// let cart = CartridgeBuilder::new()
+    ///     .slug("events")
+    ///     .title("Events")
+    ///     .path("/data/events")
+    ///     .with_audit_logging()
+    ///     .open_or_create()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no-fs"))]
+    pub fn open_or_create(self) -> Result<Cartridge> {
+        let (path, slug, title) = self.names()?;
+        info!("Opening or building cartridge with slug '{}', title '{}'", slug, title);
+
+        let options = self.create_options();
+        let inner = CoreCartridge::open_or_create_with_options(&path, &slug, &title, &options)?;
+        Ok(self.configure(inner))
+    }
+
+    /// Path, slug and title, failing if the slug or title wasn't set
+    #[cfg(not(feature = "no-fs"))]
+    fn names(&self) -> Result<(String, String, String)> {
+        let slug = self.slug.clone().ok_or_else(|| {
             CartridgeError::Io(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
                 "slug must be set",
             ))
         })?;
 
-        let title = self.title.ok_or_else(|| {
+        let title = self.title.clone().ok_or_else(|| {
             CartridgeError::Io(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
                 "title must be set",
             ))
         })?;
 
-        info!("Building cartridge with slug '{}', title '{}'", slug, title);
+        let path = self.path.clone().unwrap_or_else(|| slug.clone());
+        Ok((path, slug, title))
+    }
 
-        let mut inner = if let Some(path) = self.path {
-            CoreCartridge::create_at(&path, &slug, &title)?
-        } else {
-            CoreCartridge::create(&slug, &title)?
+    #[cfg(not(feature = "no-fs"))]
+    fn create_options(&self) -> CreateOptions {
+        let mut options = CreateOptions {
+            passphrase: self.passphrase.clone(),
+            page_checksums: self.page_checksums,
+            auto_grow: self.auto_grow,
+            case_sensitivity: self.case_sensitivity,
+            page_size: self.page_size,
+            ..Default::default()
         };
+        if let Some(blocks) = self.initial_blocks {
+            options.initial_blocks = blocks;
+        }
+        if let Some(growth) = self.growth {
+            options.growth = growth;
+        }
+        options
+    }
 
+    /// Apply the options that live in the handle rather than the file
+    #[cfg(not(feature = "no-fs"))]
+    fn configure(self, mut inner: CoreCartridge) -> Cartridge {
         if self.enable_audit {
             use crate::core::audit::AuditLogger;
             use std::sync::Arc;
             use std::time::Duration;
 
-            let logger = Arc::new(AuditLogger::new(1000, Duration::from_secs(60)));
+            let logger =
+                Arc::new(AuditLogger::new(1000, Duration::from_secs(60)).with_path_recording());
             inner.set_audit_logger(logger);
             debug!("Audit logging enabled");
         }
 
-        Ok(Cartridge { inner, vfs_name: None })
+        if let Some(bytes) = self.max_metadata_bytes {
+            inner.set_max_user_metadata_bytes(bytes);
+        }
+
+        if let Some(bytes) = self.max_size_bytes {
+            inner.set_max_size_bytes(bytes);
+        }
+
+        if let Some(bytes) = self.inline_threshold {
+            inner.set_inline_threshold(bytes);
+        }
+
+        inner.set_dedup(self.dedup);
+        inner.set_sync_policy(self.sync_policy);
+
+        Cartridge { inner, vfs_name: None }
     }
 }
 
@@ -1069,13 +3647,60 @@ impl Default for CartridgeBuilder {
     }
 }
 
+/// A file opened with [`SharedCartridge::open_reader`]
+pub struct SharedReader {
+    cartridge: SharedCartridge,
+    path: String,
+    len: u64,
+    position: u64,
+}
+
+impl SharedReader {
+    /// The file's length when the reader was opened
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Whether the file was empty when the reader was opened
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Read for SharedReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = (buf.len() as u64).min(STREAM_CHUNK_SIZE as u64);
+        let chunk = self
+            .cartridge
+            .read_range(&self.path, self.position, len)
+            .map_err(std::io::Error::other)?;
+        buf[..chunk.len()].copy_from_slice(&chunk);
+        self.position += chunk.len() as u64;
+        Ok(chunk.len())
+    }
+}
+
+impl std::io::Seek for SharedReader {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        let position = match pos {
+            std::io::SeekFrom::Start(offset) => Some(offset),
+            std::io::SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            std::io::SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        };
+        self.position = position.ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "seek before start of file")
+        })?;
+        Ok(self.position)
+    }
+}
+
 /// Virtual Filesystem trait for unified storage interface
 ///
 /// Provides a common interface that can be implemented by different storage backends:
 /// - Cartridge (mutable containers)
 /// - Engram (immutable archives)
 /// - ZipVfs, TarVfs (other archive formats)
-/// - S3Vfs, LocalVfs (remote/local filesystems)
+/// - [`LocalVfs`] (a directory on the host filesystem), S3Vfs
 ///
 /// This allows applications to work with any storage backend using the same API.
 ///
@@ -1138,6 +3763,44 @@ pub trait Vfs {
 
     /// Get metadata for a path
     fn metadata(&self, path: &str) -> Result<FileMetadata>;
+
+    /// Whether `write` and `delete` are rejected
+    ///
+    /// Read-only backends fail every mutation with
+    /// [`CartridgeError::ReadOnly`].
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    /// Read up to `len` bytes of a file starting at `offset`
+    ///
+    /// The result is short when the range runs past the end of the file.
+    /// The default reads the whole file and slices it; backends that can
+    /// read part of a file override it.
+    fn read_range(&self, path: &str, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let content = self.read(path)?;
+        let start = usize::try_from(offset).unwrap_or(usize::MAX).min(content.len());
+        let end = start.saturating_add(usize::try_from(len).unwrap_or(usize::MAX));
+        Ok(content[start..end.min(content.len())].to_vec())
+    }
+
+    /// Open a file for streaming reads
+    ///
+    /// The default reads the whole file up front.
+    fn open_reader(&self, path: &str) -> Result<Box<dyn Read + Send + '_>> {
+        Ok(Box::new(std::io::Cursor::new(self.read(path)?)))
+    }
+
+    /// Write a file from a reader, creating it or replacing its content
+    ///
+    /// Returns the number of bytes written. The default buffers the whole
+    /// stream and calls [`write`](Vfs::write).
+    fn write_stream(&mut self, path: &str, reader: &mut dyn Read) -> Result<u64> {
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content)?;
+        self.write(path, &content)?;
+        Ok(content.len() as u64)
+    }
 }
 
 /// Implement VFS trait for Cartridge
@@ -1173,12 +3836,188 @@ impl Vfs for Cartridge {
     fn metadata(&self, path: &str) -> Result<FileMetadata> {
         self.metadata(path)
     }
+
+    fn is_read_only(&self) -> bool {
+        self.is_read_only()
+    }
+
+    fn read_range(&self, path: &str, offset: u64, len: u64) -> Result<Vec<u8>> {
+        self.read_range(path, offset, len)
+    }
+
+    fn open_reader(&self, path: &str) -> Result<Box<dyn Read + Send + '_>> {
+        Ok(Box::new(self.open_reader(path)?))
+    }
+
+    fn write_stream(&mut self, path: &str, reader: &mut dyn Read) -> Result<u64> {
+        self.write_stream(path, reader)
+    }
+}
+
+/// Implement VFS trait for SharedCartridge (mutations only need a shared handle)
+impl Vfs for SharedCartridge {
+    fn list_entries(&self, prefix: &str) -> Result<Vec<Entry>> {
+        SharedCartridge::list_entries(self, prefix)
+    }
+
+    fn list_children(&self, parent: &str) -> Result<Vec<Entry>> {
+        SharedCartridge::list_children(self, parent)
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        SharedCartridge::read(self, path)
+    }
+
+    fn write(&mut self, path: &str, data: &[u8]) -> Result<()> {
+        SharedCartridge::write(self, path, data)
+    }
+
+    fn delete(&mut self, path: &str) -> Result<()> {
+        SharedCartridge::delete(self, path)
+    }
+
+    fn exists(&self, path: &str) -> Result<bool> {
+        SharedCartridge::exists(self, path)
+    }
+
+    fn is_dir(&self, path: &str) -> Result<bool> {
+        SharedCartridge::is_dir(self, path)
+    }
+
+    fn metadata(&self, path: &str) -> Result<FileMetadata> {
+        SharedCartridge::metadata(self, path)
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.inner.read().is_read_only()
+    }
+
+    fn read_range(&self, path: &str, offset: u64, len: u64) -> Result<Vec<u8>> {
+        SharedCartridge::read_range(self, path, offset, len)
+    }
+
+    fn open_reader(&self, path: &str) -> Result<Box<dyn Read + Send + '_>> {
+        Ok(Box::new(SharedCartridge::open_reader(self, path)?))
+    }
+
+    fn write_stream(&mut self, path: &str, reader: &mut dyn Read) -> Result<u64> {
+        SharedCartridge::write_stream(self, path, reader)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_shared_cartridge_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync + Clone>() {}
+        assert_send_sync::<SharedCartridge>();
+    }
+
+    #[test]
+    fn test_shared_cartridge_try_unwrap() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let shared =
+            Cartridge::create_at(temp_dir.path().join("shared"), "shared", "Shared")?.into_shared();
+
+        let other = shared.clone();
+        other.write("a.txt", b"a")?;
+        let Err(shared) = shared.try_unwrap() else {
+            panic!("try_unwrap succeeded with another handle alive");
+        };
+        drop(other);
+
+        let cart = shared.try_unwrap().ok().unwrap();
+        assert_eq!(cart.read("a.txt")?, b"a");
+        Ok(())
+    }
+
+    #[test]
+    fn test_content_type_round_trip() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("types");
+
+        {
+            let mut cart = Cartridge::create_at(&path, "types", "Types")?;
+            cart.write("docs/readme.md", b"# hi")?;
+            cart.write("blob", b"no extension")?;
+            cart.write_with_type("events/latest.bin", b"{}", "application/json")?;
+
+            // An explicit type survives a plain overwrite
+            cart.write("events/latest.bin", b"{\"v\":2}")?;
+            cart.flush()?;
+        }
+
+        let cart = Cartridge::open(path.with_extension("cart"))?;
+        assert_eq!(
+            cart.metadata("docs/readme.md")?.content_type.as_deref(),
+            Some("text/markdown")
+        );
+        assert_eq!(cart.metadata("blob")?.content_type, None);
+        assert_eq!(
+            cart.metadata("events/latest.bin")?.content_type.as_deref(),
+            Some("application/json")
+        );
+
+        let entries = cart.list_entries("docs")?;
+        let readme = entries.iter().find(|e| e.name == "readme.md").unwrap();
+        assert_eq!(readme.content_type.as_deref(), Some("text/markdown"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_user_metadata_round_trip() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("meta");
+
+        {
+            let mut cart = CartridgeBuilder::new()
+                .slug("meta")
+                .title("Meta")
+                .path(path.to_str().unwrap())
+                .max_metadata_bytes(64)
+                .build()?;
+            cart.write("doc.txt", b"content")?;
+            cart.set_metadata(
+                "doc.txt",
+                HashMap::from([
+                    ("author".to_string(), "Alice".to_string()),
+                    ("stage".to_string(), "draft".to_string()),
+                ]),
+            )?;
+
+            // Rejected updates change nothing
+            let bad_key = HashMap::from([
+                ("stage".to_string(), "final".to_string()),
+                ("bad\nkey".to_string(), "x".to_string()),
+            ]);
+            assert!(matches!(
+                cart.set_metadata("doc.txt", bad_key),
+                Err(CartridgeError::InvalidMetadata(_))
+            ));
+            let too_big = HashMap::from([("notes".to_string(), "x".repeat(64))]);
+            assert!(matches!(
+                cart.set_metadata("doc.txt", too_big),
+                Err(CartridgeError::InvalidMetadata(_))
+            ));
+            assert_eq!(cart.get_metadata_keys("doc.txt")?["stage"], "draft");
+
+            assert_eq!(
+                cart.remove_metadata_key("doc.txt", "stage")?,
+                Some("draft".to_string())
+            );
+            assert_eq!(cart.remove_metadata_key("doc.txt", "stage")?, None);
+            cart.flush()?;
+        }
+
+        let cart = Cartridge::open(path.with_extension("cart"))?;
+        let metadata = cart.get_metadata_keys("doc.txt")?;
+        assert_eq!(metadata.len(), 1);
+        assert_eq!(metadata["author"], "Alice");
+        Ok(())
+    }
+
     #[test]
     fn test_create_and_write() -> Result<()> {
         let temp_dir = tempfile::TempDir::new().unwrap();
@@ -1211,6 +4050,129 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_builder_sync_policy() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("flash");
+
+        let mut cart = CartridgeBuilder::new()
+            .slug("flash")
+            .title("Flash")
+            .path(path.to_str().unwrap())
+            .sync_policy(SyncPolicy::OnClose)
+            .build()?;
+        assert_eq!(cart.sync_policy(), SyncPolicy::OnClose);
+        cart.write("log.txt", b"entry")?;
+        cart.flush()?;
+        cart.set_sync_policy(SyncPolicy::Always);
+        cart.close()?;
+
+        let cart = Cartridge::open(&path)?;
+        assert_eq!(cart.read("log.txt")?, b"entry");
+        assert_eq!(cart.sync_policy(), SyncPolicy::Always);
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_or_create_reopens() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("service");
+
+        let builder = || {
+            CartridgeBuilder::new()
+                .slug("service")
+                .title("Service")
+                .path(path.to_str().unwrap())
+                .with_audit_logging()
+                .max_metadata_bytes(64)
+        };
+
+        let mut cart = builder().open_or_create()?;
+        cart.write("state.json", b"{}")?;
+        drop(cart);
+
+        let mut cart = builder().open_or_create()?;
+        assert_eq!(cart.read("state.json")?, b"{}");
+        let too_big = HashMap::from([("note".to_string(), "x".repeat(100))]);
+        assert!(cart.set_metadata("state.json", too_big).is_err());
+        cart.write("more.txt", b"more")?;
+        assert!(!cart.query_audit(&AuditFilter::default())?.is_empty());
+        drop(cart);
+
+        let cart = Cartridge::open_or_create_at(&path, "service", "Other Title")?;
+        assert_eq!(cart.read("more.txt")?, b"more");
+        assert_eq!(cart.title()?, "Service");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_or_create_slug_mismatch() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("first");
+
+        let mut cart = Cartridge::open_or_create_at(&path, "first", "First")?;
+        cart.write("keep.txt", b"keep")?;
+        drop(cart);
+
+        let result = Cartridge::open_or_create_at(&path, "second", "Second");
+        match result {
+            Err(CartridgeError::SlugMismatch { expected, found }) => {
+                assert_eq!(expected, "second");
+                assert_eq!(found, "first");
+            }
+            other => panic!("expected SlugMismatch, got {:?}", other.map(|_| ())),
+        }
+
+        let cart = Cartridge::open(&path)?;
+        assert_eq!(cart.read("keep.txt")?, b"keep");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_and_string_helpers() -> Result<()> {
+        let mut cart = Cartridge::in_memory("typed", "Typed")?;
+
+        let value = serde_json::json!({"name": "cart", "pages": [1, 2, 3]});
+        cart.write_json("state", &value)?;
+        assert_eq!(cart.read_json::<_, serde_json::Value>("state")?, value);
+        assert_eq!(cart.read("state")?, serde_json::to_vec(&value).unwrap());
+        let meta = cart.metadata("state")?;
+        assert_eq!(meta.content_type.as_deref(), Some("application/json"));
+
+        cart.write_json_pretty("pretty.json", &value)?;
+        assert!(cart.read_string("pretty.json")?.contains("\n  "));
+
+        cart.write_string("notes.txt", "héllo")?;
+        assert_eq!(cart.read_string("notes.txt")?, "héllo");
+        let meta = cart.metadata("notes.txt")?;
+        assert_eq!(meta.content_type.as_deref(), Some("text/plain; charset=utf-8"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_typed_read_errors_name_the_file() -> Result<()> {
+        let mut cart = Cartridge::in_memory("typed", "Typed")?;
+        cart.write("broken.json", b"{\"name\": ")?;
+        cart.write("binary", &[b'o', b'k', 0xff, 0xfe])?;
+
+        match cart.read_json::<_, serde_json::Value>("broken.json") {
+            Err(CartridgeError::InvalidJson { path, .. }) => assert_eq!(path, "broken.json"),
+            other => panic!("expected InvalidJson, got {:?}", other),
+        }
+        match cart.read_string("binary") {
+            Err(CartridgeError::InvalidUtf8 { path, valid_up_to }) => {
+                assert_eq!(path, "binary");
+                assert_eq!(valid_up_to, 2);
+            }
+            other => panic!("expected InvalidUtf8, got {:?}", other),
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_list_entries_flat_structure() -> Result<()> {
         let temp_dir = tempfile::TempDir::new().unwrap();
@@ -1427,6 +4389,33 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_write_atomic_creates_and_replaces() -> Result<()> {
+        let mut cart = Cartridge::in_memory("atomic", "Atomic")?;
+        cart.write_atomic("config/settings.json", b"{}")?;
+        cart.write_atomic("config/settings.json", br#"{"theme":"dark"}"#)?;
+
+        assert_eq!(cart.read("config/settings.json")?, br#"{"theme":"dark"}"#);
+        let meta = cart.metadata("config/settings.json")?;
+        assert_eq!(meta.content_type.as_deref(), Some("application/json"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_dir_empty_created_directory() -> Result<()> {
+        let mut cart = Cartridge::in_memory("empty-dir", "Empty Dir")?;
+        cart.create_dir("uploads")?;
+        cart.write("notes.txt", b"n")?;
+
+        assert!(cart.is_dir("uploads")?);
+        assert!(cart.list_children("uploads")?.is_empty());
+        assert!(!cart.is_dir("notes.txt")?);
+        assert_eq!(cart.path_type("uploads")?, Some(FileType::Directory));
+        assert_eq!(cart.path_type("notes.txt")?, Some(FileType::File));
+        assert_eq!(cart.path_type("missing")?, None);
+        Ok(())
+    }
+
     #[test]
     fn test_entry_metadata_fields() -> Result<()> {
         let temp_dir = tempfile::TempDir::new().unwrap();
@@ -1456,4 +4445,298 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_inline_entry_uses_no_blocks() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("inline-cart");
+        let mut cart = Cartridge::create_at(&path, "inline-cart", "Inline")?;
+        cart.write("flag.txt", b"on")?;
+        cart.write("page.bin", &[0u8; 1024])?;
+
+        let entries = cart.list_entries("")?;
+        let sizes: Vec<_> = entries
+            .iter()
+            .map(|e| (e.name.as_str(), e.size, e.compressed_size))
+            .collect();
+        assert!(sizes.contains(&("flag.txt", Some(2), Some(0))));
+        assert!(sizes.contains(&("page.bin", Some(1024), Some(PAGE_SIZE as u64))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only_rejects_every_mutation() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("ro-cart");
+        let snapshots = temp_dir.path().join("snapshots");
+        let mut writer = Cartridge::create_at(&path, "ro-cart", "Read Only")?;
+        writer.write("docs/a.txt", b"alpha")?;
+        writer.flush()?;
+        let snapshot = writer.create_snapshot("s".into(), String::new(), &snapshots)?;
+
+        assert!(!writer.is_read_only());
+        drop(writer);
+
+        let mut cart = Cartridge::open_read_only(&path)?;
+        assert!(cart.is_read_only());
+        assert!(Vfs::is_read_only(&cart));
+
+        let read_only = |r: Result<()>| matches!(r, Err(CartridgeError::ReadOnly));
+        assert!(read_only(cart.write("docs/b.txt", b"beta")));
+        assert!(read_only(cart.write("docs/a.txt", b"changed")));
+        assert!(read_only(cart.delete("docs/a.txt")));
+        assert!(read_only(cart.create_dir("more")));
+        assert!(read_only(cart.symlink("docs/a.txt", "link")));
+        assert!(read_only(cart.update_manifest(|m| m.title = "Changed".into())));
+        let entries = HashMap::from([("k".to_string(), "v".to_string())]);
+        assert!(read_only(cart.set_metadata("docs/a.txt", entries)));
+        assert!(read_only(cart.set_quota("docs", 10)));
+        assert!(read_only(cart.reserve(PAGE_SIZE as u64)));
+        assert!(read_only(cart.restore_snapshot(snapshot, &snapshots)));
+        assert!(read_only(cart.begin_batch().write("batched.txt", b"x")));
+        assert!(read_only(Vfs::write(&mut cart, "vfs.txt", b"x")));
+        assert!(matches!(cart.vacuum(), Err(CartridgeError::ReadOnly)));
+        cart.flush()?;
+
+        // Reads are unaffected
+        assert_eq!(cart.read("docs/a.txt")?, b"alpha");
+        assert_eq!(cart.list("docs")?, vec!["/docs/a.txt".to_string()]);
+        assert_eq!(cart.read_manifest()?.title, "Read Only");
+        drop(cart);
+
+        // Read-write access afterwards works as before
+        let mut cart = Cartridge::open(&path)?;
+        assert!(!cart.is_read_only());
+        cart.write("docs/b.txt", b"beta")?;
+        cart.flush()?;
+        assert_eq!(cart.read("docs/a.txt")?, b"alpha");
+        assert_eq!(cart.read("docs/b.txt")?, b"beta");
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_entries_explicit_directory() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("dirs-cart");
+        let mut cart = Cartridge::create_at(&path, "dirs-cart", "Dirs")?;
+
+        cart.create_dir("docs")?;
+        cart.write("docs/notes/a.txt", b"a")?;
+        cart.write("docs/b.txt", b"bb")?;
+        let created = cart.metadata("docs")?.created_at;
+
+        let entries = cart.list_entries("")?;
+        let dirs: Vec<&Entry> = entries.iter().filter(|e| e.is_dir).collect();
+        assert_eq!(dirs.len(), 2);
+
+        // Explicit directory: listed once, with its catalog timestamps
+        assert_eq!(dirs[0].path, "/docs");
+        assert_eq!(dirs[0].created, Some(created));
+        assert_eq!(dirs[0].file_type, FileType::Directory);
+
+        // Inferred directory: no catalog entry, so no timestamps
+        assert_eq!(dirs[1].path, "/docs/notes");
+        assert_eq!(dirs[1].parent, "/docs");
+        assert_eq!(dirs[1].created, None);
+
+        let b = entries.iter().find(|e| e.path == "/docs/b.txt").unwrap();
+        assert_eq!(b.size, Some(2));
+
+        // Listing below the directory still reports its catalog entry
+        let walked = cart.walk("docs/notes").collect::<Result<Vec<_>>>()?;
+        for entries in [cart.list_entries("docs/notes")?, walked] {
+            let docs = entries.iter().find(|e| e.path == "/docs").unwrap();
+            assert_eq!(docs.created, Some(created));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_entries_with_sizes() -> Result<()> {
+        let mut cart = Cartridge::in_memory("sizes", "Sizes")?;
+        cart.create_dir("a")?;
+        cart.write("a/one.txt", b"1")?;
+        cart.write("a/b/two.txt", b"22")?;
+        cart.write("a/b/c/three.txt", b"333")?;
+        cart.write("a/b/c/four.txt", b"4444")?;
+        cart.write("top.txt", b"55555")?;
+
+        let entries = cart.list_entries_with_sizes("")?;
+        let dir = |path: &str| entries.iter().find(|e| e.path == path).unwrap();
+        assert_eq!(dir("/a").size, Some(1 + 2 + 3 + 4));
+        assert_eq!(dir("/a/b").size, Some(2 + 3 + 4));
+        assert_eq!(dir("/a/b/c").size, Some(3 + 4));
+        assert_eq!(dir("/top.txt").size, Some(5));
+
+        // Inferred directories take their children's latest modification
+        let latest = cart.metadata("a/b/c/four.txt")?.modified_at;
+        assert_eq!(dir("/a/b/c").modified, Some(latest));
+        assert!(dir("/a").modified >= Some(latest));
+
+        // Same entries and order as list_entries
+        let plain = cart.list_entries("")?;
+        let paths = |entries: &[Entry]| entries.iter().map(|e| e.path.clone()).collect::<Vec<_>>();
+        assert_eq!(paths(&entries), paths(&plain));
+        assert!(plain.iter().filter(|e| e.is_dir).all(|e| e.size.is_none()));
+
+        // Under a prefix, only what the listing covers is counted
+        let sub = cart.list_entries_with_sizes("a/b")?;
+        assert_eq!(sub.iter().find(|e| e.path == "/a").unwrap().size, Some(2 + 3 + 4));
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_entries() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("find-cart");
+        let mut cart = Cartridge::create_at(&path, "find-cart", "Find")?;
+
+        cart.write("docs/guide.md", b"guide")?;
+        cart.write("docs/api/Index.MD", b"index")?;
+        cart.write("data.json", b"{}")?;
+
+        let found = cart.find("docs/**/*.md")?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, "/docs/guide.md");
+        assert_eq!(found[0].parent, "/docs");
+        assert_eq!(found[0].size, Some(5));
+
+        let found = cart.find_with_case("docs/**/*.md", CaseSensitivity::Insensitive)?;
+        assert_eq!(found.len(), 2);
+
+        // The manifest lives under .cartridge/ and is never returned
+        assert_eq!(cart.find("**/*.json")?.len(), 1);
+        assert_eq!(cart.find_regex(r"(?i)\.md$")?.len(), 2);
+        assert!(cart.find_regex("[").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_symlink_entries() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("link-cart");
+        let mut cart = Cartridge::create_at(&path, "link-cart", "Links")?;
+
+        cart.write("docs/v2.md", b"two")?;
+        cart.symlink("v2.md", "docs/latest")?;
+        cart.symlink("nowhere.md", "docs/broken")?;
+        cart.flush()?;
+        drop(cart);
+
+        let mut cart = Cartridge::open(&path)?;
+        let entries = cart.list_entries("docs")?;
+        let latest = entries.iter().find(|e| e.name == "latest").unwrap();
+        assert_eq!(latest.file_type, FileType::Symlink);
+        assert!(!latest.is_dir);
+        assert_eq!(
+            cart.metadata_nofollow("docs/latest")?.symlink_target(),
+            Some("v2.md")
+        );
+        assert_eq!(cart.read("docs/latest")?, b"two");
+        assert!(matches!(
+            cart.read_nofollow("docs/latest"),
+            Err(CartridgeError::NotAFile { .. })
+        ));
+
+        // Dangling: metadata of the link works, reading explains why it fails
+        assert!(cart.metadata_nofollow("docs/broken")?.is_symlink());
+        let err = cart.read("docs/broken").unwrap_err();
+        assert!(err.to_string().contains("docs/broken"));
+
+        cart.delete("docs/latest")?;
+        assert!(!cart.exists("docs/latest")?);
+        assert_eq!(cart.read("docs/v2.md")?, b"two");
+        Ok(())
+    }
+
+    #[test]
+    fn test_walk_streams_list_entries() -> Result<()> {
+        let mut cart = Cartridge::in_memory("walk", "Walk")?;
+        cart.write("docs/a.md", b"a")?;
+        cart.write("docs/sub/b.md", b"b")?;
+        cart.write("docs/sub-x.txt", b"x")?;
+        cart.write("docs/sub/deep/c.md", b"c")?;
+        cart.create_dir("empty")?;
+
+        let walked = cart.walk("").collect::<Result<Vec<_>>>()?;
+        let paths: Vec<&str> = walked.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/docs",
+                "/docs/a.md",
+                "/docs/sub-x.txt",
+                "/docs/sub",
+                "/docs/sub/b.md",
+                "/docs/sub/deep",
+                "/docs/sub/deep/c.md",
+                "/empty",
+            ]
+        );
+        // The explicit directory keeps its catalog timestamps
+        assert!(walked.last().unwrap().created.is_some());
+
+        // Same entries as list_entries, which sorts differently
+        let mut listed = cart.list_entries("")?;
+        let mut walked = walked;
+        listed.sort_by(|a, b| a.path.cmp(&b.path));
+        walked.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(walked, listed);
+
+        // Pagination under a prefix, then drop the iterator early. Like
+        // list_entries, the prefix's own directories come first
+        let page: Vec<String> = cart
+            .walk("docs/sub")
+            .skip(2)
+            .take(2)
+            .map(|e| e.map(|e| e.path))
+            .collect::<Result<_>>()?;
+        assert_eq!(page, ["/docs/sub/b.md", "/docs/sub/deep"]);
+        let first = cart.walk("docs").next().unwrap()?;
+        assert_eq!(first.path, "/docs");
+        cart.write("docs/after.md", b"still writable")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_walk_emits_directory_once_around_sibling_prefix() -> Result<()> {
+        let mut cart = Cartridge::in_memory("walk-siblings", "Walk Siblings")?;
+        cart.create_dir("/a")?;
+        cart.write("/a-b", b"sibling")?;
+        cart.write("/a/x", b"child")?;
+
+        let walked = cart.walk("").collect::<Result<Vec<_>>>()?;
+        let paths: Vec<&str> = walked.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["/a", "/a-b", "/a/x"]);
+        assert!(walked[0].created.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dependencies_persist_and_resolve() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("content-pack");
+
+        let mut base = Cartridge::in_memory("base-assets", "Base Assets")?;
+        let mut pack = Cartridge::create_at(&path, "content-pack", "Content Pack")?;
+        pack.add_dependency("base-assets", ">=1.2")?;
+        assert!(pack.add_dependency("content-pack", "*").is_err());
+        pack.flush()?;
+        drop(pack);
+
+        let pack = Cartridge::open(&path)?;
+        assert_eq!(pack.dependencies()?, [Dependency::new("base-assets", ">=1.2")?]);
+
+        // New cartridges start at 0.1.0, too old for the pack
+        let unmet = check_dependencies(&[&base, &pack])?;
+        assert_eq!(unmet.len(), 1);
+        assert_eq!(unmet[0].installed, Some(semver::Version::new(0, 1, 0)));
+
+        assert_eq!(base.bump_version(Bump::Major)?, semver::Version::new(1, 0, 0));
+        assert_eq!(base.bump_version(Bump::Minor)?, semver::Version::new(1, 1, 0));
+        assert_eq!(base.bump_version(Bump::Minor)?, semver::Version::new(1, 2, 0));
+        assert_eq!(base.read_manifest()?.version, semver::Version::new(1, 2, 0));
+        assert!(check_dependencies(&[&base, &pack])?.is_empty());
+        Ok(())
+    }
 }
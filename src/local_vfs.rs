@@ -0,0 +1,272 @@
+//! A [`Vfs`] backed by a directory on the host filesystem
+//!
+//! [`LocalVfs`] lets code written against [`Vfs`] run on plain files, for
+//! example to import a directory tree into a cartridge or to test against a
+//! scratch directory. Paths use the same canonical `/a/b` form as a
+//! cartridge and resolve beneath the root directory; anything that would
+//! land outside it, through `..` or a symlink, is rejected.
+//!
+//! ```rust,no_run
+//! use cartridge_rs::{Cartridge, LocalVfs, Vfs};
+//!
+//! let source = LocalVfs::new("./assets")?;
+//! let mut cart = Cartridge::create("assets", "Assets")?;
+//! for entry in source.list_entries("/")? {
+//!     if !entry.is_dir {
+//!         cart.write_stream(&entry.path, &mut source.open_reader(&entry.path)?)?;
+//!     }
+//! }
+//! # Ok::<(), cartridge_rs::CartridgeError>(())
+//! ```
+
+use crate::content_type;
+use crate::validation::normalize_path;
+use crate::{
+    sort_entries, CartridgeError, Entry, FileMetadata, FileType, PathError, Result, Vfs, PAGE_SIZE,
+};
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A directory on the host filesystem, accessed through [`Vfs`]
+#[derive(Debug, Clone)]
+pub struct LocalVfs {
+    root: PathBuf,
+}
+
+impl LocalVfs {
+    /// Serve the directory at `root`
+    ///
+    /// Fails with [`CartridgeError::NotADirectory`] when `root` exists but
+    /// isn't a directory.
+    pub fn new<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let root = fs::canonicalize(root.as_ref())?;
+        if !root.is_dir() {
+            return Err(CartridgeError::NotADirectory {
+                path: root.display().to_string(),
+            });
+        }
+        Ok(LocalVfs { root })
+    }
+
+    /// The directory paths resolve beneath, canonicalized
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Resolve an archive-style path to a host path beneath the root
+    ///
+    /// The deepest part of the path that already exists is canonicalized,
+    /// so symlinks are followed before checking that it stays inside the
+    /// root. A dangling symlink is rejected too, since writing through it
+    /// would create its target.
+    fn host_path(&self, path: &str) -> Result<PathBuf> {
+        let normalized = normalize_path(path)?;
+        let host = self.root.join(normalized.trim_start_matches('/'));
+
+        let existing = host
+            .ancestors()
+            .find(|ancestor| fs::symlink_metadata(ancestor).is_ok())
+            .unwrap_or(&self.root);
+        let inside = fs::canonicalize(existing).is_ok_and(|real| real.starts_with(&self.root));
+        if !inside {
+            return Err(CartridgeError::InvalidFilePath {
+                path: normalized,
+                reason: PathError::EscapesRoot,
+            });
+        }
+        Ok(host)
+    }
+
+    /// Build an entry for `path` from host metadata, without following a
+    /// final symlink
+    fn entry(&self, path: &str, host: &Path) -> Result<Entry> {
+        let metadata = file_metadata(host, &fs::symlink_metadata(host)?);
+        let mut entry = Entry::from_catalog(path, &metadata, PAGE_SIZE);
+        // Nothing is stored in pages here
+        entry.compressed_size = None;
+        Ok(entry)
+    }
+
+    /// Collect entries below `dir`, descending into subdirectories when
+    /// `recursive` is set (but never through symlinks)
+    fn collect(&self, dir: &str, recursive: bool, entries: &mut Vec<Entry>) -> Result<()> {
+        for dirent in fs::read_dir(self.host_path(dir)?)? {
+            let dirent = dirent?;
+            let Some(name) = dirent.file_name().to_str().map(str::to_string) else {
+                // Not representable as an archive path
+                continue;
+            };
+            let path = if dir == "/" {
+                format!("/{}", name)
+            } else {
+                format!("{}/{}", dir, name)
+            };
+            let entry = self.entry(&path, &dirent.path())?;
+            let descend = recursive && entry.is_dir;
+            entries.push(entry);
+            if descend {
+                self.collect(&path, true, entries)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// List `prefix` and, if `recursive`, everything below it; a missing
+    /// prefix lists nothing
+    fn list(&self, prefix: &str, recursive: bool) -> Result<Vec<Entry>> {
+        let prefix = normalize_path(prefix)?;
+        let host = self.host_path(&prefix)?;
+        let mut entries = Vec::new();
+        match fs::symlink_metadata(&host) {
+            Ok(metadata) if metadata.is_dir() => self.collect(&prefix, recursive, &mut entries)?,
+            Ok(_) if recursive => entries.push(self.entry(&prefix, &host)?),
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        sort_entries(&mut entries);
+        Ok(entries)
+    }
+
+    /// Open an existing regular file for reading
+    fn open_file(&self, path: &str) -> Result<fs::File> {
+        let host = self.host_path(path)?;
+        let metadata = fs::metadata(&host).map_err(|e| not_found(e, path))?;
+        if metadata.is_dir() {
+            return Err(CartridgeError::NotAFile {
+                path: path.to_string(),
+            });
+        }
+        fs::File::open(&host).map_err(|e| not_found(e, path))
+    }
+
+    /// Host path for writing `path`, with its parent directories created
+    fn prepare_write(&self, path: &str) -> Result<PathBuf> {
+        let host = self.host_path(path)?;
+        if host == self.root {
+            return Err(CartridgeError::NotAFile {
+                path: path.to_string(),
+            });
+        }
+        if let Some(parent) = host.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(host)
+    }
+}
+
+impl Vfs for LocalVfs {
+    fn list_entries(&self, prefix: &str) -> Result<Vec<Entry>> {
+        self.list(prefix, true)
+    }
+
+    fn list_children(&self, parent: &str) -> Result<Vec<Entry>> {
+        self.list(parent, false)
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let mut content = Vec::new();
+        self.open_file(path)?.read_to_end(&mut content)?;
+        Ok(content)
+    }
+
+    fn write(&mut self, path: &str, data: &[u8]) -> Result<()> {
+        fs::write(self.prepare_write(path)?, data)?;
+        Ok(())
+    }
+
+    fn delete(&mut self, path: &str) -> Result<()> {
+        let host = self.host_path(path)?;
+        if host == self.root {
+            return Err(CartridgeError::NotAFile {
+                path: path.to_string(),
+            });
+        }
+        let metadata = fs::symlink_metadata(&host).map_err(|e| not_found(e, path))?;
+        if metadata.is_dir() {
+            fs::remove_dir_all(&host)?;
+        } else {
+            fs::remove_file(&host)?;
+        }
+        Ok(())
+    }
+
+    fn exists(&self, path: &str) -> Result<bool> {
+        Ok(fs::symlink_metadata(self.host_path(path)?).is_ok())
+    }
+
+    fn is_dir(&self, path: &str) -> Result<bool> {
+        Ok(fs::symlink_metadata(self.host_path(path)?).is_ok_and(|m| m.is_dir()))
+    }
+
+    fn metadata(&self, path: &str) -> Result<FileMetadata> {
+        let host = self.host_path(path)?;
+        let metadata = fs::symlink_metadata(&host).map_err(|e| not_found(e, path))?;
+        Ok(file_metadata(&host, &metadata))
+    }
+
+    fn read_range(&self, path: &str, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let mut file = self.open_file(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut content = Vec::new();
+        file.take(len).read_to_end(&mut content)?;
+        Ok(content)
+    }
+
+    fn open_reader(&self, path: &str) -> Result<Box<dyn Read + Send + '_>> {
+        Ok(Box::new(self.open_file(path)?))
+    }
+
+    fn write_stream(&mut self, path: &str, reader: &mut dyn Read) -> Result<u64> {
+        let mut file = fs::File::create(self.prepare_write(path)?)?;
+        Ok(io::copy(reader, &mut file)?)
+    }
+}
+
+/// Map a missing host file to [`CartridgeError::NotFound`]
+fn not_found(error: io::Error, path: &str) -> CartridgeError {
+    if error.kind() == io::ErrorKind::NotFound {
+        CartridgeError::NotFound {
+            path: path.to_string(),
+        }
+    } else {
+        error.into()
+    }
+}
+
+/// Describe a host file the way the catalog would
+fn file_metadata(host: &Path, metadata: &fs::Metadata) -> FileMetadata {
+    let file_type = if metadata.is_dir() {
+        FileType::Directory
+    } else if metadata.file_type().is_symlink() {
+        FileType::Symlink
+    } else {
+        FileType::File
+    };
+    let size = if file_type == FileType::File { metadata.len() } else { 0 };
+    let mut result = FileMetadata::new(file_type, size, Vec::new());
+
+    let secs = |time: io::Result<SystemTime>| {
+        time.ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+    };
+    if let Some(modified) = secs(metadata.modified()) {
+        result.modified_at = modified;
+        result.created_at = secs(metadata.created()).unwrap_or(modified);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        result.permissions = metadata.permissions().mode() & 0o7777;
+    }
+    if file_type == FileType::File {
+        result.content_type = host
+            .to_str()
+            .and_then(content_type::from_path)
+            .map(str::to_string);
+    }
+    result
+}
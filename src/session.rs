@@ -0,0 +1,234 @@
+//! Session-scoped operations
+//!
+//! [`Cartridge::set_session_id`](crate::core::Cartridge::set_session_id) and
+//! friends change who every later operation is attributed to. A
+//! [`SessionGuard`] (or a [`SharedSession`] on a [`SharedCartridge`])
+//! attributes only the operations made through it, leaving the cartridge-wide
+//! defaults alone, so handlers serving different sessions can share one
+//! cartridge.
+//!
+//! ```rust,no_run
+//! # use cartridge_rs::Cartridge;
+//! let shared = Cartridge::create("my-data", "My Data")?.into_shared();
+//!
+//! let alice = shared.session(1, 10).with_principal("alice");
+//! let bob = shared.session(2, 20);
+//! alice.write("alice/notes.txt", b"hi")?;
+//! bob.write("bob/notes.txt", b"hello")?;
+//! # Ok::<(), cartridge_rs::CartridgeError>(())
+//! ```
+
+use crate::core::Cartridge as CoreCartridge;
+use crate::{Cartridge, Entry, FileMetadata, Result, SharedCartridge};
+
+/// Who operations are attributed to: audit session and actor, and the IAM
+/// principal access checks are evaluated for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    /// Session id audit entries carry
+    pub session_id: u32,
+    /// Actor id audit entries carry
+    pub actor_id: u32,
+    /// Principal for access checks, or `None` for an anonymous caller
+    pub principal: Option<String>,
+}
+
+impl Session {
+    /// Session `session_id` acting as `actor_id`, with no principal
+    pub fn new(session_id: u32, actor_id: u32) -> Self {
+        Session {
+            session_id,
+            actor_id,
+            principal: None,
+        }
+    }
+
+    /// Evaluate access checks for `principal`
+    pub fn with_principal(mut self, principal: &str) -> Self {
+        self.principal = Some(principal.to_string());
+        self
+    }
+
+    /// The attribution `cartridge` currently uses
+    fn current(cartridge: &CoreCartridge) -> Self {
+        Session {
+            session_id: cartridge.session_id(),
+            actor_id: cartridge.actor_id(),
+            principal: cartridge.principal().map(str::to_string),
+        }
+    }
+
+    /// Attribute later operations on `cartridge` to this session
+    fn apply(&self, cartridge: &mut CoreCartridge) {
+        cartridge.set_session_id(self.session_id);
+        cartridge.set_actor(self.actor_id);
+        match &self.principal {
+            Some(principal) => cartridge.set_principal(principal),
+            None => cartridge.clear_principal(),
+        }
+    }
+}
+
+impl Cartridge {
+    /// Operations attributed to `session_id` and `actor_id`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use cartridge_rs::Cartridge;
+    /// # let mut cart = Cartridge::create("my-data", "My Data")?;
+    /// let mut session = cart.session(7, 42);
+    /// session.write("uploads/a.txt", b"a")?;
+    /// session.delete("uploads/old.txt")?;
+    /// # Ok::<(), cartridge_rs::CartridgeError>(())
+    /// ```
+    pub fn session(&mut self, session_id: u32, actor_id: u32) -> SessionGuard<'_> {
+        SessionGuard {
+            cartridge: self,
+            session: Session::new(session_id, actor_id),
+        }
+    }
+
+    /// Run `f` with operations attributed to `session`, then restore the
+    /// cartridge-wide defaults
+    pub fn with_session<T>(&mut self, session: &Session, f: impl FnOnce(&mut Self) -> T) -> T {
+        let defaults = Session::current(&self.inner);
+        session.apply(&mut self.inner);
+        let result = f(self);
+        defaults.apply(&mut self.inner);
+        result
+    }
+}
+
+impl SharedCartridge {
+    /// A handle whose operations are attributed to `session_id` and
+    /// `actor_id`. See [`Cartridge::session`].
+    pub fn session(&self, session_id: u32, actor_id: u32) -> SharedSession {
+        SharedSession {
+            cartridge: self.clone(),
+            session: Session::new(session_id, actor_id),
+        }
+    }
+}
+
+/// Operations on a [`Cartridge`] attributed to one session
+///
+/// Created by [`Cartridge::session`]. Methods mirror the cartridge's own;
+/// for anything else use [`run`](Self::run).
+pub struct SessionGuard<'a> {
+    cartridge: &'a mut Cartridge,
+    session: Session,
+}
+
+impl SessionGuard<'_> {
+    /// Evaluate access checks made through this guard for `principal`
+    pub fn with_principal(mut self, principal: &str) -> Self {
+        self.session.principal = Some(principal.to_string());
+        self
+    }
+
+    /// Who this guard attributes operations to
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Run `f` on the cartridge with operations attributed to this session
+    pub fn run<T>(&mut self, f: impl FnOnce(&mut Cartridge) -> T) -> T {
+        self.cartridge.with_session(&self.session, f)
+    }
+
+    /// Write a file, creating it if it doesn't exist
+    pub fn write<P: AsRef<str>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+        self.run(|cart| cart.write(path, content))
+    }
+
+    /// Read a file's contents
+    pub fn read<P: AsRef<str>>(&mut self, path: P) -> Result<Vec<u8>> {
+        self.run(|cart| cart.read(path))
+    }
+
+    /// Delete a file
+    pub fn delete<P: AsRef<str>>(&mut self, path: P) -> Result<()> {
+        self.run(|cart| cart.delete(path))
+    }
+
+    /// Create a directory
+    pub fn create_dir<P: AsRef<str>>(&mut self, path: P) -> Result<()> {
+        self.run(|cart| cart.create_dir(path))
+    }
+
+    /// List entries under a prefix. See [`Cartridge::list_entries`].
+    pub fn list_entries<P: AsRef<str>>(&mut self, prefix: P) -> Result<Vec<Entry>> {
+        self.run(|cart| cart.list_entries(prefix))
+    }
+
+    /// Check if a path exists
+    pub fn exists<P: AsRef<str>>(&mut self, path: P) -> Result<bool> {
+        self.run(|cart| cart.exists(path))
+    }
+
+    /// Get metadata for a path
+    pub fn metadata<P: AsRef<str>>(&mut self, path: P) -> Result<FileMetadata> {
+        self.run(|cart| cart.metadata(path))
+    }
+}
+
+/// Operations on a [`SharedCartridge`] attributed to one session
+///
+/// Created by [`SharedCartridge::session`]. Cloneable and `Send`, so each
+/// request handler can hold its own. Every operation takes the exclusive
+/// lock, since attribution is switched for its duration.
+#[derive(Clone)]
+pub struct SharedSession {
+    cartridge: SharedCartridge,
+    session: Session,
+}
+
+impl SharedSession {
+    /// Evaluate access checks made through this handle for `principal`
+    pub fn with_principal(mut self, principal: &str) -> Self {
+        self.session.principal = Some(principal.to_string());
+        self
+    }
+
+    /// Who this handle attributes operations to
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Run `f` on the cartridge with operations attributed to this session
+    pub fn run<T>(&self, f: impl FnOnce(&mut Cartridge) -> T) -> T {
+        self.cartridge
+            .with_write(|cart| cart.with_session(&self.session, f))
+    }
+
+    /// Write a file, creating it if it doesn't exist
+    pub fn write<P: AsRef<str>>(&self, path: P, content: &[u8]) -> Result<()> {
+        self.run(|cart| cart.write(path, content))
+    }
+
+    /// Read a file's contents
+    pub fn read<P: AsRef<str>>(&self, path: P) -> Result<Vec<u8>> {
+        self.run(|cart| cart.read(path))
+    }
+
+    /// Delete a file
+    pub fn delete<P: AsRef<str>>(&self, path: P) -> Result<()> {
+        self.run(|cart| cart.delete(path))
+    }
+
+    /// List entries under a prefix. See [`Cartridge::list_entries`].
+    pub fn list_entries<P: AsRef<str>>(&self, prefix: P) -> Result<Vec<Entry>> {
+        self.run(|cart| cart.list_entries(prefix))
+    }
+
+    /// Check if a path exists
+    pub fn exists<P: AsRef<str>>(&self, path: P) -> Result<bool> {
+        self.run(|cart| cart.exists(path))
+    }
+
+    /// Get metadata for a path
+    pub fn metadata<P: AsRef<str>>(&self, path: P) -> Result<FileMetadata> {
+        self.run(|cart| cart.metadata(path))
+    }
+}
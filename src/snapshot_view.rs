@@ -0,0 +1,97 @@
+//! Read-only views of snapshots
+//!
+//! [`SnapshotView`] reads a snapshot's files in place, next to the live
+//! cartridge, without restoring it. The view is assembled in memory from
+//! the snapshot's header, catalog and pages when opened, so the live
+//! cartridge stays free to change while the view is read. Every write fails
+//! with [`CartridgeError::ReadOnly`].
+//!
+//! ```rust,no_run
+//! use cartridge_rs::{Cartridge, Vfs};
+//! use std::path::Path;
+//!
+//! let cart = Cartridge::open("data.cart")?;
+//! let yesterday = cart.open_snapshot_view(1_700_000_000_000_000, Path::new("snapshots"))?;
+//! let before = yesterday.read("/config.toml")?;
+//! let after = cart.read("/config.toml")?;
+//! # Ok::<(), cartridge_rs::CartridgeError>(())
+//! ```
+
+use crate::{Cartridge, CartridgeError, Entry, FileMetadata, Result, Vfs};
+use std::io::Read;
+
+/// A snapshot's files, readable through [`Vfs`]
+pub struct SnapshotView {
+    /// Read-only, in-memory cartridge holding the snapshot's state
+    cart: Cartridge,
+    snapshot_id: u64,
+}
+
+impl SnapshotView {
+    pub(crate) fn new(cart: Cartridge, snapshot_id: u64) -> Self {
+        SnapshotView { cart, snapshot_id }
+    }
+
+    /// The id of the snapshot being viewed
+    pub fn snapshot_id(&self) -> u64 {
+        self.snapshot_id
+    }
+}
+
+impl Vfs for SnapshotView {
+    fn list_entries(&self, prefix: &str) -> Result<Vec<Entry>> {
+        self.cart.list_entries(prefix)
+    }
+
+    fn list_children(&self, parent: &str) -> Result<Vec<Entry>> {
+        self.cart.list_children(parent)
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        self.cart.read(path)
+    }
+
+    fn write(&mut self, _path: &str, _data: &[u8]) -> Result<()> {
+        Err(CartridgeError::ReadOnly)
+    }
+
+    fn delete(&mut self, _path: &str) -> Result<()> {
+        Err(CartridgeError::ReadOnly)
+    }
+
+    fn exists(&self, path: &str) -> Result<bool> {
+        self.cart.exists(path)
+    }
+
+    fn is_dir(&self, path: &str) -> Result<bool> {
+        self.cart.is_dir(path)
+    }
+
+    fn metadata(&self, path: &str) -> Result<FileMetadata> {
+        self.cart.metadata(path)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn read_range(&self, path: &str, offset: u64, len: u64) -> Result<Vec<u8>> {
+        self.cart.read_range(path, offset, len)
+    }
+
+    fn open_reader(&self, path: &str) -> Result<Box<dyn Read + Send + '_>> {
+        Ok(Box::new(self.cart.open_reader(path)?))
+    }
+
+    fn write_stream(&mut self, _path: &str, _reader: &mut dyn Read) -> Result<u64> {
+        Err(CartridgeError::ReadOnly)
+    }
+}
+
+impl std::fmt::Debug for SnapshotView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SnapshotView")
+            .field("snapshot_id", &self.snapshot_id)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,200 @@
+//! A read-only [`Vfs`] over a zip archive
+//!
+//! [`ZipVfs`] reads the archive's central directory once when opened and
+//! then serves listings and metadata from it; file content is read from the
+//! archive, inflating deflated entries, only when asked for. Every write
+//! fails with [`CartridgeError::ReadOnly`]. Paths use the same canonical
+//! `/a/b` form as a cartridge.
+//!
+//! ```rust,no_run
+//! use cartridge_rs::{Vfs, ZipVfs};
+//!
+//! let zip = ZipVfs::open("dataset.zip")?;
+//! for entry in zip.list_entries("/images")? {
+//!     println!("{} {:?} {:?}", entry.path, entry.size, entry.modified);
+//! }
+//! let readme = zip.read("/README.md")?;
+//! # Ok::<(), cartridge_rs::CartridgeError>(())
+//! ```
+
+use crate::core::zip::{open_entry, read_entries, ZipEntry};
+use crate::validation::normalize_path;
+use crate::{listing_to_entries, CartridgeError, Entry, FileMetadata, Result, Vfs, PAGE_SIZE};
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+
+/// How many symlinks [`ZipVfs::read`](Vfs::read) follows before giving up
+const MAX_SYMLINK_HOPS: usize = 8;
+
+/// A zip archive, accessed read-only through [`Vfs`]
+pub struct ZipVfs<R = File> {
+    archive: Mutex<R>,
+    /// Every path the archive names, directories included
+    entries: BTreeMap<String, (ZipEntry, FileMetadata)>,
+}
+
+impl ZipVfs<File> {
+    /// Open the zip archive at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::new(File::open(path)?)
+    }
+}
+
+impl<R: Read + Seek> ZipVfs<R> {
+    /// Serve the zip archive read from `reader`
+    ///
+    /// Fails with [`CartridgeError::InvalidZip`] if `reader` doesn't hold
+    /// a zip archive. Entries whose names aren't valid cartridge paths,
+    /// such as ones climbing out with `..`, are left out.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut entries = BTreeMap::new();
+        for entry in read_entries(&mut reader)? {
+            let Ok(path) = normalize_path(&entry.name) else {
+                continue;
+            };
+            if path == "/" {
+                continue;
+            }
+            let mut metadata = entry.metadata();
+            if entry.is_symlink() && entry.unsupported().is_none() {
+                let mut target = String::new();
+                open_entry(&mut reader, &entry)?.read_to_string(&mut target)?;
+                let times = (metadata.created_at, metadata.modified_at, metadata.permissions);
+                metadata = FileMetadata::symlink(&target);
+                (metadata.created_at, metadata.modified_at, metadata.permissions) = times;
+            }
+            entries.insert(path, (entry, metadata));
+        }
+        Ok(ZipVfs {
+            archive: Mutex::new(reader),
+            entries,
+        })
+    }
+
+    /// Give back the underlying reader
+    pub fn into_inner(self) -> R {
+        self.archive.into_inner()
+    }
+
+    /// Whether anything in the archive lies below `path`
+    fn has_children(&self, path: &str) -> bool {
+        let dir = if path == "/" { "/".to_string() } else { format!("{}/", path) };
+        self.entries
+            .range(dir.clone()..)
+            .next()
+            .is_some_and(|(key, _)| key.starts_with(&dir))
+    }
+
+    /// Catalog-style listing of `prefix` and everything below it
+    fn listing(&self, prefix: &str) -> Vec<(String, FileMetadata)> {
+        let dir = if prefix == "/" { "/".to_string() } else { format!("{}/", prefix) };
+        self.entries
+            .iter()
+            .filter(|(path, _)| path.as_str() == prefix || path.starts_with(&dir))
+            .map(|(path, (_, metadata))| (path.clone(), metadata.clone()))
+            .collect()
+    }
+}
+
+impl<R: Read + Seek> Vfs for ZipVfs<R> {
+    fn list_entries(&self, prefix: &str) -> Result<Vec<Entry>> {
+        let prefix = normalize_path(prefix)?;
+        let mut entries = listing_to_entries(&self.listing(&prefix), PAGE_SIZE);
+        // Report the archive's compressed size rather than pages
+        for entry in &mut entries {
+            entry.compressed_size = match self.entries.get(&entry.path) {
+                Some((zip_entry, _)) if !entry.is_dir => Some(zip_entry.compressed_size),
+                _ => None,
+            };
+        }
+        Ok(entries)
+    }
+
+    fn list_children(&self, parent: &str) -> Result<Vec<Entry>> {
+        let parent = normalize_path(parent)?;
+        Ok(self
+            .list_entries(&parent)?
+            .into_iter()
+            .filter(|entry| entry.parent == parent && entry.path != parent)
+            .collect())
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let mut path = normalize_path(path)?;
+        for _ in 0..MAX_SYMLINK_HOPS {
+            let Some((entry, metadata)) = self.entries.get(&path) else {
+                return Err(match self.has_children(&path) {
+                    true => CartridgeError::NotAFile { path },
+                    false => CartridgeError::NotFound { path },
+                });
+            };
+            if let Some(target) = metadata.symlink_target() {
+                // Relative targets resolve against the link's directory
+                path = match target.starts_with('/') {
+                    true => normalize_path(target)?,
+                    false => {
+                        let parent = path.rsplit_once('/').map_or("", |(parent, _)| parent);
+                        normalize_path(&format!("{}/{}", parent, target))?
+                    }
+                };
+                continue;
+            }
+            if metadata.is_directory() {
+                return Err(CartridgeError::NotAFile { path });
+            }
+            let mut archive = self.archive.lock();
+            let mut content = Vec::with_capacity(entry.size.min(1 << 20) as usize);
+            open_entry(&mut *archive, entry)?.read_to_end(&mut content)?;
+            return Ok(content);
+        }
+        Err(CartridgeError::SymlinkLoop(path))
+    }
+
+    fn write(&mut self, _path: &str, _data: &[u8]) -> Result<()> {
+        Err(CartridgeError::ReadOnly)
+    }
+
+    fn delete(&mut self, _path: &str) -> Result<()> {
+        Err(CartridgeError::ReadOnly)
+    }
+
+    fn exists(&self, path: &str) -> Result<bool> {
+        let path = normalize_path(path)?;
+        Ok(path == "/" || self.entries.contains_key(&path) || self.has_children(&path))
+    }
+
+    fn is_dir(&self, path: &str) -> Result<bool> {
+        let path = normalize_path(path)?;
+        Ok(match self.entries.get(&path) {
+            Some((_, metadata)) => metadata.is_directory(),
+            None => path == "/" || self.has_children(&path),
+        })
+    }
+
+    /// Directories that only exist as a prefix of other entries have no
+    /// metadata of their own, as in a cartridge
+    fn metadata(&self, path: &str) -> Result<FileMetadata> {
+        let path = normalize_path(path)?;
+        match self.entries.get(&path) {
+            Some((_, metadata)) => Ok(metadata.clone()),
+            None => Err(CartridgeError::NotFound { path }),
+        }
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn write_stream(&mut self, _path: &str, _reader: &mut dyn Read) -> Result<u64> {
+        Err(CartridgeError::ReadOnly)
+    }
+}
+
+impl<R> std::fmt::Debug for ZipVfs<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ZipVfs").field("entries", &self.entries.len()).finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,78 @@
+//! Async API tests (run with `--features tokio`)
+
+#![cfg(feature = "tokio")]
+
+use cartridge_rs::{AsyncCartridge, AsyncVfs, Cartridge};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_100_concurrent_writes_without_blocking_runtime() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let cart = AsyncCartridge::new(
+        Cartridge::create_at(temp_dir.path().join("async-writes"), "async-writes", "Async Writes")
+            .unwrap(),
+    );
+
+    // A single-threaded runtime only keeps ticking if the writes are off-thread
+    let done = Arc::new(AtomicBool::new(false));
+    let ticks = Arc::new(AtomicUsize::new(0));
+    let heartbeat = {
+        let done = done.clone();
+        let ticks = ticks.clone();
+        tokio::spawn(async move {
+            while !done.load(Ordering::Relaxed) {
+                ticks.fetch_add(1, Ordering::Relaxed);
+                tokio::task::yield_now().await;
+            }
+        })
+    };
+
+    let mut writes = tokio::task::JoinSet::new();
+    for i in 0..100 {
+        let cart = cart.clone();
+        writes.spawn(async move {
+            cart.write(&format!("objects/{}.bin", i), vec![i as u8; 1024])
+                .await
+        });
+    }
+    while let Some(result) = writes.join_next().await {
+        result.unwrap().unwrap();
+    }
+    done.store(true, Ordering::Relaxed);
+    heartbeat.await.unwrap();
+    assert!(ticks.load(Ordering::Relaxed) > 0);
+
+    cart.flush().await.unwrap();
+    let entries = cart.list_entries("objects").await.unwrap();
+    assert_eq!(entries.iter().filter(|e| !e.is_dir).count(), 100);
+    for i in [0usize, 42, 99] {
+        let data = cart.read(&format!("objects/{}.bin", i)).await.unwrap();
+        assert_eq!(data, vec![i as u8; 1024]);
+    }
+}
+
+#[tokio::test]
+async fn test_async_vfs_trait() {
+    async fn roundtrip<V: AsyncVfs>(vfs: &V) -> cartridge_rs::Result<Vec<u8>> {
+        vfs.write("docs/a.txt", b"hello".to_vec()).await?;
+        assert!(vfs.exists("docs/a.txt").await?);
+        assert!(!vfs.is_dir("docs/a.txt").await?);
+        assert_eq!(vfs.metadata("docs/a.txt").await?.size, 5);
+        assert_eq!(vfs.list_children("docs").await?.len(), 1);
+        let data = vfs.read("docs/a.txt").await?;
+        vfs.delete("docs/a.txt").await?;
+        assert!(!vfs.exists("docs/a.txt").await?);
+        Ok(data)
+    }
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let cart = AsyncCartridge::new(
+        Cartridge::create_at(temp_dir.path().join("async-vfs"), "async-vfs", "Async Vfs").unwrap(),
+    );
+    assert_eq!(roundtrip(&cart).await.unwrap(), b"hello");
+
+    // Sync and async callers share the same cartridge
+    cart.shared().write("sync.txt", b"sync").unwrap();
+    assert_eq!(cart.read("sync.txt").await.unwrap(), b"sync");
+}
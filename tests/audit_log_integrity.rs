@@ -126,10 +126,26 @@ fn test_audit_logger_high_frequency() {
 }
 
 #[test]
-#[ignore] // start_background_flush and flush_now not exposed in current API
 fn test_audit_logger_with_flush_callback() {
-    // Note: This test requires start_background_flush() and flush_now() methods
-    // which are not currently exposed in the public API
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let mut logger = AuditLogger::new(1000, Duration::from_secs(3600));
+    let flushed = Arc::new(AtomicUsize::new(0));
+    let flushed_clone = flushed.clone();
+    logger.start(move |entries| {
+        flushed_clone.fetch_add(entries.len(), Ordering::SeqCst);
+    });
+
+    for i in 0..100 {
+        logger.log(AuditEntry::new(1, Operation::Create, 0, i as u64, 0));
+    }
+    assert_eq!(logger.flush_now(), 100);
+    assert_eq!(flushed.load(Ordering::SeqCst), 100);
+
+    // Stopping drains whatever is left
+    logger.log(AuditEntry::new(1, Operation::Delete, 0, 0, 0));
+    logger.stop();
+    assert_eq!(flushed.load(Ordering::SeqCst), 101);
 }
 
 // TODO: Add tests for retrieving and inspecting audit entries once public API is available
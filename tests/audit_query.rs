@@ -0,0 +1,136 @@
+//! Querying and exporting the persisted audit trail
+
+use cartridge_rs::{AuditFilter, AuditRecord, CartridgeBuilder, Operation};
+use std::time::SystemTime;
+
+#[test]
+fn test_filtered_queries_after_flush() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut cart = CartridgeBuilder::new()
+        .slug("audited")
+        .title("Audited")
+        .path(dir.path().join("audited.cart").to_str().unwrap())
+        .with_audit_logging()
+        .build()
+        .unwrap();
+
+    // 100 creates, 150 reads, 50 updates
+    for i in 0..100 {
+        cart.write(format!("docs/{i}.txt"), b"first").unwrap();
+    }
+    cart.flush().unwrap();
+    let second_phase = SystemTime::now();
+    for i in 0..150 {
+        cart.read(format!("docs/{}.txt", i % 100)).unwrap();
+    }
+    for i in 0..50 {
+        cart.write(format!("docs/{i}.txt"), b"second").unwrap();
+    }
+    cart.flush().unwrap();
+
+    let all = cart.query_audit(&AuditFilter::default()).unwrap();
+    let count = |op| all.iter().filter(|r| r.operation == op).count();
+    assert_eq!(count(Operation::Create), 100);
+    assert!(count(Operation::Read) >= 150);
+    assert!(count(Operation::Update) >= 50);
+    assert!(all.iter().all(|r| r.path.as_deref().is_some_and(|p| p.starts_with("/docs/"))));
+
+    let creates = cart
+        .query_audit(&AuditFilter {
+            operations: vec![Operation::Create],
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(creates.len(), 100);
+    assert!(creates.windows(2).all(|w| w[0].timestamp_us <= w[1].timestamp_us));
+
+    let later = cart
+        .query_audit(&AuditFilter {
+            since: Some(second_phase),
+            ..Default::default()
+        })
+        .unwrap();
+    assert!(later.iter().all(|r| r.operation != Operation::Create));
+    assert_eq!(later.len(), all.len() - 100);
+
+    let one_file = cart.query_audit(&AuditFilter::for_path("docs/7.txt")).unwrap();
+    assert!(one_file.iter().all(|r| r.path.as_deref() == Some("/docs/7.txt")));
+    assert_eq!(one_file[0].operation, Operation::Create);
+
+    assert!(cart
+        .query_audit(&AuditFilter {
+            actor_id: Some(99),
+            ..Default::default()
+        })
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_export_csv_and_jsonl() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut cart = CartridgeBuilder::new()
+        .slug("exported")
+        .title("Exported")
+        .path(dir.path().join("exported.cart").to_str().unwrap())
+        .with_audit_logging()
+        .build()
+        .unwrap();
+    cart.write("a.txt", b"a").unwrap();
+    cart.delete("a.txt").unwrap();
+    cart.flush().unwrap();
+
+    let filter = AuditFilter::for_path("a.txt");
+    let mut csv = Vec::new();
+    let rows = cart.export_audit_csv(&filter, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert_eq!(csv.lines().count(), rows + 1);
+    assert!(csv.lines().last().unwrap().contains(",delete,"));
+
+    let mut jsonl = Vec::new();
+    assert_eq!(cart.export_audit_jsonl(&filter, &mut jsonl).unwrap(), rows);
+    let records: Vec<AuditRecord> = String::from_utf8(jsonl)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records, cart.query_audit(&filter).unwrap());
+    assert_eq!(records.last().unwrap().operation, Operation::Delete);
+}
+
+#[test]
+fn test_actors_are_distinguishable() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut cart = CartridgeBuilder::new()
+        .slug("actors")
+        .title("Actors")
+        .path(dir.path().join("actors.cart").to_str().unwrap())
+        .with_audit_logging()
+        .build()
+        .unwrap();
+    cart.inner_mut().register_actor(10, "ingest").unwrap();
+    cart.inner_mut().register_actor(20, "cleanup").unwrap();
+
+    cart.inner_mut().set_actor(10);
+    cart.write("in/a.txt", b"a").unwrap();
+    cart.write("in/b.txt", b"b").unwrap();
+    cart.inner_mut().set_actor(20);
+    cart.delete("in/a.txt").unwrap();
+    cart.flush().unwrap();
+
+    let by = |actor_id| {
+        cart.query_audit(&AuditFilter {
+            actor_id: Some(actor_id),
+            ..Default::default()
+        })
+        .unwrap()
+    };
+    let ingest = by(10);
+    assert_eq!(ingest.iter().filter(|r| r.operation == Operation::Create).count(), 2);
+    assert!(ingest.iter().all(|r| r.actor.as_deref() == Some("ingest")));
+    let cleanup = by(20);
+    assert_eq!(cleanup.len(), 1);
+    assert_eq!(cleanup[0].operation, Operation::Delete);
+    assert_eq!(cleanup[0].actor.as_deref(), Some("cleanup"));
+    assert!(by(1).is_empty());
+}
@@ -5,7 +5,9 @@
 //! - Multiple small files (<256KB using bitmap allocator)
 //! - Mixed workloads (both small and large files)
 
-use cartridge_rs::Cartridge;
+use cartridge_rs::{Cartridge, CartridgeBuilder, CartridgeError, CartridgeEvent};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 #[test]
 fn test_two_large_files() {
@@ -74,22 +76,22 @@ fn test_mixed_small_and_large() {
     for i in 0..10 {
         // Small file (bitmap allocator)
         let small_data = vec![i as u8; 10 * 1024]; // 10KB
-        cart.write(&format!("/small{}.bin", i), &small_data)
+        cart.write(format!("/small{}.bin", i), &small_data)
             .unwrap();
 
         // Large file (extent allocator)
         let large_data = vec![((i + 100) % 256) as u8; 300 * 1024]; // 300KB
-        cart.write(&format!("/large{}.bin", i), &large_data)
+        cart.write(format!("/large{}.bin", i), &large_data)
             .unwrap();
     }
 
     // Verify all files
     for i in 0..10 {
-        let small = cart.read(&format!("/small{}.bin", i)).unwrap();
+        let small = cart.read(format!("/small{}.bin", i)).unwrap();
         assert_eq!(small.len(), 10 * 1024, "small{}.bin has wrong length", i);
         assert_eq!(small[0], i as u8, "small{}.bin has wrong first byte", i);
 
-        let large = cart.read(&format!("/large{}.bin", i)).unwrap();
+        let large = cart.read(format!("/large{}.bin", i)).unwrap();
         assert_eq!(large.len(), 300 * 1024, "large{}.bin has wrong length", i);
         let expected_byte = ((i + 100) % 256) as u8;
         assert_eq!(large[0], expected_byte,
@@ -112,7 +114,7 @@ fn test_sequential_growth_levels() {
 
     for i in 0..20 {
         let data = vec![i as u8; 256 * 1024]; // 256KB
-        cart.write(&format!("/file{}.bin", i), &data).unwrap();
+        cart.write(format!("/file{}.bin", i), &data).unwrap();
 
         let current_blocks = cart.header().total_blocks;
         if current_blocks > previous_blocks {
@@ -165,3 +167,140 @@ fn test_hybrid_allocator_free_blocks_tracking() {
 
     println!("✓ Free blocks correctly tracked: {} -> {} -> {}", free_before, free_after_large, free_after_small);
 }
+
+fn count_grows(cart: &mut Cartridge) -> Arc<AtomicUsize> {
+    let grows = Arc::new(AtomicUsize::new(0));
+    let counter = grows.clone();
+    cart.set_event_listener(Box::new(move |event| {
+        if matches!(event, CartridgeEvent::GrowStarted { .. }) {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }
+    }));
+    grows
+}
+
+#[test]
+fn test_presized_import_never_grows() {
+    // 100MB is 25,600 blocks; leave room for the catalog and allocator pages
+    let dir = tempfile::tempdir().unwrap();
+    let mut cart = CartridgeBuilder::new()
+        .slug("presized")
+        .title("Presized")
+        .path(dir.path().join("presized").to_string_lossy())
+        .initial_blocks(27_000)
+        .build()
+        .unwrap();
+    assert_eq!(cart.header().total_blocks, 27_000);
+    let grows = count_grows(&mut cart);
+
+    let data = vec![0x5A; 1024 * 1024];
+    for i in 0..100 {
+        cart.write(format!("/import/file{}.bin", i), &data).unwrap();
+    }
+    cart.flush().unwrap();
+
+    assert_eq!(grows.load(Ordering::SeqCst), 0);
+    assert_eq!(cart.header().total_blocks, 27_000);
+}
+
+#[test]
+fn test_linear_growth_increment() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut cart = CartridgeBuilder::new()
+        .slug("linear")
+        .title("Linear")
+        .path(dir.path().join("linear").to_string_lossy())
+        .growth_increment_blocks(100)
+        .build()
+        .unwrap();
+
+    let mut sizes = vec![cart.header().total_blocks];
+    for i in 0..4 {
+        cart.write(format!("/file{}.bin", i), &vec![i as u8; 200 * 1024]).unwrap();
+        let total = cart.header().total_blocks;
+        if total != *sizes.last().unwrap() {
+            sizes.push(total);
+        }
+    }
+
+    assert!(sizes.len() > 2, "expected several growth steps, got {:?}", sizes);
+    assert!(sizes.windows(2).all(|w| (w[1] - w[0]).is_multiple_of(100)));
+}
+
+#[test]
+fn test_reserve_with_auto_grow_disabled() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut cart = CartridgeBuilder::new()
+        .slug("fixed")
+        .title("Fixed")
+        .path(dir.path().join("fixed").to_string_lossy())
+        .auto_grow(false)
+        .build()
+        .unwrap();
+
+    let data = vec![0x11; 64 * 1024];
+    assert!(matches!(
+        cart.write("/too-big.bin", &data),
+        Err(CartridgeError::OutOfSpace)
+    ));
+
+    cart.reserve(data.len() as u64).unwrap();
+    assert!(cart.header().free_blocks >= 16);
+    cart.write("/fits.bin", &data).unwrap();
+    assert_eq!(cart.read("/fits.bin").unwrap(), data);
+}
+
+#[test]
+fn test_huge_write_grows_in_one_step() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut cart = CartridgeBuilder::new()
+        .slug("one-step")
+        .title("One Step")
+        .path(dir.path().join("one-step").to_string_lossy())
+        .max_size_bytes(64 * 1024 * 1024)
+        .build()
+        .unwrap();
+    let grows = count_grows(&mut cart);
+
+    // 32MB from a few blocks would otherwise take a dozen doublings
+    let data = vec![0x42; 32 * 1024 * 1024];
+    cart.write("/huge.bin", &data).unwrap();
+    assert_eq!(grows.load(Ordering::SeqCst), 1);
+    // Still where doubling would have ended up, not just the bytes asked for
+    let size = cart.header().total_blocks * 4096;
+    assert!(size > data.len() as u64 && size <= 2 * data.len() as u64);
+    assert_eq!(cart.read("/huge.bin").unwrap().len(), data.len());
+}
+
+#[test]
+fn test_write_past_max_size_fails_without_growing() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut cart = CartridgeBuilder::new()
+        .slug("capped")
+        .title("Capped")
+        .path(dir.path().join("capped").to_string_lossy())
+        .max_size_bytes(1024 * 1024)
+        .build()
+        .unwrap();
+    cart.write("/small.bin", &[1u8; 4096]).unwrap();
+    cart.flush().unwrap();
+    let total_blocks = cart.header().total_blocks;
+    let file_len = std::fs::metadata(dir.path().join("capped.cart")).unwrap().len();
+    let headroom = cart.headroom();
+    assert!(headroom < 1024 * 1024);
+    assert!(headroom > 900 * 1024);
+    let grows = count_grows(&mut cart);
+
+    assert!(matches!(
+        cart.write("/too-big.bin", &vec![2u8; 2 * 1024 * 1024]),
+        Err(CartridgeError::OutOfSpace)
+    ));
+    assert_eq!(grows.load(Ordering::SeqCst), 0);
+    assert_eq!(cart.header().total_blocks, total_blocks);
+    assert_eq!(std::fs::metadata(dir.path().join("capped.cart")).unwrap().len(), file_len);
+
+    // Anything within the headroom still fits, growing once
+    cart.write("/fits.bin", &vec![3u8; headroom as usize / 2]).unwrap();
+    assert_eq!(grows.load(Ordering::SeqCst), 1);
+    assert!(cart.headroom() < headroom / 2 + 4096);
+}
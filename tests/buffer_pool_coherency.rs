@@ -112,7 +112,7 @@ fn test_multiple_files_coherency() {
     {
         let mut c = cart.write();
         for i in 0..10 {
-            c.write(&format!("/file{}.txt", i), format!("data{}", i).as_bytes()).unwrap();
+            c.write(format!("/file{}.txt", i), format!("data{}", i).as_bytes()).unwrap();
         }
     }
 
@@ -122,7 +122,7 @@ fn test_multiple_files_coherency() {
         std::thread::spawn(move || {
             for _ in 0..100 {
                 let c = cart_clone.read();
-                let data = c.read(&format!("/file{}.txt", file_id)).unwrap();
+                let data = c.read(format!("/file{}.txt", file_id)).unwrap();
                 assert_eq!(data, format!("data{}", file_id).as_bytes());
             }
         })
@@ -0,0 +1,120 @@
+//! Case-insensitive cartridges match paths regardless of case
+
+use cartridge_rs::{CartridgeBuilder, CartridgeError, CaseSensitivity};
+
+fn insensitive(dir: &tempfile::TempDir) -> cartridge_rs::Cartridge {
+    CartridgeBuilder::new()
+        .slug("uploads")
+        .title("Uploads")
+        .path(dir.path().join("uploads").to_str().unwrap())
+        .case_insensitive()
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_lookups_ignore_case() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut cart = insensitive(&dir);
+    assert_eq!(cart.case_sensitivity(), CaseSensitivity::Insensitive);
+    cart.write("images/Logo.PNG", b"png").unwrap();
+
+    assert_eq!(cart.read("images/logo.png").unwrap(), b"png");
+    assert_eq!(cart.read("/IMAGES/LOGO.PNG").unwrap(), b"png");
+    assert!(cart.exists("images/logo.png").unwrap());
+    assert_eq!(cart.metadata("Images/logo.png").unwrap().size, 3);
+
+    // Overwriting through another spelling keeps the original name
+    cart.write("images/logo.png", b"png v2").unwrap();
+    assert_eq!(cart.read("images/Logo.PNG").unwrap(), b"png v2");
+    let names: Vec<String> = cart
+        .list_children("IMAGES")
+        .unwrap()
+        .into_iter()
+        .map(|e| e.name)
+        .collect();
+    assert_eq!(names, ["Logo.PNG"]);
+
+    cart.delete("IMAGES/LOGO.png").unwrap();
+    assert!(!cart.exists("images/Logo.PNG").unwrap());
+}
+
+#[test]
+fn test_create_conflicts_with_other_case() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut cart = insensitive(&dir);
+    cart.inner_mut().create_file("a.txt", b"lower").unwrap();
+
+    assert!(matches!(
+        cart.inner_mut().create_file("A.txt", b"upper"),
+        Err(CartridgeError::AlreadyExists { .. })
+    ));
+    assert_eq!(cart.read("a.txt").unwrap(), b"lower");
+    assert_eq!(cart.list("").unwrap().iter().filter(|p| p.ends_with(".txt")).count(), 1);
+}
+
+#[test]
+fn test_mode_survives_reopen_and_mismatch_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("uploads.cart");
+    let mut cart = insensitive(&dir);
+    cart.write("Docs/Readme.md", b"readme").unwrap();
+    cart.flush().unwrap();
+    drop(cart);
+
+    let cart = cartridge_rs::Cartridge::open_with_case(&path, CaseSensitivity::Insensitive).unwrap();
+    assert_eq!(cart.read("docs/readme.md").unwrap(), b"readme");
+    drop(cart);
+
+    assert!(matches!(
+        cartridge_rs::Cartridge::open_with_case(&path, CaseSensitivity::Exact),
+        Err(CartridgeError::CaseSensitivityMismatch {
+            expected: CaseSensitivity::Exact,
+            found: CaseSensitivity::Insensitive,
+        })
+    ));
+
+    // A default cartridge is case-sensitive and can't be opened as insensitive
+    let exact_dir = tempfile::tempdir().unwrap();
+    let exact_path = exact_dir.path().join("exact.cart");
+    let mut exact = cartridge_rs::Cartridge::create_at(&exact_path, "exact", "Exact").unwrap();
+    exact.write("Readme.md", b"x").unwrap();
+    assert!(!exact.exists("readme.md").unwrap());
+    exact.flush().unwrap();
+    drop(exact);
+    assert!(matches!(
+        cartridge_rs::Cartridge::open_with_case(&exact_path, CaseSensitivity::Insensitive),
+        Err(CartridgeError::CaseSensitivityMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_system_files_refused_in_any_case() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut cart = insensitive(&dir);
+    for spelling in [
+        "/.CARTRIDGE/manifest.json",
+        "/.Cartridge/manifest.json",
+        ".cartridge/MANIFEST.JSON",
+    ] {
+        assert!(
+            matches!(
+                cart.delete(spelling),
+                Err(CartridgeError::ProtectedPath { .. })
+            ),
+            "{spelling}"
+        );
+        assert!(
+            matches!(
+                cart.write(spelling, b"{}"),
+                Err(CartridgeError::ProtectedPath { .. })
+            ),
+            "{spelling}"
+        );
+    }
+    assert!(matches!(
+        cart.write("/.Cartridge/extra.json", b"{}"),
+        Err(CartridgeError::ProtectedPath { .. })
+    ));
+    assert_eq!(cart.slug().unwrap(), "uploads");
+}
@@ -0,0 +1,135 @@
+//! The `cart` command-line tool
+//!
+//! Run with `cargo test --features cli --test cli`.
+
+#![cfg(feature = "cli")]
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn cart(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cart"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn json(output: &Output) -> serde_json::Value {
+    serde_json::from_str(&stdout(output)).unwrap()
+}
+
+#[test]
+fn test_put_ls_cat_get_rm() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("notes.txt"), b"hello cart").unwrap();
+
+    // put creates the cartridge
+    stdout(&cart(dir.path(), &["put", "t.cart", "notes.txt", "docs/notes.txt"]));
+    stdout(&cart(dir.path(), &["put", "t.cart", "notes.txt", "docs/old/copy.txt"]));
+
+    let listing = stdout(&cart(dir.path(), &["ls", "t.cart", "docs"]));
+    assert!(listing.contains("/docs/notes.txt\n") && listing.contains("/docs/old/copy.txt\n"));
+    let long = stdout(&cart(dir.path(), &["ls", "t.cart", "docs", "-l"]));
+    assert!(long.lines().any(|l| l.starts_with('-') && l.contains(" 10 ")), "{long}");
+    let entries = json(&cart(dir.path(), &["--json", "ls", "t.cart", "docs"]));
+    let notes = entries.as_array().unwrap().iter().find(|e| e["name"] == "notes.txt").unwrap();
+    assert_eq!(notes["size"], 10);
+
+    assert_eq!(stdout(&cart(dir.path(), &["cat", "t.cart", "docs/notes.txt"])), "hello cart");
+    stdout(&cart(dir.path(), &["get", "t.cart", "docs/notes.txt", "out.txt"]));
+    assert_eq!(std::fs::read(dir.path().join("out.txt")).unwrap(), b"hello cart");
+
+    // A directory needs -r
+    assert_eq!(cart(dir.path(), &["rm", "t.cart", "docs/old"]).status.code(), Some(1));
+    let removed = json(&cart(dir.path(), &["rm", "t.cart", "docs/old", "-r", "--json"]));
+    assert!(removed["removed"].as_u64().unwrap() >= 1);
+    let listing = stdout(&cart(dir.path(), &["ls", "t.cart", "docs"]));
+    assert!(listing.contains("/docs/notes.txt\n") && !listing.contains("/docs/old"));
+}
+
+#[test]
+fn test_exit_codes() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a.txt"), b"a").unwrap();
+    stdout(&cart(dir.path(), &["put", "t.cart", "a.txt", "a.txt"]));
+
+    let missing_path = cart(dir.path(), &["cat", "t.cart", "nope.txt"]);
+    assert_eq!(missing_path.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&missing_path.stderr).contains("nope.txt"));
+    assert_eq!(cart(dir.path(), &["ls", "missing.cart"]).status.code(), Some(3));
+    assert_eq!(cart(dir.path(), &["rm", "t.cart", "nope.txt"]).status.code(), Some(3));
+
+    // Reading a directory as a cartridge is an I/O error, not "not found"
+    std::fs::create_dir(dir.path().join("dir.cart")).unwrap();
+    assert_eq!(cart(dir.path(), &["ls", "dir.cart"]).status.code(), Some(4));
+
+    assert_eq!(cart(dir.path(), &["frobnicate"]).status.code(), Some(2));
+    assert_eq!(cart(dir.path(), &["check", "t.cart"]).status.code(), Some(0));
+}
+
+#[test]
+fn test_stat_manifest_check_vacuum() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("big.bin"), vec![7u8; 20_000]).unwrap();
+    stdout(&cart(dir.path(), &["put", "t.cart", "big.bin", "data/big.bin"]));
+
+    let stat = json(&cart(dir.path(), &["stat", "t.cart", "data/big.bin", "--json"]));
+    assert_eq!(stat["type"], "file");
+    assert_eq!(stat["size"], 20_000);
+    assert_eq!(stat["blocks"], 5);
+    let text = stdout(&cart(dir.path(), &["stat", "t.cart", "data/big.bin"]));
+    assert!(text.contains("size: 20000\n"), "{text}");
+
+    let summary = json(&cart(dir.path(), &["stat", "t.cart", "--json"]));
+    assert_eq!(summary["slug"], "t");
+    assert_eq!(summary["page_size"], 4096);
+
+    let manifest = json(&cart(
+        dir.path(),
+        &["manifest", "t.cart", "--set", "title=Renamed", "--set", "version=2.0.0"],
+    ));
+    assert_eq!(manifest["title"], "Renamed");
+    assert_eq!(manifest["version"], "2.0.0");
+    assert_eq!(json(&cart(dir.path(), &["manifest", "t.cart"]))["title"], "Renamed");
+
+    assert_eq!(stdout(&cart(dir.path(), &["check", "t.cart"])), "ok\n");
+    let vacuum = json(&cart(dir.path(), &["vacuum", "t.cart", "--json"]));
+    assert!(vacuum["bytes_reclaimed"].is_u64());
+    assert_eq!(stdout(&cart(dir.path(), &["cat", "t.cart", "data/big.bin"])).len(), 20_000);
+}
+
+#[test]
+fn test_snapshot_create_list_restore() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("v1.txt"), b"version one").unwrap();
+    std::fs::write(dir.path().join("v2.txt"), b"version two").unwrap();
+    stdout(&cart(dir.path(), &["put", "t.cart", "v1.txt", "doc.txt"]));
+
+    let created = json(&cart(dir.path(), &["snapshot", "create", "t.cart", "first", "--json"]));
+    let id = created["id"].as_u64().unwrap();
+    assert!(dir.path().join("t.snapshots").is_dir());
+
+    stdout(&cart(dir.path(), &["put", "t.cart", "v2.txt", "doc.txt"]));
+    assert_eq!(stdout(&cart(dir.path(), &["cat", "t.cart", "doc.txt"])), "version two");
+
+    let list = json(&cart(dir.path(), &["snapshot", "list", "t.cart", "--json"]));
+    assert_eq!(list[0]["id"], id);
+    assert_eq!(list[0]["name"], "first");
+
+    // By name, then by id
+    stdout(&cart(dir.path(), &["snapshot", "restore", "t.cart", "first"]));
+    assert_eq!(stdout(&cart(dir.path(), &["cat", "t.cart", "doc.txt"])), "version one");
+    stdout(&cart(dir.path(), &["snapshot", "restore", "t.cart", &id.to_string()]));
+    assert_eq!(stdout(&cart(dir.path(), &["check", "t.cart"])), "ok\n");
+
+    let missing = cart(dir.path(), &["snapshot", "restore", "t.cart", "nope"]);
+    assert_eq!(missing.status.code(), Some(3));
+    let no_dir = cart(dir.path(), &["snapshot", "list", "t.cart", "--dir", "elsewhere"]);
+    assert_eq!(no_dir.status.code(), Some(3));
+}
@@ -15,7 +15,7 @@ fn test_10_concurrent_readers_2_writers() {
     {
         let mut c = cart.write();
         for i in 0..50 {
-            c.write(&format!("/file{}.txt", i), format!("data{}", i).as_bytes()).unwrap();
+            c.write(format!("/file{}.txt", i), format!("data{}", i).as_bytes()).unwrap();
         }
     }
 
@@ -26,14 +26,14 @@ fn test_10_concurrent_readers_2_writers() {
                 // Writer thread
                 for i in 0..100 {
                     let mut c = cart_clone.write();
-                    c.write(&format!("/writer{}_{}.txt", thread_id, i), b"new data").unwrap();
+                    c.write(format!("/writer{}_{}.txt", thread_id, i), b"new data").unwrap();
                 }
             } else {
                 // Reader thread
                 for _ in 0..1000 {
                     let c = cart_clone.read();
                     let idx = rand::random::<usize>() % 50;
-                    let _ = c.read(&format!("/file{}.txt", idx));
+                    let _ = c.read(format!("/file{}.txt", idx));
                 }
             }
         })
@@ -79,7 +79,7 @@ fn test_reader_writer_lock_fairness() {
         std::thread::spawn(move || {
             for i in 0..50 {
                 let mut c = cart_clone.write();
-                c.write(&format!("/w{}_{}.txt", thread_id, i), b"data").unwrap();
+                c.write(format!("/w{}_{}.txt", thread_id, i), b"data").unwrap();
                 write_count_clone.fetch_add(1, Ordering::Relaxed);
             }
         })
@@ -106,7 +106,7 @@ fn test_concurrent_deletes() {
     {
         let mut c = cart.write();
         for i in 0..100 {
-            c.write(&format!("/file{}.txt", i), b"data").unwrap();
+            c.write(format!("/file{}.txt", i), b"data").unwrap();
         }
     }
 
@@ -117,7 +117,7 @@ fn test_concurrent_deletes() {
             for i in 0..10 {
                 let file_idx = thread_id * 10 + i;
                 let mut c = cart_clone.write();
-                c.delete(&format!("/file{}.txt", file_idx)).unwrap();
+                c.delete(format!("/file{}.txt", file_idx)).unwrap();
             }
         })
     }).collect();
@@ -149,7 +149,7 @@ fn test_concurrent_mixed_operations() {
                     // Writer
                     for i in 0..50 {
                         let mut c = cart_clone.write();
-                        c.write(&format!("/w{}_{}.txt", thread_id, i), b"data").unwrap();
+                        c.write(format!("/w{}_{}.txt", thread_id, i), b"data").unwrap();
                     }
                 }
                 1 => {
@@ -190,7 +190,7 @@ fn test_concurrent_metadata_reads() {
     {
         let mut c = cart.write();
         for i in 0..30 {
-            c.write(&format!("/file{}.txt", i), &vec![i as u8; 1024]).unwrap();
+            c.write(format!("/file{}.txt", i), &vec![i as u8; 1024]).unwrap();
         }
     }
 
@@ -201,7 +201,7 @@ fn test_concurrent_metadata_reads() {
             for _ in 0..200 {
                 let c = cart_clone.read();
                 let idx = rand::random::<usize>() % 30;
-                let meta = c.metadata(&format!("/file{}.txt", idx)).unwrap();
+                let meta = c.metadata(format!("/file{}.txt", idx)).unwrap();
                 assert_eq!(meta.size, 1024);
             }
         })
@@ -213,3 +213,58 @@ fn test_concurrent_metadata_reads() {
 
     std::fs::remove_file("metadata-reads.cart").ok();
 }
+
+#[test]
+fn test_shared_cartridge_readers_and_writers() {
+    const READERS: usize = 8;
+    const WRITERS: usize = 4;
+    const WRITES_PER_WRITER: usize = 100;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let shared = Cartridge::create_at(temp_dir.path().join("shared-stress"), "shared-stress", "Shared Stress")
+        .unwrap()
+        .into_shared();
+
+    // Fixed files the readers check throughout
+    for i in 0..20 {
+        shared.write(format!("/fixed/{}.txt", i), format!("fixed{}", i).as_bytes()).unwrap();
+    }
+
+    let reads = Arc::new(AtomicUsize::new(0));
+    let handles: Vec<_> = (0..READERS + WRITERS).map(|thread_id| {
+        let handle = shared.clone();
+        let reads = reads.clone();
+        std::thread::spawn(move || {
+            if thread_id < WRITERS {
+                for i in 0..WRITES_PER_WRITER {
+                    let path = format!("/writer{}/{}.txt", thread_id, i);
+                    handle.write(&path, format!("{}:{}", thread_id, i).as_bytes()).unwrap();
+                    if i % 25 == 0 {
+                        handle.flush().unwrap();
+                    }
+                }
+            } else {
+                for n in 0..500 {
+                    let idx = (thread_id + n) % 20;
+                    let data = handle.read(format!("/fixed/{}.txt", idx)).unwrap();
+                    assert_eq!(data, format!("fixed{}", idx).as_bytes());
+                    reads.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        })
+    }).collect();
+
+    for h in handles {
+        h.join().unwrap();
+    }
+
+    assert_eq!(reads.load(Ordering::Relaxed), READERS * 500);
+    for writer in 0..WRITERS {
+        for i in 0..WRITES_PER_WRITER {
+            let data = shared.read(format!("/writer{}/{}.txt", writer, i)).unwrap();
+            assert_eq!(data, format!("{}:{}", writer, i).as_bytes());
+        }
+    }
+    shared.flush().unwrap();
+    assert!(shared.with_read(|cart| cart.check()).unwrap().is_consistent());
+}
@@ -144,7 +144,7 @@ fn test_multiple_database_files() {
         std::thread::spawn(move || {
             for _ in 0..50 {
                 let c = cart_clone.read();
-                let data = c.read(&format!("/db{}.sqlite", db_idx + 1)).unwrap();
+                let data = c.read(format!("/db{}.sqlite", db_idx + 1)).unwrap();
                 assert!(!data.is_empty());
             }
         })
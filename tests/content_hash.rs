@@ -0,0 +1,119 @@
+//! Content hashes stored in the catalog for sync tooling
+
+use cartridge_rs::Cartridge;
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+fn hex(hash: [u8; 32]) -> String {
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn test_hash_matches_independent_digest_and_survives_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("hashes.cart");
+    let big: Vec<u8> = (0..200_000u32).map(|i| (i % 241) as u8).collect();
+
+    let mut cart = Cartridge::create_at(&path, "hashes", "Hashes").unwrap();
+    cart.write("small.txt", b"hello").unwrap();
+    cart.write("big.bin", &big).unwrap();
+    cart.write_stream("streamed.bin", &mut Cursor::new(&big)).unwrap();
+    assert_eq!(cart.hash("small.txt").unwrap(), sha256(b"hello"));
+    assert_eq!(cart.hash("big.bin").unwrap(), sha256(&big));
+    drop(cart);
+
+    let mut cart = Cartridge::open(&path).unwrap();
+    let stored = cart.metadata("streamed.bin").unwrap().content_hash;
+    assert_eq!(stored, Some(sha256(&big)));
+    assert_eq!(cart.hash("big.bin").unwrap(), sha256(&big));
+
+    let entries = cart.list_entries("/").unwrap();
+    let entry = entries.iter().find(|e| e.path == "/small.txt").unwrap();
+    assert_eq!(entry.content_hash.as_deref(), Some(hex(sha256(b"hello")).as_str()));
+}
+
+#[test]
+fn test_hash_changes_on_modify_and_not_on_rename_or_clone() {
+    let mut cart = Cartridge::in_memory("hash-changes", "Hash Changes").unwrap();
+    cart.write("a.txt", b"version one").unwrap();
+    let original = cart.hash("a.txt").unwrap();
+
+    cart.write("a.txt", b"version two").unwrap();
+    let modified = cart.hash("a.txt").unwrap();
+    assert_ne!(modified, original);
+    assert_eq!(modified, sha256(b"version two"));
+
+    // Partial writes keep the stored digest current
+    let page = vec![7u8; 8192];
+    cart.write("pages.bin", &page).unwrap();
+    cart.write_at("pages.bin", 4096, b"patched").unwrap();
+    let mut expected = page.clone();
+    expected[4096..4103].copy_from_slice(b"patched");
+    assert_eq!(cart.metadata("pages.bin").unwrap().content_hash, Some(sha256(&expected)));
+    assert_eq!(cart.hash("pages.bin").unwrap(), sha256(&expected));
+
+    cart.inner_mut().rename("a.txt", "b.txt").unwrap();
+    assert_eq!(cart.hash("b.txt").unwrap(), modified);
+    cart.clone_file("b.txt", "c.txt").unwrap();
+    assert_eq!(cart.hash("c.txt").unwrap(), modified);
+
+    // Through a symlink, the target's digest
+    cart.symlink("c.txt", "link").unwrap();
+    assert_eq!(cart.hash("link").unwrap(), modified);
+}
+
+#[test]
+fn test_appends_and_truncates_keep_hash_current() {
+    let mut cart = Cartridge::in_memory("hash-partial", "Hash Partial").unwrap();
+    let stored = |cart: &Cartridge| cart.metadata("log.bin").unwrap().content_hash;
+
+    let mut expected: Vec<u8> = (0..10_000u32).map(|i| (i % 199) as u8).collect();
+    cart.write("log.bin", &expected).unwrap();
+    for round in 0..3u8 {
+        let more = vec![round; 3000];
+        cart.inner_mut().append_file("log.bin", &more).unwrap();
+        expected.extend_from_slice(&more);
+        assert_eq!(stored(&cart), Some(sha256(&expected)));
+    }
+
+    // Past the end, with a gap of whole pages left as holes
+    cart.write_at("log.bin", 40_000, b"tail").unwrap();
+    expected.resize(40_000, 0);
+    expected.extend_from_slice(b"tail");
+    assert_eq!(stored(&cart), Some(sha256(&expected)));
+
+    cart.truncate("log.bin", 5000).unwrap();
+    expected.truncate(5000);
+    assert_eq!(stored(&cart), Some(sha256(&expected)));
+    cart.truncate("log.bin", 9000).unwrap();
+    expected.resize(9000, 0);
+    assert_eq!(stored(&cart), Some(sha256(&expected)));
+
+    // A stream is hashed as its chunks are appended
+    let big: Vec<u8> = (0..300_000u32).map(|i| (i % 97) as u8).collect();
+    cart.write_stream("log.bin", &mut Cursor::new(&big)).unwrap();
+    assert_eq!(stored(&cart), Some(sha256(&big)));
+}
+
+#[test]
+fn test_encrypted_content_has_no_stored_hash() {
+    let mut cart = Cartridge::in_memory("hash-sealed", "Hash Sealed").unwrap();
+    let key = [7u8; 32];
+    cart.write_encrypted("private.txt", b"1234", &key).unwrap();
+    assert_eq!(cart.metadata("private.txt").unwrap().content_hash, None);
+
+    cart.enable_encryption(&[9u8; 32]).unwrap();
+    cart.write("sealed.txt", b"1234").unwrap();
+    assert_eq!(cart.metadata("sealed.txt").unwrap().content_hash, None);
+    let entries = cart.list_entries("/").unwrap();
+    let entry = entries.iter().find(|e| e.path == "/sealed.txt").unwrap();
+    assert_eq!(entry.content_hash, None);
+
+    // Asking still works, but the answer isn't written to the catalog
+    assert_eq!(cart.hash("sealed.txt").unwrap(), sha256(b"1234"));
+    assert_eq!(cart.metadata("sealed.txt").unwrap().content_hash, None);
+}
@@ -0,0 +1,163 @@
+//! Copying trees into a cartridge from another cartridge or a host
+//! directory through `copy_tree_from`
+
+use cartridge_rs::{
+    Cartridge, CartridgeError, CopyOptions, LocalVfs, MetadataPatch, OverwritePolicy,
+};
+use std::collections::HashMap;
+use std::time::{Duration, UNIX_EPOCH};
+
+fn source_cartridge() -> Cartridge {
+    let mut src = Cartridge::in_memory("copy-src", "Copy Source").unwrap();
+    let big: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+    src.write("site/index.html", b"<h1>hi</h1>").unwrap();
+    src.write("site/assets/logo.bin", &big).unwrap();
+    src.write("site/assets/logo.psd", b"layers").unwrap();
+    src.write("other/ignored.txt", b"not under the prefix")
+        .unwrap();
+    src.create_dir("site/empty").unwrap();
+    src.update_metadata_bulk(vec![
+        (
+            "site/index.html",
+            MetadataPatch::times(Some(1_000), Some(2_000)).with_user_metadata("lang", "en"),
+        ),
+        (
+            "site/assets/logo.bin",
+            MetadataPatch::default().with_content_type("image/x-custom"),
+        ),
+    ])
+    .unwrap();
+    src
+}
+
+#[test]
+fn test_cartridge_to_cartridge_preserves_content_and_metadata() {
+    let src = source_cartridge();
+    let mut dst = Cartridge::in_memory("copy-dst", "Copy Destination").unwrap();
+
+    let report = dst
+        .copy_tree_from(&src, "/site", "/release", &CopyOptions::default())
+        .unwrap();
+    assert_eq!(report.files_copied, 3);
+    assert_eq!(report.directories_created, 1);
+    assert_eq!(report.bytes_copied, 11 + 100_000 + 6);
+    assert!(report.skipped.is_empty());
+
+    assert_eq!(dst.read("release/index.html").unwrap(), b"<h1>hi</h1>");
+    assert_eq!(
+        dst.read("release/assets/logo.bin").unwrap(),
+        src.read("site/assets/logo.bin").unwrap()
+    );
+    assert!(dst.is_dir("release/empty").unwrap());
+    assert!(!dst.exists("release/ignored.txt").unwrap());
+    assert!(!dst.exists("other/ignored.txt").unwrap());
+
+    let index = dst.metadata("release/index.html").unwrap();
+    assert_eq!((index.created_at, index.modified_at), (1_000, 2_000));
+    assert_eq!(index.user_metadata["lang"], "en");
+    assert_eq!(index.content_type.as_deref(), Some("text/html"));
+    let logo = dst.metadata("release/assets/logo.bin").unwrap();
+    assert_eq!(logo.content_type.as_deref(), Some("image/x-custom"));
+}
+
+#[test]
+fn test_filters_and_dry_run() {
+    let src = source_cartridge();
+    let mut dst = Cartridge::in_memory("copy-dry", "Copy Dry Run").unwrap();
+    let options = CopyOptions {
+        include: Some("assets/**".to_string()),
+        exclude: Some("**/*.psd".to_string()),
+        dry_run: true,
+        ..Default::default()
+    };
+
+    let report = dst.copy_tree_from(&src, "site", "", &options).unwrap();
+    assert_eq!(report.files_copied, 1);
+    assert_eq!(report.bytes_copied, 100_000);
+    assert_eq!(report.directories_created, 0);
+    assert!(!dst.exists("assets/logo.bin").unwrap());
+
+    let options = CopyOptions {
+        dry_run: false,
+        ..options
+    };
+    let copied = dst.copy_tree_from(&src, "site", "", &options).unwrap();
+    assert_eq!(copied, report);
+    assert!(dst.exists("assets/logo.bin").unwrap());
+    assert!(!dst.exists("assets/logo.psd").unwrap());
+    assert!(!dst.exists("index.html").unwrap());
+}
+
+#[test]
+fn test_overwrite_policies() {
+    let src = source_cartridge();
+    let mut dst = Cartridge::in_memory("copy-overwrite", "Copy Overwrite").unwrap();
+    dst.write("index.html", b"old").unwrap();
+    dst.set_metadata(
+        "index.html",
+        HashMap::from([("stale".to_string(), "1".to_string())]),
+    )
+    .unwrap();
+
+    let err = dst
+        .copy_tree_from(&src, "site", "/", &CopyOptions::default())
+        .unwrap_err();
+    assert!(matches!(err, CartridgeError::AlreadyExists { .. }));
+    // Conflicts are found before anything is written
+    assert!(!dst.exists("assets/logo.bin").unwrap());
+
+    let skip = CopyOptions {
+        overwrite: OverwritePolicy::Skip,
+        ..Default::default()
+    };
+    let report = dst.copy_tree_from(&src, "site", "/", &skip).unwrap();
+    assert_eq!(report.files_copied, 2);
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(report.skipped[0].path, "/site/index.html");
+    assert_eq!(dst.read("index.html").unwrap(), b"old");
+
+    let replace = CopyOptions {
+        overwrite: OverwritePolicy::Replace,
+        ..Default::default()
+    };
+    let report = dst.copy_tree_from(&src, "site", "/", &replace).unwrap();
+    assert_eq!(report.files_copied, 3);
+    assert_eq!(dst.read("index.html").unwrap(), b"<h1>hi</h1>");
+    let metadata = dst.metadata("index.html").unwrap();
+    assert_eq!(
+        metadata.user_metadata.get("lang").map(String::as_str),
+        Some("en")
+    );
+    assert!(!metadata.user_metadata.contains_key("stale"));
+}
+
+#[test]
+fn test_local_directory_to_cartridge() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("data/nested")).unwrap();
+    std::fs::create_dir_all(dir.path().join("data/empty")).unwrap();
+    std::fs::write(dir.path().join("data/a.json"), b"{}").unwrap();
+    std::fs::write(dir.path().join("data/nested/b.txt"), b"bee").unwrap();
+    let modified = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+    std::fs::File::options()
+        .write(true)
+        .open(dir.path().join("data/a.json"))
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+
+    let src = LocalVfs::new(dir.path()).unwrap();
+    let mut dst = Cartridge::in_memory("copy-local", "Copy Local").unwrap();
+    let report = dst
+        .copy_tree_from(&src, "/data", "imported", &CopyOptions::default())
+        .unwrap();
+
+    assert_eq!(report.files_copied, 2);
+    assert_eq!(report.bytes_copied, 5);
+    assert_eq!(report.directories_created, 2);
+    assert_eq!(dst.read("imported/nested/b.txt").unwrap(), b"bee");
+    assert!(dst.is_dir("imported/empty").unwrap());
+    let metadata = dst.metadata("imported/a.json").unwrap();
+    assert_eq!(metadata.modified_at, 1_500_000_000);
+    assert_eq!(metadata.content_type.as_deref(), Some("application/json"));
+}
@@ -12,14 +12,14 @@ fn test_allocator_consistency_small_files() {
     // Allocate many small files (bitmap allocator)
     for i in 0..50 {
         let size = (i % 10 + 1) * 1024; // 1KB-10KB
-        cart.write(&format!("/small{}.txt", i), &vec![i as u8; size]).unwrap();
+        cart.write(format!("/small{}.txt", i), &vec![i as u8; size]).unwrap();
     }
 
     // Track allocated blocks
     let mut allocated_blocks = HashSet::new();
 
     for i in 0..50 {
-        let meta = cart.metadata(&format!("/small{}.txt", i)).unwrap();
+        let meta = cart.metadata(format!("/small{}.txt", i)).unwrap();
         // Ensure no block is allocated twice
         for &block in &meta.blocks {
             assert!(
@@ -40,13 +40,13 @@ fn test_allocator_consistency_large_files() {
 
     // Allocate large files (extent allocator)
     for i in 0..10 {
-        cart.write(&format!("/large{}.bin", i), &vec![i as u8; 512 * 1024]).unwrap();
+        cart.write(format!("/large{}.bin", i), &vec![i as u8; 512 * 1024]).unwrap();
     }
 
     let mut allocated_blocks = HashSet::new();
 
     for i in 0..10 {
-        let meta = cart.metadata(&format!("/large{}.bin", i)).unwrap();
+        let meta = cart.metadata(format!("/large{}.bin", i)).unwrap();
         for &block in &meta.blocks {
             assert!(
                 !allocated_blocks.contains(&block),
@@ -64,11 +64,11 @@ fn test_allocator_consistency_large_files() {
 fn test_allocator_free_blocks_tracking() {
     let mut cart = Cartridge::create("alloc-free", "Alloc Free").unwrap();
 
-    let initial_free = cart.header().free_blocks;
+    let _initial_free = cart.header().free_blocks;
 
     // Allocate files
     for i in 0..20 {
-        cart.write(&format!("/file{}.bin", i), &vec![0xAB; 64 * 1024]).unwrap();
+        cart.write(format!("/file{}.bin", i), &vec![0xAB; 64 * 1024]).unwrap();
     }
 
     let after_alloc_free = cart.header().free_blocks;
@@ -78,7 +78,7 @@ fn test_allocator_free_blocks_tracking() {
 
     // Delete half
     for i in 0..10 {
-        cart.delete(&format!("/file{}.bin", i)).unwrap();
+        cart.delete(format!("/file{}.bin", i)).unwrap();
     }
 
     let after_delete_free = cart.header().free_blocks;
@@ -94,9 +94,9 @@ fn test_allocator_mixed_workload() {
     // Mix small and large allocations
     for i in 0..20 {
         if i % 2 == 0 {
-            cart.write(&format!("/small{}.txt", i), &vec![i as u8; 10 * 1024]).unwrap();
+            cart.write(format!("/small{}.txt", i), &vec![i as u8; 10 * 1024]).unwrap();
         } else {
-            cart.write(&format!("/large{}.bin", i), &vec![i as u8; 512 * 1024]).unwrap();
+            cart.write(format!("/large{}.bin", i), &vec![i as u8; 512 * 1024]).unwrap();
         }
     }
 
@@ -132,7 +132,7 @@ fn test_allocator_after_growth() {
 
     // Force growth
     for i in 0..5 {
-        cart.write(&format!("/file{}.bin", i), &vec![0xCD; 1024 * 1024]).unwrap();
+        cart.write(format!("/file{}.bin", i), &vec![0xCD; 1024 * 1024]).unwrap();
     }
 
     let after_growth_total = cart.header().total_blocks;
@@ -154,12 +154,12 @@ fn test_allocator_fragmentation_resistance() {
         // Create files
         for i in 0..20 {
             let size = ((i * 7) % 50 + 10) * 1024; // Variable sizes
-            cart.write(&format!("/temp{}_{}.bin", round, i), &vec![i as u8; size]).unwrap();
+            cart.write(format!("/temp{}_{}.bin", round, i), &vec![i as u8; size]).unwrap();
         }
 
         // Delete odd-numbered files
         for i in (1..20).step_by(2) {
-            cart.delete(&format!("/temp{}_{}.bin", round, i)).unwrap();
+            cart.delete(format!("/temp{}_{}.bin", round, i)).unwrap();
         }
     }
 
@@ -28,7 +28,7 @@ fn test_corrupted_btree_node() {
 
     // Add files to build B-tree
     for i in 0..50 {
-        cart.write(&format!("/file{}.txt", i), b"data").unwrap();
+        cart.write(format!("/file{}.txt", i), b"data").unwrap();
     }
     drop(cart);
 
@@ -56,7 +56,7 @@ fn test_catalog_with_many_entries() {
     let mut cart = Cartridge::create("btree-many", "BTree Many").unwrap();
 
     for i in 0..200 {
-        cart.write(&format!("/file{:04}.txt", i), b"data").unwrap();
+        cart.write(format!("/file{:04}.txt", i), b"data").unwrap();
     }
 
     // Verify all can be listed (may include directory entries)
@@ -66,7 +66,7 @@ fn test_catalog_with_many_entries() {
     // Verify random access works
     for _ in 0..50 {
         let idx = rand::random::<usize>() % 200;
-        let data = cart.read(&format!("/file{:04}.txt", idx)).unwrap();
+        let data = cart.read(format!("/file{:04}.txt", idx)).unwrap();
         assert_eq!(data, b"data");
     }
 
@@ -79,12 +79,12 @@ fn test_catalog_rebuild_after_deletes() {
 
     // Add many files
     for i in 0..100 {
-        cart.write(&format!("/file{}.txt", i), b"data").unwrap();
+        cart.write(format!("/file{}.txt", i), b"data").unwrap();
     }
 
     // Delete half
     for i in (0..100).step_by(2) {
-        cart.delete(&format!("/file{}.txt", i)).unwrap();
+        cart.delete(format!("/file{}.txt", i)).unwrap();
     }
 
     // Verify remaining files are correct (may include directories)
@@ -93,12 +93,12 @@ fn test_catalog_rebuild_after_deletes() {
 
     // Verify deleted files are gone
     for i in (0..100).step_by(2) {
-        assert!(cart.read(&format!("/file{}.txt", i)).is_err());
+        assert!(cart.read(format!("/file{}.txt", i)).is_err());
     }
 
     // Verify remaining files exist
     for i in (1..100).step_by(2) {
-        assert!(cart.read(&format!("/file{}.txt", i)).is_ok());
+        assert!(cart.read(format!("/file{}.txt", i)).is_ok());
     }
 
     std::fs::remove_file("btree-deletes.cart").ok();
@@ -3,7 +3,7 @@
 //! Tests to verify that cartridge properly detects and reports various
 //! types of page-level corruption.
 
-use cartridge_rs::{Cartridge, CartridgeError};
+use cartridge_rs::{Cartridge, CartridgeBuilder, CartridgeError};
 use std::fs::{File, OpenOptions};
 use std::io::{Seek, SeekFrom, Write};
 
@@ -124,3 +124,105 @@ fn test_read_beyond_container_size() {
 
     std::fs::remove_file("corrupt-bounds.cart").ok();
 }
+
+#[test]
+fn test_flipped_content_byte_fails_checksum() {
+    // Large enough to get its own block rather than being inlined in the catalog
+    let payload = b"CHECKSUMMED-PAYLOAD-0123456789".repeat(32);
+    let mut cart = Cartridge::create("corrupt-flip", "Corrupt Flip Test").unwrap();
+    cart.write("/data.bin", &payload).unwrap();
+    cart.write("/other.bin", b"untouched").unwrap();
+    cart.flush().unwrap();
+    assert!(cart.verify().unwrap().is_empty());
+    drop(cart);
+
+    // Flip a single byte inside the payload, wherever it landed on disk
+    let raw = std::fs::read("corrupt-flip.cart").unwrap();
+    let pos = raw
+        .windows(payload.len())
+        .position(|w| w == payload.as_slice())
+        .expect("payload should be stored in plaintext");
+    let mut file = OpenOptions::new().write(true).open("corrupt-flip.cart").unwrap();
+    file.seek(SeekFrom::Start(pos as u64 + 5)).unwrap();
+    file.write_all(&[raw[pos + 5] ^ 0x01]).unwrap();
+    drop(file);
+
+    let cart = Cartridge::open("corrupt-flip.cart").unwrap();
+    match cart.read("/data.bin") {
+        Err(CartridgeError::ChecksumMismatch { path, .. }) => {
+            assert!(path.unwrap().ends_with("data.bin"));
+        }
+        other => panic!("expected ChecksumMismatch, got {:?}", other),
+    }
+    assert_eq!(cart.read("/other.bin").unwrap(), b"untouched");
+
+    let corrupted = cart.verify().unwrap();
+    assert_eq!(corrupted.len(), 1);
+    assert!(corrupted[0].ends_with("data.bin"));
+
+    std::fs::remove_file("corrupt-flip.cart").ok();
+}
+
+#[test]
+fn test_scrub_reports_files_sharing_a_bad_block() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("scrub.cart");
+    let payload = b"SCRUBBED-PAYLOAD-0123456789".repeat(40);
+    let mut cart = Cartridge::create_at(&path, "scrub", "Scrub Test").unwrap();
+    for i in 0..4 {
+        cart.write(format!("/file{i}.bin"), &[i as u8; 3000]).unwrap();
+    }
+    cart.write("/data.bin", &payload).unwrap();
+    cart.clone_file("/data.bin", "/copy.bin").unwrap();
+    cart.flush().unwrap();
+    drop(cart);
+
+    let raw = std::fs::read(&path).unwrap();
+    let pos = raw.windows(payload.len()).position(|w| w == payload.as_slice()).unwrap();
+    let mut file = OpenOptions::new().write(true).open(&path).unwrap();
+    file.seek(SeekFrom::Start(pos as u64 + 7)).unwrap();
+    file.write_all(&[raw[pos + 7] ^ 0x01]).unwrap();
+    drop(file);
+
+    let cart = Cartridge::open(&path).unwrap();
+    let mut pass = cart.scrub(None, 2).unwrap();
+    let mut calls = 1;
+    while let Some(token) = pass.next {
+        pass.merge(cart.scrub(Some(token), 2).unwrap());
+        calls += 1;
+    }
+    // One block per small file, plus the block data.bin and copy.bin share
+    assert_eq!(pass.pages_checked, 5);
+    assert_eq!(calls, 3);
+    assert_eq!(pass.errors.len(), 1);
+    assert_eq!(pass.damaged_paths(), ["/copy.bin", "/data.bin"]);
+
+    // Results survive a round trip, so a pass can be resumed across restarts
+    let json = serde_json::to_string(&pass).unwrap();
+    assert_eq!(serde_json::from_str::<cartridge_rs::ScrubResult>(&json).unwrap(), pass);
+}
+
+#[test]
+fn test_flipped_byte_without_checksums_goes_undetected() {
+    let mut cart = CartridgeBuilder::new()
+        .slug("corrupt-nocrc")
+        .title("No Checksums")
+        .page_checksums(false)
+        .build()
+        .unwrap();
+    cart.write("/data.bin", b"PLAIN-PAYLOAD").unwrap();
+    drop(cart);
+
+    let raw = std::fs::read("corrupt-nocrc.cart").unwrap();
+    let pos = raw.windows(13).position(|w| w == b"PLAIN-PAYLOAD").unwrap();
+    let mut file = OpenOptions::new().write(true).open("corrupt-nocrc.cart").unwrap();
+    file.seek(SeekFrom::Start(pos as u64)).unwrap();
+    file.write_all(b"Q").unwrap();
+    drop(file);
+
+    let cart = Cartridge::open("corrupt-nocrc.cart").unwrap();
+    assert_eq!(cart.read("/data.bin").unwrap(), b"QLAIN-PAYLOAD");
+    assert!(cart.verify().unwrap().is_empty());
+
+    std::fs::remove_file("corrupt-nocrc.cart").ok();
+}
@@ -1,17 +1,9 @@
 //! Engram freeze validation tests
-//!
-//! Note: EngramFreezer::freeze() expects &mut cartridge_rs::core::Cartridge
-//! but the public API exposes cartridge_rs::Cartridge wrapper.
-//! These tests are temporarily disabled until freeze API is exposed on the wrapper.
 
-#![allow(dead_code, unused_imports)]
-
-use cartridge_rs::Cartridge;
-use std::path::Path;
+use cartridge_rs::{Cartridge, FreezeOptions, SigningKey};
 use tempfile::TempDir;
 
 #[test]
-#[ignore] // Requires public freeze API on Cartridge wrapper
 fn test_freeze_basic() {
     let temp_dir = TempDir::new().unwrap();
     let engram_path = temp_dir.path().join("frozen.eng");
@@ -19,28 +11,35 @@ fn test_freeze_basic() {
     let mut cart = Cartridge::create("freeze-test", "Freeze Test").unwrap();
 
     for i in 0..50 {
-        cart.write(&format!("/file{}.txt", i), format!("data{}", i).as_bytes())
+        cart.write(format!("/file{}.txt", i), format!("data{}", i).as_bytes())
             .unwrap();
     }
     cart.flush().unwrap();
 
-    // Freeze to engram using EngramFreezer
-    use cartridge_rs::core::engram_integration::EngramFreezer;
-    let freezer = EngramFreezer::new_default(
-        "freeze-test".to_string(),
-        "1.0.0".to_string(),
-        "Test Author".to_string(),
-    );
-
-    freezer.freeze(&mut cart, &engram_path).unwrap();
+    // Freeze to a signed engram
+    let key = SigningKey::from_bytes(&[42u8; 32]);
+    let options = FreezeOptions {
+        author: Some("Test Author".to_string()),
+        signing_key: Some(key.clone()),
+        ..Default::default()
+    };
+    let report = cart.freeze(&engram_path, &options).unwrap();
+    assert_eq!(report.files_frozen, 51); // 50 files + container manifest
+    assert_eq!(report.public_key, Some(key.verifying_key().to_bytes()));
 
     // Verify engram was created
     assert!(engram_path.exists());
 
-    // Verify we can read it back
+    // Verify we can read it back and the signature validates
     use engram_rs::ArchiveReader;
     let mut reader = ArchiveReader::open(&engram_path).unwrap();
     reader.initialize().unwrap(); // REQUIRED in engram-rs 1.1.1+
+    assert!(reader.verify_archive_signature(&key.verifying_key()).unwrap());
+
+    let manifest = reader.read_manifest().unwrap().unwrap();
+    assert_eq!(manifest["author"], "Test Author");
+    assert!(manifest["id"].as_str().unwrap().starts_with("freeze-test-"));
+    assert!(reader.contains(".cartridge/manifest.json"));
 
     for i in 0..50 {
         let data = reader.read_file(&format!("file{}.txt", i)).unwrap();
@@ -51,7 +50,7 @@ fn test_freeze_basic() {
 }
 
 #[test]
-#[ignore] // Requires public freeze API + large size and time
+#[ignore] // Requires large size and time
 fn test_freeze_large_container() {
     let temp_dir = TempDir::new().unwrap();
     let engram_path = temp_dir.path().join("frozen-large.eng");
@@ -61,22 +60,16 @@ fn test_freeze_large_container() {
     // 10GB container (100 x 100MB files)
     for i in 0..100 {
         let data = vec![i as u8; 100 * 1024 * 1024]; // 100MB each
-        cart.write(&format!("/large{}.bin", i), &data).unwrap();
+        cart.write(format!("/large{}.bin", i), &data).unwrap();
     }
     cart.flush().unwrap();
 
     // Freeze should succeed
-    use cartridge_rs::core::engram_integration::EngramFreezer;
-    use engram_rs::CompressionMethod;
-    let freezer = EngramFreezer::new(
-        "freeze-large".to_string(),
-        "1.0.0".to_string(),
-        "Test".to_string(),
-        Some("Large container test".to_string()),
-        CompressionMethod::Zstd,
-    );
-
-    freezer.freeze(&mut cart, &engram_path).unwrap();
+    let options = FreezeOptions {
+        description: Some("Large container test".to_string()),
+        ..Default::default()
+    };
+    cart.freeze(&engram_path, &options).unwrap();
 
     // Verify size
     let eng_size = std::fs::metadata(&engram_path).unwrap().len();
@@ -90,7 +83,6 @@ fn test_freeze_large_container() {
 }
 
 #[test]
-#[ignore] // Requires public freeze API on Cartridge wrapper
 fn test_freeze_with_snapshots() {
     let temp_dir = TempDir::new().unwrap();
     let snapshot_dir = temp_dir.path().join("snapshots");
@@ -109,15 +101,13 @@ fn test_freeze_with_snapshots() {
     cart.create_snapshot("s2".to_string(), "V2".to_string(), &snapshot_dir)
         .unwrap();
 
-    // Freeze should capture current state (v2)
-    use cartridge_rs::core::engram_integration::EngramFreezer;
-    let freezer = EngramFreezer::new_default(
-        "freeze-snapshots".to_string(),
-        "1.0.0".to_string(),
-        "Test".to_string(),
-    );
-
-    freezer.freeze(&mut cart, &engram_path).unwrap();
+    // Freeze should capture current state (v2) plus both snapshots
+    let options = FreezeOptions {
+        snapshot_dir: Some(snapshot_dir.clone()),
+        ..Default::default()
+    };
+    let report = cart.freeze(&engram_path, &options).unwrap();
+    assert_eq!(report.snapshots_included, 2);
 
     // Verify engram has v2
     use engram_rs::ArchiveReader;
@@ -125,12 +115,13 @@ fn test_freeze_with_snapshots() {
     reader.initialize().unwrap(); // REQUIRED in engram-rs 1.1.1+
     let data = reader.read_file("file.txt").unwrap();
     assert_eq!(data, b"v2");
+    // metadata.json, pages.bin and catalog.bin for each snapshot
+    assert_eq!(reader.list_prefix(".cartridge/snapshots/").len(), 6);
 
     std::fs::remove_file("freeze-snapshots.cart").ok();
 }
 
 #[test]
-#[ignore] // Requires public freeze API on Cartridge wrapper
 fn test_freeze_with_compression_methods() {
     let temp_dir = TempDir::new().unwrap();
     let zstd_path = temp_dir.path().join("zstd.eng");
@@ -141,32 +132,25 @@ fn test_freeze_with_compression_methods() {
     // Create compressible data
     let data = vec![b'A'; 1024 * 1024]; // 1MB of 'A's
     for i in 0..10 {
-        cart.write(&format!("/file{}.txt", i), &data).unwrap();
+        cart.write(format!("/file{}.txt", i), &data).unwrap();
     }
     cart.flush().unwrap();
 
     // Freeze with Zstd
-    use cartridge_rs::core::engram_integration::EngramFreezer;
-    use engram_rs::CompressionMethod;
-
-    let zstd_freezer = EngramFreezer::new(
-        "zstd".to_string(),
-        "1.0".to_string(),
-        "Test".to_string(),
-        None,
-        CompressionMethod::Zstd,
-    );
-    zstd_freezer.freeze(&mut cart, &zstd_path).unwrap();
+    use cartridge_rs::core::engram_integration::CompressionMethod;
+
+    let zstd_options = FreezeOptions {
+        compression: CompressionMethod::Zstd,
+        ..Default::default()
+    };
+    cart.freeze(&zstd_path, &zstd_options).unwrap();
 
     // Freeze with LZ4
-    let lz4_freezer = EngramFreezer::new(
-        "lz4".to_string(),
-        "1.0".to_string(),
-        "Test".to_string(),
-        None,
-        CompressionMethod::Lz4,
-    );
-    lz4_freezer.freeze(&mut cart, &lz4_path).unwrap();
+    let lz4_options = FreezeOptions {
+        compression: CompressionMethod::Lz4,
+        ..Default::default()
+    };
+    cart.freeze(&lz4_path, &lz4_options).unwrap();
 
     // Both should exist and be compressed
     assert!(zstd_path.exists());
@@ -184,7 +168,6 @@ fn test_freeze_with_compression_methods() {
 }
 
 #[test]
-#[ignore] // Requires public freeze API on Cartridge wrapper
 fn test_freeze_empty_container() {
     let temp_dir = TempDir::new().unwrap();
     let engram_path = temp_dir.path().join("frozen-empty.eng");
@@ -193,14 +176,10 @@ fn test_freeze_empty_container() {
     cart.flush().unwrap();
 
     // Freeze empty container
-    use cartridge_rs::core::engram_integration::EngramFreezer;
-    let freezer = EngramFreezer::new_default(
-        "freeze-empty".to_string(),
-        "1.0.0".to_string(),
-        "Test".to_string(),
-    );
-
-    freezer.freeze(&mut cart, &engram_path).unwrap();
+    let report = cart
+        .freeze(&engram_path, &FreezeOptions::default())
+        .unwrap();
+    assert!(report.public_key.is_none());
 
     // Verify engram exists
     assert!(engram_path.exists());
@@ -216,7 +195,6 @@ fn test_freeze_empty_container() {
 }
 
 #[test]
-#[ignore] // Requires public freeze API on Cartridge wrapper
 fn test_freeze_mixed_file_sizes() {
     let temp_dir = TempDir::new().unwrap();
     let engram_path = temp_dir.path().join("frozen-mixed.eng");
@@ -230,15 +208,13 @@ fn test_freeze_mixed_file_sizes() {
 
     cart.flush().unwrap();
 
-    // Freeze
-    use cartridge_rs::core::engram_integration::EngramFreezer;
-    let freezer = EngramFreezer::new_default(
-        "freeze-mixed".to_string(),
-        "1.0.0".to_string(),
-        "Test".to_string(),
-    );
-
-    freezer.freeze(&mut cart, &engram_path).unwrap();
+    // Freeze, vacuuming first
+    let options = FreezeOptions {
+        vacuum: true,
+        ..Default::default()
+    };
+    let report = cart.freeze(&engram_path, &options).unwrap();
+    assert!(report.vacuum.is_some());
 
     // Verify all files
     use engram_rs::ArchiveReader;
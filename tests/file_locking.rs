@@ -0,0 +1,189 @@
+//! Advisory file locking tests
+//!
+//! A read-write handle locks its file exclusively; read-only handles take no
+//! lock and refresh to see what a writer flushed. Conflicts surface as
+//! `CartridgeError::Locked`.
+
+use cartridge_rs::{Cartridge, CartridgeError};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+const CHILD_ENV: &str = "CARTRIDGE_LOCK_TEST_CHILD";
+
+#[test]
+fn test_second_writer_is_locked() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("locked.cart");
+    let mut cart = Cartridge::create_at(&path, "locked", "Locked").unwrap();
+    cart.write("/a.txt", b"first").unwrap();
+    cart.flush().unwrap();
+
+    match Cartridge::open(&path) {
+        Err(CartridgeError::Locked { holder_pid, .. }) => {
+            assert_eq!(holder_pid, Some(std::process::id()))
+        }
+        other => panic!("expected Locked, got {:?}", other.map(|_| ())),
+    }
+    // Readers don't need the lock
+    let reader = Cartridge::open_read_only(&path).unwrap();
+    assert_eq!(reader.read("/a.txt").unwrap(), b"first");
+    drop(reader);
+    // Creating over an open cartridge must not truncate it
+    assert!(matches!(
+        Cartridge::create_at(&path, "locked", "Locked"),
+        Err(CartridgeError::Locked { .. })
+    ));
+    assert_eq!(cart.read("/a.txt").unwrap(), b"first");
+
+    cart.into_inner().close().unwrap();
+    let cart = Cartridge::open(&path).unwrap();
+    assert_eq!(cart.read("/a.txt").unwrap(), b"first");
+}
+
+#[test]
+fn test_open_or_create_does_not_truncate_open_cartridge() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("racing.cart");
+    let mut cart = Cartridge::open_or_create_at(&path, "racing", "Racing").unwrap();
+    cart.write("/a.txt", b"first").unwrap();
+    cart.flush().unwrap();
+
+    assert!(matches!(
+        Cartridge::open_or_create_at(&path, "racing", "Racing"),
+        Err(CartridgeError::Locked { .. })
+    ));
+    assert_eq!(cart.read("/a.txt").unwrap(), b"first");
+
+    drop(cart);
+    let cart = Cartridge::open_or_create_at(&path, "racing", "Racing").unwrap();
+    assert_eq!(cart.read("/a.txt").unwrap(), b"first");
+}
+
+#[test]
+fn test_readers_do_not_block_a_writer() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("shared.cart");
+    Cartridge::create_at(&path, "shared", "Shared").unwrap();
+
+    let first = Cartridge::open_read_only(&path).unwrap();
+    let second = Cartridge::open_read_only(&path).unwrap();
+    let mut writer = Cartridge::open(&path).unwrap();
+    writer.write("/b.txt", b"written alongside readers").unwrap();
+    writer.flush().unwrap();
+    assert!(matches!(Cartridge::open(&path), Err(CartridgeError::Locked { .. })));
+
+    drop(first);
+    drop(second);
+    drop(writer);
+    let cart = Cartridge::open(&path).unwrap();
+    assert_eq!(cart.read("/b.txt").unwrap(), b"written alongside readers");
+}
+
+#[test]
+fn test_open_with_timeout() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("timeout.cart");
+    let cart = Cartridge::create_at(&path, "timeout", "Timeout").unwrap();
+
+    let start = Instant::now();
+    assert!(matches!(
+        Cartridge::open_with_timeout(&path, Duration::from_millis(100)),
+        Err(CartridgeError::Locked { .. })
+    ));
+    assert!(start.elapsed() >= Duration::from_millis(100));
+
+    // Released by another thread while we wait
+    let holder = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        drop(cart);
+    });
+    Cartridge::open_with_timeout(&path, Duration::from_secs(10)).unwrap();
+    holder.join().unwrap();
+}
+
+#[test]
+#[ignore] // Re-runs this test binary as a second process
+fn test_lock_across_processes() {
+    if let Ok(path) = std::env::var(CHILD_ENV) {
+        match Cartridge::open(&path) {
+            Err(CartridgeError::Locked { holder_pid, .. }) => assert_eq!(holder_pid, None),
+            other => panic!("expected Locked, got {:?}", other.map(|_| ())),
+        }
+        return;
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cross-process.cart");
+    let _cart = Cartridge::create_at(&path, "cross-process", "Cross Process").unwrap();
+
+    let status = Command::new(std::env::current_exe().unwrap())
+        .args(["test_lock_across_processes", "--exact", "--ignored"])
+        .env(CHILD_ENV, &path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn test_reader_refreshes_to_see_writer_flushes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("refresh.cart");
+    let mut writer = Cartridge::create_at(&path, "refresh", "Refresh").unwrap();
+    writer.write("/a.txt", &[1u8; 2000]).unwrap();
+    writer.flush().unwrap();
+
+    let mut reader = Cartridge::open_read_only(&path).unwrap();
+    assert_eq!(reader.read("/a.txt").unwrap(), vec![1u8; 2000]);
+    assert!(!reader.is_stale().unwrap());
+    assert!(!reader.refresh().unwrap().changed);
+
+    // A patch in place changes no metadata, only a cached page
+    writer.write_at("/a.txt", 0, &[2u8; 10]).unwrap();
+    writer.write("/b.txt", b"new").unwrap();
+    writer.flush().unwrap();
+    assert!(!writer.is_stale().unwrap(), "its own flushes don't count");
+
+    assert!(reader.is_stale().unwrap());
+    assert_eq!(reader.read("/a.txt").unwrap()[0], 1, "served from the cache");
+    assert!(!reader.exists("/b.txt").unwrap());
+
+    let report = reader.refresh().unwrap();
+    assert!(report.changed);
+    assert_eq!(report.generation, writer.inner().stats().generation);
+    assert!(report.pages_dropped >= 1);
+    assert_eq!(reader.read("/a.txt").unwrap()[..11], [2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1]);
+    assert_eq!(reader.read("/b.txt").unwrap(), b"new");
+    assert!(!reader.is_stale().unwrap());
+}
+
+#[test]
+fn test_refresh_refuses_to_merge_local_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("conflict.cart");
+    let theirs_path = dir.path().join("theirs.cart");
+    let mut cart = Cartridge::create_at(&path, "conflict", "Conflict").unwrap();
+    cart.write("/a.txt", b"original").unwrap();
+    cart.into_inner().close().unwrap();
+
+    // The same cartridge, moved on by someone else
+    std::fs::copy(&path, &theirs_path).unwrap();
+    let mut theirs = Cartridge::open(&theirs_path).unwrap();
+    theirs.write("/theirs.txt", b"remote").unwrap();
+    theirs.flush().unwrap();
+    let generation = theirs.inner().stats().generation;
+    drop(theirs);
+
+    // Copied over the file while a writer holds its lock, which only
+    // something ignoring the lock would do
+    let mut mine = Cartridge::open(&path).unwrap();
+    mine.write("/mine.txt", b"local").unwrap();
+    std::fs::copy(&theirs_path, &path).unwrap();
+
+    assert!(mine.is_stale().unwrap());
+    assert!(matches!(
+        mine.refresh(),
+        Err(CartridgeError::Conflict { generation: g }) if g == generation
+    ));
+    assert!(mine.exists("/mine.txt").unwrap(), "local changes are kept");
+    assert!(!mine.exists("/theirs.txt").unwrap());
+}
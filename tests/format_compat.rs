@@ -0,0 +1,183 @@
+//! Format version and feature flag compatibility
+//!
+//! Headers are patched on disk to look like they came from a future
+//! release: a newer minor version, or feature flags this version has never
+//! heard of.
+
+use cartridge_rs::core::header::{
+    COMPAT_FEATURE_FLAGS_OFFSET, FEATURE_CASE_INSENSITIVE, FEATURE_ENCRYPTED,
+    FEATURE_FLAGS_OFFSET, FEATURE_INLINE_DATA, FEATURE_JOURNAL, FEATURE_PAGE_CHECKSUMS,
+    FEATURE_SEGMENTED_CATALOG, FEATURE_SPARSE, VERSION_MAJOR, VERSION_MINOR,
+};
+use cartridge_rs::{Cartridge, CartridgeError};
+use std::fs::OpenOptions;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Offset of `version_minor` in the header page
+const VERSION_MINOR_AT: u64 = 10;
+
+/// Offset of the reserved field in the header page
+const RESERVED_AT: u64 = 40;
+
+fn create(dir: &Path) -> PathBuf {
+    let path = dir.join("compat.cart");
+    let mut cart = Cartridge::create_at(&path, "compat", "Compat").unwrap();
+    cart.write("docs/readme.txt", b"written by 1.0").unwrap();
+    cart.flush().unwrap();
+    path
+}
+
+fn patch(path: &Path, offset: u64, bytes: &[u8]) {
+    let mut file = OpenOptions::new().write(true).open(path).unwrap();
+    file.seek(SeekFrom::Start(offset)).unwrap();
+    file.write_all(bytes).unwrap();
+}
+
+#[test]
+fn test_newer_minor_version_opens_read_only() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = create(dir.path());
+    patch(&path, VERSION_MINOR_AT, &(VERSION_MINOR + 1).to_le_bytes());
+
+    let mut cart = Cartridge::open(&path).unwrap();
+    assert!(cart.is_read_only());
+    assert_eq!(cart.read("docs/readme.txt").unwrap(), b"written by 1.0");
+    assert!(matches!(cart.write("docs/new.txt", b"x"), Err(CartridgeError::ReadOnly)));
+    drop(cart);
+
+    // Nothing was written back, so the file still claims the newer version
+    let cart = Cartridge::open_read_only(&path).unwrap();
+    assert_eq!(cart.header().version_minor, VERSION_MINOR + 1);
+}
+
+#[test]
+fn test_current_version_opens_read_write() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = create(dir.path());
+
+    let mut cart = Cartridge::open(&path).unwrap();
+    assert!(!cart.is_read_only());
+    cart.write("docs/new.txt", b"x").unwrap();
+}
+
+#[test]
+fn test_unknown_compatible_feature_is_ignored() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = create(dir.path());
+    let offset = RESERVED_AT + COMPAT_FEATURE_FLAGS_OFFSET as u64;
+    patch(&path, offset, &(1u64 << 40).to_le_bytes());
+
+    let mut cart = Cartridge::open(&path).unwrap();
+    assert!(!cart.is_read_only());
+    cart.write("docs/new.txt", b"x").unwrap();
+    assert_eq!(cart.read("docs/readme.txt").unwrap(), b"written by 1.0");
+    drop(cart);
+
+    // Flushing kept the flag for the newer readers that know it
+    let cart = Cartridge::open_read_only(&path).unwrap();
+    assert_eq!(cart.header().compat_features(), 1 << 40);
+}
+
+#[test]
+fn test_unknown_incompatible_feature_is_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = create(dir.path());
+    let offset = RESERVED_AT + FEATURE_FLAGS_OFFSET as u64;
+    let header = Cartridge::open_read_only(&path).unwrap().header().incompat_features();
+    patch(&path, offset, &(header | 1 << 40).to_le_bytes());
+
+    for result in [Cartridge::open(&path), Cartridge::open_read_only(&path)] {
+        match result {
+            Err(CartridgeError::UnsupportedFeatures { major, flags, .. }) => {
+                assert_eq!(major, VERSION_MAJOR);
+                assert_eq!(flags, 1 << 40);
+            }
+            other => panic!("expected UnsupportedFeatures, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    // Even from a newer minor version, which otherwise only forces read-only
+    patch(&path, VERSION_MINOR_AT, &(VERSION_MINOR + 1).to_le_bytes());
+    assert!(matches!(
+        Cartridge::open_read_only(&path),
+        Err(CartridgeError::UnsupportedFeatures { .. })
+    ));
+}
+
+#[test]
+fn test_newer_major_version_is_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = create(dir.path());
+    patch(&path, 8, &(VERSION_MAJOR + 1).to_le_bytes());
+
+    assert!(matches!(
+        Cartridge::open(&path),
+        Err(CartridgeError::UnsupportedVersion { .. })
+    ));
+}
+
+/// Incompatible features known to 1.0 readers
+const KNOWN_TO_1_0: u64 = FEATURE_ENCRYPTED.mask
+    | FEATURE_PAGE_CHECKSUMS.mask
+    | FEATURE_JOURNAL.mask
+    | FEATURE_CASE_INSENSITIVE.mask;
+
+fn incompat_features(path: &Path) -> u64 {
+    Cartridge::open_read_only(path).unwrap().header().incompat_features()
+}
+
+#[test]
+fn test_storage_features_flagged_only_once_used() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("features.cart");
+
+    // The manifest is small enough to be stored inline from the start
+    let mut cart = Cartridge::create_at(&path, "features", "Features").unwrap();
+    cart.write("blocks.bin", &[7u8; 3 * 4096]).unwrap();
+    cart.flush().unwrap();
+    drop(cart);
+    let features = incompat_features(&path);
+    assert_ne!(features & FEATURE_INLINE_DATA.mask, 0);
+    assert_eq!(features & (FEATURE_SPARSE.mask | FEATURE_SEGMENTED_CATALOG.mask), 0);
+
+    let mut cart = Cartridge::open(&path).unwrap();
+    cart.write_at("blocks.bin", 1 << 20, b"past a hole").unwrap();
+    cart.flush().unwrap();
+    drop(cart);
+    let features = incompat_features(&path);
+    assert_ne!(features & FEATURE_SPARSE.mask, 0);
+    assert_eq!(features & FEATURE_SEGMENTED_CATALOG.mask, 0);
+
+    let mut cart = Cartridge::open(&path).unwrap();
+    for i in 0..200 {
+        cart.write(format!("many/file-{i:04}.txt"), b"x").unwrap();
+    }
+    cart.flush().unwrap();
+    drop(cart);
+    assert_ne!(incompat_features(&path) & FEATURE_SEGMENTED_CATALOG.mask, 0);
+
+    let cart = Cartridge::open_read_only(&path).unwrap();
+    assert_eq!(cart.header().version_minor, VERSION_MINOR);
+    assert_eq!(cart.read("blocks.bin").unwrap().len(), (1 << 20) + 11);
+}
+
+#[test]
+fn test_older_reader_refuses_newer_storage_features() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = create(dir.path());
+    let mut cart = Cartridge::open(&path).unwrap();
+    cart.truncate("docs/readme.txt", 1 << 20).unwrap();
+    cart.flush().unwrap();
+    drop(cart);
+
+    let header = *Cartridge::open_read_only(&path).unwrap().header();
+    header.validate().unwrap();
+    match header.validate_for(KNOWN_TO_1_0) {
+        Err(CartridgeError::UnsupportedFeatures { major, flags, .. }) => {
+            assert_eq!(major, VERSION_MAJOR);
+            assert_eq!(flags, FEATURE_SPARSE.mask | FEATURE_INLINE_DATA.mask);
+        }
+        other => panic!("expected UnsupportedFeatures, got {:?}", other),
+    }
+}
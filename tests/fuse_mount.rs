@@ -0,0 +1,91 @@
+//! FUSE mount tests (run with `--features fuse` on Linux)
+//!
+//! Mounting needs `/dev/fuse` and `fusermount`; where either is missing the
+//! tests log why and return early.
+
+#![cfg(all(feature = "fuse", target_os = "linux"))]
+
+use cartridge_rs::{
+    mount, mount_with_options, Cartridge, MountHandle, MountOptions, SharedCartridge,
+};
+use std::fs;
+use std::path::Path;
+
+fn try_mount(
+    cart: &SharedCartridge,
+    mountpoint: &Path,
+    options: &MountOptions,
+) -> Option<MountHandle> {
+    match mount_with_options(cart.clone(), mountpoint, options) {
+        Ok(handle) => Some(handle),
+        Err(e) => {
+            eprintln!("skipping: can't mount FUSE here ({})", e);
+            None
+        }
+    }
+}
+
+#[test]
+fn test_mount_read_write() {
+    let dir = tempfile::tempdir().unwrap();
+    let mountpoint = dir.path().join("mnt");
+    fs::create_dir(&mountpoint).unwrap();
+    let mut cart = Cartridge::create_at(dir.path().join("rw.cart"), "rw", "RW").unwrap();
+    cart.write("docs/report.txt", b"quarterly numbers").unwrap();
+    let shared = cart.into_shared();
+
+    let Some(handle) = try_mount(&shared, &mountpoint, &MountOptions::default()) else {
+        return;
+    };
+
+    let names: Vec<String> = fs::read_dir(&mountpoint)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(names, ["docs"]);
+    assert!(mountpoint.join("docs").is_dir());
+    assert_eq!(
+        fs::read(mountpoint.join("docs/report.txt")).unwrap(),
+        b"quarterly numbers"
+    );
+
+    fs::write(mountpoint.join("docs/new.txt"), b"written through FUSE").unwrap();
+    fs::create_dir(mountpoint.join("empty")).unwrap();
+    fs::remove_file(mountpoint.join("docs/report.txt")).unwrap();
+    assert!(!mountpoint.join("docs/report.txt").exists());
+    handle.unmount().unwrap();
+
+    assert_eq!(shared.read("docs/new.txt").unwrap(), b"written through FUSE");
+    assert!(shared.metadata("empty").unwrap().is_directory());
+    assert!(!shared.exists("docs/report.txt").unwrap());
+}
+
+#[test]
+fn test_mount_read_only() {
+    let dir = tempfile::tempdir().unwrap();
+    let mountpoint = dir.path().join("mnt");
+    fs::create_dir(&mountpoint).unwrap();
+    let mut cart = Cartridge::create_at(dir.path().join("ro.cart"), "ro", "RO").unwrap();
+    cart.write("a.txt", b"read me").unwrap();
+    let shared = cart.into_shared();
+
+    let options = MountOptions {
+        read_only: true,
+        ..Default::default()
+    };
+    let Some(_handle) = try_mount(&shared, &mountpoint, &options) else {
+        return;
+    };
+
+    assert_eq!(fs::read(mountpoint.join("a.txt")).unwrap(), b"read me");
+    assert!(fs::write(mountpoint.join("b.txt"), b"nope").is_err());
+    assert!(fs::remove_file(mountpoint.join("a.txt")).is_err());
+    assert!(shared.exists("a.txt").unwrap());
+}
+
+#[test]
+fn test_mount_missing_mountpoint_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let cart = Cartridge::create_at(dir.path().join("m.cart"), "m", "M").unwrap();
+    assert!(mount(cart.into_shared(), &dir.path().join("does-not-exist")).is_err());
+}
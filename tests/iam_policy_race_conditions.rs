@@ -33,7 +33,7 @@ fn test_concurrent_access_to_protected_resources() {
     {
         let mut c = cart.write();
         for i in 0..20 {
-            c.write(&format!("/public/file{}.txt", i), b"data").unwrap();
+            c.write(format!("/public/file{}.txt", i), b"data").unwrap();
         }
     }
 
@@ -44,7 +44,7 @@ fn test_concurrent_access_to_protected_resources() {
             for _ in 0..100 {
                 let c = cart_clone.read();
                 let idx = rand::random::<usize>() % 20;
-                let _ = c.read(&format!("/public/file{}.txt", idx));
+                let _ = c.read(format!("/public/file{}.txt", idx));
             }
         })
     }).collect();
@@ -102,7 +102,7 @@ fn test_concurrent_metadata_access() {
     {
         let mut c = cart.write();
         for i in 0..15 {
-            c.write(&format!("/file{}.txt", i), &vec![i as u8; 1024]).unwrap();
+            c.write(format!("/file{}.txt", i), &vec![i as u8; 1024]).unwrap();
         }
     }
 
@@ -113,7 +113,7 @@ fn test_concurrent_metadata_access() {
             for _ in 0..100 {
                 let c = cart_clone.read();
                 for i in 0..15 {
-                    let _ = c.metadata(&format!("/file{}.txt", i));
+                    let _ = c.metadata(format!("/file{}.txt", i));
                 }
             }
         })
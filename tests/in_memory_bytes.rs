@@ -0,0 +1,66 @@
+//! In-memory cartridges serialized to and from `.cart` bytes
+
+use cartridge_rs::Cartridge;
+
+fn sample_files() -> Vec<(String, Vec<u8>)> {
+    (0..40)
+        .map(|i| {
+            // Mix of inlined, single-block and multi-block files
+            let len = [10, 3000, 20_000][i % 3];
+            (format!("data/file-{i:02}.bin"), vec![i as u8; len])
+        })
+        .collect()
+}
+
+#[test]
+fn test_round_trip_through_bytes() {
+    let mut cart = Cartridge::in_memory("wasm-cart", "Wasm Cart").unwrap();
+    for (path, content) in sample_files() {
+        cart.write(&path, &content).unwrap();
+    }
+    let bytes = cart.to_bytes().unwrap();
+
+    let mut loaded = Cartridge::from_bytes(&bytes).unwrap();
+    assert_eq!(loaded.slug().unwrap(), "wasm-cart");
+    for (path, content) in sample_files() {
+        assert_eq!(loaded.read(&path).unwrap(), content, "{path}");
+    }
+
+    // Changes to a loaded image come out on the next export
+    loaded.write("data/file-00.bin", b"changed").unwrap();
+    loaded.delete("data/file-01.bin").unwrap();
+    let again = Cartridge::from_bytes(&loaded.to_bytes().unwrap()).unwrap();
+    assert_eq!(again.read("data/file-00.bin").unwrap(), b"changed");
+    assert!(!again.exists("data/file-01.bin").unwrap());
+    assert_eq!(again.read("data/file-02.bin").unwrap(), vec![2u8; 20_000]);
+}
+
+#[test]
+fn test_bytes_open_from_disk() {
+    let mut cart = Cartridge::in_memory("exported", "Exported").unwrap();
+    for (path, content) in sample_files() {
+        cart.write(&path, &content).unwrap();
+    }
+    let bytes = cart.to_bytes().unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("exported.cart");
+    std::fs::write(&path, &bytes).unwrap();
+    let mut opened = Cartridge::open(&path).unwrap();
+    for (path, content) in sample_files() {
+        assert_eq!(opened.read(&path).unwrap(), content, "{path}");
+    }
+    assert!(opened.check().unwrap().is_consistent());
+
+    // A disk-backed cartridge exports the same way
+    opened.write("extra.txt", b"from disk").unwrap();
+    let from_disk = Cartridge::from_bytes(&opened.to_bytes().unwrap()).unwrap();
+    assert_eq!(from_disk.read("extra.txt").unwrap(), b"from disk");
+    assert_eq!(from_disk.read("data/file-05.bin").unwrap(), vec![5u8; 20_000]);
+}
+
+#[test]
+fn test_from_bytes_rejects_garbage() {
+    assert!(Cartridge::from_bytes(&[]).is_err());
+    assert!(Cartridge::from_bytes(&[0x42; 8192]).is_err());
+}
@@ -0,0 +1,129 @@
+//! LocalVfs behaves like a cartridge through the Vfs trait and stays inside
+//! its root
+
+use cartridge_rs::{Cartridge, CartridgeError, LocalVfs, PathError, Vfs};
+use std::io::Read;
+
+/// The same sequence of operations, run against any backend
+fn exercise(vfs: &mut dyn Vfs) {
+    vfs.write("/docs/a.txt", b"hello world").unwrap();
+    vfs.write("docs//sub/b.md", b"# b").unwrap();
+    vfs.write("top.json", b"{}").unwrap();
+
+    assert_eq!(vfs.read("docs/a.txt").unwrap(), b"hello world");
+    assert_eq!(vfs.read_range("/docs/a.txt", 6, 100).unwrap(), b"world");
+    assert!(vfs.read_range("/docs/a.txt", 50, 1).unwrap().is_empty());
+    let mut streamed = Vec::new();
+    vfs.open_reader("docs/a.txt")
+        .unwrap()
+        .read_to_end(&mut streamed)
+        .unwrap();
+    assert_eq!(streamed, b"hello world");
+
+    let content: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+    assert_eq!(
+        vfs.write_stream("big.bin", &mut content.as_slice()).unwrap(),
+        content.len() as u64
+    );
+    assert_eq!(vfs.read("/big.bin").unwrap(), content);
+    assert_eq!(vfs.metadata("big.bin").unwrap().size, content.len() as u64);
+
+    let children: Vec<String> = vfs
+        .list_children("/")
+        .unwrap()
+        .into_iter()
+        .map(|e| e.path)
+        .filter(|p| !p.starts_with("/.cartridge"))
+        .collect();
+    assert_eq!(children, ["/docs", "/big.bin", "/top.json"]);
+    let docs: Vec<(String, String)> = vfs
+        .list_children("docs")
+        .unwrap()
+        .into_iter()
+        .map(|e| (e.path, e.parent))
+        .collect();
+    assert_eq!(
+        docs,
+        [
+            ("/docs/sub".to_string(), "/docs".to_string()),
+            ("/docs/a.txt".to_string(), "/docs".to_string()),
+        ]
+    );
+    assert!(vfs
+        .list_entries("/docs")
+        .unwrap()
+        .iter()
+        .any(|e| e.path == "/docs/sub/b.md"));
+
+    assert!(vfs.is_dir("/docs").unwrap());
+    assert!(!vfs.is_dir("/docs/a.txt").unwrap());
+    assert!(vfs.exists("top.json").unwrap());
+    assert!(matches!(vfs.read("missing.txt"), Err(CartridgeError::NotFound { .. })));
+
+    vfs.delete("top.json").unwrap();
+    assert!(!vfs.exists("/top.json").unwrap());
+}
+
+#[test]
+fn test_cartridge_and_local_vfs_agree() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut cart = Cartridge::create_at(dir.path().join("agree"), "agree", "Agree").unwrap();
+    exercise(&mut cart);
+
+    let root = tempfile::tempdir().unwrap();
+    let mut local = LocalVfs::new(root.path()).unwrap();
+    exercise(&mut local);
+    assert!(root.path().join("docs/sub/b.md").is_file());
+}
+
+#[test]
+fn test_local_vfs_rejects_paths_outside_root() {
+    let parent = tempfile::tempdir().unwrap();
+    std::fs::create_dir(parent.path().join("root")).unwrap();
+    std::fs::write(parent.path().join("secret.txt"), b"secret").unwrap();
+    let mut local = LocalVfs::new(parent.path().join("root")).unwrap();
+
+    assert!(matches!(
+        local.read("../secret.txt"),
+        Err(CartridgeError::InvalidFilePath {
+            reason: PathError::DotSegment { .. },
+            ..
+        })
+    ));
+    assert!(local.write("a/../../escape.txt", b"x").is_err());
+    assert!(!parent.path().join("escape.txt").exists());
+
+    #[cfg(unix)]
+    {
+        let escapes = |r: cartridge_rs::Result<()>| {
+            matches!(
+                r,
+                Err(CartridgeError::InvalidFilePath {
+                    reason: PathError::EscapesRoot,
+                    ..
+                })
+            )
+        };
+        std::os::unix::fs::symlink(parent.path(), parent.path().join("root/link")).unwrap();
+        assert!(escapes(local.read("link/secret.txt").map(drop)));
+        assert!(escapes(local.write("link/planted.txt", b"x")));
+        assert!(!parent.path().join("planted.txt").exists());
+
+        // A dangling link would create its target when written through
+        let target = parent.path().join("created.txt");
+        std::os::unix::fs::symlink(&target, parent.path().join("root/dangling")).unwrap();
+        assert!(escapes(local.write("dangling", b"x")));
+        assert!(!target.exists());
+    }
+}
+
+#[test]
+fn test_local_vfs_root_must_be_a_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("file"), b"x").unwrap();
+    assert!(matches!(
+        LocalVfs::new(dir.path().join("file")),
+        Err(CartridgeError::NotADirectory { .. })
+    ));
+    assert!(LocalVfs::new(dir.path().join("missing")).is_err());
+}
@@ -103,9 +103,9 @@ fn test_buffer_overflow_protection() {
     // Write various sizes
     for size in [1, 10, 100, 1000, 10000, 100000] {
         let data = vec![0xAB; size];
-        cart.write(&format!("/file_{}.bin", size), &data).unwrap();
+        cart.write(format!("/file_{}.bin", size), &data).unwrap();
 
-        let read_data = cart.read(&format!("/file_{}.bin", size)).unwrap();
+        let read_data = cart.read(format!("/file_{}.bin", size)).unwrap();
         assert_eq!(read_data.len(), size);
         assert!(read_data.iter().all(|&b| b == 0xAB));
     }
@@ -147,7 +147,7 @@ fn test_integer_overflow_protection() {
 
     for &size in &sizes {
         let data = vec![0xFF; size];
-        cart.write(&format!("/size_{}.bin", size), &data).unwrap();
+        cart.write(format!("/size_{}.bin", size), &data).unwrap();
     }
 
     std::fs::remove_file("int-overflow.cart").ok();
@@ -165,7 +165,7 @@ fn test_data_race_detection() {
     let writer = std::thread::spawn(move || {
         for i in 0..100 {
             let mut c = cart_write.write();
-            c.write(&format!("/file{}.txt", i), b"data").unwrap();
+            c.write(format!("/file{}.txt", i), b"data").unwrap();
         }
     });
 
@@ -220,7 +220,7 @@ fn test_allocation_failure_handling() {
     let mut allocated = 0;
     for i in 0..1000 {
         let data = vec![0xAB; 512 * 1024]; // 512KB each
-        match cart.write(&format!("/large{}.bin", i), &data) {
+        match cart.write(format!("/large{}.bin", i), &data) {
             Ok(_) => allocated += 1,
             Err(_) => break, // Graceful failure
         }
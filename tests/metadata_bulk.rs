@@ -0,0 +1,88 @@
+//! Bulk metadata updates and host times through `import_dir`
+
+use cartridge_rs::{Cartridge, ImportOptions, MetadataPatch};
+use std::time::{Duration, UNIX_EPOCH};
+
+#[test]
+fn test_bulk_update_of_many_entries_persists() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("bulk.cart");
+    let mut cart = Cartridge::create_at(&path, "bulk", "Bulk").unwrap();
+    for i in 0..1_000 {
+        cart.write(format!("files/{:04}.dat", i), &[i as u8]).unwrap();
+    }
+
+    let updates = (0..1_000)
+        .map(|i| {
+            let patch = MetadataPatch::times(Some(1_000 + i), Some(2_000 + i))
+                .with_content_type("application/x-bulk")
+                .with_user_metadata("index", i.to_string());
+            (format!("files/{:04}.dat", i), patch)
+        })
+        .collect();
+    cart.update_metadata_bulk(updates).unwrap();
+    drop(cart);
+
+    let cart = Cartridge::open(&path).unwrap();
+    for i in [0u64, 1, 500, 999] {
+        let metadata = cart.metadata(format!("files/{:04}.dat", i)).unwrap();
+        assert_eq!(metadata.created_at, 1_000 + i);
+        assert_eq!(metadata.modified_at, 2_000 + i);
+        assert_eq!(metadata.content_type.as_deref(), Some("application/x-bulk"));
+        assert_eq!(metadata.user_metadata["index"], i.to_string());
+    }
+    assert_eq!(cart.read("files/0007.dat").unwrap(), [7]);
+}
+
+#[test]
+fn test_bulk_update_is_all_or_nothing() {
+    let mut cart = Cartridge::in_memory("bulk-invalid", "Bulk Invalid").unwrap();
+    cart.write("a.txt", b"a").unwrap();
+    let modified = cart.metadata("a.txt").unwrap().modified_at;
+
+    let result = cart.update_metadata_bulk(vec![
+        ("a.txt", MetadataPatch::times(None, Some(1))),
+        ("missing.txt", MetadataPatch::times(None, Some(1))),
+    ]);
+    assert!(result.is_err());
+    let result = cart.update_metadata_bulk(vec![(
+        "a.txt",
+        MetadataPatch::times(None, Some(1)).with_user_metadata("", "empty key"),
+    )]);
+    assert!(result.is_err());
+    assert_eq!(cart.metadata("a.txt").unwrap().modified_at, modified);
+
+    // Keys can be removed as well as set
+    let owner = MetadataPatch::default().with_user_metadata("owner", "ops");
+    cart.update_metadata_bulk(vec![("a.txt", owner)]).unwrap();
+    assert_eq!(cart.metadata("a.txt").unwrap().user_metadata["owner"], "ops");
+    let patch = MetadataPatch::default().without_user_metadata("owner");
+    cart.update_metadata_bulk(vec![("a.txt", patch)]).unwrap();
+    assert!(!cart.metadata("a.txt").unwrap().user_metadata.contains_key("owner"));
+}
+
+#[test]
+fn test_import_dir_keeps_source_mtimes() {
+    let dir = tempfile::tempdir().unwrap();
+    let host = dir.path().join("host");
+    std::fs::create_dir_all(host.join("nested")).unwrap();
+    let files = [("a.txt", 1_400_000_000u64), ("nested/b.txt", 1_600_000_000)];
+    for (name, secs) in files {
+        let file_path = host.join(name);
+        std::fs::write(&file_path, name).unwrap();
+        let file = std::fs::File::options().write(true).open(&file_path).unwrap();
+        file.set_modified(UNIX_EPOCH + Duration::from_secs(secs)).unwrap();
+    }
+
+    let path = dir.path().join("import.cart");
+    let mut cart = Cartridge::create_at(&path, "import", "Import").unwrap();
+    cart.import_dir(&host, "copy", &ImportOptions::default()).unwrap();
+    drop(cart);
+
+    let cart = Cartridge::open(&path).unwrap();
+    for (name, secs) in files {
+        let metadata = cart.metadata(format!("copy/{}", name)).unwrap();
+        assert_eq!(metadata.modified_at, secs);
+        assert_eq!(cart.read(format!("copy/{}", name)).unwrap(), name.as_bytes());
+    }
+}
@@ -0,0 +1,79 @@
+//! Metrics reported with the `metrics` feature
+//!
+//! Run with `cargo test --features metrics --test metrics`.
+
+#![cfg(feature = "metrics")]
+
+use cartridge_rs::core::telemetry;
+use cartridge_rs::{Cartridge, CartridgeError};
+use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+use std::collections::HashMap;
+
+/// Run `f` with a local recorder and return every counter it touched
+fn record_counters(f: impl FnOnce()) -> HashMap<String, u64> {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    metrics::with_local_recorder(&recorder, f);
+    snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .filter_map(|(key, _, _, value)| match value {
+            DebugValue::Counter(n) => Some((key.key().name().to_string(), n)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_write_read_flush_cycle_is_counted() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("metered");
+    let payload = vec![7u8; 3 * 4096];
+
+    let counters = record_counters(|| {
+        let mut cart = Cartridge::create_at(&path, "metered", "Metered").unwrap();
+        cart.write("data.bin", &payload).unwrap();
+        cart.flush().unwrap();
+        assert_eq!(cart.read("data.bin").unwrap(), payload);
+        drop(cart);
+
+        let cart = Cartridge::open(&path).unwrap();
+        assert_eq!(cart.read("data.bin").unwrap(), payload);
+        assert_eq!(cart.read("data.bin").unwrap(), payload);
+    });
+
+    assert!(counters[telemetry::BYTES_WRITTEN] >= payload.len() as u64);
+    assert!(counters[telemetry::BYTES_READ] >= 3 * payload.len() as u64);
+    assert!(counters[telemetry::PAGES_FLUSHED] >= 3);
+    assert!(counters[telemetry::DISK_BYTES_WRITTEN] >= payload.len() as u64);
+    assert!(counters[telemetry::DISK_BYTES_READ] >= payload.len() as u64);
+    // The reopened cartridge loads the three pages once, then hits the cache
+    assert!(counters[telemetry::PAGE_CACHE_MISSES] >= 3);
+    assert!(counters[telemetry::PAGE_CACHE_HITS] >= 3);
+}
+
+#[test]
+fn test_growth_and_denials_are_counted() {
+    use cartridge_rs::core::iam::{Action, Effect, Policy, Statement};
+
+    let counters = record_counters(|| {
+        let mut cart = Cartridge::in_memory("grow", "Grow").unwrap();
+        cart.write("big.bin", &vec![1u8; 64 * 4096]).unwrap();
+
+        let mut policy = Policy::new();
+        policy.add_statement(Statement::new(
+            Effect::Allow,
+            vec![Action::Read],
+            vec!["/**".to_string()],
+        ));
+        cart.inner_mut().set_policy(policy);
+        assert!(matches!(
+            cart.write("denied.txt", b"no"),
+            Err(CartridgeError::AccessDenied { .. })
+        ));
+    });
+
+    assert!(counters[telemetry::GROW_EVENTS] >= 1);
+    assert_eq!(counters[telemetry::POLICY_DENIALS], 1);
+}
@@ -0,0 +1,68 @@
+//! `Cartridge::pack` and `verify_digests` across a file transfer
+
+use cartridge_rs::{Cartridge, DigestMismatch, PackOptions, SigningKey};
+use sha2::{Digest, Sha256};
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn test_pack_ships_and_verifies() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("release.cart");
+    let mut cart = Cartridge::create_at(&path, "release", "Release").unwrap();
+    for i in 0..20 {
+        cart.write(format!("assets/{i}.bin"), &vec![i as u8; 10_000]).unwrap();
+    }
+    for i in 0..10 {
+        cart.delete(format!("assets/{i}.bin")).unwrap();
+    }
+
+    let key = SigningKey::from_bytes(&[3u8; 32]);
+    let options = PackOptions {
+        signing_key: Some(key),
+        ..Default::default()
+    };
+    let report = cart.pack(&options).unwrap();
+    assert_eq!(report.files_digested, 11); // ten assets plus the manifest
+    assert!(report.vacuum.unwrap().bytes_reclaimed > 0);
+    drop(cart);
+
+    // The published hash matches the file as shipped
+    let shipped = std::fs::read(&path).unwrap();
+    assert_eq!(shipped.len() as u64, report.archive_bytes);
+    assert_eq!(hex(&Sha256::digest(&shipped)), report.archive_sha256);
+
+    let received = Cartridge::open_read_only(&path).unwrap();
+    let digests = received.read_digests().unwrap();
+    assert_eq!(digests.public_key, Some(hex(&report.public_key.unwrap())));
+    assert!(digests.verify_signature());
+    assert!(received.verify_digests().unwrap().is_empty());
+}
+
+#[test]
+fn test_corruption_in_transit_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("corrupt.cart");
+    let mut cart = Cartridge::create_at(&path, "corrupt", "Corrupt").unwrap();
+    cart.write("payload.bin", &[0xAB; 8192]).unwrap();
+    cart.write("other.bin", &[0xCD; 8192]).unwrap();
+    cart.pack(&PackOptions::default()).unwrap();
+    drop(cart);
+
+    // Flip a byte in the middle of payload.bin's content; the page
+    // checksum catches it before the digest does
+    let mut bytes = std::fs::read(&path).unwrap();
+    let start = bytes.windows(64).position(|w| w == [0xAB; 64]).unwrap();
+    bytes[start + 100] ^= 0xFF;
+    std::fs::write(&path, &bytes).unwrap();
+
+    let received = Cartridge::open_read_only(&path).unwrap();
+    assert_eq!(
+        received.verify_digests().unwrap(),
+        [DigestMismatch::Corrupt {
+            path: "/payload.bin".to_string()
+        }]
+    );
+}
@@ -0,0 +1,138 @@
+//! Different spellings of the same path reach the same file
+
+use cartridge_rs::{Cartridge, CartridgeError, PathError};
+
+fn invalid_reason(result: cartridge_rs::Result<impl std::fmt::Debug>) -> PathError {
+    match result {
+        Err(CartridgeError::InvalidFilePath { reason, .. }) => reason,
+        other => panic!("expected InvalidFilePath, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_spellings_are_equivalent() {
+    let mut cart = Cartridge::in_memory("paths", "Paths").unwrap();
+    cart.write("docs/a.txt", b"one").unwrap();
+
+    for spelling in ["docs/a.txt", "/docs/a.txt", "docs//a.txt", "//docs/a.txt/"] {
+        assert_eq!(cart.read(spelling).unwrap(), b"one", "{spelling}");
+        assert!(cart.exists(spelling).unwrap(), "{spelling}");
+        assert_eq!(cart.metadata(spelling).unwrap().size, 3, "{spelling}");
+    }
+
+    // Writing through another spelling updates the same file
+    cart.write("/docs//a.txt", b"two").unwrap();
+    assert_eq!(cart.read("docs/a.txt").unwrap(), b"two");
+    assert_eq!(cart.list("").unwrap(), cart.list("/").unwrap());
+    assert_eq!(cart.list("docs").unwrap(), ["/docs/a.txt"]);
+    assert_eq!(cart.list("/docs/").unwrap(), ["/docs/a.txt"]);
+}
+
+#[test]
+fn test_listings_use_normalized_paths() {
+    let mut cart = Cartridge::in_memory("listing", "Listing").unwrap();
+    cart.write("a.txt", b"a").unwrap();
+    cart.write("/docs/b.txt", b"b").unwrap();
+    cart.create_dir("docs//sub/").unwrap();
+
+    let children = cart.list_children("").unwrap();
+    assert_eq!(children, cart.list_children("/").unwrap());
+    let paths: Vec<&str> = children.iter().map(|e| e.path.as_str()).collect();
+    assert_eq!(paths, ["/docs", "/a.txt"]);
+    assert!(children.iter().all(|e| e.parent == "/"));
+
+    let docs = cart.list_children("docs/").unwrap();
+    let paths: Vec<&str> = docs.iter().map(|e| e.path.as_str()).collect();
+    assert_eq!(paths, ["/docs/sub", "/docs/b.txt"]);
+    assert!(cart.is_dir("/docs").unwrap());
+    assert!(!cart.is_dir("a.txt").unwrap());
+}
+
+#[test]
+fn test_rename_and_delete_across_spellings() {
+    let mut cart = Cartridge::in_memory("rename", "Rename").unwrap();
+    cart.write("a.txt", b"a").unwrap();
+
+    cart.inner_mut().rename("/a.txt", "b//c.txt").unwrap();
+    assert_eq!(cart.read("/b/c.txt").unwrap(), b"a");
+    assert!(!cart.exists("a.txt").unwrap());
+
+    // The same file can't be created twice under different spellings
+    assert!(cart.inner_mut().create_file("b/c.txt", b"again").is_err());
+
+    cart.delete("b/c.txt/").unwrap();
+    assert!(!cart.exists("/b/c.txt").unwrap());
+}
+
+#[test]
+fn test_invalid_paths_are_rejected() {
+    let mut cart = Cartridge::in_memory("invalid", "Invalid").unwrap();
+
+    assert_eq!(
+        invalid_reason(cart.write("docs/../secret.txt", b"x")),
+        PathError::DotSegment {
+            segment: "..".to_string()
+        }
+    );
+    assert_eq!(
+        invalid_reason(cart.read("./a.txt")),
+        PathError::DotSegment {
+            segment: ".".to_string()
+        }
+    );
+    assert_eq!(invalid_reason(cart.write("a\0b", b"x")), PathError::Nul);
+    assert!(matches!(
+        invalid_reason(cart.write("d/".repeat(300), b"x")),
+        PathError::TooDeep { depth: 300, .. }
+    ));
+
+    // Nothing was written
+    assert!(cart.list("").unwrap().iter().all(|p| p.starts_with("/.cartridge")));
+    assert_eq!(cart.walk("a\0b").count(), 0);
+}
+
+#[test]
+fn test_root_cannot_be_written() {
+    let mut cart = Cartridge::in_memory("root", "Root").unwrap();
+    assert!(cart.write("/", b"x").is_err());
+    assert!(cart.write("", b"x").is_err());
+    assert!(cart.create_dir("//").is_err());
+}
+
+#[test]
+fn test_system_files_refused_under_any_spelling() {
+    let mut cart = Cartridge::in_memory("system", "System").unwrap();
+    for spelling in [
+        ".cartridge/manifest.json",
+        "//.cartridge//manifest.json",
+        "/.cartridge/manifest.json/",
+    ] {
+        assert!(
+            matches!(
+                cart.delete(spelling),
+                Err(CartridgeError::ProtectedPath { .. })
+            ),
+            "{spelling}"
+        );
+        assert!(
+            matches!(
+                cart.write(spelling, b"{}"),
+                Err(CartridgeError::ProtectedPath { .. })
+            ),
+            "{spelling}"
+        );
+    }
+    assert_eq!(cart.slug().unwrap(), "system");
+
+    let manifest = cart.read(".cartridge/manifest.json").unwrap();
+    cart.system()
+        .write("/.cartridge/manifest.json", &manifest)
+        .unwrap();
+    let system: Vec<_> = cart
+        .list_system_entries()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.path)
+        .collect();
+    assert_eq!(system, ["/.cartridge/manifest.json"]);
+}
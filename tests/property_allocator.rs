@@ -2,9 +2,32 @@
 //!
 //! Uses proptest to verify allocator invariants hold across many random scenarios
 
-use cartridge_rs::Cartridge;
+use cartridge_rs::core::allocator::{hybrid::HybridAllocator, BlockAllocator};
+use cartridge_rs::{Cartridge, CartridgeError, PAGE_SIZE};
 use proptest::prelude::*;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
+
+/// One step of a random allocator workload
+#[derive(Debug, Clone)]
+enum AllocatorOp {
+    /// Allocate this many bytes
+    Allocate(u64),
+    /// Free the tail of a live allocation: (which, how many blocks to keep)
+    Free(usize, usize),
+    /// Grow by this many blocks
+    Extend(usize),
+    /// Shrink by this many blocks
+    Shrink(usize),
+}
+
+fn allocator_op() -> impl Strategy<Value = AllocatorOp> {
+    prop_oneof![
+        4 => (1u64..600 * 1024).prop_map(AllocatorOp::Allocate),
+        3 => (any::<usize>(), 0usize..4).prop_map(|(i, keep)| AllocatorOp::Free(i, keep)),
+        1 => (1usize..200).prop_map(AllocatorOp::Extend),
+        1 => (1usize..200).prop_map(AllocatorOp::Shrink),
+    ]
+}
 
 proptest! {
     #[test]
@@ -18,10 +41,10 @@ proptest! {
 
         for i in 0..file_count {
             let data = vec![i as u8; file_size];
-            cart.write(&format!("/file{}.bin", i), &data).unwrap();
+            cart.write(format!("/file{}.bin", i), &data).unwrap();
 
             // Get blocks allocated for this file
-            let metadata = cart.metadata(&format!("/file{}.bin", i)).unwrap();
+            let metadata = cart.metadata(format!("/file{}.bin", i)).unwrap();
 
             // Ensure no block is allocated twice
             for &block in &metadata.blocks {
@@ -43,19 +66,14 @@ proptest! {
     ) {
         let mut cart = Cartridge::create("prop-free-dec", "Prop Free Dec").unwrap();
 
-        let initial_free = cart.header().free_blocks;
-        let mut last_free = initial_free;
-
         for (idx, size) in allocations.iter().enumerate() {
-            cart.write(&format!("/file{}.bin", idx), &vec![idx as u8; *size]).unwrap();
+            cart.write(format!("/file{}.bin", idx), &vec![idx as u8; *size]).unwrap();
 
             let current_free = cart.header().free_blocks;
 
             // With auto-growth, free blocks can increase if container grows
             // Just verify the container is still valid
-            prop_assert!(current_free >= 0);
-
-            last_free = current_free;
+            prop_assert!(current_free <= cart.header().total_blocks);
         }
 
         std::fs::remove_file("prop-free-dec.cart").ok();
@@ -70,12 +88,12 @@ proptest! {
         // Write files with specific byte patterns
         for (idx, (size, byte)) in operations.iter().enumerate() {
             let data = vec![*byte; *size];
-            cart.write(&format!("/file{}.bin", idx), &data).unwrap();
+            cart.write(format!("/file{}.bin", idx), &data).unwrap();
         }
 
         // Verify all files have correct data
         for (idx, (size, byte)) in operations.iter().enumerate() {
-            let data = cart.read(&format!("/file{}.bin", idx)).unwrap();
+            let data = cart.read(format!("/file{}.bin", idx)).unwrap();
             prop_assert_eq!(data.len(), *size);
             prop_assert!(data.iter().all(|&b| b == *byte), "Data corrupted for file{}", idx);
         }
@@ -91,14 +109,14 @@ proptest! {
 
         // Create files
         for i in 0..file_count {
-            cart.write(&format!("/file{}.bin", i), &vec![i as u8; 16384]).unwrap();
+            cart.write(format!("/file{}.bin", i), &vec![i as u8; 16384]).unwrap();
         }
 
         let free_before_delete = cart.header().free_blocks;
 
         // Delete half
         for i in (0..file_count).step_by(2) {
-            cart.delete(&format!("/file{}.bin", i)).unwrap();
+            cart.delete(format!("/file{}.bin", i)).unwrap();
         }
 
         let free_after_delete = cart.header().free_blocks;
@@ -111,7 +129,7 @@ proptest! {
 
         // Remaining files should still be readable
         for i in (1..file_count).step_by(2) {
-            let data = cart.read(&format!("/file{}.bin", i)).unwrap();
+            let data = cart.read(format!("/file{}.bin", i)).unwrap();
             prop_assert_eq!(data.len(), 16384);
         }
 
@@ -134,9 +152,9 @@ proptest! {
                 (*size).min(255 * 1024) // Force bitmap allocator
             };
 
-            cart.write(&format!("/file{}.bin", idx), &vec![idx as u8; actual_size]).unwrap();
+            cart.write(format!("/file{}.bin", idx), &vec![idx as u8; actual_size]).unwrap();
 
-            let meta = cart.metadata(&format!("/file{}.bin", idx)).unwrap();
+            let meta = cart.metadata(format!("/file{}.bin", idx)).unwrap();
             for &block in &meta.blocks {
                 prop_assert!(
                     !all_blocks.contains(&block),
@@ -149,4 +167,68 @@ proptest! {
 
         std::fs::remove_file("prop-mixed.cart").ok();
     }
+
+    #[test]
+    fn prop_hybrid_counters_agree_under_random_workload(
+        ops in prop::collection::vec(allocator_op(), 1..80)
+    ) {
+        let mut alloc = HybridAllocator::new(256);
+        let mut live: Vec<Vec<u64>> = Vec::new();
+        let mut in_use = BTreeSet::new();
+
+        for op in ops {
+            match op {
+                AllocatorOp::Allocate(size) => match alloc.allocate(size) {
+                    Ok(blocks) => {
+                        prop_assert_eq!(blocks.len() as u64, size.div_ceil(PAGE_SIZE as u64));
+                        for &block in &blocks {
+                            prop_assert!(in_use.insert(block), "block {} handed out twice", block);
+                        }
+                        live.push(blocks);
+                    }
+                    Err(CartridgeError::OutOfSpace) => {
+                        prop_assert!(size.div_ceil(PAGE_SIZE as u64) > alloc.free_blocks() as u64);
+                    }
+                    Err(e) => prop_assert!(false, "allocate failed: {}", e),
+                },
+                AllocatorOp::Free(which, keep) => {
+                    if live.is_empty() {
+                        continue;
+                    }
+                    let index = which % live.len();
+                    let keep = keep.min(live[index].len());
+                    let freed = live[index].split_off(keep);
+                    alloc.free(&freed).unwrap();
+                    for block in &freed {
+                        in_use.remove(block);
+                    }
+                    if live[index].is_empty() {
+                        live.swap_remove(index);
+                    }
+                }
+                AllocatorOp::Extend(added) => {
+                    alloc.extend_capacity(alloc.total_blocks() + added).unwrap();
+                }
+                AllocatorOp::Shrink(removed) => {
+                    let target = alloc.total_blocks().saturating_sub(removed);
+                    let past: Vec<u64> = in_use.range(target as u64..).copied().collect();
+                    match alloc.shrink_capacity(target) {
+                        Ok(()) => prop_assert!(past.is_empty()),
+                        Err(CartridgeError::BlocksInUse { blocks, .. }) => {
+                            prop_assert_eq!(blocks, past);
+                        }
+                        Err(e) => prop_assert!(false, "shrink failed: {}", e),
+                    }
+                }
+            }
+
+            if let Err(e) = alloc.verify_consistency() {
+                prop_assert!(false, "{}", e);
+            }
+            prop_assert_eq!(alloc.free_blocks(), alloc.total_blocks() - in_use.len());
+            for block in 0..alloc.total_blocks() as u64 {
+                prop_assert_eq!(alloc.is_allocated(block), in_use.contains(&block));
+            }
+        }
+    }
 }
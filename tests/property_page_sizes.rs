@@ -0,0 +1,178 @@
+//! Property-based tests for cartridges created with 16KB and 64KB pages
+//!
+//! Files are sized around page boundaries for every supported page size,
+//! then read back whole, by range and after reopening.
+
+use cartridge_rs::{Cartridge, CartridgeBuilder, CartridgeError, PageSize};
+use proptest::prelude::*;
+use std::path::Path;
+
+const SIZES: [PageSize; 3] = [PageSize::Kb4, PageSize::Kb16, PageSize::Kb64];
+
+fn create(path: &Path, size: PageSize) -> Cartridge {
+    CartridgeBuilder::new()
+        .slug("pages")
+        .title("Pages")
+        .path(path.to_str().unwrap())
+        .page_size(size)
+        .build()
+        .unwrap()
+}
+
+fn pattern(len: usize, seed: u8) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8 ^ seed).collect()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(24))]
+
+    #[test]
+    fn prop_round_trip_around_page_boundaries(
+        size_idx in 0usize..3,
+        pages in 0usize..4,
+        delta in -2i64..=2,
+        seed in any::<u8>(),
+    ) {
+        let size = SIZES[size_idx];
+        let page = size.bytes();
+        let len = (pages as i64 * page as i64 + delta).max(0) as usize;
+        let content = pattern(len, seed);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pages");
+        let mut cart = create(&path, size);
+        cart.write("data.bin", &content).unwrap();
+        prop_assert_eq!(&cart.read("data.bin").unwrap(), &content);
+
+        let blocks = cart.metadata("data.bin").unwrap().blocks.len();
+        prop_assert!(blocks <= len.div_ceil(page).max(1));
+        drop(cart);
+
+        let cart = Cartridge::open(&path).unwrap();
+        prop_assert_eq!(cart.page_size(), page);
+        prop_assert_eq!(&cart.read("data.bin").unwrap(), &content);
+        if len > 2 {
+            let at = (len / 2) as u64;
+            let range = cart.read_range("data.bin", at - 1, 3).unwrap();
+            prop_assert_eq!(&range[..], &content[at as usize - 1..at as usize + 2]);
+        }
+    }
+
+    #[test]
+    fn prop_write_at_across_page_boundary(
+        size_idx in 0usize..3,
+        boundary in 1usize..3,
+        before in 0usize..16,
+        len in 1usize..64,
+        seed in any::<u8>(),
+    ) {
+        let size = SIZES[size_idx];
+        let page = size.bytes();
+        let mut expected = pattern(3 * page, seed);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pages");
+        let mut cart = create(&path, size);
+        cart.write("data.bin", &expected).unwrap();
+
+        let offset = boundary * page - before.min(boundary * page);
+        let patch = vec![!seed; len];
+        cart.write_at("data.bin", offset as u64, &patch).unwrap();
+        let end = offset + len;
+        if expected.len() < end {
+            expected.resize(end, 0);
+        }
+        expected[offset..end].copy_from_slice(&patch);
+        prop_assert_eq!(&cart.read("data.bin").unwrap(), &expected);
+        drop(cart);
+
+        let cart = Cartridge::open(&path).unwrap();
+        prop_assert_eq!(&cart.read("data.bin").unwrap(), &expected);
+    }
+}
+
+#[test]
+fn test_large_pages_use_fewer_blocks() {
+    let dir = tempfile::tempdir().unwrap();
+    let content = pattern(1024 * 1024, 7);
+
+    let mut blocks = Vec::new();
+    for size in SIZES {
+        let path = dir.path().join(format!("{}", size.bytes()));
+        let mut cart = create(&path, size);
+        cart.write("video.bin", &content).unwrap();
+        blocks.push(cart.metadata("video.bin").unwrap().blocks.len());
+        let entry = cart.list_entries("").unwrap().into_iter().find(|e| e.name == "video.bin");
+        assert_eq!(entry.unwrap().compressed_size, Some(content.len() as u64));
+    }
+    assert_eq!(blocks, vec![256, 64, 16]);
+}
+
+#[test]
+fn test_vacuum_and_defragment_with_large_pages() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("vacuum");
+    let mut cart = create(&path, PageSize::Kb64);
+    for i in 0..8u8 {
+        cart.write(format!("f{i}.bin"), &pattern(100_000 + i as usize, i)).unwrap();
+    }
+    for i in (0..8u8).step_by(2) {
+        cart.delete(format!("f{i}.bin")).unwrap();
+    }
+    // Incremental vacuum journals its moves in WAL pages of the same size
+    while !cart.inner_mut().vacuum_step(2).unwrap().done {}
+    cart.inner_mut().vacuum_finish().unwrap();
+    cart.defragment(usize::MAX).unwrap();
+    cart.vacuum().unwrap();
+    let copy = dir.path().join("copy.cart");
+    cart.inner().vacuum_into(&copy).unwrap();
+    drop(cart);
+
+    for path in [path, copy] {
+        let cart = Cartridge::open(&path).unwrap();
+        assert_eq!(cart.page_size(), 64 * 1024);
+        for i in (1..8u8).step_by(2) {
+            let expected = pattern(100_000 + i as usize, i);
+            assert_eq!(cart.read(format!("f{i}.bin")).unwrap(), expected);
+        }
+    }
+}
+
+#[test]
+fn test_encrypted_cartridge_with_large_pages() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sealed");
+    let content = pattern(3 * 16 * 1024 + 5, 3);
+
+    let mut cart = CartridgeBuilder::new()
+        .slug("sealed")
+        .title("Sealed")
+        .path(path.to_str().unwrap())
+        .page_size(PageSize::Kb16)
+        .with_encryption("correct horse")
+        .build()
+        .unwrap();
+    cart.write("data.bin", &content).unwrap();
+    drop(cart);
+
+    let cart = Cartridge::open_encrypted(&path, "correct horse").unwrap();
+    assert_eq!(cart.page_size(), 16 * 1024);
+    assert_eq!(cart.read("data.bin").unwrap(), content);
+}
+
+#[test]
+fn test_unsupported_block_size_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("odd.cart");
+    drop(create(&path, PageSize::Kb16));
+
+    // block_size sits right after the magic and version fields
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[12..16].copy_from_slice(&8192u32.to_le_bytes());
+    std::fs::write(&path, bytes).unwrap();
+
+    assert!(matches!(
+        Cartridge::open(&path),
+        Err(CartridgeError::InvalidBlockSize(8192))
+    ));
+}
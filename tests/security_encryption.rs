@@ -1,6 +1,6 @@
 //! Encryption security tests - Phase 5
 
-use cartridge_rs::{Cartridge, EncryptionConfig};
+use cartridge_rs::{Cartridge, CartridgeBuilder, CartridgeError, EncryptionConfig};
 use tempfile::TempDir;
 
 #[test]
@@ -45,14 +45,14 @@ fn test_encryption_nonce_uniqueness() {
     let plaintext = b"Same message every time";
 
     for i in 0..10 {
-        cart.write(&format!("/file{}.txt", i), plaintext).unwrap();
+        cart.write(format!("/file{}.txt", i), plaintext).unwrap();
     }
 
     cart.flush().unwrap();
 
     // All files should decrypt to the same plaintext
     for i in 0..10 {
-        let content = cart.read(&format!("/file{}.txt", i)).unwrap();
+        let content = cart.read(format!("/file{}.txt", i)).unwrap();
         assert_eq!(content, plaintext);
     }
 
@@ -204,3 +204,240 @@ fn test_encryption_performance() {
     // Note: For production use, run in release mode where encryption overhead
     // is typically < 2x for both reads and writes
 }
+
+#[test]
+fn test_at_rest_encryption_round_trip() {
+    let temp_dir = TempDir::new().unwrap();
+    let cart_path = temp_dir.path().join("at-rest");
+
+    let mut cart = CartridgeBuilder::new()
+        .slug("at-rest")
+        .title("At Rest")
+        .path(cart_path.to_str().unwrap())
+        .with_encryption("correct horse battery staple")
+        .build()
+        .unwrap();
+    assert!(cart.is_encrypted_at_rest());
+
+    cart.write("/secret.txt", b"TOP-SECRET-PAYLOAD").unwrap();
+    cart.flush().unwrap();
+    drop(cart);
+
+    // Neither content nor catalog paths are visible in the raw file
+    let raw = std::fs::read(cart_path.with_extension("cart")).unwrap();
+    assert!(!raw.windows(18).any(|w| w == b"TOP-SECRET-PAYLOAD"));
+    assert!(!raw.windows(10).any(|w| w == b"secret.txt"));
+
+    let cart = Cartridge::open_encrypted(&cart_path, "correct horse battery staple").unwrap();
+    assert_eq!(cart.read("/secret.txt").unwrap(), b"TOP-SECRET-PAYLOAD");
+}
+
+#[test]
+fn test_at_rest_encryption_covers_snapshots() {
+    let temp_dir = TempDir::new().unwrap();
+    let cart_path = temp_dir.path().join("snapped");
+    let snapshot_dir = temp_dir.path().join("snapshots");
+
+    let mut cart = CartridgeBuilder::new()
+        .slug("snapped")
+        .title("Snapped")
+        .path(cart_path.to_str().unwrap())
+        .with_encryption("correct horse battery staple")
+        .build()
+        .unwrap();
+    cart.write("/secret.txt", b"TOP-SECRET-PAYLOAD").unwrap();
+    cart.flush().unwrap();
+    let base = cart
+        .create_snapshot("base".into(), String::new(), &snapshot_dir)
+        .unwrap();
+    cart.write("/secret.txt", b"NEWER-SECRET-DATA!").unwrap();
+    cart.write("/later.txt", b"added later").unwrap();
+    cart.flush().unwrap();
+    let incremental = cart
+        .create_snapshot_incremental(base, "next".into(), String::new(), &snapshot_dir)
+        .unwrap();
+
+    // Neither pages nor saved catalogs give the content or paths away
+    for entry in walkdir::WalkDir::new(&snapshot_dir) {
+        let entry = entry.unwrap();
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let raw = std::fs::read(entry.path()).unwrap();
+        for plaintext in [&b"TOP-SECRET-PAYLOAD"[..], b"NEWER-SECRET-DATA!", b"secret.txt"] {
+            assert!(
+                !raw.windows(plaintext.len()).any(|w| w == plaintext),
+                "{} leaks {:?}",
+                entry.path().display(),
+                String::from_utf8_lossy(plaintext)
+            );
+        }
+    }
+
+    let diff = cart.diff_since(base, &snapshot_dir).unwrap();
+    assert_eq!(diff.added, ["/later.txt"]);
+    cart.restore_snapshot(base, &snapshot_dir).unwrap();
+    assert_eq!(cart.read("/secret.txt").unwrap(), b"TOP-SECRET-PAYLOAD");
+    assert!(!cart.exists("/later.txt").unwrap());
+    cart.restore_snapshot(incremental, &snapshot_dir).unwrap();
+    cart.flush().unwrap();
+    drop(cart);
+
+    let cart = Cartridge::open_encrypted(&cart_path, "correct horse battery staple").unwrap();
+    assert_eq!(cart.read("/secret.txt").unwrap(), b"NEWER-SECRET-DATA!");
+    assert_eq!(cart.read("/later.txt").unwrap(), b"added later");
+}
+
+#[test]
+fn test_at_rest_encryption_requires_passphrase() {
+    let temp_dir = TempDir::new().unwrap();
+    let cart_path = temp_dir.path().join("locked");
+
+    let mut cart = CartridgeBuilder::new()
+        .slug("locked")
+        .title("Locked")
+        .path(cart_path.to_str().unwrap())
+        .with_encryption("hunter2")
+        .build()
+        .unwrap();
+    cart.write("/data.txt", b"data").unwrap();
+    drop(cart);
+
+    assert!(matches!(
+        Cartridge::open(&cart_path),
+        Err(CartridgeError::EncryptionRequired)
+    ));
+    assert!(matches!(
+        Cartridge::open_encrypted(&cart_path, "hunter3"),
+        Err(CartridgeError::InvalidPassphrase)
+    ));
+    assert!(Cartridge::open_encrypted(&cart_path, "hunter2").is_ok());
+}
+
+#[test]
+fn test_open_encrypted_on_plaintext_cartridge() {
+    let temp_dir = TempDir::new().unwrap();
+    let cart_path = temp_dir.path().join("plain");
+
+    let cart = Cartridge::create_at(&cart_path, "plain", "Plain").unwrap();
+    drop(cart);
+
+    assert!(matches!(
+        Cartridge::open_encrypted(&cart_path, "anything"),
+        Err(CartridgeError::NotEncrypted)
+    ));
+}
+
+#[test]
+fn test_at_rest_encryption_survives_growth() {
+    let temp_dir = TempDir::new().unwrap();
+    let cart_path = temp_dir.path().join("growth");
+
+    let mut cart = CartridgeBuilder::new()
+        .slug("growth")
+        .title("Growth")
+        .path(cart_path.to_str().unwrap())
+        .with_encryption("passphrase")
+        .build()
+        .unwrap();
+
+    let data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+    for i in 0..20 {
+        cart.write(format!("/file{}.bin", i), &data[..1000 * (i + 1)])
+            .unwrap();
+    }
+    cart.write("/large.bin", &data).unwrap();
+    drop(cart);
+
+    let cart = Cartridge::open_encrypted(&cart_path, "passphrase").unwrap();
+    for i in 0..20 {
+        assert_eq!(
+            cart.read(format!("/file{}.bin", i)).unwrap(),
+            &data[..1000 * (i + 1)]
+        );
+    }
+    assert_eq!(cart.read("/large.bin").unwrap(), data);
+}
+
+#[test]
+fn test_per_file_encryption_persists_across_reopen() {
+    let temp_dir = TempDir::new().unwrap();
+    let cart_path = temp_dir.path().join("per-file.cart");
+    let key = EncryptionConfig::generate_key();
+    let secret: Vec<u8> = (0..20_000u32).map(|i| (i % 199) as u8).collect();
+
+    let mut cart = Cartridge::create_at(&cart_path, "per-file", "Per File").unwrap();
+    cart.write("/public/readme.txt", b"anyone may read this").unwrap();
+    cart.write_encrypted("/private/user.bin", &secret, &key).unwrap();
+    cart.write_encrypted("/private/small.txt", b"tiny", &key).unwrap();
+    drop(cart);
+
+    // Plain bytes never reach the disk
+    let raw = std::fs::read(&cart_path).unwrap();
+    assert!(!raw.windows(secret.len().min(64)).any(|w| w == &secret[..64]));
+
+    let mut cart = Cartridge::open(&cart_path).unwrap();
+    assert_eq!(cart.read("/public/readme.txt").unwrap(), b"anyone may read this");
+    assert_eq!(cart.read_encrypted("/private/user.bin", &key).unwrap(), secret);
+    assert_eq!(cart.read_encrypted("/private/small.txt", &key).unwrap(), b"tiny");
+
+    for path in ["/private/user.bin", "/private/small.txt"] {
+        match cart.read(path) {
+            Err(CartridgeError::EncryptedFile { path: failed }) => assert_eq!(failed, path),
+            other => panic!("expected EncryptedFile, got {:?}", other),
+        }
+    }
+    assert!(matches!(
+        cart.read_encrypted("/public/readme.txt", &key),
+        Err(CartridgeError::FileNotEncrypted { .. })
+    ));
+
+    let entries = cart.list_entries("/").unwrap();
+    let encrypted: Vec<&str> = entries
+        .iter()
+        .filter(|e| e.encrypted)
+        .map(|e| e.path.as_str())
+        .collect();
+    assert_eq!(encrypted, ["/private/small.txt", "/private/user.bin"]);
+    assert_eq!(cart.metadata("/private/user.bin").unwrap().size, secret.len() as u64);
+
+    // Overwriting with the key keeps it encrypted
+    cart.write_encrypted("/private/small.txt", b"replaced", &key).unwrap();
+    assert_eq!(cart.read_encrypted("/private/small.txt", &key).unwrap(), b"replaced");
+}
+
+#[test]
+fn test_per_file_encryption_wrong_key_and_rotation() {
+    let mut cart = Cartridge::in_memory("rotate", "Rotate").unwrap();
+    let old_key = EncryptionConfig::generate_key();
+    let new_key = EncryptionConfig::generate_key();
+    cart.write_encrypted("/private/keys.json", br#"{"api":"s3cr3t"}"#, &old_key)
+        .unwrap();
+
+    // The auth tag doesn't verify under another key
+    match cart.read_encrypted("/private/keys.json", &new_key) {
+        Err(CartridgeError::DecryptionFailed { path }) => assert_eq!(path, "/private/keys.json"),
+        other => panic!("expected DecryptionFailed, got {:?}", other),
+    }
+    assert!(matches!(
+        cart.reencrypt("/private/keys.json", &new_key, &old_key),
+        Err(CartridgeError::DecryptionFailed { .. })
+    ));
+
+    cart.reencrypt("/private/keys.json", &old_key, &new_key).unwrap();
+    assert_eq!(
+        cart.read_encrypted("/private/keys.json", &new_key).unwrap(),
+        br#"{"api":"s3cr3t"}"#
+    );
+    assert!(matches!(
+        cart.read_encrypted("/private/keys.json", &old_key),
+        Err(CartridgeError::DecryptionFailed { .. })
+    ));
+
+    // The nonce is bookkeeping, not user metadata to edit
+    let nonce = std::collections::HashMap::from([(
+        "encryption_nonce".to_string(),
+        "00".repeat(12),
+    )]);
+    assert!(cart.set_metadata("/private/keys.json", nonce).is_err());
+}
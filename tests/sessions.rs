@@ -0,0 +1,104 @@
+//! Operations attributed to sessions without touching cartridge-wide defaults
+
+use cartridge_rs::{
+    Action, AuditFilter, CartridgeBuilder, CartridgeError, Effect, Operation, Policy, Statement,
+};
+
+fn audited(dir: &tempfile::TempDir) -> cartridge_rs::Cartridge {
+    CartridgeBuilder::new()
+        .slug("sessions")
+        .title("Sessions")
+        .path(dir.path().join("sessions.cart").to_str().unwrap())
+        .with_audit_logging()
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_interleaved_sessions_are_attributed() {
+    let dir = tempfile::tempdir().unwrap();
+    let shared = audited(&dir).into_shared();
+    let alice = shared.session(1, 10);
+    let bob = shared.session(2, 20);
+
+    for i in 0..5 {
+        alice.write(format!("alice/{i}.txt"), b"a").unwrap();
+        bob.write(format!("bob/{i}.txt"), b"b").unwrap();
+        assert_eq!(alice.read(format!("alice/{i}.txt")).unwrap(), b"a");
+    }
+    bob.delete("bob/0.txt").unwrap();
+    shared.write("default.txt", b"d").unwrap();
+    shared.flush().unwrap();
+
+    drop((alice, bob));
+    let cart = shared.try_unwrap().ok().unwrap();
+    let by_session = |session_id| {
+        cart.query_audit(&AuditFilter::default())
+            .unwrap()
+            .into_iter()
+            .filter(move |r| r.session_id == session_id)
+    };
+
+    let alice_records: Vec<_> = by_session(1).collect();
+    assert_eq!(alice_records.len(), 10);
+    assert!(alice_records.iter().all(|r| r.actor_id == 10));
+    assert!(alice_records
+        .iter()
+        .all(|r| r.path.as_deref().is_some_and(|p| p.starts_with("/alice/"))));
+
+    let bob_records: Vec<_> = by_session(2).collect();
+    assert!(bob_records.iter().all(|r| r.actor_id == 20));
+    assert!(bob_records
+        .iter()
+        .all(|r| r.path.as_deref().is_some_and(|p| p.starts_with("/bob/"))));
+    assert_eq!(bob_records.last().unwrap().operation, Operation::Delete);
+
+    // The handle without a session kept the defaults
+    let defaults: Vec<_> = by_session(0).collect();
+    assert_eq!(defaults.len(), 1);
+    assert_eq!(defaults[0].path.as_deref(), Some("/default.txt"));
+    assert_eq!(cart.inner().session_id(), 0);
+}
+
+#[test]
+fn test_session_guard_restores_defaults() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut cart = audited(&dir);
+    cart.inner_mut().set_session_id(3);
+
+    cart.session(7, 42).write("guarded.txt", b"g").unwrap();
+    cart.write("plain.txt", b"p").unwrap();
+    cart.flush().unwrap();
+
+    assert_eq!(cart.inner().session_id(), 3);
+    assert_eq!(cart.inner().actor_id(), 1);
+    let guarded = cart.query_audit(&AuditFilter::for_path("guarded.txt")).unwrap();
+    assert!(guarded.iter().all(|r| r.session_id == 7 && r.actor_id == 42));
+    let plain = cart.query_audit(&AuditFilter::for_path("plain.txt")).unwrap();
+    assert!(plain.iter().all(|r| r.session_id == 3 && r.actor_id == 1));
+}
+
+#[test]
+fn test_session_principal() {
+    let mut cart = cartridge_rs::Cartridge::in_memory("iam", "IAM").unwrap();
+    let mut policy = Policy::new();
+    policy.add_statement(
+        Statement::new(
+            Effect::Allow,
+            vec![Action::Read, Action::Write, Action::Create],
+            vec!["**".to_string()],
+        )
+        .with_principal(vec!["alice".to_string()]),
+    );
+    cart.inner_mut().set_policy(policy);
+
+    let mut alice = cart.session(1, 10).with_principal("alice");
+    alice.write("notes.txt", b"alice").unwrap();
+    assert_eq!(alice.read("notes.txt").unwrap(), b"alice");
+
+    assert_eq!(cart.inner().principal(), None);
+    assert!(matches!(
+        cart.read("notes.txt"),
+        Err(CartridgeError::AccessDenied { .. })
+    ));
+}
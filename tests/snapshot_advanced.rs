@@ -1,6 +1,6 @@
 //! Advanced snapshot tests
 
-use cartridge_rs::Cartridge;
+use cartridge_rs::{Cartridge, CartridgeError, RetentionPolicy, SnapshotManager, Vfs};
 use tempfile::TempDir;
 
 #[test]
@@ -44,7 +44,7 @@ fn test_snapshot_metadata_integrity() {
 
     // Reduced from 100 to 10 due to catalog size limitation (4KB page)
     for i in 0..10 {
-        cart.write(&format!("/file{}.txt", i), b"data").unwrap();
+        cart.write(format!("/file{}.txt", i), b"data").unwrap();
     }
     cart.flush().unwrap();
 
@@ -58,7 +58,7 @@ fn test_snapshot_metadata_integrity() {
 
     // All metadata should match after restore
     for i in 0..10 {
-        let orig_meta = cart.metadata(&format!("/file{}.txt", i)).unwrap();
+        let orig_meta = cart.metadata(format!("/file{}.txt", i)).unwrap();
         assert_eq!(orig_meta.size, 4); // "data" is 4 bytes
     }
 
@@ -75,7 +75,7 @@ fn test_snapshot_with_deletes() {
 
     // Reduced from 50 to 10 due to catalog size limitation (4KB page)
     for i in 0..10 {
-        cart.write(&format!("/file{}.txt", i), b"data").unwrap();
+        cart.write(format!("/file{}.txt", i), b"data").unwrap();
     }
     cart.flush().unwrap();
 
@@ -85,7 +85,7 @@ fn test_snapshot_with_deletes() {
 
     // Delete half the files
     for i in 0..5 {
-        cart.delete(&format!("/file{}.txt", i)).unwrap();
+        cart.delete(format!("/file{}.txt", i)).unwrap();
     }
     cart.flush().unwrap();
 
@@ -98,7 +98,7 @@ fn test_snapshot_with_deletes() {
 
     // Verify all files are back after restore
     for i in 0..10 {
-        let data = cart.read(&format!("/file{}.txt", i)).unwrap();
+        let data = cart.read(format!("/file{}.txt", i)).unwrap();
         assert_eq!(data, b"data");
     }
 
@@ -158,7 +158,7 @@ fn test_snapshot_large_files() {
     let mut cart = Cartridge::create("snapshot-large", "Snapshot Large").unwrap();
 
     // Write large file (1MB - reduced from 10MB due to catalog size)
-    let data = vec![0xAB; 1 * 1024 * 1024];
+    let data = vec![0xAB; 1024 * 1024];
     cart.write("/large.bin", &data).unwrap();
     cart.flush().unwrap();
 
@@ -167,7 +167,7 @@ fn test_snapshot_large_files() {
         .unwrap();
 
     // Modify
-    cart.write("/large.bin", &vec![0xCD; 1 * 1024 * 1024])
+    cart.write("/large.bin", &vec![0xCD; 1024 * 1024])
         .unwrap();
     cart.flush().unwrap();
 
@@ -175,8 +175,279 @@ fn test_snapshot_large_files() {
     cart.restore_snapshot(snap_id, &snapshot_dir).unwrap();
     let restored = cart.read("/large.bin").unwrap();
 
-    assert_eq!(restored.len(), 1 * 1024 * 1024);
+    assert_eq!(restored.len(), 1024 * 1024);
     assert!(restored.iter().all(|&b| b == 0xAB));
 
     std::fs::remove_file("snapshot-large.cart").ok();
 }
+
+#[test]
+fn test_snapshot_diff() {
+    let temp_dir = TempDir::new().unwrap();
+    let snapshot_dir = temp_dir.path().join("snapshots");
+    let mut cart = Cartridge::create_at(temp_dir.path().join("diff"), "snapshot-diff", "Diff").unwrap();
+    cart.write("/keep.txt", b"keep").unwrap();
+    cart.write("/remove.txt", b"remove").unwrap();
+    cart.write("/docs/change.txt", b"before").unwrap();
+
+    let snap_id = cart
+        .create_snapshot("s1".to_string(), "Before".to_string(), &snapshot_dir)
+        .unwrap();
+    assert!(cart.diff_since(snap_id, &snapshot_dir).unwrap().is_empty());
+
+    cart.write("/docs/add.txt", b"new").unwrap();
+    cart.delete("/remove.txt").unwrap();
+    cart.write("/docs/change.txt", b"after, and longer").unwrap();
+
+    let diff = cart.diff_since(snap_id, &snapshot_dir).unwrap();
+    assert_eq!(diff.added, ["/docs/add.txt"]);
+    assert_eq!(diff.removed, ["/remove.txt"]);
+    assert_eq!(diff.modified, ["/docs/change.txt"]);
+
+    // Two snapshots compare the same way, and the result serializes
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    let later = cart
+        .create_snapshot("s2".to_string(), "After".to_string(), &snapshot_dir)
+        .unwrap();
+    let manager = cartridge_rs::SnapshotManager::new(&snapshot_dir).unwrap();
+    assert_eq!(manager.diff(snap_id, later).unwrap(), diff);
+    let json = serde_json::to_string(&diff).unwrap();
+    assert_eq!(serde_json::from_str::<cartridge_rs::SnapshotDiff>(&json).unwrap(), diff);
+
+    assert!(matches!(
+        cart.diff_since(42, &snapshot_dir),
+        Err(cartridge_rs::CartridgeError::SnapshotNotFound { id: 42 })
+    ));
+}
+
+#[test]
+fn test_incremental_snapshot_restore() {
+    let temp_dir = TempDir::new().unwrap();
+    let snapshot_dir = temp_dir.path().join("snapshots");
+    let path = temp_dir.path().join("inc");
+    let mut cart = Cartridge::create_at(path, "snapshot-inc", "Inc").unwrap();
+    for i in 0..20 {
+        cart.write(format!("/file{}.bin", i), &vec![i as u8; 8192]).unwrap();
+    }
+    cart.flush().unwrap();
+    let base = cart
+        .create_snapshot("base".to_string(), "Full".to_string(), &snapshot_dir)
+        .unwrap();
+
+    cart.write("/file3.bin", b"changed").unwrap();
+    cart.flush().unwrap();
+    let nightly = cart
+        .create_snapshot_incremental(base, "n1".to_string(), "Delta".to_string(), &snapshot_dir)
+        .unwrap();
+
+    let mut manager = cartridge_rs::SnapshotManager::new(&snapshot_dir).unwrap();
+    let full = manager.restore_snapshot(base).unwrap();
+    let delta = manager.load_snapshot(nightly).unwrap();
+    assert!(delta.is_incremental());
+    assert!(delta.size_bytes < manager.load_snapshot(base).unwrap().size_bytes);
+    assert!(delta.modified_pages.len() < full.len());
+
+    cart.write("/file3.bin", b"later").unwrap();
+    cart.flush().unwrap();
+    cart.restore_snapshot(nightly, &snapshot_dir).unwrap();
+    assert_eq!(cart.read("/file3.bin").unwrap(), b"changed");
+    assert_eq!(cart.read("/file4.bin").unwrap(), vec![4u8; 8192]);
+
+    assert!(matches!(
+        manager.delete_snapshot(base),
+        Err(cartridge_rs::CartridgeError::SnapshotHasDependents { .. })
+    ));
+}
+
+#[test]
+fn test_snapshot_retention_from_manifest() {
+    let temp_dir = TempDir::new().unwrap();
+    let snapshot_dir = temp_dir.path().join("snapshots");
+    let path = temp_dir.path().join("retained");
+
+    let mut cart = Cartridge::create_at(&path, "retained", "Retained").unwrap();
+    let mut ids = Vec::new();
+    for i in 0..4 {
+        cart.write("/file.txt", format!("v{}", i).as_bytes()).unwrap();
+        ids.push(
+            cart.create_snapshot(format!("s{}", i), String::new(), &snapshot_dir)
+                .unwrap(),
+        );
+    }
+
+    // No policy, nothing deleted
+    assert!(cart.apply_snapshot_retention(&snapshot_dir).unwrap().is_empty());
+
+    let policy = RetentionPolicy {
+        keep_last: 2,
+        ..Default::default()
+    };
+    cart.set_snapshot_retention(Some(policy)).unwrap();
+    cart.flush().unwrap();
+    drop(cart);
+
+    // The policy is read back from the manifest on reopen
+    let cart = Cartridge::open(&path).unwrap();
+    assert_eq!(cart.snapshot_retention().unwrap(), Some(policy));
+    let mut deleted = cart.apply_snapshot_retention(&snapshot_dir).unwrap();
+    deleted.sort_unstable();
+    assert_eq!(deleted, &ids[..2]);
+    assert!(cart.apply_snapshot_retention(&snapshot_dir).unwrap().is_empty());
+}
+
+#[test]
+fn test_snapshots_listed_by_a_new_manager() {
+    let temp_dir = TempDir::new().unwrap();
+    let snapshot_dir = temp_dir.path().join("snapshots");
+
+    let mut cart = Cartridge::create("registry", "Registry").unwrap();
+    cart.write("/file.txt", b"v1").unwrap();
+    cart.flush().unwrap();
+    let first = cart
+        .create_snapshot("v1".to_string(), String::new(), &snapshot_dir)
+        .unwrap();
+    cart.write("/file.txt", b"v2").unwrap();
+    cart.flush().unwrap();
+    let second = cart
+        .create_snapshot("v2".to_string(), String::new(), &snapshot_dir)
+        .unwrap();
+
+    let manager = SnapshotManager::new(&snapshot_dir).unwrap();
+    let names: Vec<(u64, &str)> = manager
+        .list_snapshots()
+        .iter()
+        .map(|s| (s.id, s.name.as_str()))
+        .collect();
+    assert_eq!(names, [(first, "v1"), (second, "v2")]);
+    assert!(manager.warnings().is_empty());
+
+    cart.restore_snapshot(first, &snapshot_dir).unwrap();
+    assert_eq!(cart.read("/file.txt").unwrap(), b"v1");
+}
+
+#[test]
+fn test_snapshot_of_reopened_cartridge() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("reopened.cart");
+    let snapshot_dir = temp_dir.path().join("snapshots");
+
+    let mut cart = Cartridge::create_at(&path, "reopened", "Reopened").unwrap();
+    cart.write("/docs/a.txt", &vec![b'a'; 10_000]).unwrap();
+    cart.write("/b.txt", b"small").unwrap();
+    drop(cart);
+
+    // Nothing is cached yet, so the snapshot has to read its pages from disk
+    let cart = Cartridge::open(&path).unwrap();
+    let snap_id = cart
+        .create_snapshot("before".to_string(), String::new(), &snapshot_dir)
+        .unwrap();
+    drop(cart);
+
+    let mut cart = Cartridge::open(&path).unwrap();
+    cart.delete("/docs/a.txt").unwrap();
+    cart.write("/b.txt", b"changed").unwrap();
+    cart.flush().unwrap();
+    cart.restore_snapshot(snap_id, &snapshot_dir).unwrap();
+    cart.flush().unwrap();
+    drop(cart);
+
+    let cart = Cartridge::open(&path).unwrap();
+    assert_eq!(cart.read("/docs/a.txt").unwrap(), vec![b'a'; 10_000]);
+    assert_eq!(cart.read("/b.txt").unwrap(), b"small");
+    assert!(cart.check().unwrap().is_consistent());
+}
+
+#[test]
+fn test_snapshot_view_reads_old_version_alongside_live() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("view.cart");
+    let snapshot_dir = temp_dir.path().join("snapshots");
+
+    let v1_big: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+    let mut cart = Cartridge::create_at(&path, "view", "View").unwrap();
+    cart.write("/docs/big.bin", &v1_big).unwrap();
+    cart.write("/docs/note.txt", b"version one").unwrap();
+    cart.write("/gone.txt", b"deleted later").unwrap();
+    let snap_id = cart
+        .create_snapshot("v1".to_string(), String::new(), &snapshot_dir)
+        .unwrap();
+
+    cart.write("/docs/big.bin", &vec![2u8; 30_000]).unwrap();
+    cart.write("/docs/note.txt", b"version two").unwrap();
+    cart.delete("/gone.txt").unwrap();
+    cart.write("/new.txt", b"added later").unwrap();
+    cart.flush().unwrap();
+
+    let mut view = cart.open_snapshot_view(snap_id, &snapshot_dir).unwrap();
+    assert_eq!(view.snapshot_id(), snap_id);
+    assert!(view.is_read_only());
+
+    std::thread::scope(|scope| {
+        let old = scope.spawn(|| {
+            for _ in 0..20 {
+                assert_eq!(view.read("/docs/big.bin").unwrap(), v1_big);
+                assert_eq!(view.read("/docs/note.txt").unwrap(), b"version one");
+            }
+        });
+        for _ in 0..20 {
+            assert_eq!(cart.read("/docs/big.bin").unwrap(), vec![2u8; 30_000]);
+            assert_eq!(cart.read("/docs/note.txt").unwrap(), b"version two");
+        }
+        old.join().unwrap();
+    });
+
+    assert_eq!(view.read("/gone.txt").unwrap(), b"deleted later");
+    assert!(!view.exists("/new.txt").unwrap());
+    assert_eq!(view.metadata("/docs/big.bin").unwrap().size, 20_000);
+    assert_eq!(view.read_range("/docs/big.bin", 251, 2).unwrap(), [0, 1]);
+    let entries = view.list_entries("/docs").unwrap();
+    assert!(entries.iter().any(|entry| entry.path == "/docs/note.txt"));
+
+    assert!(matches!(view.write("/docs/note.txt", b"x"), Err(CartridgeError::ReadOnly)));
+    assert!(matches!(view.delete("/docs/note.txt"), Err(CartridgeError::ReadOnly)));
+
+    // The live cartridge is untouched, including after a reopen
+    drop(view);
+    drop(cart);
+    let cart = Cartridge::open(&path).unwrap();
+    assert_eq!(cart.read("/docs/note.txt").unwrap(), b"version two");
+    assert!(!cart.exists("/gone.txt").unwrap());
+}
+
+#[test]
+fn test_failed_restore_leaves_cartridge_untouched() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("bad-restore");
+    let snapshot_dir = temp_dir.path().join("snapshots");
+
+    let mut cart = Cartridge::create_at(&path, "bad-restore", "Bad Restore").unwrap();
+    cart.write("/file.txt", b"current").unwrap();
+    cart.flush().unwrap();
+    let header = *cart.header();
+
+    // A snapshot whose catalog page doesn't parse
+    let mut manager = SnapshotManager::new(&snapshot_dir).unwrap();
+    let mut catalog_page = b"{ not a catalog".to_vec();
+    catalog_page.resize(4096, 0);
+    let bad = manager
+        .create_snapshot(
+            "bad".to_string(),
+            String::new(),
+            path.clone(),
+            header,
+            &[(1, catalog_page)].into_iter().collect(),
+        )
+        .unwrap();
+
+    assert!(matches!(
+        cart.restore_snapshot(bad, &snapshot_dir),
+        Err(CartridgeError::Corruption(_))
+    ));
+    assert_eq!(cart.read("/file.txt").unwrap(), b"current");
+    assert_eq!(cart.header().total_blocks, header.total_blocks);
+
+    // Nothing reached the disk either
+    drop(cart);
+    let cart = Cartridge::open(&path).unwrap();
+    assert_eq!(cart.read("/file.txt").unwrap(), b"current");
+}
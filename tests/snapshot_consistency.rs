@@ -10,7 +10,7 @@ fn test_basic_snapshot_workflow() {
 
     // Initial data
     for i in 0..20 {
-        cart.write(&format!("/file{}.txt", i), format!("v0_{}", i).as_bytes()).unwrap();
+        cart.write(format!("/file{}.txt", i), format!("v0_{}", i).as_bytes()).unwrap();
     }
 
     // Note: Snapshot API may not exist yet - this tests regular operations
@@ -18,12 +18,12 @@ fn test_basic_snapshot_workflow() {
 
     // Modify files
     for i in 0..20 {
-        cart.write(&format!("/file{}.txt", i), format!("v1_{}", i).as_bytes()).unwrap();
+        cart.write(format!("/file{}.txt", i), format!("v1_{}", i).as_bytes()).unwrap();
     }
 
     // Verify modifications
     for i in 0..20 {
-        let data = cart.read(&format!("/file{}.txt", i)).unwrap();
+        let data = cart.read(format!("/file{}.txt", i)).unwrap();
         assert_eq!(data, format!("v1_{}", i).as_bytes());
     }
 
@@ -43,7 +43,7 @@ fn test_concurrent_reads_during_writes() {
     {
         let mut c = cart.write();
         for i in 0..30 {
-            c.write(&format!("/file{}.txt", i), b"original").unwrap();
+            c.write(format!("/file{}.txt", i), b"original").unwrap();
         }
     }
 
@@ -52,7 +52,7 @@ fn test_concurrent_reads_during_writes() {
     let writer_handle = std::thread::spawn(move || {
         for i in 0..30 {
             let mut c = cart_clone1.write();
-            c.write(&format!("/file{}.txt", i), b"modified").unwrap();
+            c.write(format!("/file{}.txt", i), b"modified").unwrap();
             std::thread::sleep(std::time::Duration::from_micros(100));
         }
     });
@@ -64,7 +64,7 @@ fn test_concurrent_reads_during_writes() {
             for _ in 0..100 {
                 let c = cart_clone.read();
                 let idx = rand::random::<usize>() % 30;
-                let _ = c.read(&format!("/file{}.txt", idx));
+                let _ = c.read(format!("/file{}.txt", idx));
             }
         })
     }).collect();
@@ -121,7 +121,7 @@ fn test_concurrent_file_creation() {
         std::thread::spawn(move || {
             for i in 0..20 {
                 let mut c = cart_clone.write();
-                c.write(&format!("/t{}_{}.txt", thread_id, i), b"data").unwrap();
+                c.write(format!("/t{}_{}.txt", thread_id, i), b"data").unwrap();
             }
         })
     }).collect();
@@ -0,0 +1,138 @@
+//! Sparse files: holes from `write_at`, `truncate` and `write_sparse`
+
+use cartridge_rs::{Cartridge, ImportOptions, HOLE_BLOCK};
+
+const GB: u64 = 1024 * 1024 * 1024;
+const PAGE: u64 = 4096;
+
+fn page_of(byte: u8) -> Vec<u8> {
+    vec![byte; PAGE as usize]
+}
+
+#[test]
+fn test_one_gigabyte_file_with_one_page_of_data() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sparse.cart");
+    let mut cart = Cartridge::create_at(&path, "sparse", "Sparse").unwrap();
+    let used_before = cart.inner().stats().used_blocks;
+
+    cart.write("db/table.dat", b"").unwrap();
+    cart.write_at("db/table.dat", GB / 2, &page_of(0xAB)).unwrap();
+    cart.truncate("db/table.dat", GB).unwrap();
+
+    let metadata = cart.metadata("db/table.dat").unwrap();
+    assert_eq!(metadata.size, GB);
+    assert_eq!(metadata.blocks.len() as u64, GB / PAGE);
+    assert_eq!(metadata.data_blocks().count(), 1);
+    assert!(cart.inner().stats().used_blocks - used_before < 8);
+
+    assert_eq!(cart.read_range("db/table.dat", 0, 16).unwrap(), vec![0; 16]);
+    assert_eq!(cart.read_range("db/table.dat", GB / 2 - 2, 4).unwrap(), [0, 0, 0xAB, 0xAB]);
+    assert_eq!(cart.read_range("db/table.dat", GB / 2 + PAGE - 2, 4).unwrap(), [0xAB, 0xAB, 0, 0]);
+    assert_eq!(cart.read_range("db/table.dat", GB - 8, 100).unwrap(), vec![0; 8]);
+
+    let entry = cart.list_entries("db").unwrap().into_iter().find(|e| e.name == "table.dat");
+    let entry = entry.unwrap();
+    assert_eq!(entry.size, Some(GB));
+    assert_eq!(entry.compressed_size, Some(PAGE));
+
+    let stats = cart.detailed_stats().unwrap();
+    assert_eq!(stats.hole_bytes, GB - PAGE);
+    assert!(stats.physical_bytes < stats.logical_bytes / 1000);
+    assert!(cart.check().unwrap().is_consistent());
+    drop(cart);
+
+    // The hole runs survive a reopen without bloating the catalog
+    let cart = Cartridge::open(&path).unwrap();
+    assert!(std::fs::metadata(&path).unwrap().len() < 4 * 1024 * 1024);
+    assert_eq!(cart.metadata("db/table.dat").unwrap().data_blocks().count(), 1);
+    assert_eq!(cart.read_range("db/table.dat", GB / 2, 3).unwrap(), [0xAB; 3]);
+    assert_eq!(cart.read_range("db/table.dat", GB / 4, 3).unwrap(), [0; 3]);
+}
+
+#[test]
+fn test_writes_into_holes_and_truncation() {
+    let mut cart = Cartridge::in_memory("sparse", "Sparse").unwrap();
+    cart.write("f.bin", b"header").unwrap();
+    cart.write_at("f.bin", 3 * PAGE + 10, b"tail").unwrap();
+
+    // The inline header moved to a block; pages 1 and 2 are holes
+    let blocks = cart.metadata("f.bin").unwrap().blocks;
+    assert_eq!(blocks.len(), 4);
+    assert_eq!(blocks[1..3], [HOLE_BLOCK, HOLE_BLOCK]);
+
+    let mut expected = vec![0u8; 3 * PAGE as usize + 14];
+    expected[..6].copy_from_slice(b"header");
+    expected[3 * PAGE as usize + 10..].copy_from_slice(b"tail");
+    assert_eq!(cart.read("f.bin").unwrap(), expected);
+
+    // Writing into a hole fills just that page
+    cart.write_at("f.bin", PAGE + 1, b"mid").unwrap();
+    expected[PAGE as usize + 1..PAGE as usize + 4].copy_from_slice(b"mid");
+    assert_eq!(cart.read("f.bin").unwrap(), expected);
+    assert_eq!(cart.metadata("f.bin").unwrap().hole_count(), 1);
+
+    // Shrinking inside a page, then growing past it, shows zeros, not the
+    // old bytes of that page
+    cart.truncate("f.bin", PAGE + 2).unwrap();
+    cart.truncate("f.bin", 5 * PAGE).unwrap();
+    expected.truncate(PAGE as usize + 2);
+    expected.resize(5 * PAGE as usize, 0);
+    assert_eq!(cart.read("f.bin").unwrap(), expected);
+    assert_eq!(cart.metadata("f.bin").unwrap().data_blocks().count(), 2);
+
+    cart.delete("f.bin").unwrap();
+    assert!(cart.check().unwrap().is_consistent());
+}
+
+#[test]
+fn test_write_sparse_and_sparse_import() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut image = vec![0u8; 64 * PAGE as usize + 100];
+    image[PAGE as usize..2 * PAGE as usize].fill(1);
+    image[2 * PAGE as usize + 7] = 2;
+    image[64 * PAGE as usize + 99] = 3;
+
+    let mut cart = Cartridge::in_memory("sparse", "Sparse").unwrap();
+    cart.write_sparse("disk.img", &image).unwrap();
+    assert_eq!(cart.read("disk.img").unwrap(), image);
+    assert_eq!(cart.metadata("disk.img").unwrap().data_blocks().count(), 3);
+
+    // Replacing keeps the file sparse
+    cart.write_sparse("disk.img", &image[..3 * PAGE as usize]).unwrap();
+    assert_eq!(cart.read("disk.img").unwrap(), image[..3 * PAGE as usize]);
+    assert_eq!(cart.metadata("disk.img").unwrap().hole_count(), 1);
+
+    let host = dir.path().join("host");
+    std::fs::create_dir(&host).unwrap();
+    std::fs::write(host.join("disk.img"), &image).unwrap();
+    let options = ImportOptions {
+        sparse: true,
+        ..Default::default()
+    };
+    cart.import_dir(&host, "imported", &options).unwrap();
+    assert_eq!(cart.read("imported/disk.img").unwrap(), image);
+    assert_eq!(cart.metadata("imported/disk.img").unwrap().hole_count(), 62);
+
+    // Dense by default
+    cart.import_dir(&host, "dense", &ImportOptions::default()).unwrap();
+    assert!(!cart.metadata("dense/disk.img").unwrap().is_sparse());
+}
+
+#[test]
+fn test_vacuum_into_keeps_holes() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut cart = Cartridge::create_at(dir.path().join("src.cart"), "src", "Src").unwrap();
+    cart.write("a.bin", b"").unwrap();
+    cart.write_at("a.bin", 100 * PAGE, &page_of(7)).unwrap();
+    cart.write_at("a.bin", 200 * PAGE + 5, b"end").unwrap();
+    cart.write("dense.bin", &page_of(9)).unwrap();
+    let expected = cart.read("a.bin").unwrap();
+
+    let copy = dir.path().join("copy.cart");
+    cart.inner().vacuum_into(&copy).unwrap();
+    let copy = Cartridge::open(&copy).unwrap();
+    assert_eq!(copy.read("a.bin").unwrap(), expected);
+    assert_eq!(copy.metadata("a.bin").unwrap().data_blocks().count(), 2);
+    assert_eq!(copy.read("dense.bin").unwrap(), page_of(9));
+}
@@ -0,0 +1,99 @@
+//! SQLite databases opened through `Cartridge::open_database`
+
+#![cfg(feature = "sqlite")]
+
+use cartridge_rs::Cartridge;
+use rusqlite::params;
+
+#[test]
+fn test_database_survives_close_and_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("db.cart");
+
+    let mut cart = Cartridge::create_at(&path, "db", "Database").unwrap();
+    {
+        let mut conn = cart.open_database("/app.db").unwrap();
+        conn.execute("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)", [])
+            .unwrap();
+        let tx = conn.transaction().unwrap();
+        for i in 0..1000 {
+            tx.execute("INSERT INTO items VALUES (?1, ?2)", params![i, format!("item-{i}")])
+                .unwrap();
+        }
+        tx.commit().unwrap();
+    }
+    assert!(cart.vfs_name().is_none());
+    assert!(cart.exists("/app.db").unwrap());
+    assert!(!cart.exists("/app.db-journal").unwrap());
+    cart.into_inner().close().unwrap();
+
+    let mut cart = Cartridge::open(&path).unwrap();
+    let conn = cart.open_database("/app.db").unwrap();
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM items", [], |r| r.get(0)).unwrap();
+    assert_eq!(count, 1000);
+    let name: String = conn
+        .query_row("SELECT name FROM items WHERE id = 999", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(name, "item-999");
+    conn.close().unwrap();
+}
+
+#[test]
+fn test_wal_needs_exclusive_locking() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("wal.cart");
+
+    let mut cart = Cartridge::create_at(&path, "wal", "WAL").unwrap();
+    {
+        let conn = cart.open_database("/plain.db").unwrap();
+        // Without shared memory the VFS can't do WAL in normal locking mode
+        let mode: String = conn.query_row("PRAGMA journal_mode=WAL", [], |r| r.get(0)).unwrap();
+        assert_eq!(mode, "delete");
+    }
+    {
+        let conn = cart.open_database("/wal.db").unwrap();
+        conn.query_row("PRAGMA locking_mode=EXCLUSIVE", [], |r| r.get::<_, String>(0))
+            .unwrap();
+        let mode: String = conn.query_row("PRAGMA journal_mode=WAL", [], |r| r.get(0)).unwrap();
+        assert_eq!(mode, "wal");
+        conn.execute("CREATE TABLE t (x INTEGER)", []).unwrap();
+        conn.execute("INSERT INTO t VALUES (42)", []).unwrap();
+        assert!(select_is(&conn, "SELECT x FROM t", 42));
+    }
+    // Closing checkpointed the WAL into the database
+    assert!(!cart.exists("/wal.db-wal").unwrap());
+    cart.into_inner().close().unwrap();
+
+    // The database stays in WAL mode, so it needs exclusive locking again
+    let mut cart = Cartridge::open(&path).unwrap();
+    let conn = cart.open_database("/wal.db").unwrap();
+    assert!(conn.query_row("SELECT x FROM t", [], |r| r.get::<_, i64>(0)).is_err());
+    conn.close().unwrap();
+    let conn = cart.open_database("/wal.db").unwrap();
+    conn.query_row("PRAGMA locking_mode=EXCLUSIVE", [], |r| r.get::<_, String>(0))
+        .unwrap();
+    assert!(select_is(&conn, "SELECT x FROM t", 42));
+}
+
+#[test]
+fn test_read_only_cartridge_opens_database_read_only() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("ro.cart");
+
+    let mut cart = Cartridge::create_at(&path, "ro", "Read Only").unwrap();
+    cart.open_database("/ro.db")
+        .unwrap()
+        .execute("CREATE TABLE t (x INTEGER)", [])
+        .unwrap();
+    drop(cart);
+
+    let mut cart = Cartridge::open_read_only(&path).unwrap();
+    let conn = cart.open_database("/ro.db").unwrap();
+    assert!(conn.execute("INSERT INTO t VALUES (1)", []).is_err());
+    drop(conn);
+    assert!(cart.exists("/ro.db").unwrap());
+}
+
+fn select_is(conn: &rusqlite::Connection, sql: &str, expected: i64) -> bool {
+    conn.query_row(sql, [], |r| r.get::<_, i64>(0)).unwrap() == expected
+}
@@ -14,7 +14,7 @@ fn test_100gb_container() {
     for i in 0..100 {
         println!("Creating file {} of 100...", i + 1);
         let data = vec![i as u8; 1024 * 1024 * 1024]; // 1GB
-        cart.write(&format!("/file{:03}.bin", i), &data).unwrap();
+        cart.write(format!("/file{:03}.bin", i), &data).unwrap();
 
         // Verify allocator consistency after each large write
         assert!(cart.header().total_blocks > 0);
@@ -27,7 +27,7 @@ fn test_100gb_container() {
     // Random access
     for _ in 0..100 {
         let idx = rand::random::<usize>() % 100;
-        let data = cart.read(&format!("/file{:03}.bin", idx)).unwrap();
+        let data = cart.read(format!("/file{:03}.bin", idx)).unwrap();
         assert_eq!(data.len(), 1024 * 1024 * 1024);
         assert_eq!(data[0], idx as u8);
     }
@@ -47,7 +47,7 @@ fn test_1m_files() {
         }
 
         let data = format!("file{}", i).repeat(10); // ~50 bytes
-        cart.write(&format!("/f{}.txt", i), data.as_bytes()).unwrap();
+        cart.write(format!("/f{}.txt", i), data.as_bytes()).unwrap();
     }
 
     println!("Verifying file count...");
@@ -57,8 +57,8 @@ fn test_1m_files() {
     println!("Random access test...");
     for _ in 0..10_000 {
         let idx = rand::random::<usize>() % 1_000_000;
-        let data = cart.read(&format!("/f{}.txt", idx)).unwrap();
-        assert!(data.len() > 0);
+        let data = cart.read(format!("/f{}.txt", idx)).unwrap();
+        assert!(!data.is_empty());
     }
 
     std::fs::remove_file("stress-1m-files.cart").ok();
@@ -72,12 +72,12 @@ fn test_fragmentation_measurement() {
     // Create many files
     for i in 0..10_000 {
         let size = rand::random::<usize>() % (100 * 1024) + 1024; // 1KB-100KB
-        cart.write(&format!("/file{}.bin", i), &vec![i as u8; size]).unwrap();
+        cart.write(format!("/file{}.bin", i), &vec![i as u8; size]).unwrap();
     }
 
     // Delete random 50%
     for i in (0..10_000).step_by(2) {
-        cart.delete(&format!("/file{}.bin", i)).unwrap();
+        cart.delete(format!("/file{}.bin", i)).unwrap();
     }
 
     // Check free blocks increased
@@ -87,7 +87,7 @@ fn test_fragmentation_measurement() {
     // Re-fill deleted space
     for i in 0..5_000 {
         let size = rand::random::<usize>() % (100 * 1024) + 1024;
-        cart.write(&format!("/new{}.bin", i), &vec![0xFF; size]).unwrap();
+        cart.write(format!("/new{}.bin", i), &vec![0xFF; size]).unwrap();
     }
 
     // Verify allocator health
@@ -112,7 +112,7 @@ fn test_max_auto_growth_limit() {
             break;
         }
 
-        match cart.write(&format!("/file{}.bin", i), &vec![0xAB; 64 * 1024]) {
+        match cart.write(format!("/file{}.bin", i), &vec![0xAB; 64 * 1024]) {
             Ok(_) => {
                 i += 1;
                 if i % 100 == 0 {
@@ -161,8 +161,8 @@ fn test_moderate_scale() {
     // Verify all files
     for i in 0..200 {
         let byte_val = (i % 256) as u8;
-        let data = cart.read(&format!("/file{}.bin", i)).unwrap();
-        assert!(data.len() > 0);
+        let data = cart.read(format!("/file{}.bin", i)).unwrap();
+        assert!(!data.is_empty());
         assert_eq!(data[0], byte_val);
     }
 
@@ -195,7 +195,7 @@ fn test_many_small_files_performance() {
     // Create 10,000 small files
     let start = std::time::Instant::now();
     for i in 0..10_000 {
-        cart.write(&format!("/small{}.txt", i), b"data").unwrap();
+        cart.write(format!("/small{}.txt", i), b"data").unwrap();
     }
     let elapsed = start.elapsed();
 
@@ -217,10 +217,10 @@ fn test_alternating_large_small() {
     for i in 0..100 {
         if i % 2 == 0 {
             // Large file
-            cart.write(&format!("/large{}.bin", i), &vec![0xAB; 512 * 1024]).unwrap();
+            cart.write(format!("/large{}.bin", i), &vec![0xAB; 512 * 1024]).unwrap();
         } else {
             // Small file
-            cart.write(&format!("/small{}.txt", i), &vec![0xCD; 4 * 1024]).unwrap();
+            cart.write(format!("/small{}.txt", i), &vec![0xCD; 4 * 1024]).unwrap();
         }
     }
 
@@ -230,3 +230,53 @@ fn test_alternating_large_small() {
 
     std::fs::remove_file("stress-alternating.cart").ok();
 }
+
+#[test]
+fn test_list_entries_10k_files() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("stress-list-10k");
+    let mut cart = Cartridge::create_at(&path, "stress-list-10k", "Stress List 10k").unwrap();
+
+    for i in 0..10_000 {
+        cart.write(format!("dir{}/file{}.txt", i % 100, i), b"data")
+            .unwrap();
+    }
+
+    // One catalog scan, not one lookup per file
+    let start = std::time::Instant::now();
+    let entries = cart.list_entries("").unwrap();
+    let elapsed = start.elapsed();
+    println!("Listed {} entries in {:?}", entries.len(), elapsed);
+
+    assert_eq!(entries.len(), 10_100);
+    assert!(entries
+        .iter()
+        .filter(|e| !e.is_dir)
+        .all(|e| e.size == Some(4) && e.modified.is_some()));
+    assert!(
+        elapsed < std::time::Duration::from_secs(2),
+        "listing 10k files took {:?}",
+        elapsed
+    );
+}
+
+#[test]
+#[ignore]
+fn test_flush_50mb_dirty_set() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("stress-flush-50mb");
+    let mut cart = Cartridge::create_at(&path, "stress-flush-50mb", "Stress Flush 50MB").unwrap();
+
+    for i in 0..50 {
+        cart.write(format!("chunk{:02}.bin", i), &vec![i as u8; 1024 * 1024])
+            .unwrap();
+    }
+
+    let start = std::time::Instant::now();
+    cart.flush().unwrap();
+    println!("Flushed 50MB of dirty pages in {:?}", start.elapsed());
+
+    drop(cart);
+    let cart = Cartridge::open(&path).unwrap();
+    assert_eq!(cart.read("chunk49.bin").unwrap(), vec![49u8; 1024 * 1024]);
+}
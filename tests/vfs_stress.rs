@@ -24,7 +24,7 @@ fn test_vfs_100_concurrent_connections() {
 
     // Create initial database
     {
-        let mut conn = Connection::open_with_flags(
+        let conn = Connection::open_with_flags(
             &db_uri,
             OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE | OpenFlags::SQLITE_OPEN_URI,
         )
@@ -39,7 +39,7 @@ fn test_vfs_100_concurrent_connections() {
         .map(|thread_id| {
             let db_uri_clone = db_uri.clone();
             std::thread::spawn(move || {
-                let mut conn = Connection::open_with_flags(
+                let conn = Connection::open_with_flags(
                     &db_uri_clone,
                     OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_URI,
                 )
@@ -74,7 +74,7 @@ fn test_vfs_100_concurrent_connections() {
     }
 
     // Verify no crashes and database is still accessible
-    let mut conn = Connection::open_with_flags(
+    let conn = Connection::open_with_flags(
         &db_uri,
         OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
     )
@@ -104,7 +104,7 @@ fn test_vfs_rapid_connect_disconnect() {
 
     // Rapidly open and close connections 1000 times
     for i in 0..1000 {
-        let mut conn = Connection::open_with_flags(
+        let conn = Connection::open_with_flags(
             &db_uri,
             OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE | OpenFlags::SQLITE_OPEN_URI,
         )
@@ -123,7 +123,7 @@ fn test_vfs_rapid_connect_disconnect() {
     }
 
     // Verify no leaks and database is intact
-    let mut conn = Connection::open_with_flags(
+    let conn = Connection::open_with_flags(
         &db_uri,
         OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
     )
@@ -191,7 +191,7 @@ fn test_vfs_index_creation_performance() {
 
     let db_uri = format!("file:/indexes.db?vfs={}", VFS_NAME);
 
-    let mut conn = Connection::open_with_flags(
+    let conn = Connection::open_with_flags(
         &db_uri,
         OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE | OpenFlags::SQLITE_OPEN_URI,
     )
@@ -245,7 +245,7 @@ fn test_vfs_concurrent_writers_with_retry() {
 
     // Create initial table
     {
-        let mut conn = Connection::open_with_flags(
+        let conn = Connection::open_with_flags(
             &db_uri,
             OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE | OpenFlags::SQLITE_OPEN_URI,
         )
@@ -260,7 +260,7 @@ fn test_vfs_concurrent_writers_with_retry() {
         .map(|thread_id| {
             let db_uri_clone = db_uri.clone();
             std::thread::spawn(move || {
-                let mut conn = Connection::open_with_flags(
+                let conn = Connection::open_with_flags(
                     &db_uri_clone,
                     OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_URI,
                 )
@@ -300,7 +300,7 @@ fn test_vfs_concurrent_writers_with_retry() {
     assert!(total_successful > 0);
 
     // Verify data
-    let mut conn = Connection::open_with_flags(
+    let conn = Connection::open_with_flags(
         &db_uri,
         OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
     )
@@ -336,7 +336,7 @@ fn test_vfs_sustained_load_1_minute() {
 
     // Create table
     {
-        let mut conn = Connection::open_with_flags(
+        let conn = Connection::open_with_flags(
             &db_uri,
             OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE | OpenFlags::SQLITE_OPEN_URI,
         )
@@ -357,7 +357,7 @@ fn test_vfs_sustained_load_1_minute() {
             let dur = duration;
 
             std::thread::spawn(move || {
-                let mut conn = Connection::open_with_flags(
+                let conn = Connection::open_with_flags(
                     &db_uri_clone,
                     OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_URI,
                 )
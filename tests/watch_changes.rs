@@ -0,0 +1,54 @@
+//! Change notifications through the high-level API
+
+use cartridge_rs::{Cartridge, ChangeKind};
+use std::time::Duration;
+
+#[test]
+fn test_watch_prefix_filters_events() {
+    let mut cart = Cartridge::in_memory("watched", "Watched").unwrap();
+    let docs = cart.watch("/docs");
+
+    cart.write("/docs/a.txt", b"a").unwrap();
+    cart.write("/other/b.txt", b"b").unwrap();
+    cart.delete("/docs/a.txt").unwrap();
+
+    let events: Vec<_> = docs.try_iter().collect();
+    assert_eq!(events.len(), 2, "{events:?}");
+    assert_eq!(events[0].path, "/docs/a.txt");
+    assert_eq!(events[0].kind, ChangeKind::Created);
+    assert_eq!(events[1].kind, ChangeKind::Deleted);
+    assert!(events[0].timestamp <= events[1].timestamp);
+}
+
+#[test]
+fn test_watch_from_another_thread() {
+    let shared = Cartridge::in_memory("shared", "Shared").unwrap().into_shared();
+    let changes = shared.watch("logs");
+
+    let reader = std::thread::spawn(move || {
+        let mut paths = Vec::new();
+        while let Ok(event) = changes.recv_timeout(Duration::from_secs(5)) {
+            paths.push(event.path);
+            if paths.len() == 3 {
+                break;
+            }
+        }
+        paths
+    });
+    for i in 0..3 {
+        shared.write(format!("logs/{i}.log"), b"entry").unwrap();
+    }
+    assert_eq!(reader.join().unwrap(), ["/logs/0.log", "/logs/1.log", "/logs/2.log"]);
+}
+
+#[test]
+fn test_receiver_does_not_keep_cartridge_alive() {
+    let mut cart = Cartridge::in_memory("gone", "Gone").unwrap();
+    let changes = cart.watch("");
+    cart.write("a.txt", b"a").unwrap();
+    drop(cart);
+
+    // Buffered events are still readable, then the channel reports closed
+    assert_eq!(changes.recv().unwrap().path, "/a.txt");
+    assert!(changes.recv().is_err());
+}
@@ -0,0 +1,140 @@
+//! Zip interop: `ZipVfs` and conversion between zip archives and cartridges
+//!
+//! Run with `cargo test --features zip --test zip_interop`.
+//!
+//! `fixtures/sample.zip` was written by Python's `zipfile`: a deflated and a
+//! stored file, two directory entries (one empty), a Unix symlink, and an
+//! entry named `../evil.txt`, all dated 2021-06-01 12:00:00.
+
+#![cfg(feature = "zip")]
+
+use cartridge_rs::{Cartridge, CartridgeError, Vfs, ZipVfs};
+use std::io::Cursor;
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/sample.zip");
+const FIXTURE_TIME: u64 = 1_622_548_800;
+
+fn readme() -> Vec<u8> {
+    let mut text = b"Cartridge zip fixture.\n".to_vec();
+    for _ in 0..40 {
+        text.extend_from_slice(b"All work and no play makes a dull archive.\n");
+    }
+    text
+}
+
+fn data() -> Vec<u8> {
+    let mut data: Vec<u8> = (0..=255).collect();
+    data.resize(300, 0);
+    data
+}
+
+#[test]
+fn test_zip_vfs_reads_fixture() {
+    let mut zip = ZipVfs::open(FIXTURE).unwrap();
+    assert!(zip.is_read_only());
+
+    let entries = zip.list_entries("/").unwrap();
+    let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+    assert_eq!(paths, ["/docs", "/empty", "/docs/data.bin", "/link", "/docs/readme.txt"]);
+
+    let readme_entry = entries.iter().find(|e| e.name == "readme.txt").unwrap();
+    assert_eq!(readme_entry.size, Some(readme().len() as u64));
+    assert_eq!(readme_entry.modified, Some(FIXTURE_TIME));
+    assert!(readme_entry.compressed_size.unwrap() < 200);
+    assert_eq!(readme_entry.content_type.as_deref(), Some("text/plain"));
+
+    // Deflated, stored, and through the symlink
+    assert_eq!(zip.read("/docs/readme.txt").unwrap(), readme());
+    assert_eq!(zip.read("docs/data.bin").unwrap(), data());
+    assert_eq!(zip.read("/link").unwrap(), readme());
+    assert_eq!(zip.read_range("/docs/data.bin", 254, 4).unwrap(), [254, 255, 0, 0]);
+
+    assert!(zip.is_dir("/empty").unwrap());
+    assert!(zip.exists("/docs/data.bin").unwrap());
+    assert!(!zip.exists("/evil.txt").unwrap());
+    let children = zip.list_children("/docs").unwrap();
+    assert_eq!(children.len(), 2);
+
+    let metadata = zip.metadata("/docs/readme.txt").unwrap();
+    assert_eq!(metadata.permissions, 0o640);
+    assert_eq!(metadata.modified_at, FIXTURE_TIME);
+    assert_eq!(zip.metadata("/link").unwrap().symlink_target(), Some("docs/readme.txt"));
+
+    assert!(matches!(zip.read("/missing"), Err(CartridgeError::NotFound { .. })));
+    assert!(matches!(zip.read("/docs"), Err(CartridgeError::NotAFile { .. })));
+    assert!(matches!(zip.write("/new.txt", b"x"), Err(CartridgeError::ReadOnly)));
+    assert!(matches!(zip.delete("/docs/data.bin"), Err(CartridgeError::ReadOnly)));
+
+    let not_zip = ZipVfs::new(Cursor::new(b"not a zip".to_vec()));
+    assert!(matches!(not_zip, Err(CartridgeError::InvalidZip(_))));
+}
+
+#[test]
+fn test_import_zip() {
+    let mut cart = Cartridge::in_memory("zip-import", "Zip Import").unwrap();
+    let report = cart
+        .import_zip(std::fs::File::open(FIXTURE).unwrap(), "imported")
+        .unwrap();
+    assert_eq!(report.files_imported, 2);
+    assert_eq!(report.symlinks_imported, 1);
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(report.skipped[0].path, "../evil.txt");
+
+    assert_eq!(cart.read("imported/docs/readme.txt").unwrap(), readme());
+    assert_eq!(cart.read("imported/docs/data.bin").unwrap(), data());
+    assert!(cart.is_dir("imported/empty").unwrap());
+    assert_eq!(cart.read("imported/link").unwrap(), readme());
+    assert!(cart.check().unwrap().is_consistent());
+}
+
+#[test]
+fn test_from_zip_creates_cartridge() {
+    let cart = Cartridge::from_zip(
+        std::fs::File::open(FIXTURE).unwrap(),
+        "zip-from-fixture",
+        "From Zip",
+    )
+    .unwrap();
+    assert_eq!(cart.read("docs/readme.txt").unwrap(), readme());
+    drop(cart);
+
+    let cart = Cartridge::open("zip-from-fixture.cart").unwrap();
+    assert_eq!(cart.read("docs/data.bin").unwrap(), data());
+    drop(cart);
+    std::fs::remove_file("zip-from-fixture.cart").ok();
+}
+
+#[test]
+fn test_to_zip_round_trip() {
+    let mut cart = Cartridge::in_memory("zip-export", "Zip Export").unwrap();
+    let big: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+    cart.write("site/index.html", b"<h1>hi</h1>").unwrap();
+    cart.write("site/assets/big.bin", &big).unwrap();
+    cart.create_dir("site/empty").unwrap();
+    cart.symlink("index.html", "site/home.html").unwrap();
+    cart.write("elsewhere.txt", b"not exported").unwrap();
+    let modified = cart.metadata("site/index.html").unwrap().modified_at;
+
+    let mut archive = Vec::new();
+    let report = cart.to_zip(&mut archive, "site").unwrap();
+    assert_eq!(report.files_exported, 2);
+    assert_eq!(report.symlinks_exported, 1);
+    assert_eq!(report.bytes_written, big.len() as u64 + 11);
+
+    let zip = ZipVfs::new(Cursor::new(archive.clone())).unwrap();
+    assert_eq!(zip.read("/index.html").unwrap(), b"<h1>hi</h1>");
+    assert_eq!(zip.read("/assets/big.bin").unwrap(), big);
+    assert_eq!(zip.read("/home.html").unwrap(), b"<h1>hi</h1>");
+    assert!(zip.is_dir("/empty").unwrap());
+    assert!(!zip.exists("/elsewhere.txt").unwrap());
+    let metadata = zip.metadata("/index.html").unwrap();
+    assert_eq!(metadata.modified_at, modified);
+    assert_eq!(metadata.permissions, 0o644);
+
+    // And back into a cartridge
+    let mut copy = Cartridge::in_memory("zip-copy", "Zip Copy").unwrap();
+    copy.import_zip(Cursor::new(archive), "").unwrap();
+    assert_eq!(copy.read("assets/big.bin").unwrap(), big);
+    assert!(copy.is_dir("empty").unwrap());
+    assert_eq!(copy.metadata_nofollow("home.html").unwrap().symlink_target(), Some("index.html"));
+}